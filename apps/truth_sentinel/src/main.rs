@@ -1,17 +1,36 @@
 use std::fs;
+use std::io::{Read, Seek, SeekFrom};
+use std::net::SocketAddr;
 use std::path::PathBuf;
-use std::sync::{Arc, Mutex};
+use std::sync::{Arc, Mutex, RwLock};
 
 use anyhow::{Context, Result};
-use axum::extract::{Path, State};
-use axum::routing::{get, post};
+use axum::extract::{ConnectInfo, DefaultBodyLimit, Path, Query, State};
+use axum::http::{header, HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{delete, get, post};
 use axum::{Json, Router};
 use clap::{Parser, Subcommand};
+use futures_util::StreamExt;
 use pru_core::{PruDbHandle, PruStore};
-use pru_detectors_api::{DetectorRegistry, ImageMetadataDetector, TextComplexityDetector};
-use pru_ingest::IngestContext;
-use pru_media_schema::{add_human_verdict, bump_reliability_from_verdict, MediaId};
-use pru_truth_engine::{DetectionReport, TruthEngine, TruthEngineConfig};
+use pru_detectors_api::{evaluate_detector, load_labeled_dirs, DetectorRegistry, RegistryConfig};
+use pru_ingest::{
+    detect_media_type, BatchOptions, BlockedUrl, CancellationToken, DetectorCache, IngestContext,
+    IngestError, IngestEvent, IngestLimits, IngestOptions, IngestResult, UrlOptions,
+};
+use pru_media_schema::{
+    add_human_verdict_by, add_tag, bump_reliability_from_verdict, ensure_schema, get_detector_name,
+    get_detector_reliability, get_media_metadata, get_stored_at, get_tags, media_with_tag,
+    register_detector, set_detector_reliability, MediaId, MediaType, ANONYMOUS_ANNOTATOR,
+};
+use pru_storage::{
+    mime_for_ext, EncryptionConfig, Eviction, MediaStorage, Quota, QuotaExceeded, StorageError,
+    StorageUsage,
+};
+use pru_truth_engine::{
+    recalibrate_all, AccuracyReport, DetectionReport, EvalOverrides, MediaFilter, TruthEngine,
+    TruthEngineConfig,
+};
 use serde::{Deserialize, Serialize};
 use tokio::net::TcpListener;
 use tower_http::cors::CorsLayer;
@@ -25,21 +44,121 @@ pub struct Cli {
     /// Data directory for PRU store
     #[arg(long, default_value = "data/truth_sentinel")]
     data_dir: PathBuf,
+
+    /// TOML file overriding built-in detector thresholds and weights
+    #[arg(long)]
+    detector_config: Option<PathBuf>,
+
+    /// TOML file listing every detector as a `[[detector]]` table; replaces the
+    /// built-in set and takes precedence over `--detector-config`
+    #[arg(long)]
+    registry_config: Option<PathBuf>,
+
+    /// Directory of `*.toml` subprocess plugin descriptors
+    #[arg(long)]
+    plugins_dir: Option<PathBuf>,
+
+    /// Keep a copy of every ingested file under this directory
+    #[arg(long)]
+    media_root: Option<PathBuf>,
+
+    /// Most bytes the files under --media-root may take; new files over it are refused
+    #[arg(long, requires = "media_root")]
+    media_quota: Option<u64>,
+
+    /// Make room under --media-quota by deleting the least recently used files
+    #[arg(long, requires = "media_quota")]
+    evict_media: bool,
+
+    /// Refuse to store originals again once DELETE /media/:id/original removed them
+    #[arg(long, requires = "media_root")]
+    tombstone_deletes: bool,
+
+    /// Encrypt files under --media-root with the key in this file (32 raw bytes or 64 hex digits)
+    #[arg(long, requires = "media_root", conflicts_with = "media_key_env")]
+    media_key_file: Option<PathBuf>,
+
+    /// Encrypt files under --media-root with the hex key in this environment variable
+    #[arg(long, requires = "media_root")]
+    media_key_env: Option<String>,
+
+    /// Let POST /analyze/url fetch from loopback and private network addresses
+    #[arg(long)]
+    allow_private_urls: bool,
+
+    /// Environment variable holding the bearer token DELETE /media/:id/original
+    /// requires; without it only loopback clients may delete
+    #[arg(long)]
+    admin_token_env: Option<String>,
 }
 
 #[derive(Subcommand)]
 pub enum Commands {
     AnalyzeImage {
         path: PathBuf,
+        /// Re-run detectors even if results for these bytes are already stored
+        #[arg(long)]
+        force: bool,
     },
     AnalyzeText {
         text: Option<String>,
         #[arg(long)]
         file: Option<PathBuf>,
+        /// Re-run detectors even if results for these bytes are already stored
+        #[arg(long)]
+        force: bool,
+    },
+    /// Ingest every file under a directory (or a single file), sniffing each type
+    AnalyzeDir {
+        path: PathBuf,
+        /// Descend into subdirectories
+        #[arg(long)]
+        recursive: bool,
+        /// Files ingested at once
+        #[arg(long, default_value_t = 1)]
+        jobs: usize,
+        /// Only files matching this glob (repeatable)
+        #[arg(long)]
+        include: Vec<String>,
+        /// Skip files matching this glob (repeatable)
+        #[arg(long)]
+        exclude: Vec<String>,
+        /// Re-run detectors even if results for these bytes are already stored
+        #[arg(long)]
+        force: bool,
     },
     Label {
         media: String,
         label: String,
+        /// Who is labeling (recorded as the verdict source)
+        #[arg(long, default_value = "anonymous")]
+        annotator: String,
+        /// Annotator confidence in [0, 1]
+        #[arg(long, default_value_t = 1.0)]
+        confidence: f32,
+    },
+    /// Score labelled sample directories with one detector and print the report
+    EvalDetector {
+        #[arg(long)]
+        detector: String,
+        #[arg(long)]
+        ai_dir: PathBuf,
+        #[arg(long)]
+        human_dir: PathBuf,
+        /// Add the graded predictions to the detector's stored reliability
+        #[arg(long)]
+        seed_reliability: bool,
+    },
+    /// Refit score calibration for every detector from stored verdicts
+    Recalibrate,
+    /// Compare engine results with stored human verdicts and print accuracy metrics
+    Stats {
+        /// Only media carrying this tag
+        #[arg(long)]
+        tag: Option<String>,
+        /// Only media of this type (image, text, audio or video)
+        #[arg(long)]
+        media_type: Option<MediaType>,
     },
     Serve {
         #[arg(long, default_value = "127.0.0.1:8080")]
@@ -54,77 +173,302 @@ async fn main() -> Result<()> {
     fs::create_dir_all(&cli.data_dir)?;
     let store = PruStore::open(&cli.data_dir)?;
     let handle: PruDbHandle = Arc::new(Mutex::new(store));
-    let registry = default_registry();
+    ensure_schema(&handle)?;
+    let mut registry = match &cli.registry_config {
+        Some(path) => DetectorRegistry::from_file(path)?,
+        None => load_registry(cli.detector_config.as_deref())?,
+    };
+    if let Some(dir) = &cli.plugins_dir {
+        registry.load_plugins(dir)?;
+    }
     let engine = TruthEngine::new(TruthEngineConfig::default());
+    let storage = cli
+        .media_root
+        .as_ref()
+        .map(|root| -> Result<MediaStorage> {
+            let mut storage = MediaStorage::new(root);
+            storage.tombstones = cli.tombstone_deletes;
+            if let Some(path) = &cli.media_key_file {
+                storage = storage.with_encryption(EncryptionConfig::from_file(path)?);
+            }
+            if let Some(var) = &cli.media_key_env {
+                storage = storage.with_encryption(EncryptionConfig::from_env(var)?);
+            }
+            Ok(match cli.media_quota {
+                Some(max_bytes) => storage.with_quota(Quota {
+                    max_bytes,
+                    eviction: if cli.evict_media {
+                        Eviction::LeastRecentlyUsed
+                    } else {
+                        Eviction::Refuse
+                    },
+                }),
+                None => storage,
+            })
+        })
+        .transpose()?;
 
     match cli.command {
-        Commands::AnalyzeImage { path } => {
+        Commands::AnalyzeImage { path, force } => {
             let bytes = fs::read(&path)?;
-            let ctx = IngestContext {
-                pru: handle.clone(),
-                detectors: registry.clone(),
-            };
-            let result = ctx.ingest_image(&bytes)?;
+            let ctx = IngestContext::new(handle.clone(), registry.clone())
+                .with_force(force)
+                .with_storage(storage.clone());
+            let result = ctx.ingest_image_async(&bytes).await?;
             let report = engine.evaluate_media(&handle, result.media_id)?;
             println!(
                 "{}",
-                serde_json::to_string_pretty(&report_with_id(result.media_id, report))?
+                serde_json::to_string_pretty(&AnalyzeResponse::analyzed(result, report))?
             );
         }
-        Commands::AnalyzeText { text, file } => {
+        Commands::AnalyzeText { text, file, force } => {
             let content = if let Some(t) = text {
                 t
             } else if let Some(file) = file {
                 fs::read_to_string(file)?
             } else {
-                use std::io::Read;
                 let mut buffer = String::new();
                 std::io::stdin().read_to_string(&mut buffer)?;
                 buffer
             };
-            let ctx = IngestContext {
-                pru: handle.clone(),
-                detectors: registry.clone(),
-            };
-            let result = ctx.ingest_text(&content)?;
+            let ctx = IngestContext::new(handle.clone(), registry.clone())
+                .with_force(force)
+                .with_storage(storage.clone());
+            let result = ctx.ingest_text_async(&content).await?;
             let report = engine.evaluate_media(&handle, result.media_id)?;
             println!(
                 "{}",
-                serde_json::to_string_pretty(&report_with_id(result.media_id, report))?
+                serde_json::to_string_pretty(&AnalyzeResponse::analyzed(result, report))?
             );
         }
-        Commands::Label { media, label } => {
+        Commands::AnalyzeDir {
+            path,
+            recursive,
+            jobs,
+            include,
+            exclude,
+            force,
+        } => {
+            let ctx = IngestContext::new(handle.clone(), registry.clone())
+                .with_force(force)
+                .with_storage(storage.clone());
+            let cancel = CancellationToken::new();
+            let (events, progress) = std::sync::mpsc::channel();
+            let options = BatchOptions {
+                recursive,
+                include,
+                exclude,
+                jobs,
+                events: Some(events),
+                cancel: cancel.clone(),
+                ..Default::default()
+            };
+            // The first Ctrl-C lets files in progress finish; a second one exits.
+            let interrupt = tokio::spawn(async move {
+                if tokio::signal::ctrl_c().await.is_ok() {
+                    eprintln!("\ninterrupted, finishing files in progress (Ctrl-C again to quit)");
+                    cancel.cancel();
+                }
+                if tokio::signal::ctrl_c().await.is_ok() {
+                    std::process::exit(130);
+                }
+            });
+            let renderer = std::thread::spawn(move || render_progress(progress));
+            // Dropping `options` with the batch closes the progress channel.
+            let (report, path) = tokio::task::spawn_blocking(move || {
+                let report = ctx.ingest_path(&path, &options);
+                (report, path)
+            })
+            .await?;
+            interrupt.abort();
+            let _ = renderer.join();
+            let report = report?;
+            println!(
+                "{:<8} {:>8} {:<6} {:>8}  path",
+                "status", "media", "type", "ms"
+            );
+            for file in &report.files {
+                let shown = file.path.strip_prefix(&path).unwrap_or(&file.path);
+                let ms = file.elapsed.as_millis();
+                match &file.result {
+                    Ok(ingested) => println!(
+                        "{:<8} {:>8} {:<6} {:>8}  {}",
+                        "ok",
+                        ingested.media_id.0,
+                        format!("{:?}", ingested.media_type).to_lowercase(),
+                        ms,
+                        shown.display()
+                    ),
+                    Err(e) => println!(
+                        "{:<8} {:>8} {:<6} {:>8}  {}: {e}",
+                        "failed",
+                        "-",
+                        "-",
+                        ms,
+                        shown.display()
+                    ),
+                }
+            }
+            println!(
+                "{} files: {} ingested, {} failed in {:.2}s",
+                report.files.len(),
+                report.ingested(),
+                report.failed(),
+                report.elapsed.as_secs_f64()
+            );
+            if report.cancelled() {
+                println!("cancelled: {} files not started", report.skipped);
+            }
+        }
+        Commands::Label {
+            media,
+            label,
+            annotator,
+            confidence,
+        } => {
             let media_id = resolve_media(&handle, &media)?;
-            add_human_verdict(&handle, media_id, &label)?;
+            add_human_verdict_by(&handle, media_id, &label, &annotator, confidence)?;
             bump_reliability_from_verdict(&handle, media_id, &label)?;
             println!("Labeled {media} as {label}");
         }
+        Commands::EvalDetector {
+            detector,
+            ai_dir,
+            human_dir,
+            seed_reliability,
+        } => {
+            let found = registry
+                .get(&detector)
+                .with_context(|| format!("unknown detector {detector}"))?;
+            let sync = found.as_sync().with_context(|| {
+                format!("detector {detector} is async-only and cannot be evaluated offline")
+            })?;
+            let mut unreadable = None;
+            let samples = load_labeled_dirs(&ai_dir, &human_dir)?
+                .map_while(|sample| sample.map_err(|err| unreadable = Some(err)).ok());
+            let report = evaluate_detector(sync, samples);
+            if let Some(err) = unreadable {
+                return Err(err);
+            }
+            if seed_reliability {
+                let id = register_detector(&handle, &detector, &found.info())?;
+                let mut reliability = get_detector_reliability(&handle, id)?.unwrap_or_default();
+                report.record_into(&mut reliability);
+                set_detector_reliability(&handle, id, &reliability)?;
+            }
+            println!("{}", serde_json::to_string_pretty(&report)?);
+        }
+        Commands::Recalibrate => {
+            for (detector, calibration) in recalibrate_all(&handle)? {
+                let name =
+                    get_detector_name(&handle, detector)?.unwrap_or_else(|| detector.0.to_string());
+                match calibration {
+                    Some(c) => {
+                        println!("{name}: a={:.4} b={:.4} ({} samples)", c.a, c.b, c.samples)
+                    }
+                    None => println!("{name}: not enough labelled scores, left unchanged"),
+                }
+            }
+        }
+        Commands::Stats { tag, media_type } => {
+            let filter = media_filter(tag, media_type);
+            let report = engine.accuracy_report(&handle, filter.as_ref())?;
+            println!("{}", serde_json::to_string_pretty(&report)?);
+        }
         Commands::Serve { addr } => {
+            let admin_token = match &cli.admin_token_env {
+                Some(var) => {
+                    let token = std::env::var(var)
+                        .with_context(|| format!("reading admin token from ${var}"))?;
+                    anyhow::ensure!(!token.is_empty(), "admin token in ${var} is empty");
+                    Some(Arc::from(token))
+                }
+                None => None,
+            };
             let state = AppState {
                 handle: handle.clone(),
-                registry: registry.clone(),
+                registry: Arc::new(RwLock::new(registry.clone())),
+                cache: DetectorCache::default(),
+                storage,
+                limits: IngestLimits::default(),
+                url_options: UrlOptions {
+                    allow_private: cli.allow_private_urls,
+                    ..Default::default()
+                },
                 engine,
+                admin_token,
             };
+            let body_limit = DefaultBodyLimit::max(state.limits.largest_upload());
             let app = Router::new()
                 .route("/analyze/text", post(analyze_text))
                 .route("/analyze/image", post(analyze_image))
+                .route("/analyze/auto", post(analyze_auto))
+                .route("/analyze/url", post(analyze_url))
                 .route("/label", post(label_media))
                 .route("/media/:id/report", get(report_media))
+                .route("/media/:id/content", get(media_content))
+                .route("/media/:id/tags", post(tag_media))
+                .route("/tags/:tag/media", get(media_for_tag))
+                .route("/detectors", get(list_detectors))
+                .route("/metrics/accuracy", get(accuracy_metrics))
+                .route("/metrics/storage", get(storage_metrics))
+                .route("/detectors/:id/enable", post(enable_detector))
+                .layer(body_limit)
                 .layer(CorsLayer::permissive())
+                // Added after the CORS layer so other origins cannot call it from a browser.
+                .route("/media/:id/original", delete(delete_original))
                 .with_state(state);
             let listener = TcpListener::bind(addr).await?;
-            axum::serve(listener, app).await?;
+            axum::serve(
+                listener,
+                app.into_make_service_with_connect_info::<SocketAddr>(),
+            )
+            .await?;
         }
     }
 
     Ok(())
 }
 
-fn default_registry() -> DetectorRegistry {
-    let mut registry = DetectorRegistry::new();
-    registry.register(Arc::new(TextComplexityDetector));
-    registry.register(Arc::new(ImageMetadataDetector));
-    registry
+/// Keep one stderr line up to date with how far a batch has got.
+fn render_progress(events: std::sync::mpsc::Receiver<IngestEvent>) {
+    let (mut total, mut done, mut failed) = (0, 0, 0);
+    for event in events {
+        match event {
+            IngestEvent::Started { total: n } => total = n,
+            IngestEvent::FileDone { path, result } => {
+                done += 1;
+                failed += usize::from(result.is_err());
+                eprint!(
+                    "\r\x1b[2K[{done}/{total}] {failed} failed  {}",
+                    path.display()
+                );
+            }
+            IngestEvent::Finished { .. } => break,
+        }
+    }
+    if done > 0 {
+        eprintln!();
+    }
+}
+
+fn load_registry(config_path: Option<&std::path::Path>) -> Result<DetectorRegistry> {
+    let config = match config_path {
+        Some(path) => {
+            let raw = fs::read_to_string(path)
+                .with_context(|| format!("reading detector config {}", path.display()))?;
+            toml::from_str::<RegistryConfig>(&raw)
+                .with_context(|| format!("parsing detector config {}", path.display()))?
+        }
+        None => RegistryConfig::default(),
+    };
+    DetectorRegistry::from_config(&config)
+}
+
+/// A tag filter wins over a media type filter when both are given.
+fn media_filter(tag: Option<String>, media_type: Option<MediaType>) -> Option<MediaFilter> {
+    tag.map(MediaFilter::Tag)
+        .or(media_type.map(MediaFilter::MediaType))
 }
 
 fn resolve_media(handle: &PruDbHandle, name: &str) -> Result<MediaId> {
@@ -137,84 +481,248 @@ fn resolve_media(handle: &PruDbHandle, name: &str) -> Result<MediaId> {
 }
 
 fn report_with_id(id: MediaId, report: DetectionReport) -> serde_json::Value {
-    serde_json::json!({
-        "media_id": id.0,
-        "probability_ai": report.probability_ai,
-        "probability_human": report.probability_human,
-        "explanations": report.explanations,
-    })
+    serde_json::to_value(AnalyzeResponse::new(id, report)).expect("reports serialize to JSON")
 }
 
 #[derive(Clone)]
 struct AppState {
     handle: PruDbHandle,
-    registry: DetectorRegistry,
+    /// Shared so detectors can be toggled while the server runs.
+    registry: Arc<RwLock<DetectorRegistry>>,
+    /// Shared across requests so repeated uploads skip finished detectors.
+    cache: DetectorCache,
+    storage: Option<MediaStorage>,
+    limits: IngestLimits,
+    url_options: UrlOptions,
     engine: TruthEngine,
+    /// Bearer token for destructive routes; `None` limits them to loopback clients.
+    admin_token: Option<Arc<str>>,
+}
+
+impl AppState {
+    fn ingest_context(&self) -> IngestContext {
+        IngestContext::new(self.handle.clone(), self.registry.read().unwrap().clone())
+            .with_cache(self.cache.clone())
+            .with_storage(self.storage.clone())
+            .with_limits(self.limits.clone())
+    }
 }
 
 #[derive(Deserialize)]
 struct TextRequest {
     text: String,
+    #[serde(flatten)]
+    context: ContextParams,
+}
+
+/// Where a submission came from: JSON fields on /analyze/text, query parameters on
+/// the raw-bytes endpoints.
+#[derive(Deserialize, Default)]
+struct ContextParams {
+    #[serde(default)]
+    source_name: Option<String>,
+    #[serde(default)]
+    source_url: Option<String>,
+    #[serde(default)]
+    reporter: Option<String>,
+    /// Unix seconds.
+    #[serde(default)]
+    observed_at: Option<i64>,
+    /// Comma-separated.
+    #[serde(default)]
+    tags: Option<String>,
+}
+
+impl ContextParams {
+    fn options(&self) -> IngestOptions {
+        IngestOptions {
+            source_name: self.source_name.clone(),
+            source_url: self.source_url.clone(),
+            reporter: self.reporter.clone(),
+            observed_at: self.observed_at,
+            tags: self
+                .tags
+                .iter()
+                .flat_map(|list| list.split(','))
+                .map(str::trim)
+                .filter(|tag| !tag.is_empty())
+                .map(str::to_string)
+                .collect(),
+            ..Default::default()
+        }
+    }
 }
 
 #[derive(Serialize)]
 struct AnalyzeResponse {
     media_id: u64,
-    probability_ai: f32,
-    probability_human: f32,
-    explanations: Vec<String>,
+    /// How ingest went, for responses to analyze calls.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    ingest: Option<IngestResult>,
+    #[serde(flatten)]
+    report: DetectionReport,
+    /// `report.explanations` and notes rendered as lines.
+    explanation_text: Vec<String>,
+}
+
+impl AnalyzeResponse {
+    fn new(id: MediaId, report: DetectionReport) -> Self {
+        Self {
+            media_id: id.0,
+            ingest: None,
+            explanation_text: report.rendered_explanations(),
+            report,
+        }
+    }
+
+    fn analyzed(ingest: IngestResult, report: DetectionReport) -> Self {
+        let media_id = ingest.media_id;
+        Self {
+            ingest: Some(ingest),
+            ..Self::new(media_id, report)
+        }
+    }
+}
+
+/// A status with a `{"error": ...}` body, for endpoints whose callers need to know
+/// why a submission was refused.
+type ApiError = (StatusCode, Json<serde_json::Value>);
+
+fn error_body(status: StatusCode, err: &anyhow::Error) -> ApiError {
+    (
+        status,
+        Json(serde_json::json!({ "error": format!("{err:#}") })),
+    )
+}
+
+/// 413 for input over the ingest limits, 422 for malformed input, 507 when the
+/// media quota is full, 410 for content taken down with a tombstone, 500
+/// otherwise.
+fn ingest_failure(err: anyhow::Error) -> ApiError {
+    if err.downcast_ref::<QuotaExceeded>().is_some() {
+        return error_body(StatusCode::INSUFFICIENT_STORAGE, &err);
+    }
+    if let Some(StorageError::Deleted { .. }) = err.downcast_ref::<StorageError>() {
+        return error_body(StatusCode::GONE, &err);
+    }
+    match err.downcast_ref::<IngestError>() {
+        Some(IngestError::TooLarge { .. }) => error_body(StatusCode::PAYLOAD_TOO_LARGE, &err),
+        Some(IngestError::Invalid { .. }) => error_body(StatusCode::UNPROCESSABLE_ENTITY, &err),
+        None => internal_error(err),
+    }
+}
+
+/// Logged in full; the caller only learns that something went wrong on our side.
+fn internal_error(err: anyhow::Error) -> ApiError {
+    tracing::error!("{err:#}");
+    error_body(
+        StatusCode::INTERNAL_SERVER_ERROR,
+        &anyhow::anyhow!("internal error"),
+    )
 }
 
 async fn analyze_text(
     State(state): State<AppState>,
     Json(body): Json<TextRequest>,
-) -> Result<Json<AnalyzeResponse>, axum::http::StatusCode> {
-    let ctx = IngestContext {
-        pru: state.handle.clone(),
-        detectors: state.registry.clone(),
-    };
-    let ingest = ctx
-        .ingest_text(&body.text)
-        .map_err(|_| axum::http::StatusCode::INTERNAL_SERVER_ERROR)?;
+) -> Result<Json<AnalyzeResponse>, ApiError> {
+    let ingest = state
+        .ingest_context()
+        .ingest_text_async_with_options(&body.text, &body.context.options())
+        .await
+        .map_err(ingest_failure)?;
     let report = state
         .engine
         .evaluate_media(&state.handle, ingest.media_id)
-        .map_err(|_| axum::http::StatusCode::INTERNAL_SERVER_ERROR)?;
-    Ok(Json(AnalyzeResponse {
-        media_id: ingest.media_id.0,
-        probability_ai: report.probability_ai,
-        probability_human: report.probability_human,
-        explanations: report.explanations,
-    }))
+        .map_err(internal_error)?;
+    Ok(Json(AnalyzeResponse::analyzed(ingest, report)))
 }
 
+/// The body is streamed: to a file under `--media-root` when one is set, so large
+/// images are never held in memory whole.
 async fn analyze_image(
     State(state): State<AppState>,
-    bytes: axum::body::Bytes,
-) -> Result<Json<AnalyzeResponse>, axum::http::StatusCode> {
-    let ctx = IngestContext {
-        pru: state.handle.clone(),
-        detectors: state.registry.clone(),
-    };
+    Query(context): Query<ContextParams>,
+    body: axum::body::Body,
+) -> Result<Json<AnalyzeResponse>, ApiError> {
+    let ctx = state.ingest_context();
+    let mut upload = ctx.begin_upload(MediaType::Image).map_err(ingest_failure)?;
+    let mut chunks = body.into_data_stream();
+    while let Some(chunk) = chunks.next().await {
+        let chunk = chunk.map_err(|e| error_body(StatusCode::BAD_REQUEST, &e.into()))?;
+        upload.write(&chunk).map_err(ingest_failure)?;
+    }
     let ingest = ctx
-        .ingest_image(&bytes)
-        .map_err(|_| axum::http::StatusCode::INTERNAL_SERVER_ERROR)?;
+        .finish_upload_async(upload, &context.options())
+        .await
+        .map_err(ingest_failure)?;
     let report = state
         .engine
         .evaluate_media(&state.handle, ingest.media_id)
-        .map_err(|_| axum::http::StatusCode::INTERNAL_SERVER_ERROR)?;
-    Ok(Json(AnalyzeResponse {
-        media_id: ingest.media_id.0,
-        probability_ai: report.probability_ai,
-        probability_human: report.probability_human,
-        explanations: report.explanations,
-    }))
+        .map_err(internal_error)?;
+    Ok(Json(AnalyzeResponse::analyzed(ingest, report)))
+}
+
+/// Like the typed endpoints, but the media type is sniffed from the body; bodies
+/// that fit no type are rejected with 415.
+async fn analyze_auto(
+    State(state): State<AppState>,
+    Query(context): Query<ContextParams>,
+    bytes: axum::body::Bytes,
+) -> Result<Json<AnalyzeResponse>, ApiError> {
+    detect_media_type(&bytes).map_err(|e| error_body(StatusCode::UNSUPPORTED_MEDIA_TYPE, &e))?;
+    let ingest = state
+        .ingest_context()
+        .ingest_auto_async_with_options(&bytes, &context.options())
+        .await
+        .map_err(ingest_failure)?;
+    let report = state
+        .engine
+        .evaluate_media(&state.handle, ingest.media_id)
+        .map_err(internal_error)?;
+    Ok(Json(AnalyzeResponse::analyzed(ingest, report)))
+}
+
+#[derive(Deserialize)]
+struct UrlRequest {
+    url: String,
+}
+
+/// Fetch and analyze a URL. Blocked URLs (private addresses unless
+/// `--allow-private-urls`, non-http schemes) get 403; failed fetches get 502 and
+/// bodies refused by the ingest limits get 413 or 422.
+async fn analyze_url(
+    State(state): State<AppState>,
+    Json(body): Json<UrlRequest>,
+) -> Result<Json<AnalyzeResponse>, ApiError> {
+    let ingest = state
+        .ingest_context()
+        .ingest_url(&body.url, &state.url_options)
+        .await
+        .map_err(|e| {
+            if e.downcast_ref::<BlockedUrl>().is_some() {
+                error_body(StatusCode::FORBIDDEN, &e)
+            } else if e.downcast_ref::<IngestError>().is_some() {
+                ingest_failure(e)
+            } else {
+                tracing::warn!("analyzing {}: {e:#}", body.url);
+                error_body(StatusCode::BAD_GATEWAY, &e)
+            }
+        })?;
+    let report = state
+        .engine
+        .evaluate_media(&state.handle, ingest.media_id)
+        .map_err(internal_error)?;
+    Ok(Json(AnalyzeResponse::analyzed(ingest, report)))
 }
 
 #[derive(Deserialize)]
 struct LabelRequest {
     media_id: String,
     label: String,
+    #[serde(default)]
+    annotator: Option<String>,
+    #[serde(default)]
+    confidence: Option<f32>,
 }
 
 async fn label_media(
@@ -223,21 +731,476 @@ async fn label_media(
 ) -> Result<Json<serde_json::Value>, axum::http::StatusCode> {
     let media_id = resolve_media(&state.handle, &body.media_id)
         .map_err(|_| axum::http::StatusCode::BAD_REQUEST)?;
-    add_human_verdict(&state.handle, media_id, &body.label)
+    let annotator = body.annotator.as_deref().unwrap_or(ANONYMOUS_ANNOTATOR);
+    let confidence = body.confidence.unwrap_or(1.0);
+    add_human_verdict_by(&state.handle, media_id, &body.label, annotator, confidence)
         .and_then(|_| bump_reliability_from_verdict(&state.handle, media_id, &body.label))
         .map_err(|_| axum::http::StatusCode::INTERNAL_SERVER_ERROR)?;
     Ok(Json(serde_json::json!({"status": "ok"})))
 }
 
+#[derive(Deserialize, Default)]
+struct ReportParams {
+    /// Comma-separated detector names to leave out of a what-if evaluation.
+    #[serde(default)]
+    exclude: Option<String>,
+    #[serde(default)]
+    ignore_verdicts: bool,
+}
+
+impl ReportParams {
+    fn overrides(&self) -> EvalOverrides {
+        EvalOverrides {
+            exclude_detectors: self
+                .exclude
+                .iter()
+                .flat_map(|list| list.split(','))
+                .map(str::trim)
+                .filter(|name| !name.is_empty())
+                .map(str::to_string)
+                .collect(),
+            ignore_verdicts: self.ignore_verdicts,
+            ..Default::default()
+        }
+    }
+}
+
 async fn report_media(
     State(state): State<AppState>,
     Path(id): Path<String>,
+    Query(params): Query<ReportParams>,
 ) -> Result<Json<serde_json::Value>, axum::http::StatusCode> {
     let media_id =
         resolve_media(&state.handle, &id).map_err(|_| axum::http::StatusCode::BAD_REQUEST)?;
+    let overrides = params.overrides();
+    // What-if reports are computed on the fly and never replace the cached one.
+    let report = if overrides == EvalOverrides::default() {
+        state.engine.cached_report(&state.handle, media_id)
+    } else {
+        state
+            .engine
+            .evaluate_media_with(&state.handle, media_id, &overrides)
+    }
+    .map_err(|_| axum::http::StatusCode::INTERNAL_SERVER_ERROR)?;
+    Ok(Json(report_with_id(media_id, report)))
+}
+
+/// The stored original, streamed from disk. A single `Range: bytes=` range is
+/// honoured so video players can seek.
+async fn media_content(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    headers: HeaderMap,
+) -> Result<Response, ApiError> {
+    let not_found = |what: &str| error_body(StatusCode::NOT_FOUND, &anyhow::anyhow!("{what}"));
+    let storage = state
+        .storage
+        .clone()
+        .ok_or_else(|| not_found("originals are not kept; start with --media-root"))?;
+    let media_id =
+        resolve_media(&state.handle, &id).map_err(|e| error_body(StatusCode::BAD_REQUEST, &e))?;
+    let stored_at = get_stored_at(&state.handle, media_id)
+        .map_err(internal_error)?
+        .ok_or_else(|| not_found("no original stored for this media"))?;
+    let mime = get_media_metadata(&state.handle, media_id)
+        .map_err(internal_error)?
+        .mime
+        .or_else(|| {
+            let (_, ext) = stored_at.rsplit_once('.')?;
+            mime_for_ext(ext).map(Into::into)
+        })
+        .unwrap_or_else(|| "application/octet-stream".into());
+    // Stored files may be compressed, so they are read on a blocking thread.
+    let (mut reader, size) = tokio::task::spawn_blocking(move || -> Result<_> {
+        let mut reader = storage.open_relative(&stored_at)?;
+        let size = reader.seek(SeekFrom::End(0))?;
+        Ok((reader, size))
+    })
+    .await
+    .map_err(|e| internal_error(e.into()))?
+    .map_err(|e| match e.downcast_ref::<StorageError>() {
+        Some(StorageError::NotFound { .. }) => not_found("the original has been evicted"),
+        Some(StorageError::Deleted { .. }) => error_body(StatusCode::GONE, &e),
+        Some(StorageError::Ambiguous { .. }) | None => internal_error(e),
+    })?;
+
+    let range = headers.get(header::RANGE).and_then(|v| v.to_str().ok());
+    let Ok(range) = parse_range(range, size) else {
+        return Ok((
+            StatusCode::RANGE_NOT_SATISFIABLE,
+            [(header::CONTENT_RANGE, format!("bytes */{size}"))],
+        )
+            .into_response());
+    };
+    let (status, start, len) = match range {
+        Some((start, end)) => (StatusCode::PARTIAL_CONTENT, start, end - start + 1),
+        None => (StatusCode::OK, 0, size),
+    };
+    let (tx, rx) = tokio::sync::mpsc::channel::<std::io::Result<Vec<u8>>>(4);
+    tokio::task::spawn_blocking(move || {
+        if let Err(e) = reader.seek(SeekFrom::Start(start)) {
+            let _ = tx.blocking_send(Err(e));
+            return;
+        }
+        let mut reader = reader.take(len);
+        loop {
+            let mut chunk = Vec::with_capacity(64 * 1024);
+            match (&mut reader).take(64 * 1024).read_to_end(&mut chunk) {
+                Ok(0) => return,
+                Ok(_) => {
+                    // Fails once the client has gone away.
+                    if tx.blocking_send(Ok(chunk)).is_err() {
+                        return;
+                    }
+                }
+                Err(e) => {
+                    let _ = tx.blocking_send(Err(e));
+                    return;
+                }
+            }
+        }
+    });
+    let chunks = futures_util::stream::unfold(rx, |mut rx| async {
+        rx.recv().await.map(|chunk| (chunk, rx))
+    });
+    let mut response = (
+        status,
+        [
+            (header::CONTENT_TYPE, mime),
+            (header::CONTENT_LENGTH, len.to_string()),
+            (header::ACCEPT_RANGES, "bytes".to_string()),
+        ],
+        axum::body::Body::from_stream(chunks),
+    )
+        .into_response();
+    if range.is_some() {
+        let content_range = format!("bytes {start}-{}/{size}", start + len - 1);
+        response.headers_mut().insert(
+            header::CONTENT_RANGE,
+            content_range.parse().expect("ASCII header value"),
+        );
+    }
+    Ok(response)
+}
+
+/// Remove the stored original of a media, e.g. for a takedown. Facts and
+/// reports about it are kept. With --tombstone-deletes the same content is
+/// refused if it is uploaded again.
+async fn delete_original(
+    State(state): State<AppState>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    Path(id): Path<String>,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    authorize_admin(state.admin_token.as_deref(), peer, &headers)?;
+    let not_found = |what: &str| error_body(StatusCode::NOT_FOUND, &anyhow::anyhow!("{what}"));
+    let storage = state
+        .storage
+        .clone()
+        .ok_or_else(|| not_found("originals are not kept; start with --media-root"))?;
+    let media_id =
+        resolve_media(&state.handle, &id).map_err(|e| error_body(StatusCode::BAD_REQUEST, &e))?;
+    let stored_at = get_stored_at(&state.handle, media_id)
+        .map_err(internal_error)?
+        .ok_or_else(|| not_found("no original stored for this media"))?;
+    let hash = stored_at
+        .split_once('.')
+        .map_or(stored_at.as_str(), |(hash, _)| hash)
+        .to_string();
+    let tombstoned = storage.tombstones;
+    let removed = tokio::task::spawn_blocking(move || storage.delete_all(&hash))
+        .await
+        .map_err(|e| internal_error(e.into()))?
+        .map_err(internal_error)?;
+    Ok(Json(serde_json::json!({
+        "media_id": media_id.0,
+        "files_removed": removed,
+        "tombstoned": tombstoned,
+    })))
+}
+
+/// 401 unless the request carries `Authorization: Bearer <token>` for the
+/// configured admin token; with no token configured, 403 for non-loopback peers.
+fn authorize_admin(
+    token: Option<&str>,
+    peer: SocketAddr,
+    headers: &HeaderMap,
+) -> Result<(), ApiError> {
+    let Some(token) = token else {
+        if peer.ip().is_loopback() {
+            return Ok(());
+        }
+        return Err(error_body(
+            StatusCode::FORBIDDEN,
+            &anyhow::anyhow!("only loopback clients may do this; start with --admin-token-env"),
+        ));
+    };
+    let given = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .unwrap_or_default();
+    // Compare every byte so the time taken does not reveal a matching prefix.
+    let matches = given.len() == token.len()
+        && given
+            .bytes()
+            .zip(token.bytes())
+            .fold(0u8, |diff, (a, b)| diff | (a ^ b))
+            == 0;
+    if matches {
+        Ok(())
+    } else {
+        Err(error_body(
+            StatusCode::UNAUTHORIZED,
+            &anyhow::anyhow!("missing or wrong admin token"),
+        ))
+    }
+}
+
+/// The inclusive byte range asked for by a `Range` header on a `size`-byte body:
+/// `Ok(None)` to send everything (no header, several ranges or one we do not
+/// understand), `Err(())` when the range lies outside the body.
+fn parse_range(header: Option<&str>, size: u64) -> Result<Option<(u64, u64)>, ()> {
+    let Some(spec) = header.and_then(|h| h.trim().strip_prefix("bytes=")) else {
+        return Ok(None);
+    };
+    if spec.contains(',') {
+        return Ok(None);
+    }
+    let Some((first, last)) = spec.split_once('-') else {
+        return Ok(None);
+    };
+    let (first, last) = (first.trim(), last.trim());
+    if first.is_empty() {
+        // A suffix: the last `n` bytes.
+        let Ok(n) = last.parse::<u64>() else {
+            return Ok(None);
+        };
+        if n == 0 || size == 0 {
+            return Err(());
+        }
+        return Ok(Some((size - n.min(size), size - 1)));
+    }
+    let Ok(start) = first.parse::<u64>() else {
+        return Ok(None);
+    };
+    let end = if last.is_empty() {
+        u64::MAX
+    } else {
+        match last.parse::<u64>() {
+            Ok(end) if end >= start => end,
+            _ => return Ok(None),
+        }
+    };
+    if start >= size {
+        return Err(());
+    }
+    Ok(Some((start, end.min(size - 1))))
+}
+
+#[derive(Deserialize)]
+struct AccuracyParams {
+    tag: Option<String>,
+    media_type: Option<String>,
+}
+
+async fn accuracy_metrics(
+    State(state): State<AppState>,
+    Query(params): Query<AccuracyParams>,
+) -> Result<Json<AccuracyReport>, axum::http::StatusCode> {
+    let media_type = params
+        .media_type
+        .map(|raw| raw.parse::<MediaType>())
+        .transpose()
+        .map_err(|_| axum::http::StatusCode::BAD_REQUEST)?;
+    let filter = media_filter(params.tag, media_type);
     let report = state
         .engine
-        .evaluate_media(&state.handle, media_id)
+        .accuracy_report(&state.handle, filter.as_ref())
         .map_err(|_| axum::http::StatusCode::INTERNAL_SERVER_ERROR)?;
-    Ok(Json(report_with_id(media_id, report)))
+    Ok(Json(report))
+}
+
+/// Disk use under --media-root; 404 when originals are not kept.
+async fn storage_metrics(State(state): State<AppState>) -> Result<Json<StorageUsage>, ApiError> {
+    let storage = state.storage.clone().ok_or_else(|| {
+        error_body(
+            StatusCode::NOT_FOUND,
+            &anyhow::anyhow!("originals are not kept; start with --media-root"),
+        )
+    })?;
+    let usage = tokio::task::spawn_blocking(move || storage.usage())
+        .await
+        .map_err(|e| internal_error(e.into()))?
+        .map_err(internal_error)?;
+    Ok(Json(usage))
+}
+
+#[derive(Deserialize)]
+struct TagRequest {
+    tag: String,
+}
+
+async fn tag_media(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Json(body): Json<TagRequest>,
+) -> Result<Json<serde_json::Value>, axum::http::StatusCode> {
+    let media_id =
+        resolve_media(&state.handle, &id).map_err(|_| axum::http::StatusCode::BAD_REQUEST)?;
+    if body.tag.trim().is_empty() {
+        return Err(axum::http::StatusCode::BAD_REQUEST);
+    }
+    add_tag(&state.handle, media_id, body.tag.trim())
+        .map_err(|_| axum::http::StatusCode::INTERNAL_SERVER_ERROR)?;
+    let tags = get_tags(&state.handle, media_id)
+        .map_err(|_| axum::http::StatusCode::INTERNAL_SERVER_ERROR)?;
+    Ok(Json(
+        serde_json::json!({"media_id": media_id.0, "tags": tags}),
+    ))
+}
+
+#[derive(Deserialize)]
+struct PageParams {
+    #[serde(default)]
+    offset: usize,
+    #[serde(default = "default_page_limit")]
+    limit: usize,
+}
+
+fn default_page_limit() -> usize {
+    100
+}
+
+async fn media_for_tag(
+    State(state): State<AppState>,
+    Path(tag): Path<String>,
+    Query(page): Query<PageParams>,
+) -> Result<Json<serde_json::Value>, axum::http::StatusCode> {
+    let media = media_with_tag(&state.handle, &tag, page.offset, page.limit)
+        .map_err(|_| axum::http::StatusCode::INTERNAL_SERVER_ERROR)?;
+    let guard = state.handle.lock().unwrap();
+    let items: Vec<serde_json::Value> = media
+        .iter()
+        .map(|m| serde_json::json!({"media_id": m.0, "name": guard.get_entity_name(m.0)}))
+        .collect();
+    Ok(Json(serde_json::json!({"tag": tag, "media": items})))
+}
+
+async fn list_detectors(State(state): State<AppState>) -> Json<serde_json::Value> {
+    let registry = state.registry.read().unwrap();
+    let items: Vec<serde_json::Value> = registry
+        .ids()
+        .into_iter()
+        .map(|(id, kind, enabled)| {
+            serde_json::json!({
+                "id": id,
+                "kind": format!("{kind:?}").to_lowercase(),
+                "enabled": enabled,
+            })
+        })
+        .collect();
+    Json(serde_json::json!({ "detectors": items }))
+}
+
+#[derive(Deserialize)]
+struct EnableRequest {
+    enabled: bool,
+}
+
+async fn enable_detector(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Json(body): Json<EnableRequest>,
+) -> Result<Json<serde_json::Value>, axum::http::StatusCode> {
+    if !state
+        .registry
+        .write()
+        .unwrap()
+        .set_enabled(&id, body.enabled)
+    {
+        return Err(axum::http::StatusCode::NOT_FOUND);
+    }
+    Ok(Json(serde_json::json!({"id": id, "enabled": body.enabled})))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn deleting_originals_needs_loopback_or_the_admin_token() {
+        let local: SocketAddr = "127.0.0.1:9000".parse().unwrap();
+        let remote: SocketAddr = "203.0.113.7:9000".parse().unwrap();
+        let none = HeaderMap::new();
+        assert!(authorize_admin(None, local, &none).is_ok());
+        assert_eq!(
+            authorize_admin(None, remote, &none).unwrap_err().0,
+            StatusCode::FORBIDDEN
+        );
+
+        let mut bearer = HeaderMap::new();
+        bearer.insert(header::AUTHORIZATION, "Bearer s3cret".parse().unwrap());
+        assert!(authorize_admin(Some("s3cret"), remote, &bearer).is_ok());
+        for token in ["s3cre", "s3cret!", "other!"] {
+            assert_eq!(
+                authorize_admin(Some(token), remote, &bearer).unwrap_err().0,
+                StatusCode::UNAUTHORIZED
+            );
+        }
+        assert_eq!(
+            authorize_admin(Some("s3cret"), local, &none).unwrap_err().0,
+            StatusCode::UNAUTHORIZED
+        );
+    }
+
+    #[test]
+    fn refused_ingests_map_to_client_errors() {
+        let too_large = anyhow::Error::new(IngestError::TooLarge {
+            media_type: MediaType::Image,
+            unit: "bytes",
+            actual: 10,
+            limit: 5,
+        });
+        let (status, Json(body)) = ingest_failure(too_large);
+        assert_eq!(status, StatusCode::PAYLOAD_TOO_LARGE);
+        assert!(body["error"]
+            .as_str()
+            .unwrap()
+            .contains("over the limit of 5"));
+
+        let invalid = anyhow::Error::new(IngestError::Invalid {
+            media_type: MediaType::Text,
+            reason: "text is not valid UTF-8".into(),
+        });
+        assert_eq!(ingest_failure(invalid).0, StatusCode::UNPROCESSABLE_ENTITY);
+
+        let full = anyhow::Error::new(QuotaExceeded {
+            needed: 10,
+            used: 95,
+            max_bytes: 100,
+        });
+        assert_eq!(ingest_failure(full).0, StatusCode::INSUFFICIENT_STORAGE);
+        let deleted = anyhow::Error::new(StorageError::Deleted { hash: "abc".into() });
+        assert_eq!(ingest_failure(deleted).0, StatusCode::GONE);
+
+        let (status, Json(body)) = ingest_failure(anyhow::anyhow!("disk full at /srv/data"));
+        assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(body["error"], "internal error");
+    }
+
+    #[test]
+    fn range_headers_are_clamped_to_the_body() {
+        assert_eq!(parse_range(None, 100), Ok(None));
+        assert_eq!(parse_range(Some("bytes=0-9"), 100), Ok(Some((0, 9))));
+        assert_eq!(parse_range(Some("bytes=90-"), 100), Ok(Some((90, 99))));
+        assert_eq!(parse_range(Some("bytes=90-500"), 100), Ok(Some((90, 99))));
+        assert_eq!(parse_range(Some("bytes=-10"), 100), Ok(Some((90, 99))));
+        assert_eq!(parse_range(Some("bytes=-500"), 100), Ok(Some((0, 99))));
+        assert_eq!(parse_range(Some("bytes=100-"), 100), Err(()));
+        assert_eq!(parse_range(Some("bytes=-0"), 100), Err(()));
+        assert_eq!(parse_range(Some("bytes=0-1,5-6"), 100), Ok(None));
+        assert_eq!(parse_range(Some("bytes=9-1"), 100), Ok(None));
+        assert_eq!(parse_range(Some("items=0-1"), 100), Ok(None));
+    }
 }
@@ -0,0 +1,194 @@
+//! `pru bench`: a quick throughput check on synthetic data, without criterion.
+//!
+//! The store goes in a fresh `pru-bench-*` directory under `--dir`, which is
+//! removed afterwards unless `--keep` is given.
+
+use anyhow::Result;
+use pru_core::{atom_id128, Fact, IndexOptions, KeyKind, PruStore, ResolverKey};
+use rand::Rng;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use crate::output::{self, OutputFormat};
+
+/// Predicates the synthetic facts draw from.
+const PREDICATES: usize = 16;
+
+pub struct BenchOptions {
+    pub facts: usize,
+    pub entities: usize,
+    /// Facts added per transaction.
+    pub batch: usize,
+    /// Build an S index and resolve this many random subjects.
+    pub resolve_keys: Option<usize>,
+}
+
+#[derive(Serialize)]
+pub struct Throughput {
+    pub ops: usize,
+    pub secs: f64,
+    pub ops_per_sec: f64,
+}
+
+impl Throughput {
+    fn new(ops: usize, elapsed: Duration) -> Self {
+        let secs = elapsed.as_secs_f64();
+        Self {
+            ops,
+            secs,
+            ops_per_sec: if secs > 0.0 { ops as f64 / secs } else { 0.0 },
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct Latency {
+    #[serde(flatten)]
+    pub throughput: Throughput,
+    pub p50_us: f64,
+    pub p99_us: f64,
+    pub max_us: f64,
+}
+
+impl Latency {
+    fn new(mut samples: Vec<Duration>) -> Self {
+        samples.sort_unstable();
+        let total = samples.iter().sum();
+        let micros = |d: Option<&Duration>| d.map_or(0.0, |d| d.as_secs_f64() * 1e6);
+        let at = |q: f64| {
+            let i = ((samples.len() as f64 * q).ceil() as usize).saturating_sub(1);
+            micros(samples.get(i))
+        };
+        Self {
+            p50_us: at(0.50),
+            p99_us: at(0.99),
+            max_us: micros(samples.last()),
+            throughput: Throughput::new(samples.len(), total),
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct BenchReport {
+    pub dir: PathBuf,
+    pub kept: bool,
+    pub intern: Throughput,
+    pub add_fact: Throughput,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub index: Option<Throughput>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub resolve: Option<Latency>,
+}
+
+fn entity(i: usize) -> String {
+    format!("entity-{i}")
+}
+
+/// Run the benchmark in a new directory under `parent` and print the report.
+pub fn run(parent: &Path, options: BenchOptions, keep: bool, format: OutputFormat) -> Result<()> {
+    let dir = parent.join(format!("pru-bench-{}", crate::now_id()));
+    let result = measure(&dir, &options);
+    if !keep {
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+    let (intern, add_fact, index, resolve) = result?;
+    let report = BenchReport {
+        dir,
+        kept: keep,
+        intern,
+        add_fact,
+        index,
+        resolve,
+    };
+    print(&report, format)
+}
+
+type Measurements = (Throughput, Throughput, Option<Throughput>, Option<Latency>);
+
+fn measure(dir: &Path, options: &BenchOptions) -> Result<Measurements> {
+    let mut store = PruStore::open(dir)?;
+    let mut rng = rand::rng();
+
+    let predicates = (0..PREDICATES)
+        .map(|i| store.intern_predicate(&format!("predicate-{i}")))
+        .collect::<pru_core::errors::Result<Vec<_>>>()?;
+    let start = Instant::now();
+    let mut entities = Vec::with_capacity(options.entities);
+    for i in 0..options.entities {
+        entities.push(store.intern_entity(&entity(i))?);
+    }
+    let intern = Throughput::new(entities.len(), start.elapsed());
+
+    let start = Instant::now();
+    if !entities.is_empty() {
+        let mut left = options.facts;
+        while left > 0 {
+            let n = left.min(options.batch.max(1));
+            left -= n;
+            store.transaction(|store| {
+                (0..n).try_for_each(|_| {
+                    store.add_fact(Fact {
+                        subject: entities[rng.random_range(0..entities.len())],
+                        predicate: predicates[rng.random_range(0..predicates.len())],
+                        object: entities[rng.random_range(0..entities.len())],
+                        source: None,
+                        timestamp: None,
+                        confidence: None,
+                    })
+                })
+            })?;
+        }
+    }
+    let add_fact = Throughput::new(store.fact_count(), start.elapsed());
+
+    let Some(keys) = options.resolve_keys else {
+        return Ok((intern, add_fact, None, None));
+    };
+    let start = Instant::now();
+    store.build_index(&[KeyKind::S], IndexOptions::default())?;
+    let index = Throughput::new(store.fact_count(), start.elapsed());
+    let mut samples = Vec::with_capacity(keys);
+    if let (Some(resolver), false) = (store.resolver_store(), entities.is_empty()) {
+        for _ in 0..keys {
+            let name = entity(rng.random_range(0..entities.len()));
+            let key = ResolverKey::single(KeyKind::S, &atom_id128(name.as_bytes()));
+            let start = Instant::now();
+            std::hint::black_box(resolver.resolve(&key.0));
+            samples.push(start.elapsed());
+        }
+    }
+    Ok((intern, add_fact, Some(index), Some(Latency::new(samples))))
+}
+
+fn print(report: &BenchReport, format: OutputFormat) -> Result<()> {
+    if format != OutputFormat::Text {
+        return output::emit_one(format, report);
+    }
+    let line = |label: &str, what: &str, t: &Throughput| {
+        println!(
+            "{label:<9}{:>9} {what:<9} in {:>8.3}s  {:>10.0} ops/s",
+            t.ops, t.secs, t.ops_per_sec
+        );
+    };
+    line("intern", "entities", &report.intern);
+    line("add_fact", "facts", &report.add_fact);
+    if let Some(index) = &report.index {
+        line("index", "facts", index);
+    }
+    if let Some(resolve) = &report.resolve {
+        println!(
+            "{:<9}{:>9} {:<9} p50 {:.1}µs  p99 {:.1}µs  max {:.1}µs  {:>10.0} ops/s",
+            "resolve",
+            resolve.throughput.ops,
+            "keys",
+            resolve.p50_us,
+            resolve.p99_us,
+            resolve.max_us,
+            resolve.throughput.ops_per_sec
+        );
+    }
+    let state = if report.kept { "kept" } else { "removed" };
+    println!("store {state}: {}", report.dir.display());
+    Ok(())
+}
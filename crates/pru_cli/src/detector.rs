@@ -0,0 +1,165 @@
+//! `pru detector`: what the store knows about detectors and how reliable they
+//! have proven against human verdicts.
+
+use anyhow::{anyhow, Result};
+use pru_core::PruDbHandle;
+use pru_media_schema::{
+    detector_entity_name, get_detector_info, get_detector_reliability, set_detector_reliability,
+    DetectorId, DetectorInfo, DetectorReliability,
+};
+use serde::Serialize;
+use std::collections::BTreeSet;
+
+use crate::output::{self, OutputFormat};
+
+const PREFIX: &str = "detector:";
+
+/// A detector entity by name, with or without the `detector:` prefix.
+pub fn resolve(handle: &PruDbHandle, id: &str) -> Result<(DetectorId, String)> {
+    let name = if id.starts_with(PREFIX) {
+        id.to_string()
+    } else {
+        detector_entity_name(id)
+    };
+    let found = handle.lock().unwrap().get_entity_id(&name);
+    found
+        .map(|id| (DetectorId(id), name))
+        .ok_or_else(|| anyhow!("no detector named {id}"))
+}
+
+/// One line of `detector list`.
+#[derive(Serialize)]
+pub struct DetectorEntry {
+    pub detector_id: u64,
+    pub name: String,
+    /// Registered metadata, when the detector was registered rather than only scored.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub info: Option<DetectorInfo>,
+    pub seen: u64,
+    pub correct: u64,
+}
+
+pub fn list(handle: &PruDbHandle, format: OutputFormat) -> Result<()> {
+    let entities = handle.lock().unwrap().entities();
+    let mut entries = Vec::new();
+    for (id, name) in entities {
+        if !name.starts_with(PREFIX) {
+            continue;
+        }
+        let reliability = get_detector_reliability(handle, DetectorId(id))?.unwrap_or_default();
+        entries.push(DetectorEntry {
+            detector_id: id,
+            name,
+            info: get_detector_info(handle, DetectorId(id))?,
+            seen: reliability.seen,
+            correct: reliability.correct,
+        });
+    }
+    entries.sort_by(|a, b| a.name.cmp(&b.name));
+    if format != OutputFormat::Text {
+        return output::emit(format, &entries);
+    }
+    if entries.is_empty() {
+        println!("no detectors found");
+    }
+    for entry in &entries {
+        let info = entry
+            .info
+            .as_ref()
+            .map(|info| format!("\t{} {}", info.kind, info.version))
+            .unwrap_or_default();
+        println!(
+            "#{}\t{}{info}\t{}/{} correct",
+            entry.detector_id, entry.name, entry.correct, entry.seen
+        );
+    }
+    Ok(())
+}
+
+/// `detector reliability`; `--format json` prints this.
+#[derive(Serialize)]
+pub struct ReliabilityReport {
+    pub detector_id: u64,
+    pub name: String,
+    /// Whether any reliability has been stored; the counts are zero otherwise.
+    pub recorded: bool,
+    #[serde(flatten)]
+    pub reliability: DetectorReliability,
+}
+
+pub fn reliability(handle: &PruDbHandle, id: &str, format: OutputFormat) -> Result<()> {
+    let (detector, name) = resolve(handle, id)?;
+    let stored = get_detector_reliability(handle, detector)?;
+    let report = ReliabilityReport {
+        detector_id: detector.0,
+        name,
+        recorded: stored.is_some(),
+        reliability: stored.unwrap_or_default(),
+    };
+    if format != OutputFormat::Text {
+        return output::emit_one(format, &report);
+    }
+    println!("{} (#{})", report.name, report.detector_id);
+    if !report.recorded {
+        println!("no reliability recorded");
+        return Ok(());
+    }
+    print_table(&report.reliability);
+    Ok(())
+}
+
+/// Totals, then the confusion matrix with predicted labels as rows and the
+/// human verdicts as columns.
+fn print_table(reliability: &DetectorReliability) {
+    let share = |n: u64, of: u64| {
+        if of == 0 {
+            "-".to_string()
+        } else {
+            format!("{:.1}%", n as f64 * 100.0 / of as f64)
+        }
+    };
+    println!(
+        "seen {}, correct {} ({})",
+        reliability.seen,
+        reliability.correct,
+        share(reliability.correct, reliability.seen)
+    );
+    if reliability.confusion.is_empty() {
+        return;
+    }
+    let predicted: Vec<&String> = reliability.confusion.keys().collect();
+    let actual: BTreeSet<&String> = reliability
+        .confusion
+        .values()
+        .flat_map(|row| row.keys())
+        .collect();
+    let corner = "predicted \\ actual";
+    let first = predicted
+        .iter()
+        .map(|l| l.len())
+        .fold(corner.len(), usize::max);
+    let width = actual.iter().map(|l| l.len()).fold(6, usize::max);
+    let mut header = format!("{corner:<first$}");
+    for label in &actual {
+        header.push_str(&format!("  {label:>width$}"));
+    }
+    println!("{header}  {:>9}", "precision");
+    for label in predicted {
+        let mut row = format!("{label:<first$}");
+        for truth in &actual {
+            row.push_str(&format!("  {:>width$}", reliability.count(label, truth)));
+        }
+        let precision = reliability
+            .precision(label)
+            .map_or_else(|| "-".to_string(), |p| format!("{:.1}%", p * 100.0));
+        println!("{row}  {precision:>9}");
+    }
+}
+
+/// Replace the detector's reliability with an empty one. The history stays in
+/// the fact log; the engine reads the latest payload.
+pub fn reset(handle: &PruDbHandle, detector: DetectorId, name: &str) -> Result<()> {
+    set_detector_reliability(handle, detector, &DetectorReliability::default())?;
+    println!("reset reliability of {name}");
+    Ok(())
+}
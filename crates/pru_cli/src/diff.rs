@@ -0,0 +1,172 @@
+//! `pru diff`: how two stores differ, by name rather than by id.
+//!
+//! Facts are compared as subject, predicate and object names (with the object's
+//! kind); source, timestamp and confidence are ignored. Atoms without a name
+//! fall back to their id. Each side is held as a set of 128-bit digests, and
+//! only the differing facts are kept in full, when they are to be listed.
+
+use anyhow::Result;
+use pru_core::{atom_id128, AtomHash, Fact, PruStore, Query};
+use serde::Serialize;
+use std::collections::HashSet;
+
+use crate::output::{self, OutputFormat};
+use pru_core::export::{FactRecord, ObjectKind};
+
+/// Names or facts present in one store but not the other. `added` are only in
+/// the other store, `removed` only in the first one.
+#[derive(Serialize)]
+pub struct Changes<T> {
+    pub added: usize,
+    pub removed: usize,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub added_items: Vec<T>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub removed_items: Vec<T>,
+}
+
+impl<T> Changes<T> {
+    fn is_empty(&self) -> bool {
+        self.added == 0 && self.removed == 0
+    }
+}
+
+#[derive(Serialize)]
+pub struct StoreDiff {
+    pub entities: Changes<String>,
+    pub predicates: Changes<String>,
+    pub literals: Changes<String>,
+    pub facts: Changes<FactRecord>,
+}
+
+impl StoreDiff {
+    pub fn is_empty(&self) -> bool {
+        self.entities.is_empty()
+            && self.predicates.is_empty()
+            && self.literals.is_empty()
+            && self.facts.is_empty()
+    }
+}
+
+/// Compare `ours` against `theirs`; `list` keeps the differing names and facts
+/// as well as their counts.
+pub fn diff(ours: &PruStore, theirs: &PruStore, list: bool) -> StoreDiff {
+    StoreDiff {
+        entities: names(ours.entities(), theirs.entities(), list),
+        predicates: names(ours.predicates(), theirs.predicates(), list),
+        literals: names(ours.literals(), theirs.literals(), list),
+        facts: facts(ours, theirs, list),
+    }
+}
+
+fn names(ours: Vec<(u64, String)>, theirs: Vec<(u64, String)>, list: bool) -> Changes<String> {
+    let ours: HashSet<String> = ours.into_iter().map(|(_, name)| name).collect();
+    let theirs: HashSet<String> = theirs.into_iter().map(|(_, name)| name).collect();
+    let mut added: Vec<String> = theirs.difference(&ours).cloned().collect();
+    let mut removed: Vec<String> = ours.difference(&theirs).cloned().collect();
+    added.sort();
+    removed.sort();
+    Changes {
+        added: added.len(),
+        removed: removed.len(),
+        added_items: if list { added } else { vec![] },
+        removed_items: if list { removed } else { vec![] },
+    }
+}
+
+/// The fact by name, without the metadata the diff ignores.
+fn normalized(store: &PruStore, fact: &Fact) -> FactRecord {
+    FactRecord {
+        source_id: None,
+        timestamp: None,
+        confidence: None,
+        ..FactRecord::new(store, fact, false)
+    }
+}
+
+fn digest(record: &FactRecord) -> AtomHash {
+    let key = serde_json::to_vec(record).expect("fact records serialize");
+    atom_id128(&key)
+}
+
+fn digests(store: &PruStore) -> HashSet<AtomHash> {
+    store
+        .query_iter(Query::default())
+        .map(|f| digest(&normalized(store, f)))
+        .collect()
+}
+
+/// Facts of `store` whose digest is not in `other`, counted and, with `list`,
+/// collected once each.
+fn missing_from(
+    store: &PruStore,
+    other: &HashSet<AtomHash>,
+    list: bool,
+) -> (usize, Vec<FactRecord>) {
+    let mut seen = HashSet::new();
+    let mut items = vec![];
+    for fact in store.query_iter(Query::default()) {
+        let record = normalized(store, fact);
+        let key = digest(&record);
+        if !other.contains(&key) && seen.insert(key) && list {
+            items.push(record);
+        }
+    }
+    (seen.len(), items)
+}
+
+fn facts(ours: &PruStore, theirs: &PruStore, list: bool) -> Changes<FactRecord> {
+    let (removed, removed_items) = missing_from(ours, &digests(theirs), list);
+    let (added, added_items) = missing_from(theirs, &digests(ours), list);
+    Changes {
+        added,
+        removed,
+        added_items,
+        removed_items,
+    }
+}
+
+fn render_fact(record: &FactRecord) -> String {
+    let atom = |name: &Option<String>, id: Option<u64>| {
+        name.clone()
+            .unwrap_or_else(|| format!("#{}", id.unwrap_or_default()))
+    };
+    let object = match (&record.object, record.object_kind) {
+        (Some(value), Some(ObjectKind::Literal)) => format!("{value:?}"),
+        _ => atom(&record.object, record.object_id),
+    };
+    format!(
+        "{} {} {object}",
+        atom(&record.subject, record.subject_id),
+        atom(&record.predicate, record.predicate_id)
+    )
+}
+
+pub fn print(diff: &StoreDiff, format: OutputFormat) -> Result<()> {
+    if format != OutputFormat::Text {
+        return output::emit_one(format, diff);
+    }
+    let sections = [
+        ("entities", &diff.entities),
+        ("predicates", &diff.predicates),
+        ("literals", &diff.literals),
+    ];
+    for (label, changes) in sections {
+        println!("{label}: +{} -{}", changes.added, changes.removed);
+        for name in &changes.added_items {
+            println!("  + {name}");
+        }
+        for name in &changes.removed_items {
+            println!("  - {name}");
+        }
+    }
+    let facts = &diff.facts;
+    println!("facts: +{} -{}", facts.added, facts.removed);
+    for record in &facts.added_items {
+        println!("  + {}", render_fact(record));
+    }
+    for record in &facts.removed_items {
+        println!("  - {}", render_fact(record));
+    }
+    Ok(())
+}
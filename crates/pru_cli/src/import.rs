@@ -0,0 +1,443 @@
+//! `pru import`: facts from JSONL (as written by `pru export`) or N-Triples.
+
+use anyhow::{anyhow, bail, Result};
+use clap::ValueEnum;
+use pru_core::{Fact, PruStore, Query};
+use std::collections::{HashMap, HashSet};
+use std::io::{BufRead, Read};
+
+use pru_core::export::{FactRecord, ObjectKind, IRI_PREFIX};
+
+/// Facts written to the store per fact-log write.
+const BATCH: usize = 1000;
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ImportFormat {
+    Jsonl,
+    Ntriples,
+}
+
+/// What to do with a fact whose subject, predicate, object and source are
+/// already stored (or appeared earlier in the same file).
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OnDuplicate {
+    #[default]
+    Skip,
+    Add,
+}
+
+#[derive(Debug, Default)]
+pub struct ImportSummary {
+    pub entities_created: usize,
+    pub predicates_created: usize,
+    pub literals_created: usize,
+    pub facts_added: usize,
+    pub duplicates_skipped: usize,
+    /// Line number and reason for every line that was not imported.
+    pub rejected: Vec<(usize, String)>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+enum Kind {
+    Entity,
+    Predicate,
+    Literal,
+}
+
+/// Interns atoms, or with `dry_run` only counts the ones that would be created,
+/// handing out placeholder ids for them.
+struct Atoms<'a> {
+    store: &'a mut PruStore,
+    dry_run: bool,
+    pending: HashMap<(Kind, String), u64>,
+    summary: ImportSummary,
+}
+
+impl Atoms<'_> {
+    fn name(&mut self, kind: Kind, name: &str) -> Result<u64> {
+        if name.trim().is_empty() {
+            bail!("empty name");
+        }
+        let existing = match kind {
+            Kind::Entity => self.store.get_entity_id(name),
+            Kind::Predicate => self.store.get_predicate_id(name),
+            Kind::Literal => self.store.get_literal_id(name),
+        };
+        if let Some(id) = existing {
+            return Ok(id);
+        }
+        if let Some(id) = self.pending.get(&(kind, name.to_string())) {
+            return Ok(*id);
+        }
+        let id = if self.dry_run {
+            u64::MAX - self.pending.len() as u64
+        } else {
+            match kind {
+                Kind::Entity => self.store.intern_entity(name)?,
+                Kind::Predicate => self.store.intern_predicate(name)?,
+                Kind::Literal => self.store.intern_literal(name)?,
+            }
+        };
+        self.pending.insert((kind, name.to_string()), id);
+        match kind {
+            Kind::Entity => self.summary.entities_created += 1,
+            Kind::Predicate => self.summary.predicates_created += 1,
+            Kind::Literal => self.summary.literals_created += 1,
+        }
+        Ok(id)
+    }
+
+    fn id(&self, kind: Kind, id: u64) -> Result<u64> {
+        let known = match kind {
+            Kind::Entity => self.store.get_entity_name(id).is_some(),
+            Kind::Predicate => self.store.get_predicate_name(id).is_some(),
+            Kind::Literal => {
+                self.store.get_literal_value(id).is_some()
+                    || self.store.get_entity_name(id).is_some()
+            }
+        };
+        if !known {
+            bail!("unknown id {id}");
+        }
+        Ok(id)
+    }
+
+    fn atom(
+        &mut self,
+        kind: Kind,
+        name: Option<String>,
+        id: Option<u64>,
+        what: &str,
+    ) -> Result<u64> {
+        match (name, id) {
+            (Some(name), None) => self.name(kind, &name),
+            (None, Some(id)) => self.id(kind, id),
+            (Some(_), Some(_)) => bail!("both {what} and {what}_id given"),
+            (None, None) => bail!("{what} is missing"),
+        }
+        .map_err(|e| anyhow!("{what}: {e}"))
+    }
+
+    fn fact(&mut self, record: FactRecord) -> Result<Fact> {
+        let subject = self.atom(Kind::Entity, record.subject, record.subject_id, "subject")?;
+        let predicate = self.atom(
+            Kind::Predicate,
+            record.predicate,
+            record.predicate_id,
+            "predicate",
+        )?;
+        // Objects named without a kind are literals, as with `fact add --object`.
+        let kind = match record.object_kind {
+            Some(ObjectKind::Entity) => Kind::Entity,
+            Some(ObjectKind::Literal) | None => Kind::Literal,
+        };
+        let object = self.atom(kind, record.object, record.object_id, "object")?;
+        Ok(Fact {
+            subject,
+            predicate,
+            object,
+            source: record.source_id,
+            timestamp: record.timestamp,
+            confidence: record.confidence,
+        })
+    }
+}
+
+type Key = (u64, u64, u64, Option<u64>);
+
+fn key(fact: &Fact) -> Key {
+    (fact.subject, fact.predicate, fact.object, fact.source)
+}
+
+/// Read facts from `input` into `store`. Lines that cannot be parsed or refer
+/// to unknown ids are rejected and reported; everything else is imported.
+/// With `dry_run` nothing is written, but the summary is the same.
+pub fn import(
+    store: &mut PruStore,
+    input: impl BufRead,
+    format: ImportFormat,
+    on_duplicate: OnDuplicate,
+    dry_run: bool,
+) -> Result<ImportSummary> {
+    let mut seen: HashSet<Key> = store.query_iter(Query::default()).map(key).collect();
+    let mut atoms = Atoms {
+        store,
+        dry_run,
+        pending: HashMap::new(),
+        summary: ImportSummary::default(),
+    };
+    let mut batch = Vec::with_capacity(BATCH);
+    for (n, line) in input.lines().enumerate() {
+        let line = line?;
+        let trimmed = line.trim();
+        if trimmed.is_empty() || (format == ImportFormat::Ntriples && trimmed.starts_with('#')) {
+            continue;
+        }
+        let record = match format {
+            ImportFormat::Jsonl => serde_json::from_str(trimmed).map_err(anyhow::Error::from),
+            ImportFormat::Ntriples => parse_triple(trimmed),
+        };
+        let fact = match record.and_then(|record| atoms.fact(record)) {
+            Ok(fact) => fact,
+            Err(e) => {
+                atoms.summary.rejected.push((n + 1, e.to_string()));
+                continue;
+            }
+        };
+        if !seen.insert(key(&fact)) && on_duplicate == OnDuplicate::Skip {
+            atoms.summary.duplicates_skipped += 1;
+            continue;
+        }
+        atoms.summary.facts_added += 1;
+        if !dry_run {
+            batch.push(fact);
+            if batch.len() == BATCH {
+                write_batch(atoms.store, &mut batch)?;
+            }
+        }
+    }
+    if !batch.is_empty() {
+        write_batch(atoms.store, &mut batch)?;
+    }
+    Ok(atoms.summary)
+}
+
+/// Column names that mark the first CSV row as a header.
+const CSV_COLUMNS: [&str; 5] = ["subject", "predicate", "object", "confidence", "timestamp"];
+
+/// Add facts from `subject,predicate,object[,confidence[,timestamp]]` rows,
+/// interning names as needed. Objects are entities when an entity of that name
+/// exists and literals otherwise. A first row naming the columns is skipped.
+/// Rows without a timestamp get `default_timestamp`; duplicates are added.
+pub fn add_csv(
+    store: &mut PruStore,
+    input: impl Read,
+    delimiter: u8,
+    default_timestamp: i64,
+) -> Result<ImportSummary> {
+    let mut reader = csv::ReaderBuilder::new()
+        .has_headers(false)
+        .flexible(true)
+        .delimiter(delimiter)
+        .from_reader(input);
+    let mut atoms = Atoms {
+        store,
+        dry_run: false,
+        pending: HashMap::new(),
+        summary: ImportSummary::default(),
+    };
+    let mut batch = Vec::with_capacity(BATCH);
+    for (n, row) in reader.records().enumerate() {
+        let line = match &row {
+            Ok(row) => row.position().map_or(n as u64 + 1, |p| p.line()) as usize,
+            Err(e) => e.position().map_or(n as u64 + 1, |p| p.line()) as usize,
+        };
+        let row = match row {
+            Ok(row) => row,
+            Err(e) => {
+                atoms.summary.rejected.push((line, e.to_string()));
+                continue;
+            }
+        };
+        let is_header = n == 0
+            && row
+                .iter()
+                .zip(CSV_COLUMNS)
+                .all(|(field, name)| field.trim().eq_ignore_ascii_case(name));
+        if is_header {
+            continue;
+        }
+        match csv_fact(&mut atoms, &row, default_timestamp) {
+            Ok(fact) => {
+                atoms.summary.facts_added += 1;
+                batch.push(fact);
+                if batch.len() == BATCH {
+                    write_batch(atoms.store, &mut batch)?;
+                }
+            }
+            Err(e) => atoms.summary.rejected.push((line, e.to_string())),
+        }
+    }
+    if !batch.is_empty() {
+        write_batch(atoms.store, &mut batch)?;
+    }
+    Ok(atoms.summary)
+}
+
+fn csv_fact(atoms: &mut Atoms, row: &csv::StringRecord, default_timestamp: i64) -> Result<Fact> {
+    if !(3..=5).contains(&row.len()) {
+        bail!(
+            "expected subject,predicate,object[,confidence[,timestamp]], got {} fields",
+            row.len()
+        );
+    }
+    let subject = atoms
+        .name(Kind::Entity, &row[0])
+        .map_err(|e| anyhow!("subject: {e}"))?;
+    let predicate = atoms
+        .name(Kind::Predicate, &row[1])
+        .map_err(|e| anyhow!("predicate: {e}"))?;
+    let kind = if atoms.store.get_entity_id(&row[2]).is_some() {
+        Kind::Entity
+    } else {
+        Kind::Literal
+    };
+    let object = atoms
+        .name(kind, &row[2])
+        .map_err(|e| anyhow!("object: {e}"))?;
+    let field = |i: usize| row.get(i).map(str::trim).filter(|f| !f.is_empty());
+    let confidence = match field(3) {
+        Some(c) => c
+            .parse::<f32>()
+            .ok()
+            .filter(|c| (0.0..=1.0).contains(c))
+            .ok_or_else(|| anyhow!("confidence {c:?} is not a number in [0, 1]"))?,
+        None => 1.0,
+    };
+    let timestamp = match field(4) {
+        Some(t) => t
+            .parse::<i64>()
+            .map_err(|_| anyhow!("timestamp {t:?} is not an integer"))?,
+        None => default_timestamp,
+    };
+    Ok(Fact {
+        subject,
+        predicate,
+        object,
+        source: None,
+        timestamp: Some(timestamp),
+        confidence: Some(confidence),
+    })
+}
+
+fn write_batch(store: &mut PruStore, batch: &mut Vec<Fact>) -> Result<()> {
+    store.transaction(|store| batch.drain(..).try_for_each(|fact| store.add_fact(fact)))?;
+    Ok(())
+}
+
+/// One N-Triples statement. IRIs in the `urn:pru:` scheme map back to the
+/// atoms `pru export` wrote; any other IRI is used as an entity or predicate
+/// name as it is. Datatypes and language tags on literals are dropped.
+fn parse_triple(line: &str) -> Result<FactRecord> {
+    let (subject, rest) = parse_iri(line)?;
+    let (predicate, rest) = parse_iri(rest.trim_start())?;
+    let rest = rest.trim_start();
+    let mut record = FactRecord::default();
+    let rest = if rest.starts_with('"') {
+        let (value, rest) = parse_literal(rest)?;
+        record.object = Some(value);
+        record.object_kind = Some(ObjectKind::Literal);
+        rest
+    } else {
+        let (object, rest) = parse_iri(rest)?;
+        match atom_of(&object, "entity")? {
+            Ok(name) => {
+                record.object = Some(name);
+                record.object_kind = Some(ObjectKind::Entity);
+            }
+            Err(id) => record.object_id = Some(id),
+        }
+        rest
+    };
+    if rest.trim_start().strip_prefix('.').is_none() {
+        bail!("expected '.' at the end of the triple");
+    }
+    match atom_of(&subject, "entity")? {
+        Ok(name) => record.subject = Some(name),
+        Err(id) => record.subject_id = Some(id),
+    }
+    match atom_of(&predicate, "predicate")? {
+        Ok(name) => record.predicate = Some(name),
+        Err(id) => record.predicate_id = Some(id),
+    }
+    Ok(record)
+}
+
+/// The name (`Ok`) or id (`Err`) an IRI stands for.
+fn atom_of(iri: &str, kind: &str) -> Result<Result<String, u64>> {
+    let Some(rest) = iri.strip_prefix(IRI_PREFIX) else {
+        return Ok(Ok(iri.to_string()));
+    };
+    if let Some(id) = rest.strip_prefix("id:") {
+        return Ok(Err(id.parse().map_err(|_| anyhow!("bad id in <{iri}>"))?));
+    }
+    match rest.strip_prefix(kind).and_then(|r| r.strip_prefix(':')) {
+        Some(name) => Ok(Ok(percent_decode(name)?)),
+        None => bail!(
+            "<{iri}> is not a{} {kind}",
+            if kind == "entity" { "n" } else { "" }
+        ),
+    }
+}
+
+fn parse_iri(text: &str) -> Result<(String, &str)> {
+    let Some(rest) = text.strip_prefix('<') else {
+        bail!("expected an IRI at {:?}", truncate(text));
+    };
+    let end = rest.find('>').ok_or_else(|| anyhow!("unterminated IRI"))?;
+    Ok((rest[..end].to_string(), &rest[end + 1..]))
+}
+
+fn parse_literal(text: &str) -> Result<(String, &str)> {
+    let mut value = String::new();
+    let mut chars = text[1..].char_indices();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '"' => {
+                let mut rest = &text[i + 2..];
+                // A datatype or language tag.
+                if let Some(typed) = rest.strip_prefix("^^") {
+                    rest = parse_iri(typed)?.1;
+                } else if let Some(tagged) = rest.strip_prefix('@') {
+                    let end = tagged.find(|c: char| c.is_whitespace() || c == '.');
+                    rest = &tagged[end.unwrap_or(tagged.len())..];
+                }
+                return Ok((value, rest));
+            }
+            '\\' => {
+                let escaped = match chars.next().map(|(_, c)| c) {
+                    Some('n') => '\n',
+                    Some('r') => '\r',
+                    Some('t') => '\t',
+                    Some('"') => '"',
+                    Some('\\') => '\\',
+                    Some(u @ ('u' | 'U')) => {
+                        let len = if u == 'u' { 4 } else { 8 };
+                        let hex: String = chars.by_ref().take(len).map(|(_, c)| c).collect();
+                        u32::from_str_radix(&hex, 16)
+                            .ok()
+                            .and_then(char::from_u32)
+                            .ok_or_else(|| anyhow!("bad escape \\{u}{hex}"))?
+                    }
+                    other => bail!("bad escape \\{}", other.unwrap_or(' ')),
+                };
+                value.push(escaped);
+            }
+            c => value.push(c),
+        }
+    }
+    bail!("unterminated literal")
+}
+
+fn percent_decode(text: &str) -> Result<String> {
+    let bytes = text.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hex = text
+                .get(i + 1..i + 3)
+                .ok_or_else(|| anyhow!("bad escape in {text}"))?;
+            out.push(u8::from_str_radix(hex, 16).map_err(|_| anyhow!("bad escape in {text}"))?);
+            i += 3;
+        } else {
+            out.push(bytes[i]);
+            i += 1;
+        }
+    }
+    Ok(String::from_utf8(out)?)
+}
+
+fn truncate(text: &str) -> String {
+    text.chars().take(20).collect()
+}
@@ -0,0 +1,80 @@
+//! `pru segment inspect|dump`: look inside a single segment file.
+
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use pru_core::{consts::SegmentKind, decode_sorted_u64, SegmentLayout, SegmentReader};
+use serde::Serialize;
+use std::path::Path;
+
+/// What to print for each entry's value.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ValueFormat {
+    Hex,
+    Len,
+    /// The decoded id list; resolver segments only
+    Postings,
+}
+
+#[derive(Serialize)]
+struct Inspection<'a> {
+    path: &'a Path,
+    #[serde(flatten)]
+    layout: SegmentLayout,
+}
+
+fn open(path: &Path) -> Result<SegmentReader> {
+    SegmentReader::open(path).with_context(|| format!("failed to open segment {}", path.display()))
+}
+
+pub fn inspect(path: &Path) -> Result<()> {
+    let reader = open(path)?;
+    let inspection = Inspection {
+        path,
+        layout: reader.layout(),
+    };
+    println!("{}", serde_json::to_string_pretty(&inspection)?);
+    Ok(())
+}
+
+/// Print up to `limit` index entries. Entries whose bounds or checksum are
+/// wrong are marked and the dump goes on.
+pub fn dump(path: &Path, limit: Option<usize>, values: Option<ValueFormat>) -> Result<()> {
+    let reader = open(path)?;
+    let file_len = reader.layout().file_len;
+    let (mut shown, mut corrupt) = (0usize, 0usize);
+    for entry in reader.iter().take(limit.unwrap_or(usize::MAX)) {
+        shown += 1;
+        let (off, size) = (entry.off as usize, entry.size as usize);
+        let in_bounds = entry.off.saturating_add(entry.size as u64) <= file_len && size >= 4;
+        let crc_ok = in_bounds && reader.verify_crc_at(off, size);
+        let crc = match (in_bounds, crc_ok) {
+            (false, _) => "OUT-OF-BOUNDS",
+            (true, false) => "BAD",
+            (true, true) => "ok",
+        };
+        if !crc_ok {
+            corrupt += 1;
+        }
+        let fingerprint = entry
+            .fingerprint
+            .map_or_else(|| "-".to_string(), |fp| format!("{fp:016x}"));
+        let value = match (values, reader.value_at(off, size).filter(|_| in_bounds)) {
+            (None, _) | (_, None) => String::new(),
+            (Some(ValueFormat::Hex), Some(v)) => format!(" value={}", hex::encode(v)),
+            (Some(ValueFormat::Len), Some(v)) => format!(" len={}", v.len()),
+            (Some(ValueFormat::Postings), Some(_)) if reader.kind != SegmentKind::Resolver => {
+                " postings=(not a resolver segment)".to_string()
+            }
+            (Some(ValueFormat::Postings), Some(_)) if !crc_ok => " postings=(corrupt)".to_string(),
+            (Some(ValueFormat::Postings), Some(v)) => {
+                format!(" postings={:?}", decode_sorted_u64(v))
+            }
+        };
+        println!(
+            "hash={:016x} fp={fingerprint} off={off} size={size} crc={crc}{value}",
+            entry.hash
+        );
+    }
+    println!("{shown} entries shown, {corrupt} corrupt");
+    Ok(())
+}
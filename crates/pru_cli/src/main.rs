@@ -1,17 +1,31 @@
 use anyhow::{anyhow, Context, Result};
 use clap::{Args, Parser, Subcommand, ValueEnum};
 use rand::Rng;
-use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 
+mod bench;
+mod detector;
+mod diff;
+mod import;
+mod inspect;
+mod media;
+mod output;
+
+use import::{ImportFormat, OnDuplicate};
+use inspect::ValueFormat;
+use output::{OutputArgs, OutputFormat};
 use pru_core::{
+    atom_id128, compact,
     consts::SegmentKind,
+    export::{self, ExportFormat},
     manifest::Manifest,
-    postings::{decode_sorted_u64, encode_sorted_u64, merge_sorted},
+    postings::encode_sorted_u64,
     resolver_store::{ResolveMode, ResolverStore},
-    segment::{SegmentReader, SegmentWriter},
-    Fact, PruStore, Query,
+    segment::SegmentReader,
+    AtomHash, AtomKind, Durability, Fact, FilterKind, GcOptions, IndexKind, IndexOptions,
+    IndexOutcome, IndexRec, KeyKind, PruStore, Query, QuerySort, ResolverKey, StoreConfig,
 };
+use pru_media_schema::MediaType;
 
 #[derive(Parser)]
 #[command(
@@ -29,14 +43,125 @@ enum CliResolveMode {
     Union,
     Dedup,
     Intersect,
+    /// Ids of the first key that none of the other keys have
+    Difference,
+}
+
+/// Index layouts the segment format supports.
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum CliIndexKind {
+    V1,
+    /// Adds a key fingerprint per entry
+    V2,
+}
+
+impl From<CliIndexKind> for IndexKind {
+    fn from(kind: CliIndexKind) -> Self {
+        match kind {
+            CliIndexKind::V1 => IndexKind::V1,
+            CliIndexKind::V2 => IndexKind::V2,
+        }
+    }
+}
+
+/// Formats `pru export` writes.
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum CliExportFormat {
+    Jsonl,
+    Csv,
+    Ntriples,
+}
+
+impl From<CliExportFormat> for ExportFormat {
+    fn from(format: CliExportFormat) -> Self {
+        match format {
+            CliExportFormat::Jsonl => ExportFormat::Jsonl,
+            CliExportFormat::Csv => ExportFormat::Csv,
+            CliExportFormat::Ntriples => ExportFormat::Ntriples,
+        }
+    }
+}
+
+/// Membership filters the segment format supports.
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum CliFilter {
+    Xor8,
+    Bloom,
+}
+
+impl From<CliFilter> for FilterKind {
+    fn from(filter: CliFilter) -> Self {
+        match filter {
+            CliFilter::Xor8 => FilterKind::Xor8,
+            CliFilter::Bloom => FilterKind::Bloom,
+        }
+    }
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum CliDurability {
+    None,
+    Flush,
+    Fsync,
+}
+
+impl From<CliDurability> for Durability {
+    fn from(durability: CliDurability) -> Self {
+        match durability {
+            CliDurability::None => Durability::None,
+            CliDurability::Flush => Durability::Flush,
+            CliDurability::Fsync => Durability::Fsync,
+        }
+    }
+}
+
+/// Which segments `compact` and `promote` work on.
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum SegmentKinds {
+    /// Posting lists are unioned per key
+    Resolver,
+    /// The latest segment's value wins per key
+    Dict,
+    /// Entries are concatenated, dropping exact repeats
+    Fact,
+    All,
+}
+
+impl SegmentKinds {
+    fn kinds(self) -> Vec<SegmentKind> {
+        match self {
+            SegmentKinds::Resolver => vec![SegmentKind::Resolver],
+            SegmentKinds::Dict => vec![SegmentKind::Dict],
+            SegmentKinds::Fact => vec![SegmentKind::Fact],
+            SegmentKinds::All => SegmentKind::ALL.to_vec(),
+        }
+    }
 }
 
 #[derive(Subcommand)]
 enum Cmd {
     /// Initialize a PRU-DB directory (creates manifest and tables)
+    ///
+    /// The options are recorded as store defaults; on an existing store only the
+    /// ones given are changed.
     Init {
         #[arg(long, value_name = "DIR", help = "Data directory to initialize")]
         dir: PathBuf,
+        /// Index layout of new segments [default: v2]
+        #[arg(long, value_enum)]
+        index_kind: Option<CliIndexKind>,
+        /// Filter of new segments [default: xor8]
+        #[arg(long, value_enum)]
+        filter: Option<CliFilter>,
+        /// How hard writes try to reach the disk [default: fsync]
+        #[arg(long, value_enum)]
+        durability: Option<CliDurability>,
+        /// Bloom filter size in bits [default: 1048576]
+        #[arg(long, value_name = "N")]
+        bloom_bits: Option<u32>,
+        /// Bloom filter hash count [default: 7]
+        #[arg(long, value_name = "K")]
+        bloom_k: Option<u32>,
     },
 
     /// Add a resolver segment from a hex key and id list
@@ -58,7 +183,7 @@ enum Cmd {
         /// Optional extra keys for intersect/union
         #[arg(long, value_name = "HEX", num_args = 0.., value_delimiter = ',')]
         and_key_hex: Vec<String>,
-        /// union (default), dedup, intersect
+        /// union (default), dedup, intersect, difference
         #[arg(long, value_enum, default_value_t = CliResolveMode::Union)]
         mode: CliResolveMode,
         /// Apply set-like intersection semantics after deduplication
@@ -70,24 +195,91 @@ enum Cmd {
     Verify {
         #[arg(long, value_name = "DIR")]
         dir: PathBuf,
+        #[arg(
+            long,
+            help = "Salvage the intact entries of damaged segments and archive the originals"
+        )]
+        fix: bool,
+        #[arg(
+            long,
+            requires = "fix",
+            help = "Apply the fix instead of only listing it"
+        )]
+        yes: bool,
     },
 
-    /// Compact resolver segments
+    /// Merge the active segments of a kind into one new segment
     Compact {
         #[arg(long, value_name = "DIR")]
         dir: PathBuf,
+        #[arg(long, value_enum, default_value_t = SegmentKinds::Resolver)]
+        kind: SegmentKinds,
     },
 
-    /// Promote a compacted resolver segment to active
+    /// Leave only the latest compacted segment of a kind active
     Promote {
         #[arg(long, value_name = "DIR")]
         dir: PathBuf,
+        #[arg(long, value_enum, default_value_t = SegmentKinds::Resolver)]
+        kind: SegmentKinds,
+    },
+
+    /// Build resolver keys from atom names
+    Key {
+        #[command(subcommand)]
+        cmd: KeyCmd,
+    },
+
+    /// Compare the atoms and facts of two stores by name; exits 1 when they differ
+    Diff {
+        #[arg(long, value_name = "DIR")]
+        dir: PathBuf,
+        #[arg(long, value_name = "DIR", help = "Store to compare against --dir")]
+        other: PathBuf,
+        #[arg(
+            long,
+            default_value_t = false,
+            help = "List the differing names and facts, not just their counts"
+        )]
+        list: bool,
+        #[command(flatten)]
+        output: OutputArgs,
+    },
+
+    /// Delete archived segments and leftover temp files
+    Gc {
+        #[arg(long, value_name = "DIR")]
+        dir: PathBuf,
+        #[arg(
+            long,
+            value_name = "N",
+            default_value_t = 0,
+            help = "Archived segments to keep, newest first"
+        )]
+        keep: usize,
+        #[arg(
+            long,
+            value_name = "SECS",
+            default_value_t = 3600,
+            help = "Only remove temp files at least this old"
+        )]
+        temp_age: u64,
+        #[arg(long, help = "List what would be removed without removing it")]
+        dry_run: bool,
+    },
+
+    /// Look inside a single segment file
+    Segment {
+        #[command(subcommand)]
+        cmd: SegmentCmd,
     },
 
     /// Inspect manifest and segments
     Info {
         #[arg(long, value_name = "DIR")]
         dir: PathBuf,
+        #[command(flatten)]
+        output: OutputArgs,
     },
 
     /// Entity dictionary operations
@@ -116,6 +308,54 @@ enum Cmd {
 
     /// Run an ad-hoc fact query
     Query(QueryCmd),
+
+    /// Write facts (optionally filtered) as JSONL, CSV or N-Triples
+    Export(ExportCmd),
+
+    /// Add facts from a JSONL (as written by export) or N-Triples file
+    Import(ImportCmd),
+
+    /// Build resolver segments from the fact log and check their freshness
+    Index {
+        #[command(subcommand)]
+        cmd: IndexCmd,
+    },
+
+    /// Analyze media with the built-in detectors and inspect the results
+    Media {
+        #[command(subcommand)]
+        cmd: MediaCmd,
+    },
+
+    /// Inspect detectors and their reliability against human verdicts
+    Detector {
+        #[command(subcommand)]
+        cmd: DetectorCmd,
+    },
+
+    /// Measure intern, add-fact and resolve throughput on synthetic data
+    Bench {
+        /// Where to create the scratch store [default: the system temp dir]
+        #[arg(long, value_name = "DIR")]
+        dir: Option<PathBuf>,
+        #[arg(long, value_name = "N", default_value_t = 100_000)]
+        facts: usize,
+        #[arg(long, value_name = "N", default_value_t = 10_000)]
+        entities: usize,
+        #[arg(
+            long,
+            value_name = "N",
+            default_value_t = 1_000,
+            help = "Facts per transaction"
+        )]
+        batch: usize,
+        #[arg(long, value_name = "N", help = "Build an index and time N resolves")]
+        resolve_keys: Option<usize>,
+        #[arg(long, help = "Leave the scratch store on disk")]
+        keep: bool,
+        #[command(flatten)]
+        output: OutputArgs,
+    },
 }
 
 #[derive(Subcommand)]
@@ -131,6 +371,26 @@ enum EntityCmd {
     List {
         #[arg(long, value_name = "DIR")]
         dir: PathBuf,
+        #[command(flatten)]
+        output: OutputArgs,
+    },
+    /// Rename an entity; facts follow the new name
+    Rename {
+        #[arg(long, value_name = "DIR")]
+        dir: PathBuf,
+        #[arg(long, value_name = "NAME")]
+        from: String,
+        #[arg(long, value_name = "NAME")]
+        to: String,
+    },
+    /// Remove an entity that no fact uses, or with --cascade the facts too
+    Delete {
+        #[arg(long, value_name = "DIR")]
+        dir: PathBuf,
+        #[arg(long, value_name = "NAME")]
+        name: String,
+        #[arg(long, help = "Also delete the facts that reference it")]
+        cascade: bool,
     },
 }
 
@@ -147,6 +407,26 @@ enum PredicateCmd {
     List {
         #[arg(long, value_name = "DIR")]
         dir: PathBuf,
+        #[command(flatten)]
+        output: OutputArgs,
+    },
+    /// Rename a predicate; facts follow the new name
+    Rename {
+        #[arg(long, value_name = "DIR")]
+        dir: PathBuf,
+        #[arg(long, value_name = "NAME")]
+        from: String,
+        #[arg(long, value_name = "NAME")]
+        to: String,
+    },
+    /// Remove a predicate that no fact uses, or with --cascade the facts too
+    Delete {
+        #[arg(long, value_name = "DIR")]
+        dir: PathBuf,
+        #[arg(long, value_name = "NAME")]
+        name: String,
+        #[arg(long, help = "Also delete the facts that reference it")]
+        cascade: bool,
     },
 }
 
@@ -163,6 +443,193 @@ enum LiteralCmd {
     List {
         #[arg(long, value_name = "DIR")]
         dir: PathBuf,
+        #[command(flatten)]
+        output: OutputArgs,
+    },
+    /// Rename a literal; facts follow the new name
+    Rename {
+        #[arg(long, value_name = "DIR")]
+        dir: PathBuf,
+        #[arg(long, value_name = "VALUE")]
+        from: String,
+        #[arg(long, value_name = "VALUE")]
+        to: String,
+    },
+    /// Remove a literal that no fact uses, or with --cascade the facts too
+    Delete {
+        #[arg(long, value_name = "DIR")]
+        dir: PathBuf,
+        #[arg(long, value_name = "VALUE", alias = "value")]
+        name: String,
+        #[arg(long, help = "Also delete the facts that reference it")]
+        cascade: bool,
+    },
+    /// Find literals by substring or regular expression
+    Search {
+        #[arg(long, value_name = "DIR")]
+        dir: PathBuf,
+        #[arg(long, value_name = "NEEDLE")]
+        text: String,
+        #[arg(long, help = "Treat --text as a regular expression")]
+        regex: bool,
+        #[arg(long, value_name = "N", help = "Stop after N hits")]
+        limit: Option<usize>,
+        #[arg(long, help = "Print values in full instead of truncating them")]
+        full: bool,
+        #[command(flatten)]
+        output: OutputArgs,
+    },
+}
+
+#[derive(Subcommand)]
+enum SegmentCmd {
+    /// Print header, index and filter metadata as JSON
+    Inspect { file: PathBuf },
+    /// List index entries with their checksum status
+    Dump {
+        file: PathBuf,
+        #[arg(long, value_name = "N", help = "Stop after N entries")]
+        limit: Option<usize>,
+        #[arg(long, value_enum, help = "Also print each value")]
+        values: Option<ValueFormat>,
+    },
+}
+
+#[derive(Subcommand)]
+enum KeyCmd {
+    /// Print the resolver key for one or two atoms as hex
+    Build {
+        #[arg(long, value_name = "KIND", help = "s, p, o, sp, po or so")]
+        kind: KeyKind,
+        #[arg(long, value_name = "NAME", help = "First atom, by name")]
+        a: Option<String>,
+        #[arg(long, value_name = "HEX", help = "First atom as a 16-byte atom id")]
+        a_hex: Option<String>,
+        #[arg(long, value_name = "NAME", help = "Second atom of a pair, by name")]
+        b: Option<String>,
+        #[arg(long, value_name = "HEX", help = "Second atom as a 16-byte atom id")]
+        b_hex: Option<String>,
+        #[arg(long, requires = "dir", help = "Also resolve the key against --dir")]
+        resolve: bool,
+        #[arg(long, value_name = "DIR")]
+        dir: Option<PathBuf>,
+    },
+}
+
+#[derive(Subcommand)]
+enum IndexCmd {
+    /// Index facts by subject, predicate, object or pairs of them
+    Build {
+        #[arg(long, value_name = "DIR")]
+        dir: PathBuf,
+        #[arg(
+            long,
+            value_delimiter = ',',
+            default_value = "s,p,o,sp,po,so",
+            value_name = "KINDS",
+            help = "Key kinds to index: s, p, o, sp, po, so"
+        )]
+        kinds: Vec<KeyKind>,
+        #[arg(long, help = "Replace indexes that already exist")]
+        rebuild: bool,
+        #[arg(
+            long,
+            help = "Make the index segments the only active resolver segments"
+        )]
+        promote: bool,
+    },
+    /// Show which kinds are indexed and whether facts changed since
+    Status {
+        #[arg(long, value_name = "DIR")]
+        dir: PathBuf,
+    },
+}
+
+#[derive(Subcommand)]
+enum MediaCmd {
+    /// Ingest a file (type sniffed from its content) and print its report
+    Analyze {
+        #[arg(long, value_name = "DIR")]
+        dir: PathBuf,
+        path: PathBuf,
+        #[arg(
+            long,
+            help = "Re-run detectors even if results for these bytes are stored"
+        )]
+        force: bool,
+        #[command(flatten)]
+        output: OutputArgs,
+    },
+    /// Print the truth engine's report for stored media
+    Report {
+        #[arg(long, value_name = "DIR")]
+        dir: PathBuf,
+        #[arg(value_name = "HASH|ID")]
+        media: String,
+        #[command(flatten)]
+        output: OutputArgs,
+    },
+    /// Record a human verdict for stored media
+    Label {
+        #[arg(long, value_name = "DIR")]
+        dir: PathBuf,
+        #[arg(value_name = "HASH|ID")]
+        media: String,
+        label: String,
+        #[arg(long, default_value = "anonymous", help = "Who is labeling")]
+        annotator: String,
+        #[arg(long, default_value_t = 1.0, help = "Annotator confidence in [0, 1]")]
+        confidence: f32,
+    },
+    /// List stored media
+    List {
+        #[arg(long, value_name = "DIR")]
+        dir: PathBuf,
+        #[arg(
+            long = "type",
+            value_name = "TYPE",
+            help = "image, text, audio or video"
+        )]
+        media_type: Option<MediaType>,
+        #[command(flatten)]
+        output: OutputArgs,
+    },
+}
+
+#[derive(Subcommand)]
+enum DetectorCmd {
+    /// List detector entities with their registered metadata
+    List {
+        #[arg(long, value_name = "DIR")]
+        dir: PathBuf,
+        #[command(flatten)]
+        output: OutputArgs,
+    },
+    /// Print a detector's seen/correct counts and confusion matrix
+    Reliability {
+        #[arg(long, value_name = "DIR")]
+        dir: PathBuf,
+        #[arg(
+            long,
+            value_name = "NAME",
+            help = "Detector name, detector: prefix optional"
+        )]
+        id: String,
+        #[command(flatten)]
+        output: OutputArgs,
+    },
+    /// Start a detector's reliability over from zero
+    ReliabilityReset {
+        #[arg(long, value_name = "DIR")]
+        dir: PathBuf,
+        #[arg(
+            long,
+            value_name = "NAME",
+            help = "Detector name, detector: prefix optional"
+        )]
+        id: String,
+        #[arg(long, help = "Do not ask for confirmation")]
+        yes: bool,
     },
 }
 
@@ -170,10 +637,27 @@ enum LiteralCmd {
 enum FactCmd {
     /// Append a fact with optional metadata
     Add(FactAddCmd),
+    /// Add facts from subject,predicate,object[,confidence[,timestamp]] CSV rows
+    AddBulk(FactAddBulkCmd),
     /// List facts for a subject (optionally filtered by predicate)
     List(FactListCmd),
     /// Run a query with optional filters
     Query(QueryCmd),
+    /// Remove the facts matching a pattern
+    Retract(FactRetractCmd),
+    /// Print facts as they are added, until interrupted
+    Tail(FactTailCmd),
+}
+
+#[derive(Args)]
+struct FactAddBulkCmd {
+    #[arg(long, value_name = "DIR")]
+    dir: PathBuf,
+    /// CSV file to read; stdin when omitted or `-`
+    #[arg(long, value_name = "FILE")]
+    file: Option<PathBuf>,
+    #[arg(long, default_value_t = ',')]
+    delimiter: char,
 }
 
 #[derive(Args)]
@@ -220,6 +704,66 @@ struct FactListCmd {
     predicate: Option<String>,
     #[arg(long, default_value_t = false)]
     pretty: bool,
+    #[command(flatten)]
+    output: OutputArgs,
+}
+
+#[derive(Args)]
+struct FactRetractCmd {
+    #[arg(long, value_name = "DIR")]
+    dir: PathBuf,
+    #[arg(long, value_name = "ID", help = "Subject id")]
+    subject_id: Option<u64>,
+    #[arg(long, value_name = "NAME", help = "Subject name (entity)")]
+    subject: Option<String>,
+    #[arg(long, value_name = "ID", help = "Predicate id")]
+    predicate_id: Option<u64>,
+    #[arg(long, value_name = "NAME", help = "Predicate name")]
+    predicate: Option<String>,
+    #[arg(long, value_name = "ID", help = "Object id (entity or literal)")]
+    object_id: Option<u64>,
+    #[arg(long, value_name = "VALUE", help = "Object literal or entity name")]
+    object: Option<String>,
+    #[arg(long, value_name = "ID")]
+    source_id: Option<u64>,
+    #[arg(long)]
+    timestamp: Option<i64>,
+    #[arg(long, help = "List the matching facts without removing them")]
+    dry_run: bool,
+    #[arg(
+        long,
+        value_name = "N",
+        default_value_t = 10,
+        help = "Refuse to remove more than N facts without --force"
+    )]
+    max: usize,
+    #[arg(long, help = "Remove however many facts match")]
+    force: bool,
+    #[arg(
+        long,
+        default_value_t = false,
+        help = "Render facts in a human-readable form"
+    )]
+    pretty: bool,
+}
+
+#[derive(Args)]
+struct FactTailCmd {
+    #[arg(long, value_name = "DIR")]
+    dir: PathBuf,
+    #[arg(long, value_name = "NAME", help = "Only facts with this predicate")]
+    predicate: Option<String>,
+    #[arg(
+        long,
+        value_name = "MS",
+        default_value_t = 500,
+        help = "How often to check the fact log"
+    )]
+    interval_ms: u64,
+    #[arg(long, value_name = "N", help = "Exit after printing N facts")]
+    limit: Option<usize>,
+    #[command(flatten)]
+    output: OutputArgs,
 }
 
 #[derive(Args, Clone)]
@@ -240,8 +784,86 @@ struct QueryCmd {
     object: Option<String>,
     #[arg(long, value_name = "FLOAT")]
     min_confidence: Option<f32>,
+    #[arg(long, value_name = "ID")]
+    source_id: Option<u64>,
+    #[arg(long, value_name = "NAME", help = "Source name (entity)")]
+    source: Option<String>,
+    #[arg(long, value_name = "TIME", value_parser = parse_time, help = "Earliest timestamp, unix seconds or RFC 3339")]
+    since: Option<i64>,
+    #[arg(long, value_name = "TIME", value_parser = parse_time, help = "Latest timestamp, unix seconds or RFC 3339")]
+    until: Option<i64>,
+    #[arg(long, value_enum)]
+    sort: Option<SortBy>,
+    #[arg(long, default_value_t = false, requires = "sort")]
+    desc: bool,
+    #[arg(long, default_value_t = 0)]
+    offset: usize,
+    #[arg(long)]
+    limit: Option<usize>,
     #[arg(long, default_value_t = false)]
     pretty: bool,
+    #[command(flatten)]
+    output: OutputArgs,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum SortBy {
+    Timestamp,
+    Confidence,
+}
+
+impl From<SortBy> for QuerySort {
+    fn from(sort: SortBy) -> Self {
+        match sort {
+            SortBy::Timestamp => QuerySort::Timestamp,
+            SortBy::Confidence => QuerySort::Confidence,
+        }
+    }
+}
+
+#[derive(Args)]
+struct ExportCmd {
+    #[arg(long, value_name = "DIR")]
+    dir: PathBuf,
+    #[arg(long, value_enum, default_value_t = CliExportFormat::Jsonl)]
+    format: CliExportFormat,
+    #[arg(long, value_name = "ID")]
+    subject_id: Option<u64>,
+    #[arg(long, value_name = "NAME")]
+    subject: Option<String>,
+    #[arg(long, value_name = "ID")]
+    predicate_id: Option<u64>,
+    #[arg(long, value_name = "NAME")]
+    predicate: Option<String>,
+    #[arg(long, value_name = "ID")]
+    object_id: Option<u64>,
+    #[arg(long, value_name = "VALUE")]
+    object: Option<String>,
+    #[arg(long, value_name = "FLOAT")]
+    min_confidence: Option<f32>,
+    #[arg(long, help = "Write atom ids instead of names")]
+    ids: bool,
+    #[arg(long, value_name = "FILE", help = "Write to FILE instead of stdout")]
+    out: Option<PathBuf>,
+}
+
+#[derive(Args)]
+struct ImportCmd {
+    #[arg(long, value_name = "DIR")]
+    dir: PathBuf,
+    #[arg(long, value_name = "FILE")]
+    file: PathBuf,
+    #[arg(long, value_enum, default_value_t = ImportFormat::Jsonl)]
+    format: ImportFormat,
+    #[arg(long, help = "Parse and validate everything without writing")]
+    dry_run: bool,
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = OnDuplicate::Skip,
+        help = "What to do with facts already in the store"
+    )]
+    on_duplicate: OnDuplicate,
 }
 
 fn ensure_dir(p: &Path) -> Result<()> {
@@ -253,6 +875,16 @@ fn now_ts() -> i64 {
     time::OffsetDateTime::now_utc().unix_timestamp()
 }
 
+/// Unix seconds, or an RFC 3339 date-time such as `2024-05-01T12:00:00Z`.
+fn parse_time(s: &str) -> Result<i64, String> {
+    if let Ok(secs) = s.parse::<i64>() {
+        return Ok(secs);
+    }
+    time::OffsetDateTime::parse(s, &time::format_description::well_known::Rfc3339)
+        .map(|t| t.unix_timestamp())
+        .map_err(|_| format!("expected unix seconds or an RFC 3339 time, got {s:?}"))
+}
+
 fn now_id() -> String {
     let now = time::OffsetDateTime::now_utc();
     let secs = now.unix_timestamp();
@@ -267,6 +899,14 @@ fn open_store(dir: &Path) -> Result<PruStore> {
     PruStore::open(dir).with_context(|| format!("failed to open store at {}", dir.display()))
 }
 
+/// Open a store that must already exist, rather than starting an empty one.
+fn open_existing(dir: &Path) -> Result<PruStore> {
+    if !dir.is_dir() {
+        return Err(anyhow!("no store at {}", dir.display()));
+    }
+    open_store(dir)
+}
+
 fn render_atom(store: &PruStore, id: u64) -> String {
     if let Some(name) = store.get_entity_name(id) {
         return format!("{name} [entity #{id}]");
@@ -360,6 +1000,19 @@ fn resolve_object(store: &PruStore, id: Option<u64>, name: Option<String>) -> Re
     }
 }
 
+/// A filter given by id or name, or `None` when neither was passed.
+fn optional(
+    store: &PruStore,
+    id: Option<u64>,
+    name: Option<String>,
+    resolve: fn(&PruStore, Option<u64>, Option<String>) -> Result<u64>,
+) -> Result<Option<u64>> {
+    match (id, name) {
+        (None, None) => Ok(None),
+        (id, name) => resolve(store, id, name).map(Some),
+    }
+}
+
 fn handle_fact_list(store: &PruStore, args: FactListCmd) -> Result<()> {
     let subject = resolve_entity(store, args.subject_id, args.subject)?;
     let facts = if let Some(pred) = args.predicate_id {
@@ -371,6 +1024,9 @@ fn handle_fact_list(store: &PruStore, args: FactListCmd) -> Result<()> {
         store.facts_for_subject(subject)?
     };
 
+    if !args.output.is_text() {
+        return output::emit_facts(store, args.output.format, &facts);
+    }
     if facts.is_empty() {
         println!("no facts found");
         return Ok(());
@@ -382,17 +1038,20 @@ fn handle_fact_list(store: &PruStore, args: FactListCmd) -> Result<()> {
 }
 
 fn handle_query(store: &PruStore, args: QueryCmd) -> Result<()> {
-    let subject = match (args.subject_id, args.subject) {
-        (None, None) => None,
-        (id, name) => Some(resolve_entity(store, id, name)?),
-    };
-    let predicate = match (args.predicate_id, args.predicate) {
-        (None, None) => None,
-        (id, name) => Some(resolve_predicate(store, id, name)?),
-    };
-    let object = match (args.object_id, args.object) {
+    let subject = optional(store, args.subject_id, args.subject, resolve_entity)?;
+    let predicate = optional(store, args.predicate_id, args.predicate, resolve_predicate)?;
+    let object = optional(store, args.object_id, args.object, resolve_object)?;
+    let source = match (args.source_id, args.source) {
+        (Some(_), Some(_)) => {
+            return Err(anyhow!("Specify either --source-id or --source, not both"));
+        }
+        (Some(id), None) => Some(id),
+        (None, Some(name)) => Some(
+            store
+                .get_entity_id(&name)
+                .ok_or_else(|| anyhow!("Entity not found for name: {name}"))?,
+        ),
         (None, None) => None,
-        (id, name) => Some(resolve_object(store, id, name)?),
     };
 
     let query = Query {
@@ -400,8 +1059,19 @@ fn handle_query(store: &PruStore, args: QueryCmd) -> Result<()> {
         predicate,
         object,
         min_confidence: args.min_confidence,
+        source,
+        since: args.since,
+        until: args.until,
+        sort: args.sort.map(QuerySort::from),
+        descending: args.desc,
+        offset: args.offset,
+        limit: args.limit,
+        ..Default::default()
     };
     let res = store.query(query)?;
+    if !args.output.is_text() {
+        return output::emit_facts(store, args.output.format, &res);
+    }
     if res.is_empty() {
         println!("no facts matched query");
     }
@@ -411,14 +1081,650 @@ fn handle_query(store: &PruStore, args: QueryCmd) -> Result<()> {
     Ok(())
 }
 
+fn handle_fact_retract(store: &mut PruStore, args: FactRetractCmd) -> Result<()> {
+    let subject = optional(store, args.subject_id, args.subject, resolve_entity)?;
+    let predicate = optional(store, args.predicate_id, args.predicate, resolve_predicate)?;
+    let object = optional(store, args.object_id, args.object, resolve_object)?;
+    if subject.is_none() && predicate.is_none() && object.is_none() {
+        return Err(anyhow!(
+            "Give at least a subject, predicate or object to retract"
+        ));
+    }
+    let matches = |f: &Fact| {
+        subject.is_none_or(|s| f.subject == s)
+            && predicate.is_none_or(|p| f.predicate == p)
+            && object.is_none_or(|o| f.object == o)
+            && args.source_id.is_none_or(|s| f.source == Some(s))
+            && args.timestamp.is_none_or(|t| f.timestamp == Some(t))
+    };
+
+    let query = Query {
+        subject,
+        predicate,
+        object,
+        ..Default::default()
+    };
+    let found: Vec<Fact> = store.query(query)?.into_iter().filter(matches).collect();
+    if found.is_empty() {
+        println!("no facts matched");
+        return Ok(());
+    }
+    if args.dry_run {
+        for f in &found {
+            print_fact(store, f, args.pretty);
+        }
+        println!("{} fact(s) would be retracted", found.len());
+        return Ok(());
+    }
+    if found.len() > args.max && !args.force {
+        return Err(anyhow!(
+            "{} facts match, more than --max {}; use --dry-run to see them or --force to retract them all",
+            found.len(),
+            args.max
+        ));
+    }
+    let removed = store.retract_facts(matches)?;
+    for f in &removed {
+        print_fact(store, f, args.pretty);
+    }
+    println!("{} fact(s) retracted", removed.len());
+    Ok(())
+}
+
+/// Size and modification time of the fact log, to notice writes without
+/// reloading it.
+fn fact_log_stamp(path: &Path) -> Option<(u64, std::time::SystemTime)> {
+    let meta = std::fs::metadata(path).ok()?;
+    Some((meta.len(), meta.modified().ok()?))
+}
+
+/// Poll the fact log, which works whichever process writes to it. Facts are
+/// only ever appended except by retract, rewrite and compaction; when the log
+/// no longer starts with what was already seen, tail re-syncs to its end and
+/// says that changes may have been missed.
+fn handle_fact_tail(args: FactTailCmd) -> Result<()> {
+    let store = open_store(&args.dir)?;
+    let path = store.fact_log_path();
+    let mut seen = store.fact_count();
+    let mut last = store.query_iter(Query::default()).last().cloned();
+    let mut stamp = fact_log_stamp(&path);
+    let mut printed = 0;
+    eprintln!("tailing {} from fact {seen}", args.dir.display());
+    while args.limit.is_none_or(|n| printed < n) {
+        std::thread::sleep(std::time::Duration::from_millis(args.interval_ms));
+        let now = fact_log_stamp(&path);
+        if now == stamp {
+            continue;
+        }
+        // A half-written or vanished log is retried on the next tick.
+        let Ok(store) = PruStore::open(&args.dir) else {
+            continue;
+        };
+        stamp = now;
+        let facts = store.query(Query::default())?;
+        let continues = seen <= facts.len() && (seen == 0 || facts.get(seen - 1) == last.as_ref());
+        if !continues {
+            eprintln!(
+                "fact log was rewritten ({seen} facts before, {} now); resuming at its end, changes may have been missed",
+                facts.len()
+            );
+            seen = facts.len();
+        }
+        for fact in &facts[seen..] {
+            let wanted = args
+                .predicate
+                .as_ref()
+                .is_none_or(|p| store.get_predicate_name(fact.predicate).as_ref() == Some(p));
+            if !wanted || args.limit.is_some_and(|n| printed >= n) {
+                continue;
+            }
+            if args.output.is_text() {
+                print_fact(&store, fact, true);
+            } else {
+                println!(
+                    "{}",
+                    serde_json::to_string(&output::FactJson::new(&store, fact))?
+                );
+            }
+            printed += 1;
+        }
+        std::io::Write::flush(&mut std::io::stdout())?;
+        seen = facts.len();
+        last = facts.last().cloned();
+    }
+    Ok(())
+}
+
+fn handle_export(store: &PruStore, args: ExportCmd) -> Result<()> {
+    let query = Query {
+        subject: optional(store, args.subject_id, args.subject, resolve_entity)?,
+        predicate: optional(store, args.predicate_id, args.predicate, resolve_predicate)?,
+        object: optional(store, args.object_id, args.object, resolve_object)?,
+        min_confidence: args.min_confidence,
+        ..Default::default()
+    };
+    let facts = store.query_iter(query);
+    match &args.out {
+        Some(path) => {
+            let file = std::fs::File::create(path)
+                .with_context(|| format!("failed to create {}", path.display()))?;
+            let out = std::io::BufWriter::new(file);
+            let count = export::write_facts(store, facts, args.format.into(), args.ids, out)?;
+            println!("exported {count} fact(s) to {}", path.display());
+        }
+        None => {
+            let out = std::io::BufWriter::new(std::io::stdout().lock());
+            export::write_facts(store, facts, args.format.into(), args.ids, out)?;
+        }
+    }
+    Ok(())
+}
+
+fn handle_import(store: &mut PruStore, args: ImportCmd) -> Result<()> {
+    let file = std::fs::File::open(&args.file)
+        .with_context(|| format!("failed to open {}", args.file.display()))?;
+    let summary = import::import(
+        store,
+        std::io::BufReader::new(file),
+        args.format,
+        args.on_duplicate,
+        args.dry_run,
+    )?;
+    print_import_summary(&summary, args.dry_run);
+    Ok(())
+}
+
+fn handle_fact_add_bulk(store: &mut PruStore, args: FactAddBulkCmd) -> Result<()> {
+    if !args.delimiter.is_ascii() {
+        return Err(anyhow!("--delimiter must be a single ASCII character"));
+    }
+    let delimiter = args.delimiter as u8;
+    let summary = match args.file.as_deref() {
+        Some(path) if path != Path::new("-") => {
+            let file = std::fs::File::open(path)
+                .with_context(|| format!("failed to open {}", path.display()))?;
+            import::add_csv(store, std::io::BufReader::new(file), delimiter, now_ts())?
+        }
+        _ => import::add_csv(store, std::io::stdin().lock(), delimiter, now_ts())?,
+    };
+    print_import_summary(&summary, false);
+    Ok(())
+}
+
+fn print_import_summary(summary: &import::ImportSummary, dry_run: bool) {
+    let verb = if dry_run { "would be " } else { "" };
+    println!(
+        "atoms {verb}created: {} ({} entities, {} predicates, {} literals)",
+        summary.entities_created + summary.predicates_created + summary.literals_created,
+        summary.entities_created,
+        summary.predicates_created,
+        summary.literals_created
+    );
+    println!("facts {verb}added: {}", summary.facts_added);
+    println!("duplicates skipped: {}", summary.duplicates_skipped);
+    println!("lines rejected: {}", summary.rejected.len());
+    for (line, reason) in &summary.rejected {
+        println!("  line {line}: {reason}");
+    }
+}
+
+/// `verify --fix`: copy the intact entries of active segments with corrupt
+/// entries into repaired segments, and archive the originals along with any
+/// segment whose header cannot be read. Without `yes` only the plan is printed.
+fn handle_verify_fix(dir: &Path, yes: bool) -> Result<()> {
+    let mut man = Manifest::load(dir)?;
+    let active = man.active_segment_paths();
+    let mut unreadable = vec![];
+    let mut damaged = vec![];
+    for s in man.segments.iter().filter(|s| active.contains(&s.path)) {
+        let name = s.path.to_string_lossy().to_string();
+        let Ok(r) = SegmentReader::open(dir.join(&s.path)) else {
+            unreadable.push(name);
+            continue;
+        };
+        let bad = r.iter().filter(|e| !r.entry_ok(e)).count();
+        if bad > 0 {
+            damaged.push((name, r.iter().count() - bad, bad));
+        }
+    }
+    if damaged.is_empty() && unreadable.is_empty() {
+        println!("fix: nothing to repair");
+        return Ok(());
+    }
+    if !yes {
+        for (name, good, bad) in &damaged {
+            println!("fix: would salvage {good} entries of {name} and drop {bad}");
+        }
+        for name in &unreadable {
+            println!("fix: would archive {name} (unreadable header)");
+        }
+        println!("fix: nothing was changed; rerun with --fix --yes to apply");
+        return Ok(());
+    }
+
+    let (mut salvaged, mut dropped, mut archived) = (0, 0, 0);
+    for (name, good, bad) in damaged {
+        // Archive before adding, so an implicit all-active manifest keeps the
+        // other segments active.
+        man.archive_segment(&name);
+        man.indexes
+            .retain(|r| r.path.as_deref() != Some(name.as_str()));
+        archived += 1;
+        if good == 0 {
+            println!("fix: {name}: no intact entries, archived");
+            dropped += bad;
+            continue;
+        }
+        let r = SegmentReader::open(dir.join(&name))?;
+        let stem = name.strip_suffix(".prus").unwrap_or(&name);
+        let repaired = format!("{stem}-repaired-{}.prus", now_id());
+        let (kept, lost) = r.salvage_to(dir.join(&repaired))?;
+        man.add_segment(dir, &repaired, r.kind)?;
+        println!("fix: {name}: {kept} entries salvaged, {lost} dropped -> {repaired}");
+        salvaged += kept;
+        dropped += lost;
+    }
+    for name in unreadable {
+        println!("fix: {name}: unreadable header, archived");
+        man.archive_segment(&name);
+        man.indexes
+            .retain(|r| r.path.as_deref() != Some(name.as_str()));
+        archived += 1;
+    }
+    man.save_atomic(dir)?;
+    println!("fix: {salvaged} entries salvaged, {dropped} dropped, {archived} segments archived");
+    Ok(())
+}
+
+/// A key atom from `--{flag}` (hashed with atom_id128) or `--{flag}-hex`.
+fn key_atom(flag: &str, name: Option<String>, hex: Option<String>) -> Result<AtomHash> {
+    match (name, hex) {
+        (Some(name), None) => Ok(atom_id128(name.as_bytes())),
+        (None, Some(h)) => {
+            let bytes = hex::decode(&h)?;
+            AtomHash::try_from(bytes.as_slice()).map_err(|_| {
+                anyhow!(
+                    "--{flag}-hex must be 16 bytes (32 hex digits), got {}",
+                    bytes.len()
+                )
+            })
+        }
+        (Some(_), Some(_)) => Err(anyhow!("Specify either --{flag} or --{flag}-hex, not both")),
+        (None, None) => Err(anyhow!("--{flag} or --{flag}-hex is required")),
+    }
+}
+
+fn handle_atom_rename(store: &mut PruStore, kind: AtomKind, from: &str, to: &str) -> Result<()> {
+    let id = store.rename_atom(kind, from, to)?;
+    let facts = store.fact_references(id);
+    println!("{kind} renamed: {from} -> {to} (#{id}, {facts} facts affected)");
+    Ok(())
+}
+
+fn handle_atom_delete(
+    store: &mut PruStore,
+    kind: AtomKind,
+    name: &str,
+    cascade: bool,
+) -> Result<()> {
+    if !cascade {
+        let id = match kind {
+            AtomKind::Entity => store.get_entity_id(name),
+            AtomKind::Predicate => store.get_predicate_id(name),
+            AtomKind::Literal => store.get_literal_id(name),
+        };
+        let facts = id.map_or(0, |id| store.fact_references(id));
+        if facts > 0 {
+            return Err(anyhow!(
+                "refusing to delete {kind} {name}: {facts} facts reference it (use --cascade to delete them too)"
+            ));
+        }
+    }
+    let (id, facts) = store.delete_atom(kind, name, cascade)?;
+    println!("{kind} deleted: {name} (#{id}, {facts} facts removed)");
+    Ok(())
+}
+
+/// One hit of `literal search`.
+#[derive(serde::Serialize)]
+struct LiteralHit {
+    id: u64,
+    value: String,
+    facts: usize,
+}
+
+/// Values longer than this are cut short in text output unless `--full` is given.
+const LITERAL_PREVIEW_CHARS: usize = 80;
+
+fn handle_literal_search(
+    dir: &Path,
+    text: &str,
+    regex: bool,
+    limit: Option<usize>,
+    full: bool,
+    output: OutputArgs,
+) -> Result<()> {
+    let pattern = regex
+        .then(|| regex::Regex::new(text))
+        .transpose()
+        .map_err(|e| anyhow!("invalid --text regular expression: {e}"))?;
+    let store = open_existing(dir)?;
+    let mut found = match &pattern {
+        Some(pattern) => store.literals_where(|value| pattern.is_match(value)),
+        None => store.search_literals(text),
+    };
+    found.truncate(limit.unwrap_or(usize::MAX));
+    let ids: Vec<u64> = found.iter().map(|(id, _)| *id).collect();
+    let counts = store.fact_reference_counts(&ids);
+    let hits: Vec<LiteralHit> = found
+        .into_iter()
+        .map(|(id, value)| LiteralHit {
+            id,
+            value,
+            facts: counts[&id],
+        })
+        .collect();
+    if !output.is_text() {
+        return output::emit(output.format, &hits);
+    }
+    if hits.is_empty() {
+        println!("no literals found");
+    }
+    for hit in &hits {
+        let value = if full || hit.value.chars().count() <= LITERAL_PREVIEW_CHARS {
+            hit.value.clone()
+        } else {
+            let head: String = hit.value.chars().take(LITERAL_PREVIEW_CHARS).collect();
+            format!("{head}…")
+        };
+        println!("#{}\t{value}\t{} facts", hit.id, hit.facts);
+    }
+    Ok(())
+}
+
+/// Compact each kind in turn, saving the manifest after each. With `all`,
+/// kinds without active segments are skipped.
+fn handle_compact(dir: &Path, kinds: SegmentKinds) -> Result<()> {
+    let mut man = Manifest::load(dir)?;
+    let mut compacted = 0;
+    for kind in kinds.kinds() {
+        let active = man.active_segment_paths();
+        let has_segments = man
+            .segments
+            .iter()
+            .any(|s| s.kind == kind && active.contains(&s.path));
+        if matches!(kinds, SegmentKinds::All) && !has_segments {
+            println!("compact {kind}: no segments");
+            continue;
+        }
+        let report = compact::compact(dir, &mut man, kind)?;
+        man.save_atomic(dir)?;
+        compacted += 1;
+        println!(
+            "compact {kind}: {} segments ({} bytes) -> {} ({} bytes), entries={}",
+            report.inputs, report.input_bytes, report.output, report.output_bytes, report.entries
+        );
+    }
+    if compacted == 0 {
+        return Err(anyhow!("no segments to compact"));
+    }
+    Ok(())
+}
+
+fn index_summary(rec: &IndexRec) -> String {
+    let segment = rec.path.as_deref().unwrap_or("no segment");
+    format!(
+        "{} keys, {} postings from {} facts ({segment})",
+        rec.keys, rec.postings, rec.facts
+    )
+}
+
+fn handle_index_build(
+    store: &mut PruStore,
+    kinds: &[KeyKind],
+    options: IndexOptions,
+) -> Result<()> {
+    for outcome in store.build_index(kinds, options)? {
+        match outcome {
+            IndexOutcome::Built(rec) => println!("{:<3} built: {}", rec.kind, index_summary(&rec)),
+            IndexOutcome::Skipped(status) => println!(
+                "{:<3} already indexed{}; use --rebuild to replace it",
+                status.kind,
+                if status.stale { " (stale)" } else { "" }
+            ),
+        }
+    }
+    if options.promote {
+        println!("promoted index segments");
+    }
+    Ok(())
+}
+
+fn handle_index_status(store: &PruStore) -> Result<()> {
+    for status in store.index_status()? {
+        let Some(rec) = &status.index else {
+            println!("{:<3} not indexed", status.kind);
+            continue;
+        };
+        let freshness = if status.stale {
+            format!("stale, fact log now has {} facts", status.facts_now)
+        } else {
+            "up to date".to_string()
+        };
+        println!("{:<3} {}: {freshness}", status.kind, index_summary(rec));
+    }
+    Ok(())
+}
+
+fn handle_gc(dir: &Path, options: GcOptions) -> Result<()> {
+    let mut man = Manifest::load(dir).with_context(|| {
+        format!(
+            "failed to load the manifest in {}; nothing was removed",
+            dir.display()
+        )
+    })?;
+    let report = man.gc(dir, options)?;
+    let verb = if options.dry_run {
+        "would be removed"
+    } else {
+        "removed"
+    };
+    println!(
+        "archived segments {verb}: {} ({} bytes)",
+        report.archived.len(),
+        report.archived_bytes()
+    );
+    for (name, size) in &report.archived {
+        println!("  {name} ({size} bytes)");
+    }
+    println!(
+        "temp files {verb}: {} ({} bytes)",
+        report.temp_files.len(),
+        report.temp_bytes()
+    );
+    for (name, size) in &report.temp_files {
+        println!("  {name} ({size} bytes)");
+    }
+    Ok(())
+}
+
+#[derive(serde::Serialize)]
+struct SegmentInfo {
+    path: PathBuf,
+    kind: String,
+    active: bool,
+    entries: Option<usize>,
+    capacity: Option<u64>,
+    load_factor: Option<f64>,
+    index_kind: Option<u32>,
+}
+
+#[derive(serde::Serialize)]
+struct StoreInfo {
+    config: StoreConfig,
+    segments: usize,
+    active: usize,
+    items: Vec<SegmentInfo>,
+}
+
+fn print_config(config: &StoreConfig) {
+    println!("index    : {}", config.index_kind);
+    println!("filter   : {}", config.filter);
+    if config.filter == FilterKind::Bloom {
+        println!(
+            "bloom    : {} bits, k={}",
+            config.bloom_bits, config.bloom_k
+        );
+    }
+    println!("durability: {}", config.durability);
+}
+
+fn handle_info(dir: &Path, output: OutputArgs) -> Result<()> {
+    let man = Manifest::load(dir)?;
+    let act = man.active_segment_paths();
+    let mut items = Vec::new();
+    for s in &man.segments {
+        let mut info = SegmentInfo {
+            path: s.path.clone(),
+            kind: format!("{:?}", s.kind),
+            active: act.iter().any(|p| *p == s.path),
+            entries: None,
+            capacity: None,
+            load_factor: None,
+            index_kind: None,
+        };
+        if let Ok(r) = SegmentReader::open(dir.join(&s.path)) {
+            if let Some((k, cap)) = r.index_meta() {
+                let filled = r.iter().count();
+                info.entries = Some(filled);
+                info.capacity = Some(cap);
+                info.load_factor = Some(if cap > 0 {
+                    (filled as f64) / (cap as f64)
+                } else {
+                    0.0
+                });
+                info.index_kind = Some(k);
+            }
+        }
+        items.push(info);
+    }
+    if !output.is_text() {
+        let info = StoreInfo {
+            config: man.config,
+            segments: man.segments.len(),
+            active: act.len(),
+            items,
+        };
+        return output::emit_one(output.format, &info);
+    }
+
+    print_config(&man.config);
+    println!("segments: {}", man.segments.len());
+    println!("active   : {}", act.len());
+    for s in &items {
+        let mark = if s.active { '*' } else { ' ' };
+        let extra = match (s.entries, s.capacity, s.load_factor, s.index_kind) {
+            (Some(filled), Some(cap), Some(lf), Some(k)) => format!(
+                "  [ entries={} cap={} load≈{:.2} kind={}]",
+                filled, cap, lf, k
+            ),
+            _ => String::new(),
+        };
+        println!("{} {} {}{}", mark, s.kind, s.path.display(), extra);
+    }
+    Ok(())
+}
+
+impl Cmd {
+    /// The `--format` the command was given, for reporting errors the same way.
+    fn output_format(&self) -> OutputFormat {
+        match self {
+            Cmd::Info { output, .. }
+            | Cmd::Bench { output, .. }
+            | Cmd::Diff { output, .. }
+            | Cmd::Entity {
+                cmd: EntityCmd::List { output, .. },
+            }
+            | Cmd::Predicate {
+                cmd: PredicateCmd::List { output, .. },
+            }
+            | Cmd::Literal {
+                cmd: LiteralCmd::List { output, .. } | LiteralCmd::Search { output, .. },
+            }
+            | Cmd::Media {
+                cmd:
+                    MediaCmd::Analyze { output, .. }
+                    | MediaCmd::Report { output, .. }
+                    | MediaCmd::List { output, .. },
+            }
+            | Cmd::Detector {
+                cmd: DetectorCmd::List { output, .. } | DetectorCmd::Reliability { output, .. },
+            } => output.format,
+            Cmd::Fact {
+                cmd: FactCmd::List(args),
+            } => args.output.format,
+            Cmd::Fact {
+                cmd: FactCmd::Tail(args),
+            } => args.output.format,
+            Cmd::Fact {
+                cmd: FactCmd::Query(args),
+            }
+            | Cmd::Query(args) => args.output.format,
+            _ => OutputFormat::Text,
+        }
+    }
+}
+
 fn main() -> Result<()> {
     let cli = Cli::parse();
+    let format = cli.cmd.output_format();
+    match run(cli) {
+        Err(err) if format != OutputFormat::Text => {
+            let error = serde_json::json!({ "error": format!("{err:#}") });
+            eprintln!("{error}");
+            std::process::exit(1);
+        }
+        result => result,
+    }
+}
+
+fn run(cli: Cli) -> Result<()> {
     match cli.cmd {
-        Cmd::Init { dir } => {
+        Cmd::Init {
+            dir,
+            index_kind,
+            filter,
+            durability,
+            bloom_bits,
+            bloom_k,
+        } => {
             ensure_dir(&dir)?;
-            let m = Manifest::load(&dir)?;
+            let mut m = Manifest::load(&dir)?;
+            let config = &mut m.config;
+            if let Some(kind) = index_kind {
+                config.index_kind = kind.into();
+            }
+            if let Some(filter) = filter {
+                config.filter = filter.into();
+            }
+            if let Some(durability) = durability {
+                config.durability = durability.into();
+            }
+            if let Some(bits) = bloom_bits {
+                if bits == 0 {
+                    return Err(anyhow!("--bloom-bits must be positive"));
+                }
+                config.bloom_bits = bits;
+            }
+            if let Some(k) = bloom_k {
+                if k == 0 {
+                    return Err(anyhow!("--bloom-k must be positive"));
+                }
+                config.bloom_k = k;
+            }
             m.save_atomic(&dir)?;
             println!("init: {}", dir.display());
+            print_config(&m.config);
         }
         Cmd::AddResolver { dir, key_hex, ids } => {
             ensure_dir(&dir)?;
@@ -426,14 +1732,16 @@ fn main() -> Result<()> {
             let seg_name = format!("resolver-{}.prus", now_ts());
             let seg_path = dir.join(&seg_name);
 
-            let mut w = SegmentWriter::create(&seg_path, SegmentKind::Resolver, 1 << 20, 7)?;
+            let mut man = Manifest::load(&dir)?;
+            let mut w = man
+                .config
+                .segment_writer(&seg_path, SegmentKind::Resolver)?;
             let mut lst = ids;
             lst.sort_unstable();
             lst.dedup();
             w.add(&key, &encode_sorted_u64(&lst))?;
             w.finalize()?;
 
-            let mut man = Manifest::load(&dir)?;
             man.add_segment(&dir, &seg_name, SegmentKind::Resolver)?;
             man.save_atomic(&dir)?;
             println!("added segment: {}", seg_name);
@@ -455,11 +1763,42 @@ fn main() -> Result<()> {
                 CliResolveMode::Union => ResolveMode::Union,
                 CliResolveMode::Dedup => ResolveMode::Dedup,
                 CliResolveMode::Intersect => ResolveMode::Intersect,
+                CliResolveMode::Difference => ResolveMode::Difference,
             };
             let out = store.resolve_with_mode_set(m, &keys, set);
             println!("{:?}", out);
         }
-        Cmd::Verify { dir } => {
+        Cmd::Key {
+            cmd:
+                KeyCmd::Build {
+                    kind,
+                    a,
+                    a_hex,
+                    b,
+                    b_hex,
+                    resolve,
+                    dir,
+                },
+        } => {
+            let a = key_atom("a", a, a_hex)?;
+            let b = if kind.is_pair() {
+                Some(key_atom("b", b, b_hex)?)
+            } else if b.is_some() || b_hex.is_some() {
+                return Err(anyhow!("--b is only used with sp, po and so keys"));
+            } else {
+                None
+            };
+            let key = match b {
+                Some(b) => ResolverKey::pair(kind, &a, &b),
+                None => ResolverKey::single(kind, &a),
+            };
+            println!("{}", hex::encode(&key.0));
+            if let (true, Some(dir)) = (resolve, dir) {
+                let store = ResolverStore::open(&dir)?;
+                println!("{:?}", store.resolve(&key.0));
+            }
+        }
+        Cmd::Verify { dir, fix, yes } => {
             let man = Manifest::load(&dir)?;
             let mut seg_ok = 0usize;
             let mut seg_fail = 0usize;
@@ -469,8 +1808,14 @@ fn main() -> Result<()> {
             let mut filter_miss = 0usize;
             let mut total_slots: u64 = 0;
             let mut total_filled: u64 = 0;
+            let mut skipped = 0usize;
 
             for s in &man.segments {
+                // Archived segments are kept for reference, not read.
+                if man.archived_paths.iter().any(|p| Path::new(p) == s.path) {
+                    skipped += 1;
+                    continue;
+                }
                 let path = dir.join(&s.path);
                 match SegmentReader::open(&path) {
                     Ok(r) => {
@@ -478,25 +1823,20 @@ fn main() -> Result<()> {
                             total_slots += cap;
                         }
                         if r.kind == SegmentKind::Resolver {
-                            let mut filled_here: u64 = 0;
-                            for e in r.iter() {
-                                filled_here += 1;
-                                total += 1;
-                                let end = (e.off as usize).saturating_add(e.size as usize);
-                                if end > std::fs::metadata(&path)?.len() as usize || e.size < 4 {
-                                    bad_bounds += 1;
-                                    continue;
-                                }
-                                if !r.verify_crc_at(e.off as usize, e.size as usize) {
-                                    bad_crc += 1;
+                            match r.verify() {
+                                Ok(check) => {
+                                    total += check.entries;
+                                    total_filled += check.entries as u64;
+                                    bad_bounds += check.bad_bounds;
+                                    bad_crc += check.bad_crc;
+                                    filter_miss += check.filter_miss;
                                 }
-                                if let Some(hit) = r.filter_contains_digest(e.hash) {
-                                    if !hit {
-                                        filter_miss += 1;
-                                    }
+                                Err(e) => {
+                                    eprintln!("verify: {}: {e}", path.display());
+                                    seg_fail += 1;
+                                    continue;
                                 }
                             }
-                            total_filled += filled_here;
                         }
                         seg_ok += 1;
                     }
@@ -511,7 +1851,12 @@ fn main() -> Result<()> {
             } else {
                 0.0
             };
-            println!("verify: segments ok={}, fail={}", seg_ok, seg_fail);
+            let skipped = if skipped > 0 {
+                format!(" ({skipped} archived, not checked)")
+            } else {
+                String::new()
+            };
+            println!("verify: segments ok={}, fail={}{skipped}", seg_ok, seg_fail);
             println!(
                 "         entries={}  bad_bounds={}  bad_crc={}  filter_miss(XOR)={}",
                 total, bad_bounds, bad_crc, filter_miss
@@ -520,106 +1865,59 @@ fn main() -> Result<()> {
                 "         load_factor(avg)≈{:.2} (filled={total_filled} / slots={total_slots})",
                 lf
             );
-        }
-        Cmd::Compact { dir } => {
-            let man = Manifest::load(&dir)?;
-            let mut mp: HashMap<u64, Vec<u64>> = HashMap::new();
-            let mut input_segments = 0usize;
-            for s in &man.segments {
-                if s.kind != SegmentKind::Resolver {
-                    continue;
-                }
-                let r = SegmentReader::open(dir.join(&s.path))?;
-                input_segments += 1;
-                for e in r.iter() {
-                    if let Some(val) = r.value_at(e.off as usize, e.size as usize) {
-                        let mut lst = decode_sorted_u64(val);
-                        if lst.is_empty() {
-                            continue;
-                        }
-                        lst.sort_unstable();
-                        lst.dedup();
-                        mp.entry(e.hash)
-                            .and_modify(|acc| {
-                                let merged = merge_sorted(acc, &lst);
-                                *acc = merged;
-                            })
-                            .or_insert(lst);
-                    }
-                }
+            if fix {
+                handle_verify_fix(&dir, yes)?;
             }
-            if input_segments == 0 {
-                return Err(anyhow!("no resolver segments to compact"));
-            }
-
-            // Çakışma guard: nano + random
-            let seg_name = format!("resolver-compact-{}.prus", now_id());
-            let seg_path = dir.join(&seg_name);
-            let mut w = SegmentWriter::create(&seg_path, SegmentKind::Resolver, 1 << 20, 7)?;
-            w.set_index_kind(pru_core::consts::INDEX_KIND_HASHTAB); // V1
-            w.set_filter_xor8();
-
-            let mut keys: Vec<u64> = mp.keys().copied().collect();
-            keys.sort_unstable();
-            for h in keys {
-                let enc = encode_sorted_u64(mp.get(&h).unwrap());
-                w.add_hashed(h, &enc)?;
-            }
-            w.finalize()?;
-
-            let mut man2 = Manifest::load(&dir)?;
-            man2.add_segment(&dir, &seg_name, SegmentKind::Resolver)?;
-            man2.save_atomic(&dir)?;
-            println!("compact: wrote {}, entries={}", seg_name, mp.len());
         }
-        Cmd::Promote { dir } => {
+        Cmd::Compact { dir, kind } => handle_compact(&dir, kind)?,
+        Cmd::Promote { dir, kind } => {
             let mut man = Manifest::load(&dir)?;
-            let changed = man.promote_resolver_compact()?;
+            for kind in kind.kinds() {
+                let changed = man.promote_compact(kind)?;
+                println!("promote: active set updated ({kind} active={changed})");
+            }
             man.save_atomic(&dir)?;
-            println!("promote: active set updated (resolver active={})", changed);
             println!("active:  {:?}", man.active_paths);
             if !man.archived_paths.is_empty() {
                 println!("archived: {:?}", man.archived_paths);
             }
         }
-        Cmd::Info { dir } => {
-            let man = Manifest::load(&dir)?;
-            println!("segments: {}", man.segments.len());
-            let act = man.active_segment_paths();
-            println!("active   : {}", act.len());
-            for s in &man.segments {
-                let full = dir.join(&s.path);
-                let mark = if act.iter().any(|p| *p == s.path) {
-                    '*'
-                } else {
-                    ' '
-                };
-                let mut extra = String::new();
-                if let Ok(r) = SegmentReader::open(&full) {
-                    if let Some((k, cap)) = r.index_meta() {
-                        let filled = r.iter().count();
-                        let lf = if cap > 0 {
-                            (filled as f64) / (cap as f64)
-                        } else {
-                            0.0
-                        };
-                        extra =
-                            format!(" entries={} cap={} load≈{:.2} kind={}", filled, cap, lf, k);
-                    }
-                }
-                println!(
-                    "{} {:?} {}{}",
-                    mark,
-                    s.kind,
-                    s.path.display(),
-                    if extra.is_empty() {
-                        String::new()
-                    } else {
-                        format!("  [{}]", extra)
-                    }
-                );
+        Cmd::Diff {
+            dir,
+            other,
+            list,
+            output,
+        } => {
+            let ours = open_existing(&dir)?;
+            let theirs = open_existing(&other)?;
+            let diff = diff::diff(&ours, &theirs, list);
+            diff::print(&diff, output.format)?;
+            if !diff.is_empty() {
+                std::process::exit(1);
             }
         }
+        Cmd::Gc {
+            dir,
+            keep,
+            temp_age,
+            dry_run,
+        } => {
+            let options = GcOptions {
+                keep,
+                temp_age: std::time::Duration::from_secs(temp_age),
+                dry_run,
+            };
+            handle_gc(&dir, options)?;
+        }
+        Cmd::Segment { cmd } => match cmd {
+            SegmentCmd::Inspect { file } => inspect::inspect(&file)?,
+            SegmentCmd::Dump {
+                file,
+                limit,
+                values,
+            } => inspect::dump(&file, limit, values)?,
+        },
+        Cmd::Info { dir, output } => handle_info(&dir, output)?,
 
         Cmd::Entity { cmd } => match cmd {
             EntityCmd::Add { dir, name } => {
@@ -627,9 +1925,20 @@ fn main() -> Result<()> {
                 let id = store.intern_entity(&name)?;
                 println!("entity added: {name} -> #{id}");
             }
-            EntityCmd::List { dir } => {
+            EntityCmd::Rename { dir, from, to } => {
+                let mut store = open_store(&dir)?;
+                handle_atom_rename(&mut store, AtomKind::Entity, &from, &to)?;
+            }
+            EntityCmd::Delete { dir, name, cascade } => {
+                let mut store = open_store(&dir)?;
+                handle_atom_delete(&mut store, AtomKind::Entity, &name, cascade)?;
+            }
+            EntityCmd::List { dir, output } => {
                 let store = open_store(&dir)?;
                 let entities = store.entities();
+                if !output.is_text() {
+                    return output::emit_atoms(output.format, entities);
+                }
                 if entities.is_empty() {
                     println!("no entities found");
                 }
@@ -645,9 +1954,20 @@ fn main() -> Result<()> {
                 let id = store.intern_predicate(&name)?;
                 println!("predicate added: {name} -> #{id}");
             }
-            PredicateCmd::List { dir } => {
+            PredicateCmd::Rename { dir, from, to } => {
+                let mut store = open_store(&dir)?;
+                handle_atom_rename(&mut store, AtomKind::Predicate, &from, &to)?;
+            }
+            PredicateCmd::Delete { dir, name, cascade } => {
+                let mut store = open_store(&dir)?;
+                handle_atom_delete(&mut store, AtomKind::Predicate, &name, cascade)?;
+            }
+            PredicateCmd::List { dir, output } => {
                 let store = open_store(&dir)?;
                 let preds = store.predicates();
+                if !output.is_text() {
+                    return output::emit_atoms(output.format, preds);
+                }
                 if preds.is_empty() {
                     println!("no predicates found");
                 }
@@ -663,9 +1983,30 @@ fn main() -> Result<()> {
                 let id = store.intern_literal(&value)?;
                 println!("literal added: {value} -> #{id}");
             }
-            LiteralCmd::List { dir } => {
+            LiteralCmd::Rename { dir, from, to } => {
+                let mut store = open_store(&dir)?;
+                handle_atom_rename(&mut store, AtomKind::Literal, &from, &to)?;
+            }
+            LiteralCmd::Delete { dir, name, cascade } => {
+                let mut store = open_store(&dir)?;
+                handle_atom_delete(&mut store, AtomKind::Literal, &name, cascade)?;
+            }
+            LiteralCmd::Search {
+                dir,
+                text,
+                regex,
+                limit,
+                full,
+                output,
+            } => {
+                handle_literal_search(&dir, &text, regex, limit, full, output)?;
+            }
+            LiteralCmd::List { dir, output } => {
                 let store = open_store(&dir)?;
                 let lits = store.literals();
+                if !output.is_text() {
+                    return output::emit_atoms(output.format, lits);
+                }
                 if lits.is_empty() {
                     println!("no literals found");
                 }
@@ -702,12 +2043,130 @@ fn main() -> Result<()> {
                 let store = open_store(&args.dir)?;
                 handle_query(&store, args)?;
             }
+            FactCmd::AddBulk(args) => {
+                let mut store = open_store(&args.dir)?;
+                handle_fact_add_bulk(&mut store, args)?;
+            }
+            FactCmd::Tail(args) => handle_fact_tail(args)?,
+            FactCmd::Retract(args) => {
+                let mut store = open_store(&args.dir)?;
+                handle_fact_retract(&mut store, args)?;
+            }
         },
 
         Cmd::Query(args) => {
             let store = open_store(&args.dir)?;
             handle_query(&store, args)?;
         }
+
+        Cmd::Export(args) => {
+            let store = open_store(&args.dir)?;
+            handle_export(&store, args)?;
+        }
+
+        Cmd::Import(args) => {
+            let mut store = open_store(&args.dir)?;
+            handle_import(&mut store, args)?;
+        }
+        Cmd::Index { cmd } => match cmd {
+            IndexCmd::Build {
+                dir,
+                kinds,
+                rebuild,
+                promote,
+            } => {
+                let mut store = open_store(&dir)?;
+                handle_index_build(&mut store, &kinds, IndexOptions { rebuild, promote })?;
+            }
+            IndexCmd::Status { dir } => {
+                let store = open_store(&dir)?;
+                handle_index_status(&store)?;
+            }
+        },
+        Cmd::Media { cmd } => match cmd {
+            MediaCmd::Analyze {
+                dir,
+                path,
+                force,
+                output,
+            } => {
+                let handle = media::handle(open_store(&dir)?)?;
+                media::analyze(&handle, &path, force, output.format)?;
+            }
+            MediaCmd::Report { dir, media, output } => {
+                let handle = media::handle(open_store(&dir)?)?;
+                media::report(&handle, &media, output.format)?;
+            }
+            MediaCmd::Label {
+                dir,
+                media,
+                label,
+                annotator,
+                confidence,
+            } => {
+                let handle = media::handle(open_store(&dir)?)?;
+                media::label(&handle, &media, &label, &annotator, confidence)?;
+            }
+            MediaCmd::List {
+                dir,
+                media_type,
+                output,
+            } => {
+                let handle = media::handle(open_store(&dir)?)?;
+                media::list(&handle, media_type, output.format)?;
+            }
+        },
+        Cmd::Bench {
+            dir,
+            facts,
+            entities,
+            batch,
+            resolve_keys,
+            keep,
+            output,
+        } => {
+            let dir = dir.unwrap_or_else(std::env::temp_dir);
+            ensure_dir(&dir)?;
+            let options = bench::BenchOptions {
+                facts,
+                entities,
+                batch,
+                resolve_keys,
+            };
+            bench::run(&dir, options, keep, output.format)?;
+        }
+        Cmd::Detector { cmd } => match cmd {
+            DetectorCmd::List { dir, output } => {
+                let handle = media::handle(open_store(&dir)?)?;
+                detector::list(&handle, output.format)?;
+            }
+            DetectorCmd::Reliability { dir, id, output } => {
+                let handle = media::handle(open_store(&dir)?)?;
+                detector::reliability(&handle, &id, output.format)?;
+            }
+            DetectorCmd::ReliabilityReset { dir, id, yes } => {
+                let handle = media::handle(open_store(&dir)?)?;
+                let (detector, name) = detector::resolve(&handle, &id)?;
+                if !yes && !confirm(&format!("Reset the reliability of {name}?"))? {
+                    println!("nothing was reset");
+                    return Ok(());
+                }
+                detector::reset(&handle, detector, &name)?;
+            }
+        },
     }
     Ok(())
 }
+
+/// Ask on stderr and read a yes/no answer from stdin; anything but yes is no.
+fn confirm(question: &str) -> Result<bool> {
+    use std::io::Write;
+    eprint!("{question} [y/N] ");
+    std::io::stderr().flush()?;
+    let mut answer = String::new();
+    std::io::stdin().read_line(&mut answer)?;
+    Ok(matches!(
+        answer.trim().to_ascii_lowercase().as_str(),
+        "y" | "yes"
+    ))
+}
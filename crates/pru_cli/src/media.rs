@@ -0,0 +1,235 @@
+//! `pru media`: ingest files with the built-in detectors and read back what the
+//! truth engine makes of them, on the same store layout truth_sentinel uses.
+
+use anyhow::{anyhow, Context, Result};
+use pru_core::{PruDbHandle, PruStore};
+use pru_detectors_api::{DetectorRegistry, RegistryConfig};
+use pru_ingest::{IngestContext, IngestResult};
+use pru_media_schema::{
+    add_human_verdict_by, bump_reliability_from_verdict, ensure_schema, find_media_entity,
+    get_human_verdicts, get_media_type, MediaId, MediaType,
+};
+use pru_truth_engine::{DetectionReport, TruthEngine, TruthEngineConfig, Verdict};
+use serde::Serialize;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use crate::output::{self, OutputFormat};
+
+const MEDIA_TYPES: [MediaType; 4] = [
+    MediaType::Image,
+    MediaType::Text,
+    MediaType::Audio,
+    MediaType::Video,
+];
+
+/// Share `store` the way the media crates expect, with the media schema in place.
+pub fn handle(store: PruStore) -> Result<PruDbHandle> {
+    let handle: PruDbHandle = Arc::new(Mutex::new(store));
+    ensure_schema(&handle)?;
+    Ok(handle)
+}
+
+/// A media entity by id, entity name or content hash.
+pub fn resolve(handle: &PruDbHandle, media: &str) -> Result<MediaId> {
+    let found = if let Ok(id) = media.parse::<u64>() {
+        Some(MediaId(id))
+    } else if media.starts_with("media:") {
+        handle.lock().unwrap().get_entity_id(media).map(MediaId)
+    } else {
+        let mut found = None;
+        for media_type in MEDIA_TYPES {
+            found = find_media_entity(handle, media, media_type)?;
+            if found.is_some() {
+                break;
+            }
+        }
+        found
+    };
+    match found {
+        Some(id) if get_media_type(handle, id)?.is_some() => Ok(id),
+        _ => Err(anyhow!("no media with id or hash {media}")),
+    }
+}
+
+/// A report on one media item; `--format json` prints this.
+#[derive(Serialize)]
+pub struct MediaReport {
+    pub media_id: u64,
+    pub media_type: Option<MediaType>,
+    pub hash: Option<String>,
+    /// How ingest went, for `media analyze`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ingest: Option<IngestResult>,
+    #[serde(flatten)]
+    pub report: DetectionReport,
+    /// `report.explanations` and notes rendered as lines.
+    pub explanation_text: Vec<String>,
+}
+
+impl MediaReport {
+    fn new(handle: &PruDbHandle, id: MediaId, report: DetectionReport) -> Result<Self> {
+        Ok(MediaReport {
+            media_id: id.0,
+            media_type: get_media_type(handle, id)?,
+            hash: media_hash(handle, id),
+            ingest: None,
+            explanation_text: report.rendered_explanations(),
+            report,
+        })
+    }
+
+    fn print(&self) {
+        let kind = self.media_type.map(type_name).unwrap_or("?");
+        let hash = self.hash.as_deref().unwrap_or("-");
+        println!("media #{} {kind} {hash}", self.media_id);
+        if let Some(ingest) = &self.ingest {
+            println!(
+                "detectors: {} succeeded, {} failed, {} timed out, {} cached",
+                ingest.succeeded().len(),
+                ingest.failed().len(),
+                ingest.timed_out().len(),
+                ingest.cached().len()
+            );
+        }
+        let report = &self.report;
+        println!(
+            "verdict: {}  p(ai)={:.2} [{:.2}, {:.2}]  evidence={}",
+            verdict_name(report.verdict),
+            report.probability_ai,
+            report.confidence_interval.low,
+            report.confidence_interval.high,
+            report.evidence_count
+        );
+        for line in &self.explanation_text {
+            println!("  {line}");
+        }
+    }
+}
+
+/// One line of `media list`.
+#[derive(Serialize)]
+pub struct MediaEntry {
+    pub media_id: u64,
+    pub media_type: MediaType,
+    pub hash: Option<String>,
+    pub human_verdicts: Vec<String>,
+}
+
+/// The content hash in a media entity's name.
+fn media_hash(handle: &PruDbHandle, id: MediaId) -> Option<String> {
+    let name = handle.lock().unwrap().get_entity_name(id.0)?;
+    name.rsplit_once("sha256:")
+        .map(|(_, hash)| hash.to_string())
+}
+
+fn type_name(media_type: MediaType) -> &'static str {
+    match media_type {
+        MediaType::Image => "image",
+        MediaType::Text => "text",
+        MediaType::Audio => "audio",
+        MediaType::Video => "video",
+    }
+}
+
+fn verdict_name(verdict: Verdict) -> &'static str {
+    match verdict {
+        Verdict::LikelyAi => "likely AI",
+        Verdict::LikelyHuman => "likely human",
+        Verdict::Inconclusive => "inconclusive",
+    }
+}
+
+fn emit_report(report: &MediaReport, format: OutputFormat) -> Result<()> {
+    if format == OutputFormat::Text {
+        report.print();
+        return Ok(());
+    }
+    output::emit_one(format, report)
+}
+
+/// Ingest the file at `path` with the default detectors, its type sniffed from
+/// the content, and print the resulting report.
+pub fn analyze(handle: &PruDbHandle, path: &Path, force: bool, format: OutputFormat) -> Result<()> {
+    let bytes =
+        std::fs::read(path).with_context(|| format!("failed to read {}", path.display()))?;
+    let ctx = IngestContext::new(
+        handle.clone(),
+        DetectorRegistry::from_config(&RegistryConfig::default())?,
+    )
+    .with_force(force);
+    let result = ctx.ingest_auto(&bytes)?;
+    let engine = TruthEngine::new(TruthEngineConfig::default());
+    let report = engine.evaluate_media(handle, result.media_id)?;
+    let mut report = MediaReport::new(handle, result.media_id, report)?;
+    report.ingest = Some(result);
+    emit_report(&report, format)
+}
+
+pub fn report(handle: &PruDbHandle, media: &str, format: OutputFormat) -> Result<()> {
+    let id = resolve(handle, media)?;
+    let engine = TruthEngine::new(TruthEngineConfig::default());
+    let report = engine.evaluate_media(handle, id)?;
+    emit_report(&MediaReport::new(handle, id, report)?, format)
+}
+
+/// Record a human verdict and credit or debit the detectors that scored the media.
+pub fn label(
+    handle: &PruDbHandle,
+    media: &str,
+    label: &str,
+    annotator: &str,
+    confidence: f32,
+) -> Result<()> {
+    let id = resolve(handle, media)?;
+    add_human_verdict_by(handle, id, label, annotator, confidence)?;
+    bump_reliability_from_verdict(handle, id, label)?;
+    println!("labeled media #{} as {label}", id.0);
+    Ok(())
+}
+
+pub fn list(
+    handle: &PruDbHandle,
+    media_type: Option<MediaType>,
+    format: OutputFormat,
+) -> Result<()> {
+    let entities = handle.lock().unwrap().entities();
+    let mut entries = Vec::new();
+    for (id, name) in entities {
+        if !name.starts_with("media:") {
+            continue;
+        }
+        let Some(kind) = get_media_type(handle, MediaId(id))? else {
+            continue;
+        };
+        if media_type.is_some_and(|wanted| wanted != kind) {
+            continue;
+        }
+        entries.push(MediaEntry {
+            media_id: id,
+            media_type: kind,
+            hash: media_hash(handle, MediaId(id)),
+            human_verdicts: get_human_verdicts(handle, MediaId(id))?,
+        });
+    }
+    if format != OutputFormat::Text {
+        return output::emit(format, &entries);
+    }
+    if entries.is_empty() {
+        println!("no media found");
+    }
+    for entry in &entries {
+        let verdicts = if entry.human_verdicts.is_empty() {
+            String::new()
+        } else {
+            format!("  labeled {}", entry.human_verdicts.join(", "))
+        };
+        println!(
+            "#{}\t{}\t{}{verdicts}",
+            entry.media_id,
+            type_name(entry.media_type),
+            entry.hash.as_deref().unwrap_or("-")
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,111 @@
+//! `--format json|jsonl` for commands whose text output is meant for people.
+
+use anyhow::Result;
+use clap::{Args, ValueEnum};
+use pru_core::{Fact, PruStore};
+use serde::Serialize;
+
+use pru_core::export::ObjectKind;
+
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OutputFormat {
+    #[default]
+    Text,
+    /// One JSON array (or object) for the whole output
+    Json,
+    /// One JSON object per line
+    Jsonl,
+}
+
+#[derive(Args, Clone, Copy, Default)]
+pub struct OutputArgs {
+    #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+    pub format: OutputFormat,
+}
+
+impl OutputArgs {
+    pub fn is_text(self) -> bool {
+        self.format == OutputFormat::Text
+    }
+}
+
+/// A fact with its ids and the names they stand for. Field names are stable.
+#[derive(Serialize)]
+pub struct FactJson {
+    pub subject: u64,
+    pub subject_name: Option<String>,
+    pub predicate: u64,
+    pub predicate_name: Option<String>,
+    pub object: u64,
+    pub object_kind: Option<ObjectKind>,
+    pub object_value: Option<String>,
+    pub source: Option<u64>,
+    pub confidence: Option<f32>,
+    pub timestamp: Option<i64>,
+}
+
+impl FactJson {
+    pub fn new(store: &PruStore, fact: &Fact) -> Self {
+        let (object_kind, object_value) = match store.get_entity_name(fact.object) {
+            Some(name) => (Some(ObjectKind::Entity), Some(name)),
+            None => match store.get_literal_value(fact.object) {
+                Some(value) => (Some(ObjectKind::Literal), Some(value)),
+                None => (None, None),
+            },
+        };
+        FactJson {
+            subject: fact.subject,
+            subject_name: store.get_entity_name(fact.subject),
+            predicate: fact.predicate,
+            predicate_name: store.get_predicate_name(fact.predicate),
+            object: fact.object,
+            object_kind,
+            object_value,
+            source: fact.source,
+            confidence: fact.confidence,
+            timestamp: fact.timestamp,
+        }
+    }
+}
+
+/// An entity, predicate or literal from a dictionary listing.
+#[derive(Serialize)]
+pub struct AtomJson {
+    pub id: u64,
+    pub name: String,
+}
+
+/// Print `records` as a JSON array, or one per line for jsonl.
+pub fn emit<T: Serialize>(format: OutputFormat, records: &[T]) -> Result<()> {
+    match format {
+        OutputFormat::Jsonl => {
+            for record in records {
+                println!("{}", serde_json::to_string(record)?);
+            }
+        }
+        _ => println!("{}", serde_json::to_string_pretty(records)?),
+    }
+    Ok(())
+}
+
+/// Print a single record; jsonl puts it on one line.
+pub fn emit_one<T: Serialize>(format: OutputFormat, record: &T) -> Result<()> {
+    match format {
+        OutputFormat::Jsonl => println!("{}", serde_json::to_string(record)?),
+        _ => println!("{}", serde_json::to_string_pretty(record)?),
+    }
+    Ok(())
+}
+
+pub fn emit_facts(store: &PruStore, format: OutputFormat, facts: &[Fact]) -> Result<()> {
+    let records: Vec<FactJson> = facts.iter().map(|f| FactJson::new(store, f)).collect();
+    emit(format, &records)
+}
+
+pub fn emit_atoms(format: OutputFormat, atoms: Vec<(u64, String)>) -> Result<()> {
+    let records: Vec<AtomJson> = atoms
+        .into_iter()
+        .map(|(id, name)| AtomJson { id, name })
+        .collect();
+    emit(format, &records)
+}
@@ -0,0 +1,209 @@
+//! Merge the active segments of one kind into a single new segment.
+//!
+//! Each kind merges its own way: resolver postings are unioned per key, dict
+//! entries keep the value of the latest segment, and fact entries are
+//! concatenated with exact duplicates dropped. Inputs only keep the hash of
+//! each key, so the merged segment always uses the V1 index.
+
+use crate::config::IndexKind;
+use crate::consts::SegmentKind;
+use crate::errors::{PruError, Result};
+use crate::manifest::Manifest;
+use crate::postings::{decode_sorted_u64, encode_sorted_u64, merge_sorted};
+use crate::segment::SegmentReader;
+use crate::truth_store::PruStore;
+use rand::Rng;
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::path::Path;
+
+/// `(key hash, value)` pairs of one segment.
+pub type Entries = Vec<(u64, Vec<u8>)>;
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct CompactReport {
+    pub kind: SegmentKind,
+    pub inputs: usize,
+    pub input_bytes: u64,
+    /// File name of the merged segment.
+    pub output: String,
+    pub output_bytes: u64,
+    pub entries: usize,
+}
+
+/// Union the posting lists of each key; empty lists are dropped.
+pub fn union_postings(segments: Vec<Entries>) -> Entries {
+    let mut merged: BTreeMap<u64, Vec<u64>> = BTreeMap::new();
+    for (hash, value) in segments.into_iter().flatten() {
+        let mut ids = decode_sorted_u64(&value);
+        if ids.is_empty() {
+            continue;
+        }
+        ids.sort_unstable();
+        ids.dedup();
+        merged
+            .entry(hash)
+            .and_modify(|acc| {
+                *acc = merge_sorted(acc, &ids);
+                acc.dedup();
+            })
+            .or_insert(ids);
+    }
+    merged
+        .into_iter()
+        .map(|(hash, ids)| (hash, encode_sorted_u64(&ids)))
+        .collect()
+}
+
+/// One value per key, from the last segment that has it.
+pub fn last_writer_wins(segments: Vec<Entries>) -> Entries {
+    let merged: BTreeMap<u64, Vec<u8>> = segments.into_iter().flatten().collect();
+    merged.into_iter().collect()
+}
+
+/// Every entry of every segment, in order, without exact repeats.
+pub fn concat_dedup(segments: Vec<Entries>) -> Entries {
+    let mut seen = HashSet::new();
+    segments
+        .into_iter()
+        .flatten()
+        .filter(|entry| seen.insert(entry.clone()))
+        .collect()
+}
+
+/// Entries whose value lies inside the file and matches its checksum.
+fn read_entries(reader: &SegmentReader) -> Entries {
+    reader
+        .iter()
+        .filter(|e| reader.entry_ok(e))
+        .filter_map(|e| {
+            let value = reader.value_at(e.off as usize, e.size as usize)?;
+            Some((e.hash, value.to_vec()))
+        })
+        .collect()
+}
+
+/// Merge the active `kind` segments of the store at `dir` into a new segment
+/// and add it to `manifest`, which the caller saves. The inputs stay active
+/// until they are promoted away.
+pub fn compact(dir: &Path, manifest: &mut Manifest, kind: SegmentKind) -> Result<CompactReport> {
+    let active = manifest.active_segment_paths();
+    let inputs: Vec<_> = manifest
+        .segments
+        .iter()
+        .filter(|s| s.kind == kind && active.contains(&s.path))
+        .map(|s| dir.join(&s.path))
+        .collect();
+    if inputs.is_empty() {
+        return Err(PruError::InvalidInput(format!(
+            "no {kind} segments to compact"
+        )));
+    }
+    let mut segments = vec![];
+    let mut input_bytes = 0;
+    for path in &inputs {
+        let reader = SegmentReader::open(path)?;
+        input_bytes += reader.layout().file_len;
+        segments.push(read_entries(&reader));
+    }
+    let merged = match kind {
+        SegmentKind::Resolver => union_postings(segments),
+        SegmentKind::Dict => last_writer_wins(segments),
+        SegmentKind::Fact => concat_dedup(segments),
+    };
+
+    // Seconds, nanoseconds and a random suffix: names sort by age and never collide.
+    let now = time::OffsetDateTime::now_utc();
+    let output = format!(
+        "{kind}-compact-{}-{:09}-{:04x}.prus",
+        now.unix_timestamp(),
+        now.nanosecond(),
+        rand::rng().random::<u16>()
+    );
+    let mut w = manifest.config.segment_writer(dir.join(&output), kind)?;
+    w.set_index_kind(IndexKind::V1.code());
+    for (hash, value) in &merged {
+        w.add_hashed(*hash, value)?;
+    }
+    let path = w.finalize()?;
+    manifest.add_segment(dir, &output, kind)?;
+    Ok(CompactReport {
+        kind,
+        inputs: inputs.len(),
+        input_bytes,
+        output,
+        output_bytes: fs::metadata(path)?.len(),
+        entries: merged.len(),
+    })
+}
+
+impl PruStore {
+    /// Merge the active `kind` segments with [`compact`], leave the merged
+    /// segment as the only active one of its kind and save the manifest.
+    pub fn compact_segments(&mut self, kind: SegmentKind) -> Result<CompactReport> {
+        let mut manifest = self.manifest().clone();
+        let report = compact(self.dir(), &mut manifest, kind)?;
+        manifest.promote_compact(kind)?;
+        self.replace_manifest(manifest)?;
+        Ok(report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::segment::SegmentWriter;
+    use std::path::PathBuf;
+
+    fn entries(pairs: &[(u64, &[u8])]) -> Entries {
+        pairs.iter().map(|(h, v)| (*h, v.to_vec())).collect()
+    }
+
+    #[test]
+    fn merges_follow_the_segment_kind() {
+        let postings = union_postings(vec![
+            entries(&[
+                (1, &encode_sorted_u64(&[1, 3])),
+                (2, &encode_sorted_u64(&[])),
+            ]),
+            entries(&[(1, &encode_sorted_u64(&[2, 3]))]),
+        ]);
+        assert_eq!(postings, entries(&[(1, &encode_sorted_u64(&[1, 2, 3]))]));
+
+        let dict = last_writer_wins(vec![
+            entries(&[(1, b"old"), (2, b"kept")]),
+            entries(&[(1, b"new")]),
+        ]);
+        assert_eq!(dict, entries(&[(1, b"new"), (2, b"kept")]));
+
+        let facts = concat_dedup(vec![
+            entries(&[(1, b"a"), (1, b"b")]),
+            entries(&[(1, b"a"), (2, b"c")]),
+        ]);
+        assert_eq!(facts, entries(&[(1, b"a"), (1, b"b"), (2, b"c")]));
+    }
+
+    #[test]
+    fn compact_segments_leaves_one_active_segment() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut manifest = Manifest::default();
+        for (name, ids) in [("a.prus", [1u64, 2]), ("b.prus", [2, 3])] {
+            let path = dir.path().join(name);
+            let mut w = SegmentWriter::create(path, SegmentKind::Resolver, 1 << 10, 7).unwrap();
+            w.add(b"key", &encode_sorted_u64(&ids)).unwrap();
+            w.finalize().unwrap();
+            manifest
+                .add_segment(dir.path(), name, SegmentKind::Resolver)
+                .unwrap();
+        }
+        manifest.save_atomic(dir.path()).unwrap();
+
+        let mut store = PruStore::open(dir.path()).unwrap();
+        let report = store.compact_segments(SegmentKind::Resolver).unwrap();
+        assert_eq!((report.inputs, report.entries), (2, 1));
+        let active = store.manifest().active_segment_paths();
+        assert_eq!(active, vec![PathBuf::from(&report.output)]);
+        let saved = Manifest::load(dir.path()).unwrap();
+        assert_eq!(saved.active_segment_paths(), active);
+    }
+}
@@ -0,0 +1,106 @@
+//! Store-wide defaults, kept in the manifest: how new segments are indexed and
+//! filtered, and how hard writes try to reach the disk.
+
+use crate::consts::{SegmentKind, INDEX_KIND_HASHTAB};
+use crate::errors::Result;
+use crate::segment::SegmentWriter;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::path::Path;
+
+/// Index block layout of new segments.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum IndexKind {
+    /// Hash table of `(hash, off, size)`.
+    V1,
+    /// Hash table with a key fingerprint per entry, to rule out hash collisions.
+    #[default]
+    V2,
+}
+
+impl IndexKind {
+    /// The kind as written in the segment's index block.
+    pub fn code(self) -> u32 {
+        match self {
+            IndexKind::V1 => INDEX_KIND_HASHTAB,
+            IndexKind::V2 => 2,
+        }
+    }
+}
+
+/// Membership filter written after the index block.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FilterKind {
+    #[default]
+    Xor8,
+    /// Sized by [`StoreConfig::bloom_bits`] and [`StoreConfig::bloom_k`].
+    Bloom,
+}
+
+/// What a write does before it counts as done.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Durability {
+    /// Leave the data to the OS.
+    None,
+    /// Flush buffered writes to the OS, without waiting for the disk.
+    Flush,
+    /// Flush and fsync files and their directory before publishing them.
+    #[default]
+    Fsync,
+}
+
+macro_rules! lowercase_display {
+    ($($ty:ty),*) => {$(
+        impl fmt::Display for $ty {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.pad(&format!("{self:?}").to_lowercase())
+            }
+        }
+    )*};
+}
+
+lowercase_display!(IndexKind, FilterKind, Durability);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StoreConfig {
+    pub index_kind: IndexKind,
+    pub filter: FilterKind,
+    pub durability: Durability,
+    pub bloom_bits: u32,
+    pub bloom_k: u32,
+}
+
+impl Default for StoreConfig {
+    fn default() -> Self {
+        Self {
+            index_kind: IndexKind::default(),
+            filter: FilterKind::default(),
+            durability: Durability::default(),
+            bloom_bits: 1 << 20,
+            bloom_k: 7,
+        }
+    }
+}
+
+impl StoreConfig {
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// A writer for a new segment at `path`, set up with these defaults.
+    pub fn segment_writer(
+        &self,
+        path: impl AsRef<Path>,
+        kind: SegmentKind,
+    ) -> Result<SegmentWriter> {
+        let mut w = SegmentWriter::create(path, kind, self.bloom_bits, self.bloom_k)?;
+        w.set_index_kind(self.index_kind.code());
+        w.set_filter(self.filter);
+        w.set_durability(self.durability);
+        Ok(w)
+    }
+}
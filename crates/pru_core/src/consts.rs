@@ -13,6 +13,25 @@ pub enum SegmentKind {
     Resolver = 3, // resolver postings
 }
 
+impl SegmentKind {
+    pub const ALL: [SegmentKind; 3] = [SegmentKind::Dict, SegmentKind::Fact, SegmentKind::Resolver];
+
+    /// Lowercase name, as used in segment file names and on the command line.
+    pub fn name(self) -> &'static str {
+        match self {
+            SegmentKind::Dict => "dict",
+            SegmentKind::Fact => "fact",
+            SegmentKind::Resolver => "resolver",
+        }
+    }
+}
+
+impl core::fmt::Display for SegmentKind {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.pad(self.name())
+    }
+}
+
 pub const HDR_SIZE: usize = 48;
 pub const IDX_ENTRY_SIZE: usize = 24;
 
@@ -0,0 +1,266 @@
+//! Facts out of a store as JSONL, CSV or N-Triples, as `pru export` and the
+//! explorer write them.
+
+use crate::errors::Result;
+use crate::truth_store::{Fact, PruStore};
+use serde::{Deserialize, Serialize};
+use std::io::{self, Write};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ExportFormat {
+    #[default]
+    Jsonl,
+    Csv,
+    Ntriples,
+}
+
+impl ExportFormat {
+    pub fn name(self) -> &'static str {
+        match self {
+            ExportFormat::Jsonl => "jsonl",
+            ExportFormat::Csv => "csv",
+            ExportFormat::Ntriples => "ntriples",
+        }
+    }
+
+    /// File extension for the format.
+    pub fn extension(self) -> &'static str {
+        match self {
+            ExportFormat::Jsonl => "jsonl",
+            ExportFormat::Csv => "csv",
+            ExportFormat::Ntriples => "nt",
+        }
+    }
+}
+
+/// Whether an object is an entity or a literal, which its name alone cannot tell.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ObjectKind {
+    Entity,
+    Literal,
+}
+
+/// One fact as a JSONL line. Each atom is given by name, or by id with `--ids`
+/// or when it has no name; this is also what `pru import` reads.
+#[derive(Serialize, Deserialize, Default, Debug, PartialEq)]
+pub struct FactRecord {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub subject: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub subject_id: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub predicate: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub predicate_id: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub object: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub object_kind: Option<ObjectKind>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub object_id: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source_id: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timestamp: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub confidence: Option<f32>,
+}
+
+impl FactRecord {
+    pub fn new(store: &PruStore, fact: &Fact, ids: bool) -> Self {
+        let mut record = FactRecord {
+            source_id: fact.source,
+            timestamp: fact.timestamp,
+            confidence: fact.confidence,
+            ..Default::default()
+        };
+        match store.get_entity_name(fact.subject).filter(|_| !ids) {
+            Some(name) => record.subject = Some(name),
+            None => record.subject_id = Some(fact.subject),
+        }
+        match store.get_predicate_name(fact.predicate).filter(|_| !ids) {
+            Some(name) => record.predicate = Some(name),
+            None => record.predicate_id = Some(fact.predicate),
+        }
+        let object = store
+            .get_entity_name(fact.object)
+            .map(|name| (name, ObjectKind::Entity))
+            .or_else(|| {
+                let value = store.get_literal_value(fact.object)?;
+                Some((value, ObjectKind::Literal))
+            });
+        match object.filter(|_| !ids) {
+            Some((name, kind)) => {
+                record.object = Some(name);
+                record.object_kind = Some(kind);
+            }
+            None => record.object_id = Some(fact.object),
+        }
+        record
+    }
+}
+
+const CSV_HEADER: [&str; 10] = [
+    "subject",
+    "subject_id",
+    "predicate",
+    "predicate_id",
+    "object",
+    "object_kind",
+    "object_id",
+    "source_id",
+    "timestamp",
+    "confidence",
+];
+
+/// Write `facts` to `out` one at a time and return how many were written.
+/// N-Triples has no place for source, timestamp or confidence, so they are left out.
+pub fn write_facts<'a>(
+    store: &PruStore,
+    facts: impl Iterator<Item = &'a Fact>,
+    format: ExportFormat,
+    ids: bool,
+    mut out: impl Write,
+) -> Result<usize> {
+    let mut count = 0;
+    match format {
+        ExportFormat::Jsonl => {
+            for fact in facts {
+                serde_json::to_writer(&mut out, &FactRecord::new(store, fact, ids))?;
+                out.write_all(b"\n")?;
+                count += 1;
+            }
+            out.flush()?;
+        }
+        ExportFormat::Csv => {
+            let mut out = csv::Writer::from_writer(out);
+            out.write_record(CSV_HEADER).map_err(io::Error::from)?;
+            for fact in facts {
+                out.write_record(csv_row(&FactRecord::new(store, fact, ids)))
+                    .map_err(io::Error::from)?;
+                count += 1;
+            }
+            out.flush()?;
+        }
+        ExportFormat::Ntriples => {
+            for fact in facts {
+                writeln!(out, "{}", triple(&FactRecord::new(store, fact, ids)))?;
+                count += 1;
+            }
+            out.flush()?;
+        }
+    }
+    Ok(count)
+}
+
+fn csv_row(record: &FactRecord) -> [String; 10] {
+    fn field<T: ToString>(value: &Option<T>) -> String {
+        value.as_ref().map(T::to_string).unwrap_or_default()
+    }
+    let kind = record.object_kind.map(|kind| match kind {
+        ObjectKind::Entity => "entity",
+        ObjectKind::Literal => "literal",
+    });
+    [
+        field(&record.subject),
+        field(&record.subject_id),
+        field(&record.predicate),
+        field(&record.predicate_id),
+        field(&record.object),
+        field(&kind),
+        field(&record.object_id),
+        field(&record.source_id),
+        field(&record.timestamp),
+        field(&record.confidence),
+    ]
+}
+
+/// Prefix of the IRIs atoms are written as.
+pub const IRI_PREFIX: &str = "urn:pru:";
+
+fn triple(record: &FactRecord) -> String {
+    let atom = |kind: &str, name: &Option<String>, id: Option<u64>| match name {
+        Some(name) => format!("<{IRI_PREFIX}{kind}:{}>", iri_escape(name)),
+        None => format!("<{IRI_PREFIX}id:{}>", id.unwrap_or_default()),
+    };
+    let object = match (&record.object, record.object_kind) {
+        (Some(value), Some(ObjectKind::Literal)) => format!("\"{}\"", literal_escape(value)),
+        _ => atom("entity", &record.object, record.object_id),
+    };
+    format!(
+        "{} {} {object} .",
+        atom("entity", &record.subject, record.subject_id),
+        atom("predicate", &record.predicate, record.predicate_id),
+    )
+}
+
+/// Percent-encode everything but unreserved characters, so any name is a valid IRI.
+fn iri_escape(name: &str) -> String {
+    let mut out = String::with_capacity(name.len());
+    for byte in name.bytes() {
+        if byte.is_ascii_alphanumeric() || b"-._~".contains(&byte) {
+            out.push(byte as char);
+        } else {
+            out.push_str(&format!("%{byte:02X}"));
+        }
+    }
+    out
+}
+
+fn literal_escape(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '\\' => out.push_str("\\\\"),
+            '"' => out.push_str("\\\""),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn formats_name_atoms_the_same_way() {
+        let dir = tempdir().unwrap();
+        let mut store = PruStore::open(dir.path()).unwrap();
+        let alice = store.intern_entity("alice").unwrap();
+        let knows = store.intern_predicate("knows").unwrap();
+        let bob = store.intern_literal("bob, \"the builder\"").unwrap();
+        store
+            .add_fact(Fact {
+                subject: alice,
+                predicate: knows,
+                object: bob,
+                source: None,
+                timestamp: Some(7),
+                confidence: None,
+            })
+            .unwrap();
+        let facts = store.query(Default::default()).unwrap();
+
+        let mut jsonl = Vec::new();
+        let count =
+            write_facts(&store, facts.iter(), ExportFormat::Jsonl, false, &mut jsonl).unwrap();
+        assert_eq!(count, 1);
+        let record: FactRecord = serde_json::from_slice(&jsonl).unwrap();
+        assert_eq!(record.subject.as_deref(), Some("alice"));
+        assert_eq!(record.object_kind, Some(ObjectKind::Literal));
+
+        let mut csv = Vec::new();
+        write_facts(&store, facts.iter(), ExportFormat::Csv, false, &mut csv).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        assert_eq!(
+            csv.lines().nth(1),
+            Some("alice,,knows,,\"bob, \"\"the builder\"\"\",literal,,,7,1")
+        );
+    }
+}
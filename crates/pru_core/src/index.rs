@@ -0,0 +1,282 @@
+//! Resolver segments built from the fact log, one segment per [`KeyKind`].
+//!
+//! Keys are [`ResolverKey`]s over [`atom_id128`] of atom names (entity and
+//! predicate names, literal values). Each posting list holds the ids that
+//! complete the pattern: S and SP keys list objects, P, O and PO keys list
+//! subjects, and SO keys list predicates.
+
+use crate::atoms::{atom_id128, AtomHash, AtomId};
+use crate::consts::SegmentKind;
+use crate::errors::Result;
+use crate::manifest::IndexRec;
+use crate::postings::encode_sorted_u64;
+use crate::resolver::{KeyKind, ResolverKey};
+use crate::truth_store::{PruStore, Query};
+use std::collections::{BTreeMap, HashMap};
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct IndexOptions {
+    /// Replace indexes that already exist instead of skipping them.
+    pub rebuild: bool,
+    /// Leave the index segments as the only active resolver segments.
+    pub promote: bool,
+}
+
+#[derive(Debug, Clone)]
+pub enum IndexOutcome {
+    Built(IndexRec),
+    /// Already indexed and `rebuild` was not set.
+    Skipped(IndexStatus),
+}
+
+#[derive(Debug, Clone)]
+pub struct IndexStatus {
+    pub kind: KeyKind,
+    pub index: Option<IndexRec>,
+    /// The fact log changed since the index was built.
+    pub stale: bool,
+    pub facts_now: u64,
+}
+
+impl PruStore {
+    /// Build resolver segments for `kinds` from the current fact log and
+    /// register them in the manifest.
+    pub fn build_index(
+        &mut self,
+        kinds: &[KeyKind],
+        options: IndexOptions,
+    ) -> Result<Vec<IndexOutcome>> {
+        let digest = fact_digest(self)?;
+        let facts_now = self.fact_count() as u64;
+        let mut manifest = self.manifest().clone();
+        let mut replaced = vec![];
+        let mut outcomes = vec![];
+        let mut hashes = HashMap::new();
+        let mut seen = vec![];
+        for &kind in kinds {
+            if seen.contains(&kind) {
+                continue;
+            }
+            seen.push(kind);
+            if let Some(i) = manifest.indexes.iter().position(|r| r.kind == kind) {
+                if !options.rebuild {
+                    let rec = manifest.indexes[i].clone();
+                    outcomes.push(IndexOutcome::Skipped(status(
+                        kind,
+                        Some(rec),
+                        facts_now,
+                        &digest,
+                    )));
+                    continue;
+                }
+                let old = manifest.indexes.remove(i);
+                if let Some(path) = old.path {
+                    manifest.remove_segment(&path);
+                    replaced.push(path);
+                }
+            }
+
+            let postings = postings(self, kind, &mut hashes);
+            let now = time::OffsetDateTime::now_utc();
+            let mut rec = IndexRec {
+                kind,
+                path: None,
+                keys: postings.len() as u64,
+                postings: postings.values().map(|ids| ids.len() as u64).sum(),
+                facts: facts_now,
+                fact_digest: digest.clone(),
+                built_at: now.unix_timestamp(),
+            };
+            if !postings.is_empty() {
+                let name = format!("resolver-index-{kind}-{}.prus", now.unix_timestamp_nanos());
+                let mut w = self
+                    .config()
+                    .segment_writer(self.dir().join(&name), SegmentKind::Resolver)?;
+                for (key, ids) in &postings {
+                    w.add(key, &encode_sorted_u64(ids))?;
+                }
+                w.finalize()?;
+                manifest.add_segment(self.dir(), &name, SegmentKind::Resolver)?;
+                rec.path = Some(name);
+            }
+            manifest.indexes.push(rec.clone());
+            outcomes.push(IndexOutcome::Built(rec));
+        }
+
+        if options.promote {
+            let keep: Vec<String> = manifest
+                .indexes
+                .iter()
+                .filter_map(|r| r.path.clone())
+                .collect();
+            if !keep.is_empty() {
+                manifest.promote_resolvers(&keep);
+            }
+        }
+        self.replace_manifest(manifest)?;
+        for path in replaced {
+            let _ = std::fs::remove_file(self.dir().join(path));
+        }
+        Ok(outcomes)
+    }
+
+    /// Every key kind with its index, if any, and whether the fact log has
+    /// changed since it was built.
+    pub fn index_status(&self) -> Result<Vec<IndexStatus>> {
+        let digest = fact_digest(self)?;
+        let facts_now = self.fact_count() as u64;
+        Ok(KeyKind::ALL
+            .into_iter()
+            .map(|kind| {
+                let rec = self.manifest().indexes.iter().find(|r| r.kind == kind);
+                status(kind, rec.cloned(), facts_now, &digest)
+            })
+            .collect())
+    }
+}
+
+fn status(kind: KeyKind, index: Option<IndexRec>, facts_now: u64, digest: &str) -> IndexStatus {
+    IndexStatus {
+        kind,
+        stale: index.as_ref().is_some_and(|r| r.fact_digest != digest),
+        index,
+        facts_now,
+    }
+}
+
+/// Digest of the fact log, so an index notices edits as well as additions.
+fn fact_digest(store: &PruStore) -> Result<String> {
+    let mut hasher = blake3::Hasher::new();
+    for fact in store.query_iter(Query::default()) {
+        hasher.update(&serde_json::to_vec(fact)?);
+    }
+    Ok(hasher.finalize().to_hex().to_string())
+}
+
+/// [`atom_id128`] of the name an id stands for; atom ids are unique across
+/// entities, predicates and literals.
+fn atom_hash(
+    store: &PruStore,
+    id: AtomId,
+    cache: &mut HashMap<AtomId, Option<AtomHash>>,
+) -> Option<AtomHash> {
+    *cache.entry(id).or_insert_with(|| {
+        let name = store
+            .get_entity_name(id)
+            .or_else(|| store.get_predicate_name(id))
+            .or_else(|| store.get_literal_value(id))?;
+        Some(atom_id128(name.as_bytes()))
+    })
+}
+
+fn postings(
+    store: &PruStore,
+    kind: KeyKind,
+    cache: &mut HashMap<AtomId, Option<AtomHash>>,
+) -> BTreeMap<Vec<u8>, Vec<u64>> {
+    let mut out: BTreeMap<Vec<u8>, Vec<u64>> = BTreeMap::new();
+    for f in store.query_iter(Query::default()) {
+        let (Some(s), Some(p), Some(o)) = (
+            atom_hash(store, f.subject, cache),
+            atom_hash(store, f.predicate, cache),
+            atom_hash(store, f.object, cache),
+        ) else {
+            continue;
+        };
+        let (key, id) = match kind {
+            KeyKind::S => (ResolverKey::single(kind, &s), f.object),
+            KeyKind::P => (ResolverKey::single(kind, &p), f.subject),
+            KeyKind::O => (ResolverKey::single(kind, &o), f.subject),
+            KeyKind::SP => (ResolverKey::pair(kind, &s, &p), f.object),
+            KeyKind::PO => (ResolverKey::pair(kind, &p, &o), f.subject),
+            KeyKind::SO => (ResolverKey::pair(kind, &s, &o), f.predicate),
+        };
+        out.entry(key.0).or_default().push(id);
+    }
+    for ids in out.values_mut() {
+        ids.sort_unstable();
+        ids.dedup();
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::truth_store::Fact;
+    use tempfile::tempdir;
+
+    fn fact(subject: u64, predicate: u64, object: u64) -> Fact {
+        Fact {
+            subject,
+            predicate,
+            object,
+            source: None,
+            timestamp: None,
+            confidence: None,
+        }
+    }
+
+    #[test]
+    fn index_resolves_facts_and_goes_stale() {
+        let tmp = tempdir().unwrap();
+        let mut store = PruStore::open(tmp.path()).unwrap();
+        let earth = store.intern_entity("Earth").unwrap();
+        let moon = store.intern_entity("Moon").unwrap();
+        let orbits = store.intern_predicate("orbits").unwrap();
+        let sun = store.intern_literal("Sun").unwrap();
+        store.add_fact(fact(earth, orbits, sun)).unwrap();
+        store.add_fact(fact(moon, orbits, earth)).unwrap();
+
+        let outcomes = store
+            .build_index(&[KeyKind::S, KeyKind::PO], IndexOptions::default())
+            .unwrap();
+        assert_eq!(outcomes.len(), 2);
+        let resolver = store.resolver_store().expect("resolver segments");
+        let h = |name: &str| atom_id128(name.as_bytes());
+        let key = ResolverKey::single(KeyKind::S, &h("Earth"));
+        assert_eq!(resolver.resolve(&key.0), vec![sun]);
+        let key = ResolverKey::pair(KeyKind::PO, &h("orbits"), &h("Earth"));
+        assert_eq!(resolver.resolve(&key.0), vec![moon]);
+
+        let status = store.index_status().unwrap();
+        let s = status.iter().find(|s| s.kind == KeyKind::S).unwrap();
+        assert!(s.index.is_some() && !s.stale);
+        assert!(status
+            .iter()
+            .find(|s| s.kind == KeyKind::O)
+            .unwrap()
+            .index
+            .is_none());
+
+        store.add_fact(fact(moon, orbits, sun)).unwrap();
+        let reopened = PruStore::open(tmp.path()).unwrap();
+        let s = reopened.index_status().unwrap().remove(0);
+        assert!(s.stale);
+        assert_eq!((s.index.unwrap().facts, s.facts_now), (2, 3));
+
+        let mut store = reopened;
+        let skipped = store
+            .build_index(&[KeyKind::S], IndexOptions::default())
+            .unwrap();
+        assert!(matches!(skipped[0], IndexOutcome::Skipped(_)));
+        let rebuilt = store
+            .build_index(
+                &[KeyKind::S],
+                IndexOptions {
+                    rebuild: true,
+                    promote: true,
+                },
+            )
+            .unwrap();
+        let IndexOutcome::Built(rec) = &rebuilt[0] else {
+            panic!("expected a rebuild");
+        };
+        assert_eq!(rec.keys, 2);
+        let key = ResolverKey::single(KeyKind::S, &h("Moon"));
+        let resolver = store.resolver_store().unwrap();
+        assert_eq!(resolver.resolve(&key.0), vec![earth, sun]);
+        let segments = store.manifest().segments.len();
+        assert_eq!(segments, 2, "the replaced segment is dropped");
+    }
+}
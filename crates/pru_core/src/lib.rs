@@ -1,7 +1,11 @@
 pub mod atoms;
+pub mod compact;
+pub mod config;
 pub mod consts;
 pub mod errors;
+pub mod export;
 pub mod filter;
+pub mod index;
 pub mod manifest;
 pub mod postings;
 pub mod resolver;
@@ -11,12 +15,18 @@ pub mod truth_store;
 pub mod utils;
 
 pub use atoms::{atom_id128, AtomHash, AtomId, EntityId, LiteralId, PredicateId};
+pub use compact::CompactReport;
+pub use config::{Durability, FilterKind, IndexKind, StoreConfig};
 pub use consts::SegmentKind;
-pub use postings::{decode_sorted_u64, encode_sorted_u64, intersect_sorted, merge_sorted};
+pub use index::{IndexOptions, IndexOutcome, IndexStatus};
+pub use manifest::{GcOptions, GcReport, IndexRec};
+pub use postings::{
+    decode_sorted_u64, difference_sorted, encode_sorted_u64, intersect_sorted, merge_sorted,
+};
 pub use resolver::{KeyKind, ResolverKey};
 pub use resolver_store::ResolveMode; // ← ek
-pub use segment::{SegmentReader, SegmentWriter};
-pub use truth_store::{Fact, PruStore, Query};
+pub use segment::{SegmentCheck, SegmentLayout, SegmentReader, SegmentWriter};
+pub use truth_store::{AtomKind, Fact, PruStore, Query, QuerySort};
 
 use std::sync::{Arc, Mutex};
 
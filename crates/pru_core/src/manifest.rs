@@ -1,9 +1,12 @@
+use crate::config::{Durability, StoreConfig};
 use crate::consts::SegmentKind;
 use crate::errors::Result;
+use crate::resolver::KeyKind;
 use serde::{Deserialize, Serialize};
 use std::fs;
 use std::io::Write;
 use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SegmentRec {
@@ -25,6 +28,47 @@ mod path_serde {
     }
 }
 
+/// A resolver index built from the fact log by [`PruStore::build_index`](crate::PruStore::build_index).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IndexRec {
+    pub kind: KeyKind,
+    /// Segment holding the postings; `None` when the fact log gave no keys.
+    pub path: Option<String>,
+    pub keys: u64,
+    pub postings: u64,
+    /// Fact count and fact log digest at build time, to tell when it went stale.
+    pub facts: u64,
+    pub fact_digest: String,
+    pub built_at: i64,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct GcOptions {
+    /// Archived segments to keep, newest first.
+    pub keep: usize,
+    /// Temp files younger than this may still be in use by a writer.
+    pub temp_age: Duration,
+    /// Report what would be removed without removing anything.
+    pub dry_run: bool,
+}
+
+/// Files [`Manifest::gc`] removed, or would remove, with their sizes in bytes.
+#[derive(Debug, Default)]
+pub struct GcReport {
+    pub archived: Vec<(String, u64)>,
+    pub temp_files: Vec<(String, u64)>,
+}
+
+impl GcReport {
+    pub fn archived_bytes(&self) -> u64 {
+        self.archived.iter().map(|(_, n)| n).sum()
+    }
+
+    pub fn temp_bytes(&self) -> u64 {
+        self.temp_files.iter().map(|(_, n)| n).sum()
+    }
+}
+
 /// Manifest format (geri uyumlu):
 /// - Eski dosyalarda sadece `segments` vardır.
 /// - Yeni formatta `active_paths`/`archived_paths` opsiyoneldir.
@@ -38,6 +82,13 @@ pub struct Manifest {
 
     #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub archived_paths: Vec<String>,
+
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub indexes: Vec<IndexRec>,
+
+    /// Defaults for new segments and writes, as set by `pru init`.
+    #[serde(default, skip_serializing_if = "StoreConfig::is_default")]
+    pub config: StoreConfig,
 }
 
 impl Default for Manifest {
@@ -46,13 +97,20 @@ impl Default for Manifest {
             segments: vec![],
             active_paths: vec![],
             archived_paths: vec![],
+            indexes: vec![],
+            config: StoreConfig::default(),
         }
     }
 }
 
 impl Manifest {
+    /// Where the manifest of the store at `dir` is kept.
+    pub fn path(dir: &Path) -> PathBuf {
+        dir.join("manifest.json")
+    }
+
     pub fn load(dir: &Path) -> Result<Self> {
-        let p = dir.join("manifest.json");
+        let p = Self::path(dir);
         if !p.exists() {
             return Ok(Self::default());
         }
@@ -62,11 +120,13 @@ impl Manifest {
     }
 
     pub fn save_atomic(&self, dir: &Path) -> Result<()> {
-        let p = dir.join("manifest.json");
+        let p = Self::path(dir);
         let tmp = dir.join("manifest.json.tmp");
         let mut f = fs::File::create(&tmp)?;
         f.write_all(serde_json::to_string_pretty(self)?.as_bytes())?;
-        f.sync_all()?;
+        if self.config.durability == Durability::Fsync {
+            f.sync_all()?;
+        }
         drop(f);
         fs::rename(&tmp, &p)?;
         Ok(())
@@ -99,66 +159,180 @@ impl Manifest {
             .collect()
     }
 
+    /// Drop a segment from the manifest; the file itself is left alone.
+    pub fn remove_segment(&mut self, name: &str) {
+        self.segments.retain(|s| s.path.to_string_lossy() != name);
+        self.active_paths.retain(|p| p != name);
+        self.archived_paths.retain(|p| p != name);
+    }
+
+    /// Stop reading `name` and keep it on disk as an archived segment. An empty
+    /// active list is spelled out first so the other segments stay active.
+    pub fn archive_segment(&mut self, name: &str) {
+        if self.active_paths.is_empty() {
+            self.active_paths = self
+                .segments
+                .iter()
+                .map(|s| s.path.to_string_lossy().to_string())
+                .collect();
+        }
+        self.active_paths.retain(|p| p != name);
+        if !self.archived_paths.iter().any(|p| p == name) {
+            self.archived_paths.push(name.to_string());
+        }
+    }
+
+    /// Leave exactly the `keep` resolver segments active and archive the other
+    /// resolver segments. Other kinds stay as they are. `keep` must not be empty,
+    /// since an empty active list means every segment is active.
+    pub fn promote_resolvers(&mut self, keep: &[String]) {
+        let active = self.active_segment_paths();
+        let mut active_paths = vec![];
+        let mut archived = vec![];
+        for s in &self.segments {
+            let name = s.path.to_string_lossy().to_string();
+            if s.kind == SegmentKind::Resolver {
+                if keep.contains(&name) {
+                    active_paths.push(name);
+                } else {
+                    archived.push(name);
+                }
+            } else if active.contains(&s.path) {
+                active_paths.push(name);
+            } else {
+                archived.push(name);
+            }
+        }
+        self.active_paths = active_paths;
+        self.archived_paths = archived;
+    }
+
+    /// Delete archived segments beyond the newest `options.keep`, and temp files
+    /// left behind by interrupted writes (`pru_seg_*`, `*.tmp`) older than
+    /// `options.temp_age`. Active segments are never removed. The manifest is
+    /// saved before any file is deleted.
+    pub fn gc(&mut self, dir: &Path, options: GcOptions) -> Result<GcReport> {
+        let active = self.active_segment_paths();
+        let mut archived: Vec<(String, u64, SystemTime)> = vec![];
+        for name in &self.archived_paths {
+            if active.iter().any(|p| p.to_string_lossy() == name.as_str()) {
+                continue;
+            }
+            let (size, modified) = match fs::metadata(dir.join(name)) {
+                Ok(meta) => (meta.len(), meta.modified()?),
+                Err(_) => (0, SystemTime::UNIX_EPOCH),
+            };
+            archived.push((name.clone(), size, modified));
+        }
+        archived.sort_by_key(|a| std::cmp::Reverse(a.2));
+        let doomed: Vec<(String, u64)> = archived
+            .into_iter()
+            .skip(options.keep)
+            .map(|(name, size, _)| (name, size))
+            .collect();
+
+        let mut temp_files = vec![];
+        let now = SystemTime::now();
+        for entry in fs::read_dir(dir)? {
+            let entry = entry?;
+            let name = entry.file_name().to_string_lossy().to_string();
+            if !(name.starts_with("pru_seg_") || name.ends_with(".tmp")) {
+                continue;
+            }
+            let meta = entry.metadata()?;
+            let age = now.duration_since(meta.modified()?).unwrap_or_default();
+            if meta.is_file() && age >= options.temp_age {
+                temp_files.push((name, meta.len()));
+            }
+        }
+        temp_files.sort();
+
+        let report = GcReport {
+            archived: doomed,
+            temp_files,
+        };
+        if options.dry_run {
+            return Ok(report);
+        }
+        if !report.archived.is_empty() {
+            for (name, _) in &report.archived {
+                self.remove_segment(name);
+                self.indexes
+                    .retain(|r| r.path.as_deref() != Some(name.as_str()));
+            }
+            self.save_atomic(dir)?;
+        }
+        for (name, _) in report.archived.iter().chain(&report.temp_files) {
+            match fs::remove_file(dir.join(name)) {
+                Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e.into()),
+                _ => {}
+            }
+        }
+        Ok(report)
+    }
+
     /// Promote: Resolver segmentleri için tek “aktif” segment bırak.
-    /// - Eğer `resolver-compact-*.prus` varsa en sonuncuyu aktif bırak.
-    /// - Yoksa en son yazılmış resolver segmentini aktif bırak.
-    /// Diğer türler (Dict/Fact) aktif kalır.
+    /// See [`Manifest::promote_compact`].
     pub fn promote_resolver_compact(&mut self) -> Result<usize> {
-        // 1) Resolver segmentlerini ayır
-        let mut resolver: Vec<&SegmentRec> = self
-            .segments
-            .iter()
-            .filter(|s| s.kind == SegmentKind::Resolver)
-            .collect();
-        if resolver.is_empty() {
+        self.promote_compact(SegmentKind::Resolver)
+    }
+
+    /// Promote: `kind` segmentleri için tek “aktif” segment bırak.
+    /// - Eğer `{kind}-compact-*.prus` varsa en sonuncuyu aktif bırak.
+    /// - Yoksa en son yazılmış `kind` segmentini aktif bırak.
+    ///
+    /// Diğer türler aktif kalır. `kind` segmenti yoksa 0 döner.
+    pub fn promote_compact(&mut self, kind: SegmentKind) -> Result<usize> {
+        // 1) `kind` segmentlerini ayır
+        let mut of_kind: Vec<&SegmentRec> =
+            self.segments.iter().filter(|s| s.kind == kind).collect();
+        if of_kind.is_empty() {
             return Ok(0);
         }
 
         // 2) Önce compact olanları bul
-        resolver.sort_by_key(|s| s.path.clone());
+        of_kind.sort_by_key(|s| s.path.clone());
         let mut last_compact: Option<&SegmentRec> = None;
-        for s in &resolver {
+        for s in &of_kind {
             let fname = s.path.to_string_lossy();
-            if fname.starts_with("resolver-compact-") {
+            if fname.starts_with(&format!("{kind}-compact-")) {
                 last_compact = Some(s);
             }
         }
         let chosen = if let Some(s) = last_compact {
             s
         } else {
-            // compact yoksa en son resolver’ı seç
-            *resolver.last().unwrap()
+            // compact yoksa en son `kind` segmentini seç
+            *of_kind.last().unwrap()
         };
 
         // 3) active_paths’i yeniden kur:
-        // - resolver için sadece `chosen`
+        // - `kind` için sadece `chosen`
         // - diğer türler için mevcut aktif (varsa) veya tümü
-        let resolver_name = chosen.path.to_string_lossy().to_string();
+        let chosen_name = chosen.path.to_string_lossy().to_string();
 
         // Diğer türler:
         let mut keep: Vec<String> = vec![];
         if self.active_paths.is_empty() {
-            // henüz hiç set edilmemişse tüm non-resolver’ları ekle
+            // henüz hiç set edilmemişse diğer türlerin hepsini ekle
             for s in &self.segments {
-                if s.kind != SegmentKind::Resolver {
+                if s.kind != kind {
                     keep.push(s.path.to_string_lossy().to_string());
                 }
             }
         } else {
-            // aktifler içinden non-resolver olanları koru
+            // aktifler içinden diğer türleri koru
             let set: std::collections::HashSet<&str> =
                 self.active_paths.iter().map(|s| s.as_str()).collect();
             for s in &self.segments {
-                if s.kind != SegmentKind::Resolver
-                    && set.contains(s.path.to_string_lossy().as_ref())
-                {
+                if s.kind != kind && set.contains(s.path.to_string_lossy().as_ref()) {
                     keep.push(s.path.to_string_lossy().to_string());
                 }
             }
         }
 
-        // resolver için tek seçilmiş segment
-        keep.push(resolver_name.clone());
+        // `kind` için tek seçilmiş segment
+        keep.push(chosen_name.clone());
 
         // archived_paths’i de dolduralım ( bilgi amaçlı )
         let keep_set: std::collections::HashSet<&str> = keep.iter().map(|s| s.as_str()).collect();
@@ -32,3 +32,18 @@ pub fn intersect_sorted(a: &[u64], b: &[u64]) -> Vec<u64> {
     }
     out
 }
+
+/// Items of `a` that are not in `b`; both sorted.
+pub fn difference_sorted(a: &[u64], b: &[u64]) -> Vec<u64> {
+    let mut j = 0usize;
+    let mut out = Vec::new();
+    for &x in a {
+        while j < b.len() && b[j] < x {
+            j += 1;
+        }
+        if j == b.len() || b[j] != x {
+            out.push(x);
+        }
+    }
+    out
+}
@@ -1,7 +1,66 @@
 use crate::consts::ATOM_ID_BYTES;
+use crate::errors::PruError;
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, Copy)]
-pub enum KeyKind { S, P, O, SP, PO, SO }
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum KeyKind {
+    S,
+    P,
+    O,
+    SP,
+    PO,
+    SO,
+}
+
+impl KeyKind {
+    pub const ALL: [KeyKind; 6] = [
+        KeyKind::S,
+        KeyKind::P,
+        KeyKind::O,
+        KeyKind::SP,
+        KeyKind::PO,
+        KeyKind::SO,
+    ];
+
+    /// Lowercase name, as accepted by `from_str`.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            KeyKind::S => "s",
+            KeyKind::P => "p",
+            KeyKind::O => "o",
+            KeyKind::SP => "sp",
+            KeyKind::PO => "po",
+            KeyKind::SO => "so",
+        }
+    }
+
+    /// Whether keys of this kind are built from two atoms.
+    pub fn is_pair(self) -> bool {
+        matches!(self, KeyKind::SP | KeyKind::PO | KeyKind::SO)
+    }
+}
+
+impl std::fmt::Display for KeyKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.pad(self.as_str())
+    }
+}
+
+impl std::str::FromStr for KeyKind {
+    type Err = PruError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        KeyKind::ALL
+            .into_iter()
+            .find(|k| k.as_str().eq_ignore_ascii_case(s))
+            .ok_or_else(|| {
+                PruError::InvalidInput(format!(
+                    "unknown key kind {s:?} (use s, p, o, sp, po or so)"
+                ))
+            })
+    }
+}
 
 #[derive(Debug, Clone)]
 pub struct ResolverKey(pub Vec<u8>); // 1-byte prefix + 16 or 32 bytes
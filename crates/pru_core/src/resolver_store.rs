@@ -1,7 +1,7 @@
 use crate::consts::SegmentKind;
 use crate::errors::Result;
 use crate::manifest::Manifest;
-use crate::postings::{decode_sorted_u64, intersect_sorted, merge_sorted};
+use crate::postings::{decode_sorted_u64, difference_sorted, intersect_sorted, merge_sorted};
 use crate::segment::SegmentReader;
 use std::path::Path;
 
@@ -9,6 +9,8 @@ pub enum ResolveMode {
     Union,
     Dedup,
     Intersect,
+    /// The first key's ids without those of the other keys.
+    Difference,
 }
 
 pub struct ResolverStore {
@@ -109,6 +111,22 @@ impl ResolverStore {
                 }
                 acc
             }
+            ResolveMode::Difference => {
+                let Some((first, rest)) = keys.split_first() else {
+                    return vec![];
+                };
+                let mut acc = self.resolve(first);
+                if set_semantics {
+                    acc.dedup();
+                }
+                for k in rest {
+                    acc = difference_sorted(&acc, &self.resolve(k));
+                    if acc.is_empty() {
+                        break;
+                    }
+                }
+                acc
+            }
         }
     }
 }
@@ -28,7 +28,8 @@
 //!
 //! Value kaydı: [value bytes][crc32(value)]
 
-use crate::consts::{MAGIC_SEG, VERSION, HDR_SIZE, INDEX_KIND_HASHTAB, SegmentKind};
+use crate::config::{Durability, FilterKind};
+use crate::consts::{SegmentKind, HDR_SIZE, INDEX_KIND_HASHTAB, MAGIC_SEG, VERSION};
 use crate::errors::{PruError, Result};
 use crate::filter::Bloom;
 use crate::utils::{crc32, write_u32};
@@ -63,9 +64,6 @@ fn fsync_dir(path: &Path) -> std::io::Result<()> {
 #[cfg(not(unix))]
 fn fsync_dir(_path: &Path) -> std::io::Result<()> { Ok(()) }
 
-#[derive(Clone, Copy)]
-enum FilterKind { Bloom, Xor8 }
-
 /// Writer: append-only; index & filter bloklarını yazar, sonra atomik publish (Windows-safe).
 pub struct SegmentWriter {
     path_final: PathBuf,
@@ -76,6 +74,7 @@ pub struct SegmentWriter {
     bloom: Bloom,
     index_kind: u32,      // V1/V2 (default V2)
     filter_kind: FilterKind, // default XOR8
+    durability: Durability,  // default fsync
 }
 
 impl SegmentWriter {
@@ -93,13 +92,27 @@ impl SegmentWriter {
             bloom: Bloom::new(bloom_bits, bloom_k),
             index_kind: INDEX_KIND_HASHTAB_V2,
             filter_kind: FilterKind::Xor8, // varsayılan: XOR8
+            durability: Durability::Fsync,
         })
     }
 
     /// İndeks türünü seç (geri uyum veya compact için)
-    pub fn set_index_kind(&mut self, kind: u32) { self.index_kind = kind; }
-    pub fn set_filter_xor8(&mut self) { self.filter_kind = FilterKind::Xor8; }
-    pub fn set_filter_bloom(&mut self) { self.filter_kind = FilterKind::Bloom; }
+    pub fn set_index_kind(&mut self, kind: u32) {
+        self.index_kind = kind;
+    }
+    pub fn set_filter_xor8(&mut self) {
+        self.filter_kind = FilterKind::Xor8;
+    }
+    pub fn set_filter_bloom(&mut self) {
+        self.filter_kind = FilterKind::Bloom;
+    }
+    pub fn set_filter(&mut self, kind: FilterKind) {
+        self.filter_kind = kind;
+    }
+    /// What `finalize` does before publishing; fsync by default.
+    pub fn set_durability(&mut self, durability: Durability) {
+        self.durability = durability;
+    }
 
     /// (key,value) kaydı ekle. Value sonuna crc32(value).
     pub fn add(&mut self, key: &[u8], value: &[u8]) -> Result<()> {
@@ -258,17 +271,59 @@ impl SegmentWriter {
             hdr.extend_from_slice(&footer_off.to_le_bytes());
             hdr.resize(HDR_SIZE, 0);
             f.write_all(&hdr)?;
-            f.sync_all()?; // diske yaz
+            match self.durability {
+                Durability::Fsync => f.sync_all()?, // diske yaz
+                Durability::Flush => f.flush()?,
+                Durability::None => {}
+            }
         }
 
         // 5) Atomic publish (Windows-safe)
         let _persisted = self.tmp.persist(&self.path_final)?;
-        let _ = fsync_dir(&self.path_final);
+        if self.durability == Durability::Fsync {
+            let _ = fsync_dir(&self.path_final);
+        }
         Ok(self.path_final)
     }
 
 }
 
+/// Header fields and block layout of a segment, for inspection tools.
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct SegmentLayout {
+    pub version: u16,
+    pub kind: SegmentKind,
+    pub file_len: u64,
+    pub data_off: u64,
+    pub index_off: u64,
+    /// `None` when the index kind is unknown.
+    pub index_kind: Option<u32>,
+    pub index_capacity: Option<u64>,
+    pub entries: usize,
+    pub filter_off: u64,
+    /// "xor8" or "bloom".
+    pub filter: &'static str,
+    pub filter_bytes: u64,
+    pub footer_off: u64,
+}
+
+/// Entry counts from [`SegmentReader::verify`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize)]
+pub struct SegmentCheck {
+    pub entries: usize,
+    /// Values that run past the end of the file or are too short for a checksum.
+    pub bad_bounds: usize,
+    pub bad_crc: usize,
+    /// Keys an XOR filter does not report, so lookups of them would miss.
+    pub filter_miss: usize,
+}
+
+impl SegmentCheck {
+    pub fn is_ok(&self) -> bool {
+        self.bad_bounds == 0 && self.bad_crc == 0 && self.filter_miss == 0
+    }
+}
+
 /// Reader: V1/V2 index + Bloom/XOR filter okur, iterator & verify yardımcıları sağlar.
 pub struct SegmentReader {
     _f: File,
@@ -288,7 +343,9 @@ impl SegmentReader {
     pub fn open(path: impl AsRef<Path>) -> Result<Self> {
         let f = File::open(path)?;
         let mmap = unsafe { Mmap::map(&f)? };
-        if &mmap[0..4] != MAGIC_SEG { return Err(PruError::BadHeader); }
+        if mmap.len() < 28 || &mmap[0..4] != MAGIC_SEG {
+            return Err(PruError::BadHeader);
+        }
         let ver = u16::from_le_bytes(mmap[4..6].try_into().unwrap());
         if ver != VERSION { return Err(PruError::BadHeader); }
         let kind = u16::from_le_bytes(mmap[6..8].try_into().unwrap());
@@ -355,7 +412,57 @@ impl SegmentReader {
 
     /// Tekil get (crc hariç dilim). Bulamazsa None.
     pub fn get(&self, key: &[u8]) -> Option<&[u8]> {
-        if !self.filter_allows_key(key) { return None; }
+        let (off, size) = self.find(key)?;
+        self.value_at(off, size)
+    }
+
+    /// Like [`Self::get`], but a segment whose blocks run past the end of the
+    /// file, or a value that fails its checksum, is [`PruError::Corrupt`]
+    /// instead of a miss or a panic.
+    pub fn get_checked(&self, key: &[u8]) -> Result<Option<&[u8]>> {
+        if !self.blocks_in_bounds() {
+            return Err(PruError::Corrupt);
+        }
+        match self.find(key) {
+            Some((off, size)) if self.verify_crc_at(off, size) => Ok(self.value_at(off, size)),
+            Some(_) => Err(PruError::Corrupt),
+            None => Ok(None),
+        }
+    }
+
+    /// Whether the index table and the filter lie inside the file; the
+    /// index and filter helpers assume they do.
+    pub fn blocks_in_bounds(&self) -> bool {
+        let len = self.mmap.len();
+        let u32_at = |pos: usize| {
+            self.mmap
+                .get(pos..pos + 4)
+                .map(|b| u32::from_le_bytes(b.try_into().unwrap()) as usize)
+        };
+        let (Ok(index_off), Ok(filter_off)) = (
+            usize::try_from(self.index_off),
+            usize::try_from(self.bloom_off),
+        ) else {
+            return false;
+        };
+        if index_off.checked_add(12).is_none_or(|end| end > len) {
+            return false;
+        }
+        let (_, cap, base, esz) = self.index_info();
+        let table_end = usize::try_from(cap)
+            .ok()
+            .and_then(|cap| cap.checked_mul(esz))
+            .and_then(|bytes| bytes.checked_add(base));
+        let filter_end = u32_at(filter_off.saturating_add(4))
+            .and_then(|n| n.checked_add(filter_off)?.checked_add(8));
+        table_end.is_some_and(|end| end <= len) && filter_end.is_some_and(|end| end <= len)
+    }
+
+    /// `(off, size)` of the record stored under `key`, checksum included.
+    fn find(&self, key: &[u8]) -> Option<(usize, usize)> {
+        if !self.filter_allows_key(key) {
+            return None;
+        }
         let (kind, cap, base, esz) = self.index_info();
         if esz == 0 || cap == 0 { return None; }
         let h = h64(key);
@@ -368,19 +475,25 @@ impl SegmentReader {
             match kind {
                 INDEX_KIND_HASHTAB_V1 => {
                     if eh == h {
-                        let off = u64::from_le_bytes(self.mmap[epos+8..epos+16].try_into().unwrap()) as usize;
-                        let size = u32::from_le_bytes(self.mmap[epos+16..epos+20].try_into().unwrap()) as usize;
-                        let end = off + size;
-                        return Some(&self.mmap[off..end-4]);
+                        let off =
+                            u64::from_le_bytes(self.mmap[epos + 8..epos + 16].try_into().unwrap())
+                                as usize;
+                        let size =
+                            u32::from_le_bytes(self.mmap[epos + 16..epos + 20].try_into().unwrap())
+                                as usize;
+                        return self.value_at(off, size).map(|_| (off, size));
                     }
                 }
                 INDEX_KIND_HASHTAB_V2 => {
                     let efp = u64::from_le_bytes(self.mmap[epos+8..epos+16].try_into().unwrap());
                     if eh == h && efp == fp {
-                        let off = u64::from_le_bytes(self.mmap[epos+16..epos+24].try_into().unwrap()) as usize;
-                        let size = u32::from_le_bytes(self.mmap[epos+24..epos+28].try_into().unwrap()) as usize;
-                        let end = off + size;
-                        return Some(&self.mmap[off..end-4]);
+                        let off =
+                            u64::from_le_bytes(self.mmap[epos + 16..epos + 24].try_into().unwrap())
+                                as usize;
+                        let size =
+                            u32::from_le_bytes(self.mmap[epos + 24..epos + 28].try_into().unwrap())
+                                as usize;
+                        return self.value_at(off, size).map(|_| (off, size));
                     }
                 }
                 _ => return None,
@@ -407,8 +520,98 @@ impl SegmentReader {
     /// Kayıt payload (crc hariç)
     pub fn value_at(&self, off: usize, size: usize) -> Option<&[u8]> {
         let end = off.checked_add(size)?;
-        if end < 4 || end > self.mmap.len() { return None; }
-        Some(&self.mmap[off..end-4])
+        if size < 4 || end > self.mmap.len() {
+            return None;
+        }
+        Some(&self.mmap[off..end - 4])
+    }
+
+    /// Whether an entry's value lies inside the file and matches its checksum.
+    pub fn entry_ok(&self, e: &IndexEntry) -> bool {
+        let (Ok(off), size) = (usize::try_from(e.off), e.size as usize) else {
+            return false;
+        };
+        off.checked_add(size).is_some() && self.verify_crc_at(off, size)
+    }
+
+    /// Copy the entries that pass [`Self::entry_ok`] into a new segment of the
+    /// same kind at `dst` and return how many were kept and dropped. Keys are
+    /// not stored, so the copy is written by hash with a V1 index, as
+    /// compaction does.
+    pub fn salvage_to(&self, dst: impl AsRef<Path>) -> Result<(usize, usize)> {
+        let mut w = SegmentWriter::create(dst, self.kind, 1 << 20, 7)?;
+        w.set_index_kind(INDEX_KIND_HASHTAB_V1);
+        let (mut kept, mut dropped) = (0, 0);
+        for e in self.iter() {
+            match self.value_at(e.off as usize, e.size as usize) {
+                Some(value) if self.entry_ok(&e) => {
+                    w.add_hashed(e.hash, value)?;
+                    kept += 1;
+                }
+                _ => dropped += 1,
+            }
+        }
+        w.finalize()?;
+        Ok((kept, dropped))
+    }
+
+    /// Check every index entry's bounds, checksum and filter membership
+    /// without decoding values. Blocks running past the end of the file are
+    /// [`PruError::Corrupt`].
+    pub fn verify(&self) -> Result<SegmentCheck> {
+        if !self.blocks_in_bounds() {
+            return Err(PruError::Corrupt);
+        }
+        let mut check = SegmentCheck::default();
+        for e in self.iter() {
+            check.entries += 1;
+            let end = (e.off as usize).saturating_add(e.size as usize);
+            if end > self.mmap.len() || e.size < 4 {
+                check.bad_bounds += 1;
+                continue;
+            }
+            if !self.verify_crc_at(e.off as usize, e.size as usize) {
+                check.bad_crc += 1;
+            }
+            if self.filter_contains_digest(e.hash) == Some(false) {
+                check.filter_miss += 1;
+            }
+        }
+        Ok(check)
+    }
+
+    /// Header and block offsets as written, plus index and filter sizes.
+    pub fn layout(&self) -> SegmentLayout {
+        let u64_at = |pos: usize| {
+            self.mmap
+                .get(pos..pos + 8)
+                .map_or(0, |b| u64::from_le_bytes(b.try_into().unwrap()))
+        };
+        let u32_at = |pos: usize| {
+            self.mmap
+                .get(pos..pos + 4)
+                .map_or(0, |b| u32::from_le_bytes(b.try_into().unwrap()))
+        };
+        let filter_off = self.bloom_off as usize;
+        let meta = self.index_meta();
+        SegmentLayout {
+            version: u16::from_le_bytes(self.mmap[4..6].try_into().unwrap()),
+            kind: self.kind,
+            file_len: self.mmap.len() as u64,
+            data_off: u64_at(28),
+            index_off: self.index_off,
+            index_kind: meta.map(|(kind, _)| kind),
+            index_capacity: meta.map(|(_, cap)| cap),
+            entries: self.iter().count(),
+            filter_off: self.bloom_off,
+            filter: if u32_at(filter_off) == FILTER_TAG_XOR8 {
+                "xor8"
+            } else {
+                "bloom"
+            },
+            filter_bytes: u32_at(filter_off + 4) as u64,
+            footer_off: u64_at(36),
+        }
     }
 
     /// İndeks üzerinde dolaşan iterator (V1/V2 farklarını soyutlar).
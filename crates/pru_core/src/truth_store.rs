@@ -1,11 +1,12 @@
 use crate::atoms::{AtomId, EntityId, LiteralId, PredicateId};
+use crate::config::{Durability, StoreConfig};
 use crate::errors::{PruError, Result};
 use crate::manifest::Manifest;
 use crate::resolver_store::ResolverStore;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap};
 use std::fs::{self, File};
-use std::io::{BufReader, BufWriter};
+use std::io::{BufReader, BufWriter, Write};
 use std::path::{Path, PathBuf};
 
 /// Minimal fact representation stored by the high-level API.
@@ -20,6 +21,13 @@ pub struct Fact {
     pub confidence: Option<f32>,
 }
 
+impl Fact {
+    /// Whether `id` is the subject, predicate, object or source of this fact.
+    pub fn mentions(&self, id: AtomId) -> bool {
+        self.subject == id || self.predicate == id || self.object == id || self.source == Some(id)
+    }
+}
+
 fn default_confidence() -> Option<f32> {
     Some(1.0)
 }
@@ -60,6 +68,33 @@ struct FactLog {
     facts: Vec<Fact>,
 }
 
+/// Positions in the fact log by subject and by predicate/object pair, so
+/// lookups on either do not scan the log. Appends extend it; anything that
+/// removes or edits facts rebuilds it.
+#[derive(Debug, Default)]
+struct FactIndex {
+    by_subject: HashMap<EntityId, Vec<usize>>,
+    by_predicate_object: HashMap<(PredicateId, AtomId), Vec<usize>>,
+}
+
+impl FactIndex {
+    fn build(facts: &[Fact]) -> Self {
+        let mut index = Self::default();
+        for (pos, fact) in facts.iter().enumerate() {
+            index.insert(pos, fact);
+        }
+        index
+    }
+
+    fn insert(&mut self, pos: usize, fact: &Fact) {
+        self.by_subject.entry(fact.subject).or_default().push(pos);
+        self.by_predicate_object
+            .entry((fact.predicate, fact.object))
+            .or_default()
+            .push(pos);
+    }
+}
+
 /// A high-level store facade that keeps atom dictionaries and simple fact logs on disk.
 ///
 /// The store is intentionally small and ergonomic while remaining compatible with the
@@ -68,8 +103,12 @@ pub struct PruStore {
     dir: PathBuf,
     atoms: AtomTables,
     facts: FactLog,
+    index: FactIndex,
     manifest: Manifest,
     resolver_store: Option<ResolverStore>,
+    in_transaction: bool,
+    /// Atoms interned inside a transaction, not yet written.
+    atoms_dirty: bool,
 }
 
 impl PruStore {
@@ -83,13 +122,17 @@ impl PruStore {
 
         let atoms = Self::load_atoms(&dir)?;
         let facts = Self::load_facts(&dir)?;
+        let index = FactIndex::build(&facts.facts);
 
         Ok(Self {
             dir,
             atoms,
             facts,
+            index,
             manifest,
             resolver_store,
+            in_transaction: false,
+            atoms_dirty: false,
         })
     }
 
@@ -98,11 +141,44 @@ impl PruStore {
         &self.manifest
     }
 
+    /// The store's defaults, as recorded in the manifest by `pru init`.
+    pub fn config(&self) -> &StoreConfig {
+        &self.manifest.config
+    }
+
     /// Access the resolver store if resolver segments are present.
     pub fn resolver_store(&self) -> Option<&ResolverStore> {
         self.resolver_store.as_ref()
     }
 
+    /// The directory the store lives in.
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// The file the fact log is kept in; it is replaced as a whole on every write.
+    pub fn fact_log_path(&self) -> PathBuf {
+        Self::facts_path(&self.dir)
+    }
+
+    /// The files a write to the store at `dir` replaces: atoms, fact log and
+    /// manifest.
+    pub fn data_paths(dir: &Path) -> [PathBuf; 3] {
+        [
+            Self::atoms_path(dir),
+            Self::facts_path(dir),
+            Manifest::path(dir),
+        ]
+    }
+
+    /// Save `manifest` and reopen the resolver segments it lists.
+    pub(crate) fn replace_manifest(&mut self, manifest: Manifest) -> Result<()> {
+        manifest.save_atomic(&self.dir)?;
+        self.manifest = manifest;
+        self.resolver_store = ResolverStore::open(&self.dir).ok();
+        Ok(())
+    }
+
     /// Insert or return an existing entity by name.
     pub fn intern_entity(&mut self, name: &str) -> Result<EntityId> {
         self.ensure_non_empty(name, "entity name")?;
@@ -175,6 +251,59 @@ impl PruStore {
         out
     }
 
+    /// Literals whose value contains `needle`, sorted by id.
+    pub fn search_literals(&self, needle: &str) -> Vec<(LiteralId, String)> {
+        self.literals_where(|value| value.contains(needle))
+    }
+
+    /// Atoms of `kind` whose name contains `needle`, ignoring case: names that
+    /// start with it come first, each group sorted by id, `limit` in all.
+    pub fn search_atoms(
+        &self,
+        kind: AtomKind,
+        needle: &str,
+        limit: usize,
+    ) -> Vec<(AtomId, String)> {
+        let needle = needle.to_lowercase();
+        let (mut prefix, mut inner) = (vec![], vec![]);
+        for (id, name) in self.atoms_of(kind) {
+            let lower = name.to_lowercase();
+            if lower.starts_with(&needle) {
+                prefix.push((*id, name.clone()));
+            } else if lower.contains(&needle) {
+                inner.push((*id, name.clone()));
+            }
+        }
+        prefix.sort_by_key(|(id, _)| *id);
+        inner.sort_by_key(|(id, _)| *id);
+        prefix.into_iter().chain(inner).take(limit).collect()
+    }
+
+    /// Entities and literals whose name contains `needle`, ignoring case: the
+    /// atoms a fact's object must be for its rendered value to contain it.
+    pub fn objects_containing(&self, needle: &str) -> BTreeSet<AtomId> {
+        let needle = needle.to_lowercase();
+        [AtomKind::Entity, AtomKind::Literal]
+            .into_iter()
+            .flat_map(|kind| self.atoms_of(kind))
+            .filter(|(_, name)| name.to_lowercase().contains(&needle))
+            .map(|(id, _)| *id)
+            .collect()
+    }
+
+    /// Literals whose value satisfies `matches`, sorted by id.
+    pub fn literals_where(&self, matches: impl Fn(&str) -> bool) -> Vec<(LiteralId, String)> {
+        let mut out: Vec<(LiteralId, String)> = self
+            .atoms
+            .literals
+            .iter()
+            .filter(|(_, v)| matches(v))
+            .map(|(k, v)| (*k, v.clone()))
+            .collect();
+        out.sort_by_key(|(id, _)| *id);
+        out
+    }
+
     /// Look up an entity name by id.
     pub fn get_entity_name(&self, id: EntityId) -> Option<String> {
         self.atoms.entities.get(&id).cloned()
@@ -217,6 +346,92 @@ impl PruStore {
             .map(|(id, _)| *id)
     }
 
+    fn atom_table(&mut self, kind: AtomKind) -> &mut HashMap<AtomId, String> {
+        match kind {
+            AtomKind::Entity => &mut self.atoms.entities,
+            AtomKind::Predicate => &mut self.atoms.predicates,
+            AtomKind::Literal => &mut self.atoms.literals,
+        }
+    }
+
+    fn atoms_of(&self, kind: AtomKind) -> &HashMap<AtomId, String> {
+        match kind {
+            AtomKind::Entity => &self.atoms.entities,
+            AtomKind::Predicate => &self.atoms.predicates,
+            AtomKind::Literal => &self.atoms.literals,
+        }
+    }
+
+    fn find_atom(&self, kind: AtomKind, name: &str) -> Result<AtomId> {
+        self.atoms_of(kind)
+            .iter()
+            .find(|(_, v)| v.as_str() == name)
+            .map(|(id, _)| *id)
+            .ok_or_else(|| PruError::AtomNotFound(format!("{kind} {name}")))
+    }
+
+    /// Number of facts that mention `id` as subject, predicate, object or source.
+    pub fn fact_references(&self, id: AtomId) -> usize {
+        self.facts.facts.iter().filter(|f| f.mentions(id)).count()
+    }
+
+    /// [`Self::fact_references`] for each of `ids`, in one pass over the log.
+    pub fn fact_reference_counts(&self, ids: &[AtomId]) -> HashMap<AtomId, usize> {
+        let mut counts: HashMap<AtomId, usize> = ids.iter().map(|&id| (id, 0)).collect();
+        for fact in &self.facts.facts {
+            let mut atoms = vec![fact.subject, fact.predicate, fact.object];
+            atoms.extend(fact.source);
+            atoms.sort_unstable();
+            atoms.dedup();
+            for id in atoms {
+                if let Some(n) = counts.get_mut(&id) {
+                    *n += 1;
+                }
+            }
+        }
+        counts
+    }
+
+    /// Give the atom named `from` the name `to`. Facts refer to atoms by id, so
+    /// they follow the new name. Fails if `to` is already taken in that dictionary.
+    pub fn rename_atom(&mut self, kind: AtomKind, from: &str, to: &str) -> Result<AtomId> {
+        self.ensure_non_empty(to, &format!("{kind} name"))?;
+        let id = self.find_atom(kind, from)?;
+        if from == to {
+            return Ok(id);
+        }
+        if self.find_atom(kind, to).is_ok() {
+            return Err(PruError::InvalidInput(format!(
+                "{kind} {to} already exists"
+            )));
+        }
+        self.atom_table(kind).insert(id, to.to_string());
+        self.persist_atoms()?;
+        Ok(id)
+    }
+
+    /// Remove the atom named `name` and return its id with the number of facts
+    /// retracted along with it. Facts that still reference the atom are only
+    /// retracted with `cascade`; otherwise nothing changes and an error is returned.
+    pub fn delete_atom(
+        &mut self,
+        kind: AtomKind,
+        name: &str,
+        cascade: bool,
+    ) -> Result<(AtomId, usize)> {
+        let id = self.find_atom(kind, name)?;
+        let references = self.fact_references(id);
+        if references > 0 && !cascade {
+            return Err(PruError::InvalidInput(format!(
+                "{kind} {name} is referenced by {references} facts"
+            )));
+        }
+        let removed = self.retract_facts(|f| f.mentions(id))?;
+        self.atom_table(kind).remove(&id);
+        self.persist_atoms()?;
+        Ok((id, removed.len()))
+    }
+
     /// Append a fact to the local fact log.
     pub fn add_fact(&mut self, fact: Fact) -> Result<()> {
         let mut fact = fact;
@@ -228,10 +443,98 @@ impl PruStore {
             fact.confidence = default_confidence();
         }
 
+        self.index.insert(self.facts.facts.len(), &fact);
         self.facts.facts.push(fact);
         self.persist_facts()
     }
 
+    /// Like [`add_fact`](Self::add_fact), but skips the write when a fact with the
+    /// same subject, predicate, object and source is already stored. Returns whether
+    /// the fact was added.
+    pub fn add_fact_unique(&mut self, fact: Fact) -> Result<bool> {
+        let exists = self
+            .index
+            .by_subject
+            .get(&fact.subject)
+            .into_iter()
+            .flatten()
+            .map(|&pos| &self.facts.facts[pos])
+            .any(|f| {
+                f.predicate == fact.predicate && f.object == fact.object && f.source == fact.source
+            });
+        if exists {
+            return Ok(false);
+        }
+        self.add_fact(fact)?;
+        Ok(true)
+    }
+
+    /// Remove every fact matching `pred` and return the removed facts.
+    pub fn retract_facts(&mut self, pred: impl Fn(&Fact) -> bool) -> Result<Vec<Fact>> {
+        let (removed, kept): (Vec<Fact>, Vec<Fact>) =
+            self.facts.facts.drain(..).partition(|f| pred(f));
+        self.facts.facts = kept;
+        if !removed.is_empty() {
+            self.index = FactIndex::build(&self.facts.facts);
+            self.persist_facts()?;
+        }
+        Ok(removed)
+    }
+
+    /// Edit facts in place; `edit` returns `true` when it changed the fact.
+    /// Returns the number of changed facts.
+    pub fn rewrite_facts(&mut self, mut edit: impl FnMut(&mut Fact) -> bool) -> Result<usize> {
+        let mut changed = 0;
+        for fact in self.facts.facts.iter_mut() {
+            if edit(fact) {
+                changed += 1;
+            }
+        }
+        if changed > 0 {
+            self.index = FactIndex::build(&self.facts.facts);
+            self.persist_facts()?;
+        }
+        Ok(changed)
+    }
+
+    /// Run `f` with fact-log and atom writes deferred until it returns.
+    ///
+    /// On success the fact log is written once; on error the in-memory facts are
+    /// rolled back and the fact log is not written. Interned atoms are kept, and
+    /// written once, either way.
+    pub fn transaction<T, E>(
+        &mut self,
+        f: impl FnOnce(&mut Self) -> std::result::Result<T, E>,
+    ) -> std::result::Result<T, E>
+    where
+        E: From<PruError>,
+    {
+        if self.in_transaction {
+            return f(self);
+        }
+        let snapshot = self.facts.clone();
+        self.in_transaction = true;
+        let result = f(self);
+        self.in_transaction = false;
+        let atoms = if self.atoms_dirty {
+            self.persist_atoms()
+        } else {
+            Ok(())
+        };
+        match result {
+            Ok(value) => {
+                atoms?;
+                self.persist_facts()?;
+                Ok(value)
+            }
+            Err(err) => {
+                self.facts = snapshot;
+                self.index = FactIndex::build(&self.facts.facts);
+                Err(err)
+            }
+        }
+    }
+
     /// Return number of stored facts.
     pub fn fact_count(&self) -> usize {
         self.facts.facts.len()
@@ -239,13 +542,10 @@ impl PruStore {
 
     /// Return all facts for a subject.
     pub fn facts_for_subject(&self, subj: EntityId) -> Result<Vec<Fact>> {
-        Ok(self
-            .facts
-            .facts
-            .iter()
-            .filter(|f| f.subject == subj)
-            .cloned()
-            .collect())
+        self.query(Query {
+            subject: Some(subj),
+            ..Default::default()
+        })
     }
 
     /// Return all facts for a subject and predicate pair.
@@ -254,39 +554,50 @@ impl PruStore {
         subj: EntityId,
         pred: PredicateId,
     ) -> Result<Vec<Fact>> {
-        Ok(self
-            .facts
-            .facts
-            .iter()
-            .filter(|f| f.subject == subj && f.predicate == pred)
-            .cloned()
-            .collect())
+        self.query(Query {
+            subject: Some(subj),
+            predicate: Some(pred),
+            ..Default::default()
+        })
     }
 
     /// Query facts using optional filters.
     pub fn query(&self, q: Query) -> Result<Vec<Fact>> {
-        Ok(self
-            .facts
-            .facts
-            .iter()
-            .filter(|f| match q.subject {
-                Some(s) => f.subject == s,
-                None => true,
-            })
-            .filter(|f| match q.predicate {
-                Some(p) => f.predicate == p,
-                None => true,
-            })
-            .filter(|f| match q.object {
-                Some(o) => f.object == o,
-                None => true,
-            })
-            .filter(|f| match q.min_confidence {
-                Some(min) => f.confidence.unwrap_or(1.0) >= min,
-                None => true,
-            })
-            .cloned()
-            .collect())
+        Ok(self.query_iter(q).cloned().collect())
+    }
+
+    /// The facts matching `q` without copying them, in insertion order unless
+    /// `q.sort` is set, after `q.offset` and up to `q.limit`. A subject, or a
+    /// predicate with an object, is looked up in the fact index instead of
+    /// scanning the log.
+    pub fn query_iter(&self, q: Query) -> Box<dyn Iterator<Item = &Fact> + '_> {
+        let (offset, limit) = (q.offset, q.limit.unwrap_or(usize::MAX));
+        let sort = q.sort.map(|sort| (sort, q.descending));
+        let positions = match (q.subject, q.predicate, q.object) {
+            (Some(s), _, _) => Some(self.index.by_subject.get(&s)),
+            (None, Some(p), Some(o)) => Some(self.index.by_predicate_object.get(&(p, o))),
+            _ => None,
+        };
+        let facts = &self.facts.facts;
+        let candidates: Box<dyn Iterator<Item = &Fact>> = match positions {
+            Some(positions) => Box::new(positions.into_iter().flatten().map(move |&i| &facts[i])),
+            None => Box::new(facts.iter()),
+        };
+        let matched = candidates.filter(move |f| q.matches(f));
+        let Some((sort, descending)) = sort else {
+            return Box::new(matched.skip(offset).take(limit));
+        };
+        let mut sorted: Vec<&Fact> = matched.collect();
+        // Stable, so ties keep insertion order either way.
+        sorted.sort_by(|a, b| {
+            let ord = sort.compare(a, b);
+            if descending {
+                ord.reverse()
+            } else {
+                ord
+            }
+        });
+        Box::new(sorted.into_iter().skip(offset).take(limit))
     }
 
     fn ensure_non_empty(&self, value: &str, what: &str) -> Result<()> {
@@ -350,32 +661,124 @@ impl PruStore {
         Ok(serde_json::from_reader(reader)?)
     }
 
-    fn persist_atoms(&self) -> Result<()> {
-        let path = Self::atoms_path(&self.dir);
+    /// Write `value` next to `path` and rename it into place, syncing as far
+    /// as the configured durability asks.
+    fn replace_json(&self, path: &Path, value: &impl Serialize) -> Result<()> {
         let tmp = path.with_extension("json.tmp");
-        let writer = BufWriter::new(File::create(&tmp)?);
-        serde_json::to_writer_pretty(writer, &self.atoms)?;
-        fs::rename(&tmp, &path)?;
+        let mut writer = BufWriter::new(File::create(&tmp)?);
+        serde_json::to_writer_pretty(&mut writer, value)?;
+        match self.config().durability {
+            Durability::None => {}
+            Durability::Flush => writer.flush()?,
+            Durability::Fsync => writer
+                .into_inner()
+                .map_err(|e| e.into_error())?
+                .sync_all()?,
+        }
+        fs::rename(&tmp, path)?;
         Ok(())
     }
 
+    fn persist_atoms(&mut self) -> Result<()> {
+        self.atoms_dirty = self.in_transaction;
+        if self.in_transaction {
+            return Ok(());
+        }
+        self.replace_json(&Self::atoms_path(&self.dir), &self.atoms)
+    }
+
     fn persist_facts(&self) -> Result<()> {
-        let path = Self::facts_path(&self.dir);
-        let tmp = path.with_extension("json.tmp");
-        let writer = BufWriter::new(File::create(&tmp)?);
-        serde_json::to_writer_pretty(writer, &self.facts)?;
-        fs::rename(&tmp, &path)?;
-        Ok(())
+        if self.in_transaction {
+            return Ok(());
+        }
+        self.replace_json(&Self::facts_path(&self.dir), &self.facts)
+    }
+}
+
+/// The dictionary an atom belongs to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AtomKind {
+    Entity,
+    Predicate,
+    Literal,
+}
+
+impl std::fmt::Display for AtomKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.pad(match self {
+            AtomKind::Entity => "entity",
+            AtomKind::Predicate => "predicate",
+            AtomKind::Literal => "literal",
+        })
     }
 }
 
 /// Simple in-memory query filter for facts.
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
 pub struct Query {
     pub subject: Option<EntityId>,
     pub predicate: Option<PredicateId>,
     pub object: Option<AtomId>,
+    /// Only facts whose object is one of these, e.g. the atoms found by
+    /// [`PruStore::objects_containing`].
+    pub objects: Option<BTreeSet<AtomId>>,
     pub min_confidence: Option<f32>,
+    pub source: Option<u64>,
+    /// Earliest timestamp, inclusive; facts without one are left out.
+    pub since: Option<i64>,
+    /// Latest timestamp, inclusive; facts without one are left out.
+    pub until: Option<i64>,
+    pub sort: Option<QuerySort>,
+    pub descending: bool,
+    pub offset: usize,
+    pub limit: Option<usize>,
+}
+
+impl Query {
+    /// Whether `f` passes the filters; sorting and paging are left to the caller.
+    pub fn matches(&self, f: &Fact) -> bool {
+        self.subject.is_none_or(|s| f.subject == s)
+            && self.predicate.is_none_or(|p| f.predicate == p)
+            && self.object.is_none_or(|o| f.object == o)
+            && self
+                .objects
+                .as_ref()
+                .is_none_or(|ids| ids.contains(&f.object))
+            && self
+                .min_confidence
+                .is_none_or(|min| f.confidence.unwrap_or(1.0) >= min)
+            && self.source.is_none_or(|s| f.source == Some(s))
+            && self
+                .since
+                .is_none_or(|t| f.timestamp.is_some_and(|ts| ts >= t))
+            && self
+                .until
+                .is_none_or(|t| f.timestamp.is_some_and(|ts| ts <= t))
+    }
+}
+
+/// Fact order for [`Query::sort`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum QuerySort {
+    /// Facts without a timestamp sort first.
+    Timestamp,
+    /// A missing confidence counts as 1.0, as in `min_confidence`.
+    Confidence,
+}
+
+impl QuerySort {
+    fn compare(self, a: &Fact, b: &Fact) -> std::cmp::Ordering {
+        match self {
+            QuerySort::Timestamp => a.timestamp.cmp(&b.timestamp),
+            QuerySort::Confidence => a
+                .confidence
+                .unwrap_or(1.0)
+                .total_cmp(&b.confidence.unwrap_or(1.0)),
+        }
+    }
 }
 
 #[cfg(test)]
@@ -410,4 +813,274 @@ mod tests {
         assert_eq!(filtered.len(), 1);
         assert_eq!(filtered[0], fact);
     }
+
+    #[test]
+    fn add_fact_unique_skips_duplicates() {
+        let tmp = tempdir().unwrap();
+        let mut store = PruStore::open(tmp.path()).unwrap();
+        let earth = store.intern_entity("Earth").unwrap();
+        let moon = store.intern_entity("Moon").unwrap();
+        let orbits = store.intern_predicate("orbits").unwrap();
+        let fact = Fact {
+            subject: moon,
+            predicate: orbits,
+            object: earth,
+            source: None,
+            timestamp: Some(1),
+            confidence: None,
+        };
+
+        assert!(store.add_fact_unique(fact.clone()).unwrap());
+        let later = Fact {
+            timestamp: Some(2),
+            ..fact.clone()
+        };
+        assert!(!store.add_fact_unique(later).unwrap());
+        let sourced = Fact {
+            source: Some(7),
+            ..fact
+        };
+        assert!(store.add_fact_unique(sourced).unwrap());
+        assert_eq!(store.fact_count(), 2);
+    }
+
+    #[test]
+    fn indexed_lookups_follow_retracts_and_rollbacks() {
+        let tmp = tempdir().unwrap();
+        let mut store = PruStore::open(tmp.path()).unwrap();
+        let earth = store.intern_entity("Earth").unwrap();
+        let moon = store.intern_entity("Moon").unwrap();
+        let mars = store.intern_entity("Mars").unwrap();
+        let orbits = store.intern_predicate("orbits").unwrap();
+        let fact = |subject| Fact {
+            subject,
+            predicate: orbits,
+            object: earth,
+            source: None,
+            timestamp: None,
+            confidence: None,
+        };
+        store.add_fact(fact(moon)).unwrap();
+        store.add_fact(fact(mars)).unwrap();
+        let orbiting_earth = |store: &PruStore| -> Vec<EntityId> {
+            store
+                .query_iter(Query {
+                    predicate: Some(orbits),
+                    object: Some(earth),
+                    ..Default::default()
+                })
+                .map(|f| f.subject)
+                .collect()
+        };
+        assert_eq!(orbiting_earth(&store), [moon, mars]);
+
+        store.retract_facts(|f| f.subject == moon).unwrap();
+        assert_eq!(orbiting_earth(&store), [mars]);
+        assert_eq!(store.facts_for_subject(mars).unwrap().len(), 1);
+
+        let failed: Result<()> = store.transaction(|store| {
+            store.add_fact(fact(moon))?;
+            Err(PruError::InvalidInput("abort".into()))
+        });
+        assert!(failed.is_err());
+        assert_eq!(orbiting_earth(&store), [mars]);
+        assert!(store.facts_for_subject(moon).unwrap().is_empty());
+
+        let reopened = PruStore::open(tmp.path()).unwrap();
+        assert_eq!(orbiting_earth(&reopened), [mars]);
+    }
+
+    #[test]
+    fn retract_removes_matching_facts() {
+        let tmp = tempdir().unwrap();
+        let mut store = PruStore::open(tmp.path()).unwrap();
+        let earth = store.intern_entity("Earth").unwrap();
+        let moon = store.intern_entity("Moon").unwrap();
+        let orbits = store.intern_predicate("orbits").unwrap();
+        for subject in [earth, moon] {
+            store
+                .add_fact(Fact {
+                    subject,
+                    predicate: orbits,
+                    object: earth,
+                    source: None,
+                    timestamp: None,
+                    confidence: None,
+                })
+                .unwrap();
+        }
+
+        let removed = store.retract_facts(|f| f.subject == moon).unwrap();
+        assert_eq!(removed.len(), 1);
+        assert_eq!(store.fact_count(), 1);
+
+        let reopened = PruStore::open(tmp.path()).unwrap();
+        assert_eq!(reopened.fact_count(), 1);
+        assert!(reopened.facts_for_subject(moon).unwrap().is_empty());
+    }
+
+    #[test]
+    fn failed_transaction_rolls_back() {
+        let tmp = tempdir().unwrap();
+        let mut store = PruStore::open(tmp.path()).unwrap();
+        let earth = store.intern_entity("Earth").unwrap();
+        let orbits = store.intern_predicate("orbits").unwrap();
+        let fact = Fact {
+            subject: earth,
+            predicate: orbits,
+            object: earth,
+            source: None,
+            timestamp: None,
+            confidence: None,
+        };
+
+        let result: Result<()> = store.transaction(|s| {
+            s.add_fact(fact.clone())?;
+            Err(PruError::InvalidInput("abort".into()))
+        });
+        assert!(result.is_err());
+        assert_eq!(store.fact_count(), 0);
+
+        store
+            .transaction(|s| -> Result<()> {
+                s.add_fact(fact.clone())?;
+                s.rewrite_facts(|f| {
+                    f.timestamp = Some(1);
+                    true
+                })?;
+                Ok(())
+            })
+            .unwrap();
+        let reopened = PruStore::open(tmp.path()).unwrap();
+        assert_eq!(
+            reopened.facts_for_subject(earth).unwrap()[0].timestamp,
+            Some(1)
+        );
+
+        let result: Result<()> = store.transaction(|s| {
+            s.intern_entity("Mars")?;
+            Err(PruError::InvalidInput("abort".into()))
+        });
+        assert!(result.is_err());
+        let reopened = PruStore::open(tmp.path()).unwrap();
+        assert!(reopened.get_entity_id("Mars").is_some());
+    }
+
+    #[test]
+    fn search_atoms_ranks_prefix_matches_first() {
+        let tmp = tempdir().unwrap();
+        let mut store = PruStore::open(tmp.path()).unwrap();
+        let moonbase = store.intern_entity("Moonbase").unwrap();
+        let blue_moon = store.intern_entity("Blue Moon").unwrap();
+        let moon = store.intern_entity("moon").unwrap();
+        store.intern_entity("Earth").unwrap();
+        store.intern_predicate("moon_phase").unwrap();
+
+        let names =
+            |hits: Vec<(AtomId, String)>| hits.into_iter().map(|(id, _)| id).collect::<Vec<_>>();
+        assert_eq!(
+            names(store.search_atoms(AtomKind::Entity, "MOON", 10)),
+            vec![moonbase, moon, blue_moon]
+        );
+        assert_eq!(
+            names(store.search_atoms(AtomKind::Entity, "moon", 2)),
+            vec![moonbase, moon]
+        );
+        assert_eq!(
+            store.search_atoms(AtomKind::Predicate, "phase", 10).len(),
+            1
+        );
+        assert!(store.search_atoms(AtomKind::Literal, "moon", 10).is_empty());
+    }
+
+    #[test]
+    fn objects_containing_filters_facts_by_object_text() {
+        let tmp = tempdir().unwrap();
+        let mut store = PruStore::open(tmp.path()).unwrap();
+        let earth = store.intern_entity("Earth").unwrap();
+        let moon = store.intern_entity("Moon").unwrap();
+        let orbits = store.intern_predicate("orbits").unwrap();
+        let nick = store.intern_predicate("nickname").unwrap();
+        let blue = store.intern_literal("the blue MOONlet").unwrap();
+        let pale = store.intern_literal("pale dot").unwrap();
+        for (subject, predicate, object) in [
+            (moon, orbits, earth),
+            (earth, nick, pale),
+            (earth, nick, blue),
+            (earth, orbits, moon),
+        ] {
+            store
+                .add_fact(Fact {
+                    subject,
+                    predicate,
+                    object,
+                    source: None,
+                    timestamp: None,
+                    confidence: None,
+                })
+                .unwrap();
+        }
+
+        let ids = store.objects_containing("moon");
+        assert_eq!(ids, BTreeSet::from([moon, blue]));
+        let facts = store
+            .query(Query {
+                subject: Some(earth),
+                objects: Some(ids),
+                ..Default::default()
+            })
+            .unwrap();
+        let objects: Vec<AtomId> = facts.iter().map(|f| f.object).collect();
+        assert_eq!(objects, vec![blue, moon]);
+        assert!(store.objects_containing("orbit").is_empty());
+    }
+
+    #[test]
+    fn query_sorts_pages_and_filters_by_time_and_source() {
+        let tmp = tempdir().unwrap();
+        let mut store = PruStore::open(tmp.path()).unwrap();
+        let earth = store.intern_entity("Earth").unwrap();
+        let seen = store.intern_predicate("seen").unwrap();
+        for (ts, confidence, source) in [(30, 0.2, 1), (10, 0.9, 2), (20, 0.5, 1)] {
+            store
+                .add_fact(Fact {
+                    subject: earth,
+                    predicate: seen,
+                    object: earth,
+                    source: Some(source),
+                    timestamp: Some(ts),
+                    confidence: Some(confidence),
+                })
+                .unwrap();
+        }
+        let timestamps =
+            |q: Query| -> Vec<i64> { store.query_iter(q).filter_map(|f| f.timestamp).collect() };
+
+        assert_eq!(timestamps(Query::default()), vec![30, 10, 20]);
+        let by_time = Query {
+            sort: Some(QuerySort::Timestamp),
+            ..Default::default()
+        };
+        assert_eq!(timestamps(by_time.clone()), vec![10, 20, 30]);
+        let page = Query {
+            descending: true,
+            offset: 1,
+            limit: Some(1),
+            ..by_time
+        };
+        assert_eq!(timestamps(page), vec![20]);
+        let by_confidence = Query {
+            sort: Some(QuerySort::Confidence),
+            descending: true,
+            ..Default::default()
+        };
+        assert_eq!(timestamps(by_confidence), vec![10, 20, 30]);
+        let window = Query {
+            since: Some(15),
+            until: Some(30),
+            source: Some(1),
+            ..Default::default()
+        };
+        assert_eq!(timestamps(window), vec![30, 20]);
+    }
 }
@@ -0,0 +1,284 @@
+//! Error Level Analysis: how much an image changes when saved as JPEG again.
+//!
+//! An image that has already been through the same JPEG pipeline barely moves,
+//! and moves evenly; fresh camera output or spliced regions move more and
+//! unevenly. Non-JPEG inputs are analysed the same way on their decoded pixels.
+
+use anyhow::{Context, Result};
+use image::codecs::jpeg::JpegEncoder;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use crate::{config_hash, DetectorMediaKind, DetectorOutput, LabelPolicy, MediaDetector};
+use pru_media_schema::{DetectorInfo, FeatureValue};
+
+const BLOCK: u32 = 8;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ElaConfig {
+    /// JPEG quality used for the re-encode.
+    pub quality: u8,
+    /// 8x8 blocks whose mean absolute error is below this count as low-error.
+    pub low_error_threshold: f32,
+    /// Block-error coefficient of variation treated as fully uneven.
+    pub unevenness_norm: f32,
+    /// Scores above this are labelled AI.
+    pub ai_threshold: f32,
+    /// Scores below this are labelled human; the band in between is unknown.
+    pub human_threshold: f32,
+}
+
+impl Default for ElaConfig {
+    fn default() -> Self {
+        Self {
+            quality: 90,
+            low_error_threshold: 1.5,
+            unevenness_norm: 1.5,
+            ai_threshold: 0.6,
+            human_threshold: 0.4,
+        }
+    }
+}
+
+/// Error statistics for one image.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ElaStats {
+    /// Mean absolute per-channel difference, 0..255.
+    pub mean_error: f64,
+    /// Variance of the per-block mean errors.
+    pub block_variance: f64,
+    pub low_error_block_ratio: f64,
+}
+
+impl ElaStats {
+    fn block_cv(&self) -> f64 {
+        if self.mean_error <= f64::EPSILON {
+            0.0
+        } else {
+            self.block_variance.sqrt() / self.mean_error
+        }
+    }
+}
+
+/// Re-encode `img` at `quality` and measure the difference block by block.
+pub fn error_level(
+    img: &image::RgbImage,
+    quality: u8,
+    low_error_threshold: f32,
+) -> Result<ElaStats> {
+    let mut jpeg = Vec::new();
+    JpegEncoder::new_with_quality(&mut jpeg, quality)
+        .encode_image(img)
+        .context("re-encoding as JPEG")?;
+    let resaved = image::load_from_memory(&jpeg)
+        .context("decoding re-encoded JPEG")?
+        .to_rgb8();
+
+    let (width, height) = img.dimensions();
+    let blocks_x = width.div_ceil(BLOCK);
+    let blocks_y = height.div_ceil(BLOCK);
+    let mut sums = vec![0.0f64; (blocks_x * blocks_y) as usize];
+    let mut counts = vec![0u32; sums.len()];
+    let mut total = 0.0f64;
+    for (x, y, px) in img.enumerate_pixels() {
+        let other = resaved.get_pixel(x, y);
+        let err: f64 =
+            px.0.iter()
+                .zip(other.0)
+                .map(|(a, b)| (*a as f64 - b as f64).abs())
+                .sum::<f64>()
+                / 3.0;
+        let block = ((y / BLOCK) * blocks_x + x / BLOCK) as usize;
+        sums[block] += err;
+        counts[block] += 1;
+        total += err;
+    }
+    let block_means: Vec<f64> = sums
+        .iter()
+        .zip(&counts)
+        .filter(|(_, n)| **n > 0)
+        .map(|(sum, n)| sum / *n as f64)
+        .collect();
+    let pixels = (width as f64 * height as f64).max(1.0);
+    let mean_error = total / pixels;
+    let blocks = block_means.len().max(1) as f64;
+    let block_mean = block_means.iter().sum::<f64>() / blocks;
+    let block_variance = block_means
+        .iter()
+        .map(|m| (m - block_mean).powi(2))
+        .sum::<f64>()
+        / blocks;
+    let low_error_block_ratio = block_means
+        .iter()
+        .filter(|m| **m < low_error_threshold as f64)
+        .count() as f64
+        / blocks;
+    Ok(ElaStats {
+        mean_error,
+        block_variance,
+        low_error_block_ratio,
+    })
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct ElaDetector {
+    config: ElaConfig,
+}
+
+impl ElaDetector {
+    pub fn new(config: ElaConfig) -> Self {
+        Self { config }
+    }
+}
+
+impl MediaDetector for ElaDetector {
+    fn id(&self) -> String {
+        "detector:image:ela_v1".to_string()
+    }
+
+    fn kind(&self) -> DetectorMediaKind {
+        DetectorMediaKind::Image
+    }
+
+    fn info(&self) -> DetectorInfo {
+        DetectorInfo {
+            kind: "image".to_string(),
+            version: "v1".to_string(),
+            description: "JPEG error level analysis over 8x8 blocks".to_string(),
+            config_hash: config_hash(&self.config),
+        }
+    }
+
+    fn detect(&self, bytes: &[u8]) -> Result<DetectorOutput> {
+        let cfg = &self.config;
+        let format = image::guess_format(bytes)
+            .map(|f| format!("{f:?}").to_lowercase())
+            .unwrap_or_else(|_| "unknown".to_string());
+        let img = image::load_from_memory(bytes)
+            .context("decoding image")?
+            .to_rgb8();
+        let stats = error_level(&img, cfg.quality, cfg.low_error_threshold)?;
+
+        // Evenly low error reads as one uniform generation pass; uneven error as edits.
+        let evenness = 1.0 - (stats.block_cv() as f32 / cfg.unevenness_norm).clamp(0.0, 1.0);
+        let score_ai = (stats.low_error_block_ratio as f32 * 0.6 + evenness * 0.4).clamp(0.0, 1.0);
+        let label = LabelPolicy::new(cfg.ai_threshold, cfg.human_threshold).label(score_ai);
+        let features = BTreeMap::from([
+            (
+                "ela_mean_error".to_string(),
+                FeatureValue::F64(stats.mean_error),
+            ),
+            (
+                "ela_block_variance".to_string(),
+                FeatureValue::F64(stats.block_variance),
+            ),
+            (
+                "ela_low_error_ratio".to_string(),
+                FeatureValue::F64(stats.low_error_block_ratio),
+            ),
+            (
+                "source_format".to_string(),
+                FeatureValue::Str(format.clone()),
+            ),
+        ]);
+        let note = if format == "jpeg" {
+            String::new()
+        } else {
+            format!(" (source {format}, analysed decoded pixels)")
+        };
+        Ok(DetectorOutput {
+            score_ai,
+            label,
+            details: Some(format!(
+                "ela mean {:.2}, block variance {:.2}, low-error blocks {:.2}{note}",
+                stats.mean_error, stats.block_variance, stats.low_error_block_ratio
+            )),
+            features,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DetectorLabel;
+
+    /// Textured stand-in for a photo: gradients plus pseudo-random sensor noise.
+    fn photo() -> image::RgbImage {
+        let mut seed = 0x2545_F491u32;
+        image::RgbImage::from_fn(96, 96, |x, y| {
+            seed ^= seed << 13;
+            seed ^= seed >> 17;
+            seed ^= seed << 5;
+            let noise = (seed % 40) as u8;
+            image::Rgb([
+                (x * 2) as u8 + noise,
+                (y * 2) as u8 + noise / 2,
+                ((x + y) as u8).wrapping_mul(3) / 2 + noise,
+            ])
+        })
+    }
+
+    fn encode(img: &image::RgbImage, format: image::ImageFormat) -> Vec<u8> {
+        let mut buf = Vec::new();
+        image::DynamicImage::ImageRgb8(img.clone())
+            .write_to(&mut std::io::Cursor::new(&mut buf), format)
+            .unwrap();
+        buf
+    }
+
+    fn recompress(img: &image::RgbImage, quality: u8, rounds: usize) -> Vec<u8> {
+        let mut current = img.clone();
+        let mut jpeg = Vec::new();
+        for _ in 0..rounds {
+            jpeg.clear();
+            JpegEncoder::new_with_quality(&mut jpeg, quality)
+                .encode_image(&current)
+                .unwrap();
+            current = image::load_from_memory(&jpeg).unwrap().to_rgb8();
+        }
+        jpeg
+    }
+
+    #[test]
+    fn recompressed_image_has_lower_error_than_original() {
+        let detector = ElaDetector::default();
+        let untouched = detector
+            .detect(&encode(&photo(), image::ImageFormat::Png))
+            .unwrap();
+        let recompressed = detector.detect(&recompress(&photo(), 90, 6)).unwrap();
+
+        let mean = |out: &DetectorOutput| match out.features.get("ela_mean_error") {
+            Some(FeatureValue::F64(v)) => *v,
+            other => panic!("missing ela_mean_error: {other:?}"),
+        };
+        assert!(mean(&recompressed) < mean(&untouched));
+        assert!(recompressed.score_ai > untouched.score_ai);
+        assert_eq!(
+            untouched.features.get("source_format"),
+            Some(&FeatureValue::Str("png".into()))
+        );
+        assert!(untouched.details.unwrap().contains("source png"));
+        assert!(!recompressed.details.unwrap().contains("source"));
+    }
+
+    #[test]
+    fn undecodable_input_is_an_error() {
+        assert!(ElaDetector::default()
+            .detect(b"definitely not pixels")
+            .is_err());
+    }
+
+    #[test]
+    fn scores_between_the_thresholds_are_unknown() {
+        let png = encode(&photo(), image::ImageFormat::Png);
+        let score = ElaDetector::default().detect(&png).unwrap().score_ai;
+        let banded = ElaDetector::new(ElaConfig {
+            ai_threshold: score + 0.05,
+            human_threshold: score - 0.05,
+            ..Default::default()
+        });
+        assert_eq!(banded.detect(&png).unwrap().label, DetectorLabel::Unknown);
+    }
+}
@@ -0,0 +1,317 @@
+//! Measuring a detector against samples whose true label is known.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use crate::{DetectorLabel, MediaDetector};
+use pru_media_schema::DetectorReliability;
+
+/// Thresholds swept for the ROC curve: 0.0, 0.05, ..., 1.0.
+const ROC_STEPS: usize = 20;
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct LabelMetrics {
+    /// Samples whose true label this is.
+    pub support: usize,
+    pub precision: Option<f64>,
+    pub recall: Option<f64>,
+}
+
+/// Treating "ai" as positive, rates at one score threshold.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct RocPoint {
+    pub threshold: f32,
+    pub true_positive_rate: f64,
+    pub false_positive_rate: f64,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct EvalReport {
+    pub detector: String,
+    /// Samples the detector scored; errors are counted separately.
+    pub samples: usize,
+    pub errors: usize,
+    /// Share of scored samples labelled correctly; an unknown label counts as a miss.
+    pub accuracy: f64,
+    pub unknown_predictions: usize,
+    /// Keyed by "ai" and "human".
+    pub per_label: BTreeMap<String, LabelMetrics>,
+    /// Mean `score_ai` per true label.
+    pub mean_score: BTreeMap<String, f64>,
+    pub roc: Vec<RocPoint>,
+    /// Area under `roc`; `None` unless both classes are present.
+    pub auc: Option<f64>,
+    /// Predicted label -> true label -> count, as in [`DetectorReliability`].
+    pub confusion: BTreeMap<String, BTreeMap<String, u64>>,
+}
+
+impl EvalReport {
+    /// Add this run's graded predictions to `reliability`, skipping unknown labels
+    /// like verdict grading does.
+    pub fn record_into(&self, reliability: &mut DetectorReliability) {
+        for (predicted, row) in &self.confusion {
+            if predicted == DetectorLabel::Unknown.as_str() {
+                continue;
+            }
+            for (actual, count) in row {
+                for _ in 0..*count {
+                    reliability.record(predicted, actual);
+                }
+            }
+        }
+    }
+}
+
+/// Run `detector` over labelled samples and summarize how well it separates them.
+///
+/// Samples whose true label is [`DetectorLabel::Unknown`] are ignored.
+pub fn evaluate_detector(
+    detector: &dyn MediaDetector,
+    samples: impl Iterator<Item = (Vec<u8>, DetectorLabel)>,
+) -> EvalReport {
+    let mut report = EvalReport {
+        detector: detector.id(),
+        ..Default::default()
+    };
+    let mut scored: Vec<(f32, bool)> = Vec::new();
+    let mut correct = 0usize;
+    let mut score_sums: BTreeMap<String, f64> = BTreeMap::new();
+    for (bytes, truth) in samples {
+        if truth == DetectorLabel::Unknown {
+            continue;
+        }
+        let Ok(output) = detector.detect(&bytes) else {
+            report.errors += 1;
+            continue;
+        };
+        report.samples += 1;
+        if output.label == truth {
+            correct += 1;
+        }
+        if output.label == DetectorLabel::Unknown {
+            report.unknown_predictions += 1;
+        }
+        *report
+            .confusion
+            .entry(output.label.as_str().to_string())
+            .or_default()
+            .entry(truth.as_str().to_string())
+            .or_insert(0) += 1;
+        *score_sums.entry(truth.as_str().to_string()).or_default() += output.score_ai as f64;
+        scored.push((output.score_ai, truth == DetectorLabel::Ai));
+    }
+
+    if report.samples > 0 {
+        report.accuracy = correct as f64 / report.samples as f64;
+    }
+    for label in [DetectorLabel::Ai, DetectorLabel::Human] {
+        let name = label.as_str();
+        let predicted: f64 = report
+            .confusion
+            .get(name)
+            .map(|row| row.values().sum::<u64>() as f64)
+            .unwrap_or(0.0);
+        let support = report
+            .confusion
+            .values()
+            .filter_map(|row| row.get(name))
+            .sum::<u64>() as usize;
+        let hits = report
+            .confusion
+            .get(name)
+            .and_then(|row| row.get(name))
+            .copied()
+            .unwrap_or(0) as f64;
+        report.per_label.insert(
+            name.to_string(),
+            LabelMetrics {
+                support,
+                precision: (predicted > 0.0).then(|| hits / predicted),
+                recall: (support > 0).then(|| hits / support as f64),
+            },
+        );
+        if let Some(sum) = score_sums.get(name) {
+            report
+                .mean_score
+                .insert(name.to_string(), sum / support.max(1) as f64);
+        }
+    }
+
+    let positives = scored.iter().filter(|(_, ai)| *ai).count();
+    let negatives = scored.len() - positives;
+    if positives > 0 && negatives > 0 {
+        report.roc = (0..=ROC_STEPS)
+            .map(|step| {
+                let threshold = step as f32 / ROC_STEPS as f32;
+                let above = |want_ai: bool| {
+                    scored
+                        .iter()
+                        .filter(|(score, ai)| *ai == want_ai && *score >= threshold)
+                        .count()
+                };
+                RocPoint {
+                    threshold,
+                    true_positive_rate: above(true) as f64 / positives as f64,
+                    false_positive_rate: above(false) as f64 / negatives as f64,
+                }
+            })
+            .collect();
+        // Thresholds ascend, so the curve runs from (1, 1) towards (0, 0); close it there.
+        let mut curve: Vec<(f64, f64)> = report
+            .roc
+            .iter()
+            .map(|p| (p.false_positive_rate, p.true_positive_rate))
+            .collect();
+        curve.push((0.0, 0.0));
+        report.auc = Some(
+            curve
+                .windows(2)
+                .map(|w| (w[0].0 - w[1].0) * (w[0].1 + w[1].1) / 2.0)
+                .sum(),
+        );
+    }
+    report
+}
+
+/// Every regular, non-hidden file in `ai_dir` labelled AI and in `human_dir`
+/// labelled human, each directory read in file-name order.
+///
+/// Both directories are listed up front, but each file is only read when the
+/// iterator reaches it, so one sample is held in memory at a time.
+pub fn load_labeled_dirs(
+    ai_dir: &Path,
+    human_dir: &Path,
+) -> Result<impl Iterator<Item = Result<(Vec<u8>, DetectorLabel)>>> {
+    let mut files = Vec::new();
+    for (dir, label) in [
+        (ai_dir, DetectorLabel::Ai),
+        (human_dir, DetectorLabel::Human),
+    ] {
+        files.extend(sample_files(dir)?.into_iter().map(|path| (path, label)));
+    }
+    Ok(files.into_iter().map(|(path, label)| {
+        let bytes = std::fs::read(&path).with_context(|| format!("reading {}", path.display()))?;
+        Ok((bytes, label))
+    }))
+}
+
+fn sample_files(dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut files: Vec<PathBuf> = std::fs::read_dir(dir)
+        .with_context(|| format!("reading sample dir {}", dir.display()))?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.is_file())
+        .filter(|path| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| !name.starts_with('.'))
+        })
+        .collect();
+    files.sort();
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{DetectorMediaKind, DetectorOutput};
+
+    /// Scores a sample by its first byte; `?` is an error and mid scores are unknown.
+    struct ByteDetector;
+
+    impl MediaDetector for ByteDetector {
+        fn id(&self) -> String {
+            "detector:text:byte".to_string()
+        }
+
+        fn kind(&self) -> DetectorMediaKind {
+            DetectorMediaKind::Text
+        }
+
+        fn detect(&self, bytes: &[u8]) -> Result<DetectorOutput> {
+            let score_ai = match bytes.first() {
+                Some(b'?') | None => anyhow::bail!("unreadable"),
+                Some(b) => (*b - b'0') as f32 / 10.0,
+            };
+            Ok(DetectorOutput {
+                score_ai,
+                label: crate::LabelPolicy::default().label(score_ai),
+                details: None,
+                features: BTreeMap::new(),
+            })
+        }
+    }
+
+    #[test]
+    fn report_summarizes_a_small_corpus() {
+        let samples = [
+            ("9", DetectorLabel::Ai),
+            ("8", DetectorLabel::Ai),
+            ("3", DetectorLabel::Ai),
+            ("5", DetectorLabel::Ai),
+            ("1", DetectorLabel::Human),
+            ("2", DetectorLabel::Human),
+            ("7", DetectorLabel::Human),
+            ("?", DetectorLabel::Human),
+            ("0", DetectorLabel::Unknown),
+        ];
+        let report = evaluate_detector(
+            &ByteDetector,
+            samples
+                .iter()
+                .map(|(bytes, label)| (bytes.as_bytes().to_vec(), *label)),
+        );
+
+        assert_eq!(report.samples, 7);
+        assert_eq!(report.errors, 1);
+        assert_eq!(report.unknown_predictions, 1);
+        assert!((report.accuracy - 4.0 / 7.0).abs() < 1e-9);
+        let ai = &report.per_label["ai"];
+        assert_eq!(ai.support, 4);
+        assert_eq!(ai.precision, Some(2.0 / 3.0));
+        assert_eq!(ai.recall, Some(0.5));
+        assert_eq!(report.per_label["human"].recall, Some(2.0 / 3.0));
+        assert!((report.mean_score["ai"] - 0.625).abs() < 1e-6);
+        assert!((report.mean_score["human"] - 1.0 / 3.0).abs() < 1e-6);
+
+        assert_eq!(report.roc.len(), ROC_STEPS + 1);
+        assert_eq!(report.roc[0].true_positive_rate, 1.0);
+        assert_eq!(report.roc[0].false_positive_rate, 1.0);
+        let auc = report.auc.unwrap();
+        assert!(auc > 0.5 && auc < 1.0, "{auc}");
+
+        let mut reliability = DetectorReliability::default();
+        report.record_into(&mut reliability);
+        assert_eq!(reliability.seen, 6);
+        assert_eq!(reliability.correct, 4);
+    }
+
+    #[test]
+    fn samples_load_from_two_directories() {
+        let ai = tempfile::tempdir().unwrap();
+        let human = tempfile::tempdir().unwrap();
+        std::fs::write(ai.path().join("b.txt"), "9").unwrap();
+        std::fs::write(ai.path().join("a.txt"), "8").unwrap();
+        std::fs::write(ai.path().join(".DS_Store"), "junk").unwrap();
+        std::fs::create_dir(ai.path().join("nested")).unwrap();
+        std::fs::write(human.path().join("h.txt"), "1").unwrap();
+
+        let samples: Vec<_> = load_labeled_dirs(ai.path(), human.path())
+            .unwrap()
+            .collect::<Result<_>>()
+            .unwrap();
+        assert_eq!(
+            samples,
+            vec![
+                (b"8".to_vec(), DetectorLabel::Ai),
+                (b"9".to_vec(), DetectorLabel::Ai),
+                (b"1".to_vec(), DetectorLabel::Human),
+            ]
+        );
+        let report = evaluate_detector(&ByteDetector, samples.into_iter());
+        assert_eq!(report.accuracy, 1.0);
+        assert_eq!(report.auc, Some(1.0));
+        assert!(load_labeled_dirs(Path::new("/no/such/dir"), human.path()).is_err());
+    }
+}
@@ -0,0 +1,240 @@
+//! Small language identifier for gating text heuristics.
+//!
+//! Non-Latin scripts are told apart by their Unicode blocks; Latin-script text is
+//! matched against short trigram profiles plus a few letters only one of the
+//! profiled languages uses. Good enough to say "this is not English", not more.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use crate::{DetectorLabel, DetectorOutput};
+use pru_media_schema::FeatureValue;
+
+/// Fewer letters than this and no guess is made.
+const MIN_LETTERS: usize = 8;
+
+/// Extra weight for one occurrence of a letter in [`DISTINCTIVE`].
+const DISTINCTIVE_BONUS: f64 = 3.0;
+
+/// Most common trigrams per language, most frequent first; spaces mark word edges.
+const PROFILES: &[(&str, &[&str])] = &[
+    (
+        "en",
+        &[
+            " th", "the", "he ", "nd ", " an", "and", " of", "of ", "ing", " to", "to ", "ng ",
+            " in", "ed ", "is ", " is", "in ", "ion", " a ", "er ", "at ", "es ", "tio", "re ",
+            "on ", " be", "ent", "hat", "tha", " wa", " fo", "for", "ter", "his", " hi", "ly ",
+            "you", " it", "it ", "ati",
+        ],
+    ),
+    (
+        "tr",
+        &[
+            "lar", "ler", " bi", "bir", "ir ", "in ", "an ", "eri", " ve", "ve ", "da ", "de ",
+            "ara", "ını", "ın ", "nda", "yor", "ile", " il", "le ", "len", "ası", "bu ", " bu",
+            "ind", "dır", "mak", "ak ", "içi", " iç", "çin", "ına", "rin", "ni ", "ya ", "ola",
+            " ol", "lma", "iye", "mış",
+        ],
+    ),
+    (
+        "de",
+        &[
+            "en ", "er ", " de", "der", "ie ", "ich", "ein", " ei", "sch", "che", "die", " di",
+            "nd ", "und", " un", "den", "cht", "ch ", "ine", "gen", " zu", "ist", " is", "es ",
+            "te ", "ung", "ng ", "nde", "ter", "ber", " ge", "ten", "nen", "das", " da", "auf",
+            " au", "hen", "ht ", "sie",
+        ],
+    ),
+    (
+        "fr",
+        &[
+            "es ", " de", "de ", "ent", "le ", " le", "nt ", "la ", " la", "ion", "les", " co",
+            "re ", "on ", "que", "ue ", " qu", "des", "et ", " et", "ait", "ne ", "ais", " pa",
+            "ans", "our", "tio", "men", "eme", " un", "une", "ous", " po", "par", "ur ", "est",
+            " es", "se ", " se", "ire",
+        ],
+    ),
+    (
+        "es",
+        &[
+            " de", "de ", "os ", "la ", " la", "el ", " el", "es ", "en ", " qu", "que", "ue ",
+            "ent", "as ", "los", " lo", "aci", "ado", "con", " co", "ien", "nte", " se", "par",
+            "ara", "sta", "est", " es", "ar ", "ón ", "ión", "cio", " en", "del", "do ", "una",
+            " un", "ero", "por", " po",
+        ],
+    ),
+];
+
+/// Letters that, among the profiled languages, only one of them writes.
+const DISTINCTIVE: &[(&str, &str)] = &[
+    ("tr", "ıİğĞşŞ"),
+    ("de", "ß"),
+    ("es", "ñÑ¿¡"),
+    ("fr", "œèêëàâîûù"),
+];
+
+/// ISO 639-1 code of the language `text` is most likely written in, or `None` when
+/// it is too short or does not resemble any profile.
+pub fn detect_language(text: &str) -> Option<&'static str> {
+    let mut scripts: HashMap<&'static str, usize> = HashMap::new();
+    let mut letters = 0;
+    for c in text.chars().filter(|c| c.is_alphabetic()) {
+        letters += 1;
+        if let Some(language) = script_language(c) {
+            *scripts.entry(language).or_default() += 1;
+        }
+    }
+    if letters < MIN_LETTERS {
+        return None;
+    }
+    // Japanese mixes kana into Han text, so any real share of kana decides it.
+    let kana = scripts.get("ja").copied().unwrap_or(0);
+    if kana * 10 >= letters {
+        return Some("ja");
+    }
+    if let Some((language, count)) = scripts.iter().max_by_key(|(_, count)| **count) {
+        if *count * 2 > letters {
+            return Some(language);
+        }
+    }
+    latin_language(text)
+}
+
+fn script_language(c: char) -> Option<&'static str> {
+    match c as u32 {
+        0x3040..=0x30FF => Some("ja"),
+        0x4E00..=0x9FFF | 0x3400..=0x4DBF => Some("zh"),
+        0xAC00..=0xD7AF | 0x1100..=0x11FF => Some("ko"),
+        0x0400..=0x04FF => Some("ru"),
+        0x0600..=0x06FF => Some("ar"),
+        0x0370..=0x03FF => Some("el"),
+        _ => None,
+    }
+}
+
+fn latin_language(text: &str) -> Option<&'static str> {
+    let lower = text.to_lowercase();
+    let mut trigrams: HashMap<String, usize> = HashMap::new();
+    let mut total = 0usize;
+    for word in lower
+        .split(|c: char| !c.is_alphabetic())
+        .filter(|w| !w.is_empty())
+    {
+        let padded: Vec<char> = format!(" {word} ").chars().collect();
+        for window in padded.windows(3) {
+            *trigrams.entry(window.iter().collect()).or_default() += 1;
+            total += 1;
+        }
+    }
+    if total == 0 {
+        return None;
+    }
+
+    let mut best: Option<(&'static str, f64)> = None;
+    let mut runner_up = 0.0;
+    for (language, profile) in PROFILES {
+        let mut score: f64 = profile
+            .iter()
+            .enumerate()
+            .filter_map(|(rank, gram)| {
+                let count = trigrams.get(*gram)?;
+                Some(*count as f64 * (2.0 - rank as f64 / profile.len() as f64))
+            })
+            .sum();
+        if let Some((_, letters)) = DISTINCTIVE.iter().find(|(l, _)| l == language) {
+            score +=
+                text.chars().filter(|c| letters.contains(*c)).count() as f64 * DISTINCTIVE_BONUS;
+        }
+        match best {
+            Some((_, top)) if score <= top => runner_up = f64::max(runner_up, score),
+            _ => {
+                runner_up = best.map_or(0.0, |(_, top)| top);
+                best = Some((language, score));
+            }
+        }
+    }
+    // Require some evidence, and a clear winner over the next profile.
+    let (language, score) = best?;
+    (score / total as f64 >= 0.15 && score > runner_up * 1.2).then_some(language)
+}
+
+/// The language feature for `language`, plus an unknown output to return instead of
+/// a score when `supported` is set and does not include it.
+///
+/// Text whose language cannot be identified is let through.
+pub(crate) fn language_gate(
+    language: Option<&'static str>,
+    supported: Option<&BTreeSet<String>>,
+) -> (Option<(String, FeatureValue)>, Option<DetectorOutput>) {
+    let Some(language) = language else {
+        return (None, None);
+    };
+    let feature = (
+        "language".to_string(),
+        FeatureValue::Str(language.to_string()),
+    );
+    let blocked = supported.filter(|set| !set.iter().any(|s| s.eq_ignore_ascii_case(language)));
+    let output = blocked.map(|set| DetectorOutput {
+        score_ai: 0.5,
+        label: DetectorLabel::Unknown,
+        details: Some(format!(
+            "language {language} is not supported (supported: {})",
+            set.iter().cloned().collect::<Vec<_>>().join(", ")
+        )),
+        features: BTreeMap::from([feature.clone()]),
+    });
+    (Some(feature), output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{MediaDetector, TextComplexityDetector};
+
+    const ENGLISH: &str = "The weather was cold, so we stayed inside and read the paper.";
+    const TURKISH: &str = "Bu kısa bir Türkçe cümledir ve dil tespiti için yazıldı.";
+    const CHINESE: &str = "这是一个简短的中文句子，用来测试语言识别功能。";
+
+    #[test]
+    fn short_snippets_are_identified() {
+        assert_eq!(detect_language(ENGLISH), Some("en"));
+        assert_eq!(detect_language(TURKISH), Some("tr"));
+        assert_eq!(detect_language(CHINESE), Some("zh"));
+        assert_eq!(
+            detect_language("Der Hund schläft und die Katze ist auch müde."),
+            Some("de")
+        );
+        assert_eq!(detect_language("ok"), None);
+        assert_eq!(detect_language("1234 5678 !!"), None);
+    }
+
+    #[test]
+    fn unsupported_language_is_unknown() {
+        let detector = TextComplexityDetector::default();
+        let english = detector.detect(ENGLISH.as_bytes()).unwrap();
+        assert_ne!(english.label, DetectorLabel::Unknown);
+        assert_eq!(
+            english.features.get("language"),
+            Some(&FeatureValue::Str("en".into()))
+        );
+
+        let turkish = detector.detect(TURKISH.as_bytes()).unwrap();
+        assert_eq!(turkish.label, DetectorLabel::Unknown);
+        assert_eq!(turkish.score_ai, 0.5);
+        assert!(turkish
+            .details
+            .unwrap()
+            .contains("language tr is not supported"));
+        assert_eq!(
+            turkish.features.get("language"),
+            Some(&FeatureValue::Str("tr".into()))
+        );
+
+        let german = detector
+            .detect("Der Hund schläft und die Katze ist auch müde.".as_bytes())
+            .unwrap();
+        assert_ne!(german.label, DetectorLabel::Unknown);
+
+        let (_, blocked) = language_gate(Some("zh"), None);
+        assert!(blocked.is_none());
+    }
+}
@@ -1,12 +1,56 @@
-use anyhow::{anyhow, Context, Result};
-use exif;
+use anyhow::{anyhow, bail, Context, Result};
+use async_trait::async_trait;
 use image::GenericImageView;
-use pru_media_schema::MediaType;
+use language::language_gate;
+use pru_core::PruDbHandle;
+use pru_media_schema::{
+    add_provenance_claim, register_detector, DetectorInfo, FeatureValue, MediaId, MediaType,
+    ProvenanceClaim,
+};
 use serde::{Deserialize, Serialize};
-use std::collections::HashSet;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::path::Path;
 use std::sync::Arc;
+use std::time::Duration;
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+mod audio;
+mod ela;
+mod eval;
+mod language;
+#[cfg(feature = "onnx")]
+mod onnx;
+mod phash;
+mod png_text;
+mod provenance;
+mod registry_file;
+mod remote;
+mod sniff;
+mod spectral;
+mod subprocess;
+mod text_stats;
+mod text_unicode;
+mod video;
+
+pub use audio::{decode_wav, AudioSpectralConfig, AudioSpectralDetector, PcmAudio};
+pub use ela::{error_level, ElaConfig, ElaDetector, ElaStats};
+pub use eval::{evaluate_detector, load_labeled_dirs, EvalReport, LabelMetrics, RocPoint};
+pub use language::detect_language;
+#[cfg(feature = "onnx")]
+pub use onnx::{OnnxImageConfig, OnnxImageDetector};
+pub use phash::{dhash, PHashConfig, PHashDetector};
+pub use png_text::{generator_hint, png_text_chunks, GeneratorHint};
+pub use provenance::{xmp_packet, ImageProvenance};
+pub use registry_file::DETECTOR_TYPES;
+pub use remote::RemoteHttpDetector;
+pub use sniff::{kind_to_media_type, sniff_media_kind, sniff_media_type};
+pub use spectral::{tile_spectrum, SpectralImageConfig, SpectralImageDetector, SpectrumStats};
+pub use subprocess::SubprocessDetector;
+pub use text_stats::{TextStatistics, TextStatisticsConfig, TextStatisticsDetector};
+pub use text_unicode::{TextUnicodeAnomalyConfig, TextUnicodeAnomalyDetector, UnicodeAnomalies};
+pub use video::{probe_container, ContainerInfo, VideoMetadataConfig, VideoMetadataDetector};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum DetectorMediaKind {
     Image,
     Text,
@@ -16,30 +60,200 @@ pub enum DetectorMediaKind {
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub enum DetectorLabel {
+    #[serde(alias = "ai")]
     Ai,
+    #[serde(alias = "human")]
     Human,
+    #[serde(alias = "unknown")]
     Unknown,
 }
 
+impl DetectorLabel {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DetectorLabel::Ai => "ai",
+            DetectorLabel::Human => "human",
+            DetectorLabel::Unknown => "unknown",
+        }
+    }
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct DetectorOutput {
     pub score_ai: f32,
     pub label: DetectorLabel,
+    /// Optional human-readable summary.
     pub details: Option<String>,
+    /// Measurements behind the score, persisted as `has_feature` facts.
+    #[serde(default)]
+    pub features: BTreeMap<String, FeatureValue>,
 }
 
 pub trait MediaDetector: Send + Sync {
     fn id(&self) -> String;
     fn kind(&self) -> DetectorMediaKind;
     fn detect(&self, bytes: &[u8]) -> Result<DetectorOutput>;
+
+    /// Detection with access to the store, for detectors that consult or link
+    /// earlier media. `media` has already been recorded when this runs.
+    fn detect_with_context(
+        &self,
+        bytes: &[u8],
+        _pru: &PruDbHandle,
+        _media: MediaId,
+    ) -> Result<DetectorOutput> {
+        self.detect(bytes)
+    }
+
+    /// Detection from the stored file at `path` (the same bytes `detect` would
+    /// get), for detectors that can read or pass on a file without holding it in
+    /// memory. `None`, the default, means use [`detect_with_context`](Self::detect_with_context).
+    fn detect_file(
+        &self,
+        _path: &Path,
+        _pru: &PruDbHandle,
+        _media: MediaId,
+    ) -> Option<Result<DetectorOutput>> {
+        None
+    }
+
+    /// Metadata recorded for the detector entity when it is registered with a store.
+    fn info(&self) -> DetectorInfo {
+        DetectorInfo {
+            kind: format!("{:?}", self.kind()).to_lowercase(),
+            version: "v1".to_string(),
+            description: String::new(),
+            config_hash: None,
+        }
+    }
 }
 
-#[derive(Default, Clone)]
+/// Detector whose work may await I/O, e.g. a call to a remote model.
+#[async_trait]
+pub trait AsyncMediaDetector: Send + Sync {
+    fn id(&self) -> String;
+    fn kind(&self) -> DetectorMediaKind;
+    async fn detect(&self, bytes: &[u8]) -> Result<DetectorOutput>;
+
+    /// See [`MediaDetector::detect_with_context`].
+    async fn detect_with_context(
+        &self,
+        bytes: &[u8],
+        _pru: &PruDbHandle,
+        _media: MediaId,
+    ) -> Result<DetectorOutput> {
+        self.detect(bytes).await
+    }
+
+    /// See [`MediaDetector::detect_file`].
+    async fn detect_file(
+        &self,
+        _path: &Path,
+        _pru: &PruDbHandle,
+        _media: MediaId,
+    ) -> Option<Result<DetectorOutput>> {
+        None
+    }
+
+    fn info(&self) -> DetectorInfo {
+        DetectorInfo {
+            kind: format!("{:?}", self.kind()).to_lowercase(),
+            version: "v1".to_string(),
+            description: String::new(),
+            config_hash: None,
+        }
+    }
+
+    /// The wrapped synchronous detector, letting sync callers skip the runtime.
+    fn as_sync(&self) -> Option<&dyn MediaDetector> {
+        None
+    }
+}
+
+/// Runs a synchronous detector on tokio's blocking pool so it never stalls a worker.
+pub struct BlockingDetector(pub Arc<dyn MediaDetector>);
+
+#[async_trait]
+impl AsyncMediaDetector for BlockingDetector {
+    fn id(&self) -> String {
+        self.0.id()
+    }
+
+    fn kind(&self) -> DetectorMediaKind {
+        self.0.kind()
+    }
+
+    async fn detect(&self, bytes: &[u8]) -> Result<DetectorOutput> {
+        let detector = self.0.clone();
+        let owned = bytes.to_vec();
+        tokio::task::spawn_blocking(move || detector.detect(&owned))
+            .await
+            .map_err(|e| anyhow!("detector task failed: {e}"))?
+    }
+
+    async fn detect_with_context(
+        &self,
+        bytes: &[u8],
+        pru: &PruDbHandle,
+        media: MediaId,
+    ) -> Result<DetectorOutput> {
+        let detector = self.0.clone();
+        let owned = bytes.to_vec();
+        let pru = pru.clone();
+        tokio::task::spawn_blocking(move || detector.detect_with_context(&owned, &pru, media))
+            .await
+            .map_err(|e| anyhow!("detector task failed: {e}"))?
+    }
+
+    async fn detect_file(
+        &self,
+        path: &Path,
+        pru: &PruDbHandle,
+        media: MediaId,
+    ) -> Option<Result<DetectorOutput>> {
+        let detector = self.0.clone();
+        let path = path.to_path_buf();
+        let pru = pru.clone();
+        tokio::task::spawn_blocking(move || detector.detect_file(&path, &pru, media))
+            .await
+            .unwrap_or_else(|e| Some(Err(anyhow!("detector task failed: {e}"))))
+    }
+
+    fn info(&self) -> DetectorInfo {
+        self.0.info()
+    }
+
+    fn as_sync(&self) -> Option<&dyn MediaDetector> {
+        Some(self.0.as_ref())
+    }
+}
+
+/// How long a detector may run before ingest gives up on it.
+pub const DEFAULT_DETECTOR_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Clone)]
 pub struct DetectorRegistry {
-    image_detectors: Vec<Arc<dyn MediaDetector>>,
-    text_detectors: Vec<Arc<dyn MediaDetector>>,
-    audio_detectors: Vec<Arc<dyn MediaDetector>>,
-    video_detectors: Vec<Arc<dyn MediaDetector>>,
+    image_detectors: Vec<Arc<dyn AsyncMediaDetector>>,
+    text_detectors: Vec<Arc<dyn AsyncMediaDetector>>,
+    audio_detectors: Vec<Arc<dyn AsyncMediaDetector>>,
+    video_detectors: Vec<Arc<dyn AsyncMediaDetector>>,
+    default_timeout: Duration,
+    timeouts: HashMap<String, Duration>,
+    disabled: HashSet<String>,
+}
+
+impl Default for DetectorRegistry {
+    fn default() -> Self {
+        Self {
+            image_detectors: Vec::new(),
+            text_detectors: Vec::new(),
+            audio_detectors: Vec::new(),
+            video_detectors: Vec::new(),
+            default_timeout: DEFAULT_DETECTOR_TIMEOUT,
+            timeouts: HashMap::new(),
+            disabled: HashSet::new(),
+        }
+    }
 }
 
 impl DetectorRegistry {
@@ -47,16 +261,124 @@ impl DetectorRegistry {
         Self::default()
     }
 
-    pub fn register(&mut self, detector: Arc<dyn MediaDetector>) {
-        match detector.kind() {
-            DetectorMediaKind::Image => self.image_detectors.push(detector),
-            DetectorMediaKind::Text => self.text_detectors.push(detector),
-            DetectorMediaKind::Audio => self.audio_detectors.push(detector),
-            DetectorMediaKind::Video => self.video_detectors.push(detector),
+    pub fn set_default_timeout(&mut self, timeout: Duration) {
+        self.default_timeout = timeout;
+    }
+
+    /// Override the timeout for one detector id.
+    pub fn set_timeout(&mut self, id: &str, timeout: Duration) {
+        self.timeouts.insert(id.to_string(), timeout);
+    }
+
+    pub fn timeout_for(&self, id: &str) -> Duration {
+        self.timeouts
+            .get(id)
+            .copied()
+            .unwrap_or(self.default_timeout)
+    }
+
+    /// Register a synchronous detector; it runs via [`BlockingDetector`] on async paths.
+    ///
+    /// Fails when the id is invalid or already registered, since two detectors
+    /// sharing an id would write to the same detector entity.
+    pub fn register(&mut self, detector: Arc<dyn MediaDetector>) -> Result<()> {
+        self.register_async(Arc::new(BlockingDetector(detector)))
+    }
+
+    pub fn register_async(&mut self, detector: Arc<dyn AsyncMediaDetector>) -> Result<()> {
+        let id = validate_id(detector.as_ref())?;
+        if self.get(&id).is_some() {
+            bail!("detector id {id} is already registered");
+        }
+        self.slot_mut(detector.kind()).push(detector);
+        Ok(())
+    }
+
+    /// Like [`register`](Self::register), but swaps out a detector with the same id.
+    /// Returns whether one was replaced; its enabled flag and timeout carry over.
+    pub fn register_or_replace(&mut self, detector: Arc<dyn MediaDetector>) -> Result<bool> {
+        self.register_async_or_replace(Arc::new(BlockingDetector(detector)))
+    }
+
+    pub fn register_async_or_replace(
+        &mut self,
+        detector: Arc<dyn AsyncMediaDetector>,
+    ) -> Result<bool> {
+        let id = validate_id(detector.as_ref())?;
+        let kind = detector.kind();
+        let Some(old_kind) = self.get(&id).map(|d| d.kind()) else {
+            self.slot_mut(kind).push(detector);
+            return Ok(false);
+        };
+        if old_kind == kind {
+            let slot = self.slot_mut(kind);
+            if let Some(existing) = slot.iter_mut().find(|d| d.id() == id) {
+                *existing = detector;
+            }
+        } else {
+            self.slot_mut(old_kind).retain(|d| d.id() != id);
+            self.slot_mut(kind).push(detector);
         }
+        Ok(true)
+    }
+
+    /// Enabled detectors for `kind`, in registration order.
+    pub fn for_media(&self, kind: DetectorMediaKind) -> Vec<Arc<dyn AsyncMediaDetector>> {
+        self.slot(kind)
+            .iter()
+            .filter(|d| !self.disabled.contains(&d.id()))
+            .cloned()
+            .collect()
     }
 
-    pub fn for_media(&self, kind: DetectorMediaKind) -> &[Arc<dyn MediaDetector>] {
+    pub fn get(&self, id: &str) -> Option<Arc<dyn AsyncMediaDetector>> {
+        self.all().find(|d| d.id() == id).cloned()
+    }
+
+    /// Remove a detector; returns false when no detector has that id.
+    pub fn unregister(&mut self, id: &str) -> bool {
+        let Some(kind) = self.get(id).map(|d| d.kind()) else {
+            return false;
+        };
+        self.slot_mut(kind).retain(|d| d.id() != id);
+        self.disabled.remove(id);
+        self.timeouts.remove(id);
+        true
+    }
+
+    /// Toggle a detector without removing it; returns false when no detector has that id.
+    pub fn set_enabled(&mut self, id: &str, enabled: bool) -> bool {
+        if self.get(id).is_none() {
+            return false;
+        }
+        if enabled {
+            self.disabled.remove(id);
+        } else {
+            self.disabled.insert(id.to_string());
+        }
+        true
+    }
+
+    /// Every registered detector as `(id, kind, enabled)`.
+    pub fn ids(&self) -> Vec<(String, DetectorMediaKind, bool)> {
+        self.all()
+            .map(|d| {
+                let id = d.id();
+                let enabled = !self.disabled.contains(&id);
+                (id, d.kind(), enabled)
+            })
+            .collect()
+    }
+
+    fn all(&self) -> impl Iterator<Item = &Arc<dyn AsyncMediaDetector>> {
+        self.image_detectors
+            .iter()
+            .chain(&self.text_detectors)
+            .chain(&self.audio_detectors)
+            .chain(&self.video_detectors)
+    }
+
+    fn slot(&self, kind: DetectorMediaKind) -> &Vec<Arc<dyn AsyncMediaDetector>> {
         match kind {
             DetectorMediaKind::Image => &self.image_detectors,
             DetectorMediaKind::Text => &self.text_detectors,
@@ -64,9 +386,116 @@ impl DetectorRegistry {
             DetectorMediaKind::Video => &self.video_detectors,
         }
     }
+
+    fn slot_mut(&mut self, kind: DetectorMediaKind) -> &mut Vec<Arc<dyn AsyncMediaDetector>> {
+        match kind {
+            DetectorMediaKind::Image => &mut self.image_detectors,
+            DetectorMediaKind::Text => &mut self.text_detectors,
+            DetectorMediaKind::Audio => &mut self.audio_detectors,
+            DetectorMediaKind::Video => &mut self.video_detectors,
+        }
+    }
 }
 
-pub struct TextComplexityDetector;
+/// The detector's id, checked to be non-empty, free of whitespace and, for
+/// `detector:<kind>:<name>` ids, consistent with the declared kind.
+fn validate_id(detector: &dyn AsyncMediaDetector) -> Result<String> {
+    let id = detector.id();
+    debug_assert_eq!(id, detector.id(), "detector id() must be deterministic");
+    if id.trim().is_empty() {
+        bail!("detector id must not be empty");
+    }
+    if id.chars().any(char::is_whitespace) {
+        bail!("detector id {id:?} must not contain whitespace");
+    }
+    let kind = format!("{:?}", detector.kind()).to_lowercase();
+    if let Some(rest) = id.strip_prefix("detector:") {
+        let prefix = rest.split(':').next().unwrap_or_default();
+        if prefix != kind {
+            bail!("detector id {id} does not match its kind {kind}");
+        }
+    }
+    Ok(id)
+}
+
+/// How a score becomes a label: above `ai_threshold` is AI, below `human_threshold`
+/// is human, and anything in between is unknown.
+///
+/// Keeping ambiguous scores out of the human bucket stops them from counting as
+/// confident misses when a verdict arrives.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LabelPolicy {
+    pub ai_threshold: f32,
+    pub human_threshold: f32,
+}
+
+impl LabelPolicy {
+    pub fn new(ai_threshold: f32, human_threshold: f32) -> Self {
+        Self {
+            ai_threshold,
+            human_threshold,
+        }
+    }
+
+    pub fn label(&self, score: f32) -> DetectorLabel {
+        if score > self.ai_threshold {
+            DetectorLabel::Ai
+        } else if score < self.human_threshold {
+            DetectorLabel::Human
+        } else {
+            DetectorLabel::Unknown
+        }
+    }
+}
+
+impl Default for LabelPolicy {
+    fn default() -> Self {
+        Self::new(0.6, 0.4)
+    }
+}
+
+/// Tuning knobs for [`TextComplexityDetector`].
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TextComplexityConfig {
+    /// Scores above this are labelled AI.
+    pub ai_threshold: f32,
+    /// Scores below this are labelled human; the band in between is unknown.
+    pub human_threshold: f32,
+    pub repetition_weight: f32,
+    pub complexity_weight: f32,
+    /// Average word length that counts as fully complex.
+    pub avg_len_norm: f32,
+    /// ISO 639-1 codes the heuristics are tuned for; other identified languages are
+    /// labelled unknown. `None` scores every language. Defaults to the profiled
+    /// languages written as space-separated words of moderate length; agglutinative
+    /// Turkish and unsegmented scripts such as Chinese are left out.
+    pub supported_languages: Option<BTreeSet<String>>,
+}
+
+impl Default for TextComplexityConfig {
+    fn default() -> Self {
+        Self {
+            ai_threshold: 0.55,
+            human_threshold: 0.45,
+            repetition_weight: 0.6,
+            complexity_weight: 0.4,
+            avg_len_norm: 10.0,
+            supported_languages: Some(["en", "de", "fr", "es"].map(String::from).into()),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct TextComplexityDetector {
+    config: TextComplexityConfig,
+}
+
+impl TextComplexityDetector {
+    pub fn new(config: TextComplexityConfig) -> Self {
+        Self { config }
+    }
+}
 
 impl MediaDetector for TextComplexityDetector {
     fn id(&self) -> String {
@@ -77,8 +506,23 @@ impl MediaDetector for TextComplexityDetector {
         DetectorMediaKind::Text
     }
 
+    fn info(&self) -> DetectorInfo {
+        DetectorInfo {
+            kind: "text".to_string(),
+            version: "v1".to_string(),
+            description: "Word length and vocabulary repetition heuristics".to_string(),
+            config_hash: config_hash(&self.config),
+        }
+    }
+
     fn detect(&self, bytes: &[u8]) -> Result<DetectorOutput> {
+        let cfg = &self.config;
         let text = std::str::from_utf8(bytes).context("text must be utf-8")?;
+        let (language, unsupported) =
+            language_gate(detect_language(text), cfg.supported_languages.as_ref());
+        if let Some(output) = unsupported {
+            return Ok(output);
+        }
         let words: Vec<&str> = text.split_whitespace().filter(|w| !w.is_empty()).collect();
         let total_chars: usize = words.iter().map(|w| w.chars().count()).sum();
         let avg_len = if words.is_empty() {
@@ -93,66 +537,299 @@ impl MediaDetector for TextComplexityDetector {
             unique.len() as f32 / words.len() as f32
         };
         let repetition_score = 1.0 - vocab_ratio;
-        let complexity_score = (avg_len / 10.0).clamp(0.0, 1.0);
-        let ai_score = ((repetition_score * 0.6) + (1.0 - complexity_score) * 0.4).clamp(0.0, 1.0);
-        let label = if ai_score > 0.55 {
-            DetectorLabel::Ai
-        } else {
-            DetectorLabel::Human
-        };
+        let complexity_score = (avg_len / cfg.avg_len_norm).clamp(0.0, 1.0);
+        let ai_score = ((repetition_score * cfg.repetition_weight)
+            + (1.0 - complexity_score) * cfg.complexity_weight)
+            .clamp(0.0, 1.0);
+        let label = LabelPolicy::new(cfg.ai_threshold, cfg.human_threshold).label(ai_score);
+        let mut features = BTreeMap::from([
+            ("avg_len".to_string(), FeatureValue::F64(avg_len as f64)),
+            (
+                "vocab_ratio".to_string(),
+                FeatureValue::F64(vocab_ratio as f64),
+            ),
+            (
+                "repetition".to_string(),
+                FeatureValue::F64(repetition_score as f64),
+            ),
+            (
+                "word_count".to_string(),
+                FeatureValue::I64(words.len() as i64),
+            ),
+        ]);
+        features.extend(language);
         Ok(DetectorOutput {
             score_ai: ai_score,
             label,
             details: Some(format!(
                 "avg_len={avg_len:.2}, vocab_ratio={vocab_ratio:.2}, repetition={repetition_score:.2}"
             )),
+            features,
         })
     }
 }
 
-pub struct ImageMetadataDetector;
+/// Tuning knobs for [`ImageMetadataDetector`].
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ImageMetadataConfig {
+    /// Scores above this are labelled AI.
+    pub ai_threshold: f32,
+    /// Scores below this are labelled human; the band in between is unknown.
+    pub human_threshold: f32,
+    /// Hint added when EXIF software or XMP names a known generator.
+    pub exif_ai_hint: f32,
+    /// Hint added when PNG text chunks carry generator parameters.
+    pub png_ai_hint: f32,
+    /// Hint added when the XMP digital source type says a trained model made the pixels.
+    pub xmp_ai_hint: f32,
+    /// Maximum contribution of image resolution to the score.
+    pub detail_weight: f32,
+    /// Pixel count at which the resolution contribution saturates.
+    pub detail_saturation_pixels: f32,
+    /// Subtracted when a named camera comes with capture date, lens or GPS.
+    pub camera_metadata_weight: f32,
+    /// Added when an image of at least `large_image_pixels` carries no camera metadata.
+    pub missing_metadata_weight: f32,
+    pub large_image_pixels: f32,
+}
 
-impl MediaDetector for ImageMetadataDetector {
-    fn id(&self) -> String {
-        "detector:image:metadata_v1".to_string()
+impl Default for ImageMetadataConfig {
+    fn default() -> Self {
+        Self {
+            ai_threshold: 0.6,
+            human_threshold: 0.4,
+            exif_ai_hint: 0.9,
+            png_ai_hint: 0.95,
+            xmp_ai_hint: 0.9,
+            detail_weight: 0.3,
+            detail_saturation_pixels: 2_000_000.0,
+            camera_metadata_weight: 0.2,
+            missing_metadata_weight: 0.1,
+            large_image_pixels: 1_000_000.0,
+        }
     }
+}
 
-    fn kind(&self) -> DetectorMediaKind {
-        DetectorMediaKind::Image
+#[derive(Clone, Debug, Default)]
+pub struct ImageMetadataDetector {
+    config: ImageMetadataConfig,
+}
+
+impl ImageMetadataDetector {
+    pub fn new(config: ImageMetadataConfig) -> Self {
+        Self { config }
     }
 
-    fn detect(&self, bytes: &[u8]) -> Result<DetectorOutput> {
-        // Try reading EXIF software tag.
+    pub fn config(&self) -> &ImageMetadataConfig {
+        &self.config
+    }
+
+    fn analyse(&self, bytes: &[u8]) -> Result<(DetectorOutput, Vec<ProvenanceClaim>)> {
+        let cfg = &self.config;
+        let provenance = ImageProvenance::read(bytes);
+        let mut claims = provenance.claims();
+        let mut reasons = Vec::new();
         let mut ai_hint = 0.0_f32;
-        let cursor = std::io::Cursor::new(bytes);
-        if let Ok(exifreader) = exif::Reader::new().read_from_container(&mut cursor.clone()) {
-            if let Some(field) = exifreader.get_field(exif::Tag::Software, exif::In::PRIMARY) {
-                let soft = field.display_value().with_unit(&exifreader).to_string();
-                let lower = soft.to_ascii_lowercase();
-                if lower.contains("stable diffusion")
-                    || lower.contains("dall-e")
-                    || lower.contains("midjourney")
-                {
-                    ai_hint = 0.9;
-                }
+        if let Some(generator) = &provenance.generator {
+            ai_hint = cfg.exif_ai_hint;
+            reasons.push(format!("metadata names {generator}"));
+        }
+        if provenance.algorithmic_source() {
+            ai_hint = ai_hint.max(cfg.xmp_ai_hint);
+            reasons.push(format!(
+                "XMP digital source type {}",
+                provenance
+                    .digital_source_type
+                    .as_deref()
+                    .unwrap_or_default()
+            ));
+        }
+
+        let png_hint = generator_hint(&png_text_chunks(bytes));
+        if let Some(hint) = &png_hint {
+            ai_hint = ai_hint.max(cfg.png_ai_hint);
+            let claim = ProvenanceClaim::GeneratedByModel {
+                model: hint.generator.clone(),
+            };
+            claims.retain(|c| !matches!(c, ProvenanceClaim::CapturedByDevice { .. }));
+            if !claims.contains(&claim) {
+                claims.push(claim);
             }
         }
 
         let img = image::load_from_memory(bytes).map_err(|e| anyhow!("image decode: {e}"))?;
         let (w, h) = img.dimensions();
         let resolution = (w * h) as f32;
-        let detail_score = ((resolution / 2_000_000.0).min(1.0)) * 0.3;
-        let base_ai = (ai_hint + detail_score).clamp(0.0, 1.0);
-        let label = if base_ai > 0.6 {
-            DetectorLabel::Ai
+        let detail_score =
+            ((resolution / cfg.detail_saturation_pixels).min(1.0)) * cfg.detail_weight;
+        let camera_adjust = if provenance.consistent_camera() {
+            reasons.push(format!(
+                "camera metadata from {}",
+                provenance.camera().unwrap_or_default()
+            ));
+            -cfg.camera_metadata_weight
+        } else if !provenance.has_camera_metadata() && resolution >= cfg.large_image_pixels {
+            reasons.push("large image without camera metadata".to_string());
+            cfg.missing_metadata_weight
         } else {
-            DetectorLabel::Human
+            0.0
         };
-        Ok(DetectorOutput {
+        let base_ai = (ai_hint + detail_score + camera_adjust).clamp(0.0, 1.0);
+        let label = LabelPolicy::new(cfg.ai_threshold, cfg.human_threshold).label(base_ai);
+        let mut features = BTreeMap::from([
+            ("width".to_string(), FeatureValue::I64(w as i64)),
+            ("height".to_string(), FeatureValue::I64(h as i64)),
+            ("ai_hint".to_string(), FeatureValue::F64(ai_hint as f64)),
+            (
+                "has_camera_metadata".to_string(),
+                FeatureValue::Bool(provenance.has_camera_metadata()),
+            ),
+            (
+                "has_gps".to_string(),
+                FeatureValue::Bool(provenance.has_gps),
+            ),
+            ("c2pa".to_string(), FeatureValue::Bool(provenance.c2pa)),
+        ]);
+        for (name, value) in [
+            ("camera", provenance.camera()),
+            ("creator_tool", provenance.creator_tool.clone()),
+            (
+                "digital_source_type",
+                provenance.digital_source_type.clone(),
+            ),
+        ] {
+            if let Some(value) = value {
+                features.insert(name.to_string(), FeatureValue::Str(value));
+            }
+        }
+        let mut details = format!("resolution={}x{}, ai_hint={ai_hint:.2}", w, h);
+        if let Some(hint) = png_hint {
+            details.push_str(&format!(", png_generator={}", hint.generator));
+            if let Some(prompt) = hint.prompt.as_deref() {
+                details.push_str(&format!(", prompt={}", png_text::details_prompt(prompt)));
+                features.insert("prompt".to_string(), FeatureValue::Str(prompt.to_string()));
+            }
+            features.insert(
+                "png_generator".to_string(),
+                FeatureValue::Str(hint.generator),
+            );
+        }
+        if !reasons.is_empty() {
+            details.push_str(&format!("; {}", reasons.join("; ")));
+        }
+        let output = DetectorOutput {
             score_ai: base_ai,
             label,
-            details: Some(format!("resolution={}x{}, ai_hint={ai_hint:.2}", w, h)),
-        })
+            details: Some(details),
+            features,
+        };
+        Ok((output, claims))
+    }
+}
+
+impl MediaDetector for ImageMetadataDetector {
+    fn id(&self) -> String {
+        "detector:image:metadata_v1".to_string()
+    }
+
+    fn kind(&self) -> DetectorMediaKind {
+        DetectorMediaKind::Image
+    }
+
+    fn info(&self) -> DetectorInfo {
+        DetectorInfo {
+            kind: "image".to_string(),
+            version: "v3".to_string(),
+            description:
+                "EXIF camera and software tags, XMP provenance, PNG generator chunks and resolution"
+                    .to_string(),
+            config_hash: config_hash(&self.config),
+        }
+    }
+
+    fn detect(&self, bytes: &[u8]) -> Result<DetectorOutput> {
+        self.analyse(bytes).map(|(output, _)| output)
+    }
+
+    /// Also records what the metadata claims as provenance facts for `media`.
+    fn detect_with_context(
+        &self,
+        bytes: &[u8],
+        pru: &PruDbHandle,
+        media: MediaId,
+    ) -> Result<DetectorOutput> {
+        let (output, claims) = self.analyse(bytes)?;
+        if !claims.is_empty() {
+            let detector = register_detector(pru, &self.id(), &self.info())?;
+            for claim in &claims {
+                add_provenance_claim(pru, media, claim, detector)?;
+            }
+        }
+        Ok(output)
+    }
+}
+
+/// Stable hash of a detector config, recorded so score changes can be traced to tuning.
+fn config_hash<T: Serialize>(config: &T) -> Option<String> {
+    serde_json::to_vec(config)
+        .ok()
+        .map(|bytes| pru_media_schema::hash_bytes(&bytes))
+}
+
+/// Per-detector overrides for the built-in detectors; missing sections keep their defaults.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RegistryConfig {
+    pub text_complexity: TextComplexityConfig,
+    pub text_statistics: TextStatisticsConfig,
+    pub text_unicode_anomaly: TextUnicodeAnomalyConfig,
+    pub image_metadata: ImageMetadataConfig,
+    pub phash: PHashConfig,
+    pub ela: ElaConfig,
+    pub spectral_image: SpectralImageConfig,
+    pub audio_spectral: AudioSpectralConfig,
+    pub video_metadata: VideoMetadataConfig,
+    /// Local ONNX image classifier; only registered when configured.
+    #[cfg(feature = "onnx")]
+    pub onnx_image: Option<OnnxImageConfig>,
+}
+
+impl DetectorRegistry {
+    /// Registry holding every built-in detector, configured from `config`.
+    ///
+    /// Fails when a configured detector cannot be constructed, e.g. a missing model file.
+    pub fn from_config(config: &RegistryConfig) -> Result<Self> {
+        let mut registry = Self::new();
+        registry.register(Arc::new(TextComplexityDetector::new(
+            config.text_complexity.clone(),
+        )))?;
+        registry.register(Arc::new(TextStatisticsDetector::new(
+            config.text_statistics.clone(),
+        )))?;
+        registry.register(Arc::new(TextUnicodeAnomalyDetector::new(
+            config.text_unicode_anomaly.clone(),
+        )))?;
+        registry.register(Arc::new(ImageMetadataDetector::new(
+            config.image_metadata.clone(),
+        )))?;
+        registry.register(Arc::new(PHashDetector::new(config.phash.clone())))?;
+        registry.register(Arc::new(ElaDetector::new(config.ela.clone())))?;
+        registry.register(Arc::new(SpectralImageDetector::new(
+            config.spectral_image.clone(),
+        )))?;
+        registry.register(Arc::new(AudioSpectralDetector::new(
+            config.audio_spectral.clone(),
+        )))?;
+        registry.register(Arc::new(
+            VideoMetadataDetector::new(config.video_metadata.clone())
+                .with_keyframe_detector(ImageMetadataDetector::new(config.image_metadata.clone())),
+        ))?;
+        #[cfg(feature = "onnx")]
+        if let Some(onnx) = &config.onnx_image {
+            registry.register(Arc::new(OnnxImageDetector::new(onnx.clone())?))?;
+        }
+        Ok(registry)
     }
 }
 
@@ -164,3 +841,103 @@ pub fn media_type_to_kind(media_type: MediaType) -> DetectorMediaKind {
         MediaType::Video => DetectorMediaKind::Video,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scores_between_thresholds_are_unknown() {
+        let policy = LabelPolicy::default();
+        assert_eq!(policy.label(0.9), DetectorLabel::Ai);
+        assert_eq!(policy.label(0.5), DetectorLabel::Unknown);
+        assert_eq!(policy.label(0.1), DetectorLabel::Human);
+
+        // "a b c d" scores exactly 0.4 * (1 - 1/10) = 0.36; "a a b b" adds repetition.
+        let detector = TextComplexityDetector::new(TextComplexityConfig {
+            ai_threshold: 0.6,
+            human_threshold: 0.3,
+            ..Default::default()
+        });
+        assert_eq!(
+            detector.detect(b"a b c d").unwrap().label,
+            DetectorLabel::Unknown
+        );
+        assert_eq!(
+            detector.detect(b"a a b b").unwrap().label,
+            DetectorLabel::Ai
+        );
+    }
+
+    /// Text detector with an arbitrary id, for exercising registration checks.
+    struct Named(&'static str);
+
+    impl MediaDetector for Named {
+        fn id(&self) -> String {
+            self.0.to_string()
+        }
+
+        fn kind(&self) -> DetectorMediaKind {
+            DetectorMediaKind::Text
+        }
+
+        fn detect(&self, _bytes: &[u8]) -> Result<DetectorOutput> {
+            bail!("not used")
+        }
+    }
+
+    #[test]
+    fn duplicate_and_invalid_ids_are_rejected() {
+        let mut registry = DetectorRegistry::new();
+        registry
+            .register(Arc::new(TextComplexityDetector::default()))
+            .unwrap();
+        let err = registry
+            .register(Arc::new(TextComplexityDetector::default()))
+            .unwrap_err();
+        assert!(err.to_string().contains("already registered"));
+        assert_eq!(registry.ids().len(), 1);
+
+        for bad in [
+            "",
+            "  ",
+            "detector:text:has space",
+            "detector:image:wrong_kind",
+        ] {
+            assert!(registry.register(Arc::new(Named(bad))).is_err(), "{bad:?}");
+        }
+        registry.register(Arc::new(Named("my-plugin"))).unwrap();
+    }
+
+    #[test]
+    fn replace_swaps_the_detector_and_keeps_its_settings() {
+        let mut registry = DetectorRegistry::new();
+        assert!(!registry
+            .register_or_replace(Arc::new(TextComplexityDetector::default()))
+            .unwrap());
+        registry.set_enabled("detector:text:complexity_v1", false);
+        registry.set_timeout("detector:text:complexity_v1", Duration::from_secs(3));
+
+        let tuned = TextComplexityDetector::new(TextComplexityConfig {
+            avg_len_norm: 6.0,
+            ..Default::default()
+        });
+        let tuned_hash = tuned.info().config_hash;
+        assert!(registry.register_or_replace(Arc::new(tuned)).unwrap());
+
+        assert_eq!(
+            registry.ids(),
+            vec![(
+                "detector:text:complexity_v1".to_string(),
+                DetectorMediaKind::Text,
+                false
+            )]
+        );
+        let current = registry.get("detector:text:complexity_v1").unwrap();
+        assert_eq!(current.info().config_hash, tuned_hash);
+        assert_eq!(
+            registry.timeout_for("detector:text:complexity_v1"),
+            Duration::from_secs(3)
+        );
+    }
+}
@@ -0,0 +1,240 @@
+//! Local ONNX image classifier, built with the `onnx` feature.
+//!
+//! The model takes one `[1, 3, height, width]` f32 tensor and returns a single
+//! logit (or probability, see [`OnnxImageConfig::apply_sigmoid`]).
+
+use anyhow::{bail, Context, Result};
+use image::imageops::FilterType;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+use tract_onnx::prelude::*;
+
+use crate::{config_hash, DetectorMediaKind, DetectorOutput, LabelPolicy, MediaDetector};
+use pru_media_schema::{DetectorInfo, FeatureValue};
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OnnxImageConfig {
+    pub model_path: PathBuf,
+    pub model_name: String,
+    pub model_version: String,
+    /// Images are resized to exactly this size before inference.
+    pub input_width: u32,
+    pub input_height: u32,
+    /// Per-channel RGB normalization applied to pixels scaled to [0, 1].
+    pub mean: [f32; 3],
+    pub std: [f32; 3],
+    /// Set to false when the model already ends in a sigmoid.
+    pub apply_sigmoid: bool,
+    /// Scores above this are labelled AI.
+    pub ai_threshold: f32,
+    /// Scores below this are labelled human; the band in between is unknown.
+    pub human_threshold: f32,
+}
+
+impl Default for OnnxImageConfig {
+    fn default() -> Self {
+        Self {
+            model_path: PathBuf::new(),
+            model_name: "onnx_image".to_string(),
+            model_version: "v1".to_string(),
+            input_width: 224,
+            input_height: 224,
+            mean: [0.485, 0.456, 0.406],
+            std: [0.229, 0.224, 0.225],
+            apply_sigmoid: true,
+            ai_threshold: 0.5,
+            human_threshold: 0.3,
+        }
+    }
+}
+
+type OnnxPlan = TypedSimplePlan<TypedModel>;
+
+#[derive(Clone)]
+pub struct OnnxImageDetector {
+    config: OnnxImageConfig,
+    model: Arc<OnnxPlan>,
+}
+
+impl OnnxImageDetector {
+    /// Load and optimize the model; fails on a missing file or a graph that does not
+    /// map the configured input shape to a single output value.
+    pub fn new(config: OnnxImageConfig) -> Result<Self> {
+        let path = &config.model_path;
+        if config.input_width == 0 || config.input_height == 0 {
+            bail!("onnx input size must be non-zero");
+        }
+        let shape = [
+            1,
+            3,
+            config.input_height as usize,
+            config.input_width as usize,
+        ];
+        let model = tract_onnx::onnx()
+            .model_for_path(path)
+            .with_context(|| format!("loading onnx model {}", path.display()))?
+            .with_input_fact(0, f32::fact(shape).into())?
+            .into_optimized()
+            .with_context(|| format!("onnx model {} rejects input {shape:?}", path.display()))?;
+        let output = model.output_fact(0)?;
+        let volume = output
+            .shape
+            .as_concrete()
+            .map(|dims| dims.iter().product::<usize>());
+        if volume != Some(1) {
+            bail!(
+                "onnx model {} must produce a single value, got shape {:?}",
+                path.display(),
+                output.shape
+            );
+        }
+        let model = model.into_runnable()?;
+        Ok(Self {
+            config,
+            model: Arc::new(model),
+        })
+    }
+
+    fn preprocess(&self, bytes: &[u8]) -> Result<Tensor> {
+        let cfg = &self.config;
+        let img = image::load_from_memory(bytes)
+            .context("decoding image")?
+            .resize_exact(cfg.input_width, cfg.input_height, FilterType::Triangle)
+            .to_rgb8();
+        let shape = (1, 3, cfg.input_height as usize, cfg.input_width as usize);
+        let input = tract_ndarray::Array4::from_shape_fn(shape, |(_, c, y, x)| {
+            let value = img.get_pixel(x as u32, y as u32)[c] as f32 / 255.0;
+            (value - cfg.mean[c]) / cfg.std[c]
+        });
+        Ok(input.into())
+    }
+}
+
+impl MediaDetector for OnnxImageDetector {
+    fn id(&self) -> String {
+        format!("detector:image:onnx_{}", self.config.model_name)
+    }
+
+    fn kind(&self) -> DetectorMediaKind {
+        DetectorMediaKind::Image
+    }
+
+    fn info(&self) -> DetectorInfo {
+        DetectorInfo {
+            kind: "image".to_string(),
+            version: self.config.model_version.clone(),
+            description: format!("ONNX classifier {}", self.config.model_name),
+            config_hash: config_hash(&self.config),
+        }
+    }
+
+    fn detect(&self, bytes: &[u8]) -> Result<DetectorOutput> {
+        let input = self.preprocess(bytes)?;
+        let outputs = self.model.run(tvec!(input.into()))?;
+        let raw = *outputs[0]
+            .to_array_view::<f32>()?
+            .iter()
+            .next()
+            .context("onnx model returned an empty tensor")?;
+        let score_ai = if self.config.apply_sigmoid {
+            1.0 / (1.0 + (-raw).exp())
+        } else {
+            raw
+        }
+        .clamp(0.0, 1.0);
+        let label =
+            LabelPolicy::new(self.config.ai_threshold, self.config.human_threshold).label(score_ai);
+        let features =
+            BTreeMap::from([("model_output".to_string(), FeatureValue::F64(raw as f64))]);
+        Ok(DetectorOutput {
+            score_ai,
+            label,
+            details: Some(format!(
+                "model={} version={} output={raw:.4}",
+                self.config.model_name, self.config.model_version
+            )),
+            features,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DetectorLabel;
+
+    fn fixture_config() -> OnnxImageConfig {
+        OnnxImageConfig {
+            model_path: concat!(
+                env!("CARGO_MANIFEST_DIR"),
+                "/tests/fixtures/mean_logit.onnx"
+            )
+            .into(),
+            model_name: "mean_logit".to_string(),
+            input_width: 8,
+            input_height: 8,
+            mean: [0.5; 3],
+            std: [0.5; 3],
+            ..OnnxImageConfig::default()
+        }
+    }
+
+    fn solid_png(value: u8) -> Vec<u8> {
+        let img = image::RgbImage::from_pixel(16, 16, image::Rgb([value; 3]));
+        let mut buf = Vec::new();
+        image::DynamicImage::ImageRgb8(img)
+            .write_to(&mut std::io::Cursor::new(&mut buf), image::ImageFormat::Png)
+            .unwrap();
+        buf
+    }
+
+    #[test]
+    fn fixture_model_scores_mean_brightness() {
+        let detector = OnnxImageDetector::new(fixture_config()).unwrap();
+        assert_eq!(detector.id(), "detector:image:onnx_mean_logit");
+
+        // The fixture graph is ReduceMean over the normalized input: white maps to 1.0.
+        let white = detector.detect(&solid_png(255)).unwrap();
+        assert_eq!(white.label, DetectorLabel::Ai);
+        assert!((white.score_ai - 0.731).abs() < 0.01);
+        assert!(white
+            .details
+            .unwrap()
+            .contains("model=mean_logit version=v1"));
+
+        let black = detector.detect(&solid_png(0)).unwrap();
+        assert_eq!(black.label, DetectorLabel::Human);
+        assert!(detector.detect(b"not an image").is_err());
+    }
+
+    #[test]
+    fn construction_errors_surface_early() {
+        let missing = OnnxImageConfig {
+            model_path: "does/not/exist.onnx".into(),
+            ..fixture_config()
+        };
+        assert!(OnnxImageDetector::new(missing).is_err());
+
+        let empty = OnnxImageConfig {
+            input_width: 0,
+            ..fixture_config()
+        };
+        assert!(OnnxImageDetector::new(empty).is_err());
+    }
+
+    #[test]
+    fn scores_between_the_thresholds_are_unknown() {
+        let detector = OnnxImageDetector::new(fixture_config()).unwrap();
+        // Just below mid-grey normalizes to a small negative logit, scoring about 0.49.
+        let grey = detector.detect(&solid_png(120)).unwrap();
+        assert!(
+            grey.score_ai > 0.3 && grey.score_ai < 0.5,
+            "{}",
+            grey.score_ai
+        );
+        assert_eq!(grey.label, DetectorLabel::Unknown);
+    }
+}
@@ -0,0 +1,183 @@
+//! Perceptual hashing for near-duplicate images.
+//!
+//! Re-uploads, recompressions and mild edits of an image keep its dHash within a
+//! few bits, so a new image can inherit what is already known about its twins.
+
+use anyhow::{Context, Result};
+use image::imageops::FilterType;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use crate::{config_hash, DetectorLabel, DetectorMediaKind, DetectorOutput, MediaDetector};
+use pru_core::PruDbHandle;
+use pru_media_schema::{
+    find_phash_matches, get_human_verdicts, DetectorInfo, FeatureValue, MediaId,
+};
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PHashConfig {
+    /// Indexed hashes at most this many bits apart count as near duplicates when
+    /// scoring. Values of 8 or more may miss matches, see
+    /// [`pru_media_schema::PHASH_BANDS`].
+    pub max_distance: u32,
+}
+
+impl Default for PHashConfig {
+    fn default() -> Self {
+        Self { max_distance: 6 }
+    }
+}
+
+/// 64-bit difference hash: each bit says whether a pixel of the 9x8 grayscale
+/// thumbnail is brighter than its right-hand neighbour.
+pub fn dhash(bytes: &[u8]) -> Result<u64> {
+    let thumb = image::load_from_memory(bytes)
+        .context("decoding image")?
+        .resize_exact(9, 8, FilterType::Triangle)
+        .to_luma8();
+    let mut hash = 0u64;
+    for y in 0..8 {
+        for x in 0..8 {
+            let bit = thumb.get_pixel(x, y)[0] > thumb.get_pixel(x + 1, y)[0];
+            hash = (hash << 1) | bit as u64;
+        }
+    }
+    Ok(hash)
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct PHashDetector {
+    config: PHashConfig,
+}
+
+impl PHashDetector {
+    pub fn new(config: PHashConfig) -> Self {
+        Self { config }
+    }
+
+    fn output(
+        &self,
+        hash: u64,
+        verdicts: Option<(usize, usize)>,
+        matches: usize,
+    ) -> DetectorOutput {
+        let mut features = BTreeMap::from([
+            (
+                "phash".to_string(),
+                FeatureValue::Str(format!("{hash:016x}")),
+            ),
+            (
+                "phash_matches".to_string(),
+                FeatureValue::I64(matches as i64),
+            ),
+        ]);
+        // Without labelled twins a hash says nothing about authorship.
+        let (score_ai, label) = match verdicts {
+            Some((ai, human)) if ai + human > 0 => {
+                features.insert(
+                    "matched_ai_verdicts".to_string(),
+                    FeatureValue::I64(ai as i64),
+                );
+                features.insert(
+                    "matched_human_verdicts".to_string(),
+                    FeatureValue::I64(human as i64),
+                );
+                let score = ai as f32 / (ai + human) as f32;
+                let label = match score {
+                    s if s > 0.5 => DetectorLabel::Ai,
+                    s if s < 0.5 => DetectorLabel::Human,
+                    _ => DetectorLabel::Unknown,
+                };
+                (score, label)
+            }
+            _ => (0.5, DetectorLabel::Unknown),
+        };
+        DetectorOutput {
+            score_ai,
+            label,
+            details: Some(format!("dhash {hash:016x}, {matches} near duplicates")),
+            features,
+        }
+    }
+}
+
+impl MediaDetector for PHashDetector {
+    fn id(&self) -> String {
+        "detector:image:phash_v1".to_string()
+    }
+
+    fn kind(&self) -> DetectorMediaKind {
+        DetectorMediaKind::Image
+    }
+
+    fn info(&self) -> DetectorInfo {
+        DetectorInfo {
+            kind: "image".to_string(),
+            version: "v1".to_string(),
+            description: "dHash near-duplicate search over previously ingested images".to_string(),
+            config_hash: config_hash(&self.config),
+        }
+    }
+
+    fn detect(&self, bytes: &[u8]) -> Result<DetectorOutput> {
+        Ok(self.output(dhash(bytes)?, None, 0))
+    }
+
+    /// Score from the human verdicts on near duplicates already in the hash
+    /// index. Ingest indexes the reported `phash` feature and writes the
+    /// `similar_to` links afterwards.
+    fn detect_with_context(
+        &self,
+        bytes: &[u8],
+        pru: &PruDbHandle,
+        media: MediaId,
+    ) -> Result<DetectorOutput> {
+        let hash = dhash(bytes)?;
+        let matches: Vec<(MediaId, u32)> = find_phash_matches(pru, hash, self.config.max_distance)?
+            .into_iter()
+            .filter(|(other, _)| *other != media)
+            .collect();
+        if matches.is_empty() {
+            return Ok(self.output(hash, None, 0));
+        }
+
+        let (mut ai, mut human) = (0, 0);
+        for (other, _) in &matches {
+            for verdict in get_human_verdicts(pru, *other)? {
+                match verdict.to_ascii_lowercase().as_str() {
+                    "ai" => ai += 1,
+                    "human" => human += 1,
+                    _ => {}
+                }
+            }
+        }
+        Ok(self.output(hash, Some((ai, human)), matches.len()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn png(f: impl Fn(u32, u32) -> [u8; 3]) -> Vec<u8> {
+        let img = image::RgbImage::from_fn(64, 64, |x, y| image::Rgb(f(x, y)));
+        let mut buf = Vec::new();
+        image::DynamicImage::ImageRgb8(img)
+            .write_to(&mut std::io::Cursor::new(&mut buf), image::ImageFormat::Png)
+            .unwrap();
+        buf
+    }
+
+    #[test]
+    fn dhash_survives_brightening_but_not_new_content() {
+        let base = |x: u32, y: u32| [((x * 7 + y * 3) % 200) as u8, (y * 4) as u8, 90];
+        let original = dhash(&png(base)).unwrap();
+        let brighter = dhash(&png(|x, y| base(x, y).map(|c| c.saturating_add(30)))).unwrap();
+        let other = dhash(&png(|x, y| [(255 - x * 4) as u8, ((x ^ y) * 4) as u8, 10])).unwrap();
+
+        assert!((original ^ brighter).count_ones() <= PHashConfig::default().max_distance);
+        assert!((original ^ other).count_ones() > 16);
+        assert!(dhash(b"not an image").is_err());
+    }
+}
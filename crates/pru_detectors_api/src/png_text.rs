@@ -0,0 +1,214 @@
+//! PNG `tEXt`/`iTXt` chunk reading for generator metadata.
+//!
+//! Stable Diffusion front-ends write their generation parameters into text
+//! chunks, which is the strongest single signal we have for AI images.
+
+const PNG_SIGNATURE: &[u8] = &[0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A];
+const MAX_PROMPT_CHARS: usize = 200;
+
+/// Uncompressed text chunks as `(keyword, text)` pairs, in file order.
+///
+/// Compressed `zTXt` and compressed `iTXt` chunks are skipped. Malformed chunk
+/// lengths end the walk instead of panicking.
+pub fn png_text_chunks(bytes: &[u8]) -> Vec<(String, String)> {
+    let mut out = Vec::new();
+    if !bytes.starts_with(PNG_SIGNATURE) {
+        return out;
+    }
+    let mut pos = PNG_SIGNATURE.len();
+    while pos + 8 <= bytes.len() {
+        let len = u32::from_be_bytes([bytes[pos], bytes[pos + 1], bytes[pos + 2], bytes[pos + 3]])
+            as usize;
+        let kind = &bytes[pos + 4..pos + 8];
+        let Some(data) = bytes.get(pos + 8..pos + 8 + len) else {
+            break;
+        };
+        match kind {
+            b"tEXt" => {
+                if let Some((key, text)) = split_nul(data) {
+                    out.push((latin1(key), latin1(text)));
+                }
+            }
+            b"iTXt" => {
+                if let Some(pair) = parse_itxt(data) {
+                    out.push(pair);
+                }
+            }
+            b"IEND" => break,
+            _ => {}
+        }
+        // Skip the CRC as well.
+        pos += 12 + len;
+    }
+    out
+}
+
+fn split_nul(data: &[u8]) -> Option<(&[u8], &[u8])> {
+    let nul = data.iter().position(|b| *b == 0)?;
+    Some((&data[..nul], &data[nul + 1..]))
+}
+
+fn latin1(raw: &[u8]) -> String {
+    raw.iter().map(|b| *b as char).collect()
+}
+
+/// keyword\0 compression_flag compression_method language\0 translated\0 text
+fn parse_itxt(data: &[u8]) -> Option<(String, String)> {
+    let (key, rest) = split_nul(data)?;
+    let (&compressed, rest) = rest.split_first()?;
+    if compressed != 0 {
+        return None;
+    }
+    let rest = rest.get(1..)?;
+    let (_language, rest) = split_nul(rest)?;
+    let (_translated, text) = split_nul(rest)?;
+    Some((latin1(key), String::from_utf8_lossy(text).into_owned()))
+}
+
+/// What gave a generator away, plus the prompt when one was recorded.
+#[derive(Clone, Debug, PartialEq)]
+pub struct GeneratorHint {
+    pub generator: String,
+    pub prompt: Option<String>,
+}
+
+/// Look for Stable Diffusion, ComfyUI, InvokeAI, NovelAI and Midjourney markers.
+pub fn generator_hint(chunks: &[(String, String)]) -> Option<GeneratorHint> {
+    let mut hint: Option<GeneratorHint> = None;
+    let mut found = |generator: &str, prompt: Option<String>| {
+        if hint.is_none() {
+            hint = Some(GeneratorHint {
+                generator: generator.to_string(),
+                prompt,
+            });
+        } else if let (Some(h), Some(p)) = (hint.as_mut(), prompt) {
+            h.prompt.get_or_insert(p);
+        }
+    };
+    for (key, text) in chunks {
+        let lower = text.to_ascii_lowercase();
+        match key.to_ascii_lowercase().as_str() {
+            // AUTOMATIC1111 / Forge: prompt first, then "Negative prompt:" and "Steps:" lines.
+            "parameters" => {
+                let prompt = text
+                    .split("Negative prompt:")
+                    .next()
+                    .and_then(|p| p.lines().next())
+                    .map(str::to_string);
+                found("automatic1111", prompt);
+            }
+            // ComfyUI stores its graph as JSON under both keys.
+            "prompt" | "workflow" if text.trim_start().starts_with('{') => found("comfyui", None),
+            "invokeai_metadata" | "sd-metadata" | "dream" => found("invokeai", None),
+            "software" | "source" if lower.contains("novelai") => found("novelai", None),
+            _ => {}
+        }
+        if lower.contains("made with midjourney") || lower.contains("midjourney job id") {
+            found("midjourney", None);
+        }
+        if lower.contains("negative prompt:") {
+            found("stable_diffusion", None);
+        }
+    }
+    hint
+}
+
+/// Prompt shortened to one line for the human-readable details summary.
+pub(crate) fn details_prompt(prompt: &str) -> String {
+    prompt
+        .chars()
+        .filter(|c| !c.is_control())
+        .take(MAX_PROMPT_CHARS)
+        .collect::<String>()
+        .trim()
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{DetectorLabel, ImageMetadataDetector, MediaDetector};
+    use pru_media_schema::FeatureValue;
+
+    fn crc32(bytes: &[u8]) -> u32 {
+        let mut crc = 0xFFFF_FFFFu32;
+        for b in bytes {
+            crc ^= *b as u32;
+            for _ in 0..8 {
+                crc = if crc & 1 == 1 {
+                    (crc >> 1) ^ 0xEDB8_8320
+                } else {
+                    crc >> 1
+                };
+            }
+        }
+        !crc
+    }
+
+    fn plain_png() -> Vec<u8> {
+        let img = image::RgbImage::from_pixel(4, 4, image::Rgb([10, 20, 30]));
+        let mut buf = Vec::new();
+        image::DynamicImage::ImageRgb8(img)
+            .write_to(&mut std::io::Cursor::new(&mut buf), image::ImageFormat::Png)
+            .unwrap();
+        buf
+    }
+
+    /// Insert a chunk right after IHDR (signature + 25-byte IHDR chunk).
+    fn with_chunk(png: &[u8], kind: &[u8; 4], data: &[u8]) -> Vec<u8> {
+        let at = PNG_SIGNATURE.len() + 25;
+        let mut chunk = (data.len() as u32).to_be_bytes().to_vec();
+        chunk.extend_from_slice(kind);
+        chunk.extend_from_slice(data);
+        chunk.extend_from_slice(&crc32(&chunk[4..]).to_be_bytes());
+        let mut out = png[..at].to_vec();
+        out.extend(chunk);
+        out.extend_from_slice(&png[at..]);
+        out
+    }
+
+    #[test]
+    fn a1111_parameters_flag_ai_with_prompt() {
+        let params = b"parameters\0a castle on a hill, sunset\nNegative prompt: blurry\nSteps: 20, Sampler: Euler a";
+        let png = with_chunk(&plain_png(), b"tEXt", params);
+        let hint = generator_hint(&png_text_chunks(&png)).unwrap();
+        assert_eq!(hint.generator, "automatic1111");
+        assert_eq!(hint.prompt.as_deref(), Some("a castle on a hill, sunset"));
+
+        let out = ImageMetadataDetector::default().detect(&png).unwrap();
+        assert_eq!(out.label, DetectorLabel::Ai);
+        assert!(out.score_ai >= 0.95);
+        assert!(out.details.unwrap().contains("png_generator=automatic1111"));
+        assert_eq!(
+            out.features.get("prompt"),
+            Some(&FeatureValue::Str("a castle on a hill, sunset".into()))
+        );
+    }
+
+    #[test]
+    fn itxt_midjourney_and_plain_png() {
+        let mut itxt = b"Description\0\0\0\0\0".to_vec();
+        itxt.extend_from_slice("Made with Midjourney".as_bytes());
+        let png = with_chunk(&plain_png(), b"iTXt", &itxt);
+        assert_eq!(
+            generator_hint(&png_text_chunks(&png)).unwrap().generator,
+            "midjourney"
+        );
+
+        let plain = plain_png();
+        assert!(generator_hint(&png_text_chunks(&plain)).is_none());
+        let out = ImageMetadataDetector::default().detect(&plain).unwrap();
+        assert_eq!(out.label, DetectorLabel::Human);
+    }
+
+    #[test]
+    fn malformed_lengths_do_not_panic() {
+        let mut png = PNG_SIGNATURE.to_vec();
+        png.extend_from_slice(&u32::MAX.to_be_bytes());
+        png.extend_from_slice(b"tEXt");
+        png.extend_from_slice(b"parameters\0x");
+        assert!(png_text_chunks(&png).is_empty());
+        assert!(png_text_chunks(&png[..10]).is_empty());
+        assert!(parse_itxt(b"key\0\x01").is_none());
+    }
+}
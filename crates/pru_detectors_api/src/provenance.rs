@@ -0,0 +1,420 @@
+//! Camera and generator provenance from EXIF tags and the XMP packet.
+//!
+//! Metadata is trivially stripped or forged, so it only nudges scores; what it
+//! claims is still worth keeping as provenance facts.
+
+use pru_media_schema::{is_algorithmic_source, ProvenanceClaim};
+
+const XMP_START: &[u8] = b"<x:xmpmeta";
+const XMP_END: &[u8] = b"</x:xmpmeta>";
+
+/// Lowercase needle and display name of generators that sign their output.
+const GENERATORS: &[(&str, &str)] = &[
+    ("stable diffusion", "Stable Diffusion"),
+    ("dall-e", "DALL·E"),
+    ("dall·e", "DALL·E"),
+    ("midjourney", "Midjourney"),
+    ("firefly", "Adobe Firefly"),
+];
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ImageProvenance {
+    pub make: Option<String>,
+    pub model: Option<String>,
+    pub software: Option<String>,
+    pub date_time_original: Option<String>,
+    pub lens: Option<String>,
+    pub has_gps: bool,
+    pub creator_tool: Option<String>,
+    /// Last path segment of the IPTC digital source type, e.g. `digitalCapture`.
+    pub digital_source_type: Option<String>,
+    /// A C2PA manifest is embedded as JUMBF or referenced from the XMP packet.
+    pub c2pa: bool,
+    /// Known generator named by the software tag, the creator tool or the XMP packet.
+    pub generator: Option<String>,
+}
+
+impl ImageProvenance {
+    pub fn read(bytes: &[u8]) -> Self {
+        let mut found = Self::default();
+        let mut cursor = std::io::Cursor::new(bytes);
+        if let Ok(exif) = exif::Reader::new().read_from_container(&mut cursor) {
+            for field in exif.fields() {
+                if field.tag.context() == exif::Context::Gps {
+                    found.has_gps = true;
+                }
+                let slot = match field.tag {
+                    exif::Tag::Make => &mut found.make,
+                    exif::Tag::Model => &mut found.model,
+                    exif::Tag::Software => &mut found.software,
+                    exif::Tag::DateTimeOriginal => &mut found.date_time_original,
+                    exif::Tag::LensModel => &mut found.lens,
+                    _ => continue,
+                };
+                if slot.is_none() {
+                    *slot = ascii(&field.value);
+                }
+            }
+        }
+
+        let packet = xmp_packet(bytes);
+        if let Some(packet) = packet {
+            found.creator_tool = xmp_value(packet, "CreatorTool");
+            found.digital_source_type = xmp_value(packet, "DigitalSourceType")
+                .map(|uri| uri.rsplit('/').next().unwrap_or(&uri).to_string());
+        }
+        found.c2pa = packet.is_some_and(|p| contains_ignore_case(p.as_bytes(), b"c2pa"))
+            || jumbf_boxes(bytes)
+                .into_iter()
+                .any(|b| contains_ignore_case(b, b"c2pa"));
+        found.generator = [
+            found.software.as_deref(),
+            found.creator_tool.as_deref(),
+            packet,
+        ]
+        .into_iter()
+        .flatten()
+        .find_map(generator_in);
+        found
+    }
+
+    pub fn has_camera_metadata(&self) -> bool {
+        self.make.is_some()
+            || self.model.is_some()
+            || self.date_time_original.is_some()
+            || self.lens.is_some()
+            || self.has_gps
+    }
+
+    /// "Make Model", without repeating the make when the model already starts with it.
+    pub fn camera(&self) -> Option<String> {
+        match (self.make.as_deref(), self.model.as_deref()) {
+            (Some(make), Some(model)) if model.starts_with(make) => Some(model.to_string()),
+            (Some(make), Some(model)) => Some(format!("{make} {model}")),
+            (make, model) => make.or(model).map(str::to_string),
+        }
+    }
+
+    pub fn algorithmic_source(&self) -> bool {
+        self.digital_source_type
+            .as_deref()
+            .is_some_and(is_algorithmic_source)
+    }
+
+    /// A generator tag or an algorithmic digital source type.
+    pub fn names_generator(&self) -> bool {
+        self.generator.is_some() || self.algorithmic_source()
+    }
+
+    /// A named camera plus capture details, with nothing pointing at a generator.
+    pub fn consistent_camera(&self) -> bool {
+        self.camera().is_some()
+            && (self.date_time_original.is_some() || self.lens.is_some() || self.has_gps)
+            && !self.names_generator()
+    }
+
+    /// Claims read from the metadata. A generator wins over a camera, so no capture
+    /// claim is made when the metadata also names a generator.
+    pub fn claims(&self) -> Vec<ProvenanceClaim> {
+        let mut claims = Vec::new();
+        if let Some(device) = self.camera().filter(|_| !self.names_generator()) {
+            claims.push(ProvenanceClaim::CapturedByDevice { device });
+        }
+        if let Some(model) = &self.generator {
+            claims.push(ProvenanceClaim::GeneratedByModel {
+                model: model.clone(),
+            });
+        }
+        let other = [
+            ("software", self.software.clone()),
+            ("date_time_original", self.date_time_original.clone()),
+            ("lens", self.lens.clone()),
+            ("gps", self.has_gps.then(|| "present".to_string())),
+            ("creator_tool", self.creator_tool.clone()),
+            ("digital_source_type", self.digital_source_type.clone()),
+            ("c2pa", self.c2pa.then(|| "manifest referenced".to_string())),
+        ];
+        for (key, value) in other {
+            if let Some(value) = value {
+                claims.push(ProvenanceClaim::Other {
+                    key: key.to_string(),
+                    value,
+                });
+            }
+        }
+        claims
+    }
+}
+
+fn ascii(value: &exif::Value) -> Option<String> {
+    let exif::Value::Ascii(parts) = value else {
+        return None;
+    };
+    let text = String::from_utf8_lossy(parts.first()?);
+    let text = text.trim_matches(|c: char| c == '\0' || c.is_whitespace());
+    (!text.is_empty()).then(|| text.to_string())
+}
+
+fn generator_in(text: &str) -> Option<String> {
+    let lower = text.to_lowercase();
+    GENERATORS
+        .iter()
+        .find(|(needle, _)| lower.contains(needle))
+        .map(|(_, name)| name.to_string())
+}
+
+fn find(haystack: &[u8], needle: &[u8], from: usize) -> Option<usize> {
+    haystack
+        .get(from..)?
+        .windows(needle.len())
+        .position(|w| w == needle)
+        .map(|i| i + from)
+}
+
+fn contains_ignore_case(haystack: &[u8], needle: &[u8]) -> bool {
+    haystack
+        .windows(needle.len())
+        .any(|w| w.eq_ignore_ascii_case(needle))
+}
+
+/// Payloads of the segments C2PA manifests are embedded in: JPEG APP11 and PNG
+/// `caBX`. Pixel data and other segments are never searched, so a stray "c2pa"
+/// in compressed data does not count.
+fn jumbf_boxes(bytes: &[u8]) -> Vec<&[u8]> {
+    const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+    let be16 = |at: usize| {
+        bytes
+            .get(at..at + 2)
+            .map(|b| u16::from_be_bytes([b[0], b[1]]) as usize)
+    };
+    let be32 = |at: usize| {
+        bytes
+            .get(at..at + 4)
+            .map(|b| u32::from_be_bytes([b[0], b[1], b[2], b[3]]) as usize)
+    };
+    let mut boxes = Vec::new();
+    if bytes.starts_with(&[0xFF, 0xD8]) {
+        let mut at = 2;
+        while bytes.get(at) == Some(&0xFF) {
+            let Some(&marker) = bytes.get(at + 1) else {
+                break;
+            };
+            match marker {
+                0xFF => at += 1,
+                // Standalone markers carry no length.
+                0x01 | 0xD0..=0xD7 => at += 2,
+                // Start of scan: entropy-coded data follows.
+                0xD9 | 0xDA => break,
+                _ => {
+                    let Some(len) = be16(at + 2).filter(|len| *len >= 2) else {
+                        break;
+                    };
+                    let Some(payload) = bytes.get(at + 4..at + 2 + len) else {
+                        break;
+                    };
+                    if marker == 0xEB {
+                        boxes.push(payload);
+                    }
+                    at += 2 + len;
+                }
+            }
+        }
+    } else if bytes.starts_with(PNG_SIGNATURE) {
+        let mut at = PNG_SIGNATURE.len();
+        while let (Some(len), Some(kind)) = (be32(at), bytes.get(at + 4..at + 8)) {
+            let Some(data) = bytes.get(at + 8..at + 8 + len) else {
+                break;
+            };
+            match kind {
+                b"caBX" => boxes.push(data),
+                b"IEND" => break,
+                _ => {}
+            }
+            at += 12 + len;
+        }
+    }
+    boxes
+}
+
+/// The first `<x:xmpmeta>` packet in the file, wherever the container put it.
+pub fn xmp_packet(bytes: &[u8]) -> Option<&str> {
+    let start = find(bytes, XMP_START, 0)?;
+    let end = find(bytes, XMP_END, start)? + XMP_END.len();
+    std::str::from_utf8(&bytes[start..end]).ok()
+}
+
+/// Value of `prefix:name` written either as an attribute or as a simple element.
+fn xmp_value(packet: &str, name: &str) -> Option<String> {
+    let lower = packet.to_ascii_lowercase();
+    let key = format!(":{}", name.to_ascii_lowercase());
+    let mut from = 0;
+    while let Some(pos) = lower[from..].find(&key) {
+        let after = from + pos + key.len();
+        let rest = packet[after..].trim_start();
+        let value = if let Some(rest) = rest.strip_prefix('=') {
+            let rest = rest.trim_start();
+            rest.chars()
+                .next()
+                .filter(|c| *c == '"' || *c == '\'')
+                .and_then(|quote| rest[1..].split(quote).next())
+        } else if let Some(rest) = rest.strip_prefix('>') {
+            rest.split('<').next()
+        } else {
+            None
+        };
+        if let Some(value) = value.map(str::trim).filter(|v| !v.is_empty()) {
+            return Some(value.replace("&amp;", "&"));
+        }
+        from = after;
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{DetectorLabel, ImageMetadataDetector, MediaDetector};
+    use pru_media_schema::{get_provenance_claims, upsert_media_entity, FeatureValue, MediaType};
+
+    const CAMERA: &[u8] = include_bytes!("../tests/fixtures/exif_camera.jpg");
+    const FIREFLY: &[u8] = include_bytes!("../tests/fixtures/xmp_firefly.jpg");
+
+    #[test]
+    fn camera_exif_is_read_and_lowers_the_score() {
+        let read = ImageProvenance::read(CAMERA);
+        assert_eq!(read.camera().as_deref(), Some("Canon EOS R5"));
+        assert_eq!(
+            read.date_time_original.as_deref(),
+            Some("2024:05:01 10:20:30")
+        );
+        assert_eq!(read.lens.as_deref(), Some("RF24-105mm F4 L IS USM"));
+        assert!(read.has_gps);
+        assert!(read.consistent_camera());
+
+        let mut stripped = Vec::new();
+        image::load_from_memory(CAMERA)
+            .unwrap()
+            .write_to(
+                &mut std::io::Cursor::new(&mut stripped),
+                image::ImageFormat::Png,
+            )
+            .unwrap();
+        let detector = ImageMetadataDetector::default();
+        let with_exif = detector.detect(CAMERA).unwrap();
+        let without = detector.detect(&stripped).unwrap();
+        assert!(with_exif.score_ai < without.score_ai);
+        assert_eq!(with_exif.label, DetectorLabel::Human);
+        assert!(with_exif
+            .details
+            .unwrap()
+            .contains("camera metadata from Canon EOS R5"));
+        assert_eq!(
+            with_exif.features.get("has_gps"),
+            Some(&FeatureValue::Bool(true))
+        );
+    }
+
+    #[test]
+    fn firefly_xmp_is_flagged_and_claims_are_stored() {
+        let out = ImageMetadataDetector::default().detect(FIREFLY).unwrap();
+        assert_eq!(out.label, DetectorLabel::Ai);
+        assert_eq!(out.features.get("c2pa"), Some(&FeatureValue::Bool(true)));
+        let details = out.details.unwrap();
+        assert!(details.contains("metadata names Adobe Firefly"));
+        assert!(details.contains("trainedAlgorithmicMedia"));
+
+        let dir = tempfile::tempdir().unwrap();
+        let pru = std::sync::Arc::new(std::sync::Mutex::new(
+            pru_core::PruStore::open(dir.path()).unwrap(),
+        ));
+        let media = upsert_media_entity(&pru, "firefly", MediaType::Image).unwrap();
+        ImageMetadataDetector::default()
+            .detect_with_context(FIREFLY, &pru, media)
+            .unwrap();
+        let claims = get_provenance_claims(&pru, media).unwrap();
+        assert_eq!(
+            claims[0],
+            ProvenanceClaim::GeneratedByModel {
+                model: "Adobe Firefly".into()
+            }
+        );
+        assert!(claims.contains(&ProvenanceClaim::Other {
+            key: "c2pa".into(),
+            value: "manifest referenced".into()
+        }));
+    }
+
+    #[test]
+    fn c2pa_is_only_found_in_jumbf_segments_and_xmp() {
+        assert!(!ImageProvenance::read(CAMERA).c2pa);
+
+        // A manifest in an APP11 segment right after the start-of-image marker.
+        let payload = b"JP\0\0jumb c2pa manifest";
+        let mut app11 = vec![0xFF, 0xEB];
+        app11.extend_from_slice(&(payload.len() as u16 + 2).to_be_bytes());
+        app11.extend_from_slice(payload);
+        let signed = [&CAMERA[..2], &app11, &CAMERA[2..]].concat();
+        assert!(ImageProvenance::read(&signed).c2pa);
+
+        // The same bytes in a comment segment, or after the image, are ignored.
+        let mut comment = app11.clone();
+        comment[1] = 0xFE;
+        let commented = [&CAMERA[..2], &comment, &CAMERA[2..]].concat();
+        assert!(!ImageProvenance::read(&commented).c2pa);
+        let trailing = [CAMERA, payload.as_slice()].concat();
+        assert!(!ImageProvenance::read(&trailing).c2pa);
+    }
+
+    #[test]
+    fn xmp_values_are_read_from_attributes_and_elements() {
+        let packet = r#"<x:xmpmeta xmlns:x="adobe:ns:meta/"><rdf:Description
+            xmp:CreatorTool = "Adobe Firefly" >
+            <Iptc4xmpExt:DigitalSourceType>http://cv.iptc.org/newscodes/digitalsourcetype/trainedAlgorithmicMedia</Iptc4xmpExt:DigitalSourceType>
+            </rdf:Description></x:xmpmeta>"#;
+        let bytes = [b"junk".as_slice(), packet.as_bytes(), b"more"].concat();
+        let xmp = xmp_packet(&bytes).unwrap();
+        assert_eq!(xmp, packet);
+        assert_eq!(
+            xmp_value(xmp, "CreatorTool").as_deref(),
+            Some("Adobe Firefly")
+        );
+        let read = ImageProvenance::read(&bytes);
+        assert_eq!(
+            read.digital_source_type.as_deref(),
+            Some("trainedAlgorithmicMedia")
+        );
+        assert!(read.algorithmic_source());
+        assert_eq!(read.generator.as_deref(), Some("Adobe Firefly"));
+        assert!(!read.has_camera_metadata());
+    }
+
+    #[test]
+    fn a_generator_tag_suppresses_the_capture_claim() {
+        let camera = ImageProvenance {
+            make: Some("Canon".into()),
+            model: Some("Canon EOS R5".into()),
+            ..Default::default()
+        };
+        assert!(camera
+            .claims()
+            .contains(&ProvenanceClaim::CapturedByDevice {
+                device: "Canon EOS R5".into()
+            }));
+        for tagged in [
+            ImageProvenance {
+                generator: Some("Adobe Firefly".into()),
+                ..camera.clone()
+            },
+            ImageProvenance {
+                digital_source_type: Some("trainedAlgorithmicMedia".into()),
+                ..camera.clone()
+            },
+        ] {
+            let claims = tagged.claims();
+            assert!(!claims
+                .iter()
+                .any(|c| matches!(c, ProvenanceClaim::CapturedByDevice { .. })));
+            assert!(claims.iter().any(|c| c.generator().is_some()));
+        }
+    }
+}
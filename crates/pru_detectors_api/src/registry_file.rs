@@ -0,0 +1,323 @@
+//! Building a [`DetectorRegistry`] from a TOML file that lists each detector.
+//!
+//! Unlike [`RegistryConfig`](crate::RegistryConfig), which tunes the fixed set of
+//! built-ins, the file decides which detectors exist at all:
+//!
+//! ```toml
+//! [[detector]]
+//! type = "text_complexity"
+//! ai_threshold = 0.6
+//!
+//! [[detector]]
+//! type = "subprocess"
+//! id = "detector:text:my_plugin"
+//! kind = "text"
+//! command = "./my_plugin.py"
+//!
+//! [[detector]]
+//! type = "remote_http"
+//! id = "detector:image:service"
+//! kind = "image"
+//! endpoint = "http://127.0.0.1:8080/detect"
+//! enabled = false
+//! ```
+//!
+//! Every table takes `type` and an optional `enabled`; the remaining keys are the
+//! fields of that detector's config. Relative paths with a directory part are
+//! resolved against the directory holding the file; a bare subprocess command
+//! name is looked up on `PATH`.
+
+use anyhow::{anyhow, bail, Context, Result};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::{
+    AsyncMediaDetector, AudioSpectralDetector, BlockingDetector, DetectorRegistry, ElaDetector,
+    ImageMetadataConfig, ImageMetadataDetector, MediaDetector, PHashDetector, RemoteHttpDetector,
+    SpectralImageDetector, SubprocessDetector, TextComplexityDetector, TextStatisticsDetector,
+    TextUnicodeAnomalyDetector, VideoMetadataDetector,
+};
+#[cfg(feature = "onnx")]
+use crate::{OnnxImageConfig, OnnxImageDetector};
+
+/// Values accepted for `type`.
+pub const DETECTOR_TYPES: &[&str] = &[
+    "text_complexity",
+    "text_statistics",
+    "text_unicode_anomaly",
+    "image_metadata",
+    "phash",
+    "ela",
+    "spectral_image",
+    "audio_spectral",
+    "video_metadata",
+    "onnx_image",
+    "subprocess",
+    "remote_http",
+];
+
+impl DetectorRegistry {
+    /// Registry holding exactly the detectors listed in the TOML file at `path`.
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let raw = std::fs::read_to_string(path)
+            .with_context(|| format!("reading registry config {}", path.display()))?;
+        let base_dir = path.parent().unwrap_or(Path::new("."));
+        Self::from_toml_str(&raw, base_dir)
+            .with_context(|| format!("loading registry config {}", path.display()))
+    }
+
+    /// Like [`from_file`](Self::from_file), resolving relative paths against `base_dir`.
+    pub fn from_toml_str(raw: &str, base_dir: &Path) -> Result<Self> {
+        let mut root: toml::Table = toml::from_str(raw).context("parsing TOML")?;
+        let tables = match root.remove("detector") {
+            Some(toml::Value::Array(tables)) => tables,
+            Some(_) => bail!("`detector` must be an array of tables, written [[detector]]"),
+            None => Vec::new(),
+        };
+        if let Some(key) = root.keys().next() {
+            bail!("unknown top-level key `{key}`; detectors go in [[detector]] tables");
+        }
+
+        let mut registry = Self::new();
+        for (index, table) in tables.into_iter().enumerate() {
+            let label = describe(index, &table);
+            let toml::Value::Table(table) = table else {
+                bail!("{label}: expected a table");
+            };
+            let (detector, enabled) = build(table, base_dir).with_context(|| label.clone())?;
+            let id = detector.id();
+            registry.register_async(detector).context(label)?;
+            if !enabled {
+                registry.set_enabled(&id, false);
+            }
+        }
+        Ok(registry)
+    }
+}
+
+/// `detector #N (type = "...")`, 1-based to match how people count tables.
+fn describe(index: usize, table: &toml::Value) -> String {
+    match table.get("type").and_then(|t| t.as_str()) {
+        Some(kind) => format!("detector #{} (type = {kind:?})", index + 1),
+        None => format!("detector #{}", index + 1),
+    }
+}
+
+fn build(mut table: toml::Table, base_dir: &Path) -> Result<(Arc<dyn AsyncMediaDetector>, bool)> {
+    let kind = match table.remove("type") {
+        Some(toml::Value::String(kind)) => kind,
+        Some(other) => bail!("`type` must be a string, got {}", other.type_str()),
+        None => bail!(
+            "missing `type`; expected one of {}",
+            DETECTOR_TYPES.join(", ")
+        ),
+    };
+    let enabled = match table.remove("enabled") {
+        Some(toml::Value::Boolean(enabled)) => enabled,
+        Some(other) => bail!("`enabled` must be a boolean, got {}", other.type_str()),
+        None => true,
+    };
+    let detector: Arc<dyn AsyncMediaDetector> = match kind.as_str() {
+        "text_complexity" => blocking(TextComplexityDetector::new(parse(table)?)),
+        "text_statistics" => blocking(TextStatisticsDetector::new(parse(table)?)),
+        "text_unicode_anomaly" => blocking(TextUnicodeAnomalyDetector::new(parse(table)?)),
+        "image_metadata" => blocking(ImageMetadataDetector::new(parse(table)?)),
+        "phash" => blocking(PHashDetector::new(parse(table)?)),
+        "ela" => blocking(ElaDetector::new(parse(table)?)),
+        "spectral_image" => blocking(SpectralImageDetector::new(parse(table)?)),
+        "audio_spectral" => blocking(AudioSpectralDetector::new(parse(table)?)),
+        "video_metadata" => {
+            // Keyframes are scored like images, tuned by an optional `keyframe` table.
+            let keyframe = match table.remove("keyframe") {
+                Some(toml::Value::Table(keyframe)) => parse(keyframe)?,
+                Some(other) => bail!("`keyframe` must be a table, got {}", other.type_str()),
+                None => ImageMetadataConfig::default(),
+            };
+            blocking(
+                VideoMetadataDetector::new(parse(table)?)
+                    .with_keyframe_detector(ImageMetadataDetector::new(keyframe)),
+            )
+        }
+        #[cfg(feature = "onnx")]
+        "onnx_image" => {
+            let mut config: OnnxImageConfig = parse(table)?;
+            if config.model_path.is_relative() {
+                config.model_path = base_dir.join(&config.model_path);
+            }
+            blocking(OnnxImageDetector::new(config)?)
+        }
+        #[cfg(not(feature = "onnx"))]
+        "onnx_image" => bail!("onnx_image needs pru_detectors_api built with the `onnx` feature"),
+        "subprocess" => {
+            let mut detector: SubprocessDetector = parse(table)?;
+            if detector.command.is_relative() && detector.command.components().count() > 1 {
+                detector.command = base_dir.join(&detector.command);
+            }
+            blocking(detector)
+        }
+        "remote_http" => {
+            let detector: RemoteHttpDetector = parse(table)?;
+            detector.validate()?;
+            Arc::new(detector)
+        }
+        other => bail!(
+            "unknown detector type {other:?}; expected one of {}",
+            DETECTOR_TYPES.join(", ")
+        ),
+    };
+    Ok((detector, enabled))
+}
+
+fn blocking(detector: impl MediaDetector + 'static) -> Arc<dyn AsyncMediaDetector> {
+    Arc::new(BlockingDetector(Arc::new(detector)))
+}
+
+/// Deserialize `table` as `T`, rejecting keys that `T` silently ignored.
+fn parse<T: Serialize + DeserializeOwned>(table: toml::Table) -> Result<T> {
+    let parsed: T = toml::Value::Table(table.clone()).try_into()?;
+    // Anything `T` understood comes back out when it is serialized again.
+    let known = toml::Value::try_from(&parsed).map_err(|e| anyhow!(e))?;
+    let known = known.as_table().cloned().unwrap_or_default();
+    let mut unknown: Vec<&str> = table
+        .keys()
+        .filter(|key| !known.contains_key(*key))
+        .map(String::as_str)
+        .collect();
+    unknown.sort_unstable();
+    if !unknown.is_empty() {
+        bail!("unknown field(s) {}", unknown.join(", "));
+    }
+    Ok(parsed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DetectorMediaKind;
+
+    const EVERY_TYPE: &str = r#"
+        [[detector]]
+        type = "text_complexity"
+        ai_threshold = 0.7
+
+        [[detector]]
+        type = "text_statistics"
+
+        [[detector]]
+        type = "text_unicode_anomaly"
+        enabled = false
+
+        [[detector]]
+        type = "image_metadata"
+
+        [[detector]]
+        type = "phash"
+
+        [[detector]]
+        type = "ela"
+        quality = 85
+
+        [[detector]]
+        type = "spectral_image"
+
+        [[detector]]
+        type = "audio_spectral"
+
+        [[detector]]
+        type = "video_metadata"
+        keyframe = { exif_ai_hint = 0.8 }
+
+        [[detector]]
+        type = "subprocess"
+        id = "detector:text:plugin"
+        kind = "text"
+        command = "./plugin.sh"
+        args = ["--fast"]
+
+        [[detector]]
+        type = "remote_http"
+        id = "detector:image:remote"
+        kind = "image"
+        endpoint = "http://127.0.0.1:9/detect"
+        timeout_ms = 500
+        headers = { "X-Api-Key" = "secret" }
+    "#;
+
+    #[test]
+    fn every_built_in_type_is_registered() {
+        let registry = DetectorRegistry::from_toml_str(EVERY_TYPE, Path::new("/plugins")).unwrap();
+        let ids = registry.ids();
+        assert_eq!(ids.len(), 11);
+        assert!(ids.contains(&(
+            "detector:text:plugin".to_string(),
+            DetectorMediaKind::Text,
+            true
+        )));
+        let disabled: Vec<_> = ids.iter().filter(|(_, _, on)| !on).collect();
+        assert_eq!(disabled.len(), 1);
+        assert_eq!(registry.for_media(DetectorMediaKind::Image).len(), 5);
+
+        let remote = registry.get("detector:image:remote").unwrap();
+        assert!(remote.as_sync().is_none());
+        assert_eq!(remote.info().version, "remote");
+        let plugin = registry.get("detector:text:plugin").unwrap();
+        assert!(plugin.info().description.contains(
+            &Path::new("/plugins")
+                .join("./plugin.sh")
+                .display()
+                .to_string()
+        ));
+
+        let ela = registry.get("detector:image:ela_v1").unwrap().info();
+        let default_ela = ElaDetector::default().info();
+        assert_ne!(ela.config_hash, default_ela.config_hash);
+
+        #[cfg(not(feature = "onnx"))]
+        {
+            let err = DetectorRegistry::from_toml_str(
+                "[[detector]]\ntype = \"onnx_image\"\nmodel_path = \"m.onnx\"\n",
+                Path::new("."),
+            )
+            .err()
+            .expect("onnx_image should be rejected");
+            assert!(format!("{err:#}").contains("`onnx` feature"));
+        }
+    }
+
+    #[test]
+    fn invalid_entries_name_the_offending_table() {
+        let err = |raw: &str| {
+            let config = format!("[[detector]]\ntype = \"phash\"\n\n[[detector]]\n{raw}");
+            format!(
+                "{:#}",
+                DetectorRegistry::from_toml_str(&config, Path::new("."))
+                    .err()
+                    .expect("config should be rejected")
+            )
+        };
+
+        let typo = err("type = \"ela\"\nqualty = 80\n");
+        assert!(typo.starts_with("detector #2 (type = \"ela\")"), "{typo}");
+        assert!(typo.contains("unknown field(s) qualty"), "{typo}");
+
+        let bad_kind = err(
+            "type = \"remote_http\"\nid = \"x\"\nkind = \"hologram\"\nendpoint = \"http://h/\"\n",
+        );
+        assert!(bad_kind.contains("detector #2"), "{bad_kind}");
+        assert!(bad_kind.contains("hologram"), "{bad_kind}");
+
+        let unknown = err("type = \"crystal_ball\"\n");
+        assert!(
+            unknown.contains("unknown detector type \"crystal_ball\""),
+            "{unknown}"
+        );
+        assert!(err("enabled = true\n").contains("missing `type`"));
+        assert!(err("type = \"phash\"\n").contains("already registered"));
+        assert!(err(
+            "type = \"remote_http\"\nid = \"x\"\nkind = \"text\"\nendpoint = \"ftp://h/\"\n"
+        )
+        .contains("must start with http:// or https://"));
+    }
+}
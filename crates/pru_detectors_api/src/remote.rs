@@ -0,0 +1,317 @@
+//! Detectors served over HTTP.
+//!
+//! The media bytes are POSTed to `endpoint` as `application/octet-stream` and the
+//! response body must be one JSON `DetectorOutput`, the same contract as a
+//! subprocess plugin. Both `http://` and `https://` endpoints work; responses
+//! over `max_response_bytes` are refused rather than buffered.
+
+use anyhow::{bail, Context, Result};
+use async_trait::async_trait;
+use reqwest::header::{ACCEPT, CONTENT_TYPE};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use crate::subprocess::parse_output;
+use crate::{config_hash, AsyncMediaDetector, DetectorMediaKind, DetectorOutput};
+use pru_media_schema::DetectorInfo;
+
+fn default_timeout_ms() -> u64 {
+    10_000
+}
+
+fn default_max_response_bytes() -> usize {
+    1 << 20
+}
+
+/// One client for every remote detector, so connections are pooled.
+fn client() -> &'static reqwest::Client {
+    static CLIENT: OnceLock<reqwest::Client> = OnceLock::new();
+    CLIENT.get_or_init(reqwest::Client::new)
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct RemoteHttpDetector {
+    pub id: String,
+    pub kind: DetectorMediaKind,
+    /// `http(s)://host[:port]/path` that accepts the POST.
+    pub endpoint: String,
+    #[serde(default = "default_timeout_ms")]
+    pub timeout_ms: u64,
+    /// Larger response bodies fail the detector.
+    #[serde(default = "default_max_response_bytes")]
+    pub max_response_bytes: usize,
+    /// Extra request headers, e.g. an API key.
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+}
+
+impl RemoteHttpDetector {
+    pub fn new(id: &str, kind: DetectorMediaKind, endpoint: &str) -> Self {
+        Self {
+            id: id.to_string(),
+            kind,
+            endpoint: endpoint.to_string(),
+            timeout_ms: default_timeout_ms(),
+            max_response_bytes: default_max_response_bytes(),
+            headers: BTreeMap::new(),
+        }
+    }
+
+    pub fn with_header(mut self, name: &str, value: &str) -> Self {
+        self.headers.insert(name.to_string(), value.to_string());
+        self
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout_ms = timeout.as_millis() as u64;
+        self
+    }
+
+    pub fn with_max_response_bytes(mut self, max: usize) -> Self {
+        self.max_response_bytes = max;
+        self
+    }
+
+    /// Reject endpoints this detector cannot reach before anything is registered.
+    pub fn validate(&self) -> Result<()> {
+        parse_endpoint(&self.endpoint).map(|_| ())
+    }
+
+    async fn post(&self, bytes: &[u8]) -> Result<Vec<u8>> {
+        let url = parse_endpoint(&self.endpoint)?;
+        let mut request = client()
+            .post(url)
+            .header(CONTENT_TYPE, "application/octet-stream")
+            .header(ACCEPT, "application/json")
+            .body(bytes.to_vec());
+        for (name, value) in &self.headers {
+            request = request.header(name, value);
+        }
+        let mut response = request
+            .send()
+            .await
+            .with_context(|| format!("posting to {}", self.endpoint))?;
+        let too_large = || {
+            anyhow::anyhow!(
+                "response from {} is over {} bytes",
+                self.endpoint,
+                self.max_response_bytes
+            )
+        };
+        if response
+            .content_length()
+            .is_some_and(|len| len > self.max_response_bytes as u64)
+        {
+            return Err(too_large());
+        }
+        let mut body = Vec::new();
+        while let Some(chunk) = response
+            .chunk()
+            .await
+            .with_context(|| format!("reading response from {}", self.endpoint))?
+        {
+            if body.len() + chunk.len() > self.max_response_bytes {
+                return Err(too_large());
+            }
+            body.extend_from_slice(&chunk);
+        }
+        let status = response.status();
+        if !status.is_success() {
+            bail!(
+                "server answered {}: {}",
+                status.as_u16(),
+                String::from_utf8_lossy(&body).trim()
+            );
+        }
+        Ok(body)
+    }
+}
+
+/// The fields that decide what score comes back; transport settings and
+/// headers (which may hold credentials) stay out of the config hash.
+#[derive(Serialize)]
+struct ScoringConfig<'a> {
+    id: &'a str,
+    kind: DetectorMediaKind,
+    endpoint: &'a str,
+}
+
+fn parse_endpoint(endpoint: &str) -> Result<reqwest::Url> {
+    let url = reqwest::Url::parse(endpoint)
+        .with_context(|| format!("endpoint {endpoint:?} is not a valid URL"))?;
+    if !matches!(url.scheme(), "http" | "https") {
+        bail!("endpoint {endpoint:?} must start with http:// or https://");
+    }
+    if url.host_str().is_none_or(str::is_empty) {
+        bail!("endpoint {endpoint:?} has no host");
+    }
+    Ok(url)
+}
+
+#[async_trait]
+impl AsyncMediaDetector for RemoteHttpDetector {
+    fn id(&self) -> String {
+        self.id.clone()
+    }
+
+    fn kind(&self) -> DetectorMediaKind {
+        self.kind
+    }
+
+    async fn detect(&self, bytes: &[u8]) -> Result<DetectorOutput> {
+        let timeout = Duration::from_millis(self.timeout_ms);
+        let raw = tokio::time::timeout(timeout, self.post(bytes))
+            .await
+            .map_err(|_| {
+                anyhow::anyhow!(
+                    "remote detector {} timed out after {}ms",
+                    self.id,
+                    self.timeout_ms
+                )
+            })?
+            .with_context(|| format!("remote detector {}", self.id))?;
+        parse_output(&raw).with_context(|| format!("remote detector {}", self.id))
+    }
+
+    fn info(&self) -> DetectorInfo {
+        DetectorInfo {
+            kind: format!("{:?}", self.kind).to_lowercase(),
+            version: "remote".to_string(),
+            description: format!("Remote HTTP detector {}", self.endpoint),
+            config_hash: config_hash(&ScoringConfig {
+                id: &self.id,
+                kind: self.kind,
+                endpoint: &self.endpoint,
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DetectorLabel;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    /// Answer one request with `response`, handing back what the client sent.
+    async fn serve_once(response: &'static str) -> (String, tokio::task::JoinHandle<Vec<u8>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/detect", listener.local_addr().unwrap());
+        let server = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut request = Vec::new();
+            let mut buf = [0u8; 1024];
+            // The client sends a fixed-size body, so stop once it has all arrived.
+            loop {
+                let n = socket.read(&mut buf).await.unwrap();
+                request.extend_from_slice(&buf[..n]);
+                if n == 0 || request.ends_with(b"payload") {
+                    break;
+                }
+            }
+            socket.write_all(response.as_bytes()).await.unwrap();
+            request
+        });
+        (url, server)
+    }
+
+    #[tokio::test]
+    async fn remote_output_is_parsed() {
+        let (url, server) = serve_once(
+            "HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n\
+             12\r\n{\"score_ai\": 0.8, \r\n\
+             18\r\n\"label\": \"ai\", \"details\"\r\n\
+             7\r\n: \"hi\"}\r\n0\r\n\r\n",
+        )
+        .await;
+        let detector =
+            RemoteHttpDetector::new("detector:text:remote", DetectorMediaKind::Text, &url)
+                .with_header("X-Api-Key", "secret");
+        let out = detector.detect(b"payload").await.unwrap();
+        assert_eq!(out.label, DetectorLabel::Ai);
+        assert_eq!(out.details.as_deref(), Some("hi"));
+
+        let request = String::from_utf8(server.await.unwrap())
+            .unwrap()
+            .to_ascii_lowercase();
+        assert!(request.starts_with("post /detect http/1.1\r\n"));
+        assert!(request.contains("x-api-key: secret\r\n"));
+        assert!(request.contains("content-length: 7\r\n"));
+    }
+
+    #[tokio::test]
+    async fn error_status_and_bad_endpoints_fail() {
+        let (url, _server) =
+            serve_once("HTTP/1.1 503 Service Unavailable\r\nContent-Length: 4\r\n\r\nbusy").await;
+        let detector = RemoteHttpDetector::new("detector:text:down", DetectorMediaKind::Text, &url);
+        let err = format!("{:#}", detector.detect(b"payload").await.unwrap_err());
+        assert!(err.contains("503"), "{err}");
+        assert!(err.contains("busy"), "{err}");
+
+        for bad in [
+            "ftp://example.com/x",
+            "http://:80/",
+            "http://host:port/",
+            "example.com",
+        ] {
+            let detector =
+                RemoteHttpDetector::new("detector:text:bad", DetectorMediaKind::Text, bad);
+            assert!(detector.validate().is_err(), "{bad}");
+        }
+        RemoteHttpDetector::new(
+            "detector:text:tls",
+            DetectorMediaKind::Text,
+            "https://example.com/x",
+        )
+        .validate()
+        .unwrap();
+    }
+
+    #[tokio::test]
+    async fn oversized_responses_are_refused() {
+        let (url, _server) = serve_once(
+            "HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n\
+             10\r\n0123456789abcdef\r\n10\r\n0123456789abcdef\r\n0\r\n\r\n",
+        )
+        .await;
+        let detector = RemoteHttpDetector::new("detector:text:big", DetectorMediaKind::Text, &url)
+            .with_max_response_bytes(20);
+        let err = format!("{:#}", detector.detect(b"payload").await.unwrap_err());
+        assert!(err.contains("over 20 bytes"), "{err}");
+
+        let (url, _server) = serve_once(
+            "HTTP/1.1 200 OK\r\nContent-Length: 33\r\n\r\n{\"score_ai\": 0.8, \"label\": \"ai\"}",
+        )
+        .await;
+        let detector = RemoteHttpDetector::new("detector:text:big", DetectorMediaKind::Text, &url)
+            .with_max_response_bytes(20);
+        let err = format!("{:#}", detector.detect(b"payload").await.unwrap_err());
+        assert!(err.contains("over 20 bytes"), "{err}");
+    }
+
+    #[test]
+    fn config_hash_ignores_headers_and_transport() {
+        let base = RemoteHttpDetector::new(
+            "detector:text:remote",
+            DetectorMediaKind::Text,
+            "https://example.com/detect",
+        );
+        let hash = base.info().config_hash;
+        assert!(hash.is_some());
+        let tuned = base
+            .clone()
+            .with_header("Authorization", "Bearer secret")
+            .with_timeout(Duration::from_secs(60))
+            .with_max_response_bytes(64);
+        assert_eq!(tuned.info().config_hash, hash);
+
+        let moved = RemoteHttpDetector {
+            endpoint: "https://example.com/v2/detect".to_string(),
+            ..base
+        };
+        assert_ne!(moved.info().config_hash, hash);
+    }
+}
@@ -0,0 +1,167 @@
+//! Media kind from the leading bytes, for uploads whose content type is missing or wrong.
+//!
+//! Only well-known signatures are trusted; anything else is `None` rather than a guess.
+
+use pru_media_schema::MediaType;
+
+use crate::DetectorMediaKind;
+
+/// How much of the input the text heuristic looks at.
+const TEXT_PROBE: usize = 4096;
+
+/// ISO-BMFF major brands that hold still images rather than video.
+const IMAGE_BRANDS: &[&[u8; 4]] = &[b"avif", b"avis", b"heic", b"heix", b"mif1", b"msf1"];
+const AUDIO_BRANDS: &[&[u8; 4]] = &[b"M4A ", b"M4B ", b"M4P "];
+
+/// Kind of media in `bytes`, judged from magic numbers and, failing that, whether
+/// the start reads as UTF-8 text.
+pub fn sniff_media_kind(bytes: &[u8]) -> Option<DetectorMediaKind> {
+    use DetectorMediaKind::*;
+
+    let at = |offset: usize, magic: &[u8]| bytes.get(offset..offset + magic.len()) == Some(magic);
+
+    if at(0, b"\x89PNG\r\n\x1a\n") || at(0, &[0xFF, 0xD8, 0xFF]) {
+        return Some(Image);
+    }
+    if at(0, b"GIF87a") || at(0, b"GIF89a") {
+        return Some(Image);
+    }
+    if at(0, b"RIFF") {
+        return match bytes.get(8..12)? {
+            b"WEBP" => Some(Image),
+            b"WAVE" => Some(Audio),
+            b"AVI " => Some(Video),
+            _ => None,
+        };
+    }
+    if at(4, b"ftyp") {
+        let brand: &[u8; 4] = bytes.get(8..12)?.try_into().ok()?;
+        return Some(if IMAGE_BRANDS.contains(&brand) {
+            Image
+        } else if AUDIO_BRANDS.contains(&brand) {
+            Audio
+        } else {
+            Video
+        });
+    }
+    if at(0, &[0x1A, 0x45, 0xDF, 0xA3]) {
+        return Some(Video);
+    }
+    if at(0, b"OggS") {
+        // Theora streams announce themselves in the first page.
+        let head = &bytes[..bytes.len().min(512)];
+        let theora = head.windows(7).any(|w| w == b"\x80theora");
+        return Some(if theora { Video } else { Audio });
+    }
+    if at(0, b"fLaC") || at(0, b"ID3") || is_mpeg_audio_frame(bytes) {
+        return Some(Audio);
+    }
+    looks_like_text(bytes).then_some(Text)
+}
+
+/// [`sniff_media_kind`] as the store's [`MediaType`].
+pub fn sniff_media_type(bytes: &[u8]) -> Option<MediaType> {
+    sniff_media_kind(bytes).map(kind_to_media_type)
+}
+
+pub fn kind_to_media_type(kind: DetectorMediaKind) -> MediaType {
+    match kind {
+        DetectorMediaKind::Image => MediaType::Image,
+        DetectorMediaKind::Text => MediaType::Text,
+        DetectorMediaKind::Audio => MediaType::Audio,
+        DetectorMediaKind::Video => MediaType::Video,
+    }
+}
+
+/// MPEG audio frame header: 11 sync bits, then a real layer and bitrate.
+fn is_mpeg_audio_frame(bytes: &[u8]) -> bool {
+    let [a, b, c, ..] = bytes else {
+        return false;
+    };
+    let layer = (b >> 1) & 0b11;
+    let bitrate = c >> 4;
+    *a == 0xFF && b & 0xE0 == 0xE0 && layer != 0 && bitrate != 0 && bitrate != 0xF
+}
+
+/// Valid UTF-8 (a character cut off at the probe boundary is fine) with almost no
+/// control characters besides whitespace.
+fn looks_like_text(bytes: &[u8]) -> bool {
+    let probe = &bytes[..bytes.len().min(TEXT_PROBE)];
+    let probe = probe.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(probe);
+    let text = match std::str::from_utf8(probe) {
+        Ok(text) => text,
+        Err(e) if e.error_len().is_none() => {
+            std::str::from_utf8(&probe[..e.valid_up_to()]).unwrap_or_default()
+        }
+        Err(_) => return false,
+    };
+    if text.trim().is_empty() || text.contains('\0') {
+        return false;
+    }
+    let chars = text.chars().count();
+    let control = text
+        .chars()
+        .filter(|c| c.is_control() && !matches!(c, '\n' | '\r' | '\t' | '\x0C'))
+        .count();
+    control * 50 <= chars
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use DetectorMediaKind::*;
+
+    #[test]
+    fn signatures_map_to_kinds() {
+        let cases: &[(&[u8], Option<DetectorMediaKind>)] = &[
+            (b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR", Some(Image)),
+            (&[0xFF, 0xD8, 0xFF, 0xE0, 0, 0x10], Some(Image)),
+            (b"GIF89a\x01\0\x01\0", Some(Image)),
+            (b"RIFF\x24\0\0\0WEBPVP8 ", Some(Image)),
+            (b"\0\0\0\x1cftypavif\0\0\0\0", Some(Image)),
+            (b"RIFF\x24\0\0\0WAVEfmt ", Some(Audio)),
+            (b"ID3\x04\0\0\0\0\0\0", Some(Audio)),
+            (&[0xFF, 0xFB, 0x90, 0x64], Some(Audio)),
+            (b"OggS\0\x02\0\0\0\0\x01vorbis", Some(Audio)),
+            (b"fLaC\0\0\0\x22", Some(Audio)),
+            (b"\0\0\0\x20ftypM4A \0\0\0\0", Some(Audio)),
+            (b"\0\0\0\x18ftypisom\0\0\x02\0", Some(Video)),
+            (b"\0\0\0\x18ftypmp42", Some(Video)),
+            (&[0x1A, 0x45, 0xDF, 0xA3, 0x9F, 0x42, 0x86], Some(Video)),
+            (b"OggS\0\x02\0\0\0\0\x80theora", Some(Video)),
+            (b"RIFF\x24\0\0\0AVI LIST", Some(Video)),
+            ("Plain prose, with ünïcödé.\n".as_bytes(), Some(Text)),
+            (b"\xEF\xBB\xBFbyte order mark", Some(Text)),
+            (b"%PDF-1.7\n\xE2\xE3\xCF\xD3\n1 0 obj", None),
+            (b"\x7FELF\x02\x01\x01\0\0\0", None),
+            (&[0xFF, 0xF1, 0x50, 0x80], None),
+            (b"   \n\t ", None),
+            (b"", None),
+        ];
+        for (bytes, expected) in cases {
+            assert_eq!(sniff_media_kind(bytes), *expected, "{bytes:?}");
+        }
+        assert_eq!(sniff_media_type(b"hello"), Some(MediaType::Text));
+    }
+
+    #[test]
+    fn truncated_headers_do_not_panic() {
+        let samples: [&[u8]; 6] = [
+            b"\x89PNG\r\n\x1a\n",
+            b"RIFF\x24\0\0\0WAVEfmt ",
+            b"\0\0\0\x1cftypavif",
+            b"OggS\0\x02",
+            &[0xFF, 0xFB, 0x90],
+            "naïve".as_bytes(),
+        ];
+        for sample in samples {
+            for end in 0..=sample.len() {
+                let _ = sniff_media_kind(&sample[..end]);
+            }
+        }
+        assert_eq!(sniff_media_kind(b"RIFF\x24\0\0\0WA"), None);
+        assert_eq!(sniff_media_kind(b"\0\0\0\x1cftyp"), None);
+        // A multi-byte character split at the end is still text.
+        assert_eq!(sniff_media_kind(&"naïve".as_bytes()[..3]), Some(Text));
+    }
+}
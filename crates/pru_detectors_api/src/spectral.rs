@@ -0,0 +1,319 @@
+//! Frequency-domain image statistics from tiled 2D DCTs.
+//!
+//! Camera images fall off roughly as 1/f² in power. Diffusion output is often
+//! smoother than that, and upsampling layers leave regular peaks.
+
+use anyhow::{Context, Result};
+use image::imageops::FilterType;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use crate::{
+    config_hash, DetectorLabel, DetectorMediaKind, DetectorOutput, LabelPolicy, MediaDetector,
+};
+use pru_media_schema::{DetectorInfo, FeatureValue};
+
+const TILE: usize = 32;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SpectralImageConfig {
+    /// Larger images are downsampled so their longest side is this many pixels.
+    pub max_dimension: u32,
+    /// At most this many 32x32 tiles are transformed, spread over the image.
+    pub max_tiles: usize,
+    /// Scores above this are labelled AI.
+    pub ai_threshold: f32,
+    /// Scores below this are labelled human; the band in between is unknown.
+    pub human_threshold: f32,
+    /// Radial power slope (log-log) below which the image counts as too smooth.
+    pub natural_slope: f32,
+    /// Height of the strongest off-trend peak, in decades, that counts as a grid artifact.
+    pub peak_decades: f32,
+}
+
+impl Default for SpectralImageConfig {
+    fn default() -> Self {
+        Self {
+            max_dimension: 512,
+            max_tiles: 64,
+            ai_threshold: 0.6,
+            human_threshold: 0.4,
+            natural_slope: -2.0,
+            peak_decades: 3.0,
+        }
+    }
+}
+
+/// Summary of the averaged tile spectrum.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SpectrumStats {
+    pub tiles: usize,
+    /// Least-squares slope of log10 power against log10 radius.
+    pub slope: f64,
+    /// Share of AC power beyond half the maximum radius.
+    pub high_freq_ratio: f64,
+    /// Largest excess of one coefficient over the fitted radial trend, in decades.
+    pub peak: f64,
+}
+
+/// Average the power spectrum of up to `max_tiles` grayscale tiles.
+/// Returns `None` when the image is smaller than one tile.
+pub fn tile_spectrum(gray: &image::GrayImage, max_tiles: usize) -> Option<SpectrumStats> {
+    let (width, height) = (gray.width() as usize, gray.height() as usize);
+    let (cols, rows) = (width / TILE, height / TILE);
+    if cols == 0 || rows == 0 {
+        return None;
+    }
+    let basis = dct_basis();
+    let step = (cols * rows).div_ceil(max_tiles.max(1));
+    let mut power = vec![0.0f64; TILE * TILE];
+    let mut tiles = 0;
+    for index in (0..cols * rows).step_by(step) {
+        let (tx, ty) = ((index % cols) * TILE, (index / cols) * TILE);
+        let mut tile = vec![0.0f64; TILE * TILE];
+        for y in 0..TILE {
+            for x in 0..TILE {
+                tile[y * TILE + x] = gray.get_pixel((tx + x) as u32, (ty + y) as u32)[0] as f64;
+            }
+        }
+        let mean = tile.iter().sum::<f64>() / tile.len() as f64;
+        tile.iter_mut().for_each(|p| *p -= mean);
+        for (acc, c) in power.iter_mut().zip(dct2(&tile, &basis)) {
+            *acc += c * c;
+        }
+        tiles += 1;
+    }
+    power.iter_mut().for_each(|p| *p /= tiles as f64);
+    Some(summarize(&power, tiles))
+}
+
+fn summarize(power: &[f64], tiles: usize) -> SpectrumStats {
+    let radius = |i: usize| (((i / TILE).pow(2) + (i % TILE).pow(2)) as f64).sqrt();
+    let log_power = |p: f64| (p + 1e-9).log10();
+
+    let mut bins = vec![(0.0f64, 0usize); TILE];
+    let (mut total, mut high) = (0.0, 0.0);
+    for (i, p) in power.iter().enumerate().skip(1) {
+        let r = radius(i);
+        total += p;
+        if r > TILE as f64 / 2.0 {
+            high += p;
+        }
+        let bin = r.round() as usize;
+        if bin < TILE {
+            bins[bin].0 += p;
+            bins[bin].1 += 1;
+        }
+    }
+    let points: Vec<(f64, f64)> = bins
+        .iter()
+        .enumerate()
+        .skip(1)
+        .map(|(r, (sum, n))| ((r as f64).log10(), log_power(sum / *n as f64)))
+        .collect();
+    let n = points.len() as f64;
+    let mean_x = points.iter().map(|p| p.0).sum::<f64>() / n;
+    let mean_y = points.iter().map(|p| p.1).sum::<f64>() / n;
+    let sxx: f64 = points.iter().map(|p| (p.0 - mean_x).powi(2)).sum();
+    let sxy: f64 = points.iter().map(|p| (p.0 - mean_x) * (p.1 - mean_y)).sum();
+    let slope = sxy / sxx;
+    let intercept = mean_y - slope * mean_x;
+
+    // The lowest radii carry the image layout, not generator artifacts.
+    let peak = power
+        .iter()
+        .enumerate()
+        .filter(|(i, _)| radius(*i) >= 2.0)
+        .map(|(i, p)| log_power(*p) - (intercept + slope * radius(i).log10()))
+        .fold(0.0f64, f64::max);
+    SpectrumStats {
+        tiles,
+        slope,
+        high_freq_ratio: if total > 0.0 { high / total } else { 0.0 },
+        peak,
+    }
+}
+
+/// Orthonormal DCT-II basis, `basis[k * TILE + n]`.
+fn dct_basis() -> Vec<f64> {
+    let mut basis = vec![0.0; TILE * TILE];
+    for k in 0..TILE {
+        let scale = if k == 0 { 1.0 } else { 2.0f64.sqrt() } / (TILE as f64).sqrt();
+        for n in 0..TILE {
+            let angle = std::f64::consts::PI * (2 * n + 1) as f64 * k as f64 / (2 * TILE) as f64;
+            basis[k * TILE + n] = scale * angle.cos();
+        }
+    }
+    basis
+}
+
+/// Separable 2D DCT of one tile: rows first, then columns.
+fn dct2(tile: &[f64], basis: &[f64]) -> Vec<f64> {
+    let mut rows = vec![0.0; TILE * TILE];
+    for y in 0..TILE {
+        for k in 0..TILE {
+            rows[y * TILE + k] = (0..TILE)
+                .map(|n| basis[k * TILE + n] * tile[y * TILE + n])
+                .sum();
+        }
+    }
+    let mut out = vec![0.0; TILE * TILE];
+    for x in 0..TILE {
+        for k in 0..TILE {
+            out[k * TILE + x] = (0..TILE)
+                .map(|n| basis[k * TILE + n] * rows[n * TILE + x])
+                .sum();
+        }
+    }
+    out
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct SpectralImageDetector {
+    config: SpectralImageConfig,
+}
+
+impl SpectralImageDetector {
+    pub fn new(config: SpectralImageConfig) -> Self {
+        Self { config }
+    }
+}
+
+impl MediaDetector for SpectralImageDetector {
+    fn id(&self) -> String {
+        "detector:image:spectral_v1".to_string()
+    }
+
+    fn kind(&self) -> DetectorMediaKind {
+        DetectorMediaKind::Image
+    }
+
+    fn info(&self) -> DetectorInfo {
+        DetectorInfo {
+            kind: "image".to_string(),
+            version: "v1".to_string(),
+            description: "Radial power slope and periodic peaks of tiled DCT spectra".to_string(),
+            config_hash: config_hash(&self.config),
+        }
+    }
+
+    fn detect(&self, bytes: &[u8]) -> Result<DetectorOutput> {
+        let cfg = &self.config;
+        let mut img = image::load_from_memory(bytes).context("decoding image")?;
+        let max_dimension = cfg.max_dimension.max(TILE as u32);
+        if img.width().max(img.height()) > max_dimension {
+            img = img.resize(max_dimension, max_dimension, FilterType::Triangle);
+        }
+        let Some(stats) = tile_spectrum(&img.to_luma8(), cfg.max_tiles) else {
+            return Ok(DetectorOutput {
+                score_ai: 0.5,
+                label: DetectorLabel::Unknown,
+                details: Some(format!("image smaller than one {TILE}x{TILE} tile")),
+                features: BTreeMap::new(),
+            });
+        };
+
+        let smoothness = ((cfg.natural_slope as f64 - stats.slope) / 1.5).clamp(0.0, 1.0) as f32;
+        let periodicity =
+            (stats.peak / cfg.peak_decades.max(f32::EPSILON) as f64).clamp(0.0, 1.0) as f32;
+        // Either signature on its own is suspicious.
+        let score_ai = 1.0 - (1.0 - smoothness) * (1.0 - periodicity);
+        let label = LabelPolicy::new(cfg.ai_threshold, cfg.human_threshold).label(score_ai);
+        let features = BTreeMap::from([
+            ("spectral_slope".to_string(), FeatureValue::F64(stats.slope)),
+            (
+                "high_freq_ratio".to_string(),
+                FeatureValue::F64(stats.high_freq_ratio),
+            ),
+            ("spectral_peak".to_string(), FeatureValue::F64(stats.peak)),
+            ("tiles".to_string(), FeatureValue::I64(stats.tiles as i64)),
+        ]);
+        Ok(DetectorOutput {
+            score_ai,
+            label,
+            details: Some(format!(
+                "slope {:.2}, high-frequency share {:.3}, peak {:.2} decades over {} tiles",
+                stats.slope, stats.high_freq_ratio, stats.peak, stats.tiles
+            )),
+            features,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn png(width: u32, height: u32, mut f: impl FnMut(u32, u32) -> u8) -> Vec<u8> {
+        let img = image::GrayImage::from_fn(width, height, |x, y| image::Luma([f(x, y)]));
+        let mut buf = Vec::new();
+        image::DynamicImage::ImageLuma8(img)
+            .write_to(&mut std::io::Cursor::new(&mut buf), image::ImageFormat::Png)
+            .unwrap();
+        buf
+    }
+
+    fn feature(out: &DetectorOutput, name: &str) -> f64 {
+        match out.features.get(name) {
+            Some(FeatureValue::F64(v)) => *v,
+            other => panic!("missing {name}: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn checkerboard_and_noise_have_different_spectra() {
+        let detector = SpectralImageDetector::default();
+        let checker = detector
+            .detect(&png(
+                128,
+                128,
+                |x, y| if (x + y) % 2 == 0 { 255 } else { 0 },
+            ))
+            .unwrap();
+        let mut seed = 0x9E37_79B9u32;
+        let noise = detector
+            .detect(&png(128, 128, |_, _| {
+                seed ^= seed << 13;
+                seed ^= seed >> 17;
+                seed ^= seed << 5;
+                seed as u8
+            }))
+            .unwrap();
+
+        assert!(feature(&checker, "spectral_slope") > feature(&noise, "spectral_slope") + 1.0);
+        assert!(feature(&checker, "spectral_peak") > feature(&noise, "spectral_peak") + 2.0);
+        assert!(feature(&checker, "high_freq_ratio") > 0.9);
+        assert!(checker.score_ai > noise.score_ai);
+    }
+
+    #[test]
+    fn large_images_are_downsampled_and_tiny_ones_are_unknown() {
+        let detector = SpectralImageDetector::new(SpectralImageConfig {
+            max_dimension: 64,
+            ..Default::default()
+        });
+        let out = detector
+            .detect(&png(1024, 512, |x, y| ((x * 3 + y) % 256) as u8))
+            .unwrap();
+        assert_eq!(out.features.get("tiles"), Some(&FeatureValue::I64(2)));
+
+        let tiny = detector.detect(&png(16, 16, |x, _| x as u8)).unwrap();
+        assert_eq!(tiny.label, DetectorLabel::Unknown);
+    }
+
+    #[test]
+    fn scores_between_the_thresholds_are_unknown() {
+        let image = png(64, 64, |x, y| ((x * 5 + y * 3) % 256) as u8);
+        let score = SpectralImageDetector::default()
+            .detect(&image)
+            .unwrap()
+            .score_ai;
+        let banded = SpectralImageDetector::new(SpectralImageConfig {
+            ai_threshold: score + 0.05,
+            human_threshold: score - 0.05,
+            ..Default::default()
+        });
+        assert_eq!(banded.detect(&image).unwrap().label, DetectorLabel::Unknown);
+    }
+}
@@ -0,0 +1,410 @@
+//! Detectors implemented as external programs.
+//!
+//! A plugin reads the media bytes on stdin and prints one JSON `DetectorOutput`
+//! on stdout, e.g. `{"score_ai": 0.8, "label": "ai", "details": "..."}`. It is
+//! described by a TOML file in the plugins directory:
+//!
+//! ```toml
+//! id = "detector:text:my_plugin"
+//! kind = "text"
+//! command = "./my_plugin.py"
+//! args = ["--fast"]
+//! timeout_ms = 5000
+//! max_output_bytes = 1048576
+//! ```
+//!
+//! Output over `max_output_bytes` fails the detector rather than being buffered.
+
+use anyhow::{anyhow, bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::{config_hash, DetectorMediaKind, DetectorOutput, DetectorRegistry, MediaDetector};
+use pru_core::PruDbHandle;
+use pru_media_schema::{DetectorInfo, MediaId};
+
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+fn default_timeout_ms() -> u64 {
+    10_000
+}
+
+fn default_max_output_bytes() -> usize {
+    1 << 20
+}
+
+/// A plugin descriptor as written in `<plugins dir>/*.toml`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SubprocessDetector {
+    pub id: String,
+    pub kind: DetectorMediaKind,
+    /// Program to run. A relative path with a directory part, like
+    /// `./plugin.sh`, is resolved against the descriptor's directory; a bare
+    /// name like `python3` is looked up on `PATH`.
+    pub command: PathBuf,
+    #[serde(default)]
+    pub args: Vec<String>,
+    #[serde(default = "default_timeout_ms")]
+    pub timeout_ms: u64,
+    /// Larger stdout fails the detector; stderr is cut to this size.
+    #[serde(default = "default_max_output_bytes")]
+    pub max_output_bytes: usize,
+}
+
+impl SubprocessDetector {
+    pub fn new(id: &str, kind: DetectorMediaKind, command: impl Into<PathBuf>) -> Self {
+        Self {
+            id: id.to_string(),
+            kind,
+            command: command.into(),
+            args: Vec::new(),
+            timeout_ms: default_timeout_ms(),
+            max_output_bytes: default_max_output_bytes(),
+        }
+    }
+
+    pub fn with_args(mut self, args: &[&str]) -> Self {
+        self.args = args.iter().map(|a| a.to_string()).collect();
+        self
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout_ms = timeout.as_millis() as u64;
+        self
+    }
+
+    pub fn with_max_output_bytes(mut self, max: usize) -> Self {
+        self.max_output_bytes = max;
+        self
+    }
+
+    /// Read a descriptor file.
+    pub fn from_descriptor(path: &Path) -> Result<Self> {
+        let raw = std::fs::read_to_string(path)
+            .with_context(|| format!("reading plugin descriptor {}", path.display()))?;
+        let mut detector: Self = toml::from_str(&raw)
+            .with_context(|| format!("parsing plugin descriptor {}", path.display()))?;
+        if detector.command.is_relative() && detector.command.components().count() > 1 {
+            if let Some(dir) = path.parent() {
+                detector.command = dir.join(&detector.command);
+            }
+        }
+        Ok(detector)
+    }
+
+    fn run(&self, input: PluginInput) -> Result<Vec<u8>> {
+        let stdin = match &input {
+            PluginInput::Bytes(_) => Stdio::piped(),
+            PluginInput::File(file) => Stdio::from(file.try_clone()?),
+        };
+        let mut child = Command::new(&self.command)
+            .args(&self.args)
+            .stdin(stdin)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .with_context(|| format!("spawning plugin {}", self.command.display()))?;
+
+        // Feed stdin and drain the pipes on their own threads so a chatty plugin
+        // cannot deadlock against us. A file is handed over as stdin directly.
+        let writer = match input {
+            PluginInput::Bytes(bytes) => {
+                let mut stdin = child.stdin.take().expect("stdin is piped");
+                let input = bytes.to_vec();
+                Some(std::thread::spawn(move || {
+                    // A plugin may exit without reading everything; that is not our error.
+                    let _ = stdin.write_all(&input);
+                }))
+            }
+            PluginInput::File(_) => None,
+        };
+        let limit = self.max_output_bytes as u64;
+        let stdout = drain(child.stdout.take().expect("stdout is piped"), limit, false);
+        let stderr = drain(child.stderr.take().expect("stderr is piped"), limit, true);
+
+        let deadline = Instant::now() + Duration::from_millis(self.timeout_ms);
+        let status = loop {
+            if let Some(status) = child.try_wait()? {
+                break status;
+            }
+            if Instant::now() >= deadline {
+                let _ = child.kill();
+                let _ = child.wait();
+                bail!("plugin {} timed out after {}ms", self.id, self.timeout_ms);
+            }
+            std::thread::sleep(POLL_INTERVAL);
+        };
+        if let Some(writer) = writer {
+            let _ = writer.join();
+        }
+        let stdout = stdout
+            .join()
+            .map_err(|_| anyhow!("stdout reader panicked"))?;
+        if stdout.len() > self.max_output_bytes {
+            bail!(
+                "plugin {} wrote more than {} bytes to stdout",
+                self.id,
+                self.max_output_bytes
+            );
+        }
+        if !status.success() {
+            let stderr = stderr.join().unwrap_or_default();
+            bail!(
+                "plugin {} exited with {status}: {}",
+                self.id,
+                String::from_utf8_lossy(&stderr).trim()
+            );
+        }
+        Ok(stdout)
+    }
+}
+
+enum PluginInput<'a> {
+    Bytes(&'a [u8]),
+    File(std::fs::File),
+}
+
+/// The fields that decide what score comes back; limits stay out of the config hash.
+#[derive(Serialize)]
+struct ScoringConfig<'a> {
+    command: &'a Path,
+    args: &'a [String],
+    kind: DetectorMediaKind,
+}
+
+/// Read at most `limit + 1` bytes of `pipe`, so an overflow shows in the length.
+///
+/// The rest is discarded when `discard_rest` is set; otherwise the pipe is closed
+/// early, which stops a runaway plugin with a broken pipe.
+fn drain(
+    pipe: impl Read + Send + 'static,
+    limit: u64,
+    discard_rest: bool,
+) -> std::thread::JoinHandle<Vec<u8>> {
+    std::thread::spawn(move || {
+        let mut buf = Vec::new();
+        let mut head = pipe.take(limit.saturating_add(1));
+        let _ = head.read_to_end(&mut buf);
+        if discard_rest {
+            let _ = std::io::copy(&mut head.into_inner(), &mut std::io::sink());
+        }
+        buf
+    })
+}
+
+/// Parse and sanity-check what a plugin or remote detector sent back.
+pub(crate) fn parse_output(raw: &[u8]) -> Result<DetectorOutput> {
+    let output: DetectorOutput =
+        serde_json::from_slice(raw).context("output is not a DetectorOutput JSON object")?;
+    if !output.score_ai.is_finite() || !(0.0..=1.0).contains(&output.score_ai) {
+        bail!("score_ai {} is outside [0, 1]", output.score_ai);
+    }
+    Ok(output)
+}
+
+impl MediaDetector for SubprocessDetector {
+    fn id(&self) -> String {
+        self.id.clone()
+    }
+
+    fn kind(&self) -> DetectorMediaKind {
+        self.kind
+    }
+
+    fn info(&self) -> DetectorInfo {
+        DetectorInfo {
+            kind: format!("{:?}", self.kind).to_lowercase(),
+            version: "plugin".to_string(),
+            description: format!("Subprocess plugin {}", self.command.display()),
+            config_hash: config_hash(&ScoringConfig {
+                command: &self.command,
+                args: &self.args,
+                kind: self.kind,
+            }),
+        }
+    }
+
+    fn detect(&self, bytes: &[u8]) -> Result<DetectorOutput> {
+        let raw = self.run(PluginInput::Bytes(bytes))?;
+        parse_output(&raw).with_context(|| format!("plugin {}", self.id))
+    }
+
+    /// Stored media is given to the plugin as its stdin file, without a copy in memory.
+    fn detect_file(
+        &self,
+        path: &Path,
+        _pru: &PruDbHandle,
+        _media: MediaId,
+    ) -> Option<Result<DetectorOutput>> {
+        let run = || {
+            let file =
+                std::fs::File::open(path).with_context(|| format!("opening {}", path.display()))?;
+            let raw = self.run(PluginInput::File(file))?;
+            parse_output(&raw).with_context(|| format!("plugin {}", self.id))
+        };
+        Some(run())
+    }
+}
+
+impl DetectorRegistry {
+    /// Register a [`SubprocessDetector`] for every `*.toml` descriptor in `dir`.
+    ///
+    /// Returns the number of plugins loaded; a bad descriptor fails the whole load.
+    pub fn load_plugins(&mut self, dir: &Path) -> Result<usize> {
+        let mut descriptors: Vec<PathBuf> = std::fs::read_dir(dir)
+            .with_context(|| format!("reading plugin dir {}", dir.display()))?
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|path| path.extension().is_some_and(|ext| ext == "toml"))
+            .collect();
+        descriptors.sort();
+        for path in &descriptors {
+            self.register(Arc::new(SubprocessDetector::from_descriptor(path)?))
+                .with_context(|| format!("registering plugin {}", path.display()))?;
+        }
+        Ok(descriptors.len())
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::DetectorLabel;
+    use std::os::unix::fs::PermissionsExt;
+
+    fn script(dir: &Path, name: &str, body: &str) -> PathBuf {
+        let path = dir.join(name);
+        std::fs::write(&path, format!("#!/bin/sh\n{body}\n")).unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+        path
+    }
+
+    #[test]
+    fn plugin_output_is_parsed() {
+        let dir = tempfile::tempdir().unwrap();
+        let cmd = script(
+            dir.path(),
+            "echo.sh",
+            r#"n=$(wc -c); echo "{\"score_ai\": 0.9, \"label\": \"ai\", \"details\": \"read $n bytes\"}""#,
+        );
+        let detector = SubprocessDetector::new("detector:text:echo", DetectorMediaKind::Text, cmd);
+        let out = detector.detect(b"hello").unwrap();
+        assert_eq!(out.label, DetectorLabel::Ai);
+        assert!((out.score_ai - 0.9).abs() < 1e-6);
+        assert_eq!(out.details.as_deref().map(str::trim), Some("read 5 bytes"));
+
+        let stored = dir.path().join("stored.txt");
+        std::fs::write(&stored, "hello, file").unwrap();
+        let pru: PruDbHandle = Arc::new(std::sync::Mutex::new(
+            pru_core::PruStore::open(dir.path().join("db")).unwrap(),
+        ));
+        let out = detector
+            .detect_file(&stored, &pru, MediaId(1))
+            .unwrap()
+            .unwrap();
+        assert_eq!(out.details.as_deref().map(str::trim), Some("read 11 bytes"));
+    }
+
+    #[test]
+    fn bare_command_is_found_on_path() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("sh.toml"),
+            "id = \"detector:text:sh\"\nkind = \"text\"\ncommand = \"sh\"\n\
+             args = [\"-c\", \"cat >/dev/null; echo '{\\\"score_ai\\\": 0.2, \\\"label\\\": \\\"human\\\"}'\"]\n",
+        )
+        .unwrap();
+        let detector = SubprocessDetector::from_descriptor(&dir.path().join("sh.toml")).unwrap();
+        assert_eq!(detector.command, PathBuf::from("sh"));
+        assert_eq!(detector.detect(b"x").unwrap().label, DetectorLabel::Human);
+    }
+
+    #[test]
+    fn slow_plugin_is_killed() {
+        let dir = tempfile::tempdir().unwrap();
+        let cmd = script(dir.path(), "slow.sh", "sleep 5");
+        let detector = SubprocessDetector::new("detector:text:slow", DetectorMediaKind::Text, cmd)
+            .with_timeout(Duration::from_millis(200));
+        let started = Instant::now();
+        let err = detector.detect(b"x").unwrap_err();
+        assert!(err.to_string().contains("timed out"));
+        assert!(started.elapsed() < Duration::from_secs(2));
+    }
+
+    #[test]
+    fn malformed_output_is_rejected() {
+        let dir = tempfile::tempdir().unwrap();
+        let garbage = script(dir.path(), "garbage.sh", "echo not json");
+        let detector =
+            SubprocessDetector::new("detector:text:garbage", DetectorMediaKind::Text, garbage);
+        assert!(detector.detect(b"x").is_err());
+
+        let out_of_range = script(
+            dir.path(),
+            "range.sh",
+            r#"echo '{"score_ai": 3.0, "label": "ai", "details": null}'"#,
+        );
+        let detector =
+            SubprocessDetector::new("detector:text:range", DetectorMediaKind::Text, out_of_range);
+        assert!(format!("{:#}", detector.detect(b"x").unwrap_err()).contains("outside"));
+
+        let failing = script(dir.path(), "fail.sh", "echo boom >&2; exit 3");
+        let detector =
+            SubprocessDetector::new("detector:text:fail", DetectorMediaKind::Text, failing);
+        assert!(detector
+            .detect(b"x")
+            .unwrap_err()
+            .to_string()
+            .contains("boom"));
+    }
+
+    #[test]
+    fn oversized_output_is_refused() {
+        let dir = tempfile::tempdir().unwrap();
+        let chatty = script(dir.path(), "chatty.sh", "yes");
+        let detector =
+            SubprocessDetector::new("detector:text:chatty", DetectorMediaKind::Text, chatty)
+                .with_max_output_bytes(1024)
+                .with_timeout(Duration::from_secs(5));
+        let err = detector.detect(b"x").unwrap_err();
+        assert!(err.to_string().contains("more than 1024 bytes"), "{err:#}");
+    }
+
+    #[test]
+    fn config_hash_ignores_limits() {
+        let base = SubprocessDetector::new("detector:text:p", DetectorMediaKind::Text, "plugin.sh");
+        let hash = base.info().config_hash;
+        let tuned = base
+            .clone()
+            .with_timeout(Duration::from_secs(60))
+            .with_max_output_bytes(64);
+        assert_eq!(tuned.info().config_hash, hash);
+        assert_ne!(base.with_args(&["--fast"]).info().config_hash, hash);
+    }
+
+    #[test]
+    fn load_plugins_reads_descriptors() {
+        let dir = tempfile::tempdir().unwrap();
+        script(
+            dir.path(),
+            "plugin.sh",
+            r#"cat >/dev/null; echo '{"score_ai": 0.1, "label": "human", "details": null}'"#,
+        );
+        std::fs::write(
+            dir.path().join("plugin.toml"),
+            "id = \"detector:image:plugin\"\nkind = \"image\"\ncommand = \"./plugin.sh\"\ntimeout_ms = 2000\n",
+        )
+        .unwrap();
+        std::fs::write(dir.path().join("README.txt"), "ignored").unwrap();
+
+        let mut registry = DetectorRegistry::new();
+        assert_eq!(registry.load_plugins(dir.path()).unwrap(), 1);
+        let detector = registry.get("detector:image:plugin").unwrap();
+        assert_eq!(detector.kind(), DetectorMediaKind::Image);
+        let out = detector.as_sync().unwrap().detect(b"bytes").unwrap();
+        assert_eq!(out.label, DetectorLabel::Human);
+    }
+}
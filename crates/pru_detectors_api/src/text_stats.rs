@@ -0,0 +1,444 @@
+//! Corpus-free text statistics: n-gram entropy, burstiness and punctuation use.
+//!
+//! Language-model output tends to have evenly sized sentences, recycled phrasing
+//! and a narrow punctuation palette; none of these need a model to measure.
+
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+
+use crate::language::{detect_language, language_gate};
+use crate::{
+    config_hash, DetectorLabel, DetectorMediaKind, DetectorOutput, LabelPolicy, MediaDetector,
+};
+use pru_media_schema::{DetectorInfo, FeatureValue};
+
+/// English letter frequencies (a..z), used as the reference character model.
+const LETTER_FREQ: [f64; 26] = [
+    0.0817, 0.0149, 0.0278, 0.0425, 0.1270, 0.0223, 0.0202, 0.0609, 0.0697, 0.0015, 0.0077, 0.0403,
+    0.0241, 0.0675, 0.0751, 0.0193, 0.0010, 0.0599, 0.0633, 0.0906, 0.0276, 0.0098, 0.0236, 0.0015,
+    0.0197, 0.0007,
+];
+
+/// The commonest English letter pairs and their share of all in-word pairs.
+/// Pairs not listed split the remaining mass evenly.
+const BIGRAM_FREQ: &[(&str, f64)] = &[
+    ("th", 0.0356),
+    ("he", 0.0307),
+    ("in", 0.0243),
+    ("er", 0.0205),
+    ("an", 0.0199),
+    ("re", 0.0185),
+    ("on", 0.0176),
+    ("at", 0.0149),
+    ("en", 0.0145),
+    ("nd", 0.0135),
+    ("ti", 0.0134),
+    ("es", 0.0134),
+    ("or", 0.0128),
+    ("te", 0.0120),
+    ("of", 0.0117),
+    ("ed", 0.0117),
+    ("is", 0.0113),
+    ("it", 0.0112),
+    ("al", 0.0109),
+    ("ar", 0.0107),
+    ("st", 0.0105),
+    ("to", 0.0104),
+    ("nt", 0.0104),
+    ("ng", 0.0095),
+    ("se", 0.0093),
+    ("ha", 0.0093),
+    ("as", 0.0087),
+    ("ou", 0.0087),
+    ("io", 0.0083),
+    ("le", 0.0083),
+    ("ve", 0.0083),
+    ("co", 0.0079),
+    ("me", 0.0079),
+    ("de", 0.0076),
+    ("hi", 0.0076),
+    ("ri", 0.0073),
+    ("ro", 0.0073),
+    ("ic", 0.0070),
+    ("ne", 0.0069),
+    ("ea", 0.0069),
+    ("ra", 0.0069),
+    ("ce", 0.0065),
+];
+
+/// Cross-entropy of ordinary English prose under [`BIGRAM_FREQ`], in bits per pair.
+const ENGLISH_BIGRAM_BITS: f64 = 8.0;
+
+/// Share of [`STOP_WORDS`] among the words of ordinary English prose.
+const ENGLISH_STOP_WORD_RATIO: f64 = 0.4;
+
+const STOP_WORDS: &[&str] = &[
+    "a", "an", "and", "are", "as", "at", "be", "but", "by", "for", "from", "had", "has", "have",
+    "he", "her", "his", "i", "in", "is", "it", "its", "me", "my", "not", "of", "on", "or", "our",
+    "she", "so", "that", "the", "their", "them", "there", "they", "this", "to", "was", "we",
+    "were", "what", "which", "who", "will", "with", "you", "your",
+];
+
+const PUNCTUATION: &str = ".,;:!?-()\"'";
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TextStatisticsConfig {
+    /// Scores above this are labelled AI.
+    pub ai_threshold: f32,
+    /// Scores below this are labelled human; the band in between is unknown.
+    pub human_threshold: f32,
+    pub burstiness_weight: f32,
+    pub repetition_weight: f32,
+    pub punctuation_weight: f32,
+    pub letter_typicality_weight: f32,
+    pub stop_word_weight: f32,
+    /// Sentence-length coefficient of variation that counts as fully human.
+    pub burstiness_norm: f32,
+    /// Texts with fewer words are labelled unknown.
+    pub min_words: usize,
+    /// ISO 639-1 codes the reference statistics fit; other identified languages are
+    /// labelled unknown. `None` scores every language. The letter-pair and
+    /// stop-word tables are English, so only English is scored by default.
+    pub supported_languages: Option<BTreeSet<String>>,
+}
+
+impl Default for TextStatisticsConfig {
+    fn default() -> Self {
+        Self {
+            ai_threshold: 0.5,
+            human_threshold: 0.3,
+            burstiness_weight: 0.3,
+            repetition_weight: 0.3,
+            punctuation_weight: 0.15,
+            letter_typicality_weight: 0.15,
+            stop_word_weight: 0.1,
+            burstiness_norm: 0.6,
+            min_words: 20,
+            supported_languages: Some(BTreeSet::from(["en".to_string()])),
+        }
+    }
+}
+
+/// The measurements behind a [`TextStatisticsDetector`] score.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TextStatistics {
+    pub words: usize,
+    /// Cross-entropy of the letters against English frequencies, bits per letter.
+    pub letter_entropy: f64,
+    /// Cross-entropy of in-word letter pairs against English pair frequencies,
+    /// bits per pair.
+    pub letter_bigram_entropy: f64,
+    /// Word-bigram entropy divided by its maximum for this many bigrams, in [0, 1].
+    pub bigram_entropy: f64,
+    /// Coefficient of variation of sentence lengths in words.
+    pub burstiness: f64,
+    pub stop_word_ratio: f64,
+    /// Distinct punctuation marks used, over the marks we look for.
+    pub punctuation_diversity: f64,
+}
+
+impl TextStatistics {
+    pub fn measure(text: &str) -> Self {
+        let words: Vec<String> = text
+            .split_whitespace()
+            .map(|w| {
+                w.trim_matches(|c: char| !c.is_alphanumeric())
+                    .to_lowercase()
+            })
+            .filter(|w| !w.is_empty())
+            .collect();
+
+        let mut letters = [0usize; 26];
+        for c in text.chars().filter(char::is_ascii_alphabetic) {
+            letters[(c.to_ascii_lowercase() as u8 - b'a') as usize] += 1;
+        }
+        let letter_total: usize = letters.iter().sum();
+        let letter_entropy = if letter_total == 0 {
+            0.0
+        } else {
+            letters
+                .iter()
+                .zip(LETTER_FREQ)
+                .map(|(n, p)| -(*n as f64) * p.log2())
+                .sum::<f64>()
+                / letter_total as f64
+        };
+
+        let table: HashMap<&str, f64> = BIGRAM_FREQ.iter().copied().collect();
+        let unlisted = (1.0 - table.values().sum::<f64>()) / (26.0 * 26.0 - table.len() as f64);
+        let (mut pair_bits, mut pairs) = (0.0, 0usize);
+        for word in &words {
+            let word = word.as_bytes();
+            for pair in word
+                .windows(2)
+                .filter(|p| p.iter().all(u8::is_ascii_lowercase))
+            {
+                let p = std::str::from_utf8(pair)
+                    .ok()
+                    .and_then(|k| table.get(k))
+                    .copied()
+                    .unwrap_or(unlisted);
+                pair_bits -= p.log2();
+                pairs += 1;
+            }
+        }
+        let letter_bigram_entropy = if pairs == 0 {
+            0.0
+        } else {
+            pair_bits / pairs as f64
+        };
+
+        let mut bigrams: HashMap<(&str, &str), usize> = HashMap::new();
+        for pair in words.windows(2) {
+            *bigrams.entry((&pair[0], &pair[1])).or_default() += 1;
+        }
+        let bigram_total = words.len().saturating_sub(1);
+        let bigram_entropy = if bigram_total < 2 {
+            1.0
+        } else {
+            let h: f64 = bigrams
+                .values()
+                .map(|n| {
+                    let p = *n as f64 / bigram_total as f64;
+                    -p * p.log2()
+                })
+                .sum();
+            h / (bigram_total as f64).log2()
+        };
+
+        let sentence_lengths: Vec<f64> = text
+            .split(['.', '!', '?'])
+            .map(|s| s.split_whitespace().count() as f64)
+            .filter(|n| *n > 0.0)
+            .collect();
+        let burstiness = if sentence_lengths.len() < 2 {
+            0.0
+        } else {
+            let mean = sentence_lengths.iter().sum::<f64>() / sentence_lengths.len() as f64;
+            let var = sentence_lengths
+                .iter()
+                .map(|n| (n - mean).powi(2))
+                .sum::<f64>()
+                / sentence_lengths.len() as f64;
+            var.sqrt() / mean
+        };
+
+        let stop: HashSet<&str> = STOP_WORDS.iter().copied().collect();
+        let stop_word_ratio = if words.is_empty() {
+            0.0
+        } else {
+            words.iter().filter(|w| stop.contains(w.as_str())).count() as f64 / words.len() as f64
+        };
+
+        let used: HashSet<char> = text.chars().filter(|c| PUNCTUATION.contains(*c)).collect();
+        let punctuation_diversity = used.len() as f64 / PUNCTUATION.chars().count() as f64;
+
+        Self {
+            words: words.len(),
+            letter_entropy,
+            letter_bigram_entropy,
+            bigram_entropy,
+            burstiness,
+            stop_word_ratio,
+            punctuation_diversity,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct TextStatisticsDetector {
+    config: TextStatisticsConfig,
+}
+
+impl TextStatisticsDetector {
+    pub fn new(config: TextStatisticsConfig) -> Self {
+        Self { config }
+    }
+}
+
+impl MediaDetector for TextStatisticsDetector {
+    fn id(&self) -> String {
+        "detector:text:statistics_v1".to_string()
+    }
+
+    fn kind(&self) -> DetectorMediaKind {
+        DetectorMediaKind::Text
+    }
+
+    fn info(&self) -> DetectorInfo {
+        DetectorInfo {
+            kind: "text".to_string(),
+            version: "v1".to_string(),
+            description:
+                "N-gram entropy, sentence burstiness, stop-word and punctuation statistics"
+                    .to_string(),
+            config_hash: config_hash(&self.config),
+        }
+    }
+
+    fn detect(&self, bytes: &[u8]) -> Result<DetectorOutput> {
+        let cfg = &self.config;
+        let Ok(text) = std::str::from_utf8(bytes) else {
+            bail!("text is not valid UTF-8");
+        };
+        let (language, unsupported) =
+            language_gate(detect_language(text), cfg.supported_languages.as_ref());
+        if let Some(output) = unsupported {
+            return Ok(output);
+        }
+        let stats = TextStatistics::measure(text);
+
+        let uniformity = 1.0 - (stats.burstiness as f32 / cfg.burstiness_norm).clamp(0.0, 1.0);
+        let repetition = 1.0 - stats.bigram_entropy.clamp(0.0, 1.0) as f32;
+        let plain_punctuation = 1.0 - stats.punctuation_diversity as f32;
+        // Very "average" letter pairs and function-word use are typical of
+        // generated prose.
+        let typicality = 1.0
+            - ((stats.letter_bigram_entropy - ENGLISH_BIGRAM_BITS).abs() as f32).clamp(0.0, 1.0);
+        let stop_typicality = 1.0
+            - ((stats.stop_word_ratio - ENGLISH_STOP_WORD_RATIO).abs() as f32 / 0.2)
+                .clamp(0.0, 1.0);
+        let total_weight = cfg.burstiness_weight
+            + cfg.repetition_weight
+            + cfg.punctuation_weight
+            + cfg.letter_typicality_weight
+            + cfg.stop_word_weight;
+        let score_ai = if total_weight <= 0.0 {
+            0.5
+        } else {
+            ((uniformity * cfg.burstiness_weight
+                + repetition * cfg.repetition_weight
+                + plain_punctuation * cfg.punctuation_weight
+                + typicality * cfg.letter_typicality_weight
+                + stop_typicality * cfg.stop_word_weight)
+                / total_weight)
+                .clamp(0.0, 1.0)
+        };
+        let label = if stats.words < cfg.min_words {
+            DetectorLabel::Unknown
+        } else {
+            LabelPolicy::new(cfg.ai_threshold, cfg.human_threshold).label(score_ai)
+        };
+
+        let mut features = BTreeMap::from([
+            (
+                "word_count".to_string(),
+                FeatureValue::I64(stats.words as i64),
+            ),
+            (
+                "letter_entropy".to_string(),
+                FeatureValue::F64(stats.letter_entropy),
+            ),
+            (
+                "letter_bigram_entropy".to_string(),
+                FeatureValue::F64(stats.letter_bigram_entropy),
+            ),
+            (
+                "bigram_entropy".to_string(),
+                FeatureValue::F64(stats.bigram_entropy),
+            ),
+            (
+                "burstiness".to_string(),
+                FeatureValue::F64(stats.burstiness),
+            ),
+            (
+                "stop_word_ratio".to_string(),
+                FeatureValue::F64(stats.stop_word_ratio),
+            ),
+            (
+                "punctuation_diversity".to_string(),
+                FeatureValue::F64(stats.punctuation_diversity),
+            ),
+        ]);
+        features.extend(language);
+        Ok(DetectorOutput {
+            score_ai,
+            label,
+            details: Some(format!(
+                "burstiness {:.3}, bigram entropy {:.3}, punctuation {:.3}, letters {:.2} bits",
+                stats.burstiness,
+                stats.bigram_entropy,
+                stats.punctuation_diversity,
+                stats.letter_entropy
+            )),
+            features,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HUMAN: &str = "Honestly? I didn't expect much. The bus was late again - forty minutes, \
+        in the rain - so by the time I got to Mum's the soup had gone cold. She laughed it off. \
+        We ate it anyway, standing at the counter, arguing about whether the cat (who is \
+        seventeen, and deaf) could hear the tin opener. He could. Of course he could!";
+
+    const LLM: &str = "It is important to note that there are many benefits to consider. \
+        It is important to note that there are many factors to consider. \
+        It is important to note that there are many options to consider. \
+        It is important to note that there are many aspects to consider. \
+        In conclusion, it is important to note that there are many things to consider.";
+
+    #[test]
+    fn repetitive_paragraph_scores_above_human_one() {
+        let detector = TextStatisticsDetector::default();
+        let human = detector.detect(HUMAN.as_bytes()).unwrap();
+        let llm = detector.detect(LLM.as_bytes()).unwrap();
+        assert!(llm.score_ai > human.score_ai);
+        assert_eq!(llm.label, DetectorLabel::Ai);
+        assert_eq!(human.label, DetectorLabel::Human);
+
+        let human_stats = TextStatistics::measure(HUMAN);
+        let llm_stats = TextStatistics::measure(LLM);
+        assert!(human_stats.burstiness > llm_stats.burstiness);
+        assert!(human_stats.bigram_entropy > llm_stats.bigram_entropy);
+        assert!(human_stats.punctuation_diversity > llm_stats.punctuation_diversity);
+        assert!(llm.features.contains_key("letter_bigram_entropy"));
+    }
+
+    #[test]
+    fn common_letter_pairs_carry_fewer_bits() {
+        let common = TextStatistics::measure("the then there are in on at");
+        let rare = TextStatistics::measure("jazz quiz fjord pyx vex");
+        assert!(common.letter_bigram_entropy < rare.letter_bigram_entropy);
+    }
+
+    #[test]
+    fn short_text_is_unknown_and_threshold_is_configurable() {
+        let short = TextStatisticsDetector::default()
+            .detect(b"Hi there.")
+            .unwrap();
+        assert_eq!(short.label, DetectorLabel::Unknown);
+
+        let lenient = TextStatisticsDetector::new(TextStatisticsConfig {
+            ai_threshold: 0.99,
+            human_threshold: 0.99,
+            ..Default::default()
+        });
+        assert_eq!(
+            lenient.detect(LLM.as_bytes()).unwrap().label,
+            DetectorLabel::Human
+        );
+    }
+
+    #[test]
+    fn scores_between_the_thresholds_are_unknown() {
+        let score = TextStatisticsDetector::default()
+            .detect(HUMAN.as_bytes())
+            .unwrap()
+            .score_ai;
+        let banded = TextStatisticsDetector::new(TextStatisticsConfig {
+            ai_threshold: score + 0.05,
+            human_threshold: score - 0.05,
+            ..Default::default()
+        });
+        assert_eq!(
+            banded.detect(HUMAN.as_bytes()).unwrap().label,
+            DetectorLabel::Unknown
+        );
+    }
+}
@@ -0,0 +1,273 @@
+//! Unicode tricks left behind by text laundering and watermarking.
+//!
+//! Finding none says nothing about authorship, so clean text is labelled
+//! unknown rather than human.
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::language::{detect_language, language_gate};
+use crate::{config_hash, DetectorLabel, DetectorMediaKind, DetectorOutput, MediaDetector};
+use pru_media_schema::{DetectorInfo, FeatureValue};
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TextUnicodeAnomalyConfig {
+    /// Scores above this are labelled AI.
+    pub ai_threshold: f32,
+    /// Weighted anomaly count at which the score sits halfway between 0.5 and 1.
+    pub half_score_anomalies: f32,
+    pub nbsp_weight: f32,
+    /// Curly quotes, em dashes and ellipses are common in human text too.
+    pub typographic_weight: f32,
+    /// ISO 639-1 codes to scan; other identified languages are labelled unknown.
+    /// `None`, the default, scans every language.
+    pub supported_languages: Option<BTreeSet<String>>,
+}
+
+impl Default for TextUnicodeAnomalyConfig {
+    fn default() -> Self {
+        Self {
+            ai_threshold: 0.7,
+            half_score_anomalies: 2.0,
+            nbsp_weight: 0.3,
+            typographic_weight: 0.05,
+            supported_languages: None,
+        }
+    }
+}
+
+/// Per-class anomaly counts for one text.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct UnicodeAnomalies {
+    pub chars: usize,
+    /// Zero-width spaces/joiners, word joiners, soft hyphens and stray BOMs.
+    pub zero_width: usize,
+    /// Bidi overrides, tag characters and variation selectors.
+    pub invisible_format: usize,
+    /// Non-breaking and other fixed-width spaces.
+    pub unusual_space: usize,
+    /// Words mixing Latin with Cyrillic or Greek letters, plus fullwidth Latin.
+    pub homoglyph: usize,
+    /// Curly quotes, en/em dashes and the ellipsis character.
+    pub typographic: usize,
+}
+
+impl UnicodeAnomalies {
+    pub fn scan(text: &str) -> Self {
+        let mut found = Self::default();
+        for (i, c) in text.chars().enumerate() {
+            found.chars += 1;
+            match c {
+                '\u{FEFF}' if i == 0 => {}
+                '\u{200B}'..='\u{200D}' | '\u{2060}' | '\u{FEFF}' | '\u{00AD}' => {
+                    found.zero_width += 1
+                }
+                '\u{202A}'..='\u{202E}'
+                | '\u{2066}'..='\u{2069}'
+                | '\u{FE00}'..='\u{FE0F}'
+                | '\u{E0000}'..='\u{E007F}' => found.invisible_format += 1,
+                '\u{00A0}' | '\u{2000}'..='\u{200A}' | '\u{202F}' | '\u{205F}' | '\u{3000}' => {
+                    found.unusual_space += 1
+                }
+                '\u{FF21}'..='\u{FF3A}' | '\u{FF41}'..='\u{FF5A}' => found.homoglyph += 1,
+                '\u{2018}' | '\u{2019}' | '\u{201C}' | '\u{201D}' | '\u{2013}' | '\u{2014}'
+                | '\u{2026}' => found.typographic += 1,
+                _ => {}
+            }
+        }
+        found.homoglyph += text
+            .split(|c: char| !c.is_alphabetic())
+            .filter(|word| mixes_scripts(word))
+            .count();
+        found
+    }
+
+    fn density(&self, count: usize) -> f64 {
+        if self.chars == 0 {
+            0.0
+        } else {
+            count as f64 / self.chars as f64
+        }
+    }
+}
+
+fn mixes_scripts(word: &str) -> bool {
+    let mut latin = false;
+    let mut other = false;
+    for c in word.chars() {
+        match c {
+            'a'..='z' | 'A'..='Z' | '\u{00C0}'..='\u{024F}' => latin = true,
+            '\u{0370}'..='\u{03FF}' | '\u{0400}'..='\u{04FF}' => other = true,
+            _ => {}
+        }
+    }
+    latin && other
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct TextUnicodeAnomalyDetector {
+    config: TextUnicodeAnomalyConfig,
+}
+
+impl TextUnicodeAnomalyDetector {
+    pub fn new(config: TextUnicodeAnomalyConfig) -> Self {
+        Self { config }
+    }
+}
+
+impl MediaDetector for TextUnicodeAnomalyDetector {
+    fn id(&self) -> String {
+        "detector:text:unicode_anomaly_v1".to_string()
+    }
+
+    fn kind(&self) -> DetectorMediaKind {
+        DetectorMediaKind::Text
+    }
+
+    fn info(&self) -> DetectorInfo {
+        DetectorInfo {
+            kind: "text".to_string(),
+            version: "v1".to_string(),
+            description: "Zero-width, invisible, homoglyph and typographic character scan"
+                .to_string(),
+            config_hash: config_hash(&self.config),
+        }
+    }
+
+    /// Never fails: binary input is reported as unknown so it cannot abort ingest.
+    fn detect(&self, bytes: &[u8]) -> Result<DetectorOutput> {
+        let cfg = &self.config;
+        let Ok(text) = std::str::from_utf8(bytes) else {
+            return Ok(DetectorOutput {
+                score_ai: 0.5,
+                label: DetectorLabel::Unknown,
+                details: Some("input is not valid UTF-8".to_string()),
+                features: BTreeMap::from([("valid_utf8".to_string(), FeatureValue::Bool(false))]),
+            });
+        };
+        let (language, unsupported) =
+            language_gate(detect_language(text), cfg.supported_languages.as_ref());
+        if let Some(output) = unsupported {
+            return Ok(output);
+        }
+        let found = UnicodeAnomalies::scan(text);
+
+        let weighted = (found.zero_width + found.invisible_format + found.homoglyph) as f32
+            + found.unusual_space as f32 * cfg.nbsp_weight
+            + found.typographic as f32 * cfg.typographic_weight;
+        let score_ai =
+            0.5 + 0.5 * weighted / (weighted + cfg.half_score_anomalies.max(f32::EPSILON));
+        let label = if score_ai > cfg.ai_threshold {
+            DetectorLabel::Ai
+        } else {
+            DetectorLabel::Unknown
+        };
+
+        let mut features = BTreeMap::from([("valid_utf8".to_string(), FeatureValue::Bool(true))]);
+        features.extend(language);
+        let classes = [
+            ("zero_width", found.zero_width),
+            ("invisible_format", found.invisible_format),
+            ("unusual_space", found.unusual_space),
+            ("homoglyph", found.homoglyph),
+            ("typographic", found.typographic),
+        ];
+        for (class, count) in classes {
+            features.insert(format!("{class}_count"), FeatureValue::I64(count as i64));
+            features.insert(
+                format!("{class}_density"),
+                FeatureValue::F64(found.density(count)),
+            );
+        }
+        let summary: Vec<String> = classes
+            .iter()
+            .filter(|(_, count)| *count > 0)
+            .map(|(class, count)| format!("{class}={count}"))
+            .collect();
+        Ok(DetectorOutput {
+            score_ai,
+            label,
+            details: Some(if summary.is_empty() {
+                "no unicode anomalies".to_string()
+            } else {
+                summary.join(", ")
+            }),
+            features,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn detect(text: &str) -> DetectorOutput {
+        TextUnicodeAnomalyDetector::default()
+            .detect(text.as_bytes())
+            .unwrap()
+    }
+
+    #[test]
+    fn each_anomaly_class_is_counted() {
+        let cases = [
+            (
+                "hidden\u{200B}marks\u{200D} in\u{2060}side",
+                "zero_width_count",
+                3,
+            ),
+            (
+                "tagged\u{E0041}\u{E0042}\u{E0043} text\u{202E}",
+                "invisible_format_count",
+                4,
+            ),
+            ("spaced\u{00A0}out\u{202F}text", "unusual_space_count", 2),
+            (
+                "p\u{0430}yp\u{0430}l and \u{FF21}pple",
+                "homoglyph_count",
+                2,
+            ),
+            (
+                "\u{201C}Quoted\u{201D} \u{2014} and so on\u{2026}",
+                "typographic_count",
+                4,
+            ),
+        ];
+        for (text, feature, expected) in cases {
+            let out = detect(text);
+            assert_eq!(
+                out.features.get(feature),
+                Some(&FeatureValue::I64(expected)),
+                "{feature} in {text:?}"
+            );
+            assert!(out.score_ai > 0.5, "{feature}");
+        }
+        assert_eq!(
+            detect("hidden\u{200B}marks\u{200D} in\u{2060}side").label,
+            DetectorLabel::Ai
+        );
+        // Typography alone is not enough to call it.
+        assert_eq!(
+            detect("\u{201C}Quoted\u{201D} \u{2014} and so on\u{2026}").label,
+            DetectorLabel::Unknown
+        );
+    }
+
+    #[test]
+    fn clean_and_binary_input_are_unknown() {
+        let clean = detect("\u{FEFF}Plain text, written by someone. Nothing hidden here!");
+        assert_eq!(clean.label, DetectorLabel::Unknown);
+        assert_eq!(clean.score_ai, 0.5);
+        assert_eq!(clean.details.as_deref(), Some("no unicode anomalies"));
+
+        let binary = TextUnicodeAnomalyDetector::default()
+            .detect(&[0xFF, 0xFE, 0x00, 0x9F])
+            .unwrap();
+        assert_eq!(binary.label, DetectorLabel::Unknown);
+        assert_eq!(
+            binary.features.get("valid_utf8"),
+            Some(&FeatureValue::Bool(false))
+        );
+    }
+}
@@ -0,0 +1,515 @@
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use crate::{
+    config_hash, DetectorLabel, DetectorMediaKind, DetectorOutput, ImageMetadataDetector,
+    LabelPolicy, MediaDetector,
+};
+use pru_media_schema::{DetectorInfo, FeatureValue};
+
+/// Encoder/handler words left behind by known AI video generators. They only
+/// match whole words of the metadata strings, so short ones like `svd` or
+/// `veo` don't fire inside unrelated names.
+const AI_VIDEO_SIGNATURES: &[&str] = &[
+    "sora",
+    "runway",
+    "gen-2",
+    "gen-3",
+    "pika",
+    "stable video",
+    "svd",
+    "animatediff",
+    "deforum",
+    "kling",
+    "luma dream",
+    "veo",
+    "haiper",
+    "synthesia",
+];
+
+/// Container-level facts read without decoding any frames.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ContainerInfo {
+    /// `mp4` or `webm`.
+    pub container: String,
+    pub duration_ms: Option<u64>,
+    pub track_count: usize,
+    /// Encoder, writing-app and handler-name strings, in file order.
+    pub encoders: Vec<String>,
+}
+
+impl ContainerInfo {
+    pub fn mime(&self) -> &'static str {
+        match self.container.as_str() {
+            "webm" => "video/webm",
+            _ => "video/mp4",
+        }
+    }
+}
+
+/// Read duration, track count and encoder strings from an MP4 or WebM container.
+pub fn probe_container(bytes: &[u8]) -> Result<ContainerInfo> {
+    let mut info = ContainerInfo::default();
+    if bytes.len() >= 8 && &bytes[4..8] == b"ftyp" {
+        info.container = "mp4".to_string();
+        walk_mp4(bytes, &mut info, 0);
+    } else if bytes.starts_with(&[0x1A, 0x45, 0xDF, 0xA3]) {
+        info.container = "webm".to_string();
+        walk_ebml(bytes, &mut info, 0);
+    } else {
+        bail!("unrecognized video container");
+    }
+    Ok(info)
+}
+
+const MAX_DEPTH: usize = 8;
+
+fn walk_mp4(buf: &[u8], info: &mut ContainerInfo, depth: usize) {
+    if depth > MAX_DEPTH {
+        return;
+    }
+    let mut pos = 0;
+    while pos + 8 <= buf.len() {
+        let size32 = u32::from_be_bytes([buf[pos], buf[pos + 1], buf[pos + 2], buf[pos + 3]]);
+        let kind = &buf[pos + 4..pos + 8];
+        let (header, size) = match size32 {
+            0 => (8, buf.len() - pos),
+            1 if pos + 16 <= buf.len() => {
+                let mut raw = [0u8; 8];
+                raw.copy_from_slice(&buf[pos + 8..pos + 16]);
+                (
+                    16,
+                    usize::try_from(u64::from_be_bytes(raw)).unwrap_or(usize::MAX),
+                )
+            }
+            n => (8, n as usize),
+        };
+        if size < header || size > buf.len() - pos {
+            break;
+        }
+        let body = &buf[pos + header..pos + size];
+        match kind {
+            b"trak" => {
+                info.track_count += 1;
+                walk_mp4(body, info, depth + 1);
+            }
+            b"moov" | b"mdia" | b"minf" | b"stbl" | b"udta" | b"edts" | b"ilst" => {
+                walk_mp4(body, info, depth + 1)
+            }
+            // `meta` is a full box: skip version and flags.
+            b"meta" if body.len() >= 4 => walk_mp4(&body[4..], info, depth + 1),
+            b"mvhd" => info.duration_ms = mvhd_duration_ms(body),
+            b"hdlr" if body.len() > 24 => push_text(info, &body[24..]),
+            // iTunes-style item: a `data` box with 8 bytes of type/locale before the value.
+            [0xA9, b't', b'o', b'o'] | [0xA9, b's', b'w', b'r'] | [0xA9, b'e', b'n', b'c']
+                if body.len() > 16 && &body[4..8] == b"data" =>
+            {
+                push_text(info, &body[16..])
+            }
+            _ => {}
+        }
+        pos += size;
+    }
+}
+
+fn mvhd_duration_ms(body: &[u8]) -> Option<u64> {
+    let (timescale, duration) = match *body.first()? {
+        1 if body.len() >= 32 => (
+            u32::from_be_bytes(body[20..24].try_into().ok()?) as u64,
+            u64::from_be_bytes(body[24..32].try_into().ok()?),
+        ),
+        0 if body.len() >= 20 => (
+            u32::from_be_bytes(body[12..16].try_into().ok()?) as u64,
+            u32::from_be_bytes(body[16..20].try_into().ok()?) as u64,
+        ),
+        _ => return None,
+    };
+    (timescale > 0).then(|| duration.saturating_mul(1000) / timescale)
+}
+
+const EBML_SEGMENT: u64 = 0x1853_8067;
+const EBML_INFO: u64 = 0x1549_A966;
+const EBML_TRACKS: u64 = 0x1654_AE6B;
+const EBML_TRACK_ENTRY: u64 = 0xAE;
+const EBML_TIMECODE_SCALE: u64 = 0x2A_D7B1;
+const EBML_DURATION: u64 = 0x4489;
+const EBML_MUXING_APP: u64 = 0x4D80;
+const EBML_WRITING_APP: u64 = 0x5741;
+
+fn walk_ebml(buf: &[u8], info: &mut ContainerInfo, depth: usize) {
+    if depth > MAX_DEPTH {
+        return;
+    }
+    let mut timecode_scale = 1_000_000u64;
+    let mut duration: Option<f64> = None;
+    let mut pos = 0;
+    while pos < buf.len() {
+        let Some((id, id_len)) = ebml_vint(&buf[pos..], true) else {
+            break;
+        };
+        let Some((size, size_len)) = ebml_vint(&buf[pos + id_len..], false) else {
+            break;
+        };
+        let start = pos + id_len + size_len;
+        // All-ones size means "unknown"; treat it as running to the end of the parent.
+        let unknown = size == (1u64 << (7 * size_len)) - 1;
+        let end = if unknown {
+            buf.len()
+        } else {
+            match usize::try_from(size)
+                .ok()
+                .and_then(|s| start.checked_add(s))
+            {
+                Some(end) if end <= buf.len() => end,
+                _ => break,
+            }
+        };
+        let body = &buf[start..end];
+        match id {
+            EBML_SEGMENT | EBML_INFO | EBML_TRACKS => walk_ebml(body, info, depth + 1),
+            EBML_TRACK_ENTRY => info.track_count += 1,
+            EBML_TIMECODE_SCALE => timecode_scale = be_uint(body),
+            EBML_DURATION => {
+                duration = match body.len() {
+                    4 => Some(f32::from_be_bytes(body.try_into().unwrap_or_default()) as f64),
+                    8 => Some(f64::from_be_bytes(body.try_into().unwrap_or_default())),
+                    _ => None,
+                }
+            }
+            EBML_MUXING_APP | EBML_WRITING_APP => push_text(info, body),
+            _ => {}
+        }
+        pos = end;
+    }
+    if let Some(d) = duration.filter(|d| d.is_finite() && *d >= 0.0) {
+        info.duration_ms = Some((d * timecode_scale as f64 / 1_000_000.0) as u64);
+    }
+}
+
+/// Decode an EBML variable-length integer; ids keep their length marker, sizes drop it.
+fn ebml_vint(buf: &[u8], keep_marker: bool) -> Option<(u64, usize)> {
+    let first = *buf.first()?;
+    let len = first.leading_zeros() as usize + 1;
+    if len > 8 || buf.len() < len {
+        return None;
+    }
+    let mut value = if keep_marker {
+        first as u64
+    } else {
+        (first as u64) & (0xFF >> len)
+    };
+    for b in &buf[1..len] {
+        value = (value << 8) | *b as u64;
+    }
+    Some((value, len))
+}
+
+fn be_uint(body: &[u8]) -> u64 {
+    body.iter().take(8).fold(0, |acc, b| (acc << 8) | *b as u64)
+}
+
+/// Whether `sig` occurs in `text` with no letter or digit right before or after it.
+fn contains_word(text: &str, sig: &str) -> bool {
+    text.match_indices(sig).any(|(at, _)| {
+        let before = text[..at].chars().next_back();
+        let after = text[at + sig.len()..].chars().next();
+        !before.is_some_and(char::is_alphanumeric) && !after.is_some_and(char::is_alphanumeric)
+    })
+}
+
+fn push_text(info: &mut ContainerInfo, raw: &[u8]) {
+    let text = String::from_utf8_lossy(raw);
+    let text = text.trim_matches(|c: char| c == '\0' || c.is_whitespace());
+    if !text.is_empty() {
+        info.encoders.push(text.to_string());
+    }
+}
+
+/// First embedded PNG or JPEG that decodes, e.g. cover art or an MJPEG keyframe.
+fn find_embedded_image(bytes: &[u8]) -> Option<&[u8]> {
+    const PNG: &[u8] = &[0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A];
+    const JPEG: &[u8] = &[0xFF, 0xD8, 0xFF];
+    let mut tried = 0;
+    for start in 0..bytes.len() {
+        let rest = &bytes[start..];
+        if !(rest.starts_with(PNG) || rest.starts_with(JPEG)) {
+            continue;
+        }
+        if image::load_from_memory(rest).is_ok() {
+            return Some(rest);
+        }
+        tried += 1;
+        if tried >= 4 {
+            break;
+        }
+    }
+    None
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct VideoMetadataConfig {
+    /// Scores above this are labelled AI.
+    pub ai_threshold: f32,
+    /// Scores below this are labelled human; the band in between is unknown.
+    pub human_threshold: f32,
+    /// Score assigned when an encoder string matches a known AI generator.
+    pub signature_score: f32,
+    /// Score used when neither a signature nor a keyframe is available.
+    pub baseline_score: f32,
+}
+
+impl Default for VideoMetadataConfig {
+    fn default() -> Self {
+        Self {
+            ai_threshold: 0.6,
+            human_threshold: 0.4,
+            signature_score: 0.92,
+            baseline_score: 0.3,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct VideoMetadataDetector {
+    config: VideoMetadataConfig,
+    /// Scores the embedded keyframe.
+    keyframe: ImageMetadataDetector,
+}
+
+impl VideoMetadataDetector {
+    pub fn new(config: VideoMetadataConfig) -> Self {
+        Self {
+            config,
+            keyframe: ImageMetadataDetector::default(),
+        }
+    }
+
+    /// Score embedded keyframes with `keyframe` rather than a default-configured
+    /// image metadata detector.
+    pub fn with_keyframe_detector(mut self, keyframe: ImageMetadataDetector) -> Self {
+        self.keyframe = keyframe;
+        self
+    }
+}
+
+impl MediaDetector for VideoMetadataDetector {
+    fn id(&self) -> String {
+        "detector:video:metadata_v1".to_string()
+    }
+
+    fn kind(&self) -> DetectorMediaKind {
+        DetectorMediaKind::Video
+    }
+
+    fn info(&self) -> DetectorInfo {
+        DetectorInfo {
+            kind: "video".to_string(),
+            version: "v1".to_string(),
+            description:
+                "MP4/WebM encoder signatures plus image heuristics on an embedded keyframe"
+                    .to_string(),
+            config_hash: config_hash(&(&self.config, self.keyframe.config())),
+        }
+    }
+
+    fn detect(&self, bytes: &[u8]) -> Result<DetectorOutput> {
+        let info = probe_container(bytes)?;
+        let signature = info.encoders.iter().find_map(|enc| {
+            let lower = enc.to_ascii_lowercase();
+            AI_VIDEO_SIGNATURES
+                .iter()
+                .find(|sig| contains_word(&lower, sig))
+                .map(|sig| sig.to_string())
+        });
+        let keyframe_score = find_embedded_image(bytes)
+            .and_then(|img| self.keyframe.detect(img).ok())
+            .map(|out| out.score_ai);
+
+        let score_ai = match (&signature, keyframe_score) {
+            (Some(_), _) => self.config.signature_score,
+            (None, Some(k)) => k,
+            (None, None) => self.config.baseline_score,
+        };
+        let policy = LabelPolicy::new(self.config.ai_threshold, self.config.human_threshold);
+        let label = match policy.label(score_ai) {
+            _ if signature.is_some() => DetectorLabel::Ai,
+            DetectorLabel::Human if info.track_count == 0 && info.duration_ms.is_none() => {
+                DetectorLabel::Unknown
+            }
+            label => label,
+        };
+
+        let encoders = info.encoders.join(" | ");
+        let mut details = format!(
+            "container={}, tracks={}, encoder={}",
+            info.container,
+            info.track_count,
+            if encoders.is_empty() {
+                "none"
+            } else {
+                &encoders
+            }
+        );
+        if let Some(ms) = info.duration_ms {
+            details.push_str(&format!(", duration_ms={ms}"));
+        }
+        if let Some(sig) = &signature {
+            details.push_str(&format!(", ai_signature={sig}"));
+        }
+        if let Some(k) = keyframe_score {
+            details.push_str(&format!(", keyframe_score={k:.2}"));
+        }
+
+        let mut features = BTreeMap::from([
+            (
+                "container".to_string(),
+                FeatureValue::Str(info.container.clone()),
+            ),
+            (
+                "track_count".to_string(),
+                FeatureValue::I64(info.track_count as i64),
+            ),
+        ]);
+        if !encoders.is_empty() {
+            features.insert("encoder".to_string(), FeatureValue::Str(encoders));
+        }
+        if let Some(ms) = info.duration_ms {
+            features.insert("duration_ms".to_string(), FeatureValue::I64(ms as i64));
+        }
+        if let Some(sig) = signature {
+            features.insert("ai_signature".to_string(), FeatureValue::Str(sig));
+        }
+        if let Some(k) = keyframe_score {
+            features.insert("keyframe_score".to_string(), FeatureValue::F64(k as f64));
+        }
+        Ok(DetectorOutput {
+            score_ai,
+            label,
+            details: Some(details),
+            features,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ImageMetadataConfig;
+
+    fn mp4_box(kind: &[u8; 4], body: &[u8]) -> Vec<u8> {
+        let mut out = ((body.len() + 8) as u32).to_be_bytes().to_vec();
+        out.extend_from_slice(kind);
+        out.extend_from_slice(body);
+        out
+    }
+
+    fn tiny_mp4(encoder: &str) -> Vec<u8> {
+        let mut mvhd = vec![0u8; 20];
+        mvhd[12..16].copy_from_slice(&1000u32.to_be_bytes());
+        mvhd[16..20].copy_from_slice(&2500u32.to_be_bytes());
+        let mut data = vec![0, 0, 0, 1, 0, 0, 0, 0];
+        data.extend_from_slice(encoder.as_bytes());
+        let item = mp4_box(&[0xA9, b't', b'o', b'o'], &mp4_box(b"data", &data));
+        let mut meta = vec![0u8; 4];
+        meta.extend(mp4_box(b"ilst", &item));
+        let udta = mp4_box(b"udta", &mp4_box(b"meta", &meta));
+        let trak = mp4_box(b"trak", &mp4_box(b"tkhd", &[0u8; 84]));
+        let mut moov_body = mp4_box(b"mvhd", &mvhd);
+        moov_body.extend(trak.clone());
+        moov_body.extend(trak);
+        moov_body.extend(udta);
+        let mut file = mp4_box(b"ftyp", b"isom\0\0\0\0isom");
+        file.extend(mp4_box(b"moov", &moov_body));
+        file
+    }
+
+    #[test]
+    fn mp4_encoder_signature_flags_ai() {
+        let info = probe_container(&tiny_mp4("Lavf60.3.100")).unwrap();
+        assert_eq!(info.container, "mp4");
+        assert_eq!(info.duration_ms, Some(2500));
+        assert_eq!(info.track_count, 2);
+        assert_eq!(info.encoders, vec!["Lavf60.3.100".to_string()]);
+
+        let human = VideoMetadataDetector::default()
+            .detect(&tiny_mp4("Lavf60.3.100"))
+            .unwrap();
+        assert_eq!(human.label, DetectorLabel::Human);
+        let ai = VideoMetadataDetector::default()
+            .detect(&tiny_mp4("OpenAI Sora"))
+            .unwrap();
+        assert_eq!(ai.label, DetectorLabel::Ai);
+        assert!(ai.details.unwrap().contains("ai_signature=sora"));
+
+        for encoder in ["Lavc60 libsvdec", "Elveo Player", "Pikachu Studio"] {
+            let out = VideoMetadataDetector::default()
+                .detect(&tiny_mp4(encoder))
+                .unwrap();
+            assert_eq!(out.label, DetectorLabel::Human, "{encoder}");
+        }
+        let svd = VideoMetadataDetector::default()
+            .detect(&tiny_mp4("SVD-XT 1.1"))
+            .unwrap();
+        assert_eq!(svd.label, DetectorLabel::Ai);
+    }
+
+    #[test]
+    fn webm_info_is_read() {
+        fn element(id: &[u8], body: &[u8]) -> Vec<u8> {
+            let mut out = id.to_vec();
+            out.push(0x80 | body.len() as u8);
+            out.extend_from_slice(body);
+            out
+        }
+        let mut info_body = element(&[0x44, 0x89], &1500.0f32.to_be_bytes());
+        info_body.extend(element(&[0x57, 0x41], b"Runway Gen-3"));
+        let mut segment = element(&[0x15, 0x49, 0xA9, 0x66], &info_body);
+        segment.extend(element(&[0x16, 0x54, 0xAE, 0x6B], &element(&[0xAE], &[])));
+        let mut file = element(&[0x1A, 0x45, 0xDF, 0xA3], &element(&[0x42, 0x82], b"webm"));
+        file.extend(element(&[0x18, 0x53, 0x80, 0x67], &segment));
+
+        let info = probe_container(&file).unwrap();
+        assert_eq!(info.container, "webm");
+        assert_eq!(info.duration_ms, Some(1500));
+        assert_eq!(info.track_count, 1);
+        let out = VideoMetadataDetector::default().detect(&file).unwrap();
+        assert_eq!(out.label, DetectorLabel::Ai);
+    }
+
+    #[test]
+    fn keyframe_detector_config_changes_the_hash() {
+        let tuned = ImageMetadataDetector::new(ImageMetadataConfig {
+            exif_ai_hint: 0.5,
+            ..Default::default()
+        });
+        assert_ne!(
+            VideoMetadataDetector::default().info().config_hash,
+            VideoMetadataDetector::default()
+                .with_keyframe_detector(tuned)
+                .info()
+                .config_hash
+        );
+    }
+
+    #[test]
+    fn truncated_boxes_do_not_panic() {
+        let mut file = tiny_mp4("x");
+        file.truncate(file.len() - 7);
+        file.extend_from_slice(&[0xFF, 0xFF, 0xFF, 0xF0, b'm', b'o', b'o', b'v']);
+        assert!(probe_container(&file).is_ok());
+        assert!(probe_container(b"garbage").is_err());
+    }
+
+    #[test]
+    fn scores_between_the_thresholds_are_unknown() {
+        let undecided = VideoMetadataDetector::new(VideoMetadataConfig {
+            baseline_score: 0.5,
+            ..Default::default()
+        });
+        let out = undecided.detect(&tiny_mp4("Lavf60.3.100")).unwrap();
+        assert_eq!(out.score_ai, 0.5);
+        assert_eq!(out.label, DetectorLabel::Unknown);
+    }
+}
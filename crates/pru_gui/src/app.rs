@@ -1,85 +1,256 @@
-use anyhow::Result;
 use eframe::egui::{self, RichText};
-use pru_core::{Fact, PruStore, Query};
-use std::path::PathBuf;
+use pru_core::export::{write_facts, ExportFormat};
+use pru_core::{AtomKind, Fact, PruDbHandle, PruStore, Query};
+use pru_media_schema::{parse_feature_payload, PRED_HAS_FEATURE};
+use std::path::{Path, PathBuf};
+use std::sync::MutexGuard;
+
+use crate::atom_list::AtomList;
+use crate::auto_refresh::AutoRefresh;
+use crate::detectors_panel::DetectorsPanel;
+use crate::fact_filter::{FactFilter, FilterAction};
+use crate::fact_form::FactForm;
+use crate::ingest_panel::IngestPanel;
+use crate::media_panel::MediaPanel;
+use crate::storage_panel::StoragePanel;
+use crate::worker::{Opened, Reply, Worker};
 
 const FACT_LIMIT: usize = 500;
 
+pub(crate) fn parse_id(input: &str) -> Option<u64> {
+    input.trim().parse::<u64>().ok()
+}
+
+pub(crate) fn resolve_entity(store: &PruStore, name: &str) -> Option<u64> {
+    store.get_entity_id(name)
+}
+
+pub(crate) fn resolve_predicate(store: &PruStore, name: &str) -> Option<u64> {
+    store.get_predicate_id(name)
+}
+
+pub(crate) fn resolve_object(store: &PruStore, name: &str) -> Option<u64> {
+    store
+        .get_literal_id(name)
+        .or_else(|| store.get_entity_id(name))
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Tab {
+    #[default]
+    Facts,
+    Media,
+    Ingest,
+    Detectors,
+    Storage,
+}
+
 #[derive(Default)]
 pub struct PruGuiApp {
     pub dir_input: String,
-    pub store: Option<PruStore>,
+    pub store: Option<PruDbHandle>,
     pub error: Option<String>,
+    pub tab: Tab,
     pub entities: Vec<(u64, String)>,
+    /// Entities named `media:…`, for the Media tab.
+    pub media: Vec<(u64, String)>,
     pub predicates: Vec<(u64, String)>,
     pub literals: Vec<(u64, String)>,
+    pub entity_list: AtomList,
+    pub predicate_list: AtomList,
+    pub literal_list: AtomList,
     pub facts: Vec<Fact>,
+    /// How many facts the view matched, of which `facts` holds the first.
+    pub matched: usize,
+    /// The subject/predicate selection or query the fact filter narrows.
+    pub base_query: Option<Query>,
+    pub fact_filter: FactFilter,
+    /// The query behind `facts`, without the display limit, for export.
+    pub view_query: Option<Query>,
+    pub export_format: ExportFormat,
+    pub export_status: Option<String>,
     pub selected_entity: Option<u64>,
     pub selected_predicate: Option<u64>,
     pub query_subject: String,
     pub query_predicate: String,
     pub query_object: String,
     pub query_min_confidence: f32,
+    pub fact_form: FactForm,
+    pub media_panel: MediaPanel,
+    pub ingest_panel: IngestPanel,
+    pub detectors_panel: DetectorsPanel,
+    pub storage_panel: StoragePanel,
+    pub worker: Worker,
+    pub auto_refresh: AutoRefresh,
+    /// The store being opened is the open one, re-read after an outside
+    /// write; keep the current view instead of starting over.
+    reloading: bool,
+    /// The atom lists or fact view wait to be re-read until the store is free.
+    atoms_stale: bool,
+    view_stale: bool,
 }
 
 impl PruGuiApp {
-    pub fn load_store(&mut self) {
+    /// The open store for drawing a frame, or `None` while a worker holds it.
+    fn try_store(&self) -> Option<MutexGuard<'_, PruStore>> {
+        self.store.as_ref().and_then(|h| h.try_lock().ok())
+    }
+
+    /// Start opening the directory in `dir_input`; the current store stays
+    /// in view until the new one is ready.
+    pub fn load_store(&mut self, ctx: &egui::Context) {
         self.error = None;
+        self.reloading = false;
         let dir = PathBuf::from(self.dir_input.trim());
-        match PruStore::open(&dir) {
-            Ok(store) => {
-                self.entities = store.entities();
-                self.predicates = store.predicates();
-                self.literals = store.literals();
-                self.selected_entity = self.entities.first().map(|(id, _)| *id);
-                self.selected_predicate = None;
-                self.facts.clear();
-                self.store = Some(store);
-                if self.selected_entity.is_some() {
-                    if let Err(e) = self.refresh_facts() {
-                        self.error = Some(format!("Failed to load facts: {e}"));
+        self.worker.open(ctx, dir);
+    }
+
+    fn apply_opened(&mut self, ctx: &egui::Context, opened: Opened, reloaded: bool) {
+        self.worker.cancel_query();
+        self.auto_refresh.loaded(opened.dir, opened.stamp);
+        self.store = Some(opened.handle.clone());
+        let store = opened.handle.lock().expect("store poisoned");
+        self.set_atoms(&store, opened.entities, opened.predicates, opened.literals);
+        drop(store);
+        if reloaded {
+            // Re-narrow too, as new atoms may match the filter text.
+            self.run_view(ctx);
+            return;
+        }
+        self.media_panel.clear();
+        self.detectors_panel.clear();
+        self.storage_panel.clear();
+        self.selected_entity = self.entities.first().map(|(id, _)| *id);
+        self.selected_predicate = None;
+        self.fact_filter.clear();
+        self.export_status = None;
+        self.refresh_facts(ctx);
+    }
+
+    /// Apply replies from the worker.
+    fn poll_worker(&mut self, ctx: &egui::Context) {
+        for reply in self.worker.poll() {
+            match reply {
+                Reply::Opened(result) => {
+                    let reloaded = std::mem::take(&mut self.reloading);
+                    match result {
+                        Ok(opened) => self.apply_opened(ctx, opened, reloaded),
+                        // The store stays as it was loaded last.
+                        Err(e) if reloaded => {
+                            self.error = Some(format!("Failed to reload store: {e}"));
+                        }
+                        Err(e) => {
+                            self.store = None;
+                            self.error = Some(format!("Failed to open store: {e}"));
+                        }
                     }
                 }
-            }
-            Err(e) => {
-                self.store = None;
-                self.error = Some(format!("Failed to open store: {e}"));
+                Reply::Facts { facts, matched } => {
+                    self.facts = facts;
+                    self.matched = matched;
+                }
             }
         }
     }
 
-    pub fn refresh_facts(&mut self) -> Result<()> {
-        let store = match self.store.as_ref() {
-            Some(s) => s,
-            None => return Ok(()),
-        };
-        let Some(subject) = self.selected_entity else {
-            self.facts.clear();
-            return Ok(());
-        };
+    /// Re-open the store in the background when another process wrote to it,
+    /// unless that would disturb work in progress here.
+    fn poll_auto_refresh(&mut self, ctx: &egui::Context) {
+        let busy = self.worker.opening.is_some()
+            || self.ingest_panel.is_busy()
+            || self.fact_form.is_dirty();
+        if self.store.is_none() || busy {
+            return;
+        }
+        if let Some(dir) = self.auto_refresh.poll(ctx) {
+            self.worker.open(ctx, dir);
+            self.reloading = true;
+        }
+    }
 
-        let mut facts = if let Some(pred) = self.selected_predicate {
-            store.facts_for_subject_predicate(subject, pred)?
-        } else {
-            store.facts_for_subject(subject)?
+    /// Re-read the atom lists and their filters, e.g. after a write interned
+    /// new names. The lists are cached until the next reload; while a worker
+    /// holds the store they are re-read on a later frame.
+    pub fn reload_atoms(&mut self) {
+        let Some(handle) = self.store.clone() else {
+            return;
         };
-        facts.truncate(FACT_LIMIT);
-        self.facts = facts;
-        Ok(())
+        let Ok(store) = handle.try_lock() else {
+            self.atoms_stale = true;
+            return;
+        };
+        self.atoms_stale = false;
+        let (entities, predicates, literals) =
+            (store.entities(), store.predicates(), store.literals());
+        self.set_atoms(&store, entities, predicates, literals);
     }
 
-    fn resolve_entity(&self, name: &str) -> Option<u64> {
-        self.store.as_ref().and_then(|s| s.get_entity_id(name))
+    fn set_atoms(
+        &mut self,
+        store: &PruStore,
+        entities: Vec<(u64, String)>,
+        predicates: Vec<(u64, String)>,
+        literals: Vec<(u64, String)>,
+    ) {
+        self.media = entities
+            .iter()
+            .filter(|(_, name)| name.starts_with("media:"))
+            .cloned()
+            .collect();
+        self.entities = entities;
+        self.predicates = predicates;
+        self.literals = literals;
+        self.entity_list.refresh(store, AtomKind::Entity);
+        self.predicate_list.refresh(store, AtomKind::Predicate);
+        self.literal_list.refresh(store, AtomKind::Literal);
     }
 
-    fn resolve_predicate(&self, name: &str) -> Option<u64> {
-        self.store.as_ref().and_then(|s| s.get_predicate_id(name))
+    /// Reload the facts of the selected subject (and predicate) in the
+    /// background.
+    pub fn refresh_facts(&mut self, ctx: &egui::Context) {
+        self.base_query = self.selected_entity.map(|subject| Query {
+            subject: Some(subject),
+            predicate: self.selected_predicate,
+            ..Default::default()
+        });
+        self.run_view(ctx);
     }
 
-    fn resolve_object(&self, name: &str) -> Option<u64> {
-        self.store
-            .as_ref()
-            .and_then(|s| s.get_literal_id(name).or_else(|| s.get_entity_id(name)))
+    /// Load the facts of the base query narrowed by the fact filter in the
+    /// background. A filter that does not resolve leaves the view as it was.
+    /// While a worker holds the store the view is run on a later frame.
+    fn run_view(&mut self, ctx: &egui::Context) {
+        self.view_stale = false;
+        let Some(handle) = self.store.clone() else {
+            return;
+        };
+        let base = match &self.base_query {
+            Some(query) => query.clone(),
+            None if self.fact_filter.is_active() => Query::default(),
+            None => {
+                self.worker.cancel_query();
+                self.facts.clear();
+                self.matched = 0;
+                self.view_query = None;
+                return;
+            }
+        };
+        let mut query = base;
+        // The scan being replaced lets go of the store between batches.
+        self.worker.cancel_query();
+        let Ok(store) = handle.try_lock() else {
+            self.view_stale = true;
+            return;
+        };
+        let narrowed = self.fact_filter.narrow(&store, &mut query);
+        drop(store);
+        if let Err(e) = narrowed {
+            self.fact_filter.error = Some(e);
+            return;
+        }
+        self.fact_filter.error = None;
+        self.view_query = Some(query.clone());
+        self.worker.facts(ctx, handle, query, FACT_LIMIT);
     }
 
     fn fact_label(store: &PruStore, fact: &Fact) -> String {
@@ -89,10 +260,21 @@ impl PruGuiApp {
         let p = store
             .get_predicate_name(fact.predicate)
             .unwrap_or_else(|| format!("#{}", fact.predicate));
-        let o = store
+        let mut o = store
             .get_entity_name(fact.object)
             .or_else(|| store.get_literal_value(fact.object))
             .unwrap_or_else(|| format!("#{}", fact.object));
+        if p == PRED_HAS_FEATURE {
+            if let Some((name, value)) = parse_feature_payload(&o) {
+                o = format!("{name}={value}");
+            }
+        }
+        // Detector outputs (scores, features, details) are attributed via the source.
+        let by = fact
+            .source
+            .and_then(|id| store.get_entity_name(id))
+            .map(|name| format!(" · by {name}"))
+            .unwrap_or_default();
         let conf = fact
             .confidence
             .map(|c| format!(" · conf={:.2}", c))
@@ -101,46 +283,74 @@ impl PruGuiApp {
             .timestamp
             .map(|t| format!(" · t={t}"))
             .unwrap_or_default();
-        format!("{s} {p} {o}{conf}{ts}")
+        format!("{s} {p} {o}{by}{conf}{ts}")
     }
 
     fn render_atoms(&mut self, ui: &mut egui::Ui) {
         ui.heading("Atoms");
         ui.separator();
+        let Some(handle) = self.store.clone() else {
+            ui.label("Open a store to list atoms.");
+            return;
+        };
+        let Ok(store) = handle.try_lock() else {
+            ui.horizontal(|ui| {
+                ui.spinner();
+                ui.label("Store busy…");
+            });
+            return;
+        };
         ui.label(RichText::new("Entities").strong());
-        for (id, name) in self.entities.clone() {
-            let selected = Some(id) == self.selected_entity;
-            if ui
-                .selectable_label(selected, format!("{name} (#{id})"))
-                .clicked()
-            {
-                self.selected_entity = Some(id);
-                if let Err(e) = self.refresh_facts() {
-                    self.error = Some(format!("Failed to refresh facts: {e}"));
-                }
-            }
-        }
+        let entity = self.entity_list.show(
+            ui,
+            &store,
+            AtomKind::Entity,
+            &self.entities,
+            self.selected_entity,
+        );
         ui.separator();
         ui.label(RichText::new("Predicates").strong());
-        for (id, name) in self.predicates.clone() {
-            let selected = Some(id) == self.selected_predicate;
-            if ui
-                .selectable_label(selected, format!("{name} (#{id})"))
-                .clicked()
-            {
-                self.selected_predicate = Some(id);
-                if let Err(e) = self.refresh_facts() {
-                    self.error = Some(format!("Failed to refresh facts: {e}"));
-                }
-            }
-        }
+        let predicate = self.predicate_list.show(
+            ui,
+            &store,
+            AtomKind::Predicate,
+            &self.predicates,
+            self.selected_predicate,
+        );
         ui.separator();
         ui.label(RichText::new("Literals").strong());
-        for (id, val) in &self.literals {
-            ui.label(format!("{val} (#{id})"));
+        self.literal_list
+            .show(ui, &store, AtomKind::Literal, &self.literals, None);
+        drop(store);
+
+        if entity.is_some() || predicate.is_some() {
+            self.selected_entity = entity.or(self.selected_entity);
+            self.selected_predicate = predicate.or(self.selected_predicate);
+            self.refresh_facts(ui.ctx());
         }
     }
 
+    #[cfg(debug_assertions)]
+    fn render_debug_menu(&mut self, ui: &mut egui::Ui) {
+        ui.menu_button("Debug", |ui| {
+            if ui.button("Generate scale test store").clicked() {
+                ui.close_menu();
+                let secs = std::time::SystemTime::now()
+                    .duration_since(std::time::UNIX_EPOCH)
+                    .map_or(0, |d| d.as_secs());
+                let dir = std::env::temp_dir().join(format!("pru-gui-scale-{secs}"));
+                match crate::debug::generate_scale_store(&dir) {
+                    Ok(()) => {
+                        self.dir_input = dir.display().to_string();
+                        self.load_store(ui.ctx());
+                    }
+                    Err(e) => self.error = Some(format!("Failed to generate store: {e}")),
+                }
+            }
+            ui.checkbox(&mut self.worker.simulate_slow, "Simulate slow store");
+        });
+    }
+
     fn render_facts(&mut self, ui: &mut egui::Ui) {
         ui.heading("Facts");
         ui.separator();
@@ -151,15 +361,54 @@ impl PruGuiApp {
         if let Some(subj) = self.selected_entity {
             ui.label(format!("Subject: #{subj}"));
         }
+        match self.fact_filter.show(ui) {
+            Some(FilterAction::Apply) => self.run_view(ui.ctx()),
+            Some(FilterAction::Clear) => {
+                self.fact_filter.clear();
+                self.run_view(ui.ctx());
+            }
+            None => {}
+        }
+        if self.view_query.is_some() {
+            let shown = if self.matched > self.facts.len() {
+                format!(" (showing first {})", self.facts.len())
+            } else {
+                String::new()
+            };
+            let filter = self
+                .fact_filter
+                .applied()
+                .map(|summary| format!("Filter: {summary} · "))
+                .unwrap_or_default();
+            ui.label(format!("{filter}{} matched{shown}", self.matched));
+        }
+        self.render_export(ui);
+        if self.view_stale {
+            ui.horizontal(|ui| {
+                ui.spinner();
+                ui.label("Store busy…");
+            });
+        }
+        if let Some(pending) = &self.worker.querying {
+            let mut cancel = false;
+            ui.horizontal(|ui| {
+                ui.spinner();
+                ui.label(format!("Loading facts… {}s", pending.elapsed().as_secs()));
+                cancel = ui.button("Cancel").clicked();
+            });
+            if cancel {
+                self.worker.cancel_query();
+            }
+        }
         if self.facts.is_empty() {
             ui.label("No facts for the current filters.");
             return;
         }
-        if let Some(store) = self.store.as_ref() {
+        if let Some(store) = self.try_store() {
             egui::ScrollArea::vertical().show(ui, |ui| {
                 for fact in &self.facts {
                     ui.horizontal(|ui| {
-                        ui.label(Self::fact_label(store, fact));
+                        ui.label(Self::fact_label(&store, fact));
                         ui.small(format!(
                             "ids: s={} p={} o={}",
                             fact.subject, fact.predicate, fact.object
@@ -171,6 +420,71 @@ impl PruGuiApp {
         }
     }
 
+    fn render_export(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            egui::ComboBox::from_id_source("export_format")
+                .selected_text(self.export_format.name())
+                .show_ui(ui, |ui| {
+                    for format in [ExportFormat::Jsonl, ExportFormat::Csv] {
+                        ui.selectable_value(&mut self.export_format, format, format.name());
+                    }
+                });
+            let export = egui::Button::new("Export…");
+            if ui.add_enabled(self.view_query.is_some(), export).clicked() {
+                self.export_view();
+            }
+            if let Some(status) = &self.export_status {
+                ui.label(status);
+            }
+        });
+    }
+
+    /// Write every fact matching the shown view, not just the displayed
+    /// ones, to a file the user picks.
+    fn export_view(&mut self) {
+        let (Some(handle), Some(query)) = (self.store.clone(), self.view_query.clone()) else {
+            return;
+        };
+        let extension = self.export_format.extension();
+        let Some(path) = rfd::FileDialog::new()
+            .add_filter(self.export_format.name(), &[extension])
+            .set_file_name(format!("facts.{extension}"))
+            .save_file()
+        else {
+            return;
+        };
+        let Ok(store) = handle.try_lock() else {
+            self.export_status = Some("Store busy; export again in a moment".to_string());
+            return;
+        };
+        match Self::export_to(&store, query, self.export_format, &path) {
+            Ok(count) => {
+                self.export_status =
+                    Some(format!("Exported {count} fact(s) to {}", path.display()));
+            }
+            Err(e) => {
+                self.export_status = None;
+                self.error = Some(format!("Export to {} failed: {e}", path.display()));
+            }
+        }
+    }
+
+    fn export_to(
+        store: &PruStore,
+        query: Query,
+        format: ExportFormat,
+        path: &Path,
+    ) -> pru_core::errors::Result<usize> {
+        let file = std::fs::File::create(path)?;
+        write_facts(
+            store,
+            store.query_iter(query),
+            format,
+            false,
+            std::io::BufWriter::new(file),
+        )
+    }
+
     fn render_query(&mut self, ui: &mut egui::Ui) {
         ui.heading("Query");
         ui.separator();
@@ -191,114 +505,243 @@ impl PruGuiApp {
             ui.add(egui::Slider::new(&mut self.query_min_confidence, 0.0..=1.0));
         });
         if ui.button("Run query").clicked() {
-            self.run_query();
+            self.run_query(ui.ctx());
         }
     }
 
-    fn parse_id(input: &str) -> Option<u64> {
-        input.trim().parse::<u64>().ok()
-    }
-
-    fn run_query(&mut self) {
-        let store = match self.store.as_ref() {
-            Some(s) => s,
-            None => {
-                self.error = Some("Open a store first".to_string());
-                return;
-            }
+    fn run_query(&mut self, ctx: &egui::Context) {
+        let Some(handle) = self.store.clone() else {
+            self.error = Some("Open a store first".to_string());
+            return;
+        };
+        let Ok(store) = handle.try_lock() else {
+            self.error = Some("Store busy; run the query again in a moment".to_string());
+            return;
         };
 
         let subject = if self.query_subject.trim().is_empty() {
             None
         } else {
-            Self::parse_id(&self.query_subject).or_else(|| self.resolve_entity(&self.query_subject))
+            parse_id(&self.query_subject).or_else(|| resolve_entity(&store, &self.query_subject))
         };
         let predicate = if self.query_predicate.trim().is_empty() {
             None
         } else {
-            Self::parse_id(&self.query_predicate)
-                .or_else(|| self.resolve_predicate(&self.query_predicate))
+            parse_id(&self.query_predicate)
+                .or_else(|| resolve_predicate(&store, &self.query_predicate))
         };
         let object = if self.query_object.trim().is_empty() {
             None
         } else {
-            Self::parse_id(&self.query_object).or_else(|| self.resolve_object(&self.query_object))
+            parse_id(&self.query_object).or_else(|| resolve_object(&store, &self.query_object))
         };
 
+        drop(store);
         let query = Query {
             subject,
             predicate,
             object,
             min_confidence: Some(self.query_min_confidence),
+            ..Default::default()
         };
-        match store.query(query) {
-            Ok(mut facts) => {
-                facts.truncate(FACT_LIMIT);
-                self.facts = facts;
-                self.selected_entity = subject;
-                self.selected_predicate = predicate;
-                self.error = None;
-            }
-            Err(e) => {
-                self.error = Some(format!("Query failed: {e}"));
-            }
+        self.selected_entity = subject;
+        self.selected_predicate = predicate;
+        self.error = None;
+        self.base_query = Some(query);
+        self.run_view(ctx);
+    }
+}
+
+impl PruGuiApp {
+    fn render_fact_form(&mut self, ui: &mut egui::Ui) {
+        let Some(handle) = self.store.clone() else {
+            ui.label("Open a store to add facts.");
+            return;
+        };
+        let Ok(mut store) = handle.try_lock() else {
+            ui.label("Store busy…");
+            return;
+        };
+        let added = self.fact_form.show(ui, &mut store);
+        drop(store);
+        if let Some(subject) = added {
+            self.auto_refresh.wrote();
+            self.reload_atoms();
+            self.selected_entity = Some(subject);
+            self.refresh_facts(ui.ctx());
         }
     }
 }
 
 impl eframe::App for PruGuiApp {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        self.poll_worker(ctx);
+        self.retry_stale(ctx);
+        self.poll_ingest(ctx);
+        self.poll_auto_refresh(ctx);
         egui::TopBottomPanel::top("controls").show(ctx, |ui| {
             ui.horizontal(|ui| {
                 ui.label("Directory:");
                 ui.text_edit_singleline(&mut self.dir_input);
                 if ui.button("Open").clicked() {
-                    self.load_store();
+                    self.load_store(ctx);
                 }
                 if ui.button("Refresh").clicked() {
                     if self.store.is_some() {
-                        self.load_store();
+                        self.load_store(ctx);
+                    }
+                }
+                #[cfg(debug_assertions)]
+                self.render_debug_menu(ui);
+                if let Some(pending) = &self.worker.opening {
+                    let what = if self.reloading {
+                        "Reloading"
+                    } else {
+                        "Opening"
+                    };
+                    ui.spinner();
+                    ui.label(format!("{what} store… {}s", pending.elapsed().as_secs()));
+                    if ui.button("Cancel").clicked() {
+                        self.worker.cancel_open();
+                        self.reloading = false;
                     }
                 }
                 if let Some(err) = &self.error {
                     ui.colored_label(egui::Color32::from_rgb(200, 60, 60), err);
                 }
             });
-            if let Some(store) = self.store.as_ref() {
+            if self.store.is_some() {
+                let facts = self
+                    .try_store()
+                    .map_or("…".to_string(), |store| store.fact_count().to_string());
                 ui.separator();
                 ui.horizontal(|ui| {
                     ui.label(RichText::new("Overview").strong());
                     ui.label(format!(
-                        "entities={} predicates={} literals={} facts={}",
-                        store.entities().len(),
-                        store.predicates().len(),
-                        store.literals().len(),
-                        store.fact_count()
+                        "entities={} predicates={} literals={} facts={facts}",
+                        self.entities.len(),
+                        self.predicates.len(),
+                        self.literals.len(),
                     ));
+                    ui.checkbox(&mut self.auto_refresh.enabled, "Auto-refresh");
+                    if self.auto_refresh.enabled {
+                        let note = if self.fact_form.is_dirty() {
+                            "paused while the add-fact form has edits".to_string()
+                        } else if let Some(age) = self.auto_refresh.age() {
+                            format!("updated {}s ago", age.as_secs())
+                        } else {
+                            String::new()
+                        };
+                        ui.label(RichText::new(note).weak().small());
+                    }
                 });
             } else {
                 ui.label("Select a PRU-DB directory to begin.");
             }
+            ui.separator();
+            ui.horizontal(|ui| {
+                ui.selectable_value(&mut self.tab, Tab::Facts, "Facts");
+                ui.selectable_value(&mut self.tab, Tab::Media, "Media");
+                ui.selectable_value(&mut self.tab, Tab::Ingest, "Ingest");
+                ui.selectable_value(&mut self.tab, Tab::Detectors, "Detectors");
+                ui.selectable_value(&mut self.tab, Tab::Storage, "Storage");
+            });
         });
 
         egui::SidePanel::left("atoms").show(ctx, |ui| {
-            egui::ScrollArea::vertical().show(ui, |ui| {
-                self.render_atoms(ui);
-            });
+            self.render_atoms(ui);
         });
 
-        egui::CentralPanel::default().show(ctx, |ui| {
-            egui::CollapsingHeader::new("Facts")
-                .default_open(true)
-                .show(ui, |ui| {
-                    self.render_facts(ui);
-                });
+        egui::CentralPanel::default().show(ctx, |ui| match self.tab {
+            Tab::Facts => self.render_facts_tab(ui),
+            Tab::Media => self.render_media_tab(ui),
+            Tab::Ingest => self.render_ingest_tab(ui),
+            Tab::Detectors => self.render_detectors_tab(ui),
+            Tab::Storage => self.render_storage_tab(ui),
+        });
+    }
+}
 
-            egui::CollapsingHeader::new("Query")
-                .default_open(true)
-                .show(ui, |ui| {
-                    self.render_query(ui);
-                });
+impl PruGuiApp {
+    /// Re-read what could not be read while a worker held the store.
+    fn retry_stale(&mut self, ctx: &egui::Context) {
+        if self.atoms_stale {
+            self.reload_atoms();
+        }
+        if self.view_stale {
+            self.run_view(ctx);
+        }
+        if self.atoms_stale || self.view_stale {
+            ctx.request_repaint_after(std::time::Duration::from_millis(50));
+        }
+    }
+
+    fn render_facts_tab(&mut self, ui: &mut egui::Ui) {
+        egui::CollapsingHeader::new("Facts")
+            .default_open(true)
+            .show(ui, |ui| {
+                self.render_facts(ui);
+            });
+
+        egui::CollapsingHeader::new("Query")
+            .default_open(true)
+            .show(ui, |ui| {
+                self.render_query(ui);
+            });
+
+        egui::CollapsingHeader::new("Add fact")
+            .default_open(false)
+            .show(ui, |ui| {
+                self.render_fact_form(ui);
+            });
+    }
+
+    fn render_ingest_tab(&mut self, ui: &mut egui::Ui) {
+        let Some(handle) = self.store.clone() else {
+            ui.label("Open a store to ingest files.");
+            return;
+        };
+        self.ingest_panel.show(ui, &handle);
+    }
+
+    /// Start ingesting dropped files from any tab, and refresh the atom lists
+    /// and fact view once a batch has written to the store.
+    fn poll_ingest(&mut self, ctx: &egui::Context) {
+        if let Some(handle) = self.store.clone() {
+            if self.ingest_panel.take_dropped(ctx, &handle) {
+                self.tab = Tab::Ingest;
+            }
+        }
+        if self.ingest_panel.poll() {
+            self.auto_refresh.wrote();
+            self.reload_atoms();
+            self.refresh_facts(ctx);
+        }
+    }
+
+    fn render_detectors_tab(&mut self, ui: &mut egui::Ui) {
+        let Some(handle) = self.store.clone() else {
+            ui.label("Open a store to list detectors.");
+            return;
+        };
+        self.detectors_panel.show(ui, &handle);
+    }
+
+    fn render_storage_tab(&mut self, ui: &mut egui::Ui) {
+        let Some(handle) = self.store.clone() else {
+            ui.label("Open a store to inspect its segments.");
+            return;
+        };
+        self.storage_panel.show(ui, &handle);
+    }
+
+    fn render_media_tab(&mut self, ui: &mut egui::Ui) {
+        let Some(handle) = self.store.clone() else {
+            ui.label("Open a store to evaluate media.");
+            return;
+        };
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            self.media_panel.show(ui, &handle, &self.media);
         });
     }
 }
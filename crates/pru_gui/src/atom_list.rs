@@ -0,0 +1,70 @@
+use eframe::egui::{self, RichText};
+use pru_core::{AtomKind, PruStore};
+
+/// Most matches a filter shows.
+const SEARCH_LIMIT: usize = 1000;
+const LIST_HEIGHT: f32 = 240.0;
+
+/// One atom kind in the side panel: a filter box over a list that only lays
+/// out the rows in view. Entities and predicates can be selected.
+#[derive(Default)]
+pub struct AtomList {
+    pub filter: String,
+    /// Matches of `filter`, or `None` to show every atom.
+    matches: Option<Vec<(u64, String)>>,
+}
+
+impl AtomList {
+    /// Re-run the filter, e.g. after the store changed.
+    pub fn refresh(&mut self, store: &PruStore, kind: AtomKind) {
+        let needle = self.filter.trim();
+        self.matches = (!needle.is_empty()).then(|| store.search_atoms(kind, needle, SEARCH_LIMIT));
+    }
+
+    /// Draw the list of `all` atoms, or of the filter's matches. Returns the id
+    /// clicked this frame.
+    pub fn show(
+        &mut self,
+        ui: &mut egui::Ui,
+        store: &PruStore,
+        kind: AtomKind,
+        all: &[(u64, String)],
+        selected: Option<u64>,
+    ) -> Option<u64> {
+        let filter = egui::TextEdit::singleline(&mut self.filter).hint_text("Filter");
+        if ui.add(filter).changed() {
+            self.refresh(store, kind);
+        }
+        let rows = self.matches.as_deref().unwrap_or(all);
+        match &self.matches {
+            Some(m) if m.len() == SEARCH_LIMIT => {
+                ui.small(format!("first {SEARCH_LIMIT} matches"));
+            }
+            Some(m) => {
+                ui.small(format!("{} of {} match", m.len(), all.len()));
+            }
+            None => {}
+        }
+
+        let mut clicked = None;
+        let row_height = ui.text_style_height(&egui::TextStyle::Body);
+        egui::ScrollArea::vertical()
+            .id_source(kind.to_string())
+            .max_height(LIST_HEIGHT)
+            .auto_shrink([false, true])
+            .show_rows(ui, row_height, rows.len(), |ui, range| {
+                for (id, name) in &rows[range] {
+                    let text = format!("{name} (#{id})");
+                    if kind == AtomKind::Literal {
+                        ui.label(text);
+                    } else if ui.selectable_label(Some(*id) == selected, text).clicked() {
+                        clicked = Some(*id);
+                    }
+                }
+            });
+        if rows.is_empty() {
+            ui.label(RichText::new("No matches").weak());
+        }
+        clicked
+    }
+}
@@ -0,0 +1,68 @@
+//! Noticing writes other processes make to the open store by polling the
+//! modification times of its files.
+
+use eframe::egui;
+use pru_core::PruStore;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Modification time and size of each of a store's data files.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StoreStamp(Vec<Option<(SystemTime, u64)>>);
+
+impl StoreStamp {
+    pub fn read(dir: &Path) -> Self {
+        let stamp = |path: &PathBuf| {
+            let meta = std::fs::metadata(path).ok()?;
+            Some((meta.modified().ok()?, meta.len()))
+        };
+        Self(PruStore::data_paths(dir).iter().map(stamp).collect())
+    }
+}
+
+#[derive(Default)]
+pub struct AutoRefresh {
+    pub enabled: bool,
+    /// The open store's directory and its files as last loaded.
+    loaded: Option<(PathBuf, StoreStamp)>,
+    updated: Option<Instant>,
+    last_poll: Option<Instant>,
+}
+
+impl AutoRefresh {
+    /// Record the store at `dir` as loaded from files stamped `stamp`.
+    pub fn loaded(&mut self, dir: PathBuf, stamp: StoreStamp) {
+        self.loaded = Some((dir, stamp));
+        self.updated = Some(Instant::now());
+    }
+
+    /// Re-stamp the files after the explorer wrote to the store itself, so
+    /// its own writes are not taken for someone else's.
+    pub fn wrote(&mut self) {
+        if let Some((dir, stamp)) = &mut self.loaded {
+            *stamp = StoreStamp::read(dir);
+        }
+    }
+
+    /// How long ago the view was last loaded from disk.
+    pub fn age(&self) -> Option<Duration> {
+        self.updated.map(|t| t.elapsed())
+    }
+
+    /// The store's directory if its files changed since they were loaded.
+    /// Looks at most once per interval, and asks for a frame at the next one.
+    pub fn poll(&mut self, ctx: &egui::Context) -> Option<PathBuf> {
+        if !self.enabled {
+            return None;
+        }
+        ctx.request_repaint_after(POLL_INTERVAL);
+        if self.last_poll.is_some_and(|t| t.elapsed() < POLL_INTERVAL) {
+            return None;
+        }
+        self.last_poll = Some(Instant::now());
+        let (dir, stamp) = self.loaded.as_ref()?;
+        (StoreStamp::read(dir) != *stamp).then(|| dir.clone())
+    }
+}
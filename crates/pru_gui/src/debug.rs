@@ -0,0 +1,33 @@
+//! Debug-build helpers for trying the explorer at scale.
+
+use pru_core::errors::Result;
+use pru_core::{Fact, PruStore};
+use std::path::Path;
+
+const ENTITIES: u64 = 50_000;
+const PREDICATES: u64 = 200;
+const FACTS: u64 = 100_000;
+
+/// Fill a new store at `dir` with generated atoms and facts, in one
+/// transaction so atoms and facts are each written once.
+pub fn generate_scale_store(dir: &Path) -> Result<()> {
+    let mut store = PruStore::open(dir)?;
+    store.transaction(|store| {
+        let entities = (0..ENTITIES)
+            .map(|i| store.intern_entity(&format!("entity-{i:05}")))
+            .collect::<Result<Vec<_>>>()?;
+        let predicates = (0..PREDICATES)
+            .map(|i| store.intern_predicate(&format!("predicate-{i:03}")))
+            .collect::<Result<Vec<_>>>()?;
+        (0..FACTS).try_for_each(|i| {
+            store.add_fact(Fact {
+                subject: entities[(i % ENTITIES) as usize],
+                predicate: predicates[(i % PREDICATES) as usize],
+                object: entities[(i * 7919 % ENTITIES) as usize],
+                source: None,
+                timestamp: None,
+                confidence: None,
+            })
+        })
+    })
+}
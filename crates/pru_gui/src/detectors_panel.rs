@@ -0,0 +1,330 @@
+use anyhow::{Context as _, Result};
+use eframe::egui::{self, Color32, RichText};
+use pru_core::PruDbHandle;
+use pru_media_schema::{
+    detector_last_scored, get_detector_name, get_detector_reliability, DetectorReliability,
+};
+use pru_truth_engine::{AccuracyReport, TruthEngine, TruthEngineConfig};
+use std::cmp::Ordering;
+use std::collections::BTreeSet;
+use std::path::Path;
+
+/// The label precision and recall are reported for.
+const POSITIVE_LABEL: &str = "ai";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Column {
+    Detector,
+    Seen,
+    Correct,
+    Accuracy,
+    Precision,
+    Recall,
+    LastScored,
+}
+
+impl Column {
+    const ALL: [Column; 7] = [
+        Column::Detector,
+        Column::Seen,
+        Column::Correct,
+        Column::Accuracy,
+        Column::Precision,
+        Column::Recall,
+        Column::LastScored,
+    ];
+
+    fn title(self) -> &'static str {
+        match self {
+            Column::Detector => "Detector",
+            Column::Seen => "Seen",
+            Column::Correct => "Correct",
+            Column::Accuracy => "Accuracy",
+            Column::Precision => "Precision (ai)",
+            Column::Recall => "Recall (ai)",
+            Column::LastScored => "Last score",
+        }
+    }
+}
+
+struct Row {
+    id: u64,
+    name: String,
+    reliability: DetectorReliability,
+    last_scored: Option<i64>,
+}
+
+impl Row {
+    fn accuracy(&self) -> Option<f64> {
+        let r = &self.reliability;
+        (r.seen > 0).then(|| r.correct as f64 / r.seen as f64)
+    }
+
+    fn precision(&self) -> Option<f64> {
+        self.reliability.precision(POSITIVE_LABEL)
+    }
+
+    fn recall(&self) -> Option<f64> {
+        self.reliability.recall(POSITIVE_LABEL)
+    }
+
+    fn compare(&self, other: &Row, column: Column) -> Ordering {
+        let by_ratio =
+            |a: Option<f64>, b: Option<f64>| a.partial_cmp(&b).unwrap_or(Ordering::Equal);
+        match column {
+            Column::Detector => self.name.cmp(&other.name),
+            Column::Seen => self.reliability.seen.cmp(&other.reliability.seen),
+            Column::Correct => self.reliability.correct.cmp(&other.reliability.correct),
+            Column::Accuracy => by_ratio(self.accuracy(), other.accuracy()),
+            Column::Precision => by_ratio(self.precision(), other.precision()),
+            Column::Recall => by_ratio(self.recall(), other.recall()),
+            Column::LastScored => self.last_scored.cmp(&other.last_scored),
+        }
+    }
+}
+
+fn ratio(value: Option<f64>) -> String {
+    value.map_or("—".to_string(), |v| format!("{v:.2}"))
+}
+
+/// Predicted label rows against true label columns.
+fn show_confusion(ui: &mut egui::Ui, reliability: &DetectorReliability) {
+    let actual: BTreeSet<&String> = reliability
+        .confusion
+        .values()
+        .flat_map(|row| row.keys())
+        .collect();
+    egui::Grid::new("confusion").striped(true).show(ui, |ui| {
+        ui.small("predicted \\ true");
+        for label in &actual {
+            ui.small(label.as_str());
+        }
+        ui.end_row();
+        for (predicted, row) in &reliability.confusion {
+            ui.small(predicted);
+            for label in &actual {
+                ui.small(row.get(*label).copied().unwrap_or(0).to_string());
+            }
+            ui.end_row();
+        }
+    });
+}
+
+/// The Detectors tab: how each scoring detector fared against human verdicts,
+/// and how the combined engine does.
+#[derive(Default)]
+pub struct DetectorsPanel {
+    rows: Vec<Row>,
+    accuracy: Option<AccuracyReport>,
+    sort: Option<(Column, bool)>,
+    loaded: bool,
+    status: Option<String>,
+    error: Option<String>,
+}
+
+impl DetectorsPanel {
+    /// Forget the loaded table, e.g. when another store is opened.
+    pub fn clear(&mut self) {
+        *self = Self {
+            sort: self.sort,
+            ..Self::default()
+        };
+    }
+
+    fn load(handle: &PruDbHandle) -> Result<(Vec<Row>, AccuracyReport)> {
+        let mut rows = Vec::new();
+        for (detector, last_scored) in detector_last_scored(handle)? {
+            rows.push(Row {
+                id: detector.0,
+                name: get_detector_name(handle, detector)?
+                    .unwrap_or_else(|| format!("#{}", detector.0)),
+                reliability: get_detector_reliability(handle, detector)?.unwrap_or_default(),
+                last_scored,
+            });
+        }
+        let engine = TruthEngine::new(TruthEngineConfig::default());
+        let accuracy = engine.accuracy_report(handle, None)?;
+        Ok((rows, accuracy))
+    }
+
+    fn refresh(&mut self, handle: &PruDbHandle) {
+        self.loaded = true;
+        self.status = None;
+        match Self::load(handle) {
+            Ok((rows, accuracy)) => {
+                self.rows = rows;
+                self.accuracy = Some(accuracy);
+                self.error = None;
+                self.apply_sort();
+            }
+            Err(e) => self.error = Some(format!("Failed to load detectors: {e:#}")),
+        }
+    }
+
+    fn apply_sort(&mut self) {
+        let Some((column, descending)) = self.sort else {
+            return;
+        };
+        self.rows.sort_by(|a, b| {
+            let ord = a.compare(b, column);
+            if descending {
+                ord.reverse()
+            } else {
+                ord
+            }
+        });
+    }
+
+    /// Sort by `column`, flipping the direction when it is already the key.
+    fn sort_by(&mut self, column: Column) {
+        self.sort = match self.sort {
+            Some((current, descending)) if current == column => Some((column, !descending)),
+            _ => Some((column, false)),
+        };
+        self.apply_sort();
+    }
+
+    /// Write the table, in its shown order, to `path`.
+    fn write_csv(&self, path: &Path) -> Result<()> {
+        let mut out = csv::Writer::from_path(path)
+            .with_context(|| format!("failed to create {}", path.display()))?;
+        out.write_record([
+            "detector",
+            "id",
+            "seen",
+            "correct",
+            "accuracy",
+            "precision_ai",
+            "recall_ai",
+            "last_scored",
+            "confusion",
+        ])?;
+        let optional = |v: Option<f64>| v.map(|v| v.to_string()).unwrap_or_default();
+        for row in &self.rows {
+            out.write_record([
+                row.name.clone(),
+                row.id.to_string(),
+                row.reliability.seen.to_string(),
+                row.reliability.correct.to_string(),
+                optional(row.accuracy()),
+                optional(row.precision()),
+                optional(row.recall()),
+                row.last_scored.map(|t| t.to_string()).unwrap_or_default(),
+                serde_json::to_string(&row.reliability.confusion)?,
+            ])?;
+        }
+        out.flush()?;
+        Ok(())
+    }
+
+    fn export(&mut self) {
+        let Some(path) = rfd::FileDialog::new()
+            .add_filter("CSV", &["csv"])
+            .set_file_name("detectors.csv")
+            .save_file()
+        else {
+            return;
+        };
+        match self.write_csv(&path) {
+            Ok(()) => {
+                self.status = Some(format!(
+                    "Wrote {} rows to {}",
+                    self.rows.len(),
+                    path.display()
+                ));
+                self.error = None;
+            }
+            Err(e) => self.error = Some(format!("Export failed: {e:#}")),
+        }
+    }
+
+    fn show_accuracy(&self, ui: &mut egui::Ui) {
+        let Some(report) = &self.accuracy else {
+            return;
+        };
+        if report.media == 0 {
+            ui.label(RichText::new("No scored media with a human verdict yet.").weak());
+            return;
+        }
+        ui.label(format!(
+            "Engine accuracy {:.1}% over {} media  brier={:.3}  log loss={:.3}  inconclusive={}",
+            report.accuracy * 100.0,
+            report.media,
+            report.brier_score,
+            report.log_loss,
+            report.inconclusive
+        ));
+    }
+
+    fn show_table(&mut self, ui: &mut egui::Ui) {
+        let mut clicked = None;
+        egui::Grid::new("detector_reliability")
+            .striped(true)
+            .show(ui, |ui| {
+                for column in Column::ALL {
+                    let arrow = match self.sort {
+                        Some((c, false)) if c == column => " ⏶",
+                        Some((c, true)) if c == column => " ⏷",
+                        _ => "",
+                    };
+                    if ui.button(format!("{}{arrow}", column.title())).clicked() {
+                        clicked = Some(column);
+                    }
+                }
+                ui.label(RichText::new("Confusion").strong());
+                ui.end_row();
+                for row in &self.rows {
+                    ui.label(format!("{} (#{})", row.name, row.id));
+                    ui.label(row.reliability.seen.to_string());
+                    ui.label(row.reliability.correct.to_string());
+                    ui.label(ratio(row.accuracy()));
+                    ui.label(ratio(row.precision()));
+                    ui.label(ratio(row.recall()));
+                    ui.label(row.last_scored.map_or("—".to_string(), |t| t.to_string()));
+                    if row.reliability.confusion.is_empty() {
+                        ui.label("—");
+                    } else {
+                        ui.push_id(row.id, |ui| {
+                            ui.collapsing("matrix", |ui| show_confusion(ui, &row.reliability));
+                        });
+                    }
+                    ui.end_row();
+                }
+            });
+        if let Some(column) = clicked {
+            self.sort_by(column);
+        }
+    }
+
+    pub fn show(&mut self, ui: &mut egui::Ui, handle: &PruDbHandle) {
+        if !self.loaded {
+            self.refresh(handle);
+        }
+        ui.heading("Detectors");
+        ui.separator();
+        ui.horizontal(|ui| {
+            if ui.button("Refresh").clicked() {
+                self.refresh(handle);
+            }
+            if ui
+                .add_enabled(!self.rows.is_empty(), egui::Button::new("Export CSV…"))
+                .clicked()
+            {
+                self.export();
+            }
+            if let Some(status) = &self.status {
+                ui.label(status);
+            }
+        });
+        if let Some(err) = &self.error {
+            ui.colored_label(Color32::from_rgb(200, 60, 60), err);
+        }
+        self.show_accuracy(ui);
+        ui.separator();
+        if self.rows.is_empty() {
+            ui.label("No detector has scored media in this store.");
+            return;
+        }
+        egui::ScrollArea::both().show(ui, |ui| self.show_table(ui));
+    }
+}
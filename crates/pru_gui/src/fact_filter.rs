@@ -0,0 +1,106 @@
+use eframe::egui::{self, Color32};
+use pru_core::{PruStore, Query};
+use std::collections::BTreeSet;
+
+use crate::app::{parse_id, resolve_object};
+
+const ERR_COLOR: Color32 = Color32::from_rgb(200, 60, 60);
+
+pub enum FilterAction {
+    Apply,
+    Clear,
+}
+
+/// Filters above the fact list that narrow the subject/predicate view by
+/// object: an object name or id, and text the rendered object must contain.
+#[derive(Default)]
+pub struct FactFilter {
+    pub object: String,
+    pub contains: String,
+    /// Why the last apply failed; the previous view stays up meanwhile.
+    pub error: Option<String>,
+    /// Summary of the filters behind the shown view, if any.
+    applied: Option<String>,
+}
+
+impl FactFilter {
+    pub fn is_active(&self) -> bool {
+        !self.object.trim().is_empty() || !self.contains.trim().is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        *self = Self::default();
+    }
+
+    /// Restrict `query` to the objects the filters allow, looked up in
+    /// `store` so facts past the display limit are matched as well.
+    pub fn narrow(&mut self, store: &PruStore, query: &mut Query) -> Result<(), String> {
+        let (object, contains) = (self.object.trim(), self.contains.trim());
+        let mut objects: Option<BTreeSet<u64>> = None;
+        if !object.is_empty() {
+            let id = parse_id(object)
+                .or_else(|| resolve_object(store, object))
+                .ok_or_else(|| format!("Unknown object {object:?}"))?;
+            objects = Some(BTreeSet::from([id]));
+        }
+        if !contains.is_empty() {
+            let found = store.objects_containing(contains);
+            objects = Some(match objects {
+                Some(ids) => ids.intersection(&found).copied().collect(),
+                None => found,
+            });
+        }
+        query.objects = objects;
+        self.applied = self.summary();
+        Ok(())
+    }
+
+    /// The filters the shown view was narrowed by, e.g.
+    /// `object=Moon · contains "blue"`.
+    pub fn applied(&self) -> Option<&str> {
+        self.applied.as_deref()
+    }
+
+    fn summary(&self) -> Option<String> {
+        let (object, contains) = (self.object.trim(), self.contains.trim());
+        let parts: Vec<String> = [
+            (!object.is_empty()).then(|| format!("object={object}")),
+            (!contains.is_empty()).then(|| format!("contains {contains:?}")),
+        ]
+        .into_iter()
+        .flatten()
+        .collect();
+        (!parts.is_empty()).then(|| parts.join(" · "))
+    }
+
+    pub fn show(&mut self, ui: &mut egui::Ui) -> Option<FilterAction> {
+        let mut action = None;
+        ui.horizontal(|ui| {
+            let mut submitted = false;
+            ui.label("Object name or id");
+            let object = ui.add(egui::TextEdit::singleline(&mut self.object).desired_width(120.0));
+            submitted |= object.lost_focus();
+            ui.label("Contains");
+            let contains =
+                ui.add(egui::TextEdit::singleline(&mut self.contains).desired_width(120.0));
+            submitted |= contains.lost_focus();
+            submitted &= ui.input(|i| i.key_pressed(egui::Key::Enter));
+            if ui.button("Filter").clicked() || submitted {
+                action = Some(FilterAction::Apply);
+            }
+            if ui
+                .add_enabled(
+                    self.is_active() || self.applied.is_some(),
+                    egui::Button::new("Clear"),
+                )
+                .clicked()
+            {
+                action = Some(FilterAction::Clear);
+            }
+        });
+        if let Some(err) = &self.error {
+            ui.colored_label(ERR_COLOR, err);
+        }
+        action
+    }
+}
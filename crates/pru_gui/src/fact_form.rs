@@ -0,0 +1,239 @@
+use eframe::egui::{self, Color32, RichText};
+use pru_core::{Fact, PruStore};
+use std::collections::BTreeMap;
+
+use crate::app::{parse_id, resolve_entity, resolve_object, resolve_predicate};
+
+const OK_COLOR: Color32 = Color32::from_rgb(60, 150, 80);
+const ERR_COLOR: Color32 = Color32::from_rgb(200, 60, 60);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum Field {
+    Subject,
+    Predicate,
+    Object,
+    Confidence,
+    Timestamp,
+}
+
+impl Field {
+    fn atom_kind(self) -> &'static str {
+        match self {
+            Field::Subject => "entity",
+            Field::Predicate => "predicate",
+            _ => "entity or literal",
+        }
+    }
+}
+
+/// How a name-or-id field resolves against the open store.
+enum Resolution {
+    Found(u64, String),
+    /// Unknown name that submitting will intern.
+    New(String),
+    Invalid(String),
+}
+
+/// State of the "Add fact" panel. Subject, predicate and object take a name
+/// or a numeric id; confidence and timestamp are optional.
+#[derive(Default)]
+pub struct FactForm {
+    pub subject: String,
+    pub predicate: String,
+    pub object: String,
+    pub confidence: String,
+    pub timestamp: String,
+    /// Intern unknown names (as entity, predicate and literal) instead of
+    /// rejecting them.
+    pub create_missing: bool,
+    errors: BTreeMap<Field, String>,
+    status: Option<String>,
+    /// Edited since the last fact was added.
+    dirty: bool,
+}
+
+impl FactForm {
+    /// Whether the form holds edits that were not submitted yet.
+    pub fn is_dirty(&self) -> bool {
+        self.dirty
+    }
+
+    fn input(&self, field: Field) -> &str {
+        match field {
+            Field::Subject => &self.subject,
+            Field::Predicate => &self.predicate,
+            Field::Object => &self.object,
+            Field::Confidence => &self.confidence,
+            Field::Timestamp => &self.timestamp,
+        }
+    }
+
+    fn resolve(&self, store: &PruStore, field: Field) -> Resolution {
+        let input = self.input(field).trim();
+        if input.is_empty() {
+            return Resolution::Invalid("required".into());
+        }
+        let kind = field.atom_kind();
+        if let Some(id) = parse_id(input) {
+            let name = match field {
+                Field::Subject => store.get_entity_name(id),
+                Field::Predicate => store.get_predicate_name(id),
+                _ => store
+                    .get_entity_name(id)
+                    .or_else(|| store.get_literal_value(id)),
+            };
+            return match name {
+                Some(name) => Resolution::Found(id, name),
+                None => Resolution::Invalid(format!("no {kind} with id #{id}")),
+            };
+        }
+        let id = match field {
+            Field::Subject => resolve_entity(store, input),
+            Field::Predicate => resolve_predicate(store, input),
+            _ => resolve_object(store, input),
+        };
+        match id {
+            Some(id) => Resolution::Found(id, input.to_string()),
+            None if self.create_missing => Resolution::New(input.to_string()),
+            None => Resolution::Invalid(format!("unknown {kind} {input:?}")),
+        }
+    }
+
+    /// Optional number in `field`; `Err` holds the inline message.
+    fn parse_optional<T: std::str::FromStr>(&self, field: Field) -> Result<Option<T>, String> {
+        let input = self.input(field).trim();
+        if input.is_empty() {
+            return Ok(None);
+        }
+        input
+            .parse()
+            .map(Some)
+            .map_err(|_| format!("not a number: {input:?}"))
+    }
+
+    /// Validate every field and add the fact. Returns its subject on success;
+    /// on failure the messages are left on the fields they belong to.
+    fn submit(&mut self, store: &mut PruStore) -> Option<u64> {
+        self.errors.clear();
+        self.status = None;
+        // Known atoms as `Ok(id)`, names still to intern as `Err(name)`.
+        let mut atoms = Vec::with_capacity(3);
+        for field in [Field::Subject, Field::Predicate, Field::Object] {
+            match self.resolve(store, field) {
+                Resolution::Found(id, _) => atoms.push(Ok(id)),
+                Resolution::New(name) => atoms.push(Err(name)),
+                Resolution::Invalid(msg) => {
+                    self.errors.insert(field, msg);
+                }
+            }
+        }
+        let confidence = self
+            .parse_optional::<f32>(Field::Confidence)
+            .and_then(|c| match c {
+                Some(c) if !(0.0..=1.0).contains(&c) => Err("must be between 0 and 1".into()),
+                c => Ok(c),
+            })
+            .map_err(|msg| self.errors.insert(Field::Confidence, msg))
+            .ok()
+            .flatten();
+        let timestamp = self
+            .parse_optional::<i64>(Field::Timestamp)
+            .map_err(|msg| self.errors.insert(Field::Timestamp, msg))
+            .ok()
+            .flatten();
+        if !self.errors.is_empty() {
+            return None;
+        }
+
+        let [subject, predicate, object]: [Result<u64, String>; 3] = atoms.try_into().ok()?;
+        let result = (|| {
+            let subject = subject.or_else(|name| store.intern_entity(&name))?;
+            let predicate = predicate.or_else(|name| store.intern_predicate(&name))?;
+            let object = object.or_else(|name| store.intern_literal(&name))?;
+            store.add_fact(Fact {
+                subject,
+                predicate,
+                object,
+                source: None,
+                timestamp,
+                confidence,
+            })?;
+            Ok::<_, pru_core::errors::PruError>(subject)
+        })();
+        match result {
+            Ok(subject) => {
+                self.status = Some(format!(
+                    "Added {} {} {}",
+                    self.subject.trim(),
+                    self.predicate.trim(),
+                    self.object.trim()
+                ));
+                self.object.clear();
+                self.dirty = false;
+                Some(subject)
+            }
+            Err(e) => {
+                self.status = Some(format!("Failed to add fact: {e}"));
+                None
+            }
+        }
+    }
+
+    fn field_row(&mut self, ui: &mut egui::Ui, store: &PruStore, field: Field, label: &str) {
+        ui.horizontal(|ui| {
+            ui.label(label);
+            let text = match field {
+                Field::Subject => &mut self.subject,
+                Field::Predicate => &mut self.predicate,
+                Field::Object => &mut self.object,
+                Field::Confidence => &mut self.confidence,
+                Field::Timestamp => &mut self.timestamp,
+            };
+            if ui.text_edit_singleline(text).changed() {
+                self.errors.remove(&field);
+                self.dirty = true;
+            }
+            if let Some(msg) = self.errors.get(&field) {
+                ui.colored_label(ERR_COLOR, msg);
+                return;
+            }
+            let atom = matches!(field, Field::Subject | Field::Predicate | Field::Object);
+            if !atom || self.input(field).trim().is_empty() {
+                return;
+            }
+            match self.resolve(store, field) {
+                Resolution::Found(id, name) => {
+                    ui.colored_label(OK_COLOR, format!("→ {name} (#{id})"));
+                }
+                Resolution::New(name) => {
+                    ui.label(RichText::new(format!("→ new {name:?}")).weak());
+                }
+                Resolution::Invalid(msg) => {
+                    ui.colored_label(ERR_COLOR, msg);
+                }
+            }
+        });
+    }
+
+    /// Draw the form. Returns the subject of a fact added this frame.
+    pub fn show(&mut self, ui: &mut egui::Ui, store: &mut PruStore) -> Option<u64> {
+        ui.heading("Add fact");
+        ui.separator();
+        self.field_row(ui, store, Field::Subject, "Subject name or id");
+        self.field_row(ui, store, Field::Predicate, "Predicate name or id");
+        self.field_row(ui, store, Field::Object, "Object name or id");
+        self.field_row(ui, store, Field::Confidence, "Confidence (optional)");
+        self.field_row(ui, store, Field::Timestamp, "Timestamp (optional)");
+        ui.checkbox(&mut self.create_missing, "Create unknown names");
+        let mut added = None;
+        ui.horizontal(|ui| {
+            if ui.button("Add fact").clicked() {
+                added = self.submit(store);
+            }
+            if let Some(status) = &self.status {
+                ui.label(status);
+            }
+        });
+        added
+    }
+}
@@ -0,0 +1,260 @@
+use anyhow::{Context as _, Result};
+use eframe::egui::{self, Color32, RichText};
+use pru_core::PruDbHandle;
+use pru_detectors_api::{DetectorRegistry, RegistryConfig};
+use pru_ingest::{DetectorStatus, IngestContext, IngestResult};
+use pru_truth_engine::{DetectionReport, TruthEngine, TruthEngineConfig};
+use std::path::PathBuf;
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::Arc;
+
+use crate::media_panel::show_report;
+
+/// Where a queued file's bytes come from.
+enum Source {
+    Path(PathBuf),
+    Bytes(Arc<[u8]>),
+}
+
+impl Source {
+    fn read(&self) -> Result<Vec<u8>> {
+        match self {
+            Source::Path(path) => {
+                std::fs::read(path).with_context(|| format!("failed to read {}", path.display()))
+            }
+            Source::Bytes(bytes) => Ok(bytes.to_vec()),
+        }
+    }
+}
+
+enum FileState {
+    Queued,
+    Running,
+    Done(Box<(IngestResult, DetectionReport)>),
+    Failed(String),
+}
+
+struct FileEntry {
+    name: String,
+    state: FileState,
+}
+
+enum Message {
+    State(usize, FileState),
+    /// A batch finished; the store has new facts.
+    BatchDone,
+}
+
+/// The Ingest tab: files dropped on the window or picked from disk are run
+/// through the default detectors on a worker thread, one batch per drop.
+pub struct IngestPanel {
+    files: Vec<FileEntry>,
+    running: usize,
+    tx: Sender<Message>,
+    rx: Receiver<Message>,
+}
+
+impl Default for IngestPanel {
+    fn default() -> Self {
+        let (tx, rx) = channel();
+        Self {
+            files: Vec::new(),
+            running: 0,
+            tx,
+            rx,
+        }
+    }
+}
+
+/// Ingest each file and evaluate it; a failure only marks its own file.
+fn run_batch(
+    handle: PruDbHandle,
+    batch: Vec<(usize, Source)>,
+    tx: Sender<Message>,
+    repaint: egui::Context,
+) {
+    let send = |message| {
+        let _ = tx.send(message);
+        repaint.request_repaint();
+    };
+    let detectors = match DetectorRegistry::from_config(&RegistryConfig::default()) {
+        Ok(detectors) => detectors,
+        Err(e) => {
+            for (index, _) in batch {
+                send(Message::State(
+                    index,
+                    FileState::Failed(format!("failed to load detectors: {e:#}")),
+                ));
+            }
+            send(Message::BatchDone);
+            return;
+        }
+    };
+    let ctx = IngestContext::new(handle.clone(), detectors);
+    let engine = TruthEngine::new(TruthEngineConfig::default());
+    for (index, source) in batch {
+        send(Message::State(index, FileState::Running));
+        let result = source.read().and_then(|bytes| {
+            let result = ctx.ingest_auto(&bytes)?;
+            let report = engine.evaluate_media(&handle, result.media_id)?;
+            Ok((result, report))
+        });
+        send(Message::State(
+            index,
+            match result {
+                Ok(done) => FileState::Done(Box::new(done)),
+                Err(e) => FileState::Failed(format!("{e:#}")),
+            },
+        ));
+    }
+    send(Message::BatchDone);
+}
+
+fn show_outcomes(ui: &mut egui::Ui, result: &IngestResult) {
+    ui.label(format!(
+        "media #{} {:?} {}{}",
+        result.media_id.0,
+        result.media_type,
+        result.hash,
+        if result.was_new { " (new)" } else { "" }
+    ));
+    for outcome in &result.outcomes {
+        let (status, color) = match &outcome.status {
+            DetectorStatus::Succeeded => ("ok".to_string(), Color32::from_rgb(60, 150, 80)),
+            DetectorStatus::Cached => ("cached".to_string(), Color32::GRAY),
+            DetectorStatus::TimedOut => ("timed out".to_string(), Color32::from_rgb(200, 60, 60)),
+            DetectorStatus::Failed(e) => (format!("failed: {e}"), Color32::from_rgb(200, 60, 60)),
+        };
+        ui.horizontal(|ui| {
+            ui.label(&outcome.detector);
+            ui.colored_label(color, status);
+            if let Some(output) = &outcome.output {
+                ui.label(format!(
+                    "score={:.2} label={}",
+                    output.score_ai,
+                    output.label.as_str()
+                ));
+            }
+            ui.small(format!("{} ms", outcome.duration_ms));
+        });
+    }
+}
+
+impl IngestPanel {
+    /// Whether a batch is still running.
+    pub fn is_busy(&self) -> bool {
+        self.running > 0
+    }
+
+    /// Queue `sources` and start a worker for them.
+    fn start(&mut self, ctx: &egui::Context, handle: &PruDbHandle, sources: Vec<(String, Source)>) {
+        if sources.is_empty() {
+            return;
+        }
+        let batch = sources
+            .into_iter()
+            .map(|(name, source)| {
+                self.files.push(FileEntry {
+                    name,
+                    state: FileState::Queued,
+                });
+                (self.files.len() - 1, source)
+            })
+            .collect();
+        self.running += 1;
+        let (handle, tx, repaint) = (handle.clone(), self.tx.clone(), ctx.clone());
+        std::thread::spawn(move || run_batch(handle, batch, tx, repaint));
+    }
+
+    /// Queue files dropped on the window this frame. Returns whether there
+    /// were any.
+    pub fn take_dropped(&mut self, ctx: &egui::Context, handle: &PruDbHandle) -> bool {
+        let dropped = ctx.input(|i| i.raw.dropped_files.clone());
+        let sources: Vec<(String, Source)> = dropped
+            .into_iter()
+            .filter_map(|file| match (file.path, file.bytes) {
+                (Some(path), _) => Some((path.display().to_string(), Source::Path(path))),
+                (None, Some(bytes)) => Some((file.name, Source::Bytes(bytes))),
+                (None, None) => None,
+            })
+            .collect();
+        let any = !sources.is_empty();
+        self.start(ctx, handle, sources);
+        any
+    }
+
+    /// Apply worker messages. Returns whether a batch finished since the last
+    /// call, so the caller can refresh what it shows of the store.
+    pub fn poll(&mut self) -> bool {
+        let mut finished = false;
+        while let Ok(message) = self.rx.try_recv() {
+            match message {
+                Message::State(index, state) => {
+                    if let Some(file) = self.files.get_mut(index) {
+                        file.state = state;
+                    }
+                }
+                Message::BatchDone => {
+                    self.running = self.running.saturating_sub(1);
+                    finished = true;
+                }
+            }
+        }
+        finished
+    }
+
+    pub fn show(&mut self, ui: &mut egui::Ui, handle: &PruDbHandle) {
+        ui.heading("Ingest");
+        ui.separator();
+        let mut sources = Vec::new();
+        ui.horizontal(|ui| {
+            ui.label("Drop files on the window, or");
+            if ui.button("Choose files…").clicked() {
+                if let Some(paths) = rfd::FileDialog::new().pick_files() {
+                    sources.extend(
+                        paths
+                            .into_iter()
+                            .map(|p| (p.display().to_string(), Source::Path(p))),
+                    );
+                }
+            }
+            let idle = !self.is_busy();
+            if ui
+                .add_enabled(idle && !self.files.is_empty(), egui::Button::new("Clear"))
+                .clicked()
+            {
+                self.files.clear();
+            }
+            if !idle {
+                ui.spinner();
+            }
+        });
+        self.start(ui.ctx(), handle, sources);
+
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            for (index, file) in self.files.iter().enumerate() {
+                ui.separator();
+                ui.label(RichText::new(&file.name).strong());
+                match &file.state {
+                    FileState::Queued => {
+                        ui.label("queued");
+                    }
+                    FileState::Running => {
+                        ui.horizontal(|ui| {
+                            ui.spinner();
+                            ui.label("running detectors…");
+                        });
+                    }
+                    FileState::Failed(e) => {
+                        ui.colored_label(Color32::from_rgb(200, 60, 60), e);
+                    }
+                    FileState::Done(done) => {
+                        let (result, report) = done.as_ref();
+                        show_outcomes(ui, result);
+                        ui.push_id(index, |ui| show_report(ui, report));
+                    }
+                }
+            }
+        });
+    }
+}
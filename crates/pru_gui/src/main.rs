@@ -1,4 +1,15 @@
 mod app;
+mod atom_list;
+mod auto_refresh;
+#[cfg(debug_assertions)]
+mod debug;
+mod detectors_panel;
+mod fact_filter;
+mod fact_form;
+mod ingest_panel;
+mod media_panel;
+mod storage_panel;
+mod worker;
 
 use app::PruGuiApp;
 
@@ -0,0 +1,273 @@
+use anyhow::{anyhow, Result};
+use eframe::egui::{self, Color32, RichText};
+use pru_core::PruDbHandle;
+use pru_media_schema::{
+    add_human_verdict_by, bump_reliability_from_verdict, ensure_schema, find_media_entity,
+    get_media_type, get_verdict_summary, MediaId, MediaType, VerdictSummary, ANONYMOUS_ANNOTATOR,
+};
+use pru_truth_engine::{DetectionReport, TruthEngine, TruthEngineConfig, Verdict};
+
+const MEDIA_TYPES: [MediaType; 4] = [
+    MediaType::Image,
+    MediaType::Text,
+    MediaType::Audio,
+    MediaType::Video,
+];
+
+/// A media entity by id, entity name or content hash.
+pub fn resolve_media(handle: &PruDbHandle, input: &str) -> Result<MediaId> {
+    let input = input.trim();
+    let found = if let Ok(id) = input.parse::<u64>() {
+        Some(MediaId(id))
+    } else if input.starts_with("media:") {
+        handle.lock().unwrap().get_entity_id(input).map(MediaId)
+    } else {
+        let mut found = None;
+        for media_type in MEDIA_TYPES {
+            found = find_media_entity(handle, input, media_type)?;
+            if found.is_some() {
+                break;
+            }
+        }
+        found
+    };
+    match found {
+        Some(id) if get_media_type(handle, id)?.is_some() => Ok(id),
+        _ => Err(anyhow!("no media with id or hash {input}")),
+    }
+}
+
+fn verdict_text(verdict: Verdict) -> RichText {
+    match verdict {
+        Verdict::LikelyAi => RichText::new("likely AI").color(Color32::from_rgb(200, 90, 60)),
+        Verdict::LikelyHuman => RichText::new("likely human").color(Color32::from_rgb(60, 150, 80)),
+        Verdict::Inconclusive => RichText::new("inconclusive"),
+    }
+}
+
+/// Verdict, probabilities, notes and one row per detector score.
+pub fn show_report(ui: &mut egui::Ui, report: &DetectionReport) {
+    ui.horizontal(|ui| {
+        ui.label("Verdict:");
+        ui.label(verdict_text(report.verdict).strong());
+    });
+    ui.label(format!(
+        "p(ai)={:.2} [{:.2}, {:.2}]  p(human)={:.2}  evidence={}  weight={:.2}",
+        report.probability_ai,
+        report.confidence_interval.low,
+        report.confidence_interval.high,
+        report.probability_human,
+        report.evidence_count,
+        report.total_weight
+    ));
+    for note in &report.notes {
+        ui.label(RichText::new(note).italics());
+    }
+    for prior in &report.prior_adjustments {
+        ui.label(prior.rendered_text());
+    }
+    if report.explanations.is_empty() {
+        return;
+    }
+    egui::Grid::new("detector_table")
+        .striped(true)
+        .num_columns(7)
+        .show(ui, |ui| {
+            for heading in [
+                "Detector",
+                "Score",
+                "Label",
+                "Weight",
+                "Reliability",
+                "Precision",
+                "Contribution",
+            ] {
+                ui.label(RichText::new(heading).strong());
+            }
+            ui.end_row();
+            for e in &report.explanations {
+                let name = match &e.detector_version {
+                    Some(version) => format!("{} ({version})", e.detector_name),
+                    None => e.detector_name.clone(),
+                };
+                ui.label(name).on_hover_text(e.rendered_text());
+                match e.raw_score {
+                    Some(raw) => ui.label(format!("{:.2} (raw {raw:.2})", e.score)),
+                    None => ui.label(format!("{:.2}", e.score)),
+                };
+                ui.label(&e.label);
+                ui.label(format!("{:.2}", e.weight));
+                match &e.reliability_summary {
+                    Some(r) => ui.label(format!("{:.1}/{:.1} correct", r.correct, r.seen)),
+                    None => ui.label("-"),
+                };
+                match e.label_precision {
+                    Some(p) => ui.label(format!("{p:.2}")),
+                    None => ui.label("-"),
+                };
+                ui.label(format!("{:.2}", e.contribution));
+                ui.end_row();
+            }
+        });
+}
+
+fn show_verdicts(ui: &mut egui::Ui, verdicts: &VerdictSummary) {
+    ui.label(RichText::new("Human verdicts").strong());
+    if verdicts.total() == 0 {
+        ui.label("None yet.");
+        return;
+    }
+    for (label, count) in &verdicts.counts {
+        let who = verdicts
+            .annotators
+            .get(label)
+            .map(|names| format!(" ({})", names.join(", ")))
+            .unwrap_or_default();
+        ui.label(format!("{label}: {count}{who}"));
+    }
+    let majority = verdicts.majority_label.as_deref().unwrap_or("none");
+    ui.label(format!(
+        "majority: {majority}, agreement {:.0}%",
+        verdicts.agreement * 100.0
+    ));
+}
+
+/// The Media tab: pick a media item, see the engine's report on it and label it.
+pub struct MediaPanel {
+    pub input: String,
+    pub annotator: String,
+    media: Option<MediaId>,
+    /// Entity name of `media`, read along with the report.
+    name: String,
+    report: Option<DetectionReport>,
+    verdicts: VerdictSummary,
+    error: Option<String>,
+}
+
+impl Default for MediaPanel {
+    fn default() -> Self {
+        Self {
+            input: String::new(),
+            annotator: "gui".into(),
+            media: None,
+            name: String::new(),
+            report: None,
+            verdicts: VerdictSummary::default(),
+            error: None,
+        }
+    }
+}
+
+impl MediaPanel {
+    /// Forget the shown report, e.g. when another store is opened.
+    pub fn clear(&mut self) {
+        *self = Self {
+            annotator: std::mem::take(&mut self.annotator),
+            ..Self::default()
+        };
+    }
+
+    fn evaluate(&mut self, handle: &PruDbHandle) {
+        let result = resolve_media(handle, &self.input).and_then(|media| {
+            let engine = TruthEngine::new(TruthEngineConfig::default());
+            let report = engine.evaluate_media(handle, media)?;
+            let name = handle.lock().unwrap().get_entity_name(media.0);
+            Ok((media, name, report, get_verdict_summary(handle, media)?))
+        });
+        match result {
+            Ok((media, name, report, verdicts)) => {
+                self.media = Some(media);
+                self.name = name.unwrap_or_default();
+                self.report = Some(report);
+                self.verdicts = verdicts;
+                self.error = None;
+            }
+            Err(e) => {
+                self.media = None;
+                self.report = None;
+                self.error = Some(format!("{e:#}"));
+            }
+        }
+    }
+
+    /// Record a verdict, grade the detectors against it and re-run the report.
+    fn label(&mut self, handle: &PruDbHandle, media: MediaId, label: &str) {
+        let annotator = match self.annotator.trim() {
+            "" => ANONYMOUS_ANNOTATOR,
+            name => name,
+        };
+        let result = ensure_schema(handle)
+            .and_then(|_| add_human_verdict_by(handle, media, label, annotator, 1.0))
+            .and_then(|_| bump_reliability_from_verdict(handle, media, label));
+        match result {
+            Ok(()) => {
+                self.input = media.0.to_string();
+                self.evaluate(handle);
+            }
+            Err(e) => self.error = Some(format!("Failed to record verdict: {e:#}")),
+        }
+    }
+
+    /// Draw the tab. `media` lists the store's media entities to pick from.
+    pub fn show(&mut self, ui: &mut egui::Ui, handle: &PruDbHandle, media: &[(u64, String)]) {
+        ui.heading("Media report");
+        ui.separator();
+        // Evaluating and labelling need the store, which a fact scan may hold.
+        let free = handle.try_lock().is_ok();
+        ui.horizontal(|ui| {
+            ui.label("Media id, entity or hash");
+            let submitted = ui.text_edit_singleline(&mut self.input).lost_focus()
+                && ui.input(|i| i.key_pressed(egui::Key::Enter));
+            let mut picked = None;
+            egui::ComboBox::from_id_source("media_pick")
+                .selected_text("Pick…")
+                .show_ui(ui, |ui| {
+                    for (id, name) in media {
+                        if ui.selectable_label(false, name).clicked() {
+                            picked = Some(*id);
+                        }
+                    }
+                });
+            if let Some(id) = picked {
+                self.input = id.to_string();
+            }
+            let evaluate = ui
+                .add_enabled(free, egui::Button::new("Evaluate"))
+                .clicked();
+            if free && (evaluate || submitted || picked.is_some()) {
+                self.evaluate(handle);
+            }
+            if !free {
+                ui.spinner();
+                ui.label("Store busy…");
+            }
+        });
+        if let Some(err) = &self.error {
+            ui.colored_label(Color32::from_rgb(200, 60, 60), err);
+        }
+        let (Some(id), Some(report)) = (self.media, &self.report) else {
+            return;
+        };
+        ui.separator();
+        ui.label(format!("#{} {}", id.0, self.name));
+        show_report(ui, report);
+        ui.separator();
+        show_verdicts(ui, &self.verdicts);
+        ui.horizontal(|ui| {
+            ui.label("Annotator");
+            ui.text_edit_singleline(&mut self.annotator);
+            if ui
+                .add_enabled(free, egui::Button::new("Label as AI"))
+                .clicked()
+            {
+                self.label(handle, id, "ai");
+            }
+            if ui
+                .add_enabled(free, egui::Button::new("Label as Human"))
+                .clicked()
+            {
+                self.label(handle, id, "human");
+            }
+        });
+    }
+}
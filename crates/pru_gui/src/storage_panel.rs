@@ -0,0 +1,252 @@
+use eframe::egui::{self, Color32, RichText};
+use pru_core::{IndexKind, PruDbHandle, SegmentCheck, SegmentKind, SegmentLayout, SegmentReader};
+use std::path::{Path, PathBuf};
+
+const ERR_COLOR: Color32 = Color32::from_rgb(200, 60, 60);
+const OK_COLOR: Color32 = Color32::from_rgb(60, 150, 80);
+
+fn index_kind_name(code: u32) -> String {
+    [IndexKind::V1, IndexKind::V2]
+        .into_iter()
+        .find(|kind| kind.code() == code)
+        .map_or(code.to_string(), |kind| kind.to_string())
+}
+
+/// One manifest segment as read from its header and index.
+struct SegmentRow {
+    path: PathBuf,
+    kind: SegmentKind,
+    status: &'static str,
+    layout: Result<SegmentLayout, String>,
+    check: Option<Result<SegmentCheck, String>>,
+}
+
+impl SegmentRow {
+    fn read(dir: &Path, path: PathBuf, kind: SegmentKind, status: &'static str) -> Self {
+        let layout = SegmentReader::open(dir.join(&path))
+            .map_err(|e| e.to_string())
+            .and_then(|reader| {
+                // The index walk behind `layout` trusts the block offsets.
+                if reader.blocks_in_bounds() {
+                    Ok(reader.layout())
+                } else {
+                    Err("index or filter runs past the end of the file".to_string())
+                }
+            });
+        Self {
+            path,
+            kind,
+            status,
+            layout,
+            check: None,
+        }
+    }
+
+    fn verify(&mut self, dir: &Path) {
+        let check = SegmentReader::open(dir.join(&self.path)).and_then(|reader| reader.verify());
+        self.check = Some(check.map_err(|e| e.to_string()));
+    }
+}
+
+/// The Storage tab: the manifest's segments with their index statistics,
+/// fast verification of one segment and resolver compaction.
+#[derive(Default)]
+pub struct StoragePanel {
+    rows: Vec<SegmentRow>,
+    dir: PathBuf,
+    selected: Option<usize>,
+    loaded: bool,
+    confirm_compact: bool,
+    status: Option<String>,
+    error: Option<String>,
+}
+
+impl StoragePanel {
+    /// Forget the listed segments, e.g. when another store is opened.
+    pub fn clear(&mut self) {
+        *self = Self::default();
+    }
+
+    fn refresh(&mut self, handle: &PruDbHandle) {
+        self.loaded = true;
+        self.selected = None;
+        let store = handle.lock().expect("store poisoned");
+        let manifest = store.manifest().clone();
+        self.dir = store.dir().to_path_buf();
+        drop(store);
+        let active = manifest.active_segment_paths();
+        self.rows = manifest
+            .segments
+            .iter()
+            .map(|s| {
+                let status = if manifest
+                    .archived_paths
+                    .iter()
+                    .any(|p| Path::new(p) == s.path)
+                {
+                    "archived"
+                } else if active.contains(&s.path) {
+                    "active"
+                } else {
+                    "inactive"
+                };
+                SegmentRow::read(&self.dir, s.path.clone(), s.kind, status)
+            })
+            .collect();
+    }
+
+    fn compact(&mut self, handle: &PruDbHandle) {
+        let result = handle
+            .lock()
+            .expect("store poisoned")
+            .compact_segments(SegmentKind::Resolver);
+        match result {
+            Ok(report) => {
+                self.status = Some(format!(
+                    "Merged {} segment(s), {} bytes, into {} ({} entries, {} bytes)",
+                    report.inputs,
+                    report.input_bytes,
+                    report.output,
+                    report.entries,
+                    report.output_bytes
+                ));
+                self.error = None;
+            }
+            Err(e) => self.error = Some(format!("Compaction failed: {e}")),
+        }
+        self.refresh(handle);
+    }
+
+    fn show_compact_dialog(&mut self, ctx: &egui::Context, handle: &PruDbHandle) {
+        let inputs = self
+            .rows
+            .iter()
+            .filter(|row| row.kind == SegmentKind::Resolver && row.status == "active")
+            .count();
+        let (mut confirmed, mut cancelled) = (false, false);
+        egui::Window::new("Compact resolver segments")
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+            .show(ctx, |ui| {
+                ui.label(format!(
+                    "Merge the {inputs} active resolver segment(s) into one new segment \
+                     and make it the only active one? The inputs stay on disk until \
+                     garbage collection."
+                ));
+                ui.horizontal(|ui| {
+                    confirmed = ui.button("Compact").clicked();
+                    cancelled = ui.button("Cancel").clicked();
+                });
+            });
+        if confirmed {
+            self.compact(handle);
+        }
+        if confirmed || cancelled {
+            self.confirm_compact = false;
+        }
+    }
+
+    fn show_row(&mut self, ui: &mut egui::Ui, index: usize) {
+        let row = &self.rows[index];
+        let name = row.path.display().to_string();
+        if ui
+            .selectable_label(self.selected == Some(index), name)
+            .clicked()
+        {
+            self.selected = Some(index);
+        }
+        ui.label(row.kind.to_string());
+        ui.label(row.status);
+        match &row.layout {
+            Ok(layout) => {
+                ui.label(layout.file_len.to_string());
+                ui.label(layout.index_kind.map_or("—".to_string(), index_kind_name));
+                ui.label(
+                    layout
+                        .index_capacity
+                        .map_or("—".to_string(), |c| c.to_string()),
+                );
+                ui.label(match layout.index_capacity {
+                    Some(cap) if cap > 0 => format!("{:.2}", layout.entries as f64 / cap as f64),
+                    _ => "—".to_string(),
+                });
+                ui.label(layout.entries.to_string());
+            }
+            Err(e) => {
+                ui.colored_label(ERR_COLOR, e);
+                for _ in 0..4 {
+                    ui.label("");
+                }
+            }
+        }
+        match &row.check {
+            None => ui.label(""),
+            Some(Ok(check)) if check.is_ok() => ui.colored_label(OK_COLOR, "ok"),
+            Some(Ok(check)) => ui.colored_label(
+                ERR_COLOR,
+                format!(
+                    "bad bounds={} bad crc={} filter misses={}",
+                    check.bad_bounds, check.bad_crc, check.filter_miss
+                ),
+            ),
+            Some(Err(e)) => ui.colored_label(ERR_COLOR, e),
+        };
+        ui.end_row();
+    }
+
+    pub fn show(&mut self, ui: &mut egui::Ui, handle: &PruDbHandle) {
+        if !self.loaded {
+            self.refresh(handle);
+        }
+        ui.heading("Storage");
+        ui.separator();
+        ui.horizontal(|ui| {
+            if ui.button("Refresh").clicked() {
+                self.refresh(handle);
+            }
+            if ui
+                .add_enabled(
+                    self.selected.is_some(),
+                    egui::Button::new("Verify selected"),
+                )
+                .clicked()
+            {
+                if let Some(row) = self.selected.and_then(|i| self.rows.get_mut(i)) {
+                    row.verify(&self.dir);
+                }
+            }
+            if ui.button("Compact resolver segments…").clicked() {
+                self.confirm_compact = true;
+            }
+            if let Some(status) = &self.status {
+                ui.label(status);
+            }
+        });
+        if let Some(err) = &self.error {
+            ui.colored_label(ERR_COLOR, err);
+        }
+        if self.confirm_compact {
+            self.show_compact_dialog(ui.ctx(), handle);
+        }
+        ui.separator();
+        if self.rows.is_empty() {
+            ui.label(RichText::new("The manifest lists no segments.").weak());
+            return;
+        }
+        egui::ScrollArea::both().show(ui, |ui| {
+            egui::Grid::new("segments").striped(true).show(ui, |ui| {
+                for title in [
+                    "Segment", "Kind", "Status", "Bytes", "Index", "Capacity", "Load", "Entries",
+                    "Verify",
+                ] {
+                    ui.label(RichText::new(title).strong());
+                }
+                ui.end_row();
+                for index in 0..self.rows.len() {
+                    self.show_row(ui, index);
+                }
+            });
+        });
+    }
+}
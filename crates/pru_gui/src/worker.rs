@@ -0,0 +1,216 @@
+//! Store opening and fact scans, run off the UI thread so a large store
+//! doesn't freeze the window. Each job carries a generation; replies from a
+//! job that was cancelled or replaced since are dropped.
+
+use eframe::egui;
+use pru_core::errors::Result;
+use pru_core::{Fact, PruDbHandle, PruStore, Query};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::auto_refresh::StoreStamp;
+
+/// How long a simulated slow store stalls each job.
+const SLOW_DELAY: Duration = Duration::from_secs(3);
+/// Facts a scan collects per turn of the store lock; the lock is let go and
+/// the cancel flag looked at between turns.
+const SCAN_BATCH: usize = 4096;
+
+/// A store opened by the worker, with its atom lists already read.
+pub struct Opened {
+    pub dir: PathBuf,
+    /// The store's files as they were just before they were read.
+    pub stamp: StoreStamp,
+    pub handle: PruDbHandle,
+    pub entities: Vec<(u64, String)>,
+    pub predicates: Vec<(u64, String)>,
+    pub literals: Vec<(u64, String)>,
+}
+
+pub enum Reply {
+    Opened(Result<Opened>),
+    /// The first facts of a scan, and how many matched in all.
+    Facts {
+        facts: Vec<Fact>,
+        matched: usize,
+    },
+}
+
+struct Message {
+    generation: u64,
+    reply: Reply,
+}
+
+/// A job the UI is waiting on.
+pub struct Pending {
+    generation: u64,
+    started: Instant,
+    cancel: Arc<AtomicBool>,
+}
+
+impl Pending {
+    pub fn elapsed(&self) -> Duration {
+        self.started.elapsed()
+    }
+
+    fn cancel(self) {
+        self.cancel.store(true, Ordering::Relaxed);
+    }
+}
+
+/// Spawns one thread per job and collects their replies, at most one store
+/// open and one fact scan awaited at a time.
+pub struct Worker {
+    /// Stall every job for a few seconds, to try the loading states without
+    /// a large store.
+    pub simulate_slow: bool,
+    pub opening: Option<Pending>,
+    pub querying: Option<Pending>,
+    generation: u64,
+    tx: Sender<Message>,
+    rx: Receiver<Message>,
+}
+
+impl Default for Worker {
+    fn default() -> Self {
+        let (tx, rx) = channel();
+        Self {
+            simulate_slow: false,
+            opening: None,
+            querying: None,
+            generation: 0,
+            tx,
+            rx,
+        }
+    }
+}
+
+fn stall(cancel: &AtomicBool) {
+    let until = Instant::now() + SLOW_DELAY;
+    while Instant::now() < until && !cancel.load(Ordering::Relaxed) {
+        std::thread::sleep(Duration::from_millis(50));
+    }
+}
+
+fn open_store(dir: PathBuf) -> Result<Opened> {
+    let stamp = StoreStamp::read(&dir);
+    let store = PruStore::open(&dir)?;
+    Ok(Opened {
+        dir,
+        stamp,
+        entities: store.entities(),
+        predicates: store.predicates(),
+        literals: store.literals(),
+        handle: Arc::new(Mutex::new(store)),
+    })
+}
+
+/// The first `limit` facts matching `query` and the number matching in all,
+/// or `None` once cancelled. The store is locked one batch at a time, so the
+/// UI can use it while a large scan runs.
+fn scan_facts(
+    handle: &PruDbHandle,
+    query: Query,
+    limit: usize,
+    cancel: &AtomicBool,
+) -> Option<Reply> {
+    let (mut facts, mut matched) = (Vec::new(), 0);
+    loop {
+        if cancel.load(Ordering::Relaxed) {
+            return None;
+        }
+        let batch = Query {
+            offset: query.offset + matched,
+            limit: Some(SCAN_BATCH),
+            ..query.clone()
+        };
+        let store = handle.lock().expect("store poisoned");
+        let before = matched;
+        for fact in store.query_iter(batch) {
+            if matched < limit {
+                facts.push(fact.clone());
+            }
+            matched += 1;
+        }
+        drop(store);
+        if matched - before < SCAN_BATCH {
+            return Some(Reply::Facts { facts, matched });
+        }
+    }
+}
+
+impl Worker {
+    fn spawn(
+        &mut self,
+        ctx: &egui::Context,
+        job: impl FnOnce(&AtomicBool) -> Option<Reply> + Send + 'static,
+    ) -> Pending {
+        self.generation += 1;
+        let cancel = Arc::new(AtomicBool::new(false));
+        let (generation, flag, slow) = (self.generation, cancel.clone(), self.simulate_slow);
+        let (tx, repaint) = (self.tx.clone(), ctx.clone());
+        std::thread::spawn(move || {
+            if slow {
+                stall(&flag);
+            }
+            if flag.load(Ordering::Relaxed) {
+                return;
+            }
+            if let Some(reply) = job(&flag) {
+                let _ = tx.send(Message { generation, reply });
+                repaint.request_repaint();
+            }
+        });
+        Pending {
+            generation,
+            started: Instant::now(),
+            cancel,
+        }
+    }
+
+    /// Open the store at `dir`, replacing any open still running.
+    pub fn open(&mut self, ctx: &egui::Context, dir: PathBuf) {
+        self.cancel_open();
+        let pending = self.spawn(ctx, move |_| Some(Reply::Opened(open_store(dir))));
+        self.opening = Some(pending);
+    }
+
+    /// Collect up to `limit` facts matching `query` and count the rest,
+    /// replacing any scan still running.
+    pub fn facts(&mut self, ctx: &egui::Context, handle: PruDbHandle, query: Query, limit: usize) {
+        self.cancel_query();
+        let pending = self.spawn(ctx, move |cancel| scan_facts(&handle, query, limit, cancel));
+        self.querying = Some(pending);
+    }
+
+    pub fn cancel_open(&mut self) {
+        if let Some(pending) = self.opening.take() {
+            pending.cancel();
+        }
+    }
+
+    pub fn cancel_query(&mut self) {
+        if let Some(pending) = self.querying.take() {
+            pending.cancel();
+        }
+    }
+
+    /// Replies to the jobs still awaited, in arrival order.
+    pub fn poll(&mut self) -> Vec<Reply> {
+        let mut replies = Vec::new();
+        while let Ok(Message { generation, reply }) = self.rx.try_recv() {
+            let slot = match reply {
+                Reply::Opened(_) => &mut self.opening,
+                Reply::Facts { .. } => &mut self.querying,
+            };
+            if slot.as_ref().is_some_and(|p| p.generation == generation) {
+                *slot = None;
+                replies.push(reply);
+            }
+        }
+        replies
+    }
+}
@@ -0,0 +1,569 @@
+//! Ingesting whole directories: walking, glob filtering and a bounded worker pool.
+
+use anyhow::{anyhow, Context, Result};
+use pru_media_schema::{MediaId, MediaType};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc::Sender;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::{detect_media_type, panic_message, DetectorOutcome, IngestContext, IngestOptions};
+
+#[derive(Clone, Debug, Default)]
+pub struct BatchOptions {
+    /// Descend into subdirectories.
+    pub recursive: bool,
+    /// Only files matching one of these globs; every file when empty. Patterns
+    /// without a `/` match the file name, others the path relative to the root.
+    /// `*` and `?` stay within one path segment; a `**` segment matches any
+    /// number of whole segments.
+    pub include: Vec<String>,
+    /// Files matching any of these globs are skipped.
+    pub exclude: Vec<String>,
+    /// Files ingested at once; 0 and 1 both mean one at a time.
+    pub jobs: usize,
+    /// Submission context recorded for every file.
+    pub ingest: IngestOptions,
+    /// Receives progress as the batch runs; a dropped receiver is ignored.
+    pub events: Option<Sender<IngestEvent>>,
+    /// Checked before each file is started; files already being analysed finish.
+    pub cancel: CancellationToken,
+}
+
+/// Progress of a batch, in order: one `Started`, a `FileDone` per file as it
+/// finishes (not in path order when `jobs > 1`), then `Finished`.
+#[derive(Clone, Debug)]
+pub enum IngestEvent {
+    Started {
+        total: usize,
+    },
+    FileDone {
+        path: PathBuf,
+        result: std::result::Result<IngestedFile, String>,
+    },
+    Finished {
+        report: BatchIngestReport,
+    },
+}
+
+/// Shared stop flag for a batch; clones cancel the same batch.
+#[derive(Clone, Debug, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct IngestedFile {
+    pub media_id: MediaId,
+    pub media_type: MediaType,
+    pub outcomes: Vec<DetectorOutcome>,
+}
+
+#[derive(Clone, Debug)]
+pub struct FileOutcome {
+    pub path: PathBuf,
+    /// The error message when the file could not be read, typed or ingested.
+    pub result: std::result::Result<IngestedFile, String>,
+    pub elapsed: Duration,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct BatchIngestReport {
+    /// One entry per file, in path order.
+    pub files: Vec<FileOutcome>,
+    /// Files never started because the batch was cancelled.
+    pub skipped: usize,
+    pub elapsed: Duration,
+}
+
+impl BatchIngestReport {
+    pub fn ingested(&self) -> usize {
+        self.files.iter().filter(|f| f.result.is_ok()).count()
+    }
+
+    pub fn failed(&self) -> usize {
+        self.files.len() - self.ingested()
+    }
+
+    /// Whether cancellation stopped the batch before every file was started.
+    pub fn cancelled(&self) -> bool {
+        self.skipped > 0
+    }
+
+    /// Ingested files of `media_type`.
+    pub fn count_of(&self, media_type: MediaType) -> usize {
+        self.files
+            .iter()
+            .filter(|f| matches!(&f.result, Ok(file) if file.media_type == media_type))
+            .count()
+    }
+}
+
+impl IngestContext {
+    /// Ingest one file, or every file under a directory as [`ingest_dir`](Self::ingest_dir) does.
+    pub fn ingest_path(&self, path: &Path, options: &BatchOptions) -> Result<BatchIngestReport> {
+        if path.is_dir() {
+            return self.ingest_dir(path, options);
+        }
+        self.ingest_paths(vec![path.to_path_buf()], options)
+    }
+
+    /// Ingest the files under `dir` that pass the option's filters, sniffing each
+    /// file's type. A file that fails is reported and does not stop the batch; an
+    /// unreadable `dir` or a panicking worker is an error.
+    pub fn ingest_dir(&self, dir: &Path, options: &BatchOptions) -> Result<BatchIngestReport> {
+        let mut paths = Vec::new();
+        collect_files(dir, dir, options, &mut paths)?;
+        paths.sort();
+        self.ingest_paths(paths, options)
+    }
+
+    fn ingest_paths(
+        &self,
+        paths: Vec<PathBuf>,
+        options: &BatchOptions,
+    ) -> Result<BatchIngestReport> {
+        let started = Instant::now();
+        let send = |event| {
+            if let Some(events) = &options.events {
+                let _ = events.send(event);
+            }
+        };
+        send(IngestEvent::Started { total: paths.len() });
+
+        let next = AtomicUsize::new(0);
+        let work = || {
+            let mut done = Vec::new();
+            loop {
+                if options.cancel.is_cancelled() {
+                    return done;
+                }
+                let i = next.fetch_add(1, Ordering::Relaxed);
+                let Some(path) = paths.get(i) else {
+                    return done;
+                };
+                let file = self.ingest_file(path, &options.ingest);
+                send(IngestEvent::FileDone {
+                    path: file.path.clone(),
+                    result: file.result.clone(),
+                });
+                done.push((i, file));
+            }
+        };
+        let jobs = options.jobs.clamp(1, paths.len().max(1));
+        let mut files: Vec<(usize, FileOutcome)> = if jobs == 1 {
+            work()
+        } else {
+            std::thread::scope(|scope| {
+                let workers: Vec<_> = (0..jobs).map(|_| scope.spawn(work)).collect();
+                // Join every worker before reporting, or the scope panics as well.
+                let joined: Vec<_> = workers.into_iter().map(|w| w.join()).collect();
+                let mut files = Vec::new();
+                for done in joined {
+                    let done = done.map_err(|payload| {
+                        anyhow!("ingest worker panicked: {}", panic_message(&*payload))
+                    })?;
+                    files.extend(done);
+                }
+                Ok::<_, anyhow::Error>(files)
+            })?
+        };
+        files.sort_by_key(|(i, _)| *i);
+        let report = BatchIngestReport {
+            skipped: paths.len() - files.len(),
+            files: files.into_iter().map(|(_, file)| file).collect(),
+            elapsed: started.elapsed(),
+        };
+        send(IngestEvent::Finished {
+            report: report.clone(),
+        });
+        Ok(report)
+    }
+
+    fn ingest_file(&self, path: &Path, options: &IngestOptions) -> FileOutcome {
+        let started = Instant::now();
+        let result = (|| -> Result<IngestedFile> {
+            let bytes =
+                std::fs::read(path).with_context(|| format!("reading {}", path.display()))?;
+            let media_type = detect_media_type(&bytes)?;
+            let ingested = self.ingest_generic(&bytes, media_type, options)?;
+            Ok(IngestedFile {
+                media_id: ingested.media_id,
+                media_type,
+                outcomes: ingested.outcomes,
+            })
+        })();
+        FileOutcome {
+            path: path.to_path_buf(),
+            result: result.map_err(|e| format!("{e:#}")),
+            elapsed: started.elapsed(),
+        }
+    }
+}
+
+/// Non-hidden files under `dir` that pass the filters; anything that is not a
+/// directory counts as a file so broken links are reported rather than dropped.
+/// Links are never followed into directories, so a link cycle cannot recurse.
+fn collect_files(
+    root: &Path,
+    dir: &Path,
+    options: &BatchOptions,
+    out: &mut Vec<PathBuf>,
+) -> Result<()> {
+    let entries =
+        std::fs::read_dir(dir).with_context(|| format!("reading directory {}", dir.display()))?;
+    for entry in entries {
+        let entry = entry?;
+        let path = entry.path();
+        let Some(name) = path.file_name().and_then(|n| n.to_str()) else {
+            continue;
+        };
+        if name.starts_with('.') {
+            continue;
+        }
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            if options.recursive {
+                collect_files(root, &path, options, out)?;
+            }
+            continue;
+        }
+        if file_type.is_symlink() && path.is_dir() {
+            continue;
+        }
+        let relative = path
+            .strip_prefix(root)
+            .unwrap_or(&path)
+            .to_string_lossy()
+            .replace('\\', "/");
+        let matches = |pattern: &String| {
+            let target = if pattern.contains('/') {
+                &relative
+            } else {
+                name
+            };
+            glob_match(pattern, target)
+        };
+        let included = options.include.is_empty() || options.include.iter().any(matches);
+        if included && !options.exclude.iter().any(matches) {
+            out.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// Match `text` against `pattern` segment by segment. Both levels backtrack
+/// only to the last wildcard seen, so matching stays linear-ish in the input
+/// however many wildcards the pattern has.
+fn glob_match(pattern: &str, text: &str) -> bool {
+    let p: Vec<&str> = pattern.split('/').collect();
+    let t: Vec<&str> = text.split('/').collect();
+    let (mut pi, mut ti) = (0, 0);
+    let mut star: Option<(usize, usize)> = None;
+    while ti < t.len() {
+        if p.get(pi) == Some(&"**") {
+            star = Some((pi, ti));
+            pi += 1;
+        } else if p.get(pi).is_some_and(|seg| segment_match(seg, t[ti])) {
+            pi += 1;
+            ti += 1;
+        } else if let Some((sp, st)) = star {
+            // Let the last `**` swallow one more segment.
+            star = Some((sp, st + 1));
+            pi = sp + 1;
+            ti = st + 1;
+        } else {
+            return false;
+        }
+    }
+    p[pi..].iter().all(|seg| *seg == "**")
+}
+
+/// `*` and `?` within one path segment.
+fn segment_match(pattern: &str, text: &str) -> bool {
+    let p: Vec<char> = pattern.chars().collect();
+    let t: Vec<char> = text.chars().collect();
+    let (mut pi, mut ti) = (0, 0);
+    let mut star: Option<(usize, usize)> = None;
+    while ti < t.len() {
+        match p.get(pi) {
+            Some('*') => {
+                star = Some((pi, ti));
+                pi += 1;
+            }
+            Some(c) if *c == '?' || *c == t[ti] => {
+                pi += 1;
+                ti += 1;
+            }
+            _ => match star {
+                Some((sp, st)) => {
+                    star = Some((sp, st + 1));
+                    pi = sp + 1;
+                    ti = st + 1;
+                }
+                None => return false,
+            },
+        }
+    }
+    p[pi..].iter().all(|c| *c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::IngestHooks;
+    use pru_core::PruStore;
+    use pru_detectors_api::{DetectorRegistry, TextComplexityDetector};
+    use std::sync::{Arc, Mutex};
+    use tempfile::tempdir;
+
+    #[test]
+    fn globs_respect_path_segments() {
+        assert!(glob_match("*.png", "a.png"));
+        assert!(!glob_match("*.png", "dir/a.png"));
+        assert!(glob_match("**/*.png", "a.png"));
+        assert!(glob_match("**/*.png", "dir/sub/a.png"));
+        assert!(glob_match("dir/**", "dir/sub/a.png"));
+        assert!(glob_match("img_??.jpg", "img_01.jpg"));
+        assert!(!glob_match("img_??.jpg", "img_1.jpg"));
+        assert!(glob_match("**/sub/**/*.png", "a/sub/b/c/x.png"));
+        assert!(!glob_match("**/sub/**/*.png", "a/b/c/x.png"));
+    }
+
+    #[test]
+    fn many_wildcards_do_not_backtrack_exponentially() {
+        let text = "a".repeat(200);
+        let started = Instant::now();
+        assert!(!glob_match(&format!("{}b", "*a".repeat(30)), &text));
+        let deep = vec!["d"; 100].join("/");
+        assert!(!glob_match(&format!("{}x", "**/".repeat(20)), &deep));
+        assert!(started.elapsed() < Duration::from_secs(1));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn linked_directories_are_not_followed() {
+        let dir = tempdir().unwrap();
+        let root = dir.path().join("in");
+        std::fs::create_dir_all(root.join("sub")).unwrap();
+        std::fs::write(root.join("sub/a.txt"), "a").unwrap();
+        std::os::unix::fs::symlink(&root, root.join("sub/loop")).unwrap();
+        let mut paths = Vec::new();
+        let options = BatchOptions {
+            recursive: true,
+            ..Default::default()
+        };
+        collect_files(&root, &root, &options, &mut paths).unwrap();
+        assert_eq!(paths, vec![root.join("sub/a.txt")]);
+    }
+
+    #[test]
+    fn mixed_directory_is_ingested_with_failures_reported() {
+        let dir = tempdir().unwrap();
+        let root = dir.path().join("in");
+        std::fs::create_dir_all(root.join("nested/deeper")).unwrap();
+        let mut png = Vec::new();
+        image::DynamicImage::ImageRgb8(image::RgbImage::from_pixel(3, 3, image::Rgb([1, 2, 3])))
+            .write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png)
+            .unwrap();
+        std::fs::write(root.join("photo.png"), &png).unwrap();
+        std::fs::write(root.join("notes.txt"), "plain words in a file").unwrap();
+        std::fs::write(root.join("nested/essay.md"), "another text, nested").unwrap();
+        std::fs::write(
+            root.join("nested/deeper/blob.bin"),
+            [0xC3u8, 0x28, 0xFF, 0x00],
+        )
+        .unwrap();
+        std::fs::write(root.join("nested/skip.log"), "excluded by glob").unwrap();
+        std::fs::write(root.join(".hidden"), "never listed").unwrap();
+        #[cfg(unix)]
+        std::os::unix::fs::symlink(root.join("gone"), root.join("broken.txt")).unwrap();
+
+        let mut registry = DetectorRegistry::new();
+        registry
+            .register(Arc::new(TextComplexityDetector::default()))
+            .unwrap();
+        let ctx = IngestContext::new(
+            Arc::new(Mutex::new(PruStore::open(dir.path().join("db")).unwrap())),
+            registry,
+        );
+        let options = BatchOptions {
+            recursive: true,
+            exclude: vec!["*.log".into()],
+            jobs: 3,
+            ..Default::default()
+        };
+        let report = ctx.ingest_dir(&root, &options).unwrap();
+
+        let names: Vec<_> = report
+            .files
+            .iter()
+            .map(|f| {
+                f.path
+                    .strip_prefix(&root)
+                    .unwrap()
+                    .to_string_lossy()
+                    .into_owned()
+            })
+            .collect();
+        let mut expected = vec![
+            "nested/deeper/blob.bin",
+            "nested/essay.md",
+            "notes.txt",
+            "photo.png",
+        ];
+        if cfg!(unix) {
+            expected.insert(0, "broken.txt");
+        }
+        assert_eq!(names, expected);
+        assert_eq!(report.ingested(), 3);
+        assert_eq!(report.count_of(MediaType::Text), 2);
+        assert_eq!(report.count_of(MediaType::Image), 1);
+        let errors: Vec<_> = report
+            .files
+            .iter()
+            .filter_map(|f| f.result.as_ref().err())
+            .collect();
+        assert!(errors
+            .iter()
+            .any(|e| e.contains("cannot determine media type")));
+        if cfg!(unix) {
+            assert_eq!(report.failed(), 2);
+            assert!(errors.iter().any(|e| e.contains("reading")));
+        }
+
+        let flat = ctx
+            .ingest_dir(
+                &root,
+                &BatchOptions {
+                    include: vec!["*.txt".into(), "*.png".into()],
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        assert_eq!(flat.ingested(), 2);
+        let single = ctx
+            .ingest_path(&root.join("notes.txt"), &BatchOptions::default())
+            .unwrap();
+        assert_eq!(single.files.len(), 1);
+        let notes = report
+            .files
+            .iter()
+            .find(|f| f.path.ends_with("notes.txt"))
+            .unwrap();
+        assert_eq!(
+            single.files[0].result.as_ref().unwrap().media_id,
+            notes.result.as_ref().unwrap().media_id
+        );
+    }
+
+    struct CancelAfter(usize, AtomicUsize, CancellationToken);
+
+    impl crate::PostIngestHook for CancelAfter {
+        fn on_complete(&self, _: &crate::IngestResult, _: &pru_core::PruDbHandle) -> Result<()> {
+            if self.1.fetch_add(1, Ordering::SeqCst) + 1 == self.0 {
+                self.2.cancel();
+            }
+            Ok(())
+        }
+    }
+
+    struct PanicOnComplete;
+
+    impl crate::PostIngestHook for PanicOnComplete {
+        fn on_complete(&self, _: &crate::IngestResult, _: &pru_core::PruDbHandle) -> Result<()> {
+            panic!("hook blew up");
+        }
+    }
+
+    #[test]
+    fn a_panicking_worker_fails_the_batch() {
+        let dir = tempdir().unwrap();
+        let root = dir.path().join("in");
+        std::fs::create_dir_all(&root).unwrap();
+        for i in 0..2 {
+            std::fs::write(root.join(format!("{i}.txt")), format!("text number {i}")).unwrap();
+        }
+        let mut hooks = IngestHooks::new();
+        hooks.add_post(Arc::new(PanicOnComplete));
+        let ctx = IngestContext::new(
+            Arc::new(Mutex::new(PruStore::open(dir.path().join("db")).unwrap())),
+            DetectorRegistry::new(),
+        )
+        .with_hooks(hooks);
+        let options = BatchOptions {
+            jobs: 2,
+            ..Default::default()
+        };
+        let err = ctx.ingest_dir(&root, &options).unwrap_err();
+        assert!(err.to_string().contains("hook blew up"), "{err}");
+    }
+
+    #[test]
+    fn cancelled_batch_reports_partial_run_and_events() {
+        let dir = tempdir().unwrap();
+        let root = dir.path().join("in");
+        std::fs::create_dir_all(&root).unwrap();
+        for i in 0..5 {
+            std::fs::write(root.join(format!("{i}.txt")), format!("text number {i}")).unwrap();
+        }
+        let cancel = CancellationToken::new();
+        let mut hooks = IngestHooks::new();
+        hooks.add_post(Arc::new(CancelAfter(
+            2,
+            AtomicUsize::new(0),
+            cancel.clone(),
+        )));
+        let ctx = IngestContext::new(
+            Arc::new(Mutex::new(PruStore::open(dir.path().join("db")).unwrap())),
+            DetectorRegistry::new(),
+        )
+        .with_hooks(hooks);
+        let (tx, rx) = std::sync::mpsc::channel();
+        let options = BatchOptions {
+            events: Some(tx),
+            cancel,
+            ..Default::default()
+        };
+        let report = ctx.ingest_dir(&root, &options).unwrap();
+
+        assert_eq!(report.ingested(), 2);
+        assert_eq!(report.skipped, 3);
+        assert!(report.cancelled());
+        drop(options);
+        let events: Vec<_> = rx.iter().collect();
+        assert!(matches!(events[0], IngestEvent::Started { total: 5 }));
+        let done: Vec<_> = events[1..events.len() - 1]
+            .iter()
+            .map(|e| match e {
+                IngestEvent::FileDone { path, result } => {
+                    assert!(result.is_ok());
+                    path.file_name().unwrap().to_string_lossy().into_owned()
+                }
+                other => panic!("unexpected event {other:?}"),
+            })
+            .collect();
+        assert_eq!(done, vec!["0.txt", "1.txt"]);
+        let Some(IngestEvent::Finished { report: finished }) = events.last() else {
+            panic!("no Finished event");
+        };
+        assert_eq!(finished.skipped, 3);
+
+        let rest = ctx.ingest_dir(&root, &BatchOptions::default()).unwrap();
+        assert_eq!(rest.ingested(), 5);
+        assert!(!rest.cancelled());
+    }
+}
@@ -0,0 +1,144 @@
+//! In-process memo of detector runs that are already in the store.
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+
+/// A detector run is reusable while the bytes, detector id, version and config are unchanged.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct CacheKey {
+    pub detector: String,
+    pub version: String,
+    pub config_hash: Option<String>,
+    pub content_hash: String,
+}
+
+/// Bounded least-recently-used set of [`CacheKey`]s. Clones share the same entries,
+/// so one cache can sit behind many short-lived ingest contexts.
+#[derive(Clone, Debug)]
+pub struct DetectorCache {
+    inner: Arc<Mutex<Lru>>,
+}
+
+#[derive(Debug)]
+struct Lru {
+    capacity: usize,
+    tick: u64,
+    entries: HashMap<CacheKey, u64>,
+    order: BTreeMap<u64, CacheKey>,
+}
+
+impl DetectorCache {
+    pub const DEFAULT_CAPACITY: usize = 4096;
+
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            inner: Arc::new(Mutex::new(Lru {
+                capacity: capacity.max(1),
+                tick: 0,
+                entries: HashMap::new(),
+                order: BTreeMap::new(),
+            })),
+        }
+    }
+
+    /// Whether `key` is cached; a hit marks it most recently used.
+    pub fn contains(&self, key: &CacheKey) -> bool {
+        let mut lru = self.inner.lock().expect("detector cache poisoned");
+        lru.touch(key)
+    }
+
+    pub fn insert(&self, key: CacheKey) {
+        let mut lru = self.inner.lock().expect("detector cache poisoned");
+        if lru.touch(&key) {
+            return;
+        }
+        if lru.entries.len() >= lru.capacity {
+            if let Some((_, oldest)) = lru.order.pop_first() {
+                lru.entries.remove(&oldest);
+            }
+        }
+        lru.tick += 1;
+        let tick = lru.tick;
+        lru.order.insert(tick, key.clone());
+        lru.entries.insert(key, tick);
+    }
+
+    pub fn len(&self) -> usize {
+        self.inner
+            .lock()
+            .expect("detector cache poisoned")
+            .entries
+            .len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Drop every entry for `detector`, so a run whose stored results were
+    /// cleared is not skipped as cached.
+    pub fn forget_detector(&self, detector: &str) {
+        let mut lru = self.inner.lock().expect("detector cache poisoned");
+        lru.entries.retain(|key, _| key.detector != detector);
+        lru.order.retain(|_, key| key.detector != detector);
+    }
+
+    pub fn clear(&self) {
+        let mut lru = self.inner.lock().expect("detector cache poisoned");
+        lru.entries.clear();
+        lru.order.clear();
+    }
+}
+
+impl Default for DetectorCache {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_CAPACITY)
+    }
+}
+
+impl Lru {
+    fn touch(&mut self, key: &CacheKey) -> bool {
+        let Some(old) = self.entries.get(key).copied() else {
+            return false;
+        };
+        self.tick += 1;
+        let tick = self.tick;
+        if let Some(key) = self.order.remove(&old) {
+            self.order.insert(tick, key);
+        }
+        self.entries.insert(key.clone(), tick);
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(detector: &str) -> CacheKey {
+        CacheKey {
+            detector: detector.to_string(),
+            version: "v1".to_string(),
+            config_hash: None,
+            content_hash: "abc".to_string(),
+        }
+    }
+
+    #[test]
+    fn least_recently_used_entry_is_evicted() {
+        let cache = DetectorCache::new(2);
+        cache.insert(key("a"));
+        cache.insert(key("b"));
+        assert!(cache.contains(&key("a")));
+        cache.insert(key("c"));
+
+        assert_eq!(cache.len(), 2);
+        assert!(cache.contains(&key("a")));
+        assert!(!cache.contains(&key("b")));
+        assert!(cache.clone().contains(&key("c")));
+        assert!(!cache.contains(&CacheKey {
+            version: "v2".to_string(),
+            ..key("a")
+        }));
+    }
+}
@@ -0,0 +1,273 @@
+//! Hooks run around ingest: pre-hooks rewrite the input before it is hashed and
+//! analysed, post-hooks see the finished result.
+
+use anyhow::{Context, Result};
+use pru_core::PruDbHandle;
+use pru_media_schema::MediaType;
+use std::borrow::Cow;
+use std::sync::Arc;
+
+use crate::IngestResult;
+
+/// Rewrites input before ingest, e.g. to redact text or shrink images. The returned
+/// bytes are what gets hashed, stored and shown to detectors.
+pub trait PreIngestHook: Send + Sync {
+    fn transform<'a>(&self, bytes: &'a [u8], media_type: MediaType) -> Result<Cow<'a, [u8]>>;
+
+    /// Used in errors and logs.
+    fn name(&self) -> String {
+        std::any::type_name::<Self>().to_string()
+    }
+}
+
+/// Runs after the results are recorded, e.g. to notify a webhook.
+pub trait PostIngestHook: Send + Sync {
+    fn on_complete(&self, result: &IngestResult, handle: &PruDbHandle) -> Result<()>;
+
+    /// Used in logs.
+    fn name(&self) -> String {
+        std::any::type_name::<Self>().to_string()
+    }
+}
+
+/// Hooks of an [`IngestContext`](crate::IngestContext), run in the order added.
+#[derive(Clone, Default)]
+pub struct IngestHooks {
+    pre: Vec<Arc<dyn PreIngestHook>>,
+    post: Vec<Arc<dyn PostIngestHook>>,
+}
+
+impl IngestHooks {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add_pre(&mut self, hook: Arc<dyn PreIngestHook>) {
+        self.pre.push(hook);
+    }
+
+    pub fn add_post(&mut self, hook: Arc<dyn PostIngestHook>) {
+        self.post.push(hook);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pre.is_empty() && self.post.is_empty()
+    }
+
+    pub(crate) fn has_pre(&self) -> bool {
+        !self.pre.is_empty()
+    }
+
+    /// Feed `bytes` through every pre-hook; the first error aborts the ingest.
+    pub(crate) fn transform<'a>(
+        &self,
+        bytes: &'a [u8],
+        media_type: MediaType,
+    ) -> Result<Cow<'a, [u8]>> {
+        let mut current = Cow::Borrowed(bytes);
+        for hook in &self.pre {
+            current = match current {
+                Cow::Borrowed(b) => hook.transform(b, media_type),
+                Cow::Owned(b) => hook
+                    .transform(&b, media_type)
+                    .map(|out| Cow::Owned(out.into_owned())),
+            }
+            .with_context(|| format!("pre-ingest hook {}", hook.name()))?;
+        }
+        Ok(current)
+    }
+
+    /// Run every post-hook; failures are logged and do not affect the ingest.
+    pub(crate) fn complete(&self, result: &IngestResult, handle: &PruDbHandle) {
+        for hook in &self.post {
+            if let Err(e) = hook.on_complete(result, handle) {
+                tracing::warn!(
+                    "post-ingest hook {} failed for media {}: {e:#}",
+                    hook.name(),
+                    result.media_id.0
+                );
+            }
+        }
+    }
+}
+
+/// Shrinks images whose width or height exceeds `max_dimension`, keeping the
+/// aspect ratio and re-encoding in the original format (PNG when that format
+/// cannot be written). Other media, and images that are small enough or cannot
+/// be decoded, pass through untouched.
+#[derive(Clone, Debug)]
+pub struct ImageDownscaleHook {
+    pub max_dimension: u32,
+}
+
+impl ImageDownscaleHook {
+    pub fn new(max_dimension: u32) -> Self {
+        Self { max_dimension }
+    }
+}
+
+impl PreIngestHook for ImageDownscaleHook {
+    fn transform<'a>(&self, bytes: &'a [u8], media_type: MediaType) -> Result<Cow<'a, [u8]>> {
+        if media_type != MediaType::Image {
+            return Ok(Cow::Borrowed(bytes));
+        }
+        let Ok(reader) = image::ImageReader::new(std::io::Cursor::new(bytes)).with_guessed_format()
+        else {
+            return Ok(Cow::Borrowed(bytes));
+        };
+        let Some(format) = reader.format() else {
+            return Ok(Cow::Borrowed(bytes));
+        };
+        let Ok(img) = reader.decode() else {
+            return Ok(Cow::Borrowed(bytes));
+        };
+        if img.width().max(img.height()) <= self.max_dimension {
+            return Ok(Cow::Borrowed(bytes));
+        }
+        let small = img.resize(
+            self.max_dimension,
+            self.max_dimension,
+            image::imageops::FilterType::Triangle,
+        );
+        let format = if format.writing_enabled() {
+            format
+        } else {
+            image::ImageFormat::Png
+        };
+        let mut out = Vec::new();
+        small
+            .write_to(&mut std::io::Cursor::new(&mut out), format)
+            .with_context(|| format!("re-encoding downscaled image as {format:?}"))?;
+        Ok(Cow::Owned(out))
+    }
+
+    fn name(&self) -> String {
+        format!("image-downscale({})", self.max_dimension)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::IngestContext;
+    use pru_core::PruStore;
+    use pru_detectors_api::DetectorRegistry;
+    use pru_media_schema::{get_media_metadata, hash_bytes};
+    use std::sync::Mutex;
+    use tempfile::tempdir;
+
+    type Log = Arc<Mutex<Vec<String>>>;
+
+    struct Append(&'static str, Log);
+
+    impl PreIngestHook for Append {
+        fn transform<'a>(&self, bytes: &'a [u8], _: MediaType) -> Result<Cow<'a, [u8]>> {
+            self.1.lock().unwrap().push(format!("pre {}", self.0));
+            Ok(Cow::Owned([bytes, self.0.as_bytes()].concat()))
+        }
+    }
+
+    struct Reject;
+
+    impl PreIngestHook for Reject {
+        fn transform<'a>(&self, _: &'a [u8], _: MediaType) -> Result<Cow<'a, [u8]>> {
+            anyhow::bail!("contains an email address")
+        }
+    }
+
+    struct Record(&'static str, Log, bool);
+
+    impl PostIngestHook for Record {
+        fn on_complete(&self, result: &IngestResult, _: &PruDbHandle) -> Result<()> {
+            self.1
+                .lock()
+                .unwrap()
+                .push(format!("post {} {}", self.0, result.hash));
+            if self.2 {
+                anyhow::bail!("webhook unreachable");
+            }
+            Ok(())
+        }
+    }
+
+    fn context(dir: &std::path::Path, hooks: IngestHooks) -> IngestContext {
+        IngestContext::new(
+            Arc::new(Mutex::new(PruStore::open(dir).unwrap())),
+            DetectorRegistry::new(),
+        )
+        .with_hooks(hooks)
+    }
+
+    #[test]
+    fn hooks_run_in_order_and_post_failures_are_not_fatal() {
+        let dir = tempdir().unwrap();
+        let log = Log::default();
+        let mut hooks = IngestHooks::new();
+        hooks.add_pre(Arc::new(Append("a", log.clone())));
+        hooks.add_pre(Arc::new(Append("b", log.clone())));
+        hooks.add_post(Arc::new(Record("first", log.clone(), true)));
+        hooks.add_post(Arc::new(Record("second", log.clone(), false)));
+        let ctx = context(dir.path(), hooks);
+
+        let result = ctx.ingest_text("text:").unwrap();
+        let hash = hash_bytes(b"text:ab");
+        assert_eq!(result.hash, hash);
+        assert_eq!(
+            *log.lock().unwrap(),
+            vec![
+                "pre a".to_string(),
+                "pre b".to_string(),
+                format!("post first {hash}"),
+                format!("post second {hash}"),
+            ]
+        );
+    }
+
+    #[test]
+    fn failing_pre_hook_aborts_before_anything_is_stored() {
+        let dir = tempdir().unwrap();
+        let log = Log::default();
+        let mut hooks = IngestHooks::new();
+        hooks.add_pre(Arc::new(Append("a", log.clone())));
+        hooks.add_pre(Arc::new(Reject));
+        hooks.add_pre(Arc::new(Append("never", log.clone())));
+        hooks.add_post(Arc::new(Record("post", log.clone(), false)));
+        let ctx = context(dir.path(), hooks);
+        let facts = ctx.pru.lock().unwrap().fact_count();
+
+        let Err(err) = ctx.ingest_text("mail me at a@b.c") else {
+            panic!("rejected text was ingested");
+        };
+        let message = format!("{err:#}");
+        assert!(message.contains("Reject"), "{message}");
+        assert!(message.contains("contains an email address"), "{message}");
+        assert_eq!(*log.lock().unwrap(), vec!["pre a".to_string()]);
+        assert_eq!(ctx.pru.lock().unwrap().fact_count(), facts);
+    }
+
+    #[test]
+    fn downscaler_shrinks_only_large_images() {
+        let dir = tempdir().unwrap();
+        let mut hooks = IngestHooks::new();
+        hooks.add_pre(Arc::new(ImageDownscaleHook::new(16)));
+        let ctx = context(dir.path(), hooks);
+        let png = |w, h| {
+            let mut buf = Vec::new();
+            image::DynamicImage::ImageRgb8(image::RgbImage::new(w, h))
+                .write_to(&mut std::io::Cursor::new(&mut buf), image::ImageFormat::Png)
+                .unwrap();
+            buf
+        };
+
+        let large = ctx.ingest_image(&png(64, 32)).unwrap();
+        let meta = get_media_metadata(&ctx.pru, large.media_id).unwrap();
+        assert_eq!((meta.width, meta.height), (Some(16), Some(8)));
+        assert_eq!(meta.mime.as_deref(), Some("image/png"));
+
+        let small = png(8, 8);
+        let kept = ctx.ingest_image(&small).unwrap();
+        assert_eq!(kept.hash, hash_bytes(&small));
+        let text = ctx.ingest_text("not an image").unwrap();
+        assert_eq!(text.hash, hash_bytes(b"not an image"));
+    }
+}
@@ -1,71 +1,875 @@
-use anyhow::{Context, Result};
+use anyhow::Result;
 use pru_core::PruDbHandle;
-use pru_detectors_api::{media_type_to_kind, DetectorRegistry};
+use pru_detectors_api::{
+    decode_wav, media_type_to_kind, probe_container, sniff_media_type, AsyncMediaDetector,
+    DetectorOutput, DetectorRegistry,
+};
 use pru_media_schema::{
-    add_content_hash, add_content_type, add_detector_score, hash_bytes, mark_analyzed_by,
-    upsert_media_entity, MediaId, MediaType,
+    add_content_hash, add_content_type, add_media_metadata, clear_detector_results,
+    create_media_entity, find_media_entity, get_stored_at, has_current_detector_score, hash_bytes,
+    record_analysis, set_stored_at, DetectorId, DetectorResult, DetectorRun, MediaId,
+    MediaMetadata, MediaType, Sighting, SubmissionContext,
+};
+use pru_storage::{MediaStorage, StorageError};
+use serde::Serialize;
+use std::any::Any;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+mod batch;
+mod cache;
+mod hooks;
+mod limits;
+mod similarity;
+mod stream;
+#[cfg(feature = "url")]
+mod url;
+
+pub use batch::{
+    BatchIngestReport, BatchOptions, CancellationToken, FileOutcome, IngestEvent, IngestedFile,
 };
+pub use cache::{CacheKey, DetectorCache};
+pub use hooks::{ImageDownscaleHook, IngestHooks, PostIngestHook, PreIngestHook};
+pub use limits::{IngestError, IngestLimits};
+pub use similarity::{NearDuplicateOptions, PHASH_FEATURE};
+pub use stream::MediaUpload;
+#[cfg(feature = "url")]
+pub use url::{is_public_ip, BlockedUrl, UrlOptions};
+
+/// How a single detector fared during ingest.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DetectorStatus {
+    Succeeded,
+    /// The detector returned an error or panicked.
+    Failed(String),
+    TimedOut,
+    /// Skipped: the store already holds this detector's result for the same bytes.
+    Cached,
+}
 
+#[derive(Clone, Debug, Serialize)]
+pub struct DetectorOutcome {
+    pub detector: String,
+    pub status: DetectorStatus,
+    /// What the detector said; only set when it ran and succeeded.
+    pub output: Option<DetectorOutput>,
+    /// Wall time of the run; 0 for cached results.
+    pub duration_ms: u64,
+}
+
+#[derive(Clone, Debug, Serialize)]
 pub struct IngestResult {
     pub media_id: MediaId,
+    pub hash: String,
+    pub media_type: MediaType,
+    /// The store had no entity for these bytes before this ingest.
+    pub was_new: bool,
+    /// One entry per enabled detector, sorted by detector id.
+    pub outcomes: Vec<DetectorOutcome>,
+}
+
+impl IngestResult {
+    pub fn succeeded(&self) -> Vec<&str> {
+        self.with_status(|s| *s == DetectorStatus::Succeeded)
+    }
+
+    pub fn failed(&self) -> Vec<&str> {
+        self.with_status(|s| matches!(s, DetectorStatus::Failed(_)))
+    }
+
+    pub fn timed_out(&self) -> Vec<&str> {
+        self.with_status(|s| *s == DetectorStatus::TimedOut)
+    }
+
+    pub fn cached(&self) -> Vec<&str> {
+        self.with_status(|s| *s == DetectorStatus::Cached)
+    }
+
+    fn with_status(&self, keep: impl Fn(&DetectorStatus) -> bool) -> Vec<&str> {
+        self.outcomes
+            .iter()
+            .filter(|o| keep(&o.status))
+            .map(|o| o.detector.as_str())
+            .collect()
+    }
+}
+
+/// Where a submission came from, recorded with the analysis facts, and what ingest
+/// derives from the results.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct IngestOptions {
+    /// Site or platform the media was seen on, e.g. "reddit.com". Defaults to the
+    /// host of `source_url`; with neither, no sighting is recorded.
+    pub source_name: Option<String>,
+    pub source_url: Option<String>,
+    /// Who submitted the media.
+    pub reporter: Option<String>,
+    /// Unix seconds of the sighting; defaults to the time of ingest.
+    pub observed_at: Option<i64>,
+    pub tags: Vec<String>,
+    pub near_duplicates: NearDuplicateOptions,
+}
+
+impl IngestOptions {
+    fn submission(&self) -> SubmissionContext {
+        let observed_at = self.observed_at.unwrap_or_else(now_ts);
+        let source = self
+            .source_name
+            .clone()
+            .or_else(|| self.source_url.as_deref().and_then(url_host));
+        SubmissionContext {
+            sighting: source.map(|source| Sighting {
+                source,
+                url: self.source_url.clone(),
+                content_type: None,
+                observed_at,
+            }),
+            reporter: self.reporter.clone(),
+            tags: self.tags.clone(),
+            reported_at: observed_at,
+        }
+    }
+}
+
+/// Host part of an absolute URL, without credentials, port or IPv6 brackets.
+fn url_host(url: &str) -> Option<String> {
+    match ::url::Url::parse(url).ok()?.host()? {
+        ::url::Host::Ipv6(ip) => Some(ip.to_string()),
+        host => Some(host.to_string().to_ascii_lowercase()),
+    }
+}
+
+fn now_ts() -> i64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0)
 }
 
 #[derive(Clone)]
 pub struct IngestContext {
     pub pru: PruDbHandle,
     pub detectors: DetectorRegistry,
+    pub cache: DetectorCache,
+    /// Run every detector even when its result for these bytes is already stored.
+    pub force: bool,
+    /// Keep a copy of the ingested bytes so media can be re-analysed later.
+    pub storage: Option<MediaStorage>,
+    /// Inputs over these limits are refused with an [`IngestError`] before any work.
+    pub limits: IngestLimits,
+    /// Run before hashing (and after the limit check) and after results are recorded.
+    pub hooks: IngestHooks,
 }
 
 impl IngestContext {
+    /// A context that runs `detectors` against `pru` with an empty cache, no
+    /// media storage, default limits and no hooks.
+    pub fn new(pru: PruDbHandle, detectors: DetectorRegistry) -> Self {
+        Self {
+            pru,
+            detectors,
+            cache: DetectorCache::default(),
+            force: false,
+            storage: None,
+            limits: IngestLimits::default(),
+            hooks: IngestHooks::default(),
+        }
+    }
+
+    pub fn with_cache(mut self, cache: DetectorCache) -> Self {
+        self.cache = cache;
+        self
+    }
+
+    pub fn with_force(mut self, force: bool) -> Self {
+        self.force = force;
+        self
+    }
+
+    pub fn with_storage(mut self, storage: impl Into<Option<MediaStorage>>) -> Self {
+        self.storage = storage.into();
+        self
+    }
+
+    pub fn with_limits(mut self, limits: IngestLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    pub fn with_hooks(mut self, hooks: IngestHooks) -> Self {
+        self.hooks = hooks;
+        self
+    }
+
     pub fn ingest_image(&self, bytes: &[u8]) -> Result<IngestResult> {
-        self.ingest_generic(bytes, MediaType::Image)
+        self.ingest_image_with_options(bytes, &IngestOptions::default())
     }
 
     pub fn ingest_text(&self, text: &str) -> Result<IngestResult> {
-        self.ingest_generic(text.as_bytes(), MediaType::Text)
+        self.ingest_text_with_options(text, &IngestOptions::default())
     }
 
     pub fn ingest_audio(&self, bytes: &[u8]) -> Result<IngestResult> {
-        self.ingest_generic(bytes, MediaType::Audio)
+        self.ingest_audio_with_options(bytes, &IngestOptions::default())
     }
 
     pub fn ingest_video(&self, bytes: &[u8]) -> Result<IngestResult> {
-        self.ingest_generic(bytes, MediaType::Video)
+        self.ingest_video_with_options(bytes, &IngestOptions::default())
     }
 
-    fn ingest_generic(&self, bytes: &[u8], media_type: MediaType) -> Result<IngestResult> {
+    /// Ingest bytes whose type is unknown or untrusted, judging it from the content.
+    pub fn ingest_auto(&self, bytes: &[u8]) -> Result<IngestResult> {
+        self.ingest_auto_with_options(bytes, &IngestOptions::default())
+    }
+
+    pub fn ingest_image_with_options(
+        &self,
+        bytes: &[u8],
+        options: &IngestOptions,
+    ) -> Result<IngestResult> {
+        self.ingest_generic(bytes, MediaType::Image, options)
+    }
+
+    pub fn ingest_text_with_options(
+        &self,
+        text: &str,
+        options: &IngestOptions,
+    ) -> Result<IngestResult> {
+        self.ingest_generic(text.as_bytes(), MediaType::Text, options)
+    }
+
+    pub fn ingest_audio_with_options(
+        &self,
+        bytes: &[u8],
+        options: &IngestOptions,
+    ) -> Result<IngestResult> {
+        self.ingest_generic(bytes, MediaType::Audio, options)
+    }
+
+    pub fn ingest_video_with_options(
+        &self,
+        bytes: &[u8],
+        options: &IngestOptions,
+    ) -> Result<IngestResult> {
+        self.ingest_generic(bytes, MediaType::Video, options)
+    }
+
+    pub fn ingest_auto_with_options(
+        &self,
+        bytes: &[u8],
+        options: &IngestOptions,
+    ) -> Result<IngestResult> {
+        self.ingest_generic(bytes, detect_media_type(bytes)?, options)
+    }
+
+    pub async fn ingest_image_async(&self, bytes: &[u8]) -> Result<IngestResult> {
+        self.ingest_image_async_with_options(bytes, &IngestOptions::default())
+            .await
+    }
+
+    pub async fn ingest_text_async(&self, text: &str) -> Result<IngestResult> {
+        self.ingest_text_async_with_options(text, &IngestOptions::default())
+            .await
+    }
+
+    pub async fn ingest_audio_async(&self, bytes: &[u8]) -> Result<IngestResult> {
+        self.ingest_audio_async_with_options(bytes, &IngestOptions::default())
+            .await
+    }
+
+    pub async fn ingest_video_async(&self, bytes: &[u8]) -> Result<IngestResult> {
+        self.ingest_video_async_with_options(bytes, &IngestOptions::default())
+            .await
+    }
+
+    pub async fn ingest_auto_async(&self, bytes: &[u8]) -> Result<IngestResult> {
+        self.ingest_auto_async_with_options(bytes, &IngestOptions::default())
+            .await
+    }
+
+    pub async fn ingest_image_async_with_options(
+        &self,
+        bytes: &[u8],
+        options: &IngestOptions,
+    ) -> Result<IngestResult> {
+        self.ingest_generic_async(bytes, MediaType::Image, options)
+            .await
+    }
+
+    pub async fn ingest_text_async_with_options(
+        &self,
+        text: &str,
+        options: &IngestOptions,
+    ) -> Result<IngestResult> {
+        self.ingest_generic_async(text.as_bytes(), MediaType::Text, options)
+            .await
+    }
+
+    pub async fn ingest_audio_async_with_options(
+        &self,
+        bytes: &[u8],
+        options: &IngestOptions,
+    ) -> Result<IngestResult> {
+        self.ingest_generic_async(bytes, MediaType::Audio, options)
+            .await
+    }
+
+    pub async fn ingest_video_async_with_options(
+        &self,
+        bytes: &[u8],
+        options: &IngestOptions,
+    ) -> Result<IngestResult> {
+        self.ingest_generic_async(bytes, MediaType::Video, options)
+            .await
+    }
+
+    pub async fn ingest_auto_async_with_options(
+        &self,
+        bytes: &[u8],
+        options: &IngestOptions,
+    ) -> Result<IngestResult> {
+        self.ingest_generic_async(bytes, detect_media_type(bytes)?, options)
+            .await
+    }
+
+    fn ingest_generic(
+        &self,
+        bytes: &[u8],
+        media_type: MediaType,
+        options: &IngestOptions,
+    ) -> Result<IngestResult> {
+        self.limits.check(bytes, media_type)?;
+        let transformed = self.hooks.transform(bytes, media_type)?;
+        let media = self.record_media(&transformed, media_type)?;
+        self.analyze(media, DetectorInput::copied(&transformed), options)
+    }
+
+    /// Run the detectors that still need to see `media` and record what they said.
+    fn analyze(
+        &self,
+        media: RecordedMedia,
+        input: DetectorInput,
+        options: &IngestOptions,
+    ) -> Result<IngestResult> {
+        let (detectors, cached) = self.split_cached(media.media_type, media.id, &media.hash)?;
+        let results = self.run_all_isolated(&detectors, input, media.id);
+        self.finish(media, &detectors, results, cached, options)
+    }
+
+    async fn analyze_async(
+        &self,
+        media: RecordedMedia,
+        input: DetectorInput,
+        options: &IngestOptions,
+    ) -> Result<IngestResult> {
+        let (detectors, cached) = self.split_cached(media.media_type, media.id, &media.hash)?;
+        let results = self
+            .run_all_isolated_async(&detectors, input, media.id)
+            .await;
+        self.finish(media, &detectors, results, cached, options)
+    }
+
+    fn finish(
+        &self,
+        media: RecordedMedia,
+        detectors: &[Arc<dyn AsyncMediaDetector>],
+        results: Vec<DetectorAttempt>,
+        cached: Vec<DetectorOutcome>,
+        options: &IngestOptions,
+    ) -> Result<IngestResult> {
+        let outcomes =
+            self.record_results(media.id, &media.hash, detectors, results, cached, options)?;
+        similarity::link_near_duplicates(&self.pru, media.id, &outcomes, &options.near_duplicates)?;
+        let result = IngestResult {
+            media_id: media.id,
+            hash: media.hash,
+            media_type: media.media_type,
+            was_new: media.was_new,
+            outcomes,
+        };
+        self.hooks.complete(&result, &self.pru);
+        Ok(result)
+    }
+
+    /// Like the sync path, but awaits detectors so slow ones never block a runtime worker.
+    async fn ingest_generic_async(
+        &self,
+        bytes: &[u8],
+        media_type: MediaType,
+        options: &IngestOptions,
+    ) -> Result<IngestResult> {
+        self.limits.check(bytes, media_type)?;
+        let transformed = self.hooks.transform(bytes, media_type)?;
+        let media = self.record_media(&transformed, media_type)?;
+        self.analyze_async(media, DetectorInput::copied(&transformed), options)
+            .await
+    }
+
+    /// Store the bytes (with storage configured), the media entity and its content facts.
+    fn record_media(&self, bytes: &[u8], media_type: MediaType) -> Result<RecordedMedia> {
         let hash = hash_bytes(bytes);
-        let media_id = upsert_media_entity(&self.pru, &hash, media_type)?;
-        add_content_type(&self.pru, media_id, media_type)?;
-        add_content_hash(&self.pru, media_id, &hash)?;
-
-        let kind = media_type_to_kind(media_type);
-        for detector in self.detectors.for_media(kind).iter() {
-            let output = detector.detect(bytes).with_context(|| detector.id())?;
-            let detector_id = pru_media_schema::ensure_detector_entity(&self.pru, &detector.id())?;
-            mark_analyzed_by(&self.pru, media_id, detector_id)?;
-            add_detector_score(
-                &self.pru,
-                media_id,
-                detector_id,
-                output.score_ai as f64,
-                &format!("{:?}", output.label),
-            )?;
-            if let Some(_details) = output.details.as_ref() {
-                // placeholder
+        let metadata = probe_metadata(bytes, media_type);
+        let stored_at = match &self.storage {
+            Some(storage) => {
+                // Content already on disk for this media is not hashed or written again.
+                if let Some(path) = self.stored_path(storage, &hash, media_type)? {
+                    return self.record_entity(hash, media_type, &metadata, Some(&path));
+                }
+                let stored = storage.store_media_auto(&hash, bytes)?;
+                if stored.repaired {
+                    tracing::warn!("replaced corrupt stored file {}", stored.path.display());
+                }
+                Some(stored.relative_path)
+            }
+            None => None,
+        };
+        self.record_entity(hash, media_type, &metadata, stored_at.as_deref())
+    }
+
+    /// Where the media with `hash` is already stored, if it was recorded with a
+    /// path that `storage` still has.
+    fn stored_path(
+        &self,
+        storage: &MediaStorage,
+        hash: &str,
+        media_type: MediaType,
+    ) -> Result<Option<String>> {
+        let Some(id) = find_media_entity(&self.pru, hash, media_type)? else {
+            return Ok(None);
+        };
+        Ok(get_stored_at(&self.pru, id)?.filter(|path| storage.contains(path)))
+    }
+
+    /// The facts half of [`record_media`](Self::record_media), for content that is
+    /// already hashed, probed and (optionally) stored.
+    fn record_entity(
+        &self,
+        hash: String,
+        media_type: MediaType,
+        metadata: &MediaMetadata,
+        stored_at: Option<&str>,
+    ) -> Result<RecordedMedia> {
+        let (id, was_new) = create_media_entity(&self.pru, &hash, media_type)?;
+        add_content_type(&self.pru, id, media_type)?;
+        add_content_hash(&self.pru, id, &hash)?;
+        add_media_metadata(&self.pru, id, metadata)?;
+        if let Some(path) = stored_at {
+            set_stored_at(&self.pru, id, path)?;
+        }
+        Ok(RecordedMedia {
+            id,
+            hash,
+            media_type,
+            was_new,
+        })
+    }
+
+    /// Retract `detector`'s stored results for `media` and drop it from the
+    /// cache, so the next ingest of the same bytes runs it again. Returns the
+    /// number of facts retracted.
+    pub fn clear_detector_results(&self, media: MediaId, detector: &str) -> Result<usize> {
+        self.cache.forget_detector(detector);
+        let id = self
+            .pru
+            .lock()
+            .expect("store poisoned")
+            .get_entity_id(detector);
+        match id {
+            Some(id) => clear_detector_results(&self.pru, media, DetectorId(id)),
+            None => Ok(0),
+        }
+    }
+
+    /// Enabled detectors for `media_type` that still need to run, plus outcomes for
+    /// those whose result is already cached in memory or stored for this media.
+    fn split_cached(
+        &self,
+        media_type: MediaType,
+        media_id: MediaId,
+        hash: &str,
+    ) -> Result<(DetectorList, Vec<DetectorOutcome>)> {
+        let detectors = self.detectors.for_media(media_type_to_kind(media_type));
+        if self.force {
+            return Ok((detectors, Vec::new()));
+        }
+        let mut pending = Vec::with_capacity(detectors.len());
+        let mut cached = Vec::new();
+        for detector in detectors {
+            let id = detector.id();
+            let info = detector.info();
+            let key = cache_key(&id, &info, hash);
+            if self.cache.contains(&key)
+                || has_current_detector_score(&self.pru, media_id, &id, &info)?
+            {
+                self.cache.insert(key);
+                cached.push(DetectorOutcome {
+                    detector: id,
+                    status: DetectorStatus::Cached,
+                    output: None,
+                    duration_ms: 0,
+                });
+            } else {
+                pending.push(detector);
+            }
+        }
+        Ok((pending, cached))
+    }
+
+    /// Run every detector on its own thread at once, catching panics and giving
+    /// up on each after its timeout.
+    ///
+    /// A detector that never returns keeps its thread; ingest just stops waiting for it.
+    fn run_all_isolated(
+        &self,
+        detectors: &[Arc<dyn AsyncMediaDetector>],
+        input: DetectorInput,
+        media_id: MediaId,
+    ) -> Vec<DetectorAttempt> {
+        let started = Instant::now();
+        let pending: Vec<_> = detectors
+            .iter()
+            .map(|detector| spawn_isolated(detector, input.clone(), self.pru.clone(), media_id))
+            .collect();
+        detectors
+            .iter()
+            .zip(pending)
+            .map(|(detector, rx)| {
+                let rx = match rx {
+                    Ok(rx) => rx,
+                    Err(status) => return (Err(status), Duration::ZERO),
+                };
+                let deadline = started + self.detectors.timeout_for(&detector.id());
+                match rx.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+                    Ok((Ok(Ok(output)), took)) => (Ok(output), took),
+                    Ok((Ok(Err(e)), took)) => (Err(DetectorStatus::Failed(format!("{e:#}"))), took),
+                    Ok((Err(payload), took)) => (
+                        Err(DetectorStatus::Failed(panic_message(payload.as_ref()))),
+                        took,
+                    ),
+                    Err(RecvTimeoutError::Timeout) => {
+                        (Err(DetectorStatus::TimedOut), started.elapsed())
+                    }
+                    Err(RecvTimeoutError::Disconnected) => (
+                        Err(DetectorStatus::Failed("detector thread exited".to_string())),
+                        started.elapsed(),
+                    ),
+                }
+            })
+            .collect()
+    }
+
+    async fn run_all_isolated_async(
+        &self,
+        detectors: &[Arc<dyn AsyncMediaDetector>],
+        input: DetectorInput,
+        media_id: MediaId,
+    ) -> Vec<DetectorAttempt> {
+        let started = tokio::time::Instant::now();
+        let tasks: Vec<_> = detectors
+            .iter()
+            .map(|detector| {
+                let worker = detector.clone();
+                let input = input.clone();
+                let pru = self.pru.clone();
+                tokio::spawn(async move {
+                    let begun = Instant::now();
+                    let from_file = match &input.file {
+                        Some(path) => worker.detect_file(path, &pru, media_id).await,
+                        None => None,
+                    };
+                    let result = match from_file {
+                        Some(result) => result,
+                        None => {
+                            worker
+                                .detect_with_context(input.bytes(), &pru, media_id)
+                                .await
+                        }
+                    };
+                    (result, begun.elapsed())
+                })
+            })
+            .collect();
+        let mut results = Vec::with_capacity(tasks.len());
+        for (detector, mut task) in detectors.iter().zip(tasks) {
+            let deadline = started + self.detectors.timeout_for(&detector.id());
+            results.push(match tokio::time::timeout_at(deadline, &mut task).await {
+                Ok(Ok((Ok(output), took))) => (Ok(output), took),
+                Ok(Ok((Err(e), took))) => (Err(DetectorStatus::Failed(format!("{e:#}"))), took),
+                Ok(Err(join)) if join.is_panic() => (
+                    Err(DetectorStatus::Failed(panic_message(
+                        join.into_panic().as_ref(),
+                    ))),
+                    started.elapsed(),
+                ),
+                Ok(Err(join)) => (
+                    Err(DetectorStatus::Failed(join.to_string())),
+                    started.elapsed(),
+                ),
+                Err(_) => {
+                    task.abort();
+                    (Err(DetectorStatus::TimedOut), started.elapsed())
+                }
+            });
+        }
+        results
+    }
+
+    /// Persist every detector's output, or an `analysis_error` fact for those that
+    /// did not finish, together with the submission context in one batched store write.
+    fn record_results(
+        &self,
+        media_id: MediaId,
+        hash: &str,
+        detectors: &[Arc<dyn AsyncMediaDetector>],
+        results: Vec<DetectorAttempt>,
+        mut outcomes: Vec<DetectorOutcome>,
+        options: &IngestOptions,
+    ) -> Result<Vec<DetectorOutcome>> {
+        let mut runs = Vec::with_capacity(results.len());
+        let mut fresh = Vec::new();
+        for (detector, (attempt, took)) in detectors.iter().zip(results) {
+            let id = detector.id();
+            let (result, status, output) = match attempt {
+                Ok(output) => (
+                    DetectorResult::Scored {
+                        score: output.score_ai as f64,
+                        label: output.label.as_str().to_string(),
+                        details: output.details.clone(),
+                        features: output.features.clone(),
+                    },
+                    DetectorStatus::Succeeded,
+                    Some(output),
+                ),
+                Err(status) => {
+                    let message = match &status {
+                        DetectorStatus::Failed(msg) => msg.clone(),
+                        DetectorStatus::TimedOut => {
+                            format!("timed out after {:?}", self.detectors.timeout_for(&id))
+                        }
+                        DetectorStatus::Succeeded | DetectorStatus::Cached => String::new(),
+                    };
+                    (DetectorResult::Failed(message), status, None)
+                }
+            };
+            let info = detector.info();
+            if status == DetectorStatus::Succeeded {
+                fresh.push(cache_key(&id, &info, hash));
+            }
+            runs.push(DetectorRun {
+                detector: id.clone(),
+                info,
+                result,
+            });
+            outcomes.push(DetectorOutcome {
+                detector: id,
+                status,
+                output,
+                duration_ms: took.as_millis() as u64,
+            });
+        }
+        let submission = options.submission();
+        if !runs.is_empty() || !submission.is_empty() {
+            record_analysis(&self.pru, media_id, runs, &submission)?;
+        }
+        // Only remember results once they are safely in the store.
+        for key in fresh {
+            self.cache.insert(key);
+        }
+        outcomes.sort_by(|a, b| a.detector.cmp(&b.detector));
+        Ok(outcomes)
+    }
+}
+
+fn cache_key(detector: &str, info: &pru_media_schema::DetectorInfo, hash: &str) -> CacheKey {
+    CacheKey {
+        detector: detector.to_string(),
+        version: info.version.clone(),
+        config_hash: info.config_hash.clone(),
+        content_hash: hash.to_string(),
+    }
+}
+
+/// A detector's result, or why there is none, and how long it took.
+type DetectorAttempt = (
+    std::result::Result<DetectorOutput, DetectorStatus>,
+    Duration,
+);
+type PanicResult = std::thread::Result<Result<DetectorOutput>>;
+type DetectorList = Vec<Arc<dyn AsyncMediaDetector>>;
+
+fn spawn_isolated(
+    detector: &Arc<dyn AsyncMediaDetector>,
+    input: DetectorInput,
+    pru: PruDbHandle,
+    media_id: MediaId,
+) -> std::result::Result<mpsc::Receiver<(PanicResult, Duration)>, DetectorStatus> {
+    let (tx, rx) = mpsc::channel();
+    let worker = detector.clone();
+    std::thread::Builder::new()
+        .name(format!("detector {}", detector.id()))
+        .spawn(move || {
+            let begun = Instant::now();
+            let result = panic::catch_unwind(AssertUnwindSafe(|| {
+                detect_blocking(worker.as_ref(), &input, &pru, media_id)
+            }));
+            let _ = tx.send((result, begun.elapsed()));
+        })
+        .map_err(|e| DetectorStatus::Failed(format!("spawning detector thread: {e}")))?;
+    Ok(rx)
+}
+
+fn panic_message(payload: &(dyn Any + Send)) -> String {
+    let msg = payload
+        .downcast_ref::<&str>()
+        .map(|s| s.to_string())
+        .or_else(|| payload.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "unknown panic".to_string());
+    format!("panicked: {msg}")
+}
+
+/// Run a detector on its own detector thread: sync detectors run inline, async
+/// ones on a runtime of their own. No runtime worker is ever blocked, so this
+/// is safe under any runtime flavor.
+fn detect_blocking(
+    detector: &dyn AsyncMediaDetector,
+    input: &DetectorInput,
+    pru: &PruDbHandle,
+    media_id: MediaId,
+) -> Result<DetectorOutput> {
+    if let Some(sync) = detector.as_sync() {
+        if let Some(path) = &input.file {
+            if let Some(result) = sync.detect_file(path, pru, media_id) {
+                return result;
+            }
+        }
+        return sync.detect_with_context(input.bytes(), pru, media_id);
+    }
+    let detection = async {
+        if let Some(path) = &input.file {
+            if let Some(result) = detector.detect_file(path, pru, media_id).await {
+                return result;
             }
         }
+        detector
+            .detect_with_context(input.bytes(), pru, media_id)
+            .await
+    };
+    tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()?
+        .block_on(detection)
+}
+
+/// A media item as just recorded, before its detectors have run.
+struct RecordedMedia {
+    id: MediaId,
+    hash: String,
+    media_type: MediaType,
+    was_new: bool,
+}
+
+/// What detectors see: the bytes, shared between detector threads, and the stored
+/// file they came from when they were streamed to storage.
+#[derive(Clone)]
+struct DetectorInput {
+    bytes: Arc<dyn AsRef<[u8]> + Send + Sync>,
+    file: Option<Arc<std::path::Path>>,
+}
 
-        Ok(IngestResult { media_id })
+impl DetectorInput {
+    fn copied(bytes: &[u8]) -> Self {
+        Self {
+            bytes: Arc::new(bytes.to_vec()),
+            file: None,
+        }
+    }
+
+    fn bytes(&self) -> &[u8] {
+        (*self.bytes).as_ref()
+    }
+}
+
+/// Media type of `bytes` from their signature or, failing that, whether they read
+/// as text; errors say why no type fits.
+pub fn detect_media_type(bytes: &[u8]) -> Result<MediaType> {
+    if bytes.is_empty() {
+        anyhow::bail!("cannot determine media type: input is empty");
+    }
+    sniff_media_type(bytes).ok_or_else(|| {
+        anyhow::anyhow!(
+            "cannot determine media type: {} bytes match no known image, audio or video \
+             signature and are not printable UTF-8 text",
+            bytes.len()
+        )
+    })
+}
+
+/// The original bytes of `media`, if they were kept at ingest time and have not
+/// been evicted or deleted since.
+pub fn load_original(
+    handle: &PruDbHandle,
+    storage: &MediaStorage,
+    media: MediaId,
+) -> Result<Option<Vec<u8>>> {
+    let Some(path) = get_stored_at(handle, media)? else {
+        return Ok(None);
+    };
+    match storage.load_relative(&path) {
+        Ok(bytes) => Ok(Some(bytes)),
+        Err(e) if e.downcast_ref::<StorageError>().is_some() => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+/// Cheap technical metadata; images only have their header read, not decoded.
+fn probe_metadata(bytes: &[u8], media_type: MediaType) -> MediaMetadata {
+    let mut meta = MediaMetadata {
+        byte_len: Some(bytes.len() as u64),
+        ..Default::default()
+    };
+    match media_type {
+        MediaType::Image => {
+            if let Ok(reader) =
+                image::ImageReader::new(std::io::Cursor::new(bytes)).with_guessed_format()
+            {
+                meta.mime = reader.format().map(|f| f.to_mime_type().to_string());
+                if let Ok((w, h)) = reader.into_dimensions() {
+                    meta.width = Some(w);
+                    meta.height = Some(h);
+                }
+            }
+        }
+        MediaType::Text => meta.mime = Some("text/plain".to_string()),
+        MediaType::Audio => {
+            if let Ok(audio) = decode_wav(bytes) {
+                meta.mime = Some("audio/wav".to_string());
+                meta.duration_ms = Some(audio.duration_ms());
+            }
+        }
+        MediaType::Video => {
+            if let Ok(info) = probe_container(bytes) {
+                meta.mime = Some(info.mime().to_string());
+                meta.duration_ms = info.duration_ms;
+            }
+        }
     }
+    meta
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use pru_core::PruStore;
-    use pru_detectors_api::{ImageMetadataDetector, TextComplexityDetector};
+    use pru_detectors_api::{
+        AudioSpectralDetector, ImageMetadataDetector, MediaDetector, RegistryConfig,
+        TextComplexityConfig, TextComplexityDetector,
+    };
+    use pru_media_schema::FeatureValue;
     use std::sync::{Arc, Mutex};
+    use std::time::Duration;
     use tempfile::tempdir;
 
     #[test]
@@ -74,15 +878,41 @@ mod tests {
         let store = PruStore::open(dir.path()).unwrap();
         let registry = {
             let mut r = DetectorRegistry::new();
-            r.register(Arc::new(TextComplexityDetector));
+            r.register(Arc::new(TextComplexityDetector::default()))
+                .unwrap();
             r
         };
-        let ctx = IngestContext {
-            pru: Arc::new(Mutex::new(store)),
-            detectors: registry,
-        };
+        let ctx = IngestContext::new(Arc::new(Mutex::new(store)), registry);
         let result = ctx.ingest_text("hello world").unwrap();
         assert!(result.media_id.0 > 0);
+        assert!(result.was_new);
+        assert_eq!(result.media_type, MediaType::Text);
+        assert_eq!(result.hash, hash_bytes(b"hello world"));
+        let run = &result.outcomes[0];
+        assert_eq!(run.status, DetectorStatus::Succeeded);
+        let output = run.output.as_ref().unwrap();
+        assert!((0.0..=1.0).contains(&output.score_ai));
+        assert!(!ctx.ingest_text("hello world").unwrap().was_new);
+
+        let features = pru_media_schema::get_features(&ctx.pru, result.media_id, None).unwrap();
+        assert!(features
+            .iter()
+            .any(|(_, name, value)| name == "avg_len" && matches!(value, FeatureValue::F64(_))));
+
+        let store = ctx.pru.lock().unwrap();
+        let pred = store
+            .get_predicate_id(pru_media_schema::PRED_DETECTOR_DETAILS)
+            .unwrap();
+        let facts = store
+            .facts_for_subject_predicate(result.media_id.0, pred)
+            .unwrap();
+        assert_eq!(facts.len(), 1);
+        let source = facts[0].source.unwrap();
+        assert_eq!(
+            store.get_entity_name(source).as_deref(),
+            Some(TextComplexityDetector::default().id().as_str())
+        );
+        assert!(!store.get_literal_value(facts[0].object).unwrap().is_empty());
     }
 
     #[test]
@@ -91,13 +921,11 @@ mod tests {
         let store = PruStore::open(dir.path()).unwrap();
         let registry = {
             let mut r = DetectorRegistry::new();
-            r.register(Arc::new(ImageMetadataDetector));
+            r.register(Arc::new(ImageMetadataDetector::default()))
+                .unwrap();
             r
         };
-        let ctx = IngestContext {
-            pru: Arc::new(Mutex::new(store)),
-            detectors: registry,
-        };
+        let ctx = IngestContext::new(Arc::new(Mutex::new(store)), registry);
         let img = image::RgbaImage::from_pixel(2, 2, image::Rgba([0, 0, 0, 0]));
         let mut buf = Vec::new();
         let mut cursor = std::io::Cursor::new(&mut buf);
@@ -106,5 +934,752 @@ mod tests {
             .unwrap();
         let result = ctx.ingest_image(&buf).unwrap();
         assert!(result.media_id.0 > 0);
+
+        let meta = pru_media_schema::get_media_metadata(&ctx.pru, result.media_id).unwrap();
+        assert_eq!(meta.byte_len, Some(buf.len() as u64));
+        assert_eq!((meta.width, meta.height), (Some(2), Some(2)));
+        assert_eq!(meta.mime.as_deref(), Some("image/png"));
+    }
+
+    #[test]
+    fn stored_originals_round_trip() {
+        let dir = tempdir().unwrap();
+        let media_root = dir.path().join("media");
+        let mut ctx = IngestContext::new(
+            Arc::new(Mutex::new(PruStore::open(dir.path()).unwrap())),
+            DetectorRegistry::new(),
+        )
+        .with_storage(MediaStorage::new(&media_root));
+        let img = image::RgbaImage::from_pixel(2, 2, image::Rgba([9, 9, 9, 255]));
+        let mut png = Vec::new();
+        image::DynamicImage::ImageRgba8(img)
+            .write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png)
+            .unwrap();
+
+        let image = ctx.ingest_image(&png).unwrap();
+        let text = ctx.ingest_text("kept for later").unwrap();
+        let expected = format!("{}.png", hash_bytes(&png));
+        assert_eq!(
+            get_stored_at(&ctx.pru, image.media_id).unwrap(),
+            Some(expected.clone())
+        );
+        assert_eq!(std::fs::read(media_root.join(&expected)).unwrap(), png);
+        // Ingesting the same bytes again neither re-reads nor rewrites the stored
+        // file: a stand-in written over it is left alone.
+        let stored = media_root.join(&expected);
+        std::fs::write(&stored, b"stand-in").unwrap();
+        ctx.ingest_image(&png).unwrap();
+        assert_eq!(std::fs::read(&stored).unwrap(), b"stand-in");
+        std::fs::write(&stored, &png).unwrap();
+
+        let storage = ctx.storage.take().unwrap();
+        assert_eq!(
+            load_original(&ctx.pru, &storage, image.media_id).unwrap(),
+            Some(png)
+        );
+        assert_eq!(
+            load_original(&ctx.pru, &storage, text.media_id).unwrap(),
+            Some(b"kept for later".to_vec())
+        );
+
+        // Without storage nothing is recorded.
+        let other = ctx.ingest_text("not kept").unwrap();
+        assert_eq!(
+            load_original(&ctx.pru, &storage, other.media_id).unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn submission_context_is_recorded() {
+        let dir = tempdir().unwrap();
+        let calls = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let mut registry = DetectorRegistry::new();
+        registry
+            .register(Arc::new(CountingTextDetector(calls.clone())))
+            .unwrap();
+        let ctx = IngestContext::new(
+            Arc::new(Mutex::new(PruStore::open(dir.path()).unwrap())),
+            registry,
+        );
+        let options = IngestOptions {
+            source_url: Some("https://user@Old.Reddit.com:443/r/pics/comments/abc".into()),
+            reporter: Some("mod_y".into()),
+            observed_at: Some(1_700_000_000),
+            tags: vec!["reddit".into(), "reported".into()],
+            ..Default::default()
+        };
+        let first = ctx
+            .ingest_text_with_options("seen somewhere", &options)
+            .unwrap();
+
+        let sightings = pru_media_schema::get_sightings(&ctx.pru, first.media_id).unwrap();
+        assert_eq!(sightings.len(), 1);
+        assert_eq!(sightings[0].source, "old.reddit.com");
+        assert_eq!(sightings[0].url, options.source_url);
+        assert_eq!(sightings[0].observed_at, 1_700_000_000);
+        assert_eq!(
+            pru_media_schema::get_reporters(&ctx.pru, first.media_id).unwrap(),
+            vec![("mod_y".to_string(), 1_700_000_000)]
+        );
+        let mut tags = pru_media_schema::get_tags(&ctx.pru, first.media_id).unwrap();
+        tags.sort();
+        assert_eq!(tags, vec!["reddit", "reported"]);
+
+        // A cached re-submission from elsewhere still adds its sighting.
+        let again = IngestOptions {
+            source_name: Some("forum".into()),
+            observed_at: Some(1_700_000_500),
+            ..Default::default()
+        };
+        let second = ctx
+            .ingest_text_with_options("seen somewhere", &again)
+            .unwrap();
+        assert_eq!(second.cached(), vec!["detector:text:counting"]);
+        let sightings = pru_media_schema::get_sightings(&ctx.pru, first.media_id).unwrap();
+        let seen: Vec<_> = sightings
+            .iter()
+            .map(|s| (s.source.as_str(), s.observed_at))
+            .collect();
+        assert_eq!(
+            seen,
+            vec![("old.reddit.com", 1_700_000_000), ("forum", 1_700_000_500)]
+        );
+        assert_eq!(url_host("http://[::1]:8080/x").as_deref(), Some("::1"));
+        assert_eq!(
+            url_host("https://user:pw@Old.Reddit.com:443/r?x=@evil.example").as_deref(),
+            Some("old.reddit.com")
+        );
+        assert_eq!(url_host("not a url"), None);
+    }
+
+    #[test]
+    fn reingest_weights_detector_once() {
+        let dir = tempdir().unwrap();
+        let store = PruStore::open(dir.path()).unwrap();
+        let registry = {
+            let mut r = DetectorRegistry::new();
+            r.register(Arc::new(TextComplexityDetector::default()))
+                .unwrap();
+            r
+        };
+        let ctx = IngestContext::new(Arc::new(Mutex::new(store)), registry);
+        let first = ctx.ingest_text("the same text again").unwrap();
+        let second = ctx.ingest_text("the same text again").unwrap();
+        assert_eq!(first.media_id, second.media_id);
+
+        let engine = pru_truth_engine::TruthEngine::new(Default::default());
+        let report = engine.evaluate_media(&ctx.pru, second.media_id).unwrap();
+        assert_eq!(report.explanations.len(), 1);
+    }
+
+    #[test]
+    fn detector_threshold_override_flips_label() {
+        let text = "it is it is it is it is";
+        let label_with = |config: &RegistryConfig| {
+            let dir = tempdir().unwrap();
+            let ctx = IngestContext::new(
+                Arc::new(Mutex::new(PruStore::open(dir.path()).unwrap())),
+                DetectorRegistry::from_config(config).unwrap(),
+            );
+            let media = ctx.ingest_text(text).unwrap().media_id;
+            let scores = pru_media_schema::get_detector_scores_for_media(&ctx.pru, media).unwrap();
+            scores[0].2.clone()
+        };
+
+        assert_eq!(label_with(&RegistryConfig::default()), "ai");
+        let with_labels = |ai_threshold, human_threshold| RegistryConfig {
+            text_complexity: TextComplexityConfig {
+                ai_threshold,
+                human_threshold,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        assert_eq!(label_with(&with_labels(0.95, 0.45)), "unknown");
+        assert_eq!(label_with(&with_labels(0.95, 0.9)), "human");
+    }
+
+    struct SlowImageDetector;
+
+    impl pru_detectors_api::MediaDetector for SlowImageDetector {
+        fn id(&self) -> String {
+            "detector:image:slow".to_string()
+        }
+
+        fn kind(&self) -> pru_detectors_api::DetectorMediaKind {
+            pru_detectors_api::DetectorMediaKind::Image
+        }
+
+        fn detect(&self, _bytes: &[u8]) -> anyhow::Result<DetectorOutput> {
+            std::thread::sleep(std::time::Duration::from_millis(600));
+            Ok(DetectorOutput {
+                score_ai: 0.5,
+                label: pru_detectors_api::DetectorLabel::Unknown,
+                details: None,
+                features: Default::default(),
+            })
+        }
+    }
+
+    #[test]
+    fn slow_detector_does_not_starve_async_ingest() {
+        let dir = tempdir().unwrap();
+        let registry = {
+            let mut r = DetectorRegistry::new();
+            r.register(Arc::new(SlowImageDetector)).unwrap();
+            r.register(Arc::new(TextComplexityDetector::default()))
+                .unwrap();
+            r
+        };
+        let ctx = IngestContext::new(
+            Arc::new(Mutex::new(PruStore::open(dir.path()).unwrap())),
+            registry,
+        );
+        // One worker: a detector blocking it would stall every other task.
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(1)
+            .enable_all()
+            .build()
+            .unwrap();
+        runtime.block_on(async {
+            let slow_ctx = ctx.clone();
+            let started = std::time::Instant::now();
+            let slow = tokio::spawn(async move { slow_ctx.ingest_image_async(b"img").await });
+            tokio::task::yield_now().await;
+            for i in 0..5 {
+                ctx.ingest_text_async(&format!("quick text number {i}"))
+                    .await
+                    .unwrap();
+            }
+            let quick_elapsed = started.elapsed();
+            slow.await.unwrap().unwrap();
+            let slow_elapsed = started.elapsed();
+            assert!(quick_elapsed < std::time::Duration::from_millis(400));
+            assert!(slow_elapsed >= std::time::Duration::from_millis(600));
+        });
+    }
+
+    struct PanickingTextDetector;
+
+    impl pru_detectors_api::MediaDetector for PanickingTextDetector {
+        fn id(&self) -> String {
+            "detector:text:panics".to_string()
+        }
+
+        fn kind(&self) -> pru_detectors_api::DetectorMediaKind {
+            pru_detectors_api::DetectorMediaKind::Text
+        }
+
+        fn detect(&self, _bytes: &[u8]) -> anyhow::Result<DetectorOutput> {
+            panic!("weird input")
+        }
+    }
+
+    struct SleepyTextDetector;
+
+    impl pru_detectors_api::MediaDetector for SleepyTextDetector {
+        fn id(&self) -> String {
+            "detector:text:sleepy".to_string()
+        }
+
+        fn kind(&self) -> pru_detectors_api::DetectorMediaKind {
+            pru_detectors_api::DetectorMediaKind::Text
+        }
+
+        fn detect(&self, _bytes: &[u8]) -> anyhow::Result<DetectorOutput> {
+            std::thread::sleep(std::time::Duration::from_secs(2));
+            anyhow::bail!("should have timed out")
+        }
+    }
+
+    fn isolation_context(dir: &std::path::Path) -> IngestContext {
+        let mut registry = DetectorRegistry::new();
+        registry.register(Arc::new(PanickingTextDetector)).unwrap();
+        registry.register(Arc::new(SleepyTextDetector)).unwrap();
+        registry
+            .register(Arc::new(TextComplexityDetector::default()))
+            .unwrap();
+        registry.set_timeout("detector:text:sleepy", Duration::from_millis(100));
+        IngestContext::new(Arc::new(Mutex::new(PruStore::open(dir).unwrap())), registry)
+    }
+
+    fn assert_isolated(ctx: &IngestContext, result: &IngestResult) {
+        assert_eq!(result.succeeded(), vec!["detector:text:complexity_v1"]);
+        assert_eq!(result.failed(), vec!["detector:text:panics"]);
+        assert_eq!(result.timed_out(), vec!["detector:text:sleepy"]);
+        let sleepy = result
+            .outcomes
+            .iter()
+            .find(|o| o.detector == "detector:text:sleepy")
+            .unwrap();
+        assert!(sleepy.duration_ms >= 100, "{}", sleepy.duration_ms);
+        assert!(sleepy.output.is_none());
+        let scores =
+            pru_media_schema::get_detector_scores_for_media(&ctx.pru, result.media_id).unwrap();
+        assert_eq!(scores.len(), 1);
+        let errors = pru_media_schema::get_analysis_errors(&ctx.pru, result.media_id).unwrap();
+        assert_eq!(errors.len(), 2);
+        assert!(errors.iter().any(|(_, m)| m.contains("weird input")));
+        assert!(errors.iter().any(|(_, m)| m.starts_with("timed out")));
+    }
+
+    #[test]
+    fn misbehaving_detectors_do_not_abort_ingest() {
+        let dir = tempdir().unwrap();
+        let ctx = isolation_context(dir.path());
+        let result = ctx.ingest_text("some ordinary words here").unwrap();
+        assert_isolated(&ctx, &result);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn misbehaving_detectors_do_not_abort_async_ingest() {
+        let dir = tempdir().unwrap();
+        let ctx = isolation_context(dir.path());
+        let result = ctx
+            .ingest_text_async("some ordinary words here")
+            .await
+            .unwrap();
+        assert_isolated(&ctx, &result);
+    }
+
+    #[test]
+    fn disabled_detector_writes_no_facts() {
+        let dir = tempdir().unwrap();
+        let mut ctx = isolation_context(dir.path());
+        assert!(ctx.detectors.unregister("detector:text:sleepy"));
+        assert!(!ctx.detectors.unregister("detector:text:sleepy"));
+        assert!(ctx.detectors.set_enabled("detector:text:panics", false));
+        assert!(!ctx.detectors.set_enabled("detector:text:missing", false));
+        assert!(ctx.detectors.get("detector:text:panics").is_some());
+        assert_eq!(
+            ctx.detectors.ids(),
+            vec![
+                (
+                    "detector:text:panics".to_string(),
+                    pru_detectors_api::DetectorMediaKind::Text,
+                    false
+                ),
+                (
+                    "detector:text:complexity_v1".to_string(),
+                    pru_detectors_api::DetectorMediaKind::Text,
+                    true
+                ),
+            ]
+        );
+
+        let result = ctx.ingest_text("some ordinary words here").unwrap();
+        assert_eq!(result.succeeded(), vec!["detector:text:complexity_v1"]);
+        assert!(result.failed().is_empty());
+        assert!(
+            pru_media_schema::get_analysis_errors(&ctx.pru, result.media_id)
+                .unwrap()
+                .is_empty()
+        );
+        let guard = ctx.pru.lock().unwrap();
+        assert!(guard.get_entity_id("detector:text:panics").is_none());
+    }
+
+    /// Holds every detector that reaches it until all `expected` have, so
+    /// detectors run one after another never all meet.
+    struct Rendezvous {
+        expected: usize,
+        arrived: Mutex<usize>,
+        all_here: std::sync::Condvar,
+        /// Detectors that gave up waiting for the others.
+        stranded: std::sync::atomic::AtomicUsize,
+    }
+
+    impl Rendezvous {
+        fn new(expected: usize) -> Arc<Self> {
+            Arc::new(Self {
+                expected,
+                arrived: Mutex::new(0),
+                all_here: std::sync::Condvar::new(),
+                stranded: Default::default(),
+            })
+        }
+
+        fn meet(&self) {
+            let mut arrived = self.arrived.lock().unwrap();
+            *arrived += 1;
+            self.all_here.notify_all();
+            let (_arrived, wait) = self
+                .all_here
+                .wait_timeout_while(arrived, Duration::from_secs(5), |n| *n < self.expected)
+                .unwrap();
+            if wait.timed_out() {
+                self.stranded
+                    .fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            }
+        }
+
+        fn all_met(&self) -> bool {
+            *self.arrived.lock().unwrap() == self.expected
+                && self.stranded.load(std::sync::atomic::Ordering::SeqCst) == 0
+        }
+    }
+
+    struct MeetingTextDetector(&'static str, Arc<Rendezvous>);
+
+    impl pru_detectors_api::MediaDetector for MeetingTextDetector {
+        fn id(&self) -> String {
+            format!("detector:text:meet_{}", self.0)
+        }
+
+        fn kind(&self) -> pru_detectors_api::DetectorMediaKind {
+            pru_detectors_api::DetectorMediaKind::Text
+        }
+
+        fn detect(&self, _bytes: &[u8]) -> anyhow::Result<DetectorOutput> {
+            self.1.meet();
+            Ok(DetectorOutput {
+                score_ai: 0.5,
+                label: pru_detectors_api::DetectorLabel::Unknown,
+                details: None,
+                features: Default::default(),
+            })
+        }
+    }
+
+    #[test]
+    fn detectors_run_concurrently_with_ordered_facts() {
+        let dir = tempdir().unwrap();
+        let rendezvous = Rendezvous::new(3);
+        let mut registry = DetectorRegistry::new();
+        for name in ["c", "a", "b"] {
+            registry
+                .register(Arc::new(MeetingTextDetector(name, rendezvous.clone())))
+                .unwrap();
+        }
+        let ctx = IngestContext::new(
+            Arc::new(Mutex::new(PruStore::open(dir.path()).unwrap())),
+            registry,
+        );
+
+        let result = ctx.ingest_text("three meeting detectors").unwrap();
+        assert!(rendezvous.all_met());
+        assert_eq!(
+            result.succeeded(),
+            vec![
+                "detector:text:meet_a",
+                "detector:text:meet_b",
+                "detector:text:meet_c"
+            ]
+        );
+
+        let guard = ctx.pru.lock().unwrap();
+        let pred = guard
+            .get_predicate_id(pru_media_schema::PRED_DETECTOR_SCORE)
+            .unwrap();
+        let sources: Vec<_> = guard
+            .facts_for_subject_predicate(result.media_id.0, pred)
+            .unwrap()
+            .iter()
+            .map(|f| guard.get_entity_name(f.source.unwrap()).unwrap())
+            .collect();
+        assert_eq!(
+            sources,
+            vec![
+                "detector:text:meet_a",
+                "detector:text:meet_b",
+                "detector:text:meet_c"
+            ]
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn async_detectors_run_concurrently() {
+        let dir = tempdir().unwrap();
+        let rendezvous = Rendezvous::new(3);
+        let mut registry = DetectorRegistry::new();
+        for name in ["a", "b", "c"] {
+            registry
+                .register(Arc::new(MeetingTextDetector(name, rendezvous.clone())))
+                .unwrap();
+        }
+        let ctx = IngestContext::new(
+            Arc::new(Mutex::new(PruStore::open(dir.path()).unwrap())),
+            registry,
+        );
+
+        let result = ctx
+            .ingest_text_async("three meeting detectors")
+            .await
+            .unwrap();
+        assert!(rendezvous.all_met());
+        assert_eq!(result.succeeded().len(), 3);
+    }
+
+    #[test]
+    fn brightened_copy_is_linked_to_original() {
+        let dir = tempdir().unwrap();
+        let mut registry = DetectorRegistry::new();
+        registry
+            .register(Arc::new(pru_detectors_api::PHashDetector::default()))
+            .unwrap();
+        let ctx = IngestContext::new(
+            Arc::new(Mutex::new(PruStore::open(dir.path()).unwrap())),
+            registry,
+        );
+        let encode = |lift: u8| {
+            let img = image::RgbImage::from_fn(64, 64, |x, y| {
+                let base = [((x * 7 + y * 3) % 200) as u8, (y * 4) as u8, 90];
+                image::Rgb(base.map(|c| c.saturating_add(lift)))
+            });
+            let mut buf = Vec::new();
+            image::DynamicImage::ImageRgb8(img)
+                .write_to(&mut std::io::Cursor::new(&mut buf), image::ImageFormat::Png)
+                .unwrap();
+            buf
+        };
+
+        let original = ctx.ingest_image(&encode(0)).unwrap().media_id;
+        pru_media_schema::add_human_verdict(&ctx.pru, original, "ai").unwrap();
+        let copy = ctx.ingest_image(&encode(25)).unwrap().media_id;
+        assert_ne!(original, copy);
+
+        let similar = pru_media_schema::get_similar_media(&ctx.pru, copy).unwrap();
+        assert_eq!(similar.len(), 1);
+        assert_eq!(similar[0].0, original);
+        assert!(similar[0].1 > 0.9);
+        let scores = pru_media_schema::get_detector_scores_for_media(&ctx.pru, copy).unwrap();
+        assert_eq!(scores[0].2, "ai");
+    }
+
+    fn wav_16bit(samples: &[f32], sample_rate: u32) -> Vec<u8> {
+        let data_len = (samples.len() * 2) as u32;
+        let mut out = Vec::new();
+        out.extend_from_slice(b"RIFF");
+        out.extend_from_slice(&(36 + data_len).to_le_bytes());
+        out.extend_from_slice(b"WAVEfmt ");
+        out.extend_from_slice(&16u32.to_le_bytes());
+        out.extend_from_slice(&1u16.to_le_bytes());
+        out.extend_from_slice(&1u16.to_le_bytes());
+        out.extend_from_slice(&sample_rate.to_le_bytes());
+        out.extend_from_slice(&(sample_rate * 2).to_le_bytes());
+        out.extend_from_slice(&2u16.to_le_bytes());
+        out.extend_from_slice(&16u16.to_le_bytes());
+        out.extend_from_slice(b"data");
+        out.extend_from_slice(&data_len.to_le_bytes());
+        for s in samples {
+            out.extend_from_slice(&((s.clamp(-1.0, 1.0) * 32_767.0) as i16).to_le_bytes());
+        }
+        out
+    }
+
+    #[test]
+    fn ingest_auto_picks_type_from_content() {
+        let dir = tempdir().unwrap();
+        let ctx = IngestContext::new(
+            Arc::new(Mutex::new(PruStore::open(dir.path()).unwrap())),
+            DetectorRegistry::new(),
+        );
+        let encode = |format| {
+            let img = image::RgbImage::from_pixel(4, 4, image::Rgb([200, 10, 10]));
+            let mut buf = Vec::new();
+            image::DynamicImage::ImageRgb8(img)
+                .write_to(&mut std::io::Cursor::new(&mut buf), format)
+                .unwrap();
+            buf
+        };
+        let cases = [
+            (encode(image::ImageFormat::Png), MediaType::Image),
+            (encode(image::ImageFormat::Jpeg), MediaType::Image),
+            (wav_16bit(&[0.0, 0.5, -0.5, 0.0], 8_000), MediaType::Audio),
+            (
+                "Ordinary UTF-8 prose, café.".as_bytes().to_vec(),
+                MediaType::Text,
+            ),
+        ];
+        for (bytes, expected) in cases {
+            let result = ctx.ingest_auto(&bytes).unwrap();
+            assert_eq!(
+                pru_media_schema::get_media_type(&ctx.pru, result.media_id).unwrap(),
+                Some(expected)
+            );
+        }
+
+        // Deterministic noise with no signature and invalid UTF-8.
+        let noise: Vec<u8> = (0u32..512)
+            .map(|i| (i.wrapping_mul(2_654_435_761) >> 13) as u8 | 0x80)
+            .collect();
+        let before = ctx.pru.lock().unwrap().fact_count();
+        let Err(err) = ctx.ingest_auto(&noise) else {
+            panic!("noise should not be ingested");
+        };
+        assert!(
+            err.to_string().contains("512 bytes match no known"),
+            "{err}"
+        );
+        let Err(err) = ctx.ingest_auto(b"") else {
+            panic!("empty input should not be ingested");
+        };
+        assert!(err.to_string().contains("empty"), "{err}");
+        assert_eq!(ctx.pru.lock().unwrap().fact_count(), before);
+    }
+
+    #[test]
+    fn ingest_audio_separates_tone_from_noise() {
+        let dir = tempdir().unwrap();
+        let store = PruStore::open(dir.path()).unwrap();
+        let registry = {
+            let mut r = DetectorRegistry::new();
+            r.register(Arc::new(AudioSpectralDetector::default()))
+                .unwrap();
+            r
+        };
+        let ctx = IngestContext::new(Arc::new(Mutex::new(store)), registry);
+        let rate = 16_000;
+        let sine: Vec<f32> = (0..rate)
+            .map(|i| 0.5 * (2.0 * std::f32::consts::PI * 440.0 * i as f32 / rate as f32).sin())
+            .collect();
+        let mut seed = 0x2545_f491_u32;
+        let noise: Vec<f32> = (0..rate)
+            .map(|_| {
+                seed = seed.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+                (seed >> 8) as f32 / (1u32 << 24) as f32 - 0.5
+            })
+            .collect();
+
+        let tone = ctx.ingest_audio(&wav_16bit(&sine, rate)).unwrap();
+        let hiss = ctx.ingest_audio(&wav_16bit(&noise, rate)).unwrap();
+        let tone_scores =
+            pru_media_schema::get_detector_scores_for_media(&ctx.pru, tone.media_id).unwrap();
+        let hiss_scores =
+            pru_media_schema::get_detector_scores_for_media(&ctx.pru, hiss.media_id).unwrap();
+        assert_eq!(tone_scores[0].2, "ai");
+        // Steady hiss scores mid-range, inside the unknown band.
+        assert_eq!(hiss_scores[0].2, "unknown");
+        assert!(tone_scores[0].1 > hiss_scores[0].1);
+
+        let meta = pru_media_schema::get_media_metadata(&ctx.pru, tone.media_id).unwrap();
+        assert_eq!(meta.duration_ms, Some(1000));
+        assert_eq!(meta.mime.as_deref(), Some("audio/wav"));
+    }
+
+    struct CountingTextDetector(Arc<std::sync::atomic::AtomicUsize>);
+
+    impl pru_detectors_api::MediaDetector for CountingTextDetector {
+        fn id(&self) -> String {
+            "detector:text:counting".to_string()
+        }
+
+        fn kind(&self) -> pru_detectors_api::DetectorMediaKind {
+            pru_detectors_api::DetectorMediaKind::Text
+        }
+
+        fn detect(&self, _bytes: &[u8]) -> anyhow::Result<DetectorOutput> {
+            self.0.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            Ok(DetectorOutput {
+                score_ai: 0.3,
+                label: pru_detectors_api::DetectorLabel::Human,
+                details: None,
+                features: Default::default(),
+            })
+        }
+    }
+
+    #[test]
+    fn reingest_of_identical_text_uses_cached_results() {
+        let dir = tempdir().unwrap();
+        let calls = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let mut registry = DetectorRegistry::new();
+        registry
+            .register(Arc::new(CountingTextDetector(calls.clone())))
+            .unwrap();
+        let mut ctx = IngestContext::new(
+            Arc::new(Mutex::new(PruStore::open(dir.path()).unwrap())),
+            registry,
+        );
+        let count = || calls.load(std::sync::atomic::Ordering::SeqCst);
+        let facts = |ctx: &IngestContext| ctx.pru.lock().unwrap().fact_count();
+
+        let first = ctx.ingest_text("same words twice").unwrap();
+        assert_eq!(first.succeeded(), vec!["detector:text:counting"]);
+        assert_eq!(count(), 1);
+        assert_eq!(ctx.cache.len(), 1);
+        let stored = facts(&ctx);
+
+        let second = ctx.ingest_text("same words twice").unwrap();
+        assert_eq!(second.cached(), vec!["detector:text:counting"]);
+        assert!(second.outcomes[0].output.is_none());
+        assert_eq!(second.outcomes[0].duration_ms, 0);
+        assert!(second.succeeded().is_empty());
+        assert_eq!(count(), 1);
+        assert_eq!(facts(&ctx), stored);
+
+        // A cold in-memory cache still finds the stored score.
+        ctx.cache = DetectorCache::default();
+        ctx.ingest_text("same words twice").unwrap();
+        assert_eq!(count(), 1);
+
+        // Clearing the stored results also drops the cache entry.
+        assert!(
+            ctx.clear_detector_results(first.media_id, "detector:text:counting")
+                .unwrap()
+                > 0
+        );
+        let rerun = ctx.ingest_text("same words twice").unwrap();
+        assert_eq!(rerun.succeeded(), vec!["detector:text:counting"]);
+        assert_eq!(count(), 2);
+
+        ctx.force = true;
+        let forced = ctx.ingest_text("same words twice").unwrap();
+        assert_eq!(forced.succeeded(), vec!["detector:text:counting"]);
+        assert_eq!(count(), 3);
+    }
+
+    #[test]
+    fn inputs_over_the_limits_are_refused_before_anything_is_stored() {
+        let dir = tempdir().unwrap();
+        let calls = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let mut registry = DetectorRegistry::new();
+        registry
+            .register(Arc::new(CountingTextDetector(calls.clone())))
+            .unwrap();
+        let ctx = IngestContext::new(
+            Arc::new(Mutex::new(PruStore::open(dir.path()).unwrap())),
+            registry,
+        )
+        .with_limits(IngestLimits {
+            max_text_bytes: 64,
+            max_text_chars: 10,
+            ..Default::default()
+        });
+        let facts = ctx.pru.lock().unwrap().fact_count();
+
+        let Err(err) = ctx.ingest_text(&"x".repeat(65)) else {
+            panic!("oversize text was ingested");
+        };
+        assert!(matches!(
+            err.downcast_ref::<IngestError>(),
+            Some(IngestError::TooLarge { unit: "bytes", .. })
+        ));
+        let Err(err) = ctx.ingest_text("eleven char") else {
+            panic!("overlong text was ingested");
+        };
+        assert!(matches!(
+            err.downcast_ref::<IngestError>(),
+            Some(IngestError::TooLarge {
+                unit: "characters",
+                actual: 11,
+                limit: 10,
+                ..
+            })
+        ));
+        let Err(err) =
+            ctx.ingest_generic(&[0xC3, 0x28], MediaType::Text, &IngestOptions::default())
+        else {
+            panic!("invalid UTF-8 was ingested");
+        };
+        assert!(matches!(
+            err.downcast_ref::<IngestError>(),
+            Some(IngestError::Invalid { .. })
+        ));
+        assert_eq!(calls.load(std::sync::atomic::Ordering::SeqCst), 0);
+        assert_eq!(ctx.pru.lock().unwrap().fact_count(), facts);
+
+        assert!(ctx.ingest_text("ten chars.").is_ok());
     }
 }
@@ -0,0 +1,211 @@
+//! Size limits and cheap well-formedness checks applied before any detector runs.
+
+use pru_media_schema::MediaType;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct IngestLimits {
+    pub max_image_bytes: usize,
+    pub max_text_bytes: usize,
+    pub max_audio_bytes: usize,
+    pub max_video_bytes: usize,
+    /// Width times height, read from the image header before anything is decoded.
+    pub max_image_pixels: u64,
+    pub max_text_chars: usize,
+}
+
+impl Default for IngestLimits {
+    fn default() -> Self {
+        Self {
+            max_image_bytes: 50 * 1024 * 1024,
+            max_text_bytes: 4 * 1024 * 1024,
+            max_audio_bytes: 200 * 1024 * 1024,
+            max_video_bytes: 1024 * 1024 * 1024,
+            max_image_pixels: 100_000_000,
+            max_text_chars: 1_000_000,
+        }
+    }
+}
+
+impl IngestLimits {
+    pub fn max_bytes(&self, media_type: MediaType) -> usize {
+        match media_type {
+            MediaType::Image => self.max_image_bytes,
+            MediaType::Text => self.max_text_bytes,
+            MediaType::Audio => self.max_audio_bytes,
+            MediaType::Video => self.max_video_bytes,
+        }
+    }
+
+    /// The most any single upload may be, whatever its type.
+    pub fn largest_upload(&self) -> usize {
+        [
+            MediaType::Image,
+            MediaType::Text,
+            MediaType::Audio,
+            MediaType::Video,
+        ]
+        .map(|t| self.max_bytes(t))
+        .into_iter()
+        .max()
+        .unwrap_or(0)
+    }
+
+    /// Reject `bytes` if they exceed a limit for `media_type` or are visibly
+    /// malformed. Images only have their header read; an unrecognised format is
+    /// let through for the detectors to judge.
+    pub fn check(&self, bytes: &[u8], media_type: MediaType) -> Result<(), IngestError> {
+        let too_large = |unit, actual: u64, limit: u64| IngestError::TooLarge {
+            media_type,
+            unit,
+            actual,
+            limit,
+        };
+        let max_bytes = self.max_bytes(media_type);
+        if bytes.len() > max_bytes {
+            return Err(too_large("bytes", bytes.len() as u64, max_bytes as u64));
+        }
+        match media_type {
+            MediaType::Image => {
+                let Ok(reader) =
+                    image::ImageReader::new(std::io::Cursor::new(bytes)).with_guessed_format()
+                else {
+                    return Ok(());
+                };
+                let Some(format) = reader.format() else {
+                    return Ok(());
+                };
+                let (width, height) =
+                    reader.into_dimensions().map_err(|e| IngestError::Invalid {
+                        media_type,
+                        reason: format!("unreadable {format:?} header: {e}"),
+                    })?;
+                let pixels = u64::from(width) * u64::from(height);
+                if pixels == 0 {
+                    return Err(IngestError::Invalid {
+                        media_type,
+                        reason: format!("image is {width}x{height}"),
+                    });
+                }
+                if pixels > self.max_image_pixels {
+                    return Err(too_large("pixels", pixels, self.max_image_pixels));
+                }
+            }
+            MediaType::Text => {
+                let text = std::str::from_utf8(bytes).map_err(|e| IngestError::Invalid {
+                    media_type,
+                    reason: format!("text is not valid UTF-8: {e}"),
+                })?;
+                let chars = text.chars().count();
+                if chars > self.max_text_chars {
+                    return Err(too_large(
+                        "characters",
+                        chars as u64,
+                        self.max_text_chars as u64,
+                    ));
+                }
+            }
+            MediaType::Audio | MediaType::Video => {}
+        }
+        Ok(())
+    }
+}
+
+/// Input refused before ingest. Callers can `downcast_ref` the `anyhow::Error`
+/// returned by the ingest methods to tell these apart from store or I/O failures.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum IngestError {
+    /// Over one of the [`IngestLimits`]; `unit` says which ("bytes", "pixels" or
+    /// "characters").
+    TooLarge {
+        media_type: MediaType,
+        unit: &'static str,
+        actual: u64,
+        limit: u64,
+    },
+    /// Not well-formed for its media type.
+    Invalid {
+        media_type: MediaType,
+        reason: String,
+    },
+}
+
+impl std::fmt::Display for IngestError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            IngestError::TooLarge {
+                media_type,
+                unit,
+                actual,
+                limit,
+            } => write!(
+                f,
+                "{media_type:?} input has {actual} {unit}, over the limit of {limit}"
+            ),
+            IngestError::Invalid { media_type, reason } => {
+                write!(f, "invalid {media_type:?} input: {reason}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for IngestError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn png(width: u32, height: u32) -> Vec<u8> {
+        let mut buf = Vec::new();
+        image::DynamicImage::ImageLuma8(image::GrayImage::new(width, height))
+            .write_to(&mut std::io::Cursor::new(&mut buf), image::ImageFormat::Png)
+            .unwrap();
+        buf
+    }
+
+    #[test]
+    fn oversize_and_malformed_inputs_are_typed() {
+        let limits = IngestLimits {
+            max_image_bytes: 4096,
+            max_image_pixels: 100,
+            max_text_chars: 5,
+            ..Default::default()
+        };
+        assert_eq!(limits.check(&png(10, 10), MediaType::Image), Ok(()));
+        assert_eq!(
+            limits.check(&png(11, 10), MediaType::Image),
+            Err(IngestError::TooLarge {
+                media_type: MediaType::Image,
+                unit: "pixels",
+                actual: 110,
+                limit: 100,
+            })
+        );
+        assert!(matches!(
+            limits.check(&vec![0u8; 4097], MediaType::Image),
+            Err(IngestError::TooLarge {
+                unit: "bytes",
+                actual: 4097,
+                ..
+            })
+        ));
+        assert!(matches!(
+            limits.check(&png(10, 10)[..20], MediaType::Image),
+            Err(IngestError::Invalid { .. })
+        ));
+        assert_eq!(limits.check(b"img", MediaType::Image), Ok(()));
+
+        assert_eq!(limits.check("héllo".as_bytes(), MediaType::Text), Ok(()));
+        assert!(matches!(
+            limits.check(b"hello!", MediaType::Text),
+            Err(IngestError::TooLarge {
+                unit: "characters",
+                actual: 6,
+                ..
+            })
+        ));
+        assert!(matches!(
+            limits.check(&[0xC3, 0x28], MediaType::Text),
+            Err(IngestError::Invalid { .. })
+        ));
+    }
+}
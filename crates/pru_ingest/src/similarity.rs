@@ -0,0 +1,179 @@
+//! Linking near-duplicate images as they are ingested.
+
+use anyhow::Result;
+use pru_core::PruDbHandle;
+use pru_media_schema::{
+    add_similarity, ensure_detector_entity, find_phash_matches, index_phash, FeatureValue, MediaId,
+};
+
+use crate::{DetectorOutcome, DetectorStatus};
+
+/// Feature a detector reports a 64-bit perceptual hash under, as 16 hex digits.
+pub const PHASH_FEATURE: &str = "phash";
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct NearDuplicateOptions {
+    /// Index perceptual hashes reported by detectors and link close matches.
+    pub enabled: bool,
+    /// Hashes at most this many bits apart are linked with `similar_to`.
+    /// Values of 8 or more may miss matches, see [`pru_media_schema::PHASH_BANDS`].
+    pub max_distance: u32,
+}
+
+impl Default for NearDuplicateOptions {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            max_distance: 6,
+        }
+    }
+}
+
+/// Add every perceptual hash in `outcomes` to the band index and link `media` to
+/// indexed media within `options.max_distance` bits, with similarity
+/// `1 - distance / 64` and the reporting detector as the fact source. Edges that
+/// already exist are not written again.
+pub(crate) fn link_near_duplicates(
+    handle: &PruDbHandle,
+    media: MediaId,
+    outcomes: &[DetectorOutcome],
+    options: &NearDuplicateOptions,
+) -> Result<()> {
+    if !options.enabled {
+        return Ok(());
+    }
+    for outcome in outcomes {
+        if outcome.status != DetectorStatus::Succeeded {
+            continue;
+        }
+        let Some(FeatureValue::Str(hex)) = outcome
+            .output
+            .as_ref()
+            .and_then(|o| o.features.get(PHASH_FEATURE))
+        else {
+            continue;
+        };
+        let Ok(hash) = u64::from_str_radix(hex, 16) else {
+            continue;
+        };
+        // Band candidates are checked against the full hash before they are returned.
+        let matches: Vec<_> = find_phash_matches(handle, hash, options.max_distance)?
+            .into_iter()
+            .filter(|(other, _)| *other != media)
+            .collect();
+        index_phash(handle, media, hash)?;
+        if matches.is_empty() {
+            continue;
+        }
+        let detector = ensure_detector_entity(handle, &outcome.detector)?;
+        for (other, distance) in matches {
+            add_similarity(handle, media, other, 1.0 - distance as f32 / 64.0, detector)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{IngestContext, IngestOptions};
+    use pru_core::PruStore;
+    use pru_detectors_api::{DetectorRegistry, PHashDetector};
+    use pru_media_schema::get_similar_media;
+    use std::sync::{Arc, Mutex};
+    use tempfile::tempdir;
+
+    fn context(dir: &std::path::Path) -> IngestContext {
+        let mut registry = DetectorRegistry::new();
+        registry
+            .register(Arc::new(PHashDetector::default()))
+            .unwrap();
+        IngestContext::new(Arc::new(Mutex::new(PruStore::open(dir).unwrap())), registry)
+    }
+
+    fn gradient(size: u32) -> Vec<u8> {
+        let img = image::RgbImage::from_fn(size, size, |x, y| {
+            let (x, y) = (x * 64 / size, y * 64 / size);
+            image::Rgb([((x * 7 + y * 3) % 200) as u8, (y * 4) as u8, 90])
+        });
+        let mut buf = Vec::new();
+        image::DynamicImage::ImageRgb8(img)
+            .write_to(&mut std::io::Cursor::new(&mut buf), image::ImageFormat::Png)
+            .unwrap();
+        buf
+    }
+
+    #[test]
+    fn resized_copy_is_linked_and_index_survives_reopen() {
+        let dir = tempdir().unwrap();
+        let (original, resized) = {
+            let ctx = context(dir.path());
+            let original = ctx.ingest_image(&gradient(64)).unwrap().media_id;
+            let resized = ctx.ingest_image(&gradient(48)).unwrap();
+            assert!(resized.was_new);
+            let similar = get_similar_media(&ctx.pru, resized.media_id).unwrap();
+            assert_eq!(similar.len(), 1);
+            assert_eq!(similar[0].0, original);
+            assert!(similar[0].1 > 0.9);
+            (original, resized.media_id)
+        };
+
+        let ctx = context(dir.path());
+        let third = ctx.ingest_image(&gradient(96)).unwrap().media_id;
+        let mut linked: Vec<_> = get_similar_media(&ctx.pru, third)
+            .unwrap()
+            .into_iter()
+            .map(|(media, _)| media)
+            .collect();
+        linked.sort_by_key(|m| m.0);
+        let mut expected = vec![original, resized];
+        expected.sort_by_key(|m| m.0);
+        assert_eq!(linked, expected);
+
+        // Re-running the detector does not duplicate the edges.
+        let forced = ctx.clone().with_force(true);
+        forced.ingest_image(&gradient(96)).unwrap();
+        let edges = |ctx: &IngestContext| {
+            let store = ctx.pru.lock().unwrap();
+            let pred = store
+                .get_predicate_id(pru_media_schema::PRED_SIMILAR_TO)
+                .unwrap();
+            store
+                .query(pru_core::Query {
+                    subject: Some(third.0),
+                    predicate: Some(pred),
+                    ..Default::default()
+                })
+                .unwrap()
+                .len()
+        };
+        assert_eq!(edges(&ctx), 2);
+    }
+
+    #[test]
+    fn linking_can_be_disabled() {
+        let dir = tempdir().unwrap();
+        let ctx = context(dir.path());
+        let off = IngestOptions {
+            near_duplicates: NearDuplicateOptions {
+                enabled: false,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let first = ctx
+            .ingest_image_with_options(&gradient(64), &off)
+            .unwrap()
+            .media_id;
+        let second = ctx
+            .ingest_image_with_options(&gradient(48), &off)
+            .unwrap()
+            .media_id;
+        assert!(get_similar_media(&ctx.pru, second).unwrap().is_empty());
+        let store = ctx.pru.lock().unwrap();
+        assert!(store
+            .get_predicate_id(pru_media_schema::PRED_PHASH_BAND)
+            .is_none());
+        assert_ne!(first, second);
+    }
+}
@@ -0,0 +1,429 @@
+//! Ingesting content that arrives in chunks, without holding all of it in memory.
+
+use anyhow::{Context, Result};
+use memmap2::Mmap;
+use pru_media_schema::{ContentHasher, MediaType};
+use pru_storage::MediaStorage;
+use std::fs::File;
+use std::io::{BufWriter, ErrorKind, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::{
+    probe_metadata, DetectorInput, IngestContext, IngestError, IngestOptions, IngestResult,
+    RecordedMedia,
+};
+
+const CHUNK: usize = 64 * 1024;
+
+/// Content being received for ingest: feed it with [`write`](Self::write), then
+/// pass it to [`IngestContext::finish_upload`].
+///
+/// With storage configured the bytes go straight to a file under the storage root
+/// and detectors later read a memory map of it; without storage, when pre-ingest
+/// hooks need the whole input, or when storage is encrypted (so no plaintext
+/// reaches the disk), they are buffered. Dropping an unfinished upload removes
+/// its file.
+pub struct MediaUpload {
+    media_type: MediaType,
+    max_bytes: usize,
+    len: usize,
+    hasher: ContentHasher,
+    sink: Sink,
+}
+
+enum Sink {
+    Memory(Vec<u8>),
+    File {
+        path: PathBuf,
+        file: BufWriter<File>,
+    },
+}
+
+impl MediaUpload {
+    /// Append `chunk`; fails with [`IngestError::TooLarge`] once the upload
+    /// exceeds the byte limit for its media type.
+    pub fn write(&mut self, chunk: &[u8]) -> Result<()> {
+        let len = self.len + chunk.len();
+        if len > self.max_bytes {
+            return Err(IngestError::TooLarge {
+                media_type: self.media_type,
+                unit: "bytes",
+                actual: len as u64,
+                limit: self.max_bytes as u64,
+            }
+            .into());
+        }
+        self.len = len;
+        match &mut self.sink {
+            Sink::Memory(buf) => buf.extend_from_slice(chunk),
+            Sink::File { file, .. } => {
+                self.hasher.update(chunk);
+                file.write_all(chunk)?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn media_type(&self) -> MediaType {
+        self.media_type
+    }
+
+    /// Bytes written so far.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
+impl Drop for MediaUpload {
+    fn drop(&mut self) {
+        if let Sink::File { path, .. } = &self.sink {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+}
+
+/// An upload ready for detectors: still in memory, or recorded from its stored file.
+enum Prepared {
+    Buffered(Vec<u8>),
+    Stored(RecordedMedia, DetectorInput),
+}
+
+impl IngestContext {
+    pub fn begin_upload(&self, media_type: MediaType) -> Result<MediaUpload> {
+        let sink = match &self.storage {
+            Some(storage) if !self.hooks.has_pre() && storage.encryption.is_none() => {
+                let (path, file) = storage.create_incoming()?;
+                Sink::File {
+                    path,
+                    file: BufWriter::with_capacity(CHUNK, file),
+                }
+            }
+            _ => Sink::Memory(Vec::new()),
+        };
+        Ok(MediaUpload {
+            media_type,
+            max_bytes: self.limits.max_bytes(media_type),
+            len: 0,
+            hasher: ContentHasher::default(),
+            sink,
+        })
+    }
+
+    /// Ingest a finished upload as the `ingest_*` methods would ingest its bytes.
+    pub fn finish_upload(
+        &self,
+        upload: MediaUpload,
+        options: &IngestOptions,
+    ) -> Result<IngestResult> {
+        let media_type = upload.media_type;
+        match self.prepare_upload(upload)? {
+            Prepared::Buffered(bytes) => self.ingest_generic(&bytes, media_type, options),
+            Prepared::Stored(media, input) => self.analyze(media, input, options),
+        }
+    }
+
+    pub async fn finish_upload_async(
+        &self,
+        upload: MediaUpload,
+        options: &IngestOptions,
+    ) -> Result<IngestResult> {
+        let media_type = upload.media_type;
+        match self.prepare_upload(upload)? {
+            Prepared::Buffered(bytes) => {
+                self.ingest_generic_async(&bytes, media_type, options).await
+            }
+            Prepared::Stored(media, input) => self.analyze_async(media, input, options).await,
+        }
+    }
+
+    /// Read `reader` to the end in fixed-size chunks and ingest what it produced;
+    /// see [`MediaUpload`] for when the content is kept in memory.
+    pub fn ingest_stream(
+        &self,
+        mut reader: impl Read,
+        media_type: MediaType,
+        options: &IngestOptions,
+    ) -> Result<IngestResult> {
+        let mut upload = self.begin_upload(media_type)?;
+        let mut buf = vec![0; CHUNK];
+        loop {
+            let n = match reader.read(&mut buf) {
+                Ok(0) => break,
+                Ok(n) => n,
+                Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e).context("reading input"),
+            };
+            upload.write(&buf[..n])?;
+        }
+        self.finish_upload(upload, options)
+    }
+
+    /// Check, probe and record a streamed file, moving it to its final name in
+    /// storage. The file is removed again if any of that fails.
+    fn prepare_upload(&self, mut upload: MediaUpload) -> Result<Prepared> {
+        let (incoming, file) = match std::mem::replace(&mut upload.sink, Sink::Memory(Vec::new())) {
+            Sink::Memory(bytes) => return Ok(Prepared::Buffered(bytes)),
+            Sink::File { path, file } => (path, file),
+        };
+        let storage = self
+            .storage
+            .as_ref()
+            .expect("uploads only stream to a file when storage is configured");
+        let hasher = std::mem::take(&mut upload.hasher);
+        let stored = (|| {
+            drop(file.into_inner().map_err(|e| e.into_error())?);
+            if upload.is_empty() {
+                return Ok(None);
+            }
+            let (metadata, ext) = {
+                let map = map_file(&incoming)?;
+                self.limits.check(&map, upload.media_type)?;
+                let metadata = probe_metadata(&map, upload.media_type);
+                let ext = pru_storage::sniff_ext(&map);
+                (metadata, ext)
+            };
+            let hash = hasher.finish();
+            let path = storage.persist_incoming(&incoming, &hash, ext)?;
+            let media = self.record_entity(
+                hash.clone(),
+                upload.media_type,
+                &metadata,
+                Some(&MediaStorage::relative_path(&hash, ext)),
+            )?;
+            let map = map_file(&path)?;
+            Ok(Some(Prepared::Stored(
+                media,
+                DetectorInput {
+                    bytes: Arc::new(map),
+                    file: Some(Arc::from(path.as_path())),
+                },
+            )))
+        })();
+        match stored {
+            Ok(Some(prepared)) => Ok(prepared),
+            Ok(None) => {
+                let _ = std::fs::remove_file(&incoming);
+                Ok(Prepared::Buffered(Vec::new()))
+            }
+            Err(e) => {
+                let _ = std::fs::remove_file(&incoming);
+                Err(e)
+            }
+        }
+    }
+}
+
+fn map_file(path: &Path) -> Result<Mmap> {
+    let file = File::open(path).with_context(|| format!("opening {}", path.display()))?;
+    // SAFETY: an incoming file is only written by its own, finished upload, and
+    // files in storage are only ever replaced by renaming a complete new file over
+    // them, never written in place, so the mapped contents cannot change.
+    unsafe { Mmap::map(&file) }.with_context(|| format!("mapping {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::IngestLimits;
+    use pru_core::{PruDbHandle, PruStore};
+    use pru_detectors_api::{DetectorMediaKind, DetectorOutput, DetectorRegistry, MediaDetector};
+    use pru_media_schema::{get_stored_at, hash_bytes, FeatureValue, MediaId};
+    use std::sync::Mutex;
+    use tempfile::tempdir;
+
+    /// `len` bytes of a repeating but not chunk-aligned pattern, generated on the fly.
+    struct Pattern {
+        pos: u64,
+        len: u64,
+    }
+
+    impl Read for Pattern {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            // Odd read sizes so chunk boundaries never line up with the pattern.
+            let n = buf.len().min(12_345).min((self.len - self.pos) as usize);
+            for b in &mut buf[..n] {
+                *b = (self.pos % 251) as u8 ^ (self.pos >> 12) as u8;
+                self.pos += 1;
+            }
+            Ok(n)
+        }
+    }
+
+    /// Reports the size of the file it is given; refuses to run from bytes.
+    struct FileSizeDetector;
+
+    impl MediaDetector for FileSizeDetector {
+        fn id(&self) -> String {
+            "detector:video:file_size".to_string()
+        }
+
+        fn kind(&self) -> DetectorMediaKind {
+            DetectorMediaKind::Video
+        }
+
+        fn detect(&self, _bytes: &[u8]) -> Result<DetectorOutput> {
+            anyhow::bail!("expected a file")
+        }
+
+        fn detect_file(
+            &self,
+            path: &Path,
+            _pru: &PruDbHandle,
+            _media: MediaId,
+        ) -> Option<Result<DetectorOutput>> {
+            Some(std::fs::metadata(path).map_err(Into::into).map(|meta| {
+                DetectorOutput {
+                    score_ai: 0.5,
+                    label: pru_detectors_api::DetectorLabel::Unknown,
+                    details: None,
+                    features: [(
+                        "file_bytes".to_string(),
+                        FeatureValue::I64(meta.len() as i64),
+                    )]
+                    .into(),
+                }
+            }))
+        }
+    }
+
+    fn context(dir: &Path, storage: bool, limits: IngestLimits) -> IngestContext {
+        let mut registry = DetectorRegistry::new();
+        registry.register(Arc::new(FileSizeDetector)).unwrap();
+        IngestContext::new(
+            Arc::new(Mutex::new(PruStore::open(dir.join("db")).unwrap())),
+            registry,
+        )
+        .with_storage(storage.then(|| MediaStorage::new(dir.join("media"))))
+        .with_limits(limits)
+    }
+
+    fn leftovers(dir: &Path) -> Vec<String> {
+        std::fs::read_dir(dir.join("media"))
+            .map(|entries| {
+                entries
+                    .filter_map(|e| e.ok()?.file_name().into_string().ok())
+                    .filter(|name| name.starts_with(".incoming"))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    #[test]
+    fn large_stream_is_hashed_in_chunks_and_read_from_storage() {
+        let dir = tempdir().unwrap();
+        let ctx = context(dir.path(), true, IngestLimits::default());
+        let len = 256 * 1024 * 1024;
+        let mut expected = ContentHasher::default();
+        let mut buf = vec![0; CHUNK];
+        let mut pattern = Pattern { pos: 0, len };
+        loop {
+            let n = pattern.read(&mut buf).unwrap();
+            if n == 0 {
+                break;
+            }
+            expected.update(&buf[..n]);
+        }
+
+        let result = ctx
+            .ingest_stream(
+                Pattern { pos: 0, len },
+                MediaType::Video,
+                &IngestOptions::default(),
+            )
+            .unwrap();
+        assert_eq!(result.hash, expected.finish());
+        let stored = get_stored_at(&ctx.pru, result.media_id).unwrap().unwrap();
+        let path = dir.path().join("media").join(&stored);
+        assert_eq!(std::fs::metadata(&path).unwrap().len(), len);
+        let output = result.outcomes[0].output.as_ref().unwrap();
+        assert_eq!(output.features["file_bytes"], FeatureValue::I64(len as i64));
+        assert!(leftovers(dir.path()).is_empty());
+    }
+
+    #[test]
+    fn oversize_stream_is_refused_and_cleaned_up() {
+        let dir = tempdir().unwrap();
+        let limits = IngestLimits {
+            max_video_bytes: 100_000,
+            ..Default::default()
+        };
+        let ctx = context(dir.path(), true, limits);
+        let Err(err) = ctx.ingest_stream(
+            Pattern {
+                pos: 0,
+                len: 100_001,
+            },
+            MediaType::Video,
+            &IngestOptions::default(),
+        ) else {
+            panic!("oversize stream was ingested");
+        };
+        assert!(matches!(
+            err.downcast_ref::<IngestError>(),
+            Some(IngestError::TooLarge { limit: 100_000, .. })
+        ));
+        assert!(leftovers(dir.path()).is_empty());
+        assert_eq!(ctx.pru.lock().unwrap().fact_count(), 0);
+    }
+
+    #[test]
+    fn without_storage_the_stream_is_buffered() {
+        let dir = tempdir().unwrap();
+        let ctx = context(dir.path(), false, IngestLimits::default());
+        let mut bytes = Vec::new();
+        Pattern {
+            pos: 0,
+            len: 50_000,
+        }
+        .read_to_end(&mut bytes)
+        .unwrap();
+        let result = ctx
+            .ingest_stream(
+                Pattern {
+                    pos: 0,
+                    len: 50_000,
+                },
+                MediaType::Video,
+                &IngestOptions::default(),
+            )
+            .unwrap();
+        assert_eq!(result.hash, hash_bytes(&bytes));
+        assert_eq!(
+            result.outcomes[0].status,
+            crate::DetectorStatus::Failed("expected a file".to_string())
+        );
+    }
+
+    #[test]
+    fn encrypted_storage_buffers_the_stream_and_stores_ciphertext() {
+        let dir = tempdir().unwrap();
+        let mut ctx = context(dir.path(), true, IngestLimits::default());
+        let storage = MediaStorage::new(dir.path().join("media"))
+            .with_encryption(pru_storage::EncryptionConfig::new([9; 32]));
+        ctx.storage = Some(storage.clone());
+        let mut bytes = Vec::new();
+        Pattern {
+            pos: 0,
+            len: 50_000,
+        }
+        .read_to_end(&mut bytes)
+        .unwrap();
+
+        let result = ctx
+            .ingest_stream(&bytes[..], MediaType::Video, &IngestOptions::default())
+            .unwrap();
+        let stored = get_stored_at(&ctx.pru, result.media_id).unwrap().unwrap();
+        let on_disk = dir.path().join("media").join(format!("{stored}.enc"));
+        assert_ne!(std::fs::read(on_disk).unwrap(), bytes);
+        assert_eq!(
+            crate::load_original(&ctx.pru, &storage, result.media_id).unwrap(),
+            Some(bytes)
+        );
+        assert!(leftovers(dir.path()).is_empty());
+    }
+}
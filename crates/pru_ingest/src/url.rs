@@ -0,0 +1,404 @@
+//! Fetching media from the web and recording where it was seen.
+
+use anyhow::{bail, Context, Result};
+use pru_media_schema::{add_sighting, Sighting};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::time::Duration;
+
+use crate::{now_ts, url_host, IngestContext, IngestResult};
+
+#[derive(Clone, Debug)]
+pub struct UrlOptions {
+    /// Bodies larger than this are rejected, by `Content-Length` or while reading.
+    pub max_bytes: usize,
+    /// Accepted `Content-Type` prefixes; empty accepts anything. A missing header
+    /// is accepted and the type is sniffed as usual.
+    pub allowed_content_types: Vec<String>,
+    /// Fetch from loopback, private, link-local and other non-public addresses.
+    /// Off by default so URLs from users cannot reach internal services.
+    pub allow_private: bool,
+    pub timeout: Duration,
+    pub max_redirects: usize,
+}
+
+impl Default for UrlOptions {
+    fn default() -> Self {
+        Self {
+            max_bytes: 50 * 1024 * 1024,
+            allowed_content_types: [
+                "image/",
+                "text/",
+                "audio/",
+                "video/",
+                "application/octet-stream",
+            ]
+            .map(String::from)
+            .to_vec(),
+            allow_private: false,
+            timeout: Duration::from_secs(30),
+            max_redirects: 5,
+        }
+    }
+}
+
+/// A URL refused before anything was fetched from it, e.g. because it points at a
+/// private address. Callers can `downcast_ref` to tell it apart from fetch errors.
+#[derive(Debug)]
+pub struct BlockedUrl(pub String);
+
+impl std::fmt::Display for BlockedUrl {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for BlockedUrl {}
+
+impl IngestContext {
+    /// Download `url`, ingest the body by sniffed type and record a sighting on the
+    /// URL's host with the final URL, fetch time and response content type.
+    ///
+    /// Every hop of a redirect chain is checked against `options`, and each
+    /// connection is pinned to the address that was checked.
+    pub async fn ingest_url(&self, url: &str, options: &UrlOptions) -> Result<IngestResult> {
+        let mut current = reqwest::Url::parse(url).with_context(|| format!("parsing {url}"))?;
+        let mut redirects = 0;
+        let response = loop {
+            let client = pinned_client(&current, options).await?;
+            let response = client
+                .get(current.clone())
+                .send()
+                .await
+                .with_context(|| format!("fetching {current}"))?;
+            if !response.status().is_redirection() {
+                break response;
+            }
+            let location = response
+                .headers()
+                .get(reqwest::header::LOCATION)
+                .and_then(|v| v.to_str().ok())
+                .with_context(|| format!("{current} redirected without a location"))?;
+            let next = current
+                .join(location)
+                .with_context(|| format!("{current} redirected to invalid {location:?}"))?;
+            redirects += 1;
+            if redirects > options.max_redirects {
+                bail!("{url} redirected more than {} times", options.max_redirects);
+            }
+            current = next;
+        };
+        if !response.status().is_success() {
+            bail!("fetching {current}: server answered {}", response.status());
+        }
+
+        let content_type = response
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .map(|v| {
+                v.split(';')
+                    .next()
+                    .unwrap_or_default()
+                    .trim()
+                    .to_ascii_lowercase()
+            });
+        if let Some(content_type) = &content_type {
+            let allowed = options.allowed_content_types.is_empty()
+                || options
+                    .allowed_content_types
+                    .iter()
+                    .any(|prefix| content_type.starts_with(prefix.as_str()));
+            if !allowed {
+                bail!("{current} has unsupported content type {content_type}");
+            }
+        }
+        let bytes = read_limited(response, options.max_bytes)
+            .await
+            .with_context(|| format!("reading {current}"))?;
+
+        let ingested = self.ingest_auto_async(&bytes).await?;
+        let sighting = Sighting {
+            source: url_host(current.as_str()).unwrap_or_default(),
+            url: Some(current.to_string()),
+            content_type,
+            observed_at: now_ts(),
+        };
+        add_sighting(&self.pru, ingested.media_id, &sighting)?;
+        Ok(ingested)
+    }
+}
+
+/// A client that connects to `url`'s host only at an address that passed the
+/// checks, so a second DNS answer cannot swap in a private one.
+async fn pinned_client(url: &reqwest::Url, options: &UrlOptions) -> Result<reqwest::Client> {
+    if !matches!(url.scheme(), "http" | "https") {
+        return Err(BlockedUrl(format!("{url}: only http and https URLs can be fetched")).into());
+    }
+    let host = url
+        .host_str()
+        .ok_or_else(|| BlockedUrl(format!("{url} has no host")))?;
+    let port = url.port_or_known_default().unwrap_or(80);
+    let lookup = host.trim_start_matches('[').trim_end_matches(']');
+    let addrs: Vec<SocketAddr> = tokio::net::lookup_host((lookup, port))
+        .await
+        .with_context(|| format!("resolving {host}"))?
+        .collect();
+    let Some(addr) = addrs.first().copied() else {
+        bail!("{host} did not resolve to any address");
+    };
+    if !options.allow_private {
+        if let Some(blocked) = addrs.iter().find(|a| !is_public_ip(a.ip())) {
+            return Err(BlockedUrl(format!(
+                "{url} resolves to non-public address {}",
+                blocked.ip()
+            ))
+            .into());
+        }
+    }
+    Ok(reqwest::Client::builder()
+        .redirect(reqwest::redirect::Policy::none())
+        .no_proxy()
+        .timeout(options.timeout)
+        .resolve(host, addr)
+        .build()?)
+}
+
+async fn read_limited(mut response: reqwest::Response, max_bytes: usize) -> Result<Vec<u8>> {
+    if response
+        .content_length()
+        .is_some_and(|len| len > max_bytes as u64)
+    {
+        bail!("body is larger than the {max_bytes} byte limit");
+    }
+    let mut bytes = Vec::new();
+    while let Some(chunk) = response.chunk().await? {
+        if bytes.len() + chunk.len() > max_bytes {
+            bail!("body is larger than the {max_bytes} byte limit");
+        }
+        bytes.extend_from_slice(&chunk);
+    }
+    Ok(bytes)
+}
+
+/// Whether `ip` is a globally routable unicast address.
+pub fn is_public_ip(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(v4) => {
+            let [a, b, c, _] = v4.octets();
+            !(v4.is_loopback()
+                || v4.is_private()
+                || v4.is_link_local()
+                || v4.is_broadcast()
+                || v4.is_documentation()
+                || v4.is_unspecified()
+                || v4.is_multicast()
+                || a == 0
+                || a >= 240
+                // Carrier-grade NAT, 100.64.0.0/10.
+                || (a == 100 && (64..128).contains(&b))
+                // Benchmarking 198.18.0.0/15 and IETF protocol assignments 192.0.0.0/24.
+                || (a == 198 && (b == 18 || b == 19))
+                || (a == 192 && b == 0 && c == 0))
+        }
+        IpAddr::V6(v6) => {
+            if let Some(v4) = embedded_ipv4(v6) {
+                return is_public_ip(IpAddr::V4(v4));
+            }
+            let first = v6.segments()[0];
+            !(v6.is_loopback()
+                || v6.is_unspecified()
+                || v6.is_multicast()
+                // Unique local fc00::/7 and link-local fe80::/10.
+                || (first & 0xfe00) == 0xfc00
+                || (first & 0xffc0) == 0xfe80
+                // Documentation 2001:db8::/32.
+                || (first == 0x2001 && v6.segments()[1] == 0xdb8))
+        }
+    }
+}
+
+/// The IPv4 address an IPv6 address forwards to, so a private one cannot be
+/// reached through a translation prefix.
+fn embedded_ipv4(v6: Ipv6Addr) -> Option<Ipv4Addr> {
+    let join = |hi: u16, lo: u16| Ipv4Addr::from((u32::from(hi) << 16) | u32::from(lo));
+    match v6.segments() {
+        // NAT64 well-known prefix 64:ff9b::/96.
+        [0x64, 0xff9b, 0, 0, 0, 0, hi, lo] => Some(join(hi, lo)),
+        // 6to4 2002::/16 carries the address in the next 32 bits.
+        [0x2002, hi, lo, ..] => Some(join(hi, lo)),
+        // Teredo 2001::/32 carries the client address inverted in the last 32 bits.
+        [0x2001, 0, .., hi, lo] => Some(!join(hi, lo)),
+        // IPv4-compatible ::/96 and IPv4-mapped ::ffff:0:0/96.
+        _ => v6.to_ipv4(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pru_core::PruStore;
+    use pru_detectors_api::DetectorRegistry;
+    use pru_media_schema::{get_media_type, get_sightings, MediaType};
+    use std::sync::{Arc, Mutex};
+    use tempfile::tempdir;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    /// Answers `GET /<path>` from a fixed table until the test ends.
+    async fn serve(routes: Vec<(&'static str, String, Vec<u8>)>) -> SocketAddr {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            loop {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                let mut request = Vec::new();
+                let mut buf = [0u8; 1024];
+                while !request.windows(4).any(|w| w == b"\r\n\r\n") {
+                    let n = stream.read(&mut buf).await.unwrap();
+                    if n == 0 {
+                        break;
+                    }
+                    request.extend_from_slice(&buf[..n]);
+                }
+                let line = String::from_utf8_lossy(&request);
+                let path = line.split_whitespace().nth(1).unwrap_or("/").to_string();
+                let (head, body) = routes
+                    .iter()
+                    .find(|(p, _, _)| *p == path)
+                    .map(|(_, head, body)| (head.clone(), body.clone()))
+                    .unwrap_or_else(|| ("404 Not Found".into(), Vec::new()));
+                let response = format!(
+                    "HTTP/1.1 {head}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                    body.len()
+                );
+                stream.write_all(response.as_bytes()).await.unwrap();
+                stream.write_all(&body).await.unwrap();
+            }
+        });
+        addr
+    }
+
+    fn context(dir: &std::path::Path) -> IngestContext {
+        IngestContext::new(
+            Arc::new(Mutex::new(PruStore::open(dir).unwrap())),
+            DetectorRegistry::new(),
+        )
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn fetched_media_is_ingested_with_a_sighting() {
+        let mut png = Vec::new();
+        image::DynamicImage::ImageRgb8(image::RgbImage::from_pixel(2, 2, image::Rgb([5, 5, 5])))
+            .write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png)
+            .unwrap();
+        let addr = serve(vec![
+            ("/a.png", "200 OK\r\nContent-Type: image/png".into(), png),
+            ("/old", "302 Found\r\nLocation: /a.png".into(), Vec::new()),
+            (
+                "/archive",
+                "200 OK\r\nContent-Type: application/zip".into(),
+                b"PK\x03\x04".to_vec(),
+            ),
+            (
+                "/big",
+                "200 OK\r\nContent-Type: text/plain".into(),
+                vec![b'a'; 2048],
+            ),
+        ])
+        .await;
+        let dir = tempdir().unwrap();
+        let ctx = context(dir.path());
+        let local = UrlOptions {
+            allow_private: true,
+            max_bytes: 1024,
+            ..Default::default()
+        };
+
+        let result = ctx
+            .ingest_url(&format!("http://{addr}/old"), &local)
+            .await
+            .unwrap();
+        assert_eq!(
+            get_media_type(&ctx.pru, result.media_id).unwrap(),
+            Some(MediaType::Image)
+        );
+        let sightings = get_sightings(&ctx.pru, result.media_id).unwrap();
+        assert_eq!(sightings.len(), 1);
+        assert_eq!(sightings[0].source, "127.0.0.1");
+        assert_eq!(
+            sightings[0].url.as_deref(),
+            Some(format!("http://{addr}/a.png").as_str())
+        );
+        assert_eq!(sightings[0].content_type.as_deref(), Some("image/png"));
+        assert!(sightings[0].observed_at > 0);
+
+        let Err(err) = ctx
+            .ingest_url(&format!("http://{addr}/archive"), &local)
+            .await
+        else {
+            panic!("zip should be refused");
+        };
+        assert!(format!("{err:#}").contains("unsupported content type application/zip"));
+        let Err(err) = ctx.ingest_url(&format!("http://{addr}/big"), &local).await else {
+            panic!("oversized body should be refused");
+        };
+        assert!(format!("{err:#}").contains("1024 byte limit"));
+
+        // Loopback is refused unless explicitly allowed.
+        let Err(err) = ctx
+            .ingest_url(&format!("http://{addr}/a.png"), &UrlOptions::default())
+            .await
+        else {
+            panic!("loopback should be refused by default");
+        };
+        assert!(err.downcast_ref::<BlockedUrl>().is_some(), "{err:#}");
+        let Err(err) = ctx
+            .ingest_url("file:///etc/passwd", &UrlOptions::default())
+            .await
+        else {
+            panic!("file URLs should be refused");
+        };
+        assert!(err.downcast_ref::<BlockedUrl>().is_some(), "{err:#}");
+    }
+
+    #[test]
+    fn private_ranges_are_not_public() {
+        for ip in [
+            "127.0.0.1",
+            "10.1.2.3",
+            "172.16.0.1",
+            "192.168.1.1",
+            "169.254.169.254",
+            "100.64.0.1",
+            "0.0.0.0",
+            "::1",
+            "fd00::1",
+            "fe80::1",
+            "::ffff:192.168.0.1",
+            "::192.168.0.1",
+            "64:ff9b::7f00:1",
+            "64:ff9b::a9fe:a9fe",
+            "2002:c0a8:0101::1",
+            "2002:7f00:1::",
+            "198.18.0.1",
+            "198.19.255.254",
+            "192.0.0.8",
+            "2001:db8::1",
+            "2001:0:4136:e378:8000:63bf:3f57:fefe",
+        ] {
+            assert!(!is_public_ip(ip.parse().unwrap()), "{ip}");
+        }
+        for ip in [
+            "93.184.216.34",
+            "1.1.1.1",
+            "2606:4700:4700::1111",
+            "64:ff9b::101:101",
+            "2002:101:101::1",
+            "198.20.0.1",
+            "192.0.1.1",
+            "2001:0:4136:e378:8000:63bf:fefe:fefe",
+        ] {
+            assert!(is_public_ip(ip.parse().unwrap()), "{ip}");
+        }
+    }
+}
@@ -11,11 +11,10 @@ fn full_flow_text() {
     let store = PruStore::open(dir.path()).unwrap();
     let handle = Arc::new(Mutex::new(store));
     let mut registry = DetectorRegistry::new();
-    registry.register(Arc::new(TextComplexityDetector));
-    let ctx = IngestContext {
-        pru: handle.clone(),
-        detectors: registry,
-    };
+    registry
+        .register(Arc::new(TextComplexityDetector::default()))
+        .unwrap();
+    let ctx = IngestContext::new(handle.clone(), registry);
     let ingest = ctx.ingest_text("hello hello hello").unwrap();
     let engine = TruthEngine::new(TruthEngineConfig::default());
     let report = engine.evaluate_media(&handle, ingest.media_id).unwrap();
@@ -1,14 +1,22 @@
 use anyhow::Result;
-use pru_core::{EntityId, PruDbHandle, PruStore};
+use pru_core::{AtomId, EntityId, PruDbHandle, PruStore};
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+mod migrations;
+
+pub use migrations::{
+    ensure_schema, rename_predicate, run_migrations, schema_version, Migration,
+    CURRENT_SCHEMA_VERSION, MIGRATIONS, PRED_SCHEMA_VERSION, SCHEMA_ENTITY,
+};
 
 pub const PRED_HAS_HASH: &str = "has_hash";
 pub const PRED_CONTENT_TYPE: &str = "content_type";
 pub const PRED_ANALYZED_BY: &str = "analyzed_by";
 pub const PRED_DETECTOR_SCORE: &str = "detector_score";
 pub const PRED_DETECTOR_LABEL: &str = "detector_label";
+pub const PRED_DETECTOR_DETAILS: &str = "detector_details";
 pub const PRED_HAS_FEATURE: &str = "has_feature";
 pub const PRED_PROVENANCE_CLAIM: &str = "provenance_claim";
 pub const PRED_CAPTURED_BY_DEVICE: &str = "captured_by_device";
@@ -17,6 +25,33 @@ pub const PRED_SIMILAR_TO: &str = "similar_to";
 pub const PRED_SEEN_ON: &str = "seen_on";
 pub const PRED_HUMAN_VERDICT: &str = "human_verdict";
 pub const PRED_DETECTOR_RELIABILITY: &str = "detector_reliability";
+pub const PRED_DETECTOR_CALIBRATION: &str = "detector_calibration";
+pub const PRED_SIGHTING_DETAIL: &str = "sighting_detail";
+pub const PRED_SOURCE_REPUTATION: &str = "source_reputation";
+pub const PRED_MEDIA_BYTES: &str = "media_bytes";
+pub const PRED_MEDIA_WIDTH: &str = "media_width";
+pub const PRED_MEDIA_HEIGHT: &str = "media_height";
+pub const PRED_MEDIA_DURATION_MS: &str = "media_duration_ms";
+pub const PRED_MEDIA_MIME: &str = "media_mime";
+pub const PRED_STORED_AT: &str = "stored_at";
+pub const PRED_REPORTED_BY: &str = "reported_by";
+pub const PRED_TAGGED: &str = "tagged";
+pub const PRED_DETECTOR_KIND: &str = "detector_kind";
+pub const PRED_DETECTOR_VERSION: &str = "detector_version";
+pub const PRED_DETECTOR_DESCRIPTION: &str = "detector_description";
+pub const PRED_DETECTOR_CONFIG_HASH: &str = "detector_config_hash";
+pub const PRED_ANALYSIS_SUMMARY: &str = "analysis_summary";
+pub const PRED_ANALYSIS_ERROR: &str = "analysis_error";
+pub const PRED_PHASH: &str = "phash";
+pub const PRED_PHASH_BAND: &str = "phash_band";
+
+/// Annotator recorded for verdicts given without a name. Its verdicts cannot
+/// be told apart, so each one counts as a separate vote.
+pub const ANONYMOUS_ANNOTATOR: &str = "anonymous";
+
+/// A 64-bit perceptual hash is indexed as this many byte-wide bands, so any two
+/// hashes within Hamming distance `PHASH_BANDS - 1` share at least one band.
+pub const PHASH_BANDS: u32 = 8;
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub enum MediaType {
@@ -26,6 +61,21 @@ pub enum MediaType {
     Video,
 }
 
+impl std::str::FromStr for MediaType {
+    type Err = anyhow::Error;
+
+    /// "image", "text", "audio" or "video", in any case.
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "image" => Ok(MediaType::Image),
+            "text" => Ok(MediaType::Text),
+            "audio" => Ok(MediaType::Audio),
+            "video" => Ok(MediaType::Video),
+            other => anyhow::bail!("unknown media type {other:?}"),
+        }
+    }
+}
+
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct MediaId(pub EntityId);
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
@@ -50,11 +100,47 @@ pub fn detector_entity_name(id: &str) -> String {
     format!("detector:{id}")
 }
 
+pub fn tag_entity_name(tag: &str) -> String {
+    format!("tag:{tag}")
+}
+
+pub fn source_entity_name(source: &str) -> String {
+    format!("source:{source}")
+}
+
+pub fn annotator_entity_name(annotator: &str) -> String {
+    format!("annotator:{annotator}")
+}
+
+pub fn reporter_entity_name(reporter: &str) -> String {
+    format!("reporter:{reporter}")
+}
+
 pub fn hash_bytes(bytes: &[u8]) -> String {
-    let mut hasher = Sha256::new();
+    let mut hasher = ContentHasher::default();
     hasher.update(bytes);
-    let hash = hasher.finalize();
-    hex::encode(hash)
+    hasher.finish()
+}
+
+/// [`hash_bytes`] for content that arrives in chunks.
+#[derive(Clone, Default)]
+pub struct ContentHasher(Sha256);
+
+impl ContentHasher {
+    pub fn update(&mut self, chunk: &[u8]) {
+        self.0.update(chunk);
+    }
+
+    pub fn finish(self) -> String {
+        hex::encode(self.0.finalize())
+    }
+}
+
+fn now_ts() -> i64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0)
 }
 
 fn with_store<R>(handle: &PruDbHandle, f: impl FnOnce(&mut PruStore) -> Result<R>) -> Result<R> {
@@ -67,10 +153,55 @@ pub fn upsert_media_entity(
     hash: &str,
     media_type: MediaType,
 ) -> Result<MediaId> {
+    Ok(create_media_entity(handle, hash, media_type)?.0)
+}
+
+/// [`upsert_media_entity`], also saying whether this call created the entity.
+/// The lookup and the insert happen under one store lock, so of two callers
+/// racing on the same content exactly one sees `true`.
+pub fn create_media_entity(
+    handle: &PruDbHandle,
+    hash: &str,
+    media_type: MediaType,
+) -> Result<(MediaId, bool)> {
     with_store(handle, |store| {
         let name = media_entity_name(hash, media_type);
+        if let Some(id) = store.get_entity_id(&name) {
+            return Ok((MediaId(id), false));
+        }
         let id = store.intern_entity(&name)?;
-        Ok(MediaId(id))
+        let pred = store.intern_predicate(PRED_CONTENT_TYPE)?;
+        let lit = store.intern_literal(&format!("{:?}", media_type))?;
+        store.add_fact(pru_core::Fact {
+            subject: id,
+            predicate: pred,
+            object: lit,
+            source: None,
+            timestamp: None,
+            confidence: None,
+        })?;
+        Ok((MediaId(id), true))
+    })
+}
+
+/// The media entity for `hash`, if one was already created.
+pub fn find_media_entity(
+    handle: &PruDbHandle,
+    hash: &str,
+    media_type: MediaType,
+) -> Result<Option<MediaId>> {
+    with_store(handle, |store| {
+        Ok(store
+            .get_entity_id(&media_entity_name(hash, media_type))
+            .map(MediaId))
+    })
+}
+
+/// Media type from the media's latest `content_type` fact; `None` for entities
+/// that have none.
+pub fn get_media_type(handle: &PruDbHandle, media: MediaId) -> Result<Option<MediaType>> {
+    with_store(handle, |store| {
+        Ok(latest_literal(store, media.0, PRED_CONTENT_TYPE)?.and_then(|value| value.parse().ok()))
     })
 }
 
@@ -78,7 +209,7 @@ pub fn add_content_type(handle: &PruDbHandle, media: MediaId, media_type: MediaT
     with_store(handle, |store| {
         let pred = store.intern_predicate(PRED_CONTENT_TYPE)?;
         let lit = store.intern_literal(&format!("{:?}", media_type))?;
-        store.add_fact(pru_core::Fact {
+        store.add_fact_unique(pru_core::Fact {
             subject: media.0,
             predicate: pred,
             object: lit,
@@ -90,6 +221,98 @@ pub fn add_content_type(handle: &PruDbHandle, media: MediaId, media_type: MediaT
     })
 }
 
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MediaMetadata {
+    pub byte_len: Option<u64>,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub duration_ms: Option<u64>,
+    pub mime: Option<String>,
+}
+
+/// Record technical metadata; fields that are `None` or unchanged are not rewritten.
+pub fn add_media_metadata(
+    handle: &PruDbHandle,
+    media: MediaId,
+    metadata: &MediaMetadata,
+) -> Result<()> {
+    let fields = [
+        (PRED_MEDIA_BYTES, metadata.byte_len.map(|v| v.to_string())),
+        (PRED_MEDIA_WIDTH, metadata.width.map(|v| v.to_string())),
+        (PRED_MEDIA_HEIGHT, metadata.height.map(|v| v.to_string())),
+        (
+            PRED_MEDIA_DURATION_MS,
+            metadata.duration_ms.map(|v| v.to_string()),
+        ),
+        (PRED_MEDIA_MIME, metadata.mime.clone()),
+    ];
+    with_store(handle, |store| upsert_literals(store, media.0, &fields))
+}
+
+/// Append `(predicate, value)` facts whose value differs from the latest stored one.
+fn upsert_literals(
+    store: &mut PruStore,
+    subject: EntityId,
+    fields: &[(&str, Option<String>)],
+) -> Result<()> {
+    for (pred_name, value) in fields {
+        let Some(value) = value else {
+            continue;
+        };
+        if latest_literal(store, subject, pred_name)?.as_deref() == Some(value.as_str()) {
+            continue;
+        }
+        let pred = store.intern_predicate(pred_name)?;
+        let lit = store.intern_literal(value)?;
+        store.add_fact(pru_core::Fact {
+            subject,
+            predicate: pred,
+            object: lit,
+            source: None,
+            timestamp: None,
+            confidence: None,
+        })?;
+    }
+    Ok(())
+}
+
+pub fn get_media_metadata(handle: &PruDbHandle, media: MediaId) -> Result<MediaMetadata> {
+    with_store(handle, |store| {
+        Ok(MediaMetadata {
+            byte_len: latest_literal(store, media.0, PRED_MEDIA_BYTES)?
+                .and_then(|v| v.parse().ok()),
+            width: latest_literal(store, media.0, PRED_MEDIA_WIDTH)?.and_then(|v| v.parse().ok()),
+            height: latest_literal(store, media.0, PRED_MEDIA_HEIGHT)?.and_then(|v| v.parse().ok()),
+            duration_ms: latest_literal(store, media.0, PRED_MEDIA_DURATION_MS)?
+                .and_then(|v| v.parse().ok()),
+            mime: latest_literal(store, media.0, PRED_MEDIA_MIME)?,
+        })
+    })
+}
+
+/// Record where the original bytes were saved, relative to the media storage root.
+pub fn set_stored_at(handle: &PruDbHandle, media: MediaId, relative_path: &str) -> Result<()> {
+    let fields = [(PRED_STORED_AT, Some(relative_path.to_string()))];
+    with_store(handle, |store| upsert_literals(store, media.0, &fields))
+}
+
+pub fn get_stored_at(handle: &PruDbHandle, media: MediaId) -> Result<Option<String>> {
+    with_store(handle, |store| {
+        latest_literal(store, media.0, PRED_STORED_AT)
+    })
+}
+
+fn latest_literal(store: &PruStore, subject: EntityId, pred_name: &str) -> Result<Option<String>> {
+    let Some(pred) = store.get_predicate_id(pred_name) else {
+        return Ok(None);
+    };
+    let facts = store.facts_for_subject_predicate(subject, pred)?;
+    Ok(facts
+        .iter()
+        .rev()
+        .find_map(|f| store.get_literal_value(f.object)))
+}
+
 pub fn add_detector_score(
     handle: &PruDbHandle,
     media: MediaId,
@@ -98,56 +321,169 @@ pub fn add_detector_score(
     label: &str,
 ) -> Result<()> {
     with_store(handle, |store| {
-        let score_pred = store.intern_predicate(PRED_DETECTOR_SCORE)?;
-        let label_pred = store.intern_predicate(PRED_DETECTOR_LABEL)?;
-        let score_lit = store.intern_literal(&score.to_string())?;
-        let label_lit = store.intern_literal(label)?;
-        store.add_fact(pru_core::Fact {
-            subject: media.0,
-            predicate: score_pred,
-            object: score_lit,
-            source: Some(detector.0),
-            timestamp: None,
-            confidence: None,
-        })?;
-        store.add_fact(pru_core::Fact {
-            subject: media.0,
-            predicate: label_pred,
-            object: label_lit,
-            source: Some(detector.0),
-            timestamp: None,
-            confidence: None,
-        })?;
-        Ok(())
+        write_detector_score(store, media, detector, score, label)
     })
 }
 
-pub fn mark_analyzed_by(handle: &PruDbHandle, media: MediaId, detector: DetectorId) -> Result<()> {
+fn write_detector_score(
+    store: &mut PruStore,
+    media: MediaId,
+    detector: DetectorId,
+    score: f64,
+    label: &str,
+) -> Result<()> {
+    let now = now_ts();
+    let score_pred = store.intern_predicate(PRED_DETECTOR_SCORE)?;
+    let label_pred = store.intern_predicate(PRED_DETECTOR_LABEL)?;
+    let score_lit = store.intern_literal(&score.to_string())?;
+    let label_lit = store.intern_literal(label)?;
+    store.add_fact(pru_core::Fact {
+        subject: media.0,
+        predicate: score_pred,
+        object: score_lit,
+        source: Some(detector.0),
+        timestamp: Some(now),
+        confidence: None,
+    })?;
+    store.add_fact(pru_core::Fact {
+        subject: media.0,
+        predicate: label_pred,
+        object: label_lit,
+        source: Some(detector.0),
+        timestamp: Some(now),
+        confidence: None,
+    })?;
+    Ok(())
+}
+
+/// Record that `detector` failed (error, panic or timeout) while analyzing `media`.
+pub fn add_analysis_error(
+    handle: &PruDbHandle,
+    media: MediaId,
+    detector: DetectorId,
+    message: &str,
+) -> Result<()> {
     with_store(handle, |store| {
-        let pred = store.intern_predicate(PRED_ANALYZED_BY)?;
-        store.add_fact(pru_core::Fact {
-            subject: media.0,
-            predicate: pred,
-            object: detector.0,
-            source: None,
-            timestamp: None,
-            confidence: None,
-        })?;
-        Ok(())
+        write_analysis_error(store, media, detector, message)
+    })
+}
+
+fn write_detector_details(
+    store: &mut PruStore,
+    media: MediaId,
+    detector: DetectorId,
+    details: &str,
+) -> Result<()> {
+    let pred = store.intern_predicate(PRED_DETECTOR_DETAILS)?;
+    let lit = store.intern_literal(details)?;
+    store.add_fact(pru_core::Fact {
+        subject: media.0,
+        predicate: pred,
+        object: lit,
+        source: Some(detector.0),
+        timestamp: Some(now_ts()),
+        confidence: None,
+    })?;
+    Ok(())
+}
+
+/// The latest human-readable details `detector` gave for `media`.
+pub fn get_detector_details(
+    handle: &PruDbHandle,
+    media: MediaId,
+    detector: DetectorId,
+) -> Result<Option<String>> {
+    with_store(handle, |store| {
+        let Some(pred) = store.get_predicate_id(PRED_DETECTOR_DETAILS) else {
+            return Ok(None);
+        };
+        Ok(store
+            .facts_for_subject_predicate(media.0, pred)?
+            .iter()
+            .rev()
+            .filter(|f| f.source == Some(detector.0))
+            .find_map(|f| store.get_literal_value(f.object)))
+    })
+}
+
+fn write_analysis_error(
+    store: &mut PruStore,
+    media: MediaId,
+    detector: DetectorId,
+    message: &str,
+) -> Result<()> {
+    let pred = store.intern_predicate(PRED_ANALYSIS_ERROR)?;
+    let lit = store.intern_literal(message)?;
+    store.add_fact(pru_core::Fact {
+        subject: media.0,
+        predicate: pred,
+        object: lit,
+        source: Some(detector.0),
+        timestamp: Some(now_ts()),
+        confidence: None,
+    })?;
+    Ok(())
+}
+
+pub fn get_analysis_errors(
+    handle: &PruDbHandle,
+    media: MediaId,
+) -> Result<Vec<(DetectorId, String)>> {
+    with_store(handle, |store| {
+        let Some(pred) = store.get_predicate_id(PRED_ANALYSIS_ERROR) else {
+            return Ok(Vec::new());
+        };
+        let facts = store.facts_for_subject_predicate(media.0, pred)?;
+        Ok(facts
+            .iter()
+            .filter_map(|f| {
+                let src = f.source?;
+                Some((DetectorId(src), store.get_literal_value(f.object)?))
+            })
+            .collect())
     })
 }
 
+pub fn mark_analyzed_by(handle: &PruDbHandle, media: MediaId, detector: DetectorId) -> Result<()> {
+    with_store(handle, |store| write_analyzed_by(store, media, detector))
+}
+
+fn write_analyzed_by(store: &mut PruStore, media: MediaId, detector: DetectorId) -> Result<()> {
+    let pred = store.intern_predicate(PRED_ANALYZED_BY)?;
+    store.add_fact(pru_core::Fact {
+        subject: media.0,
+        predicate: pred,
+        object: detector.0,
+        source: None,
+        timestamp: None,
+        confidence: None,
+    })?;
+    Ok(())
+}
+
 pub fn add_human_verdict(handle: &PruDbHandle, media: MediaId, label: &str) -> Result<()> {
+    add_human_verdict_by(handle, media, label, ANONYMOUS_ANNOTATOR, 1.0)
+}
+
+pub fn add_human_verdict_by(
+    handle: &PruDbHandle,
+    media: MediaId,
+    label: &str,
+    annotator: &str,
+    confidence: f32,
+) -> Result<()> {
+    let now = now_ts();
     with_store(handle, |store| {
+        let annotator_id = store.intern_entity(&annotator_entity_name(annotator))?;
         let pred = store.intern_predicate(PRED_HUMAN_VERDICT)?;
         let lit = store.intern_literal(label)?;
         store.add_fact(pru_core::Fact {
             subject: media.0,
             predicate: pred,
             object: lit,
-            source: None,
-            timestamp: None,
-            confidence: Some(1.0),
+            source: Some(annotator_id),
+            timestamp: Some(now),
+            confidence: Some(confidence.clamp(0.0, 1.0)),
         })?;
         Ok(())
     })
@@ -182,23 +518,68 @@ pub fn get_detector_scores_for_media(
             Some(p) => p,
             None => return Ok(Vec::new()),
         };
-        let score_facts = store.facts_for_subject_predicate(media.0, pred_score)?;
-        let mut results = Vec::new();
-        for fact in score_facts {
-            if let Some(src) = fact.source {
-                if let Some(obj_str) = store.get_literal_value(fact.object) {
-                    if let Ok(score) = obj_str.parse::<f64>() {
-                        let label = find_label_for(store, media.0, src, PRED_DETECTOR_LABEL)?;
-                        results.push((
-                            DetectorId(src),
-                            score,
-                            label.unwrap_or_else(|| "unknown".into()),
-                        ));
-                    }
-                }
+        // Latest score per detector wins; later facts break timestamp ties.
+        let mut latest: Vec<StoredScore> = Vec::new();
+        for stored in scores_with_labels(store, media.0, pred_score)? {
+            let ts = stored.timestamp.unwrap_or(i64::MIN);
+            match latest.iter_mut().find(|s| s.detector == stored.detector) {
+                Some(entry) if ts >= entry.timestamp.unwrap_or(i64::MIN) => *entry = stored,
+                Some(_) => {}
+                None => latest.push(stored),
             }
         }
-        Ok(results)
+        Ok(latest
+            .into_iter()
+            .map(|s| (DetectorId(s.detector), s.score, s.label))
+            .collect())
+    })
+}
+
+/// Whether `detector` has a stored score for `media` and its registered version and
+/// config hash still match `info`.
+pub fn has_current_detector_score(
+    handle: &PruDbHandle,
+    media: MediaId,
+    detector: &str,
+    info: &DetectorInfo,
+) -> Result<bool> {
+    let Some(id) = with_store(handle, |store| Ok(store.get_entity_id(detector)))? else {
+        return Ok(false);
+    };
+    let Some(stored) = get_detector_info(handle, DetectorId(id))? else {
+        return Ok(false);
+    };
+    if stored.version != info.version || stored.config_hash != info.config_hash {
+        return Ok(false);
+    }
+    with_store(handle, |store| {
+        let Some(pred) = store.get_predicate_id(PRED_DETECTOR_SCORE) else {
+            return Ok(false);
+        };
+        Ok(store
+            .facts_for_subject_predicate(media.0, pred)?
+            .iter()
+            .any(|f| f.source == Some(id)))
+    })
+}
+
+pub fn get_detector_score_history(
+    handle: &PruDbHandle,
+    media: MediaId,
+    detector: DetectorId,
+) -> Result<Vec<(Option<i64>, f64, String)>> {
+    with_store(handle, |store| {
+        let Some(pred_score) = store.get_predicate_id(PRED_DETECTOR_SCORE) else {
+            return Ok(Vec::new());
+        };
+        let mut history: Vec<(Option<i64>, f64, String)> =
+            scores_with_labels(store, media.0, pred_score)?
+                .into_iter()
+                .filter(|s| s.detector == detector.0)
+                .map(|s| (s.timestamp, s.score, s.label))
+                .collect();
+        history.sort_by_key(|(ts, _, _)| ts.unwrap_or(i64::MIN));
+        Ok(history)
     })
 }
 
@@ -216,121 +597,1581 @@ pub fn get_human_verdicts(handle: &PruDbHandle, media: MediaId) -> Result<Vec<St
     })
 }
 
-fn find_label_for(
-    store: &PruStore,
-    media: EntityId,
-    detector: EntityId,
-    pred_name: &str,
-) -> Result<Option<String>> {
-    if let Some(pred) = store.get_predicate_id(pred_name) {
-        let facts = store.facts_for_subject_predicate(media, pred)?;
-        for fact in facts {
-            if fact.source == Some(detector) {
-                if let Some(val) = store.get_literal_value(fact.object) {
-                    return Ok(Some(val));
-                }
-            }
-        }
+#[derive(Debug, Default, Serialize, Deserialize, Clone, PartialEq)]
+pub struct VerdictSummary {
+    /// Number of votes per (lower-cased) label: the latest verdict of each
+    /// named annotator, plus every anonymous one.
+    pub counts: BTreeMap<String, usize>,
+    /// Annotators who voted for each label.
+    pub annotators: BTreeMap<String, Vec<String>>,
+    /// Label with a strict majority of votes; `None` when there are no votes or a tie.
+    pub majority_label: Option<String>,
+    /// Share of votes backing the leading label (0.0 when there are no votes).
+    pub agreement: f32,
+}
+
+impl VerdictSummary {
+    pub fn total(&self) -> usize {
+        self.counts.values().sum()
     }
-    Ok(None)
 }
 
-#[derive(Debug, Default, Serialize, Deserialize, Clone)]
-pub struct DetectorReliability {
-    pub seen: u64,
-    pub correct: u64,
+/// Every media with at least one human verdict, in id order.
+pub fn media_with_verdicts(handle: &PruDbHandle) -> Result<Vec<MediaId>> {
+    with_store(handle, |store| {
+        let Some(pred) = store.get_predicate_id(PRED_HUMAN_VERDICT) else {
+            return Ok(Vec::new());
+        };
+        let media: BTreeSet<EntityId> = store
+            .query(pru_core::Query {
+                predicate: Some(pred),
+                ..Default::default()
+            })?
+            .iter()
+            .map(|f| f.subject)
+            .collect();
+        Ok(media.into_iter().map(MediaId).collect())
+    })
 }
 
-pub fn get_detector_reliability(
-    handle: &PruDbHandle,
-    detector: DetectorId,
-) -> Result<Option<DetectorReliability>> {
+/// Summarize human verdicts, counting only the latest verdict of each named
+/// annotator and every anonymous or unattributed verdict.
+pub fn get_verdict_summary(handle: &PruDbHandle, media: MediaId) -> Result<VerdictSummary> {
     with_store(handle, |store| {
-        let Some(pred) = store.get_predicate_id(PRED_DETECTOR_RELIABILITY) else {
-            return Ok(None);
+        let Some(pred) = store.get_predicate_id(PRED_HUMAN_VERDICT) else {
+            return Ok(VerdictSummary::default());
         };
-        let facts = store.facts_for_subject_predicate(detector.0, pred)?;
-        for fact in facts.into_iter().rev() {
-            if let Some(val) = store.get_literal_value(fact.object) {
-                if let Ok(parsed) = serde_json::from_str::<DetectorReliability>(&val) {
-                    return Ok(Some(parsed));
+        let facts = store.facts_for_subject_predicate(media.0, pred)?;
+        let mut votes: Vec<(String, String)> = Vec::new();
+        for fact in facts {
+            let Some(label) = store.get_literal_value(fact.object) else {
+                continue;
+            };
+            let label = label.to_ascii_lowercase();
+            match fact.source {
+                Some(src) => {
+                    let name = store
+                        .get_entity_name(src)
+                        .map(|n| n.strip_prefix("annotator:").unwrap_or(&n).to_string())
+                        .unwrap_or_else(|| format!("#{src}"));
+                    if name == ANONYMOUS_ANNOTATOR {
+                        votes.push((String::new(), label));
+                        continue;
+                    }
+                    match votes.iter_mut().find(|(who, _)| *who == name) {
+                        Some(vote) => vote.1 = label,
+                        None => votes.push((name, label)),
+                    }
                 }
+                None => votes.push((String::new(), label)),
             }
         }
-        Ok(None)
+
+        let mut summary = VerdictSummary::default();
+        for (who, label) in votes {
+            *summary.counts.entry(label.clone()).or_insert(0) += 1;
+            if !who.is_empty() {
+                summary.annotators.entry(label).or_default().push(who);
+            }
+        }
+        let total = summary.total();
+        if let Some((label, &max)) = summary.counts.iter().max_by_key(|(_, c)| **c) {
+            if max * 2 > total {
+                summary.majority_label = Some(label.clone());
+            }
+            summary.agreement = max as f32 / total as f32;
+        }
+        Ok(summary)
     })
 }
 
-pub fn set_detector_reliability(
-    handle: &PruDbHandle,
-    detector: DetectorId,
-    reliability: &DetectorReliability,
-) -> Result<()> {
-    let payload = serde_json::to_string(reliability)?;
-    add_detector_reliability(handle, detector, &payload)
+struct StoredScore {
+    detector: EntityId,
+    timestamp: Option<i64>,
+    score: f64,
+    label: String,
 }
 
-pub fn bump_reliability_from_verdict(
-    handle: &PruDbHandle,
-    media: MediaId,
-    verdict_label: &str,
-) -> Result<()> {
-    let scores = get_detector_scores_for_media(handle, media)?;
-    for (detector, _score, label) in scores {
-        let mut reliability = get_detector_reliability(handle, detector)?.unwrap_or_default();
-        reliability.seen += 1;
-        if label.eq_ignore_ascii_case(verdict_label) {
-            reliability.correct += 1;
+/// The detector scores of `media` in log order. A score is paired with the
+/// label written alongside it: the detector's next label fact with the same
+/// timestamp, before its next score. A score stored without a label reads as
+/// "unknown" and leaves the pairing of the others alone.
+fn scores_with_labels(
+    store: &PruStore,
+    media: EntityId,
+    pred_score: EntityId,
+) -> Result<Vec<StoredScore>> {
+    let pred_label = store.get_predicate_id(PRED_DETECTOR_LABEL);
+    let mut scores: Vec<(StoredScore, Option<f64>)> = Vec::new();
+    // Each detector's latest score, by index into `scores`, still awaiting its label.
+    let mut awaiting: HashMap<EntityId, usize> = HashMap::new();
+    for fact in store.facts_for_subject(media)? {
+        let Some(src) = fact.source else {
+            continue;
+        };
+        if fact.predicate == pred_score {
+            awaiting.insert(src, scores.len());
+            let score = store
+                .get_literal_value(fact.object)
+                .and_then(|v| v.parse::<f64>().ok());
+            let stored = StoredScore {
+                detector: src,
+                timestamp: fact.timestamp,
+                score: score.unwrap_or_default(),
+                label: "unknown".into(),
+            };
+            scores.push((stored, score));
+        } else if Some(fact.predicate) == pred_label {
+            if let Some(i) = awaiting.remove(&src) {
+                let stored = &mut scores[i].0;
+                if let Some(label) = store.get_literal_value(fact.object) {
+                    if stored.timestamp == fact.timestamp {
+                        stored.label = label;
+                    }
+                }
+            }
         }
-        set_detector_reliability(handle, detector, &reliability)?;
     }
-    Ok(())
+    // Unparseable scores still claimed their label above.
+    Ok(scores
+        .into_iter()
+        .filter(|(_, parsed)| parsed.is_some())
+        .map(|(stored, _)| stored)
+        .collect())
 }
 
-pub fn ensure_detector_entity(handle: &PruDbHandle, detector_name: &str) -> Result<DetectorId> {
-    with_store(handle, |store| {
-        let id = store.intern_entity(detector_name)?;
-        Ok(DetectorId(id))
-    })
-}
+/// Reliability of a detector measured against human verdicts.
+///
+/// `confusion` maps predicted label -> true label -> count. `seen`/`correct` are
+/// running totals; legacy `{"seen":N,"correct":M}` payloads parse with an empty matrix.
+#[derive(Debug, Default, Serialize, Deserialize, Clone, PartialEq)]
+pub struct DetectorReliability {
+    #[serde(default)]
+    pub seen: u64,
+    #[serde(default)]
+    pub correct: u64,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub confusion: BTreeMap<String, BTreeMap<String, u64>>,
+    /// Observations bucketed by week index (unix seconds / one week).
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub weekly: BTreeMap<i64, WeeklyCounts>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub struct WeeklyCounts {
+    pub seen: u64,
+    pub correct: u64,
+}
+
+/// Reliability counts after applying time decay.
+#[derive(Debug, Default, Serialize, Deserialize, Clone, PartialEq)]
+pub struct EffectiveReliability {
+    pub seen: f64,
+    pub correct: f64,
+    /// Per emitted label, how often it was called and confirmed; empty for history
+    /// recorded before the confusion matrix existed.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub by_label: BTreeMap<String, LabelCounts>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub struct LabelCounts {
+    pub predicted: f64,
+    pub confirmed: f64,
+}
+
+impl EffectiveReliability {
+    /// Counts for calls of `label`, if the detector has graded calls of it.
+    pub fn label(&self, label: &str) -> Option<LabelCounts> {
+        self.by_label
+            .get(&label.to_ascii_lowercase())
+            .copied()
+            .filter(|counts| counts.predicted > 0.0)
+    }
+}
+
+const SECS_PER_DAY: i64 = 86_400;
+const SECS_PER_WEEK: i64 = 7 * SECS_PER_DAY;
+
+impl DetectorReliability {
+    /// Record one (predicted, true) observation made now.
+    pub fn record(&mut self, predicted: &str, actual: &str) {
+        self.record_at(predicted, actual, now_ts());
+    }
+
+    /// Record one (predicted, true) observation made at unix time `ts`.
+    pub fn record_at(&mut self, predicted: &str, actual: &str, ts: i64) {
+        let predicted = predicted.to_ascii_lowercase();
+        let actual = actual.to_ascii_lowercase();
+        let hit = predicted == actual;
+        self.seen += 1;
+        let week = self.weekly.entry(ts.div_euclid(SECS_PER_WEEK)).or_default();
+        week.seen += 1;
+        if hit {
+            self.correct += 1;
+            week.correct += 1;
+        }
+        *self
+            .confusion
+            .entry(predicted)
+            .or_default()
+            .entry(actual)
+            .or_insert(0) += 1;
+    }
+
+    pub fn count(&self, predicted: &str, actual: &str) -> u64 {
+        self.confusion
+            .get(&predicted.to_ascii_lowercase())
+            .and_then(|row| row.get(&actual.to_ascii_lowercase()))
+            .copied()
+            .unwrap_or(0)
+    }
+
+    /// How many times the detector emitted `label`.
+    pub fn predicted_total(&self, label: &str) -> u64 {
+        self.confusion
+            .get(&label.to_ascii_lowercase())
+            .map(|row| row.values().sum())
+            .unwrap_or(0)
+    }
+
+    /// How many verdicts carried `label` as the true label.
+    pub fn actual_total(&self, label: &str) -> u64 {
+        let label = label.to_ascii_lowercase();
+        self.confusion
+            .values()
+            .filter_map(|row| row.get(&label))
+            .sum()
+    }
+
+    /// Lifetime counts without decay.
+    pub fn lifetime(&self) -> EffectiveReliability {
+        EffectiveReliability {
+            seen: self.seen as f64,
+            correct: self.correct as f64,
+            by_label: self.label_counts(1.0, 1.0),
+        }
+    }
+
+    /// Confusion matrix rows as per-label counts, calls multiplied by `predicted_scale`
+    /// and confirmed calls by `confirmed_scale`.
+    fn label_counts(
+        &self,
+        predicted_scale: f64,
+        confirmed_scale: f64,
+    ) -> BTreeMap<String, LabelCounts> {
+        self.confusion
+            .keys()
+            .map(|label| {
+                let counts = LabelCounts {
+                    predicted: self.predicted_total(label) as f64 * predicted_scale,
+                    confirmed: self.count(label, label) as f64 * confirmed_scale,
+                };
+                (label.clone(), counts)
+            })
+            .collect()
+    }
+
+    /// Counts as of `now`, each week weighted by `0.5^(age_days / half_life_days)`.
+    ///
+    /// Observations recorded before weekly buckets existed are aged like the oldest
+    /// bucket (or not decayed at all when there are no buckets). The confusion matrix
+    /// is not bucketed, so per-label calls shrink like `seen` and confirmed calls like
+    /// `correct`.
+    pub fn decayed(&self, half_life_days: f64, now: i64) -> EffectiveReliability {
+        if half_life_days <= 0.0 || !half_life_days.is_finite() {
+            return self.lifetime();
+        }
+        let decay = |week: i64| {
+            let mid = week * SECS_PER_WEEK + SECS_PER_WEEK / 2;
+            let age_days = ((now - mid).max(0) as f64) / SECS_PER_DAY as f64;
+            0.5_f64.powf(age_days / half_life_days)
+        };
+        let mut out = EffectiveReliability::default();
+        let (mut bucket_seen, mut bucket_correct) = (0u64, 0u64);
+        for (week, counts) in &self.weekly {
+            let w = decay(*week);
+            out.seen += counts.seen as f64 * w;
+            out.correct += counts.correct as f64 * w;
+            bucket_seen += counts.seen;
+            bucket_correct += counts.correct;
+        }
+        let legacy_weight = self.weekly.keys().next().map(|w| decay(*w)).unwrap_or(1.0);
+        out.seen += self.seen.saturating_sub(bucket_seen) as f64 * legacy_weight;
+        out.correct += self.correct.saturating_sub(bucket_correct) as f64 * legacy_weight;
+        let ratio = |decayed: f64, total: u64| {
+            if total > 0 {
+                decayed / total as f64
+            } else {
+                1.0
+            }
+        };
+        out.by_label =
+            self.label_counts(ratio(out.seen, self.seen), ratio(out.correct, self.correct));
+        out
+    }
+
+    /// Share of the detector's `label` calls that humans confirmed.
+    pub fn precision(&self, label: &str) -> Option<f64> {
+        let total = self.predicted_total(label);
+        (total > 0).then(|| self.count(label, label) as f64 / total as f64)
+    }
+
+    /// Share of true `label` media the detector called `label`.
+    pub fn recall(&self, label: &str) -> Option<f64> {
+        let total = self.actual_total(label);
+        (total > 0).then(|| self.count(label, label) as f64 / total as f64)
+    }
+}
+
+pub fn get_detector_reliability(
+    handle: &PruDbHandle,
+    detector: DetectorId,
+) -> Result<Option<DetectorReliability>> {
+    with_store(handle, |store| {
+        let Some(pred) = store.get_predicate_id(PRED_DETECTOR_RELIABILITY) else {
+            return Ok(None);
+        };
+        let facts = store.facts_for_subject_predicate(detector.0, pred)?;
+        for fact in facts.into_iter().rev() {
+            if let Some(val) = store.get_literal_value(fact.object) {
+                if let Ok(parsed) = serde_json::from_str::<DetectorReliability>(&val) {
+                    return Ok(Some(parsed));
+                }
+            }
+        }
+        Ok(None)
+    })
+}
+
+/// Decayed reliability for the truth engine; `None` when the detector has no history.
+pub fn get_effective_reliability(
+    handle: &PruDbHandle,
+    detector: DetectorId,
+    half_life_days: f64,
+) -> Result<Option<EffectiveReliability>> {
+    Ok(get_detector_reliability(handle, detector)?.map(|r| r.decayed(half_life_days, now_ts())))
+}
+
+pub fn set_detector_reliability(
+    handle: &PruDbHandle,
+    detector: DetectorId,
+    reliability: &DetectorReliability,
+) -> Result<()> {
+    let payload = serde_json::to_string(reliability)?;
+    add_detector_reliability(handle, detector, &payload)
+}
+
+pub fn bump_reliability_from_verdict(
+    handle: &PruDbHandle,
+    media: MediaId,
+    verdict_label: &str,
+) -> Result<()> {
+    // Only an ai or human verdict says what the media is; other labels grade nothing.
+    if !["ai", "human"]
+        .iter()
+        .any(|l| verdict_label.eq_ignore_ascii_case(l))
+    {
+        return Ok(());
+    }
+    let scores = get_detector_scores_for_media(handle, media)?;
+    for (detector, _score, label) in scores {
+        // An unknown label made no prediction to grade.
+        if label.eq_ignore_ascii_case("unknown") {
+            continue;
+        }
+        let mut reliability = get_detector_reliability(handle, detector)?.unwrap_or_default();
+        reliability.record(&label, verdict_label);
+        set_detector_reliability(handle, detector, &reliability)?;
+    }
+    Ok(())
+}
+
+/// Platt scaling fitted from verdict history: `sigmoid(a * score + b)` is the
+/// calibrated AI probability for a raw detector score.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub struct ScoreCalibration {
+    pub a: f64,
+    pub b: f64,
+    /// Labelled scores the parameters were fitted on.
+    pub samples: usize,
+}
+
+impl ScoreCalibration {
+    pub fn apply(&self, score: f64) -> f64 {
+        1.0 / (1.0 + (-(self.a * score + self.b)).exp())
+    }
+}
+
+pub fn set_detector_calibration(
+    handle: &PruDbHandle,
+    detector: DetectorId,
+    calibration: &ScoreCalibration,
+) -> Result<()> {
+    let payload = serde_json::to_string(calibration)?;
+    with_store(handle, |store| {
+        let pred = store.intern_predicate(PRED_DETECTOR_CALIBRATION)?;
+        let lit = store.intern_literal(&payload)?;
+        store.add_fact(pru_core::Fact {
+            subject: detector.0,
+            predicate: pred,
+            object: lit,
+            source: None,
+            timestamp: Some(now_ts()),
+            confidence: None,
+        })?;
+        Ok(())
+    })
+}
+
+/// Latest calibration stored for `detector`, if any.
+pub fn get_detector_calibration(
+    handle: &PruDbHandle,
+    detector: DetectorId,
+) -> Result<Option<ScoreCalibration>> {
+    with_store(handle, |store| {
+        let Some(pred) = store.get_predicate_id(PRED_DETECTOR_CALIBRATION) else {
+            return Ok(None);
+        };
+        let facts = store.facts_for_subject_predicate(detector.0, pred)?;
+        Ok(facts.into_iter().rev().find_map(|fact| {
+            let val = store.get_literal_value(fact.object)?;
+            serde_json::from_str(&val).ok()
+        }))
+    })
+}
+
+/// Every detector with at least one stored score, in id order.
+pub fn scoring_detectors(handle: &PruDbHandle) -> Result<Vec<DetectorId>> {
+    let detectors: BTreeSet<EntityId> = score_facts(handle, None)?
+        .into_iter()
+        .map(|(detector, _)| detector)
+        .collect();
+    Ok(detectors.into_iter().map(DetectorId).collect())
+}
+
+/// Every media `detector` has scored, in id order.
+pub fn media_scored_by(handle: &PruDbHandle, detector: DetectorId) -> Result<Vec<MediaId>> {
+    let media: BTreeSet<EntityId> = score_facts(handle, Some(detector))?
+        .into_iter()
+        .map(|(_, media)| media)
+        .collect();
+    Ok(media.into_iter().map(MediaId).collect())
+}
+
+/// Every detector with at least one stored score, with the timestamp of its
+/// newest score, in id order.
+pub fn detector_last_scored(handle: &PruDbHandle) -> Result<Vec<(DetectorId, Option<i64>)>> {
+    with_store(handle, |store| {
+        let Some(pred) = store.get_predicate_id(PRED_DETECTOR_SCORE) else {
+            return Ok(Vec::new());
+        };
+        let mut last: BTreeMap<EntityId, Option<i64>> = BTreeMap::new();
+        let query = pru_core::Query {
+            predicate: Some(pred),
+            ..Default::default()
+        };
+        for fact in store.query_iter(query) {
+            if let Some(detector) = fact.source {
+                let newest = last.entry(detector).or_default();
+                *newest = (*newest).max(fact.timestamp);
+            }
+        }
+        Ok(last
+            .into_iter()
+            .map(|(d, ts)| (DetectorId(d), ts))
+            .collect())
+    })
+}
+
+/// (detector, media) of every score fact, optionally from one detector only.
+fn score_facts(
+    handle: &PruDbHandle,
+    detector: Option<DetectorId>,
+) -> Result<Vec<(EntityId, EntityId)>> {
+    with_store(handle, |store| {
+        let Some(pred) = store.get_predicate_id(PRED_DETECTOR_SCORE) else {
+            return Ok(Vec::new());
+        };
+        Ok(store
+            .query(pru_core::Query {
+                predicate: Some(pred),
+                ..Default::default()
+            })?
+            .into_iter()
+            .filter_map(|f| Some((f.source?, f.subject)))
+            .filter(|(source, _)| detector.is_none_or(|d| d.0 == *source))
+            .collect())
+    })
+}
+
+/// Retract a detector's score, label, details, feature, claim and analyzed_by facts
+/// for one media.
+pub fn clear_detector_results(
+    handle: &PruDbHandle,
+    media: MediaId,
+    detector: DetectorId,
+) -> Result<usize> {
+    with_store(handle, |store| {
+        retract_detector_facts(store, Some(media), detector)
+    })
+}
+
+/// Retract a detector's results across every media in the store.
+pub fn clear_all_results_for_detector(handle: &PruDbHandle, detector: DetectorId) -> Result<usize> {
+    with_store(handle, |store| {
+        retract_detector_facts(store, None, detector)
+    })
+}
+
+fn retract_detector_facts(
+    store: &mut PruStore,
+    media: Option<MediaId>,
+    detector: DetectorId,
+) -> Result<usize> {
+    let sourced: Vec<EntityId> = [
+        PRED_DETECTOR_SCORE,
+        PRED_DETECTOR_LABEL,
+        PRED_DETECTOR_DETAILS,
+        PRED_HAS_FEATURE,
+        PRED_ANALYSIS_ERROR,
+        PRED_SIMILAR_TO,
+        PRED_PROVENANCE_CLAIM,
+        PRED_CAPTURED_BY_DEVICE,
+        PRED_CLAIMED_GENERATED_BY_MODEL,
+    ]
+    .iter()
+    .filter_map(|name| store.get_predicate_id(name))
+    .collect();
+    let analyzed = store.get_predicate_id(PRED_ANALYZED_BY);
+    let removed = store.retract_facts(|f| {
+        if media.is_some_and(|m| m.0 != f.subject) {
+            return false;
+        }
+        (sourced.contains(&f.predicate) && f.source == Some(detector.0))
+            || (Some(f.predicate) == analyzed && f.object == detector.0)
+    })?;
+    Ok(removed.len())
+}
+
+/// Tag a media item; adding an existing tag is a no-op.
+pub fn add_tag(handle: &PruDbHandle, media: MediaId, tag: &str) -> Result<()> {
+    with_store(handle, |store| write_tag(store, media, tag, now_ts()))
+}
+
+fn write_tag(store: &mut PruStore, media: MediaId, tag: &str, timestamp: i64) -> Result<()> {
+    let tag_id = store.intern_entity(&tag_entity_name(tag))?;
+    let pred = store.intern_predicate(PRED_TAGGED)?;
+    let existing = store.query(pru_core::Query {
+        subject: Some(media.0),
+        predicate: Some(pred),
+        object: Some(tag_id),
+        ..Default::default()
+    })?;
+    if existing.is_empty() {
+        store.add_fact(pru_core::Fact {
+            subject: media.0,
+            predicate: pred,
+            object: tag_id,
+            source: None,
+            timestamp: Some(timestamp),
+            confidence: None,
+        })?;
+    }
+    Ok(())
+}
+
+/// Remove a tag from a media item, returning whether it was present.
+pub fn remove_tag(handle: &PruDbHandle, media: MediaId, tag: &str) -> Result<bool> {
+    with_store(handle, |store| {
+        let (Some(pred), Some(tag_id)) = (
+            store.get_predicate_id(PRED_TAGGED),
+            store.get_entity_id(&tag_entity_name(tag)),
+        ) else {
+            return Ok(false);
+        };
+        let removed = store
+            .retract_facts(|f| f.subject == media.0 && f.predicate == pred && f.object == tag_id)?;
+        Ok(!removed.is_empty())
+    })
+}
+
+pub fn get_tags(handle: &PruDbHandle, media: MediaId) -> Result<Vec<String>> {
+    with_store(handle, |store| {
+        let Some(pred) = store.get_predicate_id(PRED_TAGGED) else {
+            return Ok(Vec::new());
+        };
+        let mut tags: Vec<String> = store
+            .facts_for_subject_predicate(media.0, pred)?
+            .iter()
+            .filter_map(|f| store.get_entity_name(f.object))
+            .filter_map(|name| name.strip_prefix("tag:").map(str::to_string))
+            .collect();
+        tags.sort();
+        tags.dedup();
+        Ok(tags)
+    })
+}
+
+/// Media carrying `tag`, ordered by media id.
+pub fn media_with_tag(
+    handle: &PruDbHandle,
+    tag: &str,
+    offset: usize,
+    limit: usize,
+) -> Result<Vec<MediaId>> {
+    with_store(handle, |store| {
+        let (Some(pred), Some(tag_id)) = (
+            store.get_predicate_id(PRED_TAGGED),
+            store.get_entity_id(&tag_entity_name(tag)),
+        ) else {
+            return Ok(Vec::new());
+        };
+        let mut media: Vec<EntityId> = store
+            .query(pru_core::Query {
+                predicate: Some(pred),
+                object: Some(tag_id),
+                ..Default::default()
+            })?
+            .iter()
+            .map(|f| f.subject)
+            .collect();
+        media.sort_unstable();
+        media.dedup();
+        Ok(media
+            .into_iter()
+            .skip(offset)
+            .take(limit)
+            .map(MediaId)
+            .collect())
+    })
+}
+
+/// Every tag in use with the number of media carrying it.
+pub fn tags(handle: &PruDbHandle) -> Result<Vec<(String, usize)>> {
+    with_store(handle, |store| {
+        let Some(pred) = store.get_predicate_id(PRED_TAGGED) else {
+            return Ok(Vec::new());
+        };
+        let tagged = store.query(pru_core::Query {
+            predicate: Some(pred),
+            ..Default::default()
+        })?;
+        let mut pairs: Vec<(EntityId, EntityId)> =
+            tagged.iter().map(|f| (f.object, f.subject)).collect();
+        pairs.sort_unstable();
+        pairs.dedup();
+        let mut counts: BTreeMap<String, usize> = BTreeMap::new();
+        for (tag_id, _media) in pairs {
+            if let Some(name) = store.get_entity_name(tag_id) {
+                let name = name.strip_prefix("tag:").unwrap_or(&name).to_string();
+                *counts.entry(name).or_insert(0) += 1;
+            }
+        }
+        Ok(counts.into_iter().collect())
+    })
+}
+
+/// Where and when a media item was seen, stored as a `seen_on` fact pointing at the
+/// source entity plus, when there is a URL or content type, a `sighting_detail` fact.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Sighting {
+    /// Source name, e.g. a site or platform ("reddit.com").
+    pub source: String,
+    #[serde(default)]
+    pub url: Option<String>,
+    #[serde(default)]
+    pub content_type: Option<String>,
+    /// Unix seconds.
+    pub observed_at: i64,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SightingDetail {
+    url: Option<String>,
+    content_type: Option<String>,
+}
+
+/// Record a sighting; the same source and time twice is a no-op.
+pub fn add_sighting(handle: &PruDbHandle, media: MediaId, sighting: &Sighting) -> Result<SourceId> {
+    with_store(handle, |store| write_sighting(store, media, sighting))
+}
+
+fn write_sighting(store: &mut PruStore, media: MediaId, sighting: &Sighting) -> Result<SourceId> {
+    let source = store.intern_entity(&source_entity_name(&sighting.source))?;
+    let pred = store.intern_predicate(PRED_SEEN_ON)?;
+    let existing = store.query(pru_core::Query {
+        subject: Some(media.0),
+        predicate: Some(pred),
+        object: Some(source),
+        ..Default::default()
+    })?;
+    if existing
+        .iter()
+        .any(|f| f.timestamp == Some(sighting.observed_at))
+    {
+        return Ok(SourceId(source));
+    }
+    store.add_fact(pru_core::Fact {
+        subject: media.0,
+        predicate: pred,
+        object: source,
+        source: None,
+        timestamp: Some(sighting.observed_at),
+        confidence: None,
+    })?;
+    if sighting.url.is_some() || sighting.content_type.is_some() {
+        let detail = serde_json::to_string(&SightingDetail {
+            url: sighting.url.clone(),
+            content_type: sighting.content_type.clone(),
+        })?;
+        let pred = store.intern_predicate(PRED_SIGHTING_DETAIL)?;
+        let lit = store.intern_literal(&detail)?;
+        store.add_fact(pru_core::Fact {
+            subject: media.0,
+            predicate: pred,
+            object: lit,
+            source: Some(source),
+            timestamp: Some(sighting.observed_at),
+            confidence: None,
+        })?;
+    }
+    Ok(SourceId(source))
+}
+
+fn write_reporter(
+    store: &mut PruStore,
+    media: MediaId,
+    reporter: &str,
+    source: Option<SourceId>,
+    reported_at: i64,
+) -> Result<()> {
+    let reporter = store.intern_entity(&reporter_entity_name(reporter))?;
+    let pred = store.intern_predicate(PRED_REPORTED_BY)?;
+    store.add_fact_unique(pru_core::Fact {
+        subject: media.0,
+        predicate: pred,
+        object: reporter,
+        source: source.map(|s| s.0),
+        timestamp: Some(reported_at),
+        confidence: None,
+    })?;
+    Ok(())
+}
+
+/// Who reported `media` and when, earliest first.
+pub fn get_reporters(handle: &PruDbHandle, media: MediaId) -> Result<Vec<(String, i64)>> {
+    with_store(handle, |store| {
+        let Some(pred) = store.get_predicate_id(PRED_REPORTED_BY) else {
+            return Ok(Vec::new());
+        };
+        let mut out: Vec<_> = store
+            .facts_for_subject_predicate(media.0, pred)?
+            .into_iter()
+            .filter_map(|f| {
+                let name = store.get_entity_name(f.object)?;
+                let name = name.strip_prefix("reporter:").unwrap_or(&name).to_string();
+                Some((name, f.timestamp.unwrap_or(0)))
+            })
+            .collect();
+        out.sort_by_key(|(_, at)| *at);
+        Ok(out)
+    })
+}
+
+/// Every sighting of `media`, earliest first.
+pub fn get_sightings(handle: &PruDbHandle, media: MediaId) -> Result<Vec<Sighting>> {
+    with_store(handle, |store| {
+        let Some(pred) = store.get_predicate_id(PRED_SEEN_ON) else {
+            return Ok(Vec::new());
+        };
+        let details: Vec<pru_core::Fact> = match store.get_predicate_id(PRED_SIGHTING_DETAIL) {
+            Some(detail_pred) => store.facts_for_subject_predicate(media.0, detail_pred)?,
+            None => Vec::new(),
+        };
+        let mut sightings = Vec::new();
+        for fact in store.facts_for_subject_predicate(media.0, pred)? {
+            let Some(name) = store.get_entity_name(fact.object) else {
+                continue;
+            };
+            let detail = details
+                .iter()
+                .filter(|d| d.source == Some(fact.object) && d.timestamp == fact.timestamp)
+                .find_map(|d| {
+                    serde_json::from_str::<SightingDetail>(&store.get_literal_value(d.object)?).ok()
+                });
+            let (url, content_type) = detail.map_or((None, None), |d| (d.url, d.content_type));
+            sightings.push(Sighting {
+                source: name.strip_prefix("source:").unwrap_or(&name).to_string(),
+                url,
+                content_type,
+                observed_at: fact.timestamp.unwrap_or(0),
+            });
+        }
+        sightings.sort_by_key(|s| s.observed_at);
+        Ok(sightings)
+    })
+}
+
+pub fn get_source_id(handle: &PruDbHandle, source: &str) -> Result<Option<SourceId>> {
+    with_store(handle, |store| {
+        Ok(store
+            .get_entity_id(&source_entity_name(source))
+            .map(SourceId))
+    })
+}
+
+/// Verdict history of the media seen on one source.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct SourceReputation {
+    /// Media seen on the source that have a majority human verdict.
+    pub labelled: u64,
+    /// How many of those were judged AI.
+    pub ai: u64,
+}
+
+impl SourceReputation {
+    /// Laplace-smoothed share of labelled media judged AI.
+    pub fn ai_share(&self) -> f64 {
+        (self.ai as f64 + 1.0) / (self.labelled as f64 + 2.0)
+    }
+}
+
+pub fn get_source_reputation(
+    handle: &PruDbHandle,
+    source: SourceId,
+) -> Result<Option<SourceReputation>> {
+    with_store(handle, |store| {
+        Ok(latest_literal(store, source.0, PRED_SOURCE_REPUTATION)?
+            .and_then(|payload| serde_json::from_str(&payload).ok()))
+    })
+}
+
+/// Recount every source's reputation from the current verdicts of media seen there,
+/// storing it when it changed. Returns how many sources were updated.
+pub fn update_source_reputation(handle: &PruDbHandle) -> Result<usize> {
+    let sightings = with_store(handle, |store| {
+        let Some(pred) = store.get_predicate_id(PRED_SEEN_ON) else {
+            return Ok(Vec::new());
+        };
+        let facts = store.query(pru_core::Query {
+            predicate: Some(pred),
+            ..Default::default()
+        })?;
+        let mut pairs: Vec<(EntityId, EntityId)> =
+            facts.iter().map(|f| (f.object, f.subject)).collect();
+        pairs.sort_unstable();
+        pairs.dedup();
+        Ok(pairs)
+    })?;
+    let mut reputations: BTreeMap<EntityId, SourceReputation> = BTreeMap::new();
+    for (source, media) in sightings {
+        let reputation = reputations.entry(source).or_default();
+        // Labels such as "unsure" say nothing about the source.
+        match get_verdict_summary(handle, MediaId(media))?
+            .majority_label
+            .as_deref()
+        {
+            Some("ai") => {
+                reputation.labelled += 1;
+                reputation.ai += 1;
+            }
+            Some("human") => reputation.labelled += 1,
+            _ => {}
+        }
+    }
+    let mut updated = 0;
+    for (source, reputation) in reputations {
+        if get_source_reputation(handle, SourceId(source))?.unwrap_or_default() == reputation {
+            continue;
+        }
+        let payload = serde_json::to_string(&reputation)?;
+        with_store(handle, |store| {
+            let pred = store.intern_predicate(PRED_SOURCE_REPUTATION)?;
+            let lit = store.intern_literal(&payload)?;
+            store.add_fact(pru_core::Fact {
+                subject: source,
+                predicate: pred,
+                object: lit,
+                source: None,
+                timestamp: Some(now_ts()),
+                confidence: None,
+            })?;
+            Ok(())
+        })?;
+        updated += 1;
+    }
+    Ok(updated)
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DetectorInfo {
+    pub kind: String,
+    pub version: String,
+    pub description: String,
+    pub config_hash: Option<String>,
+}
+
+/// Create the detector entity and upsert its descriptive facts.
+pub fn register_detector(
+    handle: &PruDbHandle,
+    id: &str,
+    info: &DetectorInfo,
+) -> Result<DetectorId> {
+    with_store(handle, |store| write_detector(store, id, info))
+}
+
+fn write_detector(store: &mut PruStore, id: &str, info: &DetectorInfo) -> Result<DetectorId> {
+    let non_empty = |v: &str| (!v.trim().is_empty()).then(|| v.to_string());
+    let fields = [
+        (PRED_DETECTOR_KIND, non_empty(&info.kind)),
+        (PRED_DETECTOR_VERSION, non_empty(&info.version)),
+        (PRED_DETECTOR_DESCRIPTION, non_empty(&info.description)),
+        (
+            PRED_DETECTOR_CONFIG_HASH,
+            info.config_hash.as_deref().and_then(non_empty),
+        ),
+    ];
+    let detector = store.intern_entity(id)?;
+    upsert_literals(store, detector, &fields)?;
+    Ok(DetectorId(detector))
+}
+
+pub fn get_detector_info(
+    handle: &PruDbHandle,
+    detector: DetectorId,
+) -> Result<Option<DetectorInfo>> {
+    with_store(handle, |store| {
+        let kind = latest_literal(store, detector.0, PRED_DETECTOR_KIND)?;
+        let version = latest_literal(store, detector.0, PRED_DETECTOR_VERSION)?;
+        let description = latest_literal(store, detector.0, PRED_DETECTOR_DESCRIPTION)?;
+        let config_hash = latest_literal(store, detector.0, PRED_DETECTOR_CONFIG_HASH)?;
+        if kind.is_none() && version.is_none() && description.is_none() && config_hash.is_none() {
+            return Ok(None);
+        }
+        Ok(Some(DetectorInfo {
+            kind: kind.unwrap_or_default(),
+            version: version.unwrap_or_default(),
+            description: description.unwrap_or_default(),
+            config_hash,
+        }))
+    })
+}
+
+pub fn get_detector_name(handle: &PruDbHandle, detector: DetectorId) -> Result<Option<String>> {
+    with_store(handle, |store| Ok(store.get_entity_name(detector.0)))
+}
+
+pub fn ensure_detector_entity(handle: &PruDbHandle, detector_name: &str) -> Result<DetectorId> {
+    with_store(handle, |store| {
+        let id = store.intern_entity(detector_name)?;
+        Ok(DetectorId(id))
+    })
+}
+
+/// Snapshot of the evidence behind a media's report: its detector scores, human
+/// verdicts, provenance claims and sightings, the calibration and reliability of
+/// the detectors that scored it, and the reputation of the sources it was seen on.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EvidenceStamp {
+    /// Newest evidence timestamp, if any evidence carries one.
+    pub latest: Option<i64>,
+    /// Number of evidence facts; catches retractions and same-second writes.
+    pub count: usize,
+}
+
+pub fn evidence_stamp(handle: &PruDbHandle, media: MediaId) -> Result<EvidenceStamp> {
+    with_store(handle, |store| {
+        let mut stamp = EvidenceStamp::default();
+        let mut tally = |subject: EntityId, pred_name: &str| -> Result<Vec<pru_core::Fact>> {
+            let Some(pred) = store.get_predicate_id(pred_name) else {
+                return Ok(Vec::new());
+            };
+            let facts = store.facts_for_subject_predicate(subject, pred)?;
+            for fact in &facts {
+                stamp.count += 1;
+                stamp.latest = stamp.latest.max(fact.timestamp);
+            }
+            Ok(facts)
+        };
+        let detectors: BTreeSet<EntityId> = tally(media.0, PRED_DETECTOR_SCORE)?
+            .iter()
+            .filter_map(|f| f.source)
+            .collect();
+        let sources: BTreeSet<EntityId> = tally(media.0, PRED_SEEN_ON)?
+            .iter()
+            .map(|f| f.object)
+            .collect();
+        for pred_name in [
+            PRED_HUMAN_VERDICT,
+            PRED_PROVENANCE_CLAIM,
+            PRED_CAPTURED_BY_DEVICE,
+            PRED_CLAIMED_GENERATED_BY_MODEL,
+        ] {
+            tally(media.0, pred_name)?;
+        }
+        for detector in detectors {
+            tally(detector, PRED_DETECTOR_CALIBRATION)?;
+            tally(detector, PRED_DETECTOR_RELIABILITY)?;
+        }
+        for source in sources {
+            tally(source, PRED_SOURCE_REPUTATION)?;
+        }
+        Ok(stamp)
+    })
+}
+
+/// Store a serialized analysis summary for `media`, stamped with the current time,
+/// replacing any earlier summary.
+pub fn add_analysis_summary(handle: &PruDbHandle, media: MediaId, payload: &str) -> Result<()> {
+    let now = now_ts();
+    with_store(handle, |store| {
+        store.transaction(|store| {
+            let pred = store.intern_predicate(PRED_ANALYSIS_SUMMARY)?;
+            let lit = store.intern_literal(payload)?;
+            store.retract_facts(|f| f.subject == media.0 && f.predicate == pred)?;
+            store.add_fact(pru_core::Fact {
+                subject: media.0,
+                predicate: pred,
+                object: lit,
+                source: None,
+                timestamp: Some(now),
+                confidence: None,
+            })?;
+            Ok(())
+        })
+    })
+}
+
+/// Latest analysis summary payload and its timestamp.
+pub fn get_latest_analysis_summary(
+    handle: &PruDbHandle,
+    media: MediaId,
+) -> Result<Option<(Option<i64>, String)>> {
+    with_store(handle, |store| {
+        let Some(pred) = store.get_predicate_id(PRED_ANALYSIS_SUMMARY) else {
+            return Ok(None);
+        };
+        let facts = store.facts_for_subject_predicate(media.0, pred)?;
+        Ok(facts.iter().rev().find_map(|f| {
+            store
+                .get_literal_value(f.object)
+                .map(|payload| (f.timestamp, payload))
+        }))
+    })
+}
+
+pub fn add_content_hash(handle: &PruDbHandle, media: MediaId, hash: &str) -> Result<()> {
+    with_store(handle, |store| {
+        let pred = store.intern_predicate(PRED_HAS_HASH)?;
+        let lit = store.intern_literal(hash)?;
+        store.add_fact_unique(pru_core::Fact {
+            subject: media.0,
+            predicate: pred,
+            object: lit,
+            source: None,
+            timestamp: None,
+            confidence: None,
+        })?;
+        Ok(())
+    })
+}
+
+pub fn load_detector_labels(
+    handle: &PruDbHandle,
+    media: MediaId,
+) -> Result<HashMap<EntityId, String>> {
+    with_store(handle, |store| {
+        let Some(pred) = store.get_predicate_id(PRED_DETECTOR_LABEL) else {
+            return Ok(HashMap::new());
+        };
+        let facts = store.facts_for_subject_predicate(media.0, pred)?;
+        let mut map = HashMap::new();
+        for fact in facts {
+            if let Some(src) = fact.source {
+                if let Some(val) = store.get_literal_value(fact.object) {
+                    map.insert(src, val);
+                }
+            }
+        }
+        Ok(map)
+    })
+}
+
+/// Record that `media` looks like `other`, with `similarity` in [0, 1] as the fact
+/// confidence. A link from the same source is only stored once.
+pub fn add_similarity(
+    handle: &PruDbHandle,
+    media: MediaId,
+    other: MediaId,
+    similarity: f32,
+    source: DetectorId,
+) -> Result<()> {
+    with_store(handle, |store| {
+        let pred = store.intern_predicate(PRED_SIMILAR_TO)?;
+        store.add_fact_unique(pru_core::Fact {
+            subject: media.0,
+            predicate: pred,
+            object: other.0,
+            source: Some(source.0),
+            timestamp: Some(now_ts()),
+            confidence: Some(similarity.clamp(0.0, 1.0)),
+        })?;
+        Ok(())
+    })
+}
 
-pub fn add_content_hash(handle: &PruDbHandle, media: MediaId, hash: &str) -> Result<()> {
+/// Media linked to `media` by `similar_to` in either direction, with the best similarity seen.
+pub fn get_similar_media(handle: &PruDbHandle, media: MediaId) -> Result<Vec<(MediaId, f32)>> {
     with_store(handle, |store| {
-        let pred = store.intern_predicate(PRED_HAS_HASH)?;
-        let lit = store.intern_literal(hash)?;
+        let Some(pred) = store.get_predicate_id(PRED_SIMILAR_TO) else {
+            return Ok(Vec::new());
+        };
+        let mut best: BTreeMap<EntityId, f32> = BTreeMap::new();
+        let facts = store.query(pru_core::Query {
+            predicate: Some(pred),
+            ..Default::default()
+        })?;
+        for fact in facts {
+            let other = if fact.subject == media.0 {
+                fact.object
+            } else if fact.object == media.0 {
+                fact.subject
+            } else {
+                continue;
+            };
+            let sim = fact.confidence.unwrap_or(1.0);
+            let entry = best.entry(other).or_insert(sim);
+            *entry = entry.max(sim);
+        }
+        Ok(best
+            .into_iter()
+            .map(|(id, sim)| (MediaId(id), sim))
+            .collect())
+    })
+}
+
+fn phash_band_literal(band: u32, hash: u64) -> String {
+    format!("{band}:{:02x}", (hash >> (band * 8)) & 0xFF)
+}
+
+/// Store `hash` for `media` and index it by band for [`find_phash_matches`].
+///
+/// Re-indexing the same hash is a no-op.
+pub fn index_phash(handle: &PruDbHandle, media: MediaId, hash: u64) -> Result<()> {
+    let hex = format!("{hash:016x}");
+    with_store(handle, |store| {
+        if latest_literal(store, media.0, PRED_PHASH)?.as_deref() == Some(hex.as_str()) {
+            return Ok(());
+        }
+        store.transaction(|store| -> Result<()> {
+            upsert_literals(store, media.0, &[(PRED_PHASH, Some(hex.clone()))])?;
+            let band_pred = store.intern_predicate(PRED_PHASH_BAND)?;
+            for band in 0..PHASH_BANDS {
+                let lit = store.intern_literal(&phash_band_literal(band, hash))?;
+                store.add_fact(pru_core::Fact {
+                    subject: media.0,
+                    predicate: band_pred,
+                    object: lit,
+                    source: None,
+                    timestamp: None,
+                    confidence: None,
+                })?;
+            }
+            Ok(())
+        })
+    })
+}
+
+/// Indexed media whose hash is within `max_distance` bits of `hash`, closest first.
+///
+/// Only media sharing a band with `hash` are compared, so distances of
+/// `PHASH_BANDS` or more may be missed. Each band is one lookup in the store's
+/// predicate/object index, so the cost follows the number of candidates rather
+/// than the size of the store.
+pub fn find_phash_matches(
+    handle: &PruDbHandle,
+    hash: u64,
+    max_distance: u32,
+) -> Result<Vec<(MediaId, u32)>> {
+    with_store(handle, |store| {
+        let Some(band_pred) = store.get_predicate_id(PRED_PHASH_BAND) else {
+            return Ok(Vec::new());
+        };
+        let mut candidates: Vec<EntityId> = Vec::new();
+        for band in 0..PHASH_BANDS {
+            let Some(lit) = store.get_literal_id(&phash_band_literal(band, hash)) else {
+                continue;
+            };
+            let band = store.query_iter(pru_core::Query {
+                predicate: Some(band_pred),
+                object: Some(lit),
+                ..Default::default()
+            });
+            candidates.extend(band.map(|f| f.subject));
+        }
+        candidates.sort_unstable();
+        candidates.dedup();
+
+        let mut matches = Vec::new();
+        for media in candidates {
+            let Some(other) = latest_literal(store, media, PRED_PHASH)?
+                .and_then(|hex| u64::from_str_radix(&hex, 16).ok())
+            else {
+                continue;
+            };
+            let distance = (hash ^ other).count_ones();
+            if distance <= max_distance {
+                matches.push((MediaId(media), distance));
+            }
+        }
+        matches.sort_by_key(|(media, distance)| (*distance, media.0));
+        Ok(matches)
+    })
+}
+
+/// A statement about where a media item came from, as read from its metadata.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ProvenanceClaim {
+    /// Stored under `captured_by_device`, e.g. "Canon EOS R5".
+    CapturedByDevice { device: String },
+    /// Stored under `claimed_generated_by_model`, e.g. "Adobe Firefly".
+    GeneratedByModel { model: String },
+    /// Anything else, stored as JSON under `provenance_claim`.
+    Other { key: String, value: String },
+}
+
+/// IPTC digital source types that mean the pixels came out of a trained model.
+const ALGORITHMIC_SOURCES: &[&str] = &[
+    "trainedalgorithmicmedia",
+    "compositewithtrainedalgorithmicmedia",
+    "algorithmicmedia",
+];
+
+/// Whether an IPTC digital source type, as a bare code or a full newscode URI,
+/// says the media came out of a trained model.
+pub fn is_algorithmic_source(digital_source_type: &str) -> bool {
+    let code = digital_source_type.rsplit('/').next().unwrap_or_default();
+    ALGORITHMIC_SOURCES.contains(&code.to_ascii_lowercase().as_str())
+}
+
+impl ProvenanceClaim {
+    /// The generator this claim points at: a named model, or an algorithmic
+    /// `digital_source_type` read from IPTC/C2PA metadata.
+    pub fn generator(&self) -> Option<&str> {
+        match self {
+            ProvenanceClaim::GeneratedByModel { model } => Some(model),
+            ProvenanceClaim::Other { key, value }
+                if key == "digital_source_type" && is_algorithmic_source(value) =>
+            {
+                Some(value)
+            }
+            _ => None,
+        }
+    }
+
+    fn predicate_and_literal(&self) -> Result<(&'static str, String)> {
+        Ok(match self {
+            ProvenanceClaim::CapturedByDevice { device } => {
+                (PRED_CAPTURED_BY_DEVICE, device.clone())
+            }
+            ProvenanceClaim::GeneratedByModel { model } => {
+                (PRED_CLAIMED_GENERATED_BY_MODEL, model.clone())
+            }
+            ProvenanceClaim::Other { .. } => (PRED_PROVENANCE_CLAIM, serde_json::to_string(self)?),
+        })
+    }
+}
+
+/// Record a provenance claim made by `source`; repeating the same claim is a no-op.
+pub fn add_provenance_claim(
+    handle: &PruDbHandle,
+    media: MediaId,
+    claim: &ProvenanceClaim,
+    source: DetectorId,
+) -> Result<()> {
+    let (pred_name, value) = claim.predicate_and_literal()?;
+    with_store(handle, |store| {
+        let pred = store.intern_predicate(pred_name)?;
+        let lit = store.intern_literal(&value)?;
+        let existing = store.query(pru_core::Query {
+            subject: Some(media.0),
+            predicate: Some(pred),
+            object: Some(lit),
+            ..Default::default()
+        })?;
+        if existing.iter().any(|f| f.source == Some(source.0)) {
+            return Ok(());
+        }
         store.add_fact(pru_core::Fact {
             subject: media.0,
             predicate: pred,
             object: lit,
-            source: None,
-            timestamp: None,
+            source: Some(source.0),
+            timestamp: Some(now_ts()),
             confidence: None,
         })?;
         Ok(())
     })
 }
 
-pub fn load_detector_labels(
+/// Every distinct provenance claim recorded for `media`, devices first, then models, then others.
+pub fn get_provenance_claims(handle: &PruDbHandle, media: MediaId) -> Result<Vec<ProvenanceClaim>> {
+    with_store(handle, |store| {
+        let mut claims = Vec::new();
+        for pred_name in [
+            PRED_CAPTURED_BY_DEVICE,
+            PRED_CLAIMED_GENERATED_BY_MODEL,
+            PRED_PROVENANCE_CLAIM,
+        ] {
+            let Some(pred) = store.get_predicate_id(pred_name) else {
+                continue;
+            };
+            for fact in store.facts_for_subject_predicate(media.0, pred)? {
+                let Some(value) = store.get_literal_value(fact.object) else {
+                    continue;
+                };
+                let claim = match pred_name {
+                    PRED_CAPTURED_BY_DEVICE => ProvenanceClaim::CapturedByDevice { device: value },
+                    PRED_CLAIMED_GENERATED_BY_MODEL => {
+                        ProvenanceClaim::GeneratedByModel { model: value }
+                    }
+                    _ => match serde_json::from_str(&value) {
+                        Ok(claim) => claim,
+                        Err(_) => continue,
+                    },
+                };
+                if !claims.contains(&claim) {
+                    claims.push(claim);
+                }
+            }
+        }
+        Ok(claims)
+    })
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FeatureValue {
+    F64(f64),
+    I64(i64),
+    Str(String),
+    Bool(bool),
+}
+
+impl std::fmt::Display for FeatureValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FeatureValue::F64(v) => write!(f, "{v:.3}"),
+            FeatureValue::I64(v) => write!(f, "{v}"),
+            FeatureValue::Str(v) => write!(f, "{v}"),
+            FeatureValue::Bool(v) => write!(f, "{v}"),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct FeaturePayload {
+    name: String,
+    value: FeatureValue,
+}
+
+pub fn add_feature(
     handle: &PruDbHandle,
     media: MediaId,
-) -> Result<HashMap<EntityId, String>> {
+    feature_name: &str,
+    value: FeatureValue,
+    source: DetectorId,
+) -> Result<()> {
     with_store(handle, |store| {
-        let Some(pred) = store.get_predicate_id(PRED_DETECTOR_LABEL) else {
-            return Ok(HashMap::new());
+        write_feature(store, media, feature_name, value, source)
+    })
+}
+
+/// A detector holds one value per feature and media, so a re-run replaces the
+/// value it stored before instead of adding another.
+fn write_feature(
+    store: &mut PruStore,
+    media: MediaId,
+    feature_name: &str,
+    value: FeatureValue,
+    source: DetectorId,
+) -> Result<()> {
+    let payload = serde_json::to_string(&FeaturePayload {
+        name: feature_name.to_string(),
+        value,
+    })?;
+    let pred = store.intern_predicate(PRED_HAS_FEATURE)?;
+    let lit = store.intern_literal(&payload)?;
+    let stale: Vec<AtomId> = store
+        .query_iter(pru_core::Query {
+            subject: Some(media.0),
+            predicate: Some(pred),
+            source: Some(source.0),
+            ..Default::default()
+        })
+        .map(|f| f.object)
+        .filter(|object| {
+            store
+                .get_literal_value(*object)
+                .and_then(|val| parse_feature_payload(&val))
+                .is_some_and(|(name, _)| name == feature_name)
+        })
+        .collect();
+    if !stale.is_empty() {
+        store.retract_facts(|f| {
+            f.subject == media.0
+                && f.predicate == pred
+                && f.source == Some(source.0)
+                && stale.contains(&f.object)
+        })?;
+    }
+    store.add_fact(pru_core::Fact {
+        subject: media.0,
+        predicate: pred,
+        object: lit,
+        source: Some(source.0),
+        timestamp: None,
+        confidence: None,
+    })?;
+    Ok(())
+}
+
+/// What one detector produced for a media item.
+#[derive(Clone, Debug, PartialEq)]
+pub enum DetectorResult {
+    Scored {
+        score: f64,
+        label: String,
+        /// Free-text explanation from the detector, stored under `detector_details`.
+        details: Option<String>,
+        features: BTreeMap<String, FeatureValue>,
+    },
+    /// The detector did not produce a score; recorded as an `analysis_error` fact.
+    Failed(String),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct DetectorRun {
+    pub detector: String,
+    pub info: DetectorInfo,
+    pub result: DetectorResult,
+}
+
+/// Write a batch of detector runs for `media` under one lock and one fact-log write.
+///
+/// Runs are written in detector id order so the resulting facts do not depend on
+/// which detector finished first.
+pub fn record_detector_runs(
+    handle: &PruDbHandle,
+    media: MediaId,
+    runs: Vec<DetectorRun>,
+) -> Result<()> {
+    record_analysis(handle, media, runs, &SubmissionContext::default())
+}
+
+/// Where a submitted media item came from and who reported it.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SubmissionContext {
+    pub sighting: Option<Sighting>,
+    pub reporter: Option<String>,
+    pub tags: Vec<String>,
+    /// Unix seconds, used for the reporter and tag facts.
+    pub reported_at: i64,
+}
+
+impl SubmissionContext {
+    pub fn is_empty(&self) -> bool {
+        self.sighting.is_none() && self.reporter.is_none() && self.tags.is_empty()
+    }
+}
+
+/// [`record_detector_runs`] plus the submission's sighting, reporter and tag facts,
+/// all in one transaction.
+pub fn record_analysis(
+    handle: &PruDbHandle,
+    media: MediaId,
+    mut runs: Vec<DetectorRun>,
+    context: &SubmissionContext,
+) -> Result<()> {
+    runs.sort_by(|a, b| a.detector.cmp(&b.detector));
+    with_store(handle, |store| {
+        store.transaction(|store| -> Result<()> {
+            let source = match &context.sighting {
+                Some(sighting) => Some(write_sighting(store, media, sighting)?),
+                None => None,
+            };
+            if let Some(reporter) = &context.reporter {
+                write_reporter(store, media, reporter, source, context.reported_at)?;
+            }
+            for tag in &context.tags {
+                write_tag(store, media, tag, context.reported_at)?;
+            }
+            for run in runs {
+                let detector = write_detector(store, &run.detector, &run.info)?;
+                write_analyzed_by(store, media, detector)?;
+                match run.result {
+                    DetectorResult::Scored {
+                        score,
+                        label,
+                        details,
+                        features,
+                    } => {
+                        write_detector_score(store, media, detector, score, &label)?;
+                        if let Some(details) = details.filter(|d| !d.is_empty()) {
+                            write_detector_details(store, media, detector, &details)?;
+                        }
+                        for (name, value) in features {
+                            write_feature(store, media, &name, value, detector)?;
+                        }
+                    }
+                    DetectorResult::Failed(message) => {
+                        write_analysis_error(store, media, detector, &message)?;
+                    }
+                }
+            }
+            Ok(())
+        })
+    })
+}
+
+pub fn get_features(
+    handle: &PruDbHandle,
+    media: MediaId,
+    source_filter: Option<DetectorId>,
+) -> Result<Vec<(DetectorId, String, FeatureValue)>> {
+    with_store(handle, |store| {
+        let Some(pred) = store.get_predicate_id(PRED_HAS_FEATURE) else {
+            return Ok(Vec::new());
         };
         let facts = store.facts_for_subject_predicate(media.0, pred)?;
-        let mut map = HashMap::new();
+        let mut out = Vec::new();
         for fact in facts {
-            if let Some(src) = fact.source {
-                if let Some(val) = store.get_literal_value(fact.object) {
-                    map.insert(src, val);
-                }
+            let Some(src) = fact.source else {
+                continue;
+            };
+            if source_filter.is_some_and(|d| d.0 != src) {
+                continue;
+            }
+            if let Some((name, value)) = store
+                .get_literal_value(fact.object)
+                .and_then(|val| parse_feature_payload(&val))
+            {
+                out.push((DetectorId(src), name, value));
             }
         }
-        Ok(map)
+        Ok(out)
     })
 }
 
+/// Decode the literal stored under `has_feature` into its name and value.
+pub fn parse_feature_payload(payload: &str) -> Option<(String, FeatureValue)> {
+    serde_json::from_str::<FeaturePayload>(payload)
+        .ok()
+        .map(|p| (p.name, p.value))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -343,5 +2184,480 @@ mod tests {
         let handle = std::sync::Arc::new(std::sync::Mutex::new(store));
         let media = upsert_media_entity(&handle, "abc", MediaType::Text).unwrap();
         assert!(media.0 > 0);
+        assert_eq!(
+            get_media_type(&handle, media).unwrap(),
+            Some(MediaType::Text)
+        );
+    }
+
+    #[test]
+    fn only_one_racing_caller_creates_the_media() {
+        let dir = tempdir().unwrap();
+        let store = PruStore::open(dir.path()).unwrap();
+        let handle = std::sync::Arc::new(std::sync::Mutex::new(store));
+        let created: Vec<(MediaId, bool)> = std::thread::scope(|scope| {
+            let racers: Vec<_> = (0..8)
+                .map(|_| scope.spawn(|| create_media_entity(&handle, "abc", MediaType::Image)))
+                .collect();
+            racers
+                .into_iter()
+                .map(|r| r.join().unwrap().unwrap())
+                .collect()
+        });
+        assert_eq!(created.iter().filter(|(_, new)| *new).count(), 1);
+        assert!(created.iter().all(|(id, _)| *id == created[0].0));
+    }
+
+    #[test]
+    fn features_are_distinguished_by_source() {
+        let dir = tempdir().unwrap();
+        let store = PruStore::open(dir.path()).unwrap();
+        let handle = std::sync::Arc::new(std::sync::Mutex::new(store));
+        let media = upsert_media_entity(&handle, "abc", MediaType::Image).unwrap();
+        let first = ensure_detector_entity(&handle, "detector:image:a").unwrap();
+        let second = ensure_detector_entity(&handle, "detector:image:b").unwrap();
+        add_feature(&handle, media, "width", FeatureValue::I64(640), first).unwrap();
+        add_feature(&handle, media, "width", FeatureValue::F64(640.5), second).unwrap();
+
+        let all = get_features(&handle, media, None).unwrap();
+        assert_eq!(all.len(), 2);
+
+        let only_first = get_features(&handle, media, Some(first)).unwrap();
+        assert_eq!(
+            only_first,
+            vec![(first, "width".to_string(), FeatureValue::I64(640))]
+        );
+        let only_second = get_features(&handle, media, Some(second)).unwrap();
+        assert_eq!(
+            only_second,
+            vec![(second, "width".to_string(), FeatureValue::F64(640.5))]
+        );
+
+        // A re-run replaces the detector's value rather than adding to it.
+        add_feature(&handle, media, "width", FeatureValue::I64(640), first).unwrap();
+        add_feature(&handle, media, "height", FeatureValue::I64(480), first).unwrap();
+        add_feature(&handle, media, "width", FeatureValue::I64(800), first).unwrap();
+        assert_eq!(
+            get_features(&handle, media, Some(first)).unwrap(),
+            vec![
+                (first, "height".to_string(), FeatureValue::I64(480)),
+                (first, "width".to_string(), FeatureValue::I64(800)),
+            ]
+        );
+        assert_eq!(get_features(&handle, media, None).unwrap().len(), 3);
+    }
+
+    #[test]
+    fn detector_last_scored_lists_scoring_detectors() {
+        let dir = tempdir().unwrap();
+        let store = PruStore::open(dir.path()).unwrap();
+        let handle = std::sync::Arc::new(std::sync::Mutex::new(store));
+        assert!(detector_last_scored(&handle).unwrap().is_empty());
+        let media = upsert_media_entity(&handle, "abc", MediaType::Image).unwrap();
+        let first = ensure_detector_entity(&handle, "detector:image:a").unwrap();
+        let second = ensure_detector_entity(&handle, "detector:image:b").unwrap();
+        ensure_detector_entity(&handle, "detector:image:idle").unwrap();
+        add_detector_score(&handle, media, second, 0.9, "ai").unwrap();
+        add_detector_score(&handle, media, first, 0.2, "human").unwrap();
+
+        let last = detector_last_scored(&handle).unwrap();
+        let ids: Vec<_> = last.iter().map(|(d, _)| *d).collect();
+        assert_eq!(ids, vec![first, second]);
+        assert!(last.iter().all(|(_, ts)| ts.is_some()));
+    }
+
+    #[test]
+    fn latest_score_wins_and_history_is_kept() {
+        let dir = tempdir().unwrap();
+        let store = PruStore::open(dir.path()).unwrap();
+        let handle = std::sync::Arc::new(std::sync::Mutex::new(store));
+        let media = upsert_media_entity(&handle, "abc", MediaType::Text).unwrap();
+        let detector = ensure_detector_entity(&handle, "detector:text:a").unwrap();
+        add_detector_score(&handle, media, detector, 0.2, "Human").unwrap();
+        add_detector_score(&handle, media, detector, 0.9, "Ai").unwrap();
+
+        let scores = get_detector_scores_for_media(&handle, media).unwrap();
+        assert_eq!(scores.len(), 1);
+        assert_eq!(scores[0].1, 0.9);
+        assert_eq!(scores[0].2, "Ai");
+
+        let history = get_detector_score_history(&handle, media, detector).unwrap();
+        assert_eq!(history.len(), 2);
+        assert!(history.iter().all(|(ts, _, _)| ts.is_some()));
+        assert_eq!(history[0].2, "Human");
+        assert_eq!(history[1].2, "Ai");
+    }
+
+    #[test]
+    fn score_without_label_keeps_later_pairs_aligned() {
+        let dir = tempdir().unwrap();
+        let store = PruStore::open(dir.path()).unwrap();
+        let handle = std::sync::Arc::new(std::sync::Mutex::new(store));
+        let media = upsert_media_entity(&handle, "abc", MediaType::Text).unwrap();
+        let detector = ensure_detector_entity(&handle, "detector:text:a").unwrap();
+        with_store(&handle, |store| {
+            let pred = store.intern_predicate(PRED_DETECTOR_SCORE)?;
+            let lit = store.intern_literal("0.5")?;
+            store.add_fact(pru_core::Fact {
+                subject: media.0,
+                predicate: pred,
+                object: lit,
+                source: Some(detector.0),
+                timestamp: Some(1),
+                confidence: None,
+            })?;
+            Ok(())
+        })
+        .unwrap();
+        add_detector_score(&handle, media, detector, 0.9, "ai").unwrap();
+
+        let history = get_detector_score_history(&handle, media, detector).unwrap();
+        let labels: Vec<_> = history.iter().map(|(_, _, l)| l.as_str()).collect();
+        assert_eq!(labels, vec!["unknown", "ai"]);
+        let scores = get_detector_scores_for_media(&handle, media).unwrap();
+        assert_eq!(scores[0].2, "ai");
+    }
+
+    #[test]
+    fn verdict_summary_reports_disagreement() {
+        let dir = tempdir().unwrap();
+        let store = PruStore::open(dir.path()).unwrap();
+        let handle = std::sync::Arc::new(std::sync::Mutex::new(store));
+        let media = upsert_media_entity(&handle, "abc", MediaType::Image).unwrap();
+        add_human_verdict_by(&handle, media, "ai", "alice", 0.9).unwrap();
+        add_human_verdict_by(&handle, media, "AI", "bob", 0.7).unwrap();
+        add_human_verdict_by(&handle, media, "human", "carol", 1.0).unwrap();
+
+        let summary = get_verdict_summary(&handle, media).unwrap();
+        assert_eq!(summary.total(), 3);
+        assert_eq!(summary.counts.get("ai"), Some(&2));
+        assert_eq!(summary.counts.get("human"), Some(&1));
+        assert_eq!(summary.majority_label.as_deref(), Some("ai"));
+        assert!((summary.agreement - 2.0 / 3.0).abs() < 1e-6);
+        assert_eq!(
+            summary.annotators.get("ai").unwrap(),
+            &vec!["alice".to_string(), "bob".to_string()]
+        );
+
+        // An annotator changing their mind replaces their earlier vote.
+        add_human_verdict_by(&handle, media, "human", "bob", 0.8).unwrap();
+        let summary = get_verdict_summary(&handle, media).unwrap();
+        assert_eq!(summary.total(), 3);
+        assert_eq!(summary.majority_label.as_deref(), Some("human"));
+    }
+
+    #[test]
+    fn verdict_plurality_is_not_a_majority() {
+        let dir = tempdir().unwrap();
+        let store = PruStore::open(dir.path()).unwrap();
+        let handle = std::sync::Arc::new(std::sync::Mutex::new(store));
+        let media = upsert_media_entity(&handle, "abc", MediaType::Image).unwrap();
+        add_human_verdict_by(&handle, media, "ai", "alice", 1.0).unwrap();
+        add_human_verdict_by(&handle, media, "ai", "bob", 1.0).unwrap();
+        add_human_verdict_by(&handle, media, "human", "carol", 1.0).unwrap();
+        add_human_verdict_by(&handle, media, "unsure", "dave", 1.0).unwrap();
+
+        let summary = get_verdict_summary(&handle, media).unwrap();
+        assert_eq!(summary.total(), 4);
+        assert_eq!(summary.majority_label, None);
+        assert!((summary.agreement - 0.5).abs() < 1e-6);
+    }
+
+    #[test]
+    fn anonymous_verdicts_each_count() {
+        let dir = tempdir().unwrap();
+        let store = PruStore::open(dir.path()).unwrap();
+        let handle = std::sync::Arc::new(std::sync::Mutex::new(store));
+        let media = upsert_media_entity(&handle, "abc", MediaType::Image).unwrap();
+        add_human_verdict(&handle, media, "ai").unwrap();
+        add_human_verdict(&handle, media, "ai").unwrap();
+        add_human_verdict_by(&handle, media, "human", "alice", 1.0).unwrap();
+
+        let summary = get_verdict_summary(&handle, media).unwrap();
+        assert_eq!(summary.counts.get("ai"), Some(&2));
+        assert_eq!(summary.majority_label.as_deref(), Some("ai"));
+        assert!(!summary.annotators.contains_key("ai"));
+    }
+
+    #[test]
+    fn legacy_reliability_payload_parses() {
+        let legacy: DetectorReliability =
+            serde_json::from_str(r#"{"seen":10,"correct":7}"#).unwrap();
+        assert_eq!(legacy.seen, 10);
+        assert_eq!(legacy.correct, 7);
+        assert!(legacy.confusion.is_empty());
+        assert_eq!(legacy.precision("ai"), None);
+    }
+
+    #[test]
+    fn reliability_tracks_confusion_matrix() {
+        let dir = tempdir().unwrap();
+        let store = PruStore::open(dir.path()).unwrap();
+        let handle = std::sync::Arc::new(std::sync::Mutex::new(store));
+        let detector = ensure_detector_entity(&handle, "detector:text:a").unwrap();
+        for (hash, verdict) in [("m1", "ai"), ("m2", "ai"), ("m3", "human")] {
+            let media = upsert_media_entity(&handle, hash, MediaType::Text).unwrap();
+            add_detector_score(&handle, media, detector, 0.9, "Ai").unwrap();
+            bump_reliability_from_verdict(&handle, media, verdict).unwrap();
+        }
+
+        let r = get_detector_reliability(&handle, detector)
+            .unwrap()
+            .unwrap();
+        assert_eq!((r.seen, r.correct), (3, 2));
+        assert_eq!(r.count("ai", "ai"), 2);
+        assert_eq!(r.count("ai", "human"), 1);
+        assert!((r.precision("ai").unwrap() - 2.0 / 3.0).abs() < 1e-9);
+        assert_eq!(r.recall("ai"), Some(1.0));
+        assert_eq!(r.recall("human"), Some(0.0));
+        assert_eq!(r.precision("human"), None);
+
+        let undecided = upsert_media_entity(&handle, "m4", MediaType::Text).unwrap();
+        add_detector_score(&handle, undecided, detector, 0.5, "unknown").unwrap();
+        bump_reliability_from_verdict(&handle, undecided, "human").unwrap();
+        let after = get_detector_reliability(&handle, detector)
+            .unwrap()
+            .unwrap();
+        assert_eq!(after.seen, 3);
+
+        let unsure = upsert_media_entity(&handle, "m5", MediaType::Text).unwrap();
+        add_detector_score(&handle, unsure, detector, 0.9, "ai").unwrap();
+        bump_reliability_from_verdict(&handle, unsure, "Unsure").unwrap();
+        let after = get_detector_reliability(&handle, detector)
+            .unwrap()
+            .unwrap();
+        assert_eq!(after.seen, 3);
+    }
+
+    #[test]
+    fn clearing_detector_results_hides_scores() {
+        let dir = tempdir().unwrap();
+        let store = PruStore::open(dir.path()).unwrap();
+        let handle = std::sync::Arc::new(std::sync::Mutex::new(store));
+        let first = upsert_media_entity(&handle, "m1", MediaType::Text).unwrap();
+        let second = upsert_media_entity(&handle, "m2", MediaType::Text).unwrap();
+        let old = ensure_detector_entity(&handle, "detector:text:old").unwrap();
+        let other = ensure_detector_entity(&handle, "detector:text:other").unwrap();
+        for media in [first, second] {
+            mark_analyzed_by(&handle, media, old).unwrap();
+            add_detector_score(&handle, media, old, 0.4, "Human").unwrap();
+            add_detector_score(&handle, media, other, 0.6, "Ai").unwrap();
+        }
+
+        assert_eq!(clear_detector_results(&handle, first, old).unwrap(), 3);
+        let scores = get_detector_scores_for_media(&handle, first).unwrap();
+        assert_eq!(scores.len(), 1);
+        assert_eq!(scores[0].0, other);
+        assert_eq!(
+            get_detector_scores_for_media(&handle, second)
+                .unwrap()
+                .len(),
+            2
+        );
+
+        assert_eq!(clear_all_results_for_detector(&handle, old).unwrap(), 3);
+        let scores = get_detector_scores_for_media(&handle, second).unwrap();
+        assert!(scores.iter().all(|(d, _, _)| *d == other));
+    }
+
+    #[test]
+    fn media_metadata_roundtrip() {
+        let dir = tempdir().unwrap();
+        let store = PruStore::open(dir.path()).unwrap();
+        let handle = std::sync::Arc::new(std::sync::Mutex::new(store));
+        let media = upsert_media_entity(&handle, "abc", MediaType::Image).unwrap();
+        assert_eq!(
+            get_media_metadata(&handle, media).unwrap(),
+            MediaMetadata::default()
+        );
+
+        let meta = MediaMetadata {
+            byte_len: Some(1024),
+            width: Some(64),
+            height: Some(32),
+            duration_ms: None,
+            mime: Some("image/png".into()),
+        };
+        add_media_metadata(&handle, media, &meta).unwrap();
+        let facts_after_first = handle.lock().unwrap().fact_count();
+        add_media_metadata(&handle, media, &meta).unwrap();
+        assert_eq!(handle.lock().unwrap().fact_count(), facts_after_first);
+        assert_eq!(get_media_metadata(&handle, media).unwrap(), meta);
+    }
+
+    #[test]
+    fn decayed_reliability_favours_recent_weeks() {
+        let now = 400 * SECS_PER_WEEK;
+        let year_ago = now - 52 * SECS_PER_WEEK;
+        let mut r = DetectorReliability::default();
+        for _ in 0..50 {
+            r.record_at("ai", "ai", year_ago);
+        }
+        for _ in 0..10 {
+            r.record_at("ai", "human", now);
+        }
+        assert_eq!((r.seen, r.correct), (60, 50));
+
+        let lifetime = r.lifetime();
+        let decayed = r.decayed(30.0, now);
+        assert!(decayed.correct / decayed.seen < 0.05);
+        assert!(lifetime.correct / lifetime.seen > 0.8);
+        let ai = lifetime.label("AI").unwrap();
+        assert_eq!((ai.predicted, ai.confirmed), (60.0, 50.0));
+        let decayed_ai = decayed.label("ai").unwrap();
+        assert!((decayed_ai.predicted - decayed.seen).abs() < 1e-9);
+        assert!((decayed_ai.confirmed - decayed.correct).abs() < 1e-9);
+        assert_eq!(lifetime.label("human"), None);
+
+        let roundtrip: DetectorReliability =
+            serde_json::from_str(&serde_json::to_string(&r).unwrap()).unwrap();
+        assert_eq!(roundtrip, r);
+    }
+
+    #[test]
+    fn sightings_keep_their_details() {
+        let dir = tempdir().unwrap();
+        let handle =
+            std::sync::Arc::new(std::sync::Mutex::new(PruStore::open(dir.path()).unwrap()));
+        let media = upsert_media_entity(&handle, "a", MediaType::Image).unwrap();
+        let later = Sighting {
+            source: "reddit.com".into(),
+            url: Some("https://reddit.com/r/pics/1".into()),
+            content_type: Some("image/png".into()),
+            observed_at: 200,
+        };
+        let first = Sighting {
+            source: "example.org".into(),
+            url: None,
+            content_type: None,
+            observed_at: 100,
+        };
+        let source = add_sighting(&handle, media, &later).unwrap();
+        add_sighting(&handle, media, &first).unwrap();
+        assert_eq!(add_sighting(&handle, media, &later).unwrap(), source);
+
+        assert_eq!(
+            get_sightings(&handle, media).unwrap(),
+            vec![first, later.clone()]
+        );
+        assert_eq!(get_source_id(&handle, "reddit.com").unwrap(), Some(source));
+        assert_eq!(get_source_reputation(&handle, source).unwrap(), None);
+
+        add_human_verdict(&handle, media, "AI").unwrap();
+        assert_eq!(update_source_reputation(&handle).unwrap(), 2);
+        assert_eq!(
+            get_source_reputation(&handle, source).unwrap(),
+            Some(SourceReputation { labelled: 1, ai: 1 })
+        );
+
+        // An undecided verdict is not counted as a human one.
+        let other = upsert_media_entity(&handle, "b", MediaType::Image).unwrap();
+        add_sighting(&handle, other, &later).unwrap();
+        add_human_verdict(&handle, other, "unsure").unwrap();
+        assert_eq!(update_source_reputation(&handle).unwrap(), 0);
+        assert_eq!(
+            get_source_reputation(&handle, source).unwrap(),
+            Some(SourceReputation { labelled: 1, ai: 1 })
+        );
+    }
+
+    #[test]
+    fn tagging_and_listing() {
+        let dir = tempdir().unwrap();
+        let store = PruStore::open(dir.path()).unwrap();
+        let handle = std::sync::Arc::new(std::sync::Mutex::new(store));
+        let a = upsert_media_entity(&handle, "a", MediaType::Text).unwrap();
+        let b = upsert_media_entity(&handle, "b", MediaType::Image).unwrap();
+        add_tag(&handle, a, "eval-batch").unwrap();
+        add_tag(&handle, a, "eval-batch").unwrap();
+        add_tag(&handle, b, "eval-batch").unwrap();
+        add_tag(&handle, b, "reported").unwrap();
+
+        assert_eq!(
+            get_tags(&handle, b).unwrap(),
+            vec!["eval-batch", "reported"]
+        );
+        assert_eq!(
+            media_with_tag(&handle, "eval-batch", 0, 10).unwrap(),
+            vec![a, b]
+        );
+        assert_eq!(
+            media_with_tag(&handle, "eval-batch", 1, 10).unwrap(),
+            vec![b]
+        );
+        assert_eq!(
+            tags(&handle).unwrap(),
+            vec![("eval-batch".to_string(), 2), ("reported".to_string(), 1)]
+        );
+
+        assert!(remove_tag(&handle, a, "eval-batch").unwrap());
+        assert!(!remove_tag(&handle, a, "eval-batch").unwrap());
+        assert_eq!(
+            media_with_tag(&handle, "eval-batch", 0, 10).unwrap(),
+            vec![b]
+        );
+    }
+
+    #[test]
+    fn register_detector_is_idempotent() {
+        let dir = tempdir().unwrap();
+        let store = PruStore::open(dir.path()).unwrap();
+        let handle = std::sync::Arc::new(std::sync::Mutex::new(store));
+        let info = DetectorInfo {
+            kind: "text".into(),
+            version: "v1".into(),
+            description: "Vocabulary heuristics".into(),
+            config_hash: None,
+        };
+        let id = register_detector(&handle, "detector:text:a", &info).unwrap();
+        let facts = handle.lock().unwrap().fact_count();
+        let again = register_detector(&handle, "detector:text:a", &info).unwrap();
+        assert_eq!(id, again);
+        assert_eq!(handle.lock().unwrap().fact_count(), facts);
+        assert_eq!(get_detector_info(&handle, id).unwrap(), Some(info.clone()));
+
+        let upgraded = DetectorInfo {
+            version: "v2".into(),
+            ..info
+        };
+        register_detector(&handle, "detector:text:a", &upgraded).unwrap();
+        assert_eq!(get_detector_info(&handle, id).unwrap(), Some(upgraded));
+        assert_eq!(
+            get_detector_name(&handle, id).unwrap().as_deref(),
+            Some("detector:text:a")
+        );
+    }
+
+    #[test]
+    fn provenance_claims_roundtrip_and_clear_with_detector() {
+        let dir = tempdir().unwrap();
+        let store = PruStore::open(dir.path()).unwrap();
+        let handle = std::sync::Arc::new(std::sync::Mutex::new(store));
+        let media = upsert_media_entity(&handle, "m1", MediaType::Image).unwrap();
+        let detector = ensure_detector_entity(&handle, "detector:image:metadata").unwrap();
+        let claims = [
+            ProvenanceClaim::Other {
+                key: "lens".into(),
+                value: "RF24-105mm".into(),
+            },
+            ProvenanceClaim::GeneratedByModel {
+                model: "Adobe Firefly".into(),
+            },
+            ProvenanceClaim::CapturedByDevice {
+                device: "Canon EOS R5".into(),
+            },
+        ];
+        for claim in claims.iter().chain(&claims) {
+            add_provenance_claim(&handle, media, claim, detector).unwrap();
+        }
+
+        let stored = get_provenance_claims(&handle, media).unwrap();
+        assert_eq!(stored.len(), 3);
+        assert_eq!(stored[0], claims[2]);
+        assert_eq!(stored[1], claims[1]);
+        assert_eq!(stored[2], claims[0]);
+
+        assert_eq!(clear_detector_results(&handle, media, detector).unwrap(), 3);
+        assert!(get_provenance_claims(&handle, media).unwrap().is_empty());
     }
 }
@@ -0,0 +1,222 @@
+//! Schema versioning for the media predicates.
+//!
+//! The version lives as a `schema_version` fact on a well-known entity. Each
+//! migration runs inside a store transaction together with its version bump, so
+//! a failed migration leaves both the facts and the version untouched.
+
+use std::collections::HashMap;
+
+use anyhow::{bail, Result};
+use pru_core::{Fact, PruDbHandle, PruStore, Query};
+
+use crate::{with_store, PRED_DETECTOR_LABEL};
+
+pub const SCHEMA_ENTITY: &str = "schema:pru_media";
+pub const PRED_SCHEMA_VERSION: &str = "schema_version";
+
+pub struct Migration {
+    /// Version the store is at once this migration has run.
+    pub version: u32,
+    pub description: &'static str,
+    pub apply: fn(&mut PruStore) -> Result<()>,
+}
+
+/// Ordered by `version`; append new migrations at the end.
+pub const MIGRATIONS: &[Migration] = &[Migration {
+    version: 1,
+    description: "lower-case detector_label literals",
+    apply: lowercase_detector_labels,
+}];
+
+pub const CURRENT_SCHEMA_VERSION: u32 = 1;
+
+/// Stored schema version; stores that predate versioning report 0.
+pub fn schema_version(handle: &PruDbHandle) -> Result<u32> {
+    with_store(handle, |store| read_version(store))
+}
+
+/// Bring the store up to [`CURRENT_SCHEMA_VERSION`], returning the resulting version.
+pub fn ensure_schema(handle: &PruDbHandle) -> Result<u32> {
+    with_store(handle, |store| run_migrations(store, MIGRATIONS))
+}
+
+/// Apply every migration newer than the stored version, in order.
+pub fn run_migrations(store: &mut PruStore, migrations: &[Migration]) -> Result<u32> {
+    let stored = read_version(store)?;
+    let latest = migrations.last().map(|m| m.version).unwrap_or(0);
+    if stored > latest {
+        bail!("store schema version {stored} is newer than supported version {latest}");
+    }
+    let mut version = stored;
+    for migration in migrations.iter().filter(|m| m.version > stored) {
+        store.transaction(|s| -> Result<()> {
+            (migration.apply)(s)?;
+            write_version(s, migration.version)
+        })?;
+        version = migration.version;
+    }
+    Ok(version)
+}
+
+/// Move every fact from predicate `old` to predicate `new`; returns the number moved.
+pub fn rename_predicate(store: &mut PruStore, old: &str, new: &str) -> Result<usize> {
+    let Some(old_id) = store.get_predicate_id(old) else {
+        return Ok(0);
+    };
+    let new_id = store.intern_predicate(new)?;
+    Ok(store.rewrite_facts(|f| {
+        if f.predicate != old_id {
+            return false;
+        }
+        f.predicate = new_id;
+        true
+    })?)
+}
+
+fn read_version(store: &PruStore) -> Result<u32> {
+    let (Some(entity), Some(pred)) = (
+        store.get_entity_id(SCHEMA_ENTITY),
+        store.get_predicate_id(PRED_SCHEMA_VERSION),
+    ) else {
+        return Ok(0);
+    };
+    let facts = store.facts_for_subject_predicate(entity, pred)?;
+    let Some(value) = facts.last().and_then(|f| store.get_literal_value(f.object)) else {
+        return Ok(0);
+    };
+    Ok(value.parse()?)
+}
+
+fn write_version(store: &mut PruStore, version: u32) -> Result<()> {
+    let entity = store.intern_entity(SCHEMA_ENTITY)?;
+    let pred = store.intern_predicate(PRED_SCHEMA_VERSION)?;
+    let lit = store.intern_literal(&version.to_string())?;
+    store.transaction(|s| -> Result<()> {
+        s.retract_facts(|f| f.subject == entity && f.predicate == pred)?;
+        s.add_fact(Fact {
+            subject: entity,
+            predicate: pred,
+            object: lit,
+            source: None,
+            timestamp: None,
+            confidence: None,
+        })?;
+        Ok(())
+    })
+}
+
+/// Detector labels were once written as `Ai`/`Human`; verdicts use lower case.
+fn lowercase_detector_labels(store: &mut PruStore) -> Result<()> {
+    let Some(pred) = store.get_predicate_id(PRED_DETECTOR_LABEL) else {
+        return Ok(());
+    };
+    let labels = store.query(Query {
+        predicate: Some(pred),
+        ..Default::default()
+    })?;
+    let mut targets = HashMap::new();
+    for fact in labels {
+        if targets.contains_key(&fact.object) {
+            continue;
+        }
+        let Some(value) = store.get_literal_value(fact.object) else {
+            continue;
+        };
+        let lower = value.to_ascii_lowercase();
+        if lower != value {
+            targets.insert(fact.object, store.intern_literal(&lower)?);
+        }
+    }
+    store.rewrite_facts(|f| {
+        if f.predicate != pred {
+            return false;
+        }
+        match targets.get(&f.object) {
+            Some(lower) => {
+                f.object = *lower;
+                true
+            }
+            None => false,
+        }
+    })?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        add_detector_score, ensure_detector_entity, get_detector_scores_for_media,
+        upsert_media_entity, MediaType,
+    };
+    use std::sync::{Arc, Mutex};
+    use tempfile::tempdir;
+
+    #[test]
+    fn ensure_schema_migrates_once() {
+        let dir = tempdir().unwrap();
+        let handle = Arc::new(Mutex::new(PruStore::open(dir.path()).unwrap()));
+        let media = upsert_media_entity(&handle, "hash", MediaType::Text).unwrap();
+        let detector = ensure_detector_entity(&handle, "detector:text:a").unwrap();
+        add_detector_score(&handle, media, detector, 0.9, "Ai").unwrap();
+        assert_eq!(schema_version(&handle).unwrap(), 0);
+
+        assert_eq!(ensure_schema(&handle).unwrap(), CURRENT_SCHEMA_VERSION);
+        let scores = get_detector_scores_for_media(&handle, media).unwrap();
+        assert_eq!(scores[0].2, "ai");
+
+        let facts = handle.lock().unwrap().fact_count();
+        assert_eq!(ensure_schema(&handle).unwrap(), CURRENT_SCHEMA_VERSION);
+        assert_eq!(handle.lock().unwrap().fact_count(), facts);
+
+        let reopened = Arc::new(Mutex::new(PruStore::open(dir.path()).unwrap()));
+        assert_eq!(schema_version(&reopened).unwrap(), CURRENT_SCHEMA_VERSION);
+    }
+
+    #[test]
+    fn failed_migration_keeps_version() {
+        fn rename(store: &mut PruStore) -> Result<()> {
+            rename_predicate(store, "detector_score", "detector_score_v2")?;
+            Ok(())
+        }
+        fn broken(store: &mut PruStore) -> Result<()> {
+            rename_predicate(store, "detector_score_v2", "detector_score_v3")?;
+            bail!("boom")
+        }
+        let dir = tempdir().unwrap();
+        let handle = Arc::new(Mutex::new(PruStore::open(dir.path()).unwrap()));
+        let media = upsert_media_entity(&handle, "hash", MediaType::Text).unwrap();
+        let detector = ensure_detector_entity(&handle, "detector:text:a").unwrap();
+        add_detector_score(&handle, media, detector, 0.9, "ai").unwrap();
+
+        let migrations = [
+            Migration {
+                version: 1,
+                description: "rename",
+                apply: rename,
+            },
+            Migration {
+                version: 2,
+                description: "broken",
+                apply: broken,
+            },
+        ];
+        let mut store = handle.lock().unwrap();
+        assert!(run_migrations(&mut store, &migrations).is_err());
+        assert_eq!(read_version(&store).unwrap(), 1);
+        let v2 = store.get_predicate_id("detector_score_v2").unwrap();
+        assert_eq!(
+            store
+                .facts_for_subject_predicate(media.0, v2)
+                .unwrap()
+                .len(),
+            1
+        );
+    }
+
+    #[test]
+    fn migrations_are_ordered() {
+        assert!(MIGRATIONS.windows(2).all(|w| w[0].version < w[1].version));
+        assert_eq!(MIGRATIONS.last().unwrap().version, CURRENT_SCHEMA_VERSION);
+    }
+}
@@ -0,0 +1,106 @@
+//! Python exceptions for [`pru_core::errors::PruError`], one class per variant
+//! under a common `PruError` base.
+
+use pru_core::errors::PruError as CoreError;
+use pyo3::create_exception;
+use pyo3::exceptions::PyException;
+use pyo3::prelude::*;
+
+create_exception!(
+    pru_py,
+    PruError,
+    PyException,
+    "Base class of every pru_db error."
+);
+create_exception!(
+    pru_py,
+    PruIoError,
+    PruError,
+    "Reading or writing the store failed."
+);
+create_exception!(
+    pru_py,
+    PruJsonError,
+    PruError,
+    "A store file is not valid JSON."
+);
+create_exception!(
+    pru_py,
+    PruPersistError,
+    PruError,
+    "A new file could not be moved into place."
+);
+create_exception!(
+    pru_py,
+    PruHeaderError,
+    PruError,
+    "A segment has a bad magic number or version."
+);
+create_exception!(
+    pru_py,
+    PruCorruptError,
+    PruError,
+    "A record failed its checksum or bounds check."
+);
+create_exception!(
+    pru_py,
+    PruUnsupportedError,
+    PruError,
+    "The segment uses a kind this build cannot read."
+);
+create_exception!(
+    pru_py,
+    PruAtomNotFoundError,
+    PruError,
+    "An id or name is not in the store."
+);
+create_exception!(
+    pru_py,
+    PruInvalidInputError,
+    PruError,
+    "An argument was rejected by the store."
+);
+
+pub(crate) fn to_py_err(err: CoreError) -> PyErr {
+    let msg = err.to_string();
+    match err {
+        CoreError::Io(_) => PruIoError::new_err(msg),
+        CoreError::SerdeJson(_) => PruJsonError::new_err(msg),
+        CoreError::Persist(_) => PruPersistError::new_err(msg),
+        CoreError::BadHeader => PruHeaderError::new_err(msg),
+        CoreError::Corrupt => PruCorruptError::new_err(msg),
+        CoreError::Unsupported => PruUnsupportedError::new_err(msg),
+        CoreError::AtomNotFound(_) => PruAtomNotFoundError::new_err(msg),
+        CoreError::InvalidInput(_) => PruInvalidInputError::new_err(msg),
+    }
+}
+
+/// Store errors keep their class; anything else raises the `PruError` base.
+pub(crate) fn anyhow_to_py(err: anyhow::Error) -> PyErr {
+    match err.downcast::<CoreError>() {
+        Ok(err) => to_py_err(err),
+        Err(err) => PruError::new_err(format!("{err:#}")),
+    }
+}
+
+pub(crate) fn register(py: Python<'_>, m: &PyModule) -> PyResult<()> {
+    m.add("PruError", py.get_type_bound::<PruError>())?;
+    m.add("PruIoError", py.get_type_bound::<PruIoError>())?;
+    m.add("PruJsonError", py.get_type_bound::<PruJsonError>())?;
+    m.add("PruPersistError", py.get_type_bound::<PruPersistError>())?;
+    m.add("PruHeaderError", py.get_type_bound::<PruHeaderError>())?;
+    m.add("PruCorruptError", py.get_type_bound::<PruCorruptError>())?;
+    m.add(
+        "PruUnsupportedError",
+        py.get_type_bound::<PruUnsupportedError>(),
+    )?;
+    m.add(
+        "PruAtomNotFoundError",
+        py.get_type_bound::<PruAtomNotFoundError>(),
+    )?;
+    m.add(
+        "PruInvalidInputError",
+        py.get_type_bound::<PruInvalidInputError>(),
+    )?;
+    Ok(())
+}
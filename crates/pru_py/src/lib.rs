@@ -1,8 +1,22 @@
+mod errors;
+mod media;
+mod resolver;
+mod segment;
+mod store;
+
+use errors::to_py_err;
 use pru_core::postings::decode_sorted_u64;
 use pru_core::segment::SegmentReader;
+use pyo3::exceptions::{PyDeprecationWarning, PyFileNotFoundError};
 use pyo3::prelude::*;
-use pyo3::types::PyList;
+use pyo3::types::PyBytes;
+use std::path::Path;
 
+/// Reads a single segment file. Deprecated: `ResolverStore` resolves across a
+/// store's active segments and supports the multi-key modes.
+///
+/// The file is mapped on first use or by `open()`, and unmapped by `close()`
+/// or on leaving a `with` block; a closed reader maps it again when used.
 #[pyclass]
 pub struct PRUReader {
     seg_path: String,
@@ -12,31 +26,113 @@ pub struct PRUReader {
 #[pymethods]
 impl PRUReader {
     #[new]
-    pub fn new(seg_path: String) -> PyResult<Self> {
+    pub fn new(py: Python<'_>, seg_path: String) -> PyResult<Self> {
+        PyErr::warn_bound(
+            py,
+            &py.get_type_bound::<PyDeprecationWarning>(),
+            "PRUReader is deprecated; use ResolverStore(dir) to resolve across a store",
+            1,
+        )?;
+        if !Path::new(&seg_path).is_file() {
+            return Err(PyFileNotFoundError::new_err(format!(
+                "no segment file at {seg_path}"
+            )));
+        }
         Ok(Self {
             seg_path,
             reader: None,
         })
     }
 
-    pub fn resolve(&mut self, py: Python<'_>, key: &[u8]) -> PyResult<PyObject> {
+    /// Map the segment now, so a bad header raises here rather than on the
+    /// first lookup.
+    pub fn open(&mut self) -> PyResult<()> {
+        self.reader().map(|_| ())
+    }
+
+    pub fn close(&mut self) {
+        self.reader = None;
+    }
+
+    #[getter]
+    pub fn closed(&self) -> bool {
+        self.reader.is_none()
+    }
+
+    pub fn __enter__(mut slf: PyRefMut<'_, Self>) -> PyResult<PyRefMut<'_, Self>> {
+        slf.open()?;
+        Ok(slf)
+    }
+
+    pub fn __exit__(
+        &mut self,
+        _exc_type: &Bound<'_, PyAny>,
+        _exc_value: &Bound<'_, PyAny>,
+        _traceback: &Bound<'_, PyAny>,
+    ) -> bool {
+        self.close();
+        false
+    }
+
+    /// The posting list stored under `key`; `[]` when the key is absent.
+    /// Raises `PruCorruptError` when the record fails its checksum.
+    pub fn resolve(&mut self, key: &[u8]) -> PyResult<Vec<u64>> {
+        let value = self.reader()?.get_checked(key).map_err(to_py_err)?;
+        Ok(value.map(decode_sorted_u64).unwrap_or_default())
+    }
+
+    /// Whether `key` is in the segment. The filter rules out most absent keys
+    /// without touching the index.
+    pub fn contains(&mut self, key: &[u8]) -> PyResult<bool> {
+        Ok(self
+            .reader()?
+            .get_checked(key)
+            .map_err(to_py_err)?
+            .is_some())
+    }
+
+    pub fn __contains__(&mut self, key: &[u8]) -> PyResult<bool> {
+        self.contains(key)
+    }
+
+    /// The key hash of every index entry.
+    pub fn keys(&mut self) -> PyResult<Vec<u64>> {
+        Ok(self.reader()?.iter().map(|e| e.hash).collect())
+    }
+
+    /// `(hash, size)` of every index entry; the size includes the checksum.
+    pub fn entries(&mut self) -> PyResult<Vec<(u64, u32)>> {
+        Ok(self.reader()?.iter().map(|e| (e.hash, e.size)).collect())
+    }
+
+    /// The value stored under `key` as bytes, or `None`.
+    pub fn get_raw<'py>(
+        &mut self,
+        py: Python<'py>,
+        key: &[u8],
+    ) -> PyResult<Option<Bound<'py, PyBytes>>> {
+        let value = self.reader()?.get_checked(key).map_err(to_py_err)?;
+        Ok(value.map(|v| PyBytes::new_bound(py, v)))
+    }
+}
+
+impl PRUReader {
+    fn reader(&mut self) -> PyResult<&SegmentReader> {
         if self.reader.is_none() {
-            self.reader = Some(
-                SegmentReader::open(&self.seg_path)
-                    .map_err(|e| PyErr::new::<pyo3::exceptions::PyIOError, _>(format!("{}", e)))?,
-            );
+            self.reader = Some(SegmentReader::open(&self.seg_path).map_err(to_py_err)?);
         }
-        let out = if let Some(v) = self.reader.as_ref().unwrap().get(key) {
-            decode_sorted_u64(v)
-        } else {
-            Vec::new()
-        };
-        Ok(PyList::new(py, out).into_py(py))
+        Ok(self.reader.as_ref().unwrap())
     }
 }
 
 #[pymodule]
-fn pru_py(_py: Python, m: &PyModule) -> PyResult<()> {
+fn pru_py(py: Python, m: &PyModule) -> PyResult<()> {
     m.add_class::<PRUReader>()?;
+    m.add_class::<store::PyPruStore>()?;
+    m.add_class::<segment::PySegmentWriter>()?;
+    m.add_class::<resolver::PyResolverStore>()?;
+    m.add_function(wrap_pyfunction!(segment::register_segment, m)?)?;
+    media::register(m)?;
+    errors::register(py, m)?;
     Ok(())
 }
@@ -0,0 +1,194 @@
+//! Media-schema helpers for labeling tools, over a `PruStore`.
+//!
+//! Every function takes the `PruStore` it works on and runs with the GIL
+//! released. The store's mutex serializes them against each other and
+//! against the store's own methods, so one store can be shared between
+//! Python threads.
+
+use crate::errors::anyhow_to_py;
+use crate::store::PyPruStore;
+use pru_core::PruDbHandle;
+use pru_media_schema::{self as schema, DetectorId, MediaId, MediaType};
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use pyo3::types::{PyDict, PyList};
+use serde_json::Value;
+
+fn parse_media_type(media_type: &str) -> PyResult<MediaType> {
+    media_type
+        .parse()
+        .map_err(|e: anyhow::Error| PyValueError::new_err(e.to_string()))
+}
+
+/// Run `f` on the store's handle without the GIL.
+fn with_handle<T: Send>(
+    py: Python<'_>,
+    store: &PyPruStore,
+    f: impl FnOnce(&PruDbHandle) -> anyhow::Result<T> + Send,
+) -> PyResult<T> {
+    let handle = store.handle();
+    py.allow_threads(|| f(&handle)).map_err(anyhow_to_py)
+}
+
+/// The id of the media entity for `hash`, created if needed.
+#[pyfunction]
+pub fn upsert_media_entity(
+    py: Python<'_>,
+    store: PyRef<'_, PyPruStore>,
+    hash: &str,
+    media_type: &str,
+) -> PyResult<u64> {
+    let media_type = parse_media_type(media_type)?;
+    with_handle(py, &store, |h| {
+        schema::upsert_media_entity(h, hash, media_type).map(|m| m.0)
+    })
+}
+
+/// The id of the media entity for `hash`, or `None` if there is none yet.
+#[pyfunction]
+pub fn find_media_by_hash(
+    py: Python<'_>,
+    store: PyRef<'_, PyPruStore>,
+    hash: &str,
+    media_type: &str,
+) -> PyResult<Option<u64>> {
+    let media_type = parse_media_type(media_type)?;
+    with_handle(py, &store, |h| {
+        Ok(schema::find_media_entity(h, hash, media_type)?.map(|m| m.0))
+    })
+}
+
+/// Record `detector`'s score and label for `media`.
+#[pyfunction]
+pub fn add_detector_score(
+    py: Python<'_>,
+    store: PyRef<'_, PyPruStore>,
+    media: u64,
+    detector: u64,
+    score: f64,
+    label: &str,
+) -> PyResult<()> {
+    with_handle(py, &store, |h| {
+        schema::add_detector_score(h, MediaId(media), DetectorId(detector), score, label)
+    })
+}
+
+/// The latest score of each detector for `media`, as dicts with `detector`,
+/// `score` and `label`.
+#[pyfunction]
+pub fn get_detector_scores_for_media<'py>(
+    py: Python<'py>,
+    store: PyRef<'_, PyPruStore>,
+    media: u64,
+) -> PyResult<Vec<Bound<'py, PyDict>>> {
+    let scores = with_handle(py, &store, |h| {
+        schema::get_detector_scores_for_media(h, MediaId(media))
+    })?;
+    scores
+        .into_iter()
+        .map(|(detector, score, label)| {
+            let dict = PyDict::new_bound(py);
+            dict.set_item("detector", detector.0)?;
+            dict.set_item("score", score)?;
+            dict.set_item("label", label)?;
+            Ok(dict)
+        })
+        .collect()
+}
+
+#[pyfunction]
+pub fn add_human_verdict(
+    py: Python<'_>,
+    store: PyRef<'_, PyPruStore>,
+    media: u64,
+    label: &str,
+) -> PyResult<()> {
+    with_handle(py, &store, |h| {
+        schema::add_human_verdict(h, MediaId(media), label)
+    })
+}
+
+/// Record `annotator`'s verdict; `confidence` is clamped to `0..=1`.
+#[pyfunction]
+#[pyo3(signature = (store, media, label, annotator, confidence=1.0))]
+pub fn add_human_verdict_by(
+    py: Python<'_>,
+    store: PyRef<'_, PyPruStore>,
+    media: u64,
+    label: &str,
+    annotator: &str,
+    confidence: f32,
+) -> PyResult<()> {
+    with_handle(py, &store, |h| {
+        schema::add_human_verdict_by(h, MediaId(media), label, annotator, confidence)
+    })
+}
+
+/// Grade every detector that scored `media` against the verdict `label`.
+#[pyfunction]
+pub fn bump_reliability_from_verdict(
+    py: Python<'_>,
+    store: PyRef<'_, PyPruStore>,
+    media: u64,
+    label: &str,
+) -> PyResult<()> {
+    with_handle(py, &store, |h| {
+        schema::bump_reliability_from_verdict(h, MediaId(media), label)
+    })
+}
+
+/// The detector's stored reliability as a dict shaped like its JSON payload,
+/// or `None` when it has no history.
+#[pyfunction]
+pub fn get_detector_reliability(
+    py: Python<'_>,
+    store: PyRef<'_, PyPruStore>,
+    detector: u64,
+) -> PyResult<Option<PyObject>> {
+    let reliability = with_handle(py, &store, |h| {
+        schema::get_detector_reliability(h, DetectorId(detector))?
+            .map(serde_json::to_value)
+            .transpose()
+            .map_err(Into::into)
+    })?;
+    reliability.map(|v| json_to_py(py, &v)).transpose()
+}
+
+fn json_to_py(py: Python<'_>, value: &Value) -> PyResult<PyObject> {
+    Ok(match value {
+        Value::Null => py.None(),
+        Value::Bool(b) => b.into_py(py),
+        Value::Number(n) => match (n.as_u64(), n.as_i64()) {
+            (Some(u), _) => u.into_py(py),
+            (None, Some(i)) => i.into_py(py),
+            _ => n.as_f64().into_py(py),
+        },
+        Value::String(s) => s.into_py(py),
+        Value::Array(items) => {
+            let list = PyList::empty_bound(py);
+            for item in items {
+                list.append(json_to_py(py, item)?)?;
+            }
+            list.into_py(py)
+        }
+        Value::Object(fields) => {
+            let dict = PyDict::new_bound(py);
+            for (key, item) in fields {
+                dict.set_item(key, json_to_py(py, item)?)?;
+            }
+            dict.into_py(py)
+        }
+    })
+}
+
+pub(crate) fn register(m: &PyModule) -> PyResult<()> {
+    m.add_function(wrap_pyfunction!(upsert_media_entity, m)?)?;
+    m.add_function(wrap_pyfunction!(find_media_by_hash, m)?)?;
+    m.add_function(wrap_pyfunction!(add_detector_score, m)?)?;
+    m.add_function(wrap_pyfunction!(get_detector_scores_for_media, m)?)?;
+    m.add_function(wrap_pyfunction!(add_human_verdict, m)?)?;
+    m.add_function(wrap_pyfunction!(add_human_verdict_by, m)?)?;
+    m.add_function(wrap_pyfunction!(bump_reliability_from_verdict, m)?)?;
+    m.add_function(wrap_pyfunction!(get_detector_reliability, m)?)?;
+    Ok(())
+}
@@ -0,0 +1,81 @@
+//! `pru_py.ResolverStore`: resolving keys across a store's active resolver
+//! segments. Reads run with the GIL released.
+
+use crate::errors::to_py_err;
+use pru_core::resolver_store::{ResolveMode, ResolverStore};
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use std::path::PathBuf;
+
+fn parse_mode(mode: &str) -> PyResult<ResolveMode> {
+    match mode {
+        "union" => Ok(ResolveMode::Union),
+        "dedup" => Ok(ResolveMode::Dedup),
+        "intersect" => Ok(ResolveMode::Intersect),
+        "difference" => Ok(ResolveMode::Difference),
+        _ => Err(PyValueError::new_err(format!(
+            "unknown resolve mode {mode:?} (use union, dedup, intersect or difference)"
+        ))),
+    }
+}
+
+#[pyclass(name = "ResolverStore")]
+pub struct PyResolverStore {
+    dir: PathBuf,
+    store: ResolverStore,
+}
+
+#[pymethods]
+impl PyResolverStore {
+    /// Open the active resolver segments of the store in `dir`.
+    #[new]
+    fn new(py: Python<'_>, dir: PathBuf) -> PyResult<Self> {
+        let store = py
+            .allow_threads(|| ResolverStore::open(&dir))
+            .map_err(to_py_err)?;
+        Ok(Self { dir, store })
+    }
+
+    #[staticmethod]
+    fn open(py: Python<'_>, dir: PathBuf) -> PyResult<Self> {
+        Self::new(py, dir)
+    }
+
+    #[getter]
+    fn dir(&self) -> PathBuf {
+        self.dir.clone()
+    }
+
+    /// The ids stored under `key`, merged across segments.
+    fn resolve(&self, py: Python<'_>, key: &[u8]) -> Vec<u64> {
+        py.allow_threads(|| self.store.resolve(key))
+    }
+
+    /// Combine the ids of several keys. `difference` keeps the first key's ids
+    /// that none of the others have; `set_semantics` dedups each operand of
+    /// `intersect` and `difference` first.
+    #[pyo3(signature = (keys, mode="union", set_semantics=false))]
+    fn resolve_multi(
+        &self,
+        py: Python<'_>,
+        keys: Vec<Vec<u8>>,
+        mode: &str,
+        set_semantics: bool,
+    ) -> PyResult<Vec<u64>> {
+        let mode = parse_mode(mode)?;
+        Ok(py.allow_threads(|| self.store.resolve_with_mode_set(mode, &keys, set_semantics)))
+    }
+
+    /// Reopen the segments, picking up ones added or promoted since.
+    fn refresh(&mut self, py: Python<'_>) -> PyResult<()> {
+        let dir = &self.dir;
+        self.store = py
+            .allow_threads(|| ResolverStore::open(dir))
+            .map_err(to_py_err)?;
+        Ok(())
+    }
+
+    fn __repr__(&self) -> String {
+        format!("ResolverStore({:?})", self.dir)
+    }
+}
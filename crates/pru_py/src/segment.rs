@@ -0,0 +1,114 @@
+//! Writing segments from Python, and registering them with a store.
+
+use crate::errors::to_py_err;
+use pru_core::consts::SegmentKind;
+use pru_core::errors::PruError as CoreError;
+use pru_core::manifest::Manifest;
+use pru_core::postings::encode_sorted_u64;
+use pru_core::segment::SegmentWriter;
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use std::path::{Path, PathBuf};
+
+pub(crate) fn parse_kind(kind: &str) -> PyResult<SegmentKind> {
+    SegmentKind::ALL
+        .into_iter()
+        .find(|k| k.name() == kind)
+        .ok_or_else(|| {
+            PyValueError::new_err(format!(
+                "unknown segment kind {kind:?} (use resolver, dict or fact)"
+            ))
+        })
+}
+
+/// Posting lists written to a new segment file; nothing is visible at `path`
+/// until `finalize()`.
+#[pyclass(name = "SegmentWriter")]
+pub struct PySegmentWriter {
+    path: PathBuf,
+    writer: Option<SegmentWriter>,
+}
+
+impl PySegmentWriter {
+    fn writer(&mut self) -> PyResult<&mut SegmentWriter> {
+        self.writer.as_mut().ok_or_else(|| {
+            PyValueError::new_err(format!("{} is already finalized", self.path.display()))
+        })
+    }
+}
+
+#[pymethods]
+impl PySegmentWriter {
+    #[new]
+    #[pyo3(signature = (path, kind="resolver", bloom_bits=1 << 20, bloom_k=7))]
+    fn new(path: PathBuf, kind: &str, bloom_bits: u32, bloom_k: u32) -> PyResult<Self> {
+        let writer = SegmentWriter::create(&path, parse_kind(kind)?, bloom_bits, bloom_k)
+            .map_err(to_py_err)?;
+        Ok(Self {
+            path,
+            writer: Some(writer),
+        })
+    }
+
+    #[staticmethod]
+    #[pyo3(signature = (path, kind="resolver", bloom_bits=1 << 20, bloom_k=7))]
+    fn create(path: PathBuf, kind: &str, bloom_bits: u32, bloom_k: u32) -> PyResult<Self> {
+        Self::new(path, kind, bloom_bits, bloom_k)
+    }
+
+    /// Add `key` with the posting list `ids`, in any order and with repeats.
+    fn add(&mut self, key: &[u8], mut ids: Vec<u64>) -> PyResult<()> {
+        ids.sort_unstable();
+        ids.dedup();
+        self.writer()?
+            .add(key, &encode_sorted_u64(&ids))
+            .map_err(to_py_err)
+    }
+
+    /// Add `key` with a value stored as it is.
+    fn add_raw(&mut self, key: &[u8], value: &[u8]) -> PyResult<()> {
+        self.writer()?.add(key, value).map_err(to_py_err)
+    }
+
+    /// Write the index and filter and publish the file; returns its path.
+    fn finalize(&mut self) -> PyResult<PathBuf> {
+        self.writer()?;
+        let writer = self.writer.take().unwrap();
+        writer.finalize().map_err(to_py_err)
+    }
+}
+
+/// Add the finished segment at `path`, which must lie in `dir`, to the store's
+/// manifest as an active segment.
+#[pyfunction]
+#[pyo3(signature = (dir, path, kind="resolver"))]
+pub fn register_segment(dir: PathBuf, path: PathBuf, kind: &str) -> PyResult<String> {
+    let kind = parse_kind(kind)?;
+    let name = segment_name(&dir, &path).map_err(to_py_err)?;
+    let mut manifest = Manifest::load(&dir).map_err(to_py_err)?;
+    manifest.add_segment(&dir, &name, kind).map_err(to_py_err)?;
+    manifest.save_atomic(&dir).map_err(to_py_err)?;
+    Ok(name)
+}
+
+/// `path` relative to `dir`; a bare file name is taken as already relative.
+fn segment_name(dir: &Path, path: &Path) -> Result<String, CoreError> {
+    let relative = if path.is_absolute() || path.starts_with(dir) {
+        path.strip_prefix(dir).map_err(|_| {
+            CoreError::InvalidInput(format!(
+                "{} is not inside {}",
+                path.display(),
+                dir.display()
+            ))
+        })?
+    } else {
+        path
+    };
+    if !dir.join(relative).is_file() {
+        return Err(CoreError::InvalidInput(format!(
+            "no segment at {}",
+            dir.join(relative).display()
+        )));
+    }
+    Ok(relative.to_string_lossy().into_owned())
+}
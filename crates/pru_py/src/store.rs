@@ -0,0 +1,190 @@
+//! `pru_py.PruStore`: the high-level store (atoms, facts, queries) from Python.
+
+use crate::errors::to_py_err;
+use pru_core::{Fact, PruDbHandle, PruStore, Query};
+use pyo3::exceptions::PyTypeError;
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+/// Keys `add_fact` accepts, from a dict or as keyword arguments.
+const FACT_FIELDS: [&str; 6] = [
+    "subject",
+    "predicate",
+    "object",
+    "source",
+    "timestamp",
+    "confidence",
+];
+
+#[pyclass(name = "PruStore")]
+pub struct PyPruStore {
+    handle: PruDbHandle,
+}
+
+impl PyPruStore {
+    fn store(&self) -> MutexGuard<'_, PruStore> {
+        self.handle.lock().unwrap()
+    }
+
+    /// A clone of the handle, for helpers that lock it with the GIL released.
+    pub(crate) fn handle(&self) -> PruDbHandle {
+        self.handle.clone()
+    }
+}
+
+#[pymethods]
+impl PyPruStore {
+    /// Open (or initialize) the store in `dir`.
+    #[new]
+    fn new(dir: PathBuf) -> PyResult<Self> {
+        Self::open(dir)
+    }
+
+    #[staticmethod]
+    fn open(dir: PathBuf) -> PyResult<Self> {
+        let store = PruStore::open(dir).map_err(to_py_err)?;
+        Ok(Self {
+            handle: Arc::new(Mutex::new(store)),
+        })
+    }
+
+    #[getter]
+    fn dir(&self) -> PathBuf {
+        self.store().dir().to_path_buf()
+    }
+
+    fn intern_entity(&self, name: &str) -> PyResult<u64> {
+        self.store().intern_entity(name).map_err(to_py_err)
+    }
+
+    fn intern_predicate(&self, name: &str) -> PyResult<u64> {
+        self.store().intern_predicate(name).map_err(to_py_err)
+    }
+
+    fn intern_literal(&self, value: &str) -> PyResult<u64> {
+        self.store().intern_literal(value).map_err(to_py_err)
+    }
+
+    /// Add a fact given as a dict, as keyword arguments, or both; keywords win.
+    /// `subject`, `predicate` and `object` are ids; `source`, `timestamp` and
+    /// `confidence` are optional.
+    #[pyo3(signature = (fact=None, **kwargs))]
+    fn add_fact(
+        &self,
+        py: Python<'_>,
+        fact: Option<&Bound<'_, PyDict>>,
+        kwargs: Option<&Bound<'_, PyDict>>,
+    ) -> PyResult<()> {
+        let fields = PyDict::new_bound(py);
+        for given in [fact, kwargs].into_iter().flatten() {
+            fields.update(given.as_mapping())?;
+        }
+        let fact = fact_from_fields(&fields)?;
+        self.store().add_fact(fact).map_err(to_py_err)
+    }
+
+    fn facts_for_subject<'py>(
+        &self,
+        py: Python<'py>,
+        subject: u64,
+    ) -> PyResult<Vec<Bound<'py, PyDict>>> {
+        let store = self.store();
+        let facts = store.facts_for_subject(subject).map_err(to_py_err)?;
+        facts.iter().map(|f| fact_dict(py, &store, f)).collect()
+    }
+
+    /// Facts matching every filter given, as dicts with ids and names.
+    #[pyo3(signature = (subject=None, predicate=None, object=None, min_confidence=None))]
+    fn query<'py>(
+        &self,
+        py: Python<'py>,
+        subject: Option<u64>,
+        predicate: Option<u64>,
+        object: Option<u64>,
+        min_confidence: Option<f32>,
+    ) -> PyResult<Vec<Bound<'py, PyDict>>> {
+        let store = self.store();
+        let query = Query {
+            subject,
+            predicate,
+            object,
+            min_confidence,
+            ..Default::default()
+        };
+        store
+            .query_iter(query)
+            .map(|f| fact_dict(py, &store, f))
+            .collect()
+    }
+
+    fn fact_count(&self) -> usize {
+        self.store().fact_count()
+    }
+
+    fn __len__(&self) -> usize {
+        self.fact_count()
+    }
+
+    fn __repr__(&self) -> String {
+        format!("PruStore({:?})", self.store().dir())
+    }
+}
+
+fn fact_from_fields(fields: &Bound<'_, PyDict>) -> PyResult<Fact> {
+    for key in fields.keys() {
+        let key: String = key.extract()?;
+        if !FACT_FIELDS.contains(&key.as_str()) {
+            return Err(PyTypeError::new_err(format!("unknown fact field {key:?}")));
+        }
+    }
+    let required = |name: &str| -> PyResult<u64> {
+        match field(fields, name)? {
+            Some(id) => Ok(id),
+            None => Err(PyTypeError::new_err(format!("missing fact field {name:?}"))),
+        }
+    };
+    Ok(Fact {
+        subject: required("subject")?,
+        predicate: required("predicate")?,
+        object: required("object")?,
+        source: field(fields, "source")?,
+        timestamp: field(fields, "timestamp")?,
+        confidence: field(fields, "confidence")?,
+    })
+}
+
+/// `fields[name]`, with a missing key and `None` alike read as absent.
+fn field<'py, T: FromPyObject<'py>>(
+    fields: &Bound<'py, PyDict>,
+    name: &str,
+) -> PyResult<Option<T>> {
+    match fields.get_item(name)? {
+        Some(value) if !value.is_none() => value.extract().map(Some),
+        _ => Ok(None),
+    }
+}
+
+/// The same keys `pru query --format json` prints.
+fn fact_dict<'py>(py: Python<'py>, store: &PruStore, fact: &Fact) -> PyResult<Bound<'py, PyDict>> {
+    let (object_kind, object_value) = match store.get_entity_name(fact.object) {
+        Some(name) => (Some("entity"), Some(name)),
+        None => match store.get_literal_value(fact.object) {
+            Some(value) => (Some("literal"), Some(value)),
+            None => (None, None),
+        },
+    };
+    let dict = PyDict::new_bound(py);
+    dict.set_item("subject", fact.subject)?;
+    dict.set_item("subject_name", store.get_entity_name(fact.subject))?;
+    dict.set_item("predicate", fact.predicate)?;
+    dict.set_item("predicate_name", store.get_predicate_name(fact.predicate))?;
+    dict.set_item("object", fact.object)?;
+    dict.set_item("object_kind", object_kind)?;
+    dict.set_item("object_value", object_value)?;
+    dict.set_item("source", fact.source)?;
+    dict.set_item("confidence", fact.confidence)?;
+    dict.set_item("timestamp", fact.timestamp)?;
+    Ok(dict)
+}
@@ -0,0 +1,54 @@
+"""Labeling workflow through the media-schema helpers."""
+
+import threading
+
+import pytest
+
+import pru_py
+
+
+def test_verdict_bumps_detector_reliability(tmp_path):
+    store = pru_py.PruStore(str(tmp_path))
+    media = pru_py.upsert_media_entity(store, "ab12", "image")
+    assert pru_py.upsert_media_entity(store, "ab12", "image") == media
+    assert pru_py.find_media_by_hash(store, "ab12", "image") == media
+    assert pru_py.find_media_by_hash(store, "ab12", "video") is None
+    with pytest.raises(ValueError, match="unknown media type"):
+        pru_py.find_media_by_hash(store, "ab12", "hologram")
+
+    detector = store.intern_entity("detector:demo")
+    pru_py.add_detector_score(store, media, detector, 0.91, "ai")
+    assert pru_py.get_detector_scores_for_media(store, media) == [
+        {"detector": detector, "score": 0.91, "label": "ai"}
+    ]
+    assert pru_py.get_detector_reliability(store, detector) is None
+
+    pru_py.add_human_verdict_by(store, media, "ai", "alice", confidence=0.8)
+    pru_py.bump_reliability_from_verdict(store, media, "ai")
+    first = pru_py.get_detector_reliability(store, detector)
+    assert (first["seen"], first["correct"]) == (1, 1)
+    assert first["confusion"] == {"ai": {"ai": 1}}
+
+    pru_py.add_human_verdict(store, media, "real")
+    pru_py.bump_reliability_from_verdict(store, media, "real")
+    second = pru_py.get_detector_reliability(store, detector)
+    assert second != first
+    assert (second["seen"], second["correct"]) == (2, 1)
+    assert second["confusion"] == {"ai": {"ai": 1, "real": 1}}
+
+
+def test_store_shared_between_threads(tmp_path):
+    store = pru_py.PruStore(str(tmp_path))
+
+    def label(i):
+        media = pru_py.upsert_media_entity(store, f"hash{i}", "text")
+        pru_py.add_human_verdict(store, media, "real")
+
+    threads = [threading.Thread(target=label, args=(i,)) for i in range(8)]
+    for t in threads:
+        t.start()
+    for t in threads:
+        t.join()
+    ids = {pru_py.find_media_by_hash(store, f"hash{i}", "text") for i in range(8)}
+    assert None not in ids and len(ids) == 8
+    assert len(store) == 8
@@ -0,0 +1,61 @@
+"""PRUReader lifecycle and the errors it raises for damaged segments."""
+
+import pathlib
+import struct
+
+import pytest
+
+import pru_py
+
+
+def write_segment(path, postings):
+    writer = pru_py.SegmentWriter(str(path))
+    for key, ids in postings.items():
+        writer.add(key, ids)
+    return pathlib.Path(writer.finalize())
+
+
+def test_context_manager_and_contains(tmp_path):
+    path = write_segment(tmp_path / "resolver-a.prus", {b"red": [3, 1], b"empty": []})
+    with pytest.deprecated_call():
+        reader = pru_py.PRUReader(str(path))
+    assert reader.closed
+    with reader as r:
+        assert r is reader
+        assert not reader.closed
+        assert reader.contains(b"red")
+        assert b"empty" in reader
+        assert reader.resolve(b"empty") == []
+        assert not reader.contains(b"missing")
+    assert reader.closed
+    # A closed reader maps the file again when it is used.
+    assert reader.resolve(b"red") == [1, 3]
+    reader.close()
+    assert reader.closed
+
+
+def test_missing_file_and_bad_header(tmp_path):
+    with pytest.raises(FileNotFoundError, match="no segment file"):
+        pru_py.PRUReader(str(tmp_path / "absent.prus"))
+    bogus = tmp_path / "bogus.prus"
+    bogus.write_bytes(b"not a segment")
+    reader = pru_py.PRUReader(str(bogus))
+    with pytest.raises(pru_py.PruHeaderError):
+        reader.open()
+    with pytest.raises(pru_py.PruError):
+        reader.resolve(b"red")
+
+
+def test_corrupt_record_raises(tmp_path):
+    path = write_segment(tmp_path / "resolver-a.prus", {b"red": [1, 2, 3]})
+    data = bytearray(path.read_bytes())
+    (data_off,) = struct.unpack_from("<Q", data, 28)
+    data[data_off] ^= 0xFF
+    path.write_bytes(bytes(data))
+
+    with pru_py.PRUReader(str(path)) as reader:
+        assert reader.contains(b"missing") is False
+        with pytest.raises(pru_py.PruCorruptError):
+            reader.resolve(b"red")
+        with pytest.raises(pru_py.PruCorruptError):
+            reader.get_raw(b"red")
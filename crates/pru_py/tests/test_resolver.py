@@ -0,0 +1,60 @@
+"""ResolverStore across a store's active segments, checked against `pru resolve`."""
+
+import os
+import shutil
+import subprocess
+
+import pytest
+
+import pru_py
+
+
+def build_store(dir):
+    for name, postings in [
+        ("resolver-a.prus", {b"red": [1, 2, 3, 4], b"round": [2, 4, 6]}),
+        ("resolver-b.prus", {b"red": [5], b"small": [4, 5, 9]}),
+    ]:
+        writer = pru_py.SegmentWriter(str(dir / name))
+        for key, ids in postings.items():
+            writer.add(key, ids)
+        pru_py.register_segment(str(dir), writer.finalize())
+
+
+def test_resolve_multi_modes(tmp_path):
+    build_store(tmp_path)
+    store = pru_py.ResolverStore(str(tmp_path))
+    assert store.resolve(b"red") == [1, 2, 3, 4, 5]
+    assert store.resolve(b"missing") == []
+    assert store.resolve_multi([b"red", b"round"], "intersect") == [2, 4]
+    assert store.resolve_multi([b"red", b"round", b"small"], mode="intersect") == [4]
+    assert store.resolve_multi([b"red", b"small"], "difference") == [1, 2, 3]
+    assert store.resolve_multi([b"round", b"small"], "dedup") == [2, 4, 5, 6, 9]
+    assert store.resolve_multi([b"round", b"small"]) == [2, 4, 4, 5, 6, 9]
+    with pytest.raises(ValueError, match="unknown resolve mode"):
+        store.resolve_multi([b"red"], "xor")
+
+
+def test_refresh_picks_up_new_segments(tmp_path):
+    build_store(tmp_path)
+    store = pru_py.ResolverStore.open(str(tmp_path))
+    writer = pru_py.SegmentWriter(str(tmp_path / "resolver-c.prus"))
+    writer.add(b"round", [8])
+    pru_py.register_segment(str(tmp_path), writer.finalize())
+    assert store.resolve(b"round") == [2, 4, 6]
+    store.refresh()
+    assert store.resolve(b"round") == [2, 4, 6, 8]
+
+
+def test_intersect_matches_cli(tmp_path):
+    cli = os.environ.get("PRU_CLI") or shutil.which("pru_cli")
+    if cli is None:
+        pytest.skip("set PRU_CLI to the pru_cli binary to compare against the CLI")
+    build_store(tmp_path)
+    keys = [b"red", b"round", b"small"]
+    out = subprocess.run(
+        [cli, "resolve", "--dir", str(tmp_path), "--key-hex", keys[0].hex(),
+         "--and-key-hex", ",".join(k.hex() for k in keys[1:]), "--mode", "intersect"],
+        check=True, capture_output=True, text=True,
+    ).stdout
+    store = pru_py.ResolverStore(str(tmp_path))
+    assert out.strip() == str(store.resolve_multi(keys, "intersect"))
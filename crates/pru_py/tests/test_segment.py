@@ -0,0 +1,36 @@
+"""Segments written from Python and read back through PRUReader."""
+
+import pytest
+
+import pru_py
+
+
+def test_write_register_and_resolve(tmp_path):
+    writer = pru_py.SegmentWriter.create(str(tmp_path / "resolver-py.prus"), "resolver")
+    writer.add(b"first", [9, 1, 5, 1])
+    writer.add(b"second", [2])
+    writer.add_raw(b"raw", b"\x00\x01")
+    path = writer.finalize()
+    with pytest.raises(ValueError, match="already finalized"):
+        writer.add(b"late", [1])
+
+    reader = pru_py.PRUReader(str(path))
+    assert reader.resolve(b"first") == [1, 5, 9]
+    assert reader.resolve(b"second") == [2]
+    assert reader.resolve(b"missing") == []
+    assert reader.get_raw(b"raw") == b"\x00\x01"
+    assert reader.get_raw(b"missing") is None
+    assert len(reader.keys()) == 3
+    # Each size counts the value plus its 4-byte checksum.
+    assert sorted(size for _, size in reader.entries()) == [1 + 4, 2 + 4, 3 + 4]
+
+    assert pru_py.register_segment(str(tmp_path), str(path)) == "resolver-py.prus"
+    manifest = (tmp_path / "manifest.json").read_text()
+    assert "resolver-py.prus" in manifest
+
+
+def test_bad_kind_and_foreign_path(tmp_path):
+    with pytest.raises(ValueError, match="unknown segment kind"):
+        pru_py.SegmentWriter(str(tmp_path / "x.prus"), "postings")
+    with pytest.raises(pru_py.PruInvalidInputError):
+        pru_py.register_segment(str(tmp_path / "store"), str(tmp_path / "elsewhere.prus"))
@@ -0,0 +1,48 @@
+"""PruStore round trips. Run with `maturin develop` and then `pytest crates/pru_py/tests`."""
+
+import pytest
+
+import pru_py
+
+
+def test_intern_add_query_round_trip(tmp_path):
+    store = pru_py.PruStore.open(str(tmp_path))
+    earth = store.intern_entity("Earth")
+    moon = store.intern_entity("Moon")
+    orbits = store.intern_predicate("orbits")
+    sun = store.intern_literal("Sun")
+    assert store.intern_entity("Earth") == earth
+
+    store.add_fact({"subject": earth, "predicate": orbits, "object": sun}, confidence=0.5)
+    store.add_fact(subject=moon, predicate=orbits, object=earth, timestamp=42)
+    assert len(store) == 2
+
+    reopened = pru_py.PruStore(tmp_path)
+    [fact] = reopened.query(subject=moon)
+    assert fact["subject_name"] == "Moon"
+    assert fact["predicate_name"] == "orbits"
+    assert (fact["object"], fact["object_kind"], fact["object_value"]) == (earth, "entity", "Earth")
+    assert fact["timestamp"] == 42
+
+    [fact] = reopened.facts_for_subject(earth)
+    assert (fact["object_kind"], fact["object_value"]) == ("literal", "Sun")
+    assert fact["confidence"] == 0.5
+    assert reopened.query(predicate=orbits, min_confidence=0.9) == reopened.query(subject=moon)
+    assert len(reopened.query()) == 2
+
+
+def test_errors_map_to_exception_classes(tmp_path):
+    store = pru_py.PruStore.open(str(tmp_path))
+    earth = store.intern_entity("Earth")
+    orbits = store.intern_predicate("orbits")
+
+    with pytest.raises(pru_py.PruAtomNotFoundError):
+        store.add_fact(subject=earth, predicate=orbits, object=999)
+    with pytest.raises(pru_py.PruInvalidInputError):
+        store.intern_entity("")
+    assert issubclass(pru_py.PruInvalidInputError, pru_py.PruError)
+    with pytest.raises(TypeError, match="missing fact field"):
+        store.add_fact(subject=earth, predicate=orbits)
+    with pytest.raises(TypeError, match="unknown fact field"):
+        store.add_fact(subject=earth, predicate=orbits, object=earth, weight=1)
+    assert len(store) == 0
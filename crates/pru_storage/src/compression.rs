@@ -0,0 +1,35 @@
+//! Optional zstd compression of stored files, invisible to readers.
+
+use std::collections::BTreeSet;
+
+/// Suffix added to the name of a compressed file, after its own extension.
+pub(crate) const SUFFIX: &str = "zst";
+
+/// Which files [`MediaStorage`](crate::MediaStorage) compresses on write.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum CompressionPolicy {
+    #[default]
+    Never,
+    Always,
+    /// Only files with one of these extensions (lowercase, without the dot), so
+    /// formats that are already compressed such as jpg or mp4 can be left alone.
+    ByExtension(BTreeSet<String>),
+}
+
+impl CompressionPolicy {
+    pub fn by_extension<I, S>(extensions: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        Self::ByExtension(extensions.into_iter().map(Into::into).collect())
+    }
+
+    pub fn compresses(&self, ext: &str) -> bool {
+        match self {
+            Self::Never => false,
+            Self::Always => true,
+            Self::ByExtension(set) => set.contains(&ext.to_ascii_lowercase()),
+        }
+    }
+}
@@ -0,0 +1,299 @@
+//! Optional encryption of stored files with XChaCha20-Poly1305.
+//!
+//! An encrypted file is a header (magic, format version, random nonce prefix)
+//! followed by the content in 64 KiB chunks, each sealed separately with the
+//! STREAM construction so files can be written and read without holding them in
+//! memory, and truncation or reordering is detected like any other tampering.
+
+use anyhow::{bail, Context, Result};
+use chacha20poly1305::aead::generic_array::GenericArray;
+use chacha20poly1305::aead::stream::{DecryptorBE32, EncryptorBE32};
+use chacha20poly1305::{KeyInit, XChaCha20Poly1305};
+use std::io::{self, Read, Write};
+use std::path::Path;
+
+/// Suffix added to the name of an encrypted file, after any compression suffix.
+pub(crate) const SUFFIX: &str = "enc";
+
+const MAGIC: &[u8; 4] = b"PRUE";
+const VERSION: u8 = 1;
+/// XChaCha20's 24-byte nonce minus the 5 bytes STREAM uses for its counter.
+const NONCE_PREFIX: usize = 19;
+const HEADER: usize = MAGIC.len() + 1 + NONCE_PREFIX;
+const CHUNK: usize = 64 * 1024;
+const TAG: usize = 16;
+
+/// Key for encrypting stored media. Never printed.
+#[derive(Clone)]
+pub struct EncryptionConfig {
+    key: [u8; 32],
+}
+
+impl std::fmt::Debug for EncryptionConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("EncryptionConfig { .. }")
+    }
+}
+
+impl EncryptionConfig {
+    pub fn new(key: [u8; 32]) -> Self {
+        Self { key }
+    }
+
+    /// A key written as 64 hex digits.
+    pub fn from_hex(hex: &str) -> Result<Self> {
+        let bytes = hex::decode(hex.trim()).context("media key is not valid hex")?;
+        let key = bytes
+            .try_into()
+            .map_err(|b: Vec<u8>| anyhow::anyhow!("media key is {} bytes, not 32", b.len()))?;
+        Ok(Self::new(key))
+    }
+
+    /// A key file holding either the 32 raw key bytes or 64 hex digits.
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let bytes = std::fs::read(path)
+            .with_context(|| format!("reading media key from {}", path.display()))?;
+        if let Ok(key) = <[u8; 32]>::try_from(bytes.as_slice()) {
+            return Ok(Self::new(key));
+        }
+        let text = String::from_utf8(bytes).with_context(|| {
+            format!(
+                "media key in {} is neither 32 bytes nor hex",
+                path.display()
+            )
+        })?;
+        Self::from_hex(&text).with_context(|| format!("media key in {}", path.display()))
+    }
+
+    /// A key in hex in the environment variable `var`.
+    pub fn from_env(var: &str) -> Result<Self> {
+        let hex =
+            std::env::var(var).with_context(|| format!("media key variable {var} is not set"))?;
+        Self::from_hex(&hex).with_context(|| format!("media key in {var}"))
+    }
+
+    fn cipher(&self) -> XChaCha20Poly1305 {
+        XChaCha20Poly1305::new(GenericArray::from_slice(&self.key))
+    }
+}
+
+/// Why an encrypted file could not be read. Callers can `downcast_ref` the
+/// `anyhow::Error` from the load methods; errors after the first chunk arrive
+/// wrapped in an `io::Error` of kind `InvalidData`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum EncryptionError {
+    /// The file is encrypted and the storage has no key.
+    KeyMissing,
+    /// Authentication failed: the key is wrong or the file was altered.
+    Authentication,
+    /// Not a file this version can read.
+    UnsupportedFormat,
+}
+
+impl std::fmt::Display for EncryptionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EncryptionError::KeyMissing => {
+                f.write_str("stored media is encrypted and no media key is configured")
+            }
+            EncryptionError::Authentication => {
+                f.write_str("stored media failed authentication: wrong media key or corrupted file")
+            }
+            EncryptionError::UnsupportedFormat => {
+                f.write_str("stored media has an unknown encryption header")
+            }
+        }
+    }
+}
+
+impl std::error::Error for EncryptionError {}
+
+impl From<EncryptionError> for io::Error {
+    fn from(err: EncryptionError) -> Self {
+        io::Error::new(io::ErrorKind::InvalidData, err)
+    }
+}
+
+/// An I/O error as an `anyhow::Error`, unwrapping an [`EncryptionError`]
+/// carried inside it so callers can downcast to it.
+pub(crate) fn unwrap_io(err: io::Error) -> anyhow::Error {
+    if err
+        .get_ref()
+        .is_some_and(|inner| inner.is::<EncryptionError>())
+    {
+        let inner = err.into_inner().expect("checked above");
+        return anyhow::Error::new(*inner.downcast::<EncryptionError>().expect("checked above"));
+    }
+    err.into()
+}
+
+/// Encrypts everything written to it into `inner`; [`finish`](Self::finish)
+/// seals the last chunk and must be called.
+pub(crate) struct EncryptWriter<W: Write> {
+    inner: W,
+    encryptor: Option<EncryptorBE32<XChaCha20Poly1305>>,
+    buf: Vec<u8>,
+}
+
+impl<W: Write> EncryptWriter<W> {
+    pub(crate) fn new(mut inner: W, config: &EncryptionConfig) -> io::Result<Self> {
+        let prefix: [u8; NONCE_PREFIX] = rand::random();
+        inner.write_all(MAGIC)?;
+        inner.write_all(&[VERSION])?;
+        inner.write_all(&prefix)?;
+        let encryptor =
+            EncryptorBE32::from_aead(config.cipher(), GenericArray::from_slice(&prefix));
+        Ok(Self {
+            inner,
+            encryptor: Some(encryptor),
+            buf: Vec::with_capacity(CHUNK),
+        })
+    }
+
+    pub(crate) fn finish(mut self) -> io::Result<W> {
+        let encryptor = self.encryptor.take().expect("finished once");
+        let sealed = encryptor
+            .encrypt_last(self.buf.as_slice())
+            .map_err(|_| io::Error::other("encrypting stored media"))?;
+        self.inner.write_all(&sealed)?;
+        self.inner.flush()?;
+        Ok(self.inner)
+    }
+}
+
+impl<W: Write> Write for EncryptWriter<W> {
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        self.buf.extend_from_slice(data);
+        // A full chunk is only sealed once more follows, so the last one is
+        // always left for `finish`.
+        while self.buf.len() > CHUNK {
+            let encryptor = self.encryptor.as_mut().expect("not finished");
+            let sealed = encryptor
+                .encrypt_next(&self.buf[..CHUNK])
+                .map_err(|_| io::Error::other("encrypting stored media"))?;
+            self.inner.write_all(&sealed)?;
+            self.buf.drain(..CHUNK);
+        }
+        Ok(data.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Decrypts a file written by [`EncryptWriter`]. The first chunk is checked
+/// when it is opened, so a wrong key fails there rather than on first read.
+pub(crate) struct DecryptReader<R: Read> {
+    inner: R,
+    decryptor: Option<DecryptorBE32<XChaCha20Poly1305>>,
+    sealed: Vec<u8>,
+    plain: Vec<u8>,
+    pos: usize,
+}
+
+impl<R: Read> DecryptReader<R> {
+    pub(crate) fn new(mut inner: R, config: Option<&EncryptionConfig>) -> Result<Self> {
+        let mut header = [0u8; HEADER];
+        if inner.read_exact(&mut header).is_err()
+            || &header[..MAGIC.len()] != MAGIC
+            || header[MAGIC.len()] != VERSION
+        {
+            bail!(EncryptionError::UnsupportedFormat);
+        }
+        let Some(config) = config else {
+            bail!(EncryptionError::KeyMissing);
+        };
+        let prefix = &header[MAGIC.len() + 1..];
+        let decryptor = DecryptorBE32::from_aead(config.cipher(), GenericArray::from_slice(prefix));
+        let mut reader = Self {
+            inner,
+            decryptor: Some(decryptor),
+            sealed: Vec::with_capacity(CHUNK + TAG + 1),
+            plain: Vec::new(),
+            pos: 0,
+        };
+        reader.next_chunk().map_err(unwrap_io)?;
+        Ok(reader)
+    }
+
+    /// Decrypt the next chunk into `plain`. A chunk is the last one when the
+    /// file ends within it, which is only known after reading one byte past it.
+    fn next_chunk(&mut self) -> io::Result<()> {
+        let Some(decryptor) = self.decryptor.as_mut() else {
+            self.plain.clear();
+            self.pos = 0;
+            return Ok(());
+        };
+        while self.sealed.len() < CHUNK + TAG + 1 {
+            let start = self.sealed.len();
+            self.sealed.resize(CHUNK + TAG + 1, 0);
+            let read = self.inner.read(&mut self.sealed[start..]);
+            self.sealed
+                .truncate(start + read.as_ref().map_or(0, |n| *n));
+            match read {
+                Ok(0) => break,
+                Ok(_) => {}
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+        self.plain = if self.sealed.len() > CHUNK + TAG {
+            let plain = decryptor
+                .decrypt_next(&self.sealed[..CHUNK + TAG])
+                .map_err(|_| EncryptionError::Authentication)?;
+            self.sealed.drain(..CHUNK + TAG);
+            plain
+        } else {
+            let decryptor = self.decryptor.take().expect("checked above");
+            let plain = decryptor
+                .decrypt_last(self.sealed.as_slice())
+                .map_err(|_| EncryptionError::Authentication)?;
+            self.sealed.clear();
+            plain
+        };
+        self.pos = 0;
+        Ok(())
+    }
+}
+
+impl<R: Read> Read for DecryptReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.pos == self.plain.len() {
+            if self.decryptor.is_none() {
+                return Ok(0);
+            }
+            self.next_chunk()?;
+        }
+        let n = buf.len().min(self.plain.len() - self.pos);
+        buf[..n].copy_from_slice(&self.plain[self.pos..self.pos + n]);
+        self.pos += n;
+        Ok(n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn keys_are_read_from_hex_or_raw_files() {
+        let dir = tempdir().unwrap();
+        let hex_key = "ab".repeat(32);
+        let from_hex = EncryptionConfig::from_hex(&hex_key).unwrap();
+        let raw = dir.path().join("raw.key");
+        fs::write(&raw, [0xab; 32]).unwrap();
+        let text = dir.path().join("hex.key");
+        fs::write(&text, format!("{hex_key}\n")).unwrap();
+        for config in [
+            EncryptionConfig::from_file(&raw).unwrap(),
+            EncryptionConfig::from_file(&text).unwrap(),
+        ] {
+            assert_eq!(config.key, from_hex.key);
+        }
+        assert!(EncryptionConfig::from_hex("abcd").is_err());
+        assert_eq!(format!("{from_hex:?}"), "EncryptionConfig { .. }");
+    }
+}
@@ -1,31 +1,1371 @@
-use anyhow::Result;
+use anyhow::{bail, Result};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeSet;
 use std::fs::{self, File};
-use std::io::{Read, Write};
+use std::io::{BufReader, Read, Write};
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::SystemTime;
 
+mod compression;
+mod encryption;
+mod mime;
+mod quota;
+mod reader;
+
+pub use compression::CompressionPolicy;
+pub use encryption::{EncryptionConfig, EncryptionError};
+pub use mime::{canonicalize_ext, ext_for_mime, mime_for_ext, sniff_ext, sniff_mime};
+pub use quota::{Eviction, Quota, QuotaExceeded, StorageUsage};
+pub use reader::StoredReader;
+
+use encryption::{unwrap_io, DecryptReader, EncryptWriter};
+use quota::Index;
+use reader::Encoding;
+
+static INCOMING: AtomicU64 = AtomicU64::new(0);
+
+/// Extension of the empty marker left for deleted content in tombstone mode.
+const TOMBSTONE: &str = "deleted";
+
+#[derive(Clone, Debug)]
 pub struct MediaStorage {
     pub root: PathBuf,
+    /// Check that content hashes to the name it is stored under, on write and
+    /// when deciding whether an existing file can be kept. On by default.
+    pub verify_hashes: bool,
+    /// Which new files are written zstd-compressed. Reads decompress whatever
+    /// they find, so changing this leaves existing files readable.
+    pub compression: CompressionPolicy,
+    /// Key new files are encrypted with, and encrypted files are read with.
+    /// Files stored before it was set stay readable.
+    pub encryption: Option<EncryptionConfig>,
+    /// Cap on the bytes stored files take on disk; none by default.
+    pub quota: Option<Quota>,
+    /// Leave a marker when content is deleted so it cannot be stored again.
+    /// Off by default; markers already present are honoured either way.
+    pub tombstones: bool,
+    /// Sizes, access order and pins, shared by clones. Only kept up to date
+    /// while a quota is set.
+    index: Arc<Mutex<Index>>,
+}
+
+/// What [`MediaStorage::store_media`] did.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StoredOutcome {
+    pub path: PathBuf,
+    /// What to pass to [`MediaStorage::load_relative`] for this content.
+    pub relative_path: String,
+    /// The file was already there with the right content and was not written.
+    pub already_existed: bool,
+    /// A file was there under this name with the wrong size or hash and has been
+    /// replaced.
+    pub repaired: bool,
+}
+
+/// Content that cannot be read or stored. Callers can `downcast_ref` the
+/// `anyhow::Error` from the load and store methods to tell these apart from I/O
+/// failures.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum StorageError {
+    /// Nothing is stored under this path, e.g. because it was evicted.
+    NotFound { relative_path: String },
+    /// The content was deleted in tombstone mode and may not come back.
+    Deleted { hash: String },
+    /// Content is stored for `hash` under several extensions, so looking it up
+    /// without one cannot pick.
+    Ambiguous { hash: String, exts: Vec<String> },
+}
+
+impl std::fmt::Display for StorageError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StorageError::NotFound { relative_path } => {
+                write!(f, "no stored media at {relative_path}")
+            }
+            StorageError::Deleted { hash } => write!(f, "media {hash} was deleted"),
+            StorageError::Ambiguous { hash, exts } => {
+                write!(f, "media {hash} is stored as {}", exts.join(", "))
+            }
+        }
+    }
+}
+
+impl std::error::Error for StorageError {}
+
+/// A stored file, as reported by [`MediaStorage::stat`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StoredStat {
+    /// Length of the original content.
+    pub size: u64,
+    /// Bytes taken on disk; smaller than `size` when the file is compressed.
+    pub stored_size: u64,
+    pub compressed: bool,
+    pub modified: SystemTime,
+    pub ext: String,
 }
 
 impl MediaStorage {
     pub fn new(root: impl AsRef<Path>) -> Self {
         Self {
             root: root.as_ref().to_path_buf(),
+            verify_hashes: true,
+            compression: CompressionPolicy::Never,
+            encryption: None,
+            quota: None,
+            tombstones: false,
+            index: Arc::default(),
         }
     }
 
-    pub fn store_media(&self, hash: &str, ext: &str, bytes: &[u8]) -> Result<PathBuf> {
-        fs::create_dir_all(&self.root)?;
-        let path = self.root.join(format!("{hash}.{ext}"));
-        let mut file = File::create(&path)?;
-        file.write_all(bytes)?;
+    /// Skip hash checks, for callers that have just hashed the bytes themselves.
+    /// An existing file of the right size is then assumed to be intact.
+    pub fn trusted(self) -> Self {
+        Self {
+            verify_hashes: false,
+            ..self
+        }
+    }
+
+    pub fn with_compression(self, compression: CompressionPolicy) -> Self {
+        Self {
+            compression,
+            ..self
+        }
+    }
+
+    pub fn with_encryption(self, encryption: EncryptionConfig) -> Self {
+        Self {
+            encryption: Some(encryption),
+            ..self
+        }
+    }
+
+    pub fn with_quota(self, quota: Quota) -> Self {
+        Self {
+            quota: Some(quota),
+            ..self
+        }
+    }
+
+    pub fn with_tombstones(self) -> Self {
+        Self {
+            tombstones: true,
+            ..self
+        }
+    }
+
+    /// Path of the stored file relative to `root`. Hashes and this path always
+    /// describe the original content, compressed or encrypted or not.
+    pub fn relative_path(hash: &str, ext: &str) -> String {
+        format!("{hash}.{ext}")
+    }
+
+    /// [`relative_path`](Self::relative_path), refused unless `hash` and `ext`
+    /// are plain names that cannot point outside the storage root.
+    fn checked_path(hash: &str, ext: &str) -> Result<String> {
+        check_plain(hash)?;
+        if !ext.is_empty() {
+            check_plain(ext)?;
+        }
+        Ok(Self::relative_path(hash, ext))
+    }
+
+    /// Write `bytes` under their hash, refusing bytes that do not hash to `hash`
+    /// unless [`verify_hashes`](Self::verify_hashes) is off. A file already
+    /// stored with the same content is left alone; one with other content is
+    /// replaced. New files are written aside and renamed into place, so a stored
+    /// file never changes while someone is reading it.
+    pub fn store_media(&self, hash: &str, ext: &str, bytes: &[u8]) -> Result<StoredOutcome> {
+        Self::checked_path(hash, ext)?;
+        self.refuse_deleted(hash)?;
+        if self.verify_hashes {
+            let actual = sha256_hex(bytes);
+            if !actual.eq_ignore_ascii_case(hash) {
+                bail!("refusing to store content with sha256 {actual} as {hash}");
+            }
+        }
+        let prior = self.existing(hash, ext, bytes.len() as u64)?;
+        if let Some((path, true)) = prior {
+            self.touch(&path)?;
+            return Ok(StoredOutcome {
+                path,
+                relative_path: Self::relative_path(hash, ext),
+                already_existed: true,
+                repaired: false,
+            });
+        }
+        let (incoming, file) = self.create_incoming()?;
+        let stored = self
+            .copy_in(file, ext, &mut &bytes[..])
+            .and_then(|_| self.settle(&incoming, hash, ext, prior));
+        if stored.is_err() {
+            let _ = fs::remove_file(&incoming);
+        }
+        stored
+    }
+
+    /// [`store_media`](Self::store_media) under the canonical extension for the
+    /// type sniffed from `bytes`; the outcome's `relative_path` says which.
+    pub fn store_media_auto(&self, hash: &str, bytes: &[u8]) -> Result<StoredOutcome> {
+        self.store_media(hash, sniff_ext(bytes), bytes)
+    }
+
+    /// [`store_media`](Self::store_media) for content read from `reader`, which
+    /// is copied to a temporary file (hashed on the way) and only renamed into
+    /// place once complete.
+    pub fn store_from_reader(
+        &self,
+        hash: &str,
+        ext: &str,
+        mut reader: impl Read,
+    ) -> Result<StoredOutcome> {
+        Self::checked_path(hash, ext)?;
+        self.refuse_deleted(hash)?;
+        let (incoming, file) = self.create_incoming()?;
+        let stored = self
+            .copy_in(file, ext, &mut reader)
+            .and_then(|(len, actual)| {
+                if self.verify_hashes && !actual.eq_ignore_ascii_case(hash) {
+                    bail!("refusing to store content with sha256 {actual} as {hash}");
+                }
+                let prior = self.existing(hash, ext, len)?;
+                self.settle(&incoming, hash, ext, prior)
+            });
+        if stored.is_err() {
+            let _ = fs::remove_file(&incoming);
+        }
+        stored
+    }
+
+    /// How a new file with extension `ext` is written.
+    fn encoding_for(&self, ext: &str) -> Encoding {
+        Encoding {
+            compressed: self.compression.compresses(ext),
+            encrypted: self.encryption.is_some(),
+        }
+    }
+
+    /// Copy `reader` into `file`, compressed and encrypted as configured for
+    /// `ext`. Returns the length and sha256 of what was read.
+    fn copy_in(&self, file: File, ext: &str, reader: &mut dyn Read) -> Result<(u64, String)> {
+        let mut out: Box<dyn FinishWrite> = Box::new(file);
+        if let Some(config) = &self.encryption {
+            out = Box::new(EncryptWriter::new(out, config)?);
+        }
+        if self.compression.compresses(ext) {
+            out = Box::new(zstd::stream::write::Encoder::new(out, 0)?);
+        }
+        let mut hasher = Sha256::new();
+        let mut len = 0u64;
+        let mut buf = vec![0u8; 64 * 1024];
+        loop {
+            let n = match reader.read(&mut buf) {
+                Ok(0) => break,
+                Ok(n) => n,
+                Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e.into()),
+            };
+            hasher.update(&buf[..n]);
+            out.write_all(&buf[..n])?;
+            len += n as u64;
+        }
+        out.finish()?;
+        Ok((len, hex::encode(hasher.finalize())))
+    }
+
+    /// Move a filled incoming file into place, unless `prior` (from
+    /// [`existing`](Self::existing)) is already intact. A corrupt prior file
+    /// under another name (encoded differently) is removed.
+    fn settle(
+        &self,
+        incoming: &Path,
+        hash: &str,
+        ext: &str,
+        prior: Option<(PathBuf, bool)>,
+    ) -> Result<StoredOutcome> {
+        if let Some((path, true)) = prior {
+            fs::remove_file(incoming)?;
+            self.touch(&path)?;
+            return Ok(StoredOutcome {
+                path,
+                relative_path: Self::relative_path(hash, ext),
+                already_existed: true,
+                repaired: false,
+            });
+        }
+        let name = Self::relative_path(hash, ext) + &self.encoding_for(ext).suffix();
+        let replacing = prior.as_ref().map(|(old, _)| old.as_path());
+        Ok(StoredOutcome {
+            path: self.place(incoming, hash, &name, replacing)?,
+            relative_path: Self::relative_path(hash, ext),
+            already_existed: false,
+            repaired: prior.is_some(),
+        })
+    }
+
+    /// Rename `incoming` to `name`, first evicting files if the quota needs it,
+    /// and delete `replacing` (an older file for the same content) if it had
+    /// another name.
+    fn place(
+        &self,
+        incoming: &Path,
+        hash: &str,
+        name: &str,
+        replacing: Option<&Path>,
+    ) -> Result<PathBuf> {
+        check_plain(name)?;
+        let path = self.root.join(name);
+        let old_name = replacing.and_then(file_name);
+        let mut index = self.tracked()?;
+        let size = fs::metadata(incoming)?.len();
+        if let (Some(index), Some(quota)) = (index.as_mut(), &self.quota) {
+            let freed: Vec<&str> = [Some(name), old_name].into_iter().flatten().collect();
+            for evicted in index.make_room(quota, hash, size, &freed)? {
+                match fs::remove_file(self.root.join(&evicted)) {
+                    Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e.into()),
+                    _ => index.forget(&evicted),
+                }
+            }
+        }
+        fs::rename(incoming, &path)?;
+        if let Some(old) = replacing.filter(|old| *old != path) {
+            fs::remove_file(old)?;
+        }
+        if let Some(index) = index.as_mut() {
+            if let Some(old) = old_name {
+                index.forget(old);
+            }
+            index.record(name, size);
+            index.save(&self.root)?;
+        }
         Ok(path)
     }
 
+    /// The loaded index while a quota is set.
+    fn tracked(&self) -> Result<Option<MutexGuard<'_, Index>>> {
+        if self.quota.is_none() {
+            return Ok(None);
+        }
+        let mut index = self.index.lock().expect("storage index poisoned");
+        index.load(&self.root, || self.entry_sizes())?;
+        Ok(Some(index))
+    }
+
+    /// Mark the stored file at `path` as just used.
+    fn touch(&self, path: &Path) -> Result<()> {
+        if let (Some(mut index), Some(name)) = (self.tracked()?, file_name(path)) {
+            index.touch(name);
+            index.save(&self.root)?;
+        }
+        Ok(())
+    }
+
+    /// Keep every file stored for `hash` through eviction, e.g. while a
+    /// re-analysis job still needs it. Pins are kept in the index and survive
+    /// restarts. Returns false if it was already pinned.
+    pub fn pin(&self, hash: &str) -> Result<bool> {
+        let mut index = self.index.lock().expect("storage index poisoned");
+        index.load(&self.root, || self.entry_sizes())?;
+        let added = index.pin(hash);
+        index.save(&self.root)?;
+        Ok(added)
+    }
+
+    /// Returns false if `hash` was not pinned.
+    pub fn unpin(&self, hash: &str) -> Result<bool> {
+        let mut index = self.index.lock().expect("storage index poisoned");
+        index.load(&self.root, || self.entry_sizes())?;
+        let removed = index.unpin(hash);
+        index.save(&self.root)?;
+        Ok(removed)
+    }
+
+    /// Bytes stored files take on disk, against the quota if there is one.
+    pub fn usage(&self) -> Result<StorageUsage> {
+        let mut index = self.index.lock().expect("storage index poisoned");
+        index.load(&self.root, || self.entry_sizes())?;
+        if let Some(quota) = &self.quota {
+            return Ok(index.usage(Some(quota.max_bytes)));
+        }
+        // Without a quota the index does not follow writes, so look at the disk.
+        let sizes = self.entry_sizes()?;
+        Ok(StorageUsage {
+            used_bytes: sizes.iter().map(|(_, size)| size).sum(),
+            max_bytes: None,
+            files: sizes.len(),
+            pinned: index.pinned(),
+        })
+    }
+
+    fn entry_sizes(&self) -> Result<Vec<(String, u64)>> {
+        self.entries()?
+            .into_iter()
+            .map(|(name, entry)| Ok((name, entry.metadata()?.len())))
+            .collect()
+    }
+
+    /// `None` when nothing is stored for `hash`, otherwise the file found and
+    /// whether it holds `len` bytes of intact content.
+    fn existing(&self, hash: &str, ext: &str, len: u64) -> Result<Option<(PathBuf, bool)>> {
+        let Some((path, encoding)) = self.locate(&Self::relative_path(hash, ext)) else {
+            return Ok(None);
+        };
+        if encoding.is_plain() && fs::metadata(&path)?.len() != len {
+            return Ok(Some((path, false)));
+        }
+        if encoding.is_plain() && !self.verify_hashes {
+            return Ok(Some((path, true)));
+        }
+        // Encryption errors are passed on rather than repaired: with the wrong
+        // key a good file would be overwritten.
+        let mut reader = self.reader(&path, encoding)?;
+        let mut hasher = Sha256::new();
+        let actual_len = match std::io::copy(&mut reader, &mut hasher).map_err(unwrap_io) {
+            Ok(n) => n,
+            Err(e) if e.is::<EncryptionError>() => return Err(e),
+            // A truncated or garbled compressed file.
+            Err(_) if encoding.compressed => return Ok(Some((path, false))),
+            Err(e) => return Err(e),
+        };
+        let intact = actual_len == len
+            && (!self.verify_hashes || hex::encode(hasher.finalize()).eq_ignore_ascii_case(hash));
+        Ok(Some((path, intact)))
+    }
+
+    /// Where the content for `relative_path` is kept, and how it is encoded
+    /// there.
+    fn locate(&self, relative_path: &str) -> Option<(PathBuf, Encoding)> {
+        if !is_plain_name(relative_path) {
+            return None;
+        }
+        Encoding::ALL.into_iter().find_map(|encoding| {
+            let path = self
+                .root
+                .join(relative_path.to_string() + &encoding.suffix());
+            path.is_file().then_some((path, encoding))
+        })
+    }
+
+    fn reader(&self, path: &Path, encoding: Encoding) -> Result<StoredReader> {
+        if encoding.is_plain() {
+            return Ok(StoredReader::plain(File::open(path)?));
+        }
+        let (path, key) = (path.to_path_buf(), self.encryption.clone());
+        let open = move || -> Result<Box<dyn Read + Send>> {
+            let file = BufReader::new(File::open(&path)?);
+            let decrypted: Box<dyn Read + Send> = if encoding.encrypted {
+                Box::new(DecryptReader::new(file, key.as_ref())?)
+            } else {
+                Box::new(file)
+            };
+            Ok(if encoding.compressed {
+                Box::new(zstd::stream::read::Decoder::new(decrypted)?)
+            } else {
+                decrypted
+            })
+        };
+        StoredReader::decoded(Arc::new(open), encoding)
+    }
+
+    /// A new hidden file under `root` for content whose hash is not known yet;
+    /// [`persist_incoming`](Self::persist_incoming) moves it to its final name.
+    pub fn create_incoming(&self) -> Result<(PathBuf, File)> {
+        fs::create_dir_all(&self.root)?;
+        let n = INCOMING.fetch_add(1, Ordering::Relaxed);
+        let path = self
+            .root
+            .join(format!(".incoming-{}-{n}", std::process::id()));
+        let file = File::create(&path)?;
+        Ok((path, file))
+    }
+
+    /// Rename a file from [`create_incoming`](Self::create_incoming) to where
+    /// [`store_media`](Self::store_media) would have put the same content,
+    /// uncompressed whatever the policy: the caller wrote it and may map it.
+    /// Counts against the quota like any other write. Refused when encryption
+    /// is configured, since the incoming file holds plaintext.
+    pub fn persist_incoming(&self, incoming: &Path, hash: &str, ext: &str) -> Result<PathBuf> {
+        if self.encryption.is_some() {
+            bail!("refusing to persist an unencrypted file into encrypted storage");
+        }
+        let name = Self::checked_path(hash, ext)?;
+        self.refuse_deleted(hash)?;
+        self.place(incoming, hash, &name, None)
+    }
+
+    /// Remove what is stored for `hash` with extension `ext`, compressed or not,
+    /// leaving a tombstone in tombstone mode. Returns whether a file was removed.
+    pub fn delete(&self, hash: &str, ext: &str) -> Result<bool> {
+        let name = Self::checked_path(hash, ext)?;
+        let names = Encoding::ALL.iter().map(|e| name.clone() + &e.suffix());
+        Ok(self.remove(hash, names.collect())? > 0)
+    }
+
+    /// Remove every file stored for `hash`, whatever its extension, leaving a
+    /// tombstone in tombstone mode. Returns how many files were removed.
+    pub fn delete_all(&self, hash: &str) -> Result<usize> {
+        check_plain(hash)?;
+        let names = self
+            .entries()?
+            .into_iter()
+            .map(|(name, _)| name)
+            .filter(|name| name.split_once('.').is_some_and(|(stem, _)| stem == hash))
+            .collect();
+        self.remove(hash, names)
+    }
+
+    fn remove(&self, hash: &str, names: Vec<String>) -> Result<usize> {
+        let tombstone = self.tombstone_path(hash)?;
+        for name in &names {
+            check_plain(name)?;
+        }
+        let mut index = self.tracked()?;
+        let mut removed = 0;
+        for name in names {
+            match fs::remove_file(self.root.join(&name)) {
+                Ok(()) => removed += 1,
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                Err(e) => return Err(e.into()),
+            }
+            if let Some(index) = index.as_mut() {
+                index.forget(&name);
+            }
+        }
+        if self.tombstones {
+            fs::create_dir_all(&self.root)?;
+            File::create(tombstone)?;
+        }
+        if let Some(mut index) = index {
+            index.save(&self.root)?;
+        }
+        Ok(removed)
+    }
+
+    /// Whether `hash` was deleted in tombstone mode.
+    pub fn is_deleted(&self, hash: &str) -> bool {
+        self.tombstone_path(hash).is_ok_and(|path| path.is_file())
+    }
+
+    fn tombstone_path(&self, hash: &str) -> Result<PathBuf> {
+        check_plain(hash)?;
+        Ok(self.root.join(format!("{hash}.{TOMBSTONE}")))
+    }
+
+    fn refuse_deleted(&self, hash: &str) -> Result<()> {
+        if self.tombstone_path(hash)?.is_file() {
+            return Err(StorageError::Deleted {
+                hash: hash.to_string(),
+            }
+            .into());
+        }
+        Ok(())
+    }
+
+    /// Whether content is stored at `relative_path`, without reading it.
+    pub fn contains(&self, relative_path: &str) -> bool {
+        self.locate(relative_path).is_some()
+    }
+
     pub fn load_media(&self, hash: &str, ext: &str) -> Result<Vec<u8>> {
-        let path = self.root.join(format!("{hash}.{ext}"));
+        self.load_relative(&Self::relative_path(hash, ext))
+    }
+
+    /// The content stored for `hash` under whatever extension it has. Fails
+    /// with [`StorageError::Ambiguous`] when there is more than one.
+    pub fn load_media_any(&self, hash: &str) -> Result<Vec<u8>> {
+        let exts: BTreeSet<String> = self
+            .entries()?
+            .into_iter()
+            .filter_map(|(name, _)| {
+                let (stem, rest) = name.split_once('.')?;
+                (stem == hash).then(|| Encoding::of(rest).0.to_string())
+            })
+            .collect();
+        let mut exts: Vec<String> = exts.into_iter().collect();
+        match exts.len() {
+            0 => Err(self.missing(hash, &format!("{hash}.*")).into()),
+            1 => self.load_media(hash, &exts.remove(0)),
+            _ => Err(StorageError::Ambiguous {
+                hash: hash.to_string(),
+                exts,
+            }
+            .into()),
+        }
+    }
+
+    pub fn load_relative(&self, relative_path: &str) -> Result<Vec<u8>> {
         let mut buf = Vec::new();
-        File::open(&path)?.read_to_end(&mut buf)?;
+        self.open_relative(relative_path)?
+            .read_to_end(&mut buf)
+            .map_err(unwrap_io)?;
         Ok(buf)
     }
+
+    /// The stored content, for reading (and seeking) without loading it whole.
+    pub fn open_read(&self, hash: &str, ext: &str) -> Result<StoredReader> {
+        self.open_relative(&Self::relative_path(hash, ext))
+    }
+
+    pub fn open_relative(&self, relative_path: &str) -> Result<StoredReader> {
+        if !is_plain_name(relative_path) {
+            bail!("refusing to read {relative_path:?}: not a file name under the storage root");
+        }
+        match self.locate(relative_path) {
+            Some((path, encoding)) => {
+                let reader = self.reader(&path, encoding)?;
+                self.touch(&path)?;
+                Ok(reader)
+            }
+            None => {
+                let hash = relative_path
+                    .split_once('.')
+                    .map_or(relative_path, |(hash, _)| hash);
+                Err(self.missing(hash, relative_path).into())
+            }
+        }
+    }
+
+    /// Why nothing is stored for `hash` at `relative_path`.
+    fn missing(&self, hash: &str, relative_path: &str) -> StorageError {
+        if self.is_deleted(hash) {
+            StorageError::Deleted {
+                hash: hash.to_string(),
+            }
+        } else {
+            StorageError::NotFound {
+                relative_path: relative_path.to_string(),
+            }
+        }
+    }
+
+    /// Sizes, modification time and extension of the file stored for `hash`, if
+    /// there is one. The original size of a compressed or encrypted file is
+    /// found by decoding it.
+    pub fn stat(&self, hash: &str) -> Result<Option<StoredStat>> {
+        for (name, entry) in self.entries()? {
+            let Some((stem, ext)) = name.split_once('.') else {
+                continue;
+            };
+            if stem != hash {
+                continue;
+            }
+            let meta = entry.metadata()?;
+            let (ext, encoding) = Encoding::of(ext);
+            let size = if encoding.is_plain() {
+                meta.len()
+            } else {
+                let mut reader = self.reader(&entry.path(), encoding)?;
+                std::io::copy(&mut reader, &mut std::io::sink()).map_err(unwrap_io)?
+            };
+            return Ok(Some(StoredStat {
+                size,
+                stored_size: meta.len(),
+                compressed: encoding.compressed,
+                modified: meta.modified()?,
+                ext: ext.to_string(),
+            }));
+        }
+        Ok(None)
+    }
+
+    /// Up to `limit` stored hashes starting with `prefix`, in ascending order.
+    pub fn list(&self, prefix: &str, limit: usize) -> Result<Vec<String>> {
+        let hashes: BTreeSet<String> = self
+            .entries()?
+            .into_iter()
+            .filter_map(|(name, _)| Some(name.split_once('.')?.0.to_string()))
+            .filter(|hash| hash.starts_with(prefix))
+            .collect();
+        Ok(hashes.into_iter().take(limit).collect())
+    }
+
+    /// Stored files under `root` with their names; files still being written are
+    /// hidden and left out.
+    fn entries(&self) -> Result<Vec<(String, fs::DirEntry)>> {
+        let dir = match fs::read_dir(&self.root) {
+            Ok(dir) => dir,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e.into()),
+        };
+        let mut entries = Vec::new();
+        for entry in dir {
+            let entry = entry?;
+            let Ok(name) = entry.file_name().into_string() else {
+                continue;
+            };
+            let tombstone = name.ends_with(&format!(".{TOMBSTONE}"));
+            if !name.starts_with('.') && !tombstone && entry.file_type()?.is_file() {
+                entries.push((name, entry));
+            }
+        }
+        Ok(entries)
+    }
+}
+
+fn file_name(path: &Path) -> Option<&str> {
+    path.file_name()?.to_str()
+}
+
+/// Stored files sit directly under the root, so a relative path is a single
+/// file name: no `..`, no root and no directories.
+fn check_plain(name: &str) -> Result<()> {
+    if !is_plain_name(name) {
+        bail!("refusing {name:?}: not a file name under the storage root");
+    }
+    Ok(())
+}
+
+fn is_plain_name(relative_path: &str) -> bool {
+    let mut components = Path::new(relative_path).components();
+    matches!(
+        (components.next(), components.next()),
+        (Some(std::path::Component::Normal(_)), None)
+    )
+}
+
+/// A writer in the [`copy_in`](MediaStorage::copy_in) chain, finished from
+/// the outside in so each layer writes its trailer before the next.
+trait FinishWrite: Write {
+    fn finish(self: Box<Self>) -> std::io::Result<()>;
+}
+
+impl FinishWrite for File {
+    fn finish(self: Box<Self>) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+impl FinishWrite for EncryptWriter<Box<dyn FinishWrite>> {
+    fn finish(self: Box<Self>) -> std::io::Result<()> {
+        EncryptWriter::finish(*self)?.finish()
+    }
+}
+
+impl FinishWrite for zstd::stream::write::Encoder<'static, Box<dyn FinishWrite>> {
+    fn finish(self: Box<Self>) -> std::io::Result<()> {
+        zstd::stream::write::Encoder::finish(*self)?.finish()
+    }
+}
+
+fn sha256_hex(bytes: &[u8]) -> String {
+    hex::encode(Sha256::digest(bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn content_that_does_not_match_its_hash_is_refused() {
+        let dir = tempdir().unwrap();
+        let storage = MediaStorage::new(dir.path());
+        let hash = sha256_hex(b"genuine");
+
+        let err = storage.store_media(&hash, "txt", b"forged").unwrap_err();
+        assert!(err.to_string().contains("refusing"), "{err}");
+        assert!(!dir.path().join(format!("{hash}.txt")).exists());
+
+        // Trusted callers are taken at their word.
+        let stored = storage
+            .clone()
+            .trusted()
+            .store_media(&hash, "txt", b"forged")
+            .unwrap();
+        assert_eq!(fs::read(&stored.path).unwrap(), b"forged");
+    }
+
+    #[test]
+    fn existing_files_are_kept_and_corrupt_ones_replaced() {
+        let dir = tempdir().unwrap();
+        let storage = MediaStorage::new(dir.path());
+        let hash = sha256_hex(b"content");
+
+        let first = storage.store_media(&hash, "bin", b"content").unwrap();
+        assert!(!first.already_existed && !first.repaired);
+        let modified = fs::metadata(&first.path).unwrap().modified().unwrap();
+        let again = storage.store_media(&hash, "bin", b"content").unwrap();
+        assert!(again.already_existed && !again.repaired);
+        assert_eq!(again.path, first.path);
+        assert_eq!(
+            fs::metadata(&again.path).unwrap().modified().unwrap(),
+            modified
+        );
+
+        // Same size, different bytes: only the hash check notices.
+        fs::write(&first.path, b"CONTENT").unwrap();
+        let repaired = storage.store_media(&hash, "bin", b"content").unwrap();
+        assert!(!repaired.already_existed && repaired.repaired);
+        assert_eq!(storage.load_media(&hash, "bin").unwrap(), b"content");
+
+        fs::write(&first.path, b"short").unwrap();
+        let trusted = storage.clone().trusted();
+        assert!(
+            trusted
+                .store_media(&hash, "bin", b"content")
+                .unwrap()
+                .repaired
+        );
+        assert_eq!(storage.load_media(&hash, "bin").unwrap(), b"content");
+
+        let leftovers = fs::read_dir(dir.path())
+            .unwrap()
+            .filter(|e| {
+                let name = e.as_ref().unwrap().file_name();
+                name.to_string_lossy().starts_with(".incoming")
+            })
+            .count();
+        assert_eq!(leftovers, 0);
+    }
+
+    /// Hands out `chunks` pieces of `chunk` with a pause between each.
+    struct Slow {
+        chunk: Vec<u8>,
+        chunks: usize,
+    }
+
+    impl Read for Slow {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            if self.chunks == 0 {
+                return Ok(0);
+            }
+            self.chunks -= 1;
+            std::thread::sleep(std::time::Duration::from_millis(2));
+            let n = self.chunk.len().min(buf.len());
+            buf[..n].copy_from_slice(&self.chunk[..n]);
+            Ok(n)
+        }
+    }
+
+    #[test]
+    fn readers_never_see_a_partially_written_file() {
+        let dir = tempdir().unwrap();
+        let storage = MediaStorage::new(dir.path());
+        let (chunk, chunks) = (vec![7u8; 4096], 50);
+        let full = vec![7u8; 4096 * 50];
+        let hash = sha256_hex(&full);
+
+        // Whenever the reader finds the file, it is already complete.
+        let done = std::sync::atomic::AtomicBool::new(false);
+        std::thread::scope(|s| {
+            s.spawn(|| {
+                while !done.load(Ordering::Acquire) {
+                    if let Ok(mut file) = storage.open_read(&hash, "bin") {
+                        let mut buf = Vec::new();
+                        file.read_to_end(&mut buf).unwrap();
+                        assert_eq!(buf, full);
+                    }
+                    if let Some(stat) = storage.stat(&hash).unwrap() {
+                        assert_eq!(stat.size, full.len() as u64);
+                    }
+                }
+            });
+            let stored = storage
+                .store_from_reader(&hash, "bin", Slow { chunk, chunks })
+                .unwrap();
+            assert!(!stored.already_existed);
+            done.store(true, Ordering::Release);
+        });
+
+        let again = storage.store_from_reader(&hash, "bin", &full[..]).unwrap();
+        assert!(again.already_existed);
+        let err = storage
+            .store_from_reader(&sha256_hex(b"x"), "bin", &full[..])
+            .unwrap_err();
+        assert!(err.to_string().contains("refusing"), "{err}");
+        assert_eq!(storage.list("", 10).unwrap(), vec![hash.clone()]);
+
+        let stat = storage.stat(&hash).unwrap().unwrap();
+        assert_eq!((stat.size, stat.ext.as_str()), (full.len() as u64, "bin"));
+        let mut file = storage.open_read(&hash, "bin").unwrap();
+        let end = full.len() as u64 - 2;
+        std::io::Seek::seek(&mut file, std::io::SeekFrom::Start(end)).unwrap();
+        let mut tail = Vec::new();
+        file.read_to_end(&mut tail).unwrap();
+        assert_eq!(tail, [7, 7]);
+    }
+
+    #[test]
+    fn relative_paths_outside_the_root_are_refused() {
+        let dir = tempdir().unwrap();
+        let root = dir.path().join("media");
+        let storage = MediaStorage::new(&root);
+        fs::create_dir_all(&root).unwrap();
+        fs::write(dir.path().join("secret.txt"), b"secret").unwrap();
+        let secret = dir.path().join("secret.txt");
+
+        for path in [
+            "../secret.txt",
+            secret.to_str().unwrap(),
+            "sub/../../secret.txt",
+        ] {
+            let err = storage.load_relative(path).unwrap_err();
+            assert!(
+                err.to_string().contains("refusing to read"),
+                "{path}: {err}"
+            );
+            assert!(!storage.contains(path));
+        }
+        let hash = sha256_hex(b"content");
+        let stored = storage.store_media(&hash, "bin", b"content").unwrap();
+        assert!(storage.contains(&stored.relative_path));
+        assert_eq!(
+            storage.load_relative(&stored.relative_path).unwrap(),
+            b"content"
+        );
+    }
+
+    #[test]
+    fn hashes_and_extensions_outside_the_root_are_refused() {
+        let dir = tempdir().unwrap();
+        let root = dir.path().join("media");
+        let storage = MediaStorage::new(&root).trusted().with_tombstones();
+        fs::create_dir_all(&root).unwrap();
+        let secret = dir.path().join("secret.txt");
+        fs::write(&secret, b"secret").unwrap();
+
+        for (hash, ext) in [
+            ("../secret", "txt"),
+            ("secret", "../../x"),
+            ("a/../../b", "bin"),
+        ] {
+            let err = storage.store_media(hash, ext, b"content").unwrap_err();
+            assert!(err.to_string().contains("refusing"), "{hash}.{ext}: {err}");
+            assert!(storage
+                .store_from_reader(hash, ext, &b"content"[..])
+                .is_err());
+            assert!(storage.delete(hash, ext).is_err());
+            let (incoming, _) = storage.create_incoming().unwrap();
+            assert!(storage.persist_incoming(&incoming, hash, ext).is_err());
+            fs::remove_file(incoming).unwrap();
+        }
+        assert!(storage.delete_all("../secret").is_err());
+        assert!(!storage.is_deleted("../secret"));
+        assert_eq!(fs::read(&secret).unwrap(), b"secret");
+        assert!(!dir.path().join("secret.deleted").exists());
+        assert!(!dir.path().join("x").exists());
+    }
+
+    #[test]
+    fn compressed_files_round_trip_under_their_original_hash() {
+        let dir = tempdir().unwrap();
+        let plain = MediaStorage::new(dir.path());
+        let storage = plain
+            .clone()
+            .with_compression(CompressionPolicy::by_extension(["txt"]));
+        let text = "the same sentence, over and over. ".repeat(500);
+        let hash = sha256_hex(text.as_bytes());
+
+        let stored = storage.store_media(&hash, "txt", text.as_bytes()).unwrap();
+        assert!(stored.path.to_string_lossy().ends_with(".txt.zst"));
+        assert_eq!(storage.load_media(&hash, "txt").unwrap(), text.as_bytes());
+        // Reading does not depend on the policy.
+        assert_eq!(plain.load_media(&hash, "txt").unwrap(), text.as_bytes());
+        assert!(
+            storage
+                .store_media(&hash, "txt", text.as_bytes())
+                .unwrap()
+                .already_existed
+        );
+
+        let stat = storage.stat(&hash).unwrap().unwrap();
+        assert!(stat.compressed);
+        assert_eq!((stat.size, stat.ext.as_str()), (text.len() as u64, "txt"));
+        assert!(stat.stored_size < stat.size / 10);
+
+        let mut reader = storage.open_read(&hash, "txt").unwrap();
+        assert!(reader.is_compressed());
+        let end = std::io::Seek::seek(&mut reader, std::io::SeekFrom::End(-6)).unwrap();
+        assert_eq!(end, text.len() as u64 - 6);
+        let mut tail = String::new();
+        reader.read_to_string(&mut tail).unwrap();
+        assert_eq!(tail, "over. ");
+        std::io::Seek::seek(&mut reader, std::io::SeekFrom::Start(4)).unwrap();
+        let mut word = [0u8; 4];
+        reader.read_exact(&mut word).unwrap();
+        assert_eq!(&word, b"same");
+
+        // Already-compressed formats are kept as they are.
+        let jpg = b"\xff\xd8\xff not really a jpeg";
+        let jpg_hash = sha256_hex(jpg);
+        let stored = storage.store_media(&jpg_hash, "jpg", jpg).unwrap();
+        assert!(stored.path.to_string_lossy().ends_with(".jpg"));
+        assert!(!storage.stat(&jpg_hash).unwrap().unwrap().compressed);
+        assert!(!storage.open_read(&jpg_hash, "jpg").unwrap().is_compressed());
+
+        assert_eq!(storage.list("", 10).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn policy_changes_keep_old_files_readable_and_repair_across_forms() {
+        let dir = tempdir().unwrap();
+        let plain = MediaStorage::new(dir.path());
+        let always = plain.clone().with_compression(CompressionPolicy::Always);
+        let bytes = vec![3u8; 10_000];
+        let hash = sha256_hex(&bytes);
+
+        let first = plain.store_media(&hash, "mp4", &bytes).unwrap();
+        let again = always.store_media(&hash, "mp4", &bytes).unwrap();
+        assert!(again.already_existed);
+        assert_eq!(again.path, first.path);
+        assert_eq!(always.load_media(&hash, "mp4").unwrap(), bytes);
+
+        // A corrupt uncompressed copy is replaced by a compressed one.
+        fs::write(&first.path, b"garbage").unwrap();
+        let repaired = always.store_media(&hash, "mp4", &bytes).unwrap();
+        assert!(repaired.repaired);
+        assert!(!first.path.exists());
+        assert!(repaired.path.to_string_lossy().ends_with(".mp4.zst"));
+        assert_eq!(plain.load_media(&hash, "mp4").unwrap(), bytes);
+
+        // And a garbled compressed copy is noticed too.
+        fs::write(&repaired.path, b"not zstd").unwrap();
+        let fixed = always.store_from_reader(&hash, "mp4", &bytes[..]).unwrap();
+        assert!(fixed.repaired);
+        assert_eq!(always.load_media(&hash, "mp4").unwrap(), bytes);
+    }
+
+    #[test]
+    fn encrypted_files_round_trip_and_keep_plaintext_off_disk() {
+        let dir = tempdir().unwrap();
+        let plain = MediaStorage::new(dir.path());
+        let storage = plain
+            .clone()
+            .with_encryption(EncryptionConfig::new([7; 32]));
+        // Several chunks, so seeking has to cross chunk boundaries.
+        let bytes: Vec<u8> = (0..200_000u32).flat_map(|i| i.to_le_bytes()).collect();
+        let hash = sha256_hex(&bytes);
+
+        let stored = storage.store_from_reader(&hash, "bin", &bytes[..]).unwrap();
+        assert!(stored.path.to_string_lossy().ends_with(".bin.enc"));
+        let on_disk = fs::read(&stored.path).unwrap();
+        assert!(!on_disk.windows(16).any(|w| w == &bytes[400..416]));
+        assert_eq!(storage.load_media(&hash, "bin").unwrap(), bytes);
+        assert!(
+            storage
+                .store_media(&hash, "bin", &bytes)
+                .unwrap()
+                .already_existed
+        );
+        assert_eq!(
+            storage.stat(&hash).unwrap().unwrap().size,
+            bytes.len() as u64
+        );
+
+        let mut reader = storage.open_read(&hash, "bin").unwrap();
+        assert!(reader.is_encrypted() && !reader.is_compressed());
+        std::io::Seek::seek(&mut reader, std::io::SeekFrom::Start(500_000)).unwrap();
+        let mut word = [0u8; 4];
+        reader.read_exact(&mut word).unwrap();
+        assert_eq!(u32::from_le_bytes(word), 125_000);
+
+        // Compression applies before encryption.
+        let both = storage.clone().with_compression(CompressionPolicy::Always);
+        let text = "compressible ".repeat(1000);
+        let text_hash = sha256_hex(text.as_bytes());
+        let stored = both
+            .store_media(&text_hash, "txt", text.as_bytes())
+            .unwrap();
+        assert!(stored.path.to_string_lossy().ends_with(".txt.zst.enc"));
+        assert!(fs::metadata(&stored.path).unwrap().len() < 1000);
+        assert_eq!(
+            storage.load_media(&text_hash, "txt").unwrap(),
+            text.as_bytes()
+        );
+
+        // Files stored before encryption was set stay readable.
+        let old = b"stored in the clear";
+        let old_hash = sha256_hex(old);
+        plain.store_media(&old_hash, "txt", old).unwrap();
+        assert_eq!(storage.load_media(&old_hash, "txt").unwrap(), old);
+    }
+
+    #[test]
+    fn encrypted_files_need_the_right_key() {
+        let dir = tempdir().unwrap();
+        let storage = MediaStorage::new(dir.path()).with_encryption(EncryptionConfig::new([1; 32]));
+        let bytes = b"secret footage";
+        let hash = sha256_hex(bytes);
+        storage.store_media(&hash, "mp4", bytes).unwrap();
+
+        let wrong = MediaStorage::new(dir.path()).with_encryption(EncryptionConfig::new([2; 32]));
+        let err = wrong.load_media(&hash, "mp4").unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<EncryptionError>(),
+            Some(&EncryptionError::Authentication)
+        );
+        // A file the key cannot read is not mistaken for a corrupt one.
+        assert!(wrong.store_media(&hash, "mp4", bytes).is_err());
+        assert_eq!(storage.load_media(&hash, "mp4").unwrap(), bytes);
+
+        let keyless = MediaStorage::new(dir.path());
+        let err = keyless.load_media(&hash, "mp4").unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<EncryptionError>(),
+            Some(&EncryptionError::KeyMissing)
+        );
+
+        let path = storage.root.join(format!("{hash}.mp4.enc"));
+        let mut tampered = fs::read(&path).unwrap();
+        *tampered.last_mut().unwrap() ^= 1;
+        fs::write(&path, tampered).unwrap();
+        let err = storage.load_media(&hash, "mp4").unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<EncryptionError>(),
+            Some(&EncryptionError::Authentication)
+        );
+    }
+
+    #[test]
+    fn list_filters_by_prefix_and_limit() {
+        let dir = tempdir().unwrap();
+        let storage = MediaStorage::new(dir.path());
+        assert!(storage.list("", 10).unwrap().is_empty());
+        assert_eq!(storage.stat("missing").unwrap(), None);
+
+        let mut hashes = Vec::new();
+        for i in 0..20u8 {
+            let bytes = [i];
+            let hash = sha256_hex(&bytes);
+            storage.store_media(&hash, "bin", &bytes).unwrap();
+            hashes.push(hash);
+        }
+        let (_incoming, _file) = storage.create_incoming().unwrap();
+        hashes.sort();
+
+        assert_eq!(storage.list("", 100).unwrap(), hashes);
+        assert_eq!(storage.list("", 3).unwrap(), hashes[..3]);
+        let prefix = &hashes[5][..2];
+        let expected: Vec<_> = hashes
+            .iter()
+            .filter(|h| h.starts_with(prefix))
+            .cloned()
+            .collect();
+        assert_eq!(storage.list(prefix, 100).unwrap(), expected);
+        assert!(storage.list("zz", 100).unwrap().is_empty());
+    }
+
+    fn blob(i: u8, len: usize) -> (String, Vec<u8>) {
+        let bytes = vec![i; len];
+        (sha256_hex(&bytes), bytes)
+    }
+
+    #[test]
+    fn quota_refuses_or_evicts_least_recently_used() {
+        let dir = tempdir().unwrap();
+        let quota = |eviction| Quota {
+            max_bytes: 300,
+            eviction,
+        };
+        let refusing = MediaStorage::new(dir.path()).with_quota(quota(Eviction::Refuse));
+        let blobs: Vec<_> = (1..=6).map(|i| blob(i, 100)).collect();
+        for (hash, bytes) in &blobs[..3] {
+            refusing.store_media(hash, "bin", bytes).unwrap();
+        }
+        let err = refusing
+            .store_media(&blobs[3].0, "bin", &blobs[3].1)
+            .unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<QuotaExceeded>(),
+            Some(&QuotaExceeded {
+                needed: 100,
+                used: 300,
+                max_bytes: 300,
+            })
+        );
+        // Storing what is already there needs no room.
+        assert!(
+            refusing
+                .store_media(&blobs[0].0, "bin", &blobs[0].1)
+                .unwrap()
+                .already_existed
+        );
+
+        let lru = MediaStorage::new(dir.path()).with_quota(quota(Eviction::LeastRecentlyUsed));
+        let stored = |i: usize| lru.stat(&blobs[i].0).unwrap().is_some();
+        // Blob 0 was stored again above, and loading counts as use too.
+        lru.load_media(&blobs[1].0, "bin").unwrap();
+        lru.store_media(&blobs[3].0, "bin", &blobs[3].1).unwrap();
+        assert_eq!(
+            (0..4).map(stored).collect::<Vec<_>>(),
+            [true, true, false, true]
+        );
+
+        assert!(lru.pin(&blobs[0].0).unwrap());
+        assert!(!lru.pin(&blobs[0].0).unwrap());
+        lru.store_media(&blobs[4].0, "bin", &blobs[4].1).unwrap();
+        assert_eq!(
+            (0..5).map(stored).collect::<Vec<_>>(),
+            [true, false, false, true, true]
+        );
+
+        // Too big even with everything unpinned evicted: nothing is touched.
+        let (big_hash, big) = blob(9, 250);
+        let err = lru.store_media(&big_hash, "bin", &big).unwrap_err();
+        assert!(err.downcast_ref::<QuotaExceeded>().is_some(), "{err}");
+        assert_eq!(
+            lru.usage().unwrap(),
+            StorageUsage {
+                used_bytes: 300,
+                max_bytes: Some(300),
+                files: 3,
+                pinned: 1,
+            }
+        );
+
+        // The index outlives the process: pins and access order are remembered.
+        let reopened = MediaStorage::new(dir.path()).with_quota(quota(Eviction::LeastRecentlyUsed));
+        assert_eq!(reopened.usage().unwrap(), lru.usage().unwrap());
+        assert!(reopened.unpin(&blobs[0].0).unwrap());
+        reopened
+            .store_media(&blobs[5].0, "bin", &blobs[5].1)
+            .unwrap();
+        assert!(reopened.stat(&blobs[0].0).unwrap().is_none());
+        assert!(reopened.stat(&blobs[3].0).unwrap().is_some());
+        assert_eq!(
+            MediaStorage::new(dir.path()).usage().unwrap().used_bytes,
+            300
+        );
+    }
+
+    #[test]
+    fn storages_sharing_a_root_see_each_others_writes() {
+        let dir = tempdir().unwrap();
+        let quota = Quota {
+            max_bytes: 300,
+            eviction: Eviction::LeastRecentlyUsed,
+        };
+        let first = MediaStorage::new(dir.path()).with_quota(quota.clone());
+        let second = MediaStorage::new(dir.path()).with_quota(quota);
+        let blobs: Vec<_> = (1..=4).map(|i| blob(i, 100)).collect();
+        assert_eq!(second.usage().unwrap().used_bytes, 0);
+
+        for (hash, bytes) in &blobs[..3] {
+            first.store_media(hash, "bin", bytes).unwrap();
+        }
+        assert!(first.pin(&blobs[0].0).unwrap());
+        assert_eq!(second.usage().unwrap().used_bytes, 300);
+        assert_eq!(second.usage().unwrap().pinned, 1);
+
+        // The pin made elsewhere holds, so the oldest unpinned file goes.
+        second.store_media(&blobs[3].0, "bin", &blobs[3].1).unwrap();
+        let stored = |i: usize| first.stat(&blobs[i].0).unwrap().is_some();
+        assert_eq!(
+            (0..4).map(stored).collect::<Vec<_>>(),
+            [true, false, true, true]
+        );
+        assert_eq!(first.usage().unwrap().used_bytes, 300);
+    }
+
+    #[test]
+    fn delete_removes_every_variant() {
+        let dir = tempdir().unwrap();
+        let storage = MediaStorage::new(dir.path());
+        let (hash, bytes) = blob(1, 64);
+        storage.store_media(&hash, "png", &bytes).unwrap();
+        storage
+            .clone()
+            .with_compression(CompressionPolicy::Always)
+            .store_media(&hash, "bin", &bytes)
+            .unwrap();
+        let (other, other_bytes) = blob(2, 64);
+        storage.store_media(&other, "png", &other_bytes).unwrap();
+
+        assert!(storage.delete(&hash, "png").unwrap());
+        assert!(!storage.delete(&hash, "png").unwrap());
+        let err = storage.load_media(&hash, "png").unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<StorageError>(),
+            Some(&StorageError::NotFound {
+                relative_path: format!("{hash}.png"),
+            })
+        );
+        assert_eq!(storage.load_media(&hash, "bin").unwrap(), bytes);
+
+        assert_eq!(storage.delete_all(&hash).unwrap(), 1);
+        assert_eq!(storage.list("", 10).unwrap(), vec![other.clone()]);
+        // Without tombstones the content may simply be stored again.
+        assert!(!storage.is_deleted(&hash));
+        storage.store_media(&hash, "png", &bytes).unwrap();
+    }
+
+    #[test]
+    fn tombstones_refuse_the_same_content_again() {
+        let dir = tempdir().unwrap();
+        let storage = MediaStorage::new(dir.path()).with_tombstones();
+        let (hash, bytes) = blob(1, 64);
+        storage.store_media(&hash, "jpg", &bytes).unwrap();
+        storage.store_media(&hash, "png", &bytes).unwrap();
+
+        assert_eq!(storage.delete_all(&hash).unwrap(), 2);
+        assert!(storage.is_deleted(&hash));
+        let deleted = Some(StorageError::Deleted { hash: hash.clone() });
+        let err = storage.load_media(&hash, "jpg").unwrap_err();
+        assert_eq!(err.downcast_ref::<StorageError>(), deleted.as_ref());
+        assert!(storage.list("", 10).unwrap().is_empty());
+        assert_eq!(storage.stat(&hash).unwrap(), None);
+
+        // The marker is honoured even by a storage not in tombstone mode.
+        let plain = MediaStorage::new(dir.path());
+        let err = plain.store_media(&hash, "jpg", &bytes).unwrap_err();
+        assert_eq!(err.downcast_ref::<StorageError>(), deleted.as_ref());
+        let err = plain
+            .store_from_reader(&hash, "jpg", &bytes[..])
+            .unwrap_err();
+        assert_eq!(err.downcast_ref::<StorageError>(), deleted.as_ref());
+        let (incoming, _file) = plain.create_incoming().unwrap();
+        assert!(plain.persist_incoming(&incoming, &hash, "jpg").is_err());
+        assert!(!dir.path().join(format!("{hash}.jpg")).exists());
+    }
+
+    #[test]
+    fn auto_store_uses_canonical_extensions_and_any_load_finds_them() {
+        let dir = tempdir().unwrap();
+        let storage = MediaStorage::new(dir.path());
+        let jpeg = b"\xff\xd8\xff\xe0 a small jpeg";
+        let hash = sha256_hex(jpeg);
+
+        let stored = storage.store_media_auto(&hash, jpeg).unwrap();
+        assert_eq!(stored.relative_path, format!("{hash}.jpg"));
+        assert_eq!(
+            storage
+                .load_media(&hash, &canonicalize_ext("JPEG"))
+                .unwrap(),
+            jpeg
+        );
+        assert_eq!(storage.load_media_any(&hash).unwrap(), jpeg);
+
+        // The same content under a second extension makes the lookup ambiguous.
+        storage.store_media(&hash, "jpeg", jpeg).unwrap();
+        let err = storage.load_media_any(&hash).unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<StorageError>(),
+            Some(&StorageError::Ambiguous {
+                hash: hash.clone(),
+                exts: vec!["jpeg".to_string(), "jpg".to_string()],
+            })
+        );
+        storage.delete(&hash, "jpeg").unwrap();
+        assert_eq!(storage.load_media_any(&hash).unwrap(), jpeg);
+
+        // Encoded files count under their original extension.
+        let text = b"compress me, compress me";
+        let text_hash = sha256_hex(text);
+        storage
+            .clone()
+            .with_compression(CompressionPolicy::Always)
+            .store_media_auto(&text_hash, text)
+            .unwrap();
+        assert_eq!(storage.load_media_any(&text_hash).unwrap(), text);
+
+        let err = storage
+            .load_media_any(&sha256_hex(b"never stored"))
+            .unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<StorageError>(),
+            Some(StorageError::NotFound { .. })
+        ));
+    }
 }
@@ -0,0 +1,123 @@
+//! Which extension a stored file gets, so every caller names the same content
+//! the same way.
+
+/// Known MIME types with their extensions; the first one is canonical.
+const TYPES: &[(&str, &[&str])] = &[
+    ("image/png", &["png"]),
+    ("image/jpeg", &["jpg", "jpeg", "jpe"]),
+    ("image/gif", &["gif"]),
+    ("image/webp", &["webp"]),
+    ("image/bmp", &["bmp"]),
+    ("image/tiff", &["tiff", "tif"]),
+    ("audio/wav", &["wav", "wave"]),
+    ("video/mp4", &["mp4", "m4v"]),
+    ("video/webm", &["webm"]),
+    ("text/plain", &["txt", "text"]),
+    ("application/octet-stream", &["bin"]),
+];
+
+/// Other names seen for the types above.
+const ALIASES: &[(&str, &str)] = &[
+    ("image/jpg", "image/jpeg"),
+    ("image/x-ms-bmp", "image/bmp"),
+    ("audio/x-wav", "audio/wav"),
+    ("audio/wave", "audio/wav"),
+    ("audio/vnd.wave", "audio/wav"),
+];
+
+/// The MIME type for `ext` (any case, without the dot), if it is known.
+pub fn mime_for_ext(ext: &str) -> Option<&'static str> {
+    TYPES
+        .iter()
+        .find(|(_, exts)| exts.iter().any(|e| e.eq_ignore_ascii_case(ext)))
+        .map(|(mime, _)| *mime)
+}
+
+/// The canonical extension for `mime`, ignoring parameters such as a charset.
+pub fn ext_for_mime(mime: &str) -> Option<&'static str> {
+    let essence = mime.split(';').next().unwrap_or_default().trim();
+    let essence = ALIASES
+        .iter()
+        .find(|(alias, _)| alias.eq_ignore_ascii_case(essence))
+        .map_or(essence, |(_, mime)| mime);
+    TYPES
+        .iter()
+        .find(|(known, _)| known.eq_ignore_ascii_case(essence))
+        .map(|(_, exts)| exts[0])
+}
+
+/// `ext` lowercased, or the canonical extension of its type: `JPEG` becomes
+/// `jpg`. Unknown extensions are only lowercased.
+pub fn canonicalize_ext(ext: &str) -> String {
+    match mime_for_ext(ext).and_then(ext_for_mime) {
+        Some(canonical) => canonical.to_string(),
+        None => ext.to_ascii_lowercase(),
+    }
+}
+
+/// The MIME type of `bytes` from their leading magic bytes: text when they are
+/// UTF-8 without NULs, `application/octet-stream` when nothing matches.
+pub fn sniff_mime(bytes: &[u8]) -> &'static str {
+    let riff =
+        |kind: &[u8]| bytes.len() >= 12 && bytes.starts_with(b"RIFF") && &bytes[8..12] == kind;
+    if bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+        "image/png"
+    } else if bytes.starts_with(&[0xFF, 0xD8, 0xFF]) {
+        "image/jpeg"
+    } else if bytes.starts_with(b"GIF87a") || bytes.starts_with(b"GIF89a") {
+        "image/gif"
+    } else if riff(b"WEBP") {
+        "image/webp"
+    } else if riff(b"WAVE") {
+        "audio/wav"
+    } else if bytes.len() >= 14 && bytes.starts_with(b"BM") && bytes[6..10] == [0; 4] {
+        "image/bmp"
+    } else if bytes.starts_with(b"II*\0") || bytes.starts_with(b"MM\0*") {
+        "image/tiff"
+    } else if bytes.len() >= 8 && &bytes[4..8] == b"ftyp" {
+        "video/mp4"
+    } else if bytes.starts_with(&[0x1A, 0x45, 0xDF, 0xA3]) {
+        "video/webm"
+    } else if !bytes.contains(&0) && std::str::from_utf8(bytes).is_ok() {
+        "text/plain"
+    } else {
+        "application/octet-stream"
+    }
+}
+
+/// The canonical extension for the sniffed type of `bytes`.
+pub fn sniff_ext(bytes: &[u8]) -> &'static str {
+    ext_for_mime(sniff_mime(bytes)).unwrap_or("bin")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn extensions_normalize_to_one_name_per_type() {
+        for ext in ["jpg", "jpeg", "JPEG", "Jpe"] {
+            assert_eq!(canonicalize_ext(ext), "jpg");
+            assert_eq!(mime_for_ext(ext), Some("image/jpeg"));
+        }
+        assert_eq!(canonicalize_ext("TIF"), "tiff");
+        assert_eq!(canonicalize_ext("XYZ"), "xyz");
+        assert_eq!(mime_for_ext("xyz"), None);
+        assert_eq!(ext_for_mime("image/jpg"), Some("jpg"));
+        assert_eq!(ext_for_mime("text/plain; charset=utf-8"), Some("txt"));
+        assert_eq!(ext_for_mime("audio/x-wav"), Some("wav"));
+        assert_eq!(ext_for_mime("application/x-unknown"), None);
+    }
+
+    #[test]
+    fn content_is_sniffed_by_magic_bytes() {
+        assert_eq!(sniff_ext(b"\xff\xd8\xff\xe0 rest of a jpeg"), "jpg");
+        assert_eq!(sniff_ext(b"\x89PNG\r\n\x1a\n...."), "png");
+        assert_eq!(sniff_ext(b"RIFF\0\0\0\0WAVEfmt "), "wav");
+        assert_eq!(sniff_ext(b"\0\0\0\x18ftypisom"), "mp4");
+        assert_eq!(sniff_ext("plain words, ünïcode".as_bytes()), "txt");
+        assert_eq!(sniff_ext(b"BMW makes cars"), "txt");
+        assert_eq!(sniff_ext(b"\0\x01\x02"), "bin");
+        assert_eq!(sniff_ext(b""), "txt");
+    }
+}
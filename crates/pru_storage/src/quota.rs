@@ -0,0 +1,255 @@
+//! Disk quota for stored media, tracked in a sidecar index with least recently
+//! used eviction.
+
+use anyhow::{Context, Result};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::Path;
+use std::time::SystemTime;
+
+/// Name of the index file under the storage root. Hidden, like files still
+/// being written, so it is never mistaken for stored media.
+const INDEX_FILE: &str = ".index";
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Quota {
+    /// Most bytes stored files may take on disk together.
+    pub max_bytes: u64,
+    pub eviction: Eviction,
+}
+
+/// What to do when a new file would not fit in the [`Quota`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Eviction {
+    /// Refuse the new file with [`QuotaExceeded`].
+    #[default]
+    Refuse,
+    /// Delete the least recently stored or loaded files that are not pinned
+    /// until the new one fits.
+    LeastRecentlyUsed,
+}
+
+/// A file refused because it does not fit in the [`Quota`], even after evicting
+/// everything that may be evicted. Callers can `downcast_ref` the
+/// `anyhow::Error` from the store methods to tell it apart from I/O failures.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct QuotaExceeded {
+    pub needed: u64,
+    pub used: u64,
+    pub max_bytes: u64,
+}
+
+impl std::fmt::Display for QuotaExceeded {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "storing {} bytes would exceed the media quota ({} of {} bytes used)",
+            self.needed, self.used, self.max_bytes
+        )
+    }
+}
+
+impl std::error::Error for QuotaExceeded {}
+
+/// Disk use of a [`MediaStorage`](crate::MediaStorage), as reported by
+/// [`usage`](crate::MediaStorage::usage).
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize)]
+pub struct StorageUsage {
+    pub used_bytes: u64,
+    pub max_bytes: Option<u64>,
+    pub files: usize,
+    pub pinned: usize,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Entry {
+    size: u64,
+    /// Position in access order rather than a time, so ties cannot happen.
+    last_access: u64,
+}
+
+/// Stored files with their on-disk size and last access, plus pinned hashes.
+/// Loaded on first use and reconciled with the files actually present, so an
+/// index missing, stale or written before a quota was set is repaired. It is
+/// read again whenever the sidecar changed since this process last read or
+/// wrote it, so storages opened on the same root in other processes see each
+/// other's files and pins before the quota is checked. Saves are not locked
+/// across processes: two writers saving at the same moment can still lose one
+/// update, which the next reconcile with the files on disk repairs.
+#[derive(Debug, Default)]
+pub(crate) struct Index {
+    loaded: bool,
+    /// Modification time and length of the sidecar as last read or written.
+    stamp: Option<(SystemTime, u64)>,
+    files: BTreeMap<String, Entry>,
+    pinned: BTreeSet<String>,
+    clock: u64,
+}
+
+fn stamp(path: &Path) -> Option<(SystemTime, u64)> {
+    let meta = fs::metadata(path).ok()?;
+    Some((meta.modified().ok()?, meta.len()))
+}
+
+fn hash_of(name: &str) -> &str {
+    name.split_once('.').map_or(name, |(hash, _)| hash)
+}
+
+impl Index {
+    /// Read the index under `root` unless it is loaded and the sidecar has not
+    /// changed since. `on_disk` lists the stored files and their sizes.
+    pub(crate) fn load(
+        &mut self,
+        root: &Path,
+        on_disk: impl FnOnce() -> Result<Vec<(String, u64)>>,
+    ) -> Result<()> {
+        let path = root.join(INDEX_FILE);
+        let current = stamp(&path);
+        if self.loaded && self.stamp == current {
+            return Ok(());
+        }
+        self.files.clear();
+        self.pinned.clear();
+        let text = match fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(e).with_context(|| format!("reading {}", path.display())),
+        };
+        let mut recorded = BTreeMap::new();
+        for line in text.lines() {
+            let fields: Vec<&str> = line.split('\t').collect();
+            match fields[..] {
+                ["file", name, last_access] => {
+                    let last_access = last_access.parse().unwrap_or(0);
+                    recorded.insert(name.to_string(), last_access);
+                }
+                ["pin", hash] => {
+                    self.pinned.insert(hash.to_string());
+                }
+                _ => {}
+            }
+        }
+        for (name, size) in on_disk()? {
+            let last_access = recorded.get(&name).copied().unwrap_or(0);
+            self.clock = self.clock.max(last_access);
+            self.files.insert(name, Entry { size, last_access });
+        }
+        self.loaded = true;
+        self.stamp = current;
+        Ok(())
+    }
+
+    /// Write the index aside and rename it into place.
+    pub(crate) fn save(&mut self, root: &Path) -> Result<()> {
+        let mut text = String::new();
+        for (name, entry) in &self.files {
+            text.push_str(&format!("file\t{name}\t{}\n", entry.last_access));
+        }
+        for hash in &self.pinned {
+            text.push_str(&format!("pin\t{hash}\n"));
+        }
+        fs::create_dir_all(root)?;
+        let tmp = root.join(format!("{INDEX_FILE}.tmp"));
+        fs::write(&tmp, text)?;
+        let path = root.join(INDEX_FILE);
+        fs::rename(&tmp, &path)?;
+        self.stamp = stamp(&path);
+        Ok(())
+    }
+
+    pub(crate) fn pinned(&self) -> usize {
+        self.pinned.len()
+    }
+
+    pub(crate) fn used(&self) -> u64 {
+        self.files.values().map(|e| e.size).sum()
+    }
+
+    pub(crate) fn usage(&self, max_bytes: Option<u64>) -> StorageUsage {
+        StorageUsage {
+            used_bytes: self.used(),
+            max_bytes,
+            files: self.files.len(),
+            pinned: self.pinned.len(),
+        }
+    }
+
+    pub(crate) fn touch(&mut self, name: &str) {
+        self.clock += 1;
+        if let Some(entry) = self.files.get_mut(name) {
+            entry.last_access = self.clock;
+        }
+    }
+
+    pub(crate) fn record(&mut self, name: &str, size: u64) {
+        self.clock += 1;
+        let entry = Entry {
+            size,
+            last_access: self.clock,
+        };
+        self.files.insert(name.to_string(), entry);
+    }
+
+    pub(crate) fn forget(&mut self, name: &str) {
+        self.files.remove(name);
+    }
+
+    pub(crate) fn pin(&mut self, hash: &str) -> bool {
+        self.pinned.insert(hash.to_string())
+    }
+
+    pub(crate) fn unpin(&mut self, hash: &str) -> bool {
+        self.pinned.remove(hash)
+    }
+
+    /// Files to delete so that `size` more bytes for `hash` fit under `quota`,
+    /// with `replacing` (files about to be overwritten) already counted as
+    /// freed. Other files of `hash` and pinned files are never chosen.
+    pub(crate) fn make_room(
+        &self,
+        quota: &Quota,
+        hash: &str,
+        size: u64,
+        replacing: &[&str],
+    ) -> Result<Vec<String>, QuotaExceeded> {
+        let used = self.used();
+        let freed: u64 = replacing
+            .iter()
+            .filter_map(|name| self.files.get(*name))
+            .map(|e| e.size)
+            .sum();
+        let mut after = used - freed + size;
+        if after <= quota.max_bytes {
+            return Ok(Vec::new());
+        }
+        let exceeded = QuotaExceeded {
+            needed: size,
+            used,
+            max_bytes: quota.max_bytes,
+        };
+        if quota.eviction == Eviction::Refuse {
+            return Err(exceeded);
+        }
+        let mut candidates: Vec<(&String, &Entry)> = self
+            .files
+            .iter()
+            .filter(|(name, _)| {
+                let other = hash_of(name);
+                other != hash && !self.pinned.contains(other)
+            })
+            .collect();
+        candidates.sort_by_key(|(_, e)| e.last_access);
+        let mut evict = Vec::new();
+        for (name, entry) in candidates {
+            if after <= quota.max_bytes {
+                break;
+            }
+            after -= entry.size;
+            evict.push(name.clone());
+        }
+        if after > quota.max_bytes {
+            return Err(exceeded);
+        }
+        Ok(evict)
+    }
+}
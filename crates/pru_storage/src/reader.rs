@@ -0,0 +1,168 @@
+//! Reading stored files back whatever encoding they were written with.
+
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::sync::Arc;
+
+use crate::{compression, encryption};
+
+/// How a stored file is kept on disk, told by the suffixes after its extension:
+/// `.zst` when compressed, then `.enc` when encrypted.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub(crate) struct Encoding {
+    pub(crate) compressed: bool,
+    pub(crate) encrypted: bool,
+}
+
+impl Encoding {
+    /// Every encoding, in the order a lookup tries them.
+    pub(crate) const ALL: [Encoding; 4] = [
+        Encoding {
+            compressed: false,
+            encrypted: false,
+        },
+        Encoding {
+            compressed: true,
+            encrypted: false,
+        },
+        Encoding {
+            compressed: false,
+            encrypted: true,
+        },
+        Encoding {
+            compressed: true,
+            encrypted: true,
+        },
+    ];
+
+    pub(crate) fn is_plain(self) -> bool {
+        !self.compressed && !self.encrypted
+    }
+
+    pub(crate) fn suffix(self) -> String {
+        let mut suffix = String::new();
+        if self.compressed {
+            suffix = format!("{suffix}.{}", compression::SUFFIX);
+        }
+        if self.encrypted {
+            suffix = format!("{suffix}.{}", encryption::SUFFIX);
+        }
+        suffix
+    }
+
+    /// Split the suffixes off a stored file name.
+    pub(crate) fn of(name: &str) -> (&str, Encoding) {
+        let mut encoding = Encoding::default();
+        let mut rest = name;
+        if let Some(stripped) = rest.strip_suffix(&format!(".{}", encryption::SUFFIX)) {
+            encoding.encrypted = true;
+            rest = stripped;
+        }
+        if let Some(stripped) = rest.strip_suffix(&format!(".{}", compression::SUFFIX)) {
+            encoding.compressed = true;
+            rest = stripped;
+        }
+        (rest, encoding)
+    }
+}
+
+/// Opens a decoding reader at the start of a stored file.
+pub(crate) type Opener = Arc<dyn Fn() -> anyhow::Result<Box<dyn Read + Send>> + Send + Sync>;
+
+/// A stored file opened for reading. Compressed and encrypted files are decoded
+/// as they are read; seeking backwards in one restarts decoding from the start,
+/// and seeking from the end decodes it once to learn its length.
+pub struct StoredReader(Inner);
+
+enum Inner {
+    Plain(File),
+    Decoded(Decoded),
+}
+
+struct Decoded {
+    open: Opener,
+    encoding: Encoding,
+    reader: Box<dyn Read + Send>,
+    pos: u64,
+    len: Option<u64>,
+}
+
+impl StoredReader {
+    pub(crate) fn plain(file: File) -> Self {
+        Self(Inner::Plain(file))
+    }
+
+    pub(crate) fn decoded(open: Opener, encoding: Encoding) -> anyhow::Result<Self> {
+        Ok(Self(Inner::Decoded(Decoded {
+            reader: open()?,
+            open,
+            encoding,
+            pos: 0,
+            len: None,
+        })))
+    }
+
+    pub fn is_compressed(&self) -> bool {
+        matches!(&self.0, Inner::Decoded(d) if d.encoding.compressed)
+    }
+
+    pub fn is_encrypted(&self) -> bool {
+        matches!(&self.0, Inner::Decoded(d) if d.encoding.encrypted)
+    }
+}
+
+impl Read for StoredReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match &mut self.0 {
+            Inner::Plain(file) => file.read(buf),
+            Inner::Decoded(d) => {
+                let n = d.reader.read(buf)?;
+                d.pos += n as u64;
+                Ok(n)
+            }
+        }
+    }
+}
+
+impl Seek for StoredReader {
+    fn seek(&mut self, to: SeekFrom) -> io::Result<u64> {
+        let d = match &mut self.0 {
+            Inner::Plain(file) => return file.seek(to),
+            Inner::Decoded(d) => d,
+        };
+        let target = match to {
+            SeekFrom::Start(n) => Some(n),
+            SeekFrom::Current(delta) => d.pos.checked_add_signed(delta),
+            SeekFrom::End(delta) => d.len()?.checked_add_signed(delta),
+        }
+        .ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "seek before the start of the file",
+            )
+        })?;
+        if target < d.pos {
+            d.reader = d.reopen()?;
+            d.pos = 0;
+        }
+        io::copy(&mut (&mut d.reader).take(target - d.pos), &mut io::sink())?;
+        // Past the end later reads return nothing, as they would for a plain file.
+        d.pos = target;
+        Ok(target)
+    }
+}
+
+impl Decoded {
+    fn reopen(&self) -> io::Result<Box<dyn Read + Send>> {
+        (self.open)().map_err(io::Error::other)
+    }
+
+    fn len(&mut self) -> io::Result<u64> {
+        if let Some(len) = self.len {
+            return Ok(len);
+        }
+        let len = io::copy(&mut self.reopen()?, &mut io::sink())?;
+        self.len = Some(len);
+        Ok(len)
+    }
+}
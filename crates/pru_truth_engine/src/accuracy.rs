@@ -0,0 +1,227 @@
+//! How well the combined engine agrees with the human verdicts already stored.
+
+use anyhow::Result;
+use pru_core::PruDbHandle;
+use pru_media_schema::{
+    get_detector_name, get_detector_scores_for_media, get_media_type, get_verdict_summary,
+    media_with_tag, media_with_verdicts, MediaType,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::{EvalOverrides, TruthEngine, Verdict};
+
+/// Probabilities are kept this far from 0 and 1 so one confident miss cannot make
+/// the log loss infinite.
+const LOG_LOSS_EPSILON: f64 = 1e-6;
+
+/// Restricts an accuracy report to part of the store.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MediaFilter {
+    Tag(String),
+    MediaType(MediaType),
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DetectorAgreement {
+    pub detector: String,
+    /// Verdict-labelled media the detector gave an ai or human label.
+    pub scored: usize,
+    /// How many of those labels matched the verdict.
+    pub agreed: usize,
+    pub agreement_rate: Option<f64>,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AccuracyReport {
+    /// Media with a majority ai or human verdict that were scored; tied verdicts and
+    /// other labels are skipped.
+    pub media: usize,
+    /// Share of media where `probability_ai >= 0.5` matches the verdict.
+    pub accuracy: f64,
+    /// Mean squared error of `probability_ai` against the verdict (0 is perfect).
+    pub brier_score: f64,
+    pub log_loss: f64,
+    /// Media whose report verdict was inconclusive.
+    pub inconclusive: usize,
+    /// Predicted label -> verdict label -> count.
+    pub confusion: BTreeMap<String, BTreeMap<String, u64>>,
+    /// One entry per detector, by name.
+    pub detectors: Vec<DetectorAgreement>,
+}
+
+impl TruthEngine {
+    /// Re-evaluate every media with human verdicts as if the verdicts were absent and
+    /// compare the result with them.
+    pub fn accuracy_report(
+        &self,
+        pru: &PruDbHandle,
+        filter: Option<&MediaFilter>,
+    ) -> Result<AccuracyReport> {
+        let tagged: Option<BTreeSet<_>> = match filter {
+            Some(MediaFilter::Tag(tag)) => Some(
+                media_with_tag(pru, tag, 0, usize::MAX)?
+                    .into_iter()
+                    .map(|m| m.0)
+                    .collect(),
+            ),
+            _ => None,
+        };
+        let overrides = EvalOverrides {
+            ignore_verdicts: true,
+            ..Default::default()
+        };
+
+        let mut report = AccuracyReport::default();
+        let (mut correct, mut brier, mut log_loss) = (0usize, 0.0_f64, 0.0_f64);
+        let mut agreement: BTreeMap<String, DetectorAgreement> = BTreeMap::new();
+        for media in media_with_verdicts(pru)? {
+            if tagged.as_ref().is_some_and(|set| !set.contains(&media.0)) {
+                continue;
+            }
+            if let Some(MediaFilter::MediaType(wanted)) = filter {
+                if get_media_type(pru, media)? != Some(*wanted) {
+                    continue;
+                }
+            }
+            let Some(truth) = get_verdict_summary(pru, media)?.majority_label else {
+                continue;
+            };
+            // Labels such as "unsure" say nothing about the outcome.
+            let truth = match truth.to_ascii_lowercase().as_str() {
+                "ai" => "ai",
+                "human" => "human",
+                _ => continue,
+            };
+            let scores = get_detector_scores_for_media(pru, media)?;
+            if scores.is_empty() {
+                continue;
+            }
+
+            let evaluated = self.evaluate_media_with(pru, media, &overrides)?;
+            let p = evaluated.probability_ai as f64;
+            let actual = if truth == "ai" { 1.0 } else { 0.0 };
+            let predicted = if p >= 0.5 { "ai" } else { "human" };
+            report.media += 1;
+            if predicted == truth {
+                correct += 1;
+            }
+            if evaluated.verdict == Verdict::Inconclusive {
+                report.inconclusive += 1;
+            }
+            brier += (p - actual).powi(2);
+            let clamped = p.clamp(LOG_LOSS_EPSILON, 1.0 - LOG_LOSS_EPSILON);
+            log_loss -= actual * clamped.ln() + (1.0 - actual) * (1.0 - clamped).ln();
+            *report
+                .confusion
+                .entry(predicted.to_string())
+                .or_default()
+                .entry(truth.to_string())
+                .or_insert(0) += 1;
+
+            for (detector, _score, label) in scores {
+                let label = label.to_ascii_lowercase();
+                if label != "ai" && label != "human" {
+                    continue;
+                }
+                let name =
+                    get_detector_name(pru, detector)?.unwrap_or_else(|| detector.0.to_string());
+                let entry = agreement.entry(name.clone()).or_insert(DetectorAgreement {
+                    detector: name,
+                    ..Default::default()
+                });
+                entry.scored += 1;
+                if label == truth {
+                    entry.agreed += 1;
+                }
+            }
+        }
+
+        if report.media > 0 {
+            let n = report.media as f64;
+            report.accuracy = correct as f64 / n;
+            report.brier_score = brier / n;
+            report.log_loss = log_loss / n;
+        }
+        report.detectors = agreement
+            .into_values()
+            .map(|mut a| {
+                a.agreement_rate = (a.scored > 0).then(|| a.agreed as f64 / a.scored as f64);
+                a
+            })
+            .collect();
+        Ok(report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::TruthEngineConfig;
+    use pru_core::PruStore;
+    use pru_media_schema::{
+        add_detector_score, add_human_verdict, add_tag, ensure_detector_entity, upsert_media_entity,
+    };
+    use std::sync::{Arc, Mutex};
+    use tempfile::tempdir;
+
+    #[test]
+    fn metrics_match_hand_computed_values() {
+        let dir = tempdir().unwrap();
+        let handle = Arc::new(Mutex::new(PruStore::open(dir.path()).unwrap()));
+        let detector = ensure_detector_entity(&handle, "detector:image:a").unwrap();
+        // (score, detector label, verdict, media type)
+        let cases = [
+            (0.9, "ai", "ai", MediaType::Image),
+            (0.8, "ai", "human", MediaType::Image),
+            (0.2, "human", "human", MediaType::Image),
+            (0.4, "unknown", "ai", MediaType::Text),
+        ];
+        for (i, (score, label, verdict, media_type)) in cases.iter().enumerate() {
+            let media = upsert_media_entity(&handle, &format!("m{i}"), *media_type).unwrap();
+            add_detector_score(&handle, media, detector, *score, label).unwrap();
+            add_human_verdict(&handle, media, verdict).unwrap();
+            if i < 2 {
+                add_tag(&handle, media, "batch").unwrap();
+            }
+        }
+        // Labelled but never scored: left out.
+        let unscored = upsert_media_entity(&handle, "unscored", MediaType::Image).unwrap();
+        add_human_verdict(&handle, unscored, "ai").unwrap();
+        // Scored, but the verdict is neither ai nor human: unlabelled.
+        let unsure = upsert_media_entity(&handle, "unsure", MediaType::Image).unwrap();
+        add_detector_score(&handle, unsure, detector, 0.9, "ai").unwrap();
+        add_human_verdict(&handle, unsure, "unsure").unwrap();
+
+        let engine = TruthEngine::new(TruthEngineConfig::default());
+        let report = engine.accuracy_report(&handle, None).unwrap();
+        assert_eq!(report.media, 4);
+        assert_eq!(report.accuracy, 0.5);
+        let brier = (0.01 + 0.64 + 0.04 + 0.36) / 4.0;
+        assert!((report.brier_score - brier).abs() < 1e-6);
+        let log_loss = -(0.9_f64.ln() + 0.2_f64.ln() + 0.8_f64.ln() + 0.4_f64.ln()) / 4.0;
+        assert!((report.log_loss - log_loss).abs() < 1e-6);
+        assert_eq!(report.confusion["ai"]["human"], 1);
+        assert_eq!(report.confusion["human"]["ai"], 1);
+        assert_eq!(report.inconclusive, 1);
+        assert_eq!(
+            report.detectors,
+            vec![DetectorAgreement {
+                detector: "detector:image:a".into(),
+                scored: 3,
+                agreed: 2,
+                agreement_rate: Some(2.0 / 3.0),
+            }]
+        );
+
+        let tagged = engine
+            .accuracy_report(&handle, Some(&MediaFilter::Tag("batch".into())))
+            .unwrap();
+        assert_eq!((tagged.media, tagged.accuracy), (2, 0.5));
+        let text = engine
+            .accuracy_report(&handle, Some(&MediaFilter::MediaType(MediaType::Text)))
+            .unwrap();
+        assert_eq!((text.media, text.accuracy), (1, 0.0));
+    }
+}
@@ -0,0 +1,192 @@
+//! Platt scaling: mapping a detector's raw scores to probabilities using the human
+//! verdicts recorded for media it scored.
+
+use anyhow::Result;
+use pru_core::PruDbHandle;
+use pru_media_schema::{
+    get_detector_scores_for_media, get_verdict_summary, media_scored_by, scoring_detectors,
+    set_detector_calibration, DetectorId, ScoreCalibration,
+};
+
+/// Fewer labelled scores than this and no calibration is fitted.
+pub const MIN_CALIBRATION_SAMPLES: usize = 20;
+
+const MAX_ITERATIONS: usize = 100;
+
+/// Fit `sigmoid(a * score + b)` to (score, is_ai) pairs by Newton's method.
+///
+/// Uses Platt's smoothed targets so perfectly separated data still converges.
+/// `None` with fewer than [`MIN_CALIBRATION_SAMPLES`] samples, without both
+/// classes, or when every score is the same.
+pub fn fit_platt(samples: &[(f64, bool)]) -> Option<ScoreCalibration> {
+    let positives = samples.iter().filter(|(_, ai)| *ai).count();
+    let negatives = samples.len() - positives;
+    if samples.len() < MIN_CALIBRATION_SAMPLES || positives == 0 || negatives == 0 {
+        return None;
+    }
+    let high = (positives as f64 + 1.0) / (positives as f64 + 2.0);
+    let low = 1.0 / (negatives as f64 + 2.0);
+
+    let (mut a, mut b) = (0.0_f64, 0.0_f64);
+    for _ in 0..MAX_ITERATIONS {
+        let (mut ga, mut gb) = (0.0, 0.0);
+        let (mut haa, mut hab, mut hbb) = (1e-12, 0.0, 1e-12);
+        for &(score, ai) in samples {
+            let target = if ai { high } else { low };
+            let p = 1.0 / (1.0 + (-(a * score + b)).exp());
+            let residual = p - target;
+            ga += residual * score;
+            gb += residual;
+            let w = p * (1.0 - p);
+            haa += w * score * score;
+            hab += w * score;
+            hbb += w;
+        }
+        let det = haa * hbb - hab * hab;
+        if det.abs() < 1e-12 {
+            return None;
+        }
+        let step_a = (hbb * ga - hab * gb) / det;
+        let step_b = (haa * gb - hab * ga) / det;
+        a -= step_a;
+        b -= step_b;
+        if step_a.abs() < 1e-10 && step_b.abs() < 1e-10 {
+            break;
+        }
+    }
+    (a.is_finite() && b.is_finite()).then_some(ScoreCalibration {
+        a,
+        b,
+        samples: samples.len(),
+    })
+}
+
+/// Fit `detector`'s latest score on every media with a majority verdict and store
+/// the result. Returns `None`, storing nothing, when there is too little history.
+pub fn fit_calibration(
+    handle: &PruDbHandle,
+    detector: DetectorId,
+) -> Result<Option<ScoreCalibration>> {
+    let samples = calibration_samples(handle, detector)?;
+    let Some(calibration) = fit_platt(&samples) else {
+        return Ok(None);
+    };
+    set_detector_calibration(handle, detector, &calibration)?;
+    Ok(Some(calibration))
+}
+
+/// [`fit_calibration`] for every detector that has stored scores.
+pub fn recalibrate_all(
+    handle: &PruDbHandle,
+) -> Result<Vec<(DetectorId, Option<ScoreCalibration>)>> {
+    scoring_detectors(handle)?
+        .into_iter()
+        .map(|detector| Ok((detector, fit_calibration(handle, detector)?)))
+        .collect()
+}
+
+fn calibration_samples(handle: &PruDbHandle, detector: DetectorId) -> Result<Vec<(f64, bool)>> {
+    let mut samples = Vec::new();
+    for media in media_scored_by(handle, detector)? {
+        let Some(label) = get_verdict_summary(handle, media)?.majority_label else {
+            continue;
+        };
+        // Labels such as "unsure" are neither a positive nor a negative.
+        let is_ai = match label.as_str() {
+            "ai" => true,
+            "human" => false,
+            _ => continue,
+        };
+        let score = get_detector_scores_for_media(handle, media)?
+            .into_iter()
+            .find(|(d, _, _)| *d == detector)
+            .map(|(_, score, _)| score);
+        if let Some(score) = score {
+            samples.push((score, is_ai));
+        }
+    }
+    Ok(samples)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{TruthEngine, TruthEngineConfig};
+    use pru_core::PruStore;
+    use pru_media_schema::{
+        add_detector_score, add_human_verdict, ensure_detector_entity, get_detector_calibration,
+        upsert_media_entity, MediaType,
+    };
+    use std::sync::{Arc, Mutex};
+    use tempfile::tempdir;
+
+    #[test]
+    fn known_parameters_are_recovered() {
+        let truth = ScoreCalibration {
+            a: 6.0,
+            b: -4.0,
+            samples: 0,
+        };
+        let mut samples = Vec::new();
+        for step in 0..=100 {
+            let score = step as f64 / 100.0;
+            let positives = (truth.apply(score) * 400.0).round() as usize;
+            samples.extend((0..400).map(|i| (score, i < positives)));
+        }
+        let fitted = fit_platt(&samples).unwrap();
+        assert!((fitted.a - truth.a).abs() < 0.1, "{fitted:?}");
+        assert!((fitted.b - truth.b).abs() < 0.1, "{fitted:?}");
+        assert_eq!(fitted.samples, samples.len());
+
+        assert_eq!(fit_platt(&samples[..MIN_CALIBRATION_SAMPLES - 1]), None);
+        let one_class: Vec<_> = samples.iter().map(|(s, _)| (*s, true)).collect();
+        assert_eq!(fit_platt(&one_class), None);
+    }
+
+    #[test]
+    fn stored_calibration_rescales_engine_scores() {
+        let dir = tempdir().unwrap();
+        let handle = Arc::new(Mutex::new(PruStore::open(dir.path()).unwrap()));
+        let detector = ensure_detector_entity(&handle, "detector:text:overconfident").unwrap();
+        // Says 0.7 for everything, but only half of those media are AI.
+        for i in 0..30 {
+            let media = upsert_media_entity(&handle, &format!("m{i}"), MediaType::Text).unwrap();
+            let (score, verdict) = match i % 3 {
+                0 => (0.2, "human"),
+                1 => (0.7, "ai"),
+                _ => (0.7, "human"),
+            };
+            add_detector_score(&handle, media, detector, score, "ai").unwrap();
+            add_human_verdict(&handle, media, verdict).unwrap();
+        }
+        // Undecided media are left out of the fit rather than counted as human.
+        for i in 0..5 {
+            let media = upsert_media_entity(&handle, &format!("u{i}"), MediaType::Text).unwrap();
+            add_detector_score(&handle, media, detector, 0.7, "ai").unwrap();
+            add_human_verdict(&handle, media, "unsure").unwrap();
+        }
+        let unlabelled = upsert_media_entity(&handle, "new", MediaType::Text).unwrap();
+        add_detector_score(&handle, unlabelled, detector, 0.7, "ai").unwrap();
+
+        let fitted = recalibrate_all(&handle).unwrap();
+        assert_eq!(fitted.len(), 1);
+        let calibration = fitted[0].1.unwrap();
+        assert_eq!(calibration.samples, 30);
+        assert_eq!(
+            get_detector_calibration(&handle, detector).unwrap(),
+            Some(calibration)
+        );
+
+        let report = TruthEngine::new(TruthEngineConfig::default())
+            .evaluate_media(&handle, unlabelled)
+            .unwrap();
+        let explanation = &report.explanations[0];
+        assert_eq!(explanation.raw_score, Some(0.7));
+        assert!(
+            (explanation.score - 0.5).abs() < 0.05,
+            "{}",
+            explanation.score
+        );
+        assert!(explanation.rendered_text().contains("(raw 0.70)"));
+    }
+}
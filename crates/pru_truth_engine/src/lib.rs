@@ -1,22 +1,307 @@
 use anyhow::Result;
 use pru_core::PruDbHandle;
 use pru_media_schema::{
-    get_detector_reliability, get_detector_scores_for_media, get_human_verdicts,
-    DetectorReliability, MediaId,
+    add_analysis_summary, evidence_stamp, get_detector_calibration, get_detector_details,
+    get_detector_info, get_detector_name, get_detector_reliability, get_detector_scores_for_media,
+    get_effective_reliability, get_features, get_latest_analysis_summary, get_provenance_claims,
+    get_sightings, get_source_id, get_source_reputation, get_verdict_summary, EffectiveReliability,
+    EvidenceStamp, FeatureValue, LabelCounts, MediaId, ProvenanceClaim, VerdictSummary,
 };
 use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+
+mod accuracy;
+mod calibration;
+
+pub use accuracy::{AccuracyReport, DetectorAgreement, MediaFilter};
+pub use calibration::{fit_calibration, fit_platt, recalibrate_all, MIN_CALIBRATION_SAMPLES};
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct DetectionReport {
     pub probability_ai: f32,
     pub probability_human: f32,
-    pub explanations: Vec<String>,
+    /// One entry per detector score behind the probability.
+    pub explanations: Vec<Explanation>,
+    /// Free-form notes, e.g. that human verdicts decided the result.
+    #[serde(default)]
+    pub notes: Vec<String>,
+    /// Provenance claims that shifted the detector probability, in application order.
+    #[serde(default)]
+    pub prior_adjustments: Vec<PriorAdjustment>,
+    /// Detector scores (or human verdicts) the probability was computed from.
+    #[serde(default)]
+    pub evidence_count: usize,
+    /// Sum of the weights behind the probability.
+    #[serde(default)]
+    pub total_weight: f32,
+    /// Credible interval for `probability_ai`; wide when little evidence backs it.
+    #[serde(default = "ConfidenceInterval::uninformed")]
+    pub confidence_interval: ConfidenceInterval,
+    #[serde(default)]
+    pub verdict: Verdict,
+}
+
+impl DetectionReport {
+    /// Notes, prior adjustments and then each explanation, as lines for terminal output.
+    pub fn rendered_explanations(&self) -> Vec<String> {
+        self.notes
+            .iter()
+            .cloned()
+            .chain(
+                self.prior_adjustments
+                    .iter()
+                    .map(PriorAdjustment::rendered_text),
+            )
+            .chain(self.explanations.iter().map(Explanation::rendered_text))
+            .collect()
+    }
+}
+
+/// How one detector score fed into the report.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Explanation {
+    /// Detector entity id in the store.
+    pub detector_id: u64,
+    /// Registered detector name, or the entity id when it was never registered.
+    pub detector_name: String,
+    #[serde(default)]
+    pub detector_version: Option<String>,
+    /// Score as aggregated: calibrated when the detector has a stored calibration.
+    pub score: f64,
+    /// The detector's own score, when calibration changed it.
+    #[serde(default)]
+    pub raw_score: Option<f64>,
+    pub label: String,
+    pub weight: f32,
+    /// Graded history behind `weight`; `None` before any verdicts.
+    pub reliability_summary: Option<EffectiveReliability>,
+    /// Smoothed precision of this detector's `label` calls, when `weight` came from it.
+    #[serde(default)]
+    pub label_precision: Option<f32>,
+    /// This score's share of `probability_ai`: `weight * score / total_weight`.
+    pub contribution: f32,
+    /// The detector's own explanation of its score, if it gave one.
+    #[serde(default)]
+    pub details: Option<String>,
+    /// Latest value per feature name, in name order.
+    #[serde(default)]
+    pub features: BTreeMap<String, FeatureValue>,
+}
+
+impl Explanation {
+    /// `Detector name (version): score_ai=0.80, label=ai, precision=0.91 [feature=value, ...]`.
+    pub fn rendered_text(&self) -> String {
+        let mut line = format!("Detector {}", self.detector_name);
+        if let Some(version) = &self.detector_version {
+            line.push_str(&format!(" ({version})"));
+        }
+        line.push_str(&format!(": score_ai={:.2}", self.score));
+        if let Some(raw) = self.raw_score {
+            line.push_str(&format!(" (raw {raw:.2})"));
+        }
+        line.push_str(&format!(", label={}", self.label));
+        if let Some(precision) = self.label_precision {
+            line.push_str(&format!(", precision={precision:.2}"));
+        }
+        if !self.features.is_empty() {
+            let rendered: Vec<String> = self
+                .features
+                .iter()
+                .map(|(k, v)| format!("{k}={v}"))
+                .collect();
+            line.push_str(&format!(" [{}]", rendered.join(", ")));
+        }
+        if let Some(details) = &self.details {
+            line.push_str(&format!(" - {details}"));
+        }
+        line
+    }
+}
+
+/// A provenance claim's push on `probability_ai`, applied in log-odds.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct PriorAdjustment {
+    /// The claim as read from the store, e.g. "captured by Canon EOS R5".
+    pub claim: String,
+    /// Log-odds added to the detector probability; negative leans human.
+    pub shift: f32,
+}
+
+impl PriorAdjustment {
+    pub fn rendered_text(&self) -> String {
+        let direction = if self.shift < 0.0 { "human" } else { "ai" };
+        format!(
+            "Prior: {} ({:+.2} log-odds toward {direction})",
+            self.claim, self.shift
+        )
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub struct ConfidenceInterval {
+    pub low: f32,
+    pub high: f32,
+}
+
+impl ConfidenceInterval {
+    fn uninformed() -> Self {
+        Self {
+            low: 0.0,
+            high: 1.0,
+        }
+    }
+
+    /// Normal approximation to the Beta(1 + ai, 1 + human) posterior, `z` standard
+    /// deviations either side of its mean.
+    pub fn beta(ai: f64, human: f64, z: f64) -> Self {
+        let (alpha, beta) = (1.0 + ai.max(0.0), 1.0 + human.max(0.0));
+        let n = alpha + beta;
+        let mean = alpha / n;
+        let sd = (alpha * beta / (n * n * (n + 1.0))).sqrt();
+        Self {
+            low: (mean - z * sd).clamp(0.0, 1.0) as f32,
+            high: (mean + z * sd).clamp(0.0, 1.0) as f32,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Verdict {
+    LikelyAi,
+    LikelyHuman,
+    #[default]
+    Inconclusive,
+}
+
+/// A persisted report together with the evidence it was computed from.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct StoredReport {
+    pub report: DetectionReport,
+    pub evidence: EvidenceStamp,
+    /// [`TruthEngineConfig::fingerprint`] of the config the report was computed with.
+    #[serde(default)]
+    pub config: u64,
+    /// When the report was stored (unix seconds).
+    #[serde(skip)]
+    pub stored_at: Option<i64>,
+}
+
+impl StoredReport {
+    /// A report is stale once evidence was added after it, evidence was removed,
+    /// or the engine config changed.
+    pub fn is_stale(&self, current: &EvidenceStamp, config: &TruthEngineConfig) -> bool {
+        current.count != self.evidence.count
+            || current.latest > self.evidence.latest
+            || self.config != config.fingerprint()
+    }
+}
+
+/// Store `report` as the media's analysis summary, replacing the previous one.
+pub fn store_detection_report(
+    pru: &PruDbHandle,
+    media: MediaId,
+    report: &DetectionReport,
+    config: &TruthEngineConfig,
+) -> Result<()> {
+    let stored = StoredReport {
+        report: report.clone(),
+        evidence: evidence_stamp(pru, media)?,
+        config: config.fingerprint(),
+        stored_at: None,
+    };
+    add_analysis_summary(pru, media, &serde_json::to_string(&stored)?)
+}
+
+pub fn get_latest_report(pru: &PruDbHandle, media: MediaId) -> Result<Option<StoredReport>> {
+    let Some((stored_at, payload)) = get_latest_analysis_summary(pru, media)? else {
+        return Ok(None);
+    };
+    // Reports cached before explanations were structured no longer parse; treat
+    // them as missing so the caller recomputes them.
+    let Ok(mut stored) = serde_json::from_str::<StoredReport>(&payload) else {
+        return Ok(None);
+    };
+    stored.stored_at = stored_at;
+    Ok(Some(stored))
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct TruthEngineConfig {
     pub default_detector_weight: f32,
+    /// Fewer detector scores than this always give an inconclusive verdict.
     pub min_detectors_for_confident: usize,
+    /// When set, detector reliability decays with this half-life instead of
+    /// accumulating forever.
+    #[serde(default)]
+    pub reliability_half_life_days: Option<f64>,
+    /// `probability_ai` at or above this is likely AI.
+    #[serde(default = "default_likely_ai")]
+    pub likely_ai_threshold: f32,
+    /// `probability_ai` at or below this is likely human.
+    #[serde(default = "default_likely_human")]
+    pub likely_human_threshold: f32,
+    /// Less total weight than this always gives an inconclusive verdict.
+    #[serde(default)]
+    pub min_total_weight: f32,
+    /// Width of the confidence interval in standard deviations (1.96 ~ 95%).
+    #[serde(default = "default_interval_z")]
+    pub interval_z: f32,
+    /// Human verdicts only replace detector aggregation once this many annotators voted.
+    #[serde(default = "default_min_verdicts")]
+    pub min_verdicts_to_override: usize,
+    /// Log-odds toward human when the media claims capture by a named device.
+    #[serde(default = "default_capture_prior")]
+    pub capture_prior_shift: f32,
+    /// Log-odds toward AI when the media names a generator model, e.g. in an SD
+    /// parameters chunk.
+    #[serde(default = "default_generated_prior")]
+    pub generated_prior_shift: f32,
+    /// Largest log-odds nudge from the reputation of the source media was first seen
+    /// on; 0 disables it.
+    #[serde(default = "default_source_prior")]
+    pub source_prior_shift: f32,
+    /// Sources with fewer labelled media than this give no nudge.
+    #[serde(default = "default_min_source_observations")]
+    pub min_source_observations: u64,
+    /// Cap on the combined provenance shift, so detectors can still outvote it.
+    #[serde(default = "default_max_prior")]
+    pub max_prior_shift: f32,
+}
+
+fn default_capture_prior() -> f32 {
+    0.8
+}
+
+fn default_generated_prior() -> f32 {
+    2.0
+}
+
+fn default_source_prior() -> f32 {
+    0.5
+}
+
+fn default_min_source_observations() -> u64 {
+    10
+}
+
+fn default_max_prior() -> f32 {
+    2.5
+}
+
+fn default_min_verdicts() -> usize {
+    1
+}
+
+fn default_likely_ai() -> f32 {
+    0.7
+}
+
+fn default_likely_human() -> f32 {
+    0.3
+}
+
+fn default_interval_z() -> f32 {
+    1.96
 }
 
 impl Default for TruthEngineConfig {
@@ -24,10 +309,71 @@ impl Default for TruthEngineConfig {
         Self {
             default_detector_weight: 1.0,
             min_detectors_for_confident: 1,
+            reliability_half_life_days: None,
+            likely_ai_threshold: default_likely_ai(),
+            likely_human_threshold: default_likely_human(),
+            min_total_weight: 0.0,
+            interval_z: default_interval_z(),
+            min_verdicts_to_override: default_min_verdicts(),
+            capture_prior_shift: default_capture_prior(),
+            generated_prior_shift: default_generated_prior(),
+            source_prior_shift: default_source_prior(),
+            min_source_observations: default_min_source_observations(),
+            max_prior_shift: default_max_prior(),
         }
     }
 }
 
+impl TruthEngineConfig {
+    /// Hash of every setting, so reports cached under another config can be told apart.
+    pub fn fingerprint(&self) -> u64 {
+        let json = serde_json::to_vec(self).expect("config serializes");
+        xxhash_rust::xxh3::xxh3_64(&json)
+    }
+
+    /// Verdict for a probability backed by `evidence_count` items of `total_weight`.
+    pub fn verdict(
+        &self,
+        probability_ai: f32,
+        evidence_count: usize,
+        total_weight: f32,
+    ) -> Verdict {
+        if evidence_count == 0
+            || evidence_count < self.min_detectors_for_confident
+            || total_weight < self.min_total_weight
+        {
+            Verdict::Inconclusive
+        } else {
+            self.classify(probability_ai)
+        }
+    }
+
+    /// Verdict from the probability thresholds alone.
+    pub fn classify(&self, probability_ai: f32) -> Verdict {
+        if probability_ai >= self.likely_ai_threshold {
+            Verdict::LikelyAi
+        } else if probability_ai <= self.likely_human_threshold {
+            Verdict::LikelyHuman
+        } else {
+            Verdict::Inconclusive
+        }
+    }
+}
+
+/// Hypothetical changes for a what-if evaluation; the default changes nothing.
+#[derive(Debug, Default, Serialize, Deserialize, Clone, PartialEq)]
+pub struct EvalOverrides {
+    /// Detector names (e.g. "detector:image:metadata_v1") whose scores are left out.
+    #[serde(default)]
+    pub exclude_detectors: BTreeSet<String>,
+    /// Weight to use for a detector instead of the one derived from its reliability.
+    #[serde(default)]
+    pub force_weights: BTreeMap<String, f32>,
+    /// Aggregate detectors even when human verdicts would decide the result.
+    #[serde(default)]
+    pub ignore_verdicts: bool,
+}
+
 #[derive(Clone)]
 pub struct TruthEngine {
     pub config: TruthEngineConfig,
@@ -39,67 +385,355 @@ impl TruthEngine {
     }
 
     pub fn evaluate_media(&self, pru: &PruDbHandle, media: MediaId) -> Result<DetectionReport> {
-        let human_verdicts = get_human_verdicts(pru, media)?;
-        if let Some(verdict) = human_verdicts.last() {
-            let prob_ai = if verdict.eq_ignore_ascii_case("ai") {
-                0.99
-            } else {
-                0.01
-            };
-            let prob_human = 1.0 - prob_ai;
-            return Ok(DetectionReport {
-                probability_ai: prob_ai,
-                probability_human: prob_human,
-                explanations: vec![format!("Human verdict present: {verdict}")],
-            });
+        self.evaluate_media_with(pru, media, &EvalOverrides::default())
+    }
+
+    /// [`evaluate_media`](Self::evaluate_media) as if `overrides` applied; nothing is stored.
+    pub fn evaluate_media_with(
+        &self,
+        pru: &PruDbHandle,
+        media: MediaId,
+        overrides: &EvalOverrides,
+    ) -> Result<DetectionReport> {
+        let verdicts = if overrides.ignore_verdicts {
+            VerdictSummary::default()
+        } else {
+            get_verdict_summary(pru, media)?
+        };
+        let votes = verdicts.total();
+        let mut notes = Vec::new();
+        if votes > 0 && votes < self.config.min_verdicts_to_override {
+            notes.push(format!(
+                "{votes} human verdict(s) recorded; {} needed to override detectors",
+                self.config.min_verdicts_to_override
+            ));
+        } else if let Some(label) = verdicts.majority_label.as_deref() {
+            // Only an ai or human call can stand in for the detectors.
+            if matches!(label, "ai" | "human") {
+                return Ok(self.verdict_report(&verdicts, label));
+            }
+            notes.push(format!(
+                "Human verdicts favour \"{label}\", which is neither ai nor human; \
+                 falling back to detectors"
+            ));
+        } else if votes > 0 {
+            let counts: Vec<String> = verdicts
+                .counts
+                .iter()
+                .map(|(label, count)| format!("{label}={count}"))
+                .collect();
+            notes.push(format!(
+                "Human verdicts conflict ({}); falling back to detectors",
+                counts.join(", ")
+            ));
         }
 
-        let detector_scores = get_detector_scores_for_media(pru, media)?;
+        let mut detector_scores = Vec::new();
+        for (detector, raw, label) in get_detector_scores_for_media(pru, media)? {
+            let name = get_detector_name(pru, detector)?.unwrap_or_else(|| detector.0.to_string());
+            if overrides.exclude_detectors.contains(&name) {
+                notes.push(format!("Excluded detector {name}"));
+                continue;
+            }
+            // Scores come back from string literals, so a corrupted one can be anything.
+            if raw.is_nan() {
+                notes.push(format!("Skipped detector {name}: score is not a number"));
+                continue;
+            }
+            if !(0.0..=1.0).contains(&raw) {
+                notes.push(format!(
+                    "Skipped detector {name}: score {raw} is outside [0, 1]"
+                ));
+                continue;
+            }
+            detector_scores.push((detector, name, raw, label));
+        }
         if detector_scores.is_empty() {
+            notes.push("No detector scores found for this media".to_string());
+            let (prior_adjustments, shift) = self.priors(pru, media, &mut notes)?;
+            let probability_ai = shift_log_odds(0.5, shift);
             return Ok(DetectionReport {
-                probability_ai: 0.5,
-                probability_human: 0.5,
-                explanations: vec!["No detector scores found for this media".to_string()],
+                probability_ai,
+                probability_human: 1.0 - probability_ai,
+                explanations: Vec::new(),
+                notes,
+                prior_adjustments,
+                evidence_count: 0,
+                total_weight: 0.0,
+                confidence_interval: ConfidenceInterval::uninformed(),
+                verdict: Verdict::Inconclusive,
             });
         }
 
         let mut weighted_sum = 0.0_f32;
         let mut total_weight = 0.0_f32;
         let mut explanations = Vec::new();
+        let evidence_count = detector_scores.len();
 
-        for (detector, score, label) in detector_scores {
-            let reliability = get_detector_reliability(pru, detector)?;
-            let weight = compute_weight(self.config.default_detector_weight, reliability);
+        for (detector, detector_name, raw, label) in detector_scores {
+            let calibration = get_detector_calibration(pru, detector)?
+                .filter(|c| c.a.is_finite() && c.b.is_finite());
+            let score = calibration.map_or(raw, |c| c.apply(raw));
+            let reliability = match self.config.reliability_half_life_days {
+                Some(half_life) => get_effective_reliability(pru, detector, half_life)?,
+                None => get_detector_reliability(pru, detector)?.map(|r| r.lifetime()),
+            };
+            let label_precision = reliability
+                .as_ref()
+                .and_then(|r| r.label(&label))
+                .map(smoothed_precision);
+            let weight = match overrides.force_weights.get(&detector_name) {
+                Some(forced) => *forced,
+                None => compute_weight(
+                    self.config.default_detector_weight,
+                    reliability.as_ref(),
+                    &label,
+                ),
+            };
+            let weight = if weight.is_finite() {
+                weight.max(0.0)
+            } else {
+                0.0
+            };
             weighted_sum += (score as f32) * weight;
             total_weight += weight;
-            explanations.push(format!(
-                "Detector {}: score_ai={:.2}, label={}",
-                detector.0, score, label
-            ));
+            // Latest value per feature name, in name order.
+            let features: BTreeMap<String, FeatureValue> =
+                get_features(pru, media, Some(detector))?
+                    .into_iter()
+                    .map(|(_, name, value)| (name, value))
+                    .collect();
+            let detector_version = get_detector_info(pru, detector)?
+                .map(|info| info.version)
+                .filter(|version| !version.is_empty());
+            explanations.push(Explanation {
+                detector_id: detector.0,
+                detector_name,
+                detector_version,
+                score,
+                raw_score: calibration.map(|_| raw),
+                label,
+                weight,
+                reliability_summary: reliability,
+                label_precision,
+                contribution: 0.0,
+                details: get_detector_details(pru, media, detector)?,
+                features,
+            });
         }
-
-        if total_weight == 0.0 {
-            total_weight = 1.0;
+        if total_weight > 0.0 {
+            for explanation in &mut explanations {
+                explanation.contribution =
+                    explanation.weight * explanation.score as f32 / total_weight;
+            }
         }
-        let probability_ai = (weighted_sum / total_weight).clamp(0.0, 1.0);
+
+        let detector_probability = if total_weight > 0.0 {
+            (weighted_sum / total_weight).clamp(0.0, 1.0)
+        } else {
+            notes.push("Detector weights sum to zero; probability left at 0.5".to_string());
+            0.5
+        };
+        // Each unit of weight counts as one pseudo-observation split by the score.
+        let detector_interval = ConfidenceInterval::beta(
+            weighted_sum as f64,
+            (total_weight - weighted_sum) as f64,
+            self.config.interval_z as f64,
+        );
+
+        let (prior_adjustments, shift) = self.priors(pru, media, &mut notes)?;
+        let probability_ai = shift_log_odds(detector_probability, shift);
         let probability_human = 1.0 - probability_ai;
+        let confidence_interval = ConfidenceInterval {
+            low: shift_log_odds(detector_interval.low, shift),
+            high: shift_log_odds(detector_interval.high, shift),
+        };
+        let verdict = self
+            .config
+            .verdict(probability_ai, evidence_count, total_weight);
 
         Ok(DetectionReport {
             probability_ai,
             probability_human,
             explanations,
+            notes,
+            prior_adjustments,
+            evidence_count,
+            total_weight,
+            confidence_interval,
+            verdict,
         })
     }
+
+    /// Provenance and source adjustments for `media` and their combined log-odds
+    /// shift, capped at `max_prior_shift`.
+    fn priors(
+        &self,
+        pru: &PruDbHandle,
+        media: MediaId,
+        notes: &mut Vec<String>,
+    ) -> Result<(Vec<PriorAdjustment>, f32)> {
+        let mut adjustments = self.provenance_priors(&get_provenance_claims(pru, media)?);
+        adjustments.extend(self.source_prior(pru, media)?);
+        let mut shift: f32 = adjustments.iter().map(|a| a.shift).sum();
+        if shift.abs() > self.config.max_prior_shift {
+            shift = shift.clamp(-self.config.max_prior_shift, self.config.max_prior_shift);
+            notes.push(format!(
+                "Provenance shift capped at {:.2} log-odds",
+                self.config.max_prior_shift
+            ));
+        }
+        Ok((adjustments, shift))
+    }
+
+    /// Nudge from the track record of the source `media` was first seen on.
+    fn source_prior(&self, pru: &PruDbHandle, media: MediaId) -> Result<Option<PriorAdjustment>> {
+        if self.config.source_prior_shift == 0.0 {
+            return Ok(None);
+        }
+        let Some(first) = get_sightings(pru, media)?.into_iter().next() else {
+            return Ok(None);
+        };
+        let Some(source) = get_source_id(pru, &first.source)? else {
+            return Ok(None);
+        };
+        let Some(reputation) = get_source_reputation(pru, source)? else {
+            return Ok(None);
+        };
+        if reputation.labelled < self.config.min_source_observations {
+            return Ok(None);
+        }
+        let lean = 2.0 * reputation.ai_share() as f32 - 1.0;
+        Ok(Some(PriorAdjustment {
+            claim: format!(
+                "first seen on {} ({} of {} labelled media were ai)",
+                first.source, reputation.ai, reputation.labelled
+            ),
+            shift: self.config.source_prior_shift * lean,
+        }))
+    }
+
+    /// One adjustment per kind of provenance claim the engine has a prior for. A
+    /// generator (a named model or an algorithmic digital source type) wins over a
+    /// capture device, so the two never both apply.
+    fn provenance_priors(&self, claims: &[ProvenanceClaim]) -> Vec<PriorAdjustment> {
+        let mut models: Vec<&str> = claims.iter().filter_map(|c| c.generator()).collect();
+        models.dedup();
+        let devices: Vec<&str> = claims
+            .iter()
+            .filter_map(|claim| match claim {
+                ProvenanceClaim::CapturedByDevice { device } => Some(device.as_str()),
+                _ => None,
+            })
+            .collect();
+        let mut adjustments = Vec::new();
+        if !devices.is_empty() && models.is_empty() && self.config.capture_prior_shift != 0.0 {
+            adjustments.push(PriorAdjustment {
+                claim: format!("captured by {}", devices.join(", ")),
+                shift: -self.config.capture_prior_shift,
+            });
+        }
+        if !models.is_empty() && self.config.generated_prior_shift != 0.0 {
+            adjustments.push(PriorAdjustment {
+                claim: format!("generated by {}", models.join(", ")),
+                shift: self.config.generated_prior_shift,
+            });
+        }
+        adjustments
+    }
+
+    /// Report decided by a strict majority of annotators: near-certain when they
+    /// agree unanimously, otherwise as confident as their agreement ratio.
+    fn verdict_report(&self, verdicts: &VerdictSummary, label: &str) -> DetectionReport {
+        let votes = verdicts.total();
+        let unanimous = verdicts.counts.len() == 1;
+        let label_probability = if unanimous {
+            0.99
+        } else {
+            verdicts.agreement.clamp(0.01, 0.99)
+        };
+        let probability_ai = if label == "ai" {
+            label_probability
+        } else {
+            1.0 - label_probability
+        };
+        let ai_votes = verdicts.counts.get("ai").copied().unwrap_or(0);
+        // Annotators are trusted outright, so detector minimums do not apply.
+        let verdict = if unanimous && probability_ai >= 0.5 {
+            Verdict::LikelyAi
+        } else if unanimous {
+            Verdict::LikelyHuman
+        } else {
+            self.config.classify(probability_ai)
+        };
+        DetectionReport {
+            probability_ai,
+            probability_human: 1.0 - probability_ai,
+            explanations: Vec::new(),
+            notes: vec![format!(
+                "Human verdict present: {label} ({}/{votes} votes, agreement={:.2}{})",
+                verdicts.counts.get(label).copied().unwrap_or(0),
+                verdicts.agreement,
+                if unanimous { "" } else { ", split" }
+            )],
+            prior_adjustments: Vec::new(),
+            evidence_count: votes,
+            total_weight: votes as f32,
+            confidence_interval: ConfidenceInterval::beta(
+                ai_votes as f64,
+                votes.saturating_sub(ai_votes) as f64,
+                self.config.interval_z as f64,
+            ),
+            verdict,
+        }
+    }
+
+    /// Serve the cached report when it is still fresh, otherwise recompute and cache it.
+    ///
+    /// With a reliability half-life the weights drift as time passes, so the report
+    /// is always recomputed and nothing is cached.
+    pub fn cached_report(&self, pru: &PruDbHandle, media: MediaId) -> Result<DetectionReport> {
+        if self.config.reliability_half_life_days.is_some() {
+            return self.evaluate_media(pru, media);
+        }
+        if let Some(stored) = get_latest_report(pru, media)? {
+            if !stored.is_stale(&evidence_stamp(pru, media)?, &self.config) {
+                return Ok(stored.report);
+            }
+        }
+        let report = self.evaluate_media(pru, media)?;
+        store_detection_report(pru, media, &report, &self.config)?;
+        Ok(report)
+    }
+}
+
+/// Weight of a detector's `label` call: its Laplace-smoothed precision on that label,
+/// or its overall accuracy when there is no per-label history for it.
+fn compute_weight(
+    default_weight: f32,
+    reliability: Option<&EffectiveReliability>,
+    label: &str,
+) -> f32 {
+    let Some(r) = reliability else {
+        return default_weight;
+    };
+    match r.label(label) {
+        Some(counts) => default_weight * smoothed_precision(counts),
+        None => default_weight * (r.correct as f32 + 1.0) / (r.seen as f32 + 2.0),
+    }
 }
 
-fn compute_weight(default_weight: f32, reliability: Option<DetectorReliability>) -> f32 {
-    if let Some(r) = reliability {
-        let seen = r.seen as f32;
-        let correct = r.correct as f32;
-        default_weight * (correct + 1.0) / (seen + 2.0)
-    } else {
-        default_weight
+/// `probability` moved by `shift` in log-odds; 0 and 1 stay put.
+fn shift_log_odds(probability: f32, shift: f32) -> f32 {
+    if shift == 0.0 || probability <= 0.0 || probability >= 1.0 {
+        return probability;
     }
+    let logit = (probability / (1.0 - probability)).ln() + shift;
+    1.0 / (1.0 + (-logit).exp())
+}
+
+fn smoothed_precision(counts: LabelCounts) -> f32 {
+    ((counts.confirmed + 1.0) / (counts.predicted + 2.0)) as f32
 }
 
 #[cfg(test)]
@@ -107,8 +741,11 @@ mod tests {
     use super::*;
     use pru_core::PruStore;
     use pru_media_schema::{
-        add_detector_score, add_human_verdict, ensure_detector_entity, upsert_media_entity,
-        MediaType,
+        add_detector_score, add_feature, add_human_verdict, add_human_verdict_by,
+        add_provenance_claim, add_sighting, ensure_detector_entity, register_detector,
+        set_detector_calibration, set_detector_reliability, update_source_reputation,
+        upsert_media_entity, DetectorInfo, DetectorReliability, MediaType, ScoreCalibration,
+        Sighting,
     };
     use std::sync::{Arc, Mutex};
     use tempfile::tempdir;
@@ -137,4 +774,564 @@ mod tests {
         let report = engine.evaluate_media(&handle, media).unwrap();
         assert!(report.probability_ai > 0.7);
     }
+
+    #[test]
+    fn single_detector_is_inconclusive_below_minimum() {
+        let dir = tempdir().unwrap();
+        let handle = Arc::new(Mutex::new(PruStore::open(dir.path()).unwrap()));
+        let media = upsert_media_entity(&handle, "hash", MediaType::Text).unwrap();
+        let first = ensure_detector_entity(&handle, "detector:text:a").unwrap();
+        add_detector_score(&handle, media, first, 0.9, "ai").unwrap();
+
+        let lenient = TruthEngine::new(TruthEngineConfig::default());
+        let report = lenient.evaluate_media(&handle, media).unwrap();
+        assert_eq!(report.evidence_count, 1);
+        assert_eq!(report.verdict, Verdict::LikelyAi);
+        let one_width = report.confidence_interval.high - report.confidence_interval.low;
+
+        let strict = TruthEngine::new(TruthEngineConfig {
+            min_detectors_for_confident: 2,
+            ..Default::default()
+        });
+        let report = strict.evaluate_media(&handle, media).unwrap();
+        assert!(report.probability_ai > 0.8);
+        assert_eq!(report.verdict, Verdict::Inconclusive);
+
+        for name in ["detector:text:b", "detector:text:c", "detector:text:d"] {
+            let detector = ensure_detector_entity(&handle, name).unwrap();
+            add_detector_score(&handle, media, detector, 0.9, "ai").unwrap();
+        }
+        let report = strict.evaluate_media(&handle, media).unwrap();
+        assert_eq!(report.evidence_count, 4);
+        assert!((report.total_weight - 4.0).abs() < 1e-6);
+        assert_eq!(report.verdict, Verdict::LikelyAi);
+        let interval = report.confidence_interval;
+        assert!(interval.low <= report.probability_ai && report.probability_ai <= interval.high);
+        assert!(interval.high - interval.low < one_width);
+    }
+
+    #[test]
+    fn cached_report_goes_stale_on_new_evidence() {
+        let dir = tempdir().unwrap();
+        let store = PruStore::open(dir.path()).unwrap();
+        let handle = Arc::new(Mutex::new(store));
+        let media = upsert_media_entity(&handle, "hash", MediaType::Text).unwrap();
+        let detector = ensure_detector_entity(&handle, "detector:text:a").unwrap();
+        add_detector_score(&handle, media, detector, 0.8, "ai").unwrap();
+        let engine = TruthEngine::new(TruthEngineConfig::default());
+        assert!(get_latest_report(&handle, media).unwrap().is_none());
+
+        let first = engine.cached_report(&handle, media).unwrap();
+        let stored = get_latest_report(&handle, media).unwrap().unwrap();
+        assert!(stored.stored_at.is_some());
+        assert!(!stored.is_stale(&evidence_stamp(&handle, media).unwrap(), &engine.config));
+        let facts = handle.lock().unwrap().fact_count();
+        engine.cached_report(&handle, media).unwrap();
+        assert_eq!(handle.lock().unwrap().fact_count(), facts);
+
+        // A verdict written in the same second is still detected via the count.
+        add_human_verdict(&handle, media, "human").unwrap();
+        assert!(stored.is_stale(&evidence_stamp(&handle, media).unwrap(), &engine.config));
+        let second = engine.cached_report(&handle, media).unwrap();
+        assert!(first.probability_ai > 0.7);
+        assert!(second.probability_ai < 0.1);
+        // The recomputed summary replaces the old one instead of piling up.
+        assert_eq!(handle.lock().unwrap().fact_count(), facts + 1);
+
+        let stale_by_time = StoredReport {
+            evidence: EvidenceStamp {
+                latest: Some(0),
+                ..evidence_stamp(&handle, media).unwrap()
+            },
+            ..stored
+        };
+        assert!(stale_by_time.is_stale(&evidence_stamp(&handle, media).unwrap(), &engine.config));
+    }
+
+    #[test]
+    fn cached_report_goes_stale_on_config_change() {
+        let dir = tempdir().unwrap();
+        let store = PruStore::open(dir.path()).unwrap();
+        let handle = Arc::new(Mutex::new(store));
+        let media = upsert_media_entity(&handle, "hash", MediaType::Text).unwrap();
+        let detector = ensure_detector_entity(&handle, "detector:text:a").unwrap();
+        add_detector_score(&handle, media, detector, 0.8, "ai").unwrap();
+        let engine = TruthEngine::new(TruthEngineConfig::default());
+        engine.cached_report(&handle, media).unwrap();
+        let stored = get_latest_report(&handle, media).unwrap().unwrap();
+        assert_eq!(stored.report.verdict, Verdict::LikelyAi);
+
+        let strict = TruthEngine::new(TruthEngineConfig {
+            likely_ai_threshold: 0.9,
+            ..TruthEngineConfig::default()
+        });
+        assert!(stored.is_stale(&evidence_stamp(&handle, media).unwrap(), &strict.config));
+        assert_eq!(
+            strict.cached_report(&handle, media).unwrap().verdict,
+            Verdict::Inconclusive
+        );
+
+        // Decaying reliability depends on the clock, so it is never served from cache.
+        let facts = handle.lock().unwrap().fact_count();
+        let decaying = TruthEngine::new(TruthEngineConfig {
+            reliability_half_life_days: Some(30.0),
+            ..TruthEngineConfig::default()
+        });
+        decaying.cached_report(&handle, media).unwrap();
+        assert_eq!(handle.lock().unwrap().fact_count(), facts);
+        let stored = get_latest_report(&handle, media).unwrap().unwrap();
+        assert_eq!(stored.config, strict.config.fingerprint());
+    }
+
+    #[test]
+    fn cached_report_goes_stale_on_new_inputs() {
+        let dir = tempdir().unwrap();
+        let store = PruStore::open(dir.path()).unwrap();
+        let handle = Arc::new(Mutex::new(store));
+        let media = upsert_media_entity(&handle, "hash", MediaType::Image).unwrap();
+        let detector = ensure_detector_entity(&handle, "detector:image:a").unwrap();
+        add_detector_score(&handle, media, detector, 0.8, "ai").unwrap();
+        let sighting = Sighting {
+            source: "a.example".into(),
+            url: None,
+            content_type: None,
+            observed_at: 0,
+        };
+        add_sighting(&handle, media, &sighting).unwrap();
+        let engine = TruthEngine::new(TruthEngineConfig::default());
+        let is_stale = || {
+            let stored = get_latest_report(&handle, media).unwrap().unwrap();
+            stored.is_stale(&evidence_stamp(&handle, media).unwrap(), &engine.config)
+        };
+
+        engine.cached_report(&handle, media).unwrap();
+        let calibration = ScoreCalibration {
+            a: 4.0,
+            b: -2.0,
+            samples: 10,
+        };
+        set_detector_calibration(&handle, detector, &calibration).unwrap();
+        assert!(is_stale());
+
+        engine.cached_report(&handle, media).unwrap();
+        let camera = ensure_detector_entity(&handle, "detector:image:exif").unwrap();
+        let claim = ProvenanceClaim::CapturedByDevice {
+            device: "Canon EOS R5".into(),
+        };
+        add_provenance_claim(&handle, media, &claim, camera).unwrap();
+        assert!(is_stale());
+
+        engine.cached_report(&handle, media).unwrap();
+        let other = upsert_media_entity(&handle, "other", MediaType::Image).unwrap();
+        add_sighting(&handle, other, &sighting).unwrap();
+        add_human_verdict(&handle, other, "ai").unwrap();
+        assert!(!is_stale());
+        assert_eq!(update_source_reputation(&handle).unwrap(), 1);
+        assert!(is_stale());
+    }
+
+    #[test]
+    fn explanations_name_registered_detectors() {
+        let dir = tempdir().unwrap();
+        let store = PruStore::open(dir.path()).unwrap();
+        let handle = Arc::new(Mutex::new(store));
+        let media = upsert_media_entity(&handle, "hash", MediaType::Text).unwrap();
+        let info = DetectorInfo {
+            kind: "text".into(),
+            version: "v3".into(),
+            ..Default::default()
+        };
+        let detector = register_detector(&handle, "detector:text:complexity", &info).unwrap();
+        add_detector_score(&handle, media, detector, 0.4, "human").unwrap();
+        add_feature(&handle, media, "avg_len", FeatureValue::F64(4.2), detector).unwrap();
+        add_feature(&handle, media, "words", FeatureValue::I64(12), detector).unwrap();
+        let engine = TruthEngine::new(TruthEngineConfig::default());
+        let report = engine.evaluate_media(&handle, media).unwrap();
+        let explanation = &report.explanations[0];
+        assert_eq!(explanation.detector_id, detector.0);
+        assert_eq!(explanation.detector_name, "detector:text:complexity");
+        assert_eq!(explanation.label, "human");
+        assert_eq!(explanation.weight, 1.0);
+        assert!((explanation.contribution - 0.4).abs() < 1e-6);
+        assert!(explanation.reliability_summary.is_none());
+        let text = explanation.rendered_text();
+        assert!(text.starts_with("Detector detector:text:complexity (v3):"));
+        assert!(text.ends_with("[avg_len=4.200, words=12]"));
+        assert_eq!(report.rendered_explanations(), vec![text]);
+    }
+
+    #[test]
+    fn majority_verdict_wins_over_later_dissent() {
+        let dir = tempdir().unwrap();
+        let store = PruStore::open(dir.path()).unwrap();
+        let handle = Arc::new(Mutex::new(store));
+        let media = upsert_media_entity(&handle, "hash", MediaType::Image).unwrap();
+        add_human_verdict_by(&handle, media, "ai", "alice", 1.0).unwrap();
+        add_human_verdict_by(&handle, media, "ai", "bob", 1.0).unwrap();
+        add_human_verdict_by(&handle, media, "human", "carol", 1.0).unwrap();
+        let engine = TruthEngine::new(TruthEngineConfig::default());
+        let report = engine.evaluate_media(&handle, media).unwrap();
+        assert!((report.probability_ai - 2.0 / 3.0).abs() < 1e-6);
+        assert_eq!(report.verdict, Verdict::Inconclusive);
+        assert!(report.explanations.is_empty());
+        assert!(report.notes[0].contains("2/3"));
+        assert!(report.notes[0].contains("split"));
+    }
+
+    #[test]
+    fn unanimous_verdicts_override_detectors() {
+        let dir = tempdir().unwrap();
+        let handle = Arc::new(Mutex::new(PruStore::open(dir.path()).unwrap()));
+        let media = upsert_media_entity(&handle, "hash", MediaType::Image).unwrap();
+        let detector = ensure_detector_entity(&handle, "detector:image:a").unwrap();
+        add_detector_score(&handle, media, detector, 0.9, "ai").unwrap();
+        for annotator in ["alice", "bob", "carol"] {
+            add_human_verdict_by(&handle, media, "human", annotator, 1.0).unwrap();
+        }
+        let report = TruthEngine::new(TruthEngineConfig::default())
+            .evaluate_media(&handle, media)
+            .unwrap();
+        assert!((report.probability_ai - 0.01).abs() < 1e-6);
+        assert_eq!(report.verdict, Verdict::LikelyHuman);
+        assert_eq!(report.evidence_count, 3);
+
+        let demanding = TruthEngine::new(TruthEngineConfig {
+            min_verdicts_to_override: 4,
+            ..Default::default()
+        });
+        let report = demanding.evaluate_media(&handle, media).unwrap();
+        assert!((report.probability_ai - 0.9).abs() < 1e-6);
+        assert!(report.notes[0].contains("4 needed"));
+    }
+
+    #[test]
+    fn tied_verdicts_fall_back_to_detectors() {
+        let dir = tempdir().unwrap();
+        let handle = Arc::new(Mutex::new(PruStore::open(dir.path()).unwrap()));
+        let media = upsert_media_entity(&handle, "hash", MediaType::Image).unwrap();
+        let detector = ensure_detector_entity(&handle, "detector:image:a").unwrap();
+        add_detector_score(&handle, media, detector, 0.2, "human").unwrap();
+        add_human_verdict_by(&handle, media, "ai", "alice", 1.0).unwrap();
+        add_human_verdict_by(&handle, media, "human", "bob", 1.0).unwrap();
+
+        let report = TruthEngine::new(TruthEngineConfig::default())
+            .evaluate_media(&handle, media)
+            .unwrap();
+        assert!((report.probability_ai - 0.2).abs() < 1e-6);
+        assert_eq!(report.explanations.len(), 1);
+        assert_eq!(
+            report.notes,
+            vec!["Human verdicts conflict (ai=1, human=1); falling back to detectors".to_string()]
+        );
+    }
+
+    #[test]
+    fn undecided_verdicts_fall_back_to_detectors() {
+        let dir = tempdir().unwrap();
+        let handle = Arc::new(Mutex::new(PruStore::open(dir.path()).unwrap()));
+        let media = upsert_media_entity(&handle, "hash", MediaType::Image).unwrap();
+        let detector = ensure_detector_entity(&handle, "detector:image:a").unwrap();
+        add_detector_score(&handle, media, detector, 0.9, "ai").unwrap();
+        for annotator in ["alice", "bob"] {
+            add_human_verdict_by(&handle, media, "unsure", annotator, 1.0).unwrap();
+        }
+
+        let report = TruthEngine::new(TruthEngineConfig::default())
+            .evaluate_media(&handle, media)
+            .unwrap();
+        assert!((report.probability_ai - 0.9).abs() < 1e-6);
+        assert_eq!(report.verdict, Verdict::LikelyAi);
+        assert_eq!(report.explanations.len(), 1);
+        assert!(report.notes[0].contains("\"unsure\""));
+    }
+
+    #[test]
+    fn ai_calls_outweigh_human_calls_of_a_detector_better_at_ai() {
+        let dir = tempdir().unwrap();
+        let handle = Arc::new(Mutex::new(PruStore::open(dir.path()).unwrap()));
+        let detector = ensure_detector_entity(&handle, "detector:text:lopsided").unwrap();
+        let mut history = DetectorReliability::default();
+        for _ in 0..18 {
+            history.record("ai", "ai");
+        }
+        for _ in 0..10 {
+            history.record("human", "human");
+            history.record("human", "ai");
+        }
+        set_detector_reliability(&handle, detector, &history).unwrap();
+
+        let engine = TruthEngine::new(TruthEngineConfig::default());
+        let weight_of = |hash: &str, score: f64, label: &str| {
+            let media = upsert_media_entity(&handle, hash, MediaType::Text).unwrap();
+            add_detector_score(&handle, media, detector, score, label).unwrap();
+            let report = engine.evaluate_media(&handle, media).unwrap();
+            report.explanations[0].clone()
+        };
+        let ai_call = weight_of("a", 0.9, "ai");
+        let human_call = weight_of("b", 0.1, "human");
+        assert!((ai_call.weight - 19.0 / 20.0).abs() < 1e-6);
+        assert!((human_call.weight - 11.0 / 22.0).abs() < 1e-6);
+        assert_eq!(ai_call.label_precision, Some(ai_call.weight));
+        assert!(human_call.rendered_text().contains("precision=0.50"));
+
+        // History without a confusion matrix keeps the overall accuracy weight.
+        let legacy = EffectiveReliability {
+            seen: 38.0,
+            correct: 28.0,
+            ..Default::default()
+        };
+        let expected = 29.0 / 40.0;
+        assert!((compute_weight(1.0, Some(&legacy), "ai") - expected).abs() < 1e-6);
+        assert!((compute_weight(1.0, Some(&legacy), "human") - expected).abs() < 1e-6);
+    }
+
+    #[test]
+    fn provenance_claims_shift_a_neutral_score() {
+        let dir = tempdir().unwrap();
+        let handle = Arc::new(Mutex::new(PruStore::open(dir.path()).unwrap()));
+        let detector = ensure_detector_entity(&handle, "detector:image:provenance").unwrap();
+        let engine = TruthEngine::new(TruthEngineConfig::default());
+        let evaluate = |hash: &str, score: f64, claims: &[ProvenanceClaim]| {
+            let media = upsert_media_entity(&handle, hash, MediaType::Image).unwrap();
+            add_detector_score(&handle, media, detector, score, "unknown").unwrap();
+            for claim in claims {
+                add_provenance_claim(&handle, media, claim, detector).unwrap();
+            }
+            engine.evaluate_media(&handle, media).unwrap()
+        };
+        let camera = ProvenanceClaim::CapturedByDevice {
+            device: "Canon EOS R5".into(),
+        };
+        let generator = ProvenanceClaim::GeneratedByModel {
+            model: "Stable Diffusion".into(),
+        };
+        let sigmoid = |x: f32| 1.0 / (1.0 + (-x).exp());
+
+        let plain = evaluate("plain", 0.5, &[]);
+        assert!((plain.probability_ai - 0.5).abs() < 1e-6);
+        assert!(plain.prior_adjustments.is_empty());
+
+        let captured = evaluate("captured", 0.5, std::slice::from_ref(&camera));
+        assert!((captured.probability_ai - sigmoid(-0.8)).abs() < 1e-5);
+        assert_eq!(captured.prior_adjustments[0].shift, -0.8);
+        assert!(captured.rendered_explanations()[0]
+            .contains("captured by Canon EOS R5 (-0.80 log-odds toward human)"));
+
+        let generated = evaluate("generated", 0.5, std::slice::from_ref(&generator));
+        assert!((generated.probability_ai - sigmoid(2.0)).abs() < 1e-5);
+        assert_eq!(generated.verdict, Verdict::LikelyAi);
+
+        // A generator wins over a capture device.
+        let both = evaluate("both", 0.5, &[camera.clone(), generator.clone()]);
+        assert!((both.probability_ai - sigmoid(2.0)).abs() < 1e-5);
+        assert_eq!(both.prior_adjustments.len(), 1);
+
+        let iptc = ProvenanceClaim::Other {
+            key: "digital_source_type".into(),
+            value: "http://cv.iptc.org/newscodes/digitalsourcetype/trainedAlgorithmicMedia".into(),
+        };
+        let sourced = evaluate("sourced", 0.5, &[camera.clone(), iptc]);
+        assert!((sourced.probability_ai - sigmoid(2.0)).abs() < 1e-5);
+        assert!(sourced.prior_adjustments[0]
+            .claim
+            .contains("trainedAlgorithmicMedia"));
+
+        // Without any detector score the prior still moves the probability.
+        let unscored = upsert_media_entity(&handle, "unscored", MediaType::Image).unwrap();
+        add_provenance_claim(&handle, unscored, &camera, detector).unwrap();
+        let report = engine.evaluate_media(&handle, unscored).unwrap();
+        assert!((report.probability_ai - sigmoid(-0.8)).abs() < 1e-5);
+        assert_eq!(report.verdict, Verdict::Inconclusive);
+
+        // A confident detector still outvotes the generator claim.
+        let outvoted = evaluate("outvoted", 0.02, std::slice::from_ref(&generator));
+        assert!(outvoted.probability_ai < 0.3, "{}", outvoted.probability_ai);
+
+        let strong = TruthEngine::new(TruthEngineConfig {
+            generated_prior_shift: 6.0,
+            ..Default::default()
+        });
+        let media = upsert_media_entity(&handle, "generated", MediaType::Image).unwrap();
+        let capped = strong.evaluate_media(&handle, media).unwrap();
+        assert!((capped.probability_ai - sigmoid(2.5)).abs() < 1e-5);
+        assert!(capped.notes[0].contains("capped at 2.50"));
+    }
+
+    #[test]
+    fn source_reputation_nudges_media_first_seen_there() {
+        let dir = tempdir().unwrap();
+        let handle = Arc::new(Mutex::new(PruStore::open(dir.path()).unwrap()));
+        let detector = ensure_detector_entity(&handle, "detector:image:a").unwrap();
+        let seen = |hash: &str, source: &str, observed_at: i64| {
+            let media = upsert_media_entity(&handle, hash, MediaType::Image).unwrap();
+            let sighting = Sighting {
+                source: source.into(),
+                url: None,
+                content_type: None,
+                observed_at,
+            };
+            add_sighting(&handle, media, &sighting).unwrap();
+            media
+        };
+        for i in 0..12 {
+            let farm = seen(&format!("farm{i}"), "genfarm.example", 100);
+            add_human_verdict(&handle, farm, if i < 11 { "ai" } else { "human" }).unwrap();
+            let camera = seen(&format!("cam{i}"), "photoclub.example", 100);
+            add_human_verdict(&handle, camera, "human").unwrap();
+        }
+        for i in 0..5 {
+            let sparse = seen(&format!("sparse{i}"), "new.example", 100);
+            add_human_verdict(&handle, sparse, "ai").unwrap();
+        }
+        assert_eq!(update_source_reputation(&handle).unwrap(), 3);
+        assert_eq!(update_source_reputation(&handle).unwrap(), 0);
+
+        let engine = TruthEngine::new(TruthEngineConfig::default());
+        let evaluate = |media: MediaId| {
+            add_detector_score(&handle, media, detector, 0.5, "unknown").unwrap();
+            engine.evaluate_media(&handle, media).unwrap()
+        };
+        let from_farm = evaluate(seen("new-farm", "genfarm.example", 200));
+        assert!(from_farm.probability_ai > 0.55 && from_farm.probability_ai < 0.65);
+        assert!(from_farm.prior_adjustments[0]
+            .claim
+            .contains("genfarm.example (11 of 12"));
+
+        // Later sightings elsewhere do not change where it was first seen.
+        let from_club = seen("new-club", "photoclub.example", 200);
+        seen("new-club", "genfarm.example", 300);
+        let from_club = evaluate(from_club);
+        assert!(from_club.probability_ai < 0.45 && from_club.probability_ai > 0.35);
+
+        let from_sparse = evaluate(seen("new-sparse", "new.example", 200));
+        assert_eq!(from_sparse.probability_ai, 0.5);
+        assert!(from_sparse.prior_adjustments.is_empty());
+    }
+
+    #[test]
+    fn what_if_overrides_change_only_the_evaluation() {
+        let dir = tempdir().unwrap();
+        let handle = Arc::new(Mutex::new(PruStore::open(dir.path()).unwrap()));
+        let media = upsert_media_entity(&handle, "hash", MediaType::Image).unwrap();
+        let metadata = ensure_detector_entity(&handle, "detector:image:metadata_v1").unwrap();
+        add_detector_score(&handle, media, metadata, 0.9, "ai").unwrap();
+        let engine = TruthEngine::new(TruthEngineConfig::default());
+
+        let without = EvalOverrides {
+            exclude_detectors: BTreeSet::from(["detector:image:metadata_v1".to_string()]),
+            ..Default::default()
+        };
+        let report = engine
+            .evaluate_media_with(&handle, media, &without)
+            .unwrap();
+        assert_eq!(report.probability_ai, 0.5);
+        assert_eq!(report.evidence_count, 0);
+        assert_eq!(report.verdict, Verdict::Inconclusive);
+        assert!(report.explanations.is_empty());
+        assert_eq!(
+            report.notes,
+            vec![
+                "Excluded detector detector:image:metadata_v1".to_string(),
+                "No detector scores found for this media".to_string(),
+            ]
+        );
+        assert_eq!(
+            engine
+                .evaluate_media(&handle, media)
+                .unwrap()
+                .probability_ai,
+            0.9
+        );
+
+        let other = ensure_detector_entity(&handle, "detector:image:other").unwrap();
+        add_detector_score(&handle, media, other, 0.1, "human").unwrap();
+        let forced = EvalOverrides {
+            force_weights: BTreeMap::from([("detector:image:other".to_string(), 3.0)]),
+            ..Default::default()
+        };
+        let report = engine.evaluate_media_with(&handle, media, &forced).unwrap();
+        assert!((report.probability_ai - 0.3).abs() < 1e-6);
+
+        add_human_verdict(&handle, media, "ai").unwrap();
+        let ignoring = EvalOverrides {
+            ignore_verdicts: true,
+            ..Default::default()
+        };
+        let report = engine
+            .evaluate_media_with(&handle, media, &ignoring)
+            .unwrap();
+        assert!((report.probability_ai - 0.5).abs() < 1e-6);
+        assert_eq!(report.explanations.len(), 2);
+    }
+
+    #[test]
+    fn corrupt_scores_are_skipped() {
+        let dir = tempdir().unwrap();
+        let handle = Arc::new(Mutex::new(PruStore::open(dir.path()).unwrap()));
+        let media = upsert_media_entity(&handle, "hash", MediaType::Text).unwrap();
+        for (name, score) in [
+            ("detector:text:nan", f64::NAN),
+            ("detector:text:negative", -0.2),
+            ("detector:text:huge", 1e300),
+            ("detector:text:infinite", f64::INFINITY),
+        ] {
+            let detector = ensure_detector_entity(&handle, name).unwrap();
+            add_detector_score(&handle, media, detector, score, "ai").unwrap();
+        }
+        let engine = TruthEngine::new(TruthEngineConfig::default());
+        let report = engine.evaluate_media(&handle, media).unwrap();
+        assert_eq!(report.probability_ai, 0.5);
+        assert_eq!(report.evidence_count, 0);
+        assert_eq!(report.notes.len(), 5);
+        assert!(report.notes[0].contains("detector:text:nan: score is not a number"));
+        assert!(report.notes[1].contains("score -0.2 is outside [0, 1]"));
+
+        let sane = ensure_detector_entity(&handle, "detector:text:sane").unwrap();
+        add_detector_score(&handle, media, sane, 0.8, "ai").unwrap();
+        let report = engine.evaluate_media(&handle, media).unwrap();
+        assert!((report.probability_ai - 0.8).abs() < 1e-6);
+        assert!((report.probability_ai + report.probability_human - 1.0).abs() < 1e-6);
+        assert_eq!(report.evidence_count, 1);
+        let json = serde_json::to_value(&report).unwrap();
+        assert!(json["probability_ai"].is_f64());
+        assert!(json["confidence_interval"]["low"].is_f64());
+
+        let zero = EvalOverrides {
+            force_weights: BTreeMap::from([("detector:text:sane".to_string(), 0.0)]),
+            ..Default::default()
+        };
+        let report = engine.evaluate_media_with(&handle, media, &zero).unwrap();
+        assert_eq!(report.probability_ai, 0.5);
+        assert_eq!(report.verdict, Verdict::Inconclusive);
+        assert!(report.notes.last().unwrap().contains("sum to zero"));
+    }
+
+    #[test]
+    fn drifting_detector_loses_weight_with_half_life() {
+        let dir = tempdir().unwrap();
+        let store = PruStore::open(dir.path()).unwrap();
+        let handle = Arc::new(Mutex::new(store));
+        let detector = ensure_detector_entity(&handle, "detector:text:drifting").unwrap();
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i64;
+        let mut history = DetectorReliability::default();
+        for _ in 0..50 {
+            history.record_at("ai", "ai", now - 365 * 86_400);
+        }
+        for _ in 0..10 {
+            history.record_at("ai", "human", now);
+        }
+        set_detector_reliability(&handle, detector, &history).unwrap();
+
+        let lifetime = get_detector_reliability(&handle, detector)
+            .unwrap()
+            .map(|r| r.lifetime());
+        let decayed = get_effective_reliability(&handle, detector, 30.0).unwrap();
+        let lifetime_weight = compute_weight(1.0, lifetime.as_ref(), "ai");
+        let decayed_weight = compute_weight(1.0, decayed.as_ref(), "ai");
+        assert!(lifetime_weight > 0.8);
+        assert!(decayed_weight < 0.2);
+    }
 }